@@ -0,0 +1,3044 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2016, 2018 Matteo Cypriani <mcy@lm7.fr>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, IsTerminal};
+#[cfg(unix)]
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Mutex, Once};
+
+extern crate getopts;
+use getopts::{Fail, Matches, Options};
+
+extern crate ctrlc;
+
+extern crate glob;
+
+#[macro_use]
+extern crate log;
+use log::{Log, Level, LevelFilter, Metadata, Record};
+
+extern crate nrgrip;
+
+#[cfg(feature = "tui")]
+extern crate crossterm;
+
+#[cfg(feature = "tui")]
+mod tui;
+
+use nrgrip::metadata::{self, MetadataOptions};
+use nrgrip::accuraterip::{self, NrgAccurateRipTrack};
+use nrgrip::analysis::{self, NrgTrackAnalysis};
+use nrgrip::cancel::CancellationToken;
+use nrgrip::cdtext::{self, NrgCdText, NrgCdTextTrack};
+use nrgrip::compare::{self, NrgAudioLayout, NrgComparison};
+use nrgrip::convert::{self, ImageFormat};
+use nrgrip::cue_sheet::{self, CueSheetOptions};
+use nrgrip::editor::{self, NrgDaoxEdit};
+use nrgrip::encoder::ExternalEncoder;
+use nrgrip::error::{NrgError, NrgErrorKind};
+use nrgrip::extraction::{self, AudioFormat, ChannelMode, ExtractionOptions,
+                         GapMode};
+use nrgrip::gaps;
+use nrgrip::extraction::ExtractedFile;
+use nrgrip::loudness::NrgLoudness;
+use nrgrip::manifest;
+use nrgrip::gaps::NrgGap;
+use nrgrip::hidden::{self, NrgHiddenData};
+use nrgrip::json::{self, JsonValue};
+use nrgrip::metadata::metadata::NrgMetadata;
+use nrgrip::metadata::raw::{self, NrgRawChunk};
+use nrgrip::msf::Msf;
+use nrgrip::provenance;
+use nrgrip::repair;
+use nrgrip::sessions;
+use nrgrip::tags::{self, NrgTags};
+use nrgrip::text;
+use nrgrip::tsv::{self, TsvRecord};
+use nrgrip::upgrade;
+use nrgrip::verify::{self, NrgVerification};
+use nrgrip::writer::{self, NrgWavTrack};
+#[cfg(feature = "audio")]
+use nrgrip::play;
+#[cfg(feature = "opus")]
+use nrgrip::opus;
+#[cfg(feature = "mp3")]
+use nrgrip::mp3::{self, Mp3Bitrate};
+#[cfg(feature = "vorbis")]
+use nrgrip::vorbis;
+#[cfg(feature = "online")]
+use nrgrip::accuraterip::NrgAccurateRipIds;
+#[cfg(feature = "online")]
+use nrgrip::disc_id::NrgDiscToc;
+#[cfg(feature = "online")]
+use nrgrip::musicbrainz;
+
+const PRETTY_PROGNAME: &'static str = "NRGrip";
+const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
+
+// Exit statuses, those of the errors being given by their kind
+const EXIT_SUCCESS: i32 = 0;
+/// Invalid command line.
+const EXIT_USAGE: i32 = NrgErrorKind::Usage.exit_code();
+/// Error reading or writing a file.
+const EXIT_IO: i32 = NrgErrorKind::Io.exit_code();
+/// Invalid, truncated or unsupported NRG image.
+const EXIT_FORMAT: i32 = NrgErrorKind::Format.exit_code();
+/// The compared images hold different audio.
+const EXIT_DIFFERENT: i32 = 7;
+/// The extraction was interrupted.
+const EXIT_CANCELLED: i32 = NrgErrorKind::Cancelled.exit_code();
+
+/// Logger printing the library's diagnostics and the program's errors to
+/// stderr.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let level = match record.level() {
+                Level::Error => "error",
+                Level::Warn => "warning",
+                Level::Info => "info",
+                Level::Debug => "debug",
+                Level::Trace => "trace",
+            };
+            eprintln!("{}: {}", level, record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// Temporary directory holding the copy of the image read from the standard
+/// input, if any.
+static STDIN_IMAGE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Token cancelled by the first Ctrl-C, once set by `handle_interrupts()`.
+static INTERRUPT_TOKEN: Mutex<Option<CancellationToken>> = Mutex::new(None);
+
+
+fn print_version() {
+    println!("{} v{}", PRETTY_PROGNAME, VERSION.unwrap_or("X.Y.Z"));
+}
+
+fn usage(prog_name: &str, opts: &Options) -> String {
+    let brief = format!("{prettyprog} - rip Nero Burning ROM audio images
+
+Usage:
+    {prog} [-icrx] [options] <image.nrg>...
+    {prog} [-icrx] [options] --recursive <dir>
+    {prog} verify [options] <image.nrg>...
+    {prog} compare [options] <image.nrg> <image.nrg|image.bin|sheet.cue>
+    {prog} convert --to FORMAT [options] <image.nrg>...
+    {prog} split [options] <image.nrg>...
+    {prog} dump-chunks [options] <image.nrg>...
+    {prog} play [options] <image.nrg>
+    {prog} tag [options] <image.nrg> <file>...
+    {prog} build [options] <sheet.cue> <image.nrg>
+    {prog} build [options] <track.wav>... <image.nrg>
+    {prog} edit [options] <image.nrg>
+    {prog} repair [options] <damaged.nrg> <image.nrg>
+    {prog} upgrade [options] <image-v1.nrg> <image.nrg>
+    {prog} [-h | -V]", prettyprog = PRETTY_PROGNAME, prog = prog_name);
+
+    opts.usage(&brief)
+}
+
+/// Parses a list of track numbers and ranges of track numbers, such as
+/// "1,3-5".
+///
+/// Returns None if the list is invalid.
+fn parse_track_list(list: &str) -> Option<Vec<u8>> {
+    let mut tracks = Vec::new();
+    for item in list.split(',') {
+        let (first, last): (u8, u8) = match item.find('-') {
+            None => {
+                let number = item.trim().parse().ok()?;
+                (number, number)
+            },
+            Some(i) => (item[..i].trim().parse().ok()?,
+                        item[i + 1..].trim().parse().ok()?),
+        };
+        if first == 0 || first > last {
+            return None;
+        }
+        tracks.extend(first..=last);
+    }
+    Some(tracks)
+}
+
+/// Parses a rate in MB/s (10^6 bytes per second), such as "2.5".
+///
+/// Returns the rate in bytes per second, or None if it is invalid or null.
+fn parse_rate(rate: &str) -> Option<u64> {
+    let rate: f64 = rate.parse().ok()?;
+    let bytes = (rate * 1e6).round();
+    if !(bytes >= 1.0 && bytes < u64::MAX as f64) {
+        return None;
+    }
+    Some(bytes as u64)
+}
+
+/// Parses the --cd-text-language option of `options`, if present.
+///
+/// Returns the exit status on error.
+fn cd_text_language(options: &Matches) -> Result<Option<u8>, i32> {
+    match options.opt_str("cd-text-language") {
+        None => Ok(None),
+        Some(language) => match cdtext::parse_language(&language) {
+            Ok(code) => Ok(Some(code)),
+            Err(err) => {
+                error!("{}", err);
+                Err(EXIT_USAGE)
+            },
+        },
+    }
+}
+
+/// Parses a size in bytes, optionally followed by K or M (case-insensitive)
+/// for KiB or MiB, such as "512K".
+///
+/// Returns None if the size is invalid or null.
+fn parse_size(size: &str) -> Option<usize> {
+    let (digits, multiplier) = match size.chars().last()? {
+        'k' | 'K' => (&size[..size.len() - 1], 1 << 10),
+        'm' | 'M' => (&size[..size.len() - 1], 1 << 20),
+        _ => (size, 1),
+    };
+    let size: usize = digits.parse().ok()?;
+    size.checked_mul(multiplier).filter(|&size| size > 0)
+}
+
+fn main() {
+    let status = main_main();
+    remove_stdin_image();
+    process::exit(status);
+}
+
+fn main_main() -> i32 {
+    let args: Vec<String> = env::args().collect();
+    let prog_fullname = &args.first().expect("Can't retrieve program name");
+    let prog_name = PathBuf::from(prog_fullname).file_name()
+        .expect("Can't retrieve program base name")
+        .to_string_lossy().into_owned();
+
+    // Subcommands
+    match args.get(1).map(String::as_str) {
+        Some("verify") => return verify_main(&prog_name, &args[2..]),
+        Some("compare") => return compare_main(&prog_name, &args[2..]),
+        Some("convert") => return convert_main(&prog_name, &args[2..]),
+        Some("split") => return split_main(&prog_name, &args[2..]),
+        Some("dump-chunks") =>
+            return dump_chunks_main(&prog_name, &args[2..]),
+        Some("play") => return play_main(&prog_name, &args[2..]),
+        Some("tag") => return tag_main(&prog_name, &args[2..]),
+        Some("build") => return build_main(&prog_name, &args[2..]),
+        Some("edit") => return edit_main(&prog_name, &args[2..]),
+        Some("repair") => return repair_main(&prog_name, &args[2..]),
+        Some("upgrade") => return upgrade_main(&prog_name, &args[2..]),
+        _ => (),
+    }
+
+    let mut opts = Options::new();
+    opts.optflag("i", "info",
+                 "display the image's metadata (default action)");
+    opts.optflag("", "summary",
+                 "display the metadata as one line per track (implies \
+                  --info)");
+    opts.optflag("", "chunks",
+                 "list the image's metadata chunks, with their offset, size \
+                  and whether they are parsed or skipped");
+    opts.optflag("x", "extract",
+                 "same as --extract-cue --extract-raw");
+    opts.optflag("c", "extract-cue",
+                 "extract cue sheet from the NRG metadata");
+    opts.optflag("r", "extract-raw",
+                 "extract the raw audio tracks");
+    opts.optflag("S", "no-strip-subchannel",
+                 "don't strip the 96-bit subchannel if present");
+    opts.optflag("s", "split",
+                 "extract one audio file per track");
+    // The encoded formats depend on the features
+    let mut formats = vec!["raw (default)", "wav", "flac"];
+    if cfg!(feature = "opus") {
+        formats.push("opus");
+    }
+    if cfg!(feature = "mp3") {
+        formats.push("mp3");
+    }
+    if cfg!(feature = "vorbis") {
+        formats.push("vorbis");
+    }
+    opts.optopt("F", "audio-format",
+                &format!("format of the extracted audio: {}",
+                         formats.join(", ")),
+                "FORMAT");
+    #[cfg(feature = "opus")]
+    opts.optopt("", "opus-bitrate",
+                "bitrate of the Opus files, in kbit/s (default: 128)",
+                "KBPS");
+    #[cfg(feature = "mp3")]
+    opts.optopt("", "mp3-bitrate",
+                "encode the MP3 files at a constant bitrate of KBPS kbit/s",
+                "KBPS");
+    #[cfg(feature = "mp3")]
+    opts.optopt("", "mp3-vbr",
+                "encode the MP3 files at a variable bitrate of quality Q, \
+                 from 0 (best) to 9 (smallest), default 2",
+                "Q");
+    #[cfg(feature = "vorbis")]
+    opts.optopt("", "vorbis-quality",
+                "quality of the Vorbis files, from -1 (smallest) to 10 \
+                 (best), default 3",
+                "Q");
+    opts.optopt("", "encoder",
+                "encode the extracted audio with COMMAND, which receives a \
+                 WAV stream on its standard input ({out} is replaced with \
+                 the output file's name), e.g. 'flac -s -o {out} -'",
+                "COMMAND");
+    opts.optopt("", "encoder-extension",
+                "extension of the files written by the encoder (default: \
+                 the encoder's program name)",
+                "EXT");
+    opts.optopt("", "gap-mode",
+                "where to put the pre-gaps when splitting: append (to the \
+                 previous track, default), prepend (to their own track) or \
+                 discard",
+                "MODE");
+    opts.optopt("", "channels",
+                "channels of the extracted audio: stereo (default), swap \
+                 (left and right swapped) or mono",
+                "MODE");
+    opts.optopt("O", "offset",
+                "shift the extracted audio by SAMPLES samples",
+                "SAMPLES");
+    opts.optopt("", "buffer-size",
+                "copy the audio data through a buffer of SIZE bytes, \
+                 optionally followed by K or M",
+                "SIZE");
+    opts.optopt("", "rate-limit",
+                "read the image at most at RATE MB/s",
+                "RATE");
+    opts.optopt("n", "name",
+                "name of the audio files, without extension ({image} is \
+                 replaced with the image's name, {track} with the track \
+                 number, {title} with the track's title)",
+                "TEMPLATE");
+    opts.optflag("", "ascii",
+                 "transliterate the titles to ASCII in the file names and the \
+                  cue sheets");
+    opts.optopt("t", "tracks",
+                "extract only the tracks listed in LIST, e.g. 1,3-5",
+                "LIST");
+    opts.optopt("", "session",
+                "read only the session numbered N of a multi-session image",
+                "N");
+    opts.optflag("", "strict",
+                 "reject the image if its metadata has any anomaly, \
+                  instead of warning about it");
+    opts.optflag("k", "checksums",
+                 "display the CRC32 of the extracted audio files");
+    opts.optflag("", "verify-writes",
+                 "read the extracted audio files back and check them");
+    opts.optflag("", "no-atomic",
+                 "write the audio files directly under their final names");
+    opts.optflag("", "ignore-errors",
+                 "replace the sectors that can't be read with silence \
+                  instead of failing");
+    opts.optflag("A", "accuraterip",
+                 if cfg!(feature = "online") {
+                     "compute the AccurateRip checksums of the tracks, and \
+                      look up their confidence in the AccurateRip database"
+                 } else {
+                     "compute the AccurateRip checksums of the tracks"
+                 });
+    opts.optflag("L", "loudness",
+                 "measure the loudness and peak of the extracted audio \
+                  files (EBU R128 / ReplayGain 2.0)");
+    opts.optflag("E", "de-emphasis",
+                 "apply the de-emphasis filter to the tracks recorded with \
+                  pre-emphasis");
+    opts.optflag("g", "detect-gaps",
+                 "look for silence at the track boundaries");
+    opts.optflag("", "hidden",
+                 "look for data between the end of the last track and the \
+                  chunks of the image");
+    opts.optflag("", "extract-hidden",
+                 "extract the data found by --hidden to IMAGE-hidden.raw");
+    opts.optflag("a", "analyze",
+                 "report the silence at the start and end of each track, the \
+                  duration of its audio, and its peak and RMS levels");
+    opts.optflag("G", "cue-detected-gaps",
+                 "use the detected gaps as index 0 in the cue sheet \
+                  (implies -g)");
+    opts.optflag("R", "cue-rem",
+                 "add REM lines with the disc IDs to the cue sheet");
+    opts.optflag("", "track-cues",
+                 "write a cue sheet next to each split track's audio file");
+    opts.optopt("", "cd-text-language",
+                "use the CD-Text block in LANG (a language name or code) \
+                 for the titles, cue sheets and tags",
+                "LANG");
+    opts.optopt("f", "format",
+                "format of the displayed information: text (default), json \
+                 or tsv",
+                "FORMAT");
+    opts.optopt("", "color",
+                "color the displayed information: auto (default), always \
+                 or never",
+                "WHEN");
+    opts.optopt("o", "output-dir",
+                "write the extracted files in DIR instead of the current \
+                 directory",
+                "DIR");
+    opts.optopt("", "cue-output",
+                "write the cue sheet to PATH",
+                "PATH");
+    opts.optopt("", "raw-output",
+                "write the audio data to PATH (incompatible with --split)",
+                "PATH");
+    opts.optflag("", "manifest",
+                 "write the SHA-256 of the extracted files to \
+                  manifest.sha256 in the output directory");
+    opts.optflag("", "provenance",
+                 "write a JSON report of the extracted files, their \
+                  sources and checksums to IMAGE.provenance.json");
+    opts.optopt("", "recursive",
+                "rip all the NRG images found under DIR, keeping their \
+                 relative paths in the output directory",
+                "DIR");
+    #[cfg(feature = "tui")]
+    opts.optflag("I", "interactive",
+                 "choose the tracks to extract and the audio format in a \
+                  terminal interface (implies -r)");
+    #[cfg(feature = "online")]
+    opts.optflag("M", "musicbrainz",
+                 "look up the titles of the tracks in the MusicBrainz \
+                  database");
+    opts.optflag("q", "quiet",
+                 "only print the requested information and the errors");
+    opts.optflagmulti("v", "verbose",
+                      "print more diagnostics (can be repeated)");
+    opts.optflag("h", "help",
+                 "print this help message");
+    opts.optflag("V", "version",
+                 "print program version");
+
+    let parsed_options = opts.parse(&args[1..]);
+    let quiet = init_logger(&parsed_options);
+    let options = match parsed_options {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            eprint!("{}", usage(&prog_name, &opts));
+            return EXIT_USAGE;
+        },
+    };
+
+    if options.opt_present("help") {
+        print!("{}", usage(&prog_name, &opts));
+        return EXIT_SUCCESS;
+    }
+
+    if options.opt_present("version") {
+        print_version();
+        return EXIT_SUCCESS;
+    }
+
+    let output_format = match parse_output_format(&options) {
+        Some(format) => format,
+        None => {
+            eprint!("{}", usage(&prog_name, &opts));
+            return EXIT_USAGE;
+        },
+    };
+
+    let color = match parse_color(&options) {
+        Some(color) => color,
+        None => {
+            eprint!("{}", usage(&prog_name, &opts));
+            return EXIT_USAGE;
+        },
+    };
+
+    let audio_format = match options.opt_str("audio-format") {
+        None => AudioFormat::Raw,
+        Some(ref format) if format == "raw" => AudioFormat::Raw,
+        Some(ref format) if format == "wav" => AudioFormat::Wav,
+        Some(ref format) if format == "flac" => AudioFormat::Flac,
+        #[cfg(feature = "opus")]
+        Some(ref format) if format == "opus" => AudioFormat::Opus,
+        #[cfg(feature = "mp3")]
+        Some(ref format) if format == "mp3" => AudioFormat::Mp3,
+        #[cfg(feature = "vorbis")]
+        Some(ref format) if format == "vorbis" => AudioFormat::Vorbis,
+        Some(format) => {
+            error!("Unknown audio format \"{}\"", format);
+            eprint!("{}", usage(&prog_name, &opts));
+            return EXIT_USAGE;
+        },
+    };
+
+    let encoder = match options.opt_str("encoder") {
+        None => None,
+        Some(_) if options.opt_present("audio-format") => {
+            error!("The --encoder and --audio-format options are exclusive");
+            eprint!("{}", usage(&prog_name, &opts));
+            return EXIT_USAGE;
+        },
+        Some(command) => match ExternalEncoder::parse(&command) {
+            Ok(encoder) => match options.opt_str("encoder-extension") {
+                Some(extension) => Some(encoder.extension(&extension)),
+                None => Some(encoder),
+            },
+            Err(err) => {
+                error!("{}", err);
+                eprint!("{}", usage(&prog_name, &opts));
+                return EXIT_USAGE;
+            },
+        },
+    };
+
+    let gap_mode = match options.opt_str("gap-mode") {
+        None => GapMode::Append,
+        Some(ref mode) if mode == "append" => GapMode::Append,
+        Some(ref mode) if mode == "prepend" => GapMode::Prepend,
+        Some(ref mode) if mode == "discard" => GapMode::Discard,
+        Some(mode) => {
+            error!("Unknown gap mode \"{}\"", mode);
+            eprint!("{}", usage(&prog_name, &opts));
+            return EXIT_USAGE;
+        },
+    };
+
+    let channel_mode = match options.opt_str("channels") {
+        None => ChannelMode::Stereo,
+        Some(ref mode) if mode == "stereo" => ChannelMode::Stereo,
+        Some(ref mode) if mode == "swap" => ChannelMode::Swapped,
+        Some(ref mode) if mode == "mono" => ChannelMode::Mono,
+        Some(mode) => {
+            error!("Unknown channel mode \"{}\"", mode);
+            eprint!("{}", usage(&prog_name, &opts));
+            return EXIT_USAGE;
+        },
+    };
+
+    let offset = match options.opt_str("offset") {
+        None => 0,
+        Some(offset) => match offset.parse() {
+            Ok(offset) => offset,
+            Err(_) => {
+                error!("Invalid offset \"{}\"", offset);
+                eprint!("{}", usage(&prog_name, &opts));
+                return EXIT_USAGE;
+            },
+        },
+    };
+
+    let buffer_size = match options.opt_str("buffer-size") {
+        None => None,
+        Some(size) => match parse_size(&size) {
+            Some(size) => Some(size),
+            None => {
+                error!("Invalid buffer size \"{}\"", size);
+                eprint!("{}", usage(&prog_name, &opts));
+                return EXIT_USAGE;
+            },
+        },
+    };
+
+    let rate_limit = match options.opt_str("rate-limit") {
+        None => None,
+        Some(rate) => match parse_rate(&rate) {
+            Some(rate) => Some(rate),
+            None => {
+                error!("Invalid rate limit \"{}\"", rate);
+                eprint!("{}", usage(&prog_name, &opts));
+                return EXIT_USAGE;
+            },
+        },
+    };
+
+    let cd_text_language = match cd_text_language(&options) {
+        Ok(language) => language,
+        Err(status) => return status,
+    };
+
+    #[cfg(feature = "opus")]
+    let opus_bitrate = match options.opt_str("opus-bitrate") {
+        None => None,
+        Some(bitrate) => match bitrate.parse() {
+            Ok(bitrate) if (opus::MIN_BITRATE..=opus::MAX_BITRATE)
+                .contains(&bitrate) => Some(bitrate),
+            _ => {
+                error!("Invalid Opus bitrate \"{}\" (must be between {} and \
+                        {} kbit/s)", bitrate, opus::MIN_BITRATE,
+                       opus::MAX_BITRATE);
+                eprint!("{}", usage(&prog_name, &opts));
+                return EXIT_USAGE;
+            },
+        },
+    };
+
+    #[cfg(feature = "mp3")]
+    let mp3_bitrate = match (options.opt_str("mp3-bitrate"),
+                             options.opt_str("mp3-vbr")) {
+        (None, None) => None,
+        (Some(_), Some(_)) => {
+            error!("The --mp3-bitrate and --mp3-vbr options are exclusive");
+            eprint!("{}", usage(&prog_name, &opts));
+            return EXIT_USAGE;
+        },
+        (Some(bitrate), None) => match bitrate.parse() {
+            Ok(bitrate) if mp3::BITRATES.contains(&bitrate) =>
+                Some(Mp3Bitrate::Constant(bitrate)),
+            _ => {
+                error!("Invalid MP3 bitrate \"{}\" (must be one of {:?})",
+                       bitrate, mp3::BITRATES);
+                eprint!("{}", usage(&prog_name, &opts));
+                return EXIT_USAGE;
+            },
+        },
+        (None, Some(quality)) => match quality.parse() {
+            Ok(quality) if quality <= mp3::WORST_VBR_QUALITY =>
+                Some(Mp3Bitrate::Variable(quality)),
+            _ => {
+                error!("Invalid MP3 VBR quality \"{}\" (must be between {} \
+                        and {})", quality, mp3::BEST_VBR_QUALITY,
+                       mp3::WORST_VBR_QUALITY);
+                eprint!("{}", usage(&prog_name, &opts));
+                return EXIT_USAGE;
+            },
+        },
+    };
+
+    #[cfg(feature = "vorbis")]
+    let vorbis_quality = match options.opt_str("vorbis-quality") {
+        None => None,
+        Some(quality) => match quality.parse() {
+            Ok(quality) if (vorbis::MIN_QUALITY..=vorbis::MAX_QUALITY)
+                .contains(&quality) => Some(quality),
+            _ => {
+                error!("Invalid Vorbis quality \"{}\" (must be between {} \
+                        and {})", quality, vorbis::MIN_QUALITY,
+                       vorbis::MAX_QUALITY);
+                eprint!("{}", usage(&prog_name, &opts));
+                return EXIT_USAGE;
+            },
+        },
+    };
+
+    let tracks = match options.opt_str("tracks") {
+        None => None,
+        Some(list) => match parse_track_list(&list) {
+            Some(tracks) => Some(tracks),
+            None => {
+                error!("Invalid track list \"{}\"", list);
+                eprint!("{}", usage(&prog_name, &opts));
+                return EXIT_USAGE;
+            },
+        },
+    };
+
+    let session = match options.opt_str("session") {
+        None => None,
+        Some(number) => match number.parse() {
+            Ok(number) if number > 0 => Some(number),
+            _ => {
+                error!("Invalid session number \"{}\"", number);
+                eprint!("{}", usage(&prog_name, &opts));
+                return EXIT_USAGE;
+            },
+        },
+    };
+
+    let checksums = options.opt_present("checksums");
+    let cancellation = CancellationToken::new();
+    let mut extraction_options = ExtractionOptions::new()
+        .cancellation(&cancellation)
+        .strip_subchannel(!options.opt_present("no-strip-subchannel"))
+        .split_tracks(options.opt_present("split"))
+        .format(audio_format)
+        .gap_mode(gap_mode)
+        .channel_mode(channel_mode)
+        .offset(offset)
+        .checksums(checksums)
+        .verify(options.opt_present("verify-writes"))
+        .atomic(!options.opt_present("no-atomic"))
+        .ignore_errors(options.opt_present("ignore-errors"))
+        .de_emphasis(options.opt_present("de-emphasis"))
+        .loudness(options.opt_present("loudness"));
+    if let Some(template) = options.opt_str("name") {
+        extraction_options = extraction_options.name_template(&template);
+    }
+    let ascii = options.opt_present("ascii");
+    extraction_options = extraction_options.ascii_names(ascii);
+    if let Some(tracks) = tracks {
+        extraction_options = extraction_options.tracks(&tracks);
+    }
+    if let Some(size) = buffer_size {
+        extraction_options = extraction_options.buffer_size(size);
+    }
+    if let Some(rate) = rate_limit {
+        extraction_options = extraction_options.rate_limit(rate);
+    }
+    if let Some(encoder) = encoder {
+        extraction_options = extraction_options.encoder(encoder);
+    }
+    #[cfg(feature = "opus")]
+    {
+        if let Some(bitrate) = opus_bitrate {
+            extraction_options = extraction_options.opus_bitrate(bitrate);
+        }
+    }
+    #[cfg(feature = "mp3")]
+    {
+        if let Some(bitrate) = mp3_bitrate {
+            extraction_options = extraction_options.mp3_bitrate(bitrate);
+        }
+    }
+    #[cfg(feature = "vorbis")]
+    {
+        if let Some(quality) = vorbis_quality {
+            extraction_options = extraction_options.vorbis_quality(quality);
+        }
+    }
+    if let Some(path) = options.opt_str("raw-output") {
+        extraction_options = extraction_options.output_path(Path::new(&path));
+    }
+
+    let cue_gaps = options.opt_present("cue-detected-gaps");
+    let mut cue_options = CueSheetOptions::new()
+        .rem_lines(options.opt_present("cue-rem"))
+        .ascii(ascii);
+    if let Some(path) = options.opt_str("cue-output") {
+        cue_options = cue_options.output_path(Path::new(&path));
+    }
+
+    // See what actions are to be taken on the files
+    let action_cue =
+        options.opt_present("extract-cue") || options.opt_present("extract");
+    let interactive =
+        cfg!(feature = "tui") && options.opt_present("interactive");
+    let action_raw = options.opt_present("extract-raw")
+        || options.opt_present("extract") || interactive;
+    let musicbrainz =
+        cfg!(feature = "online") && options.opt_present("musicbrainz");
+    let summary = options.opt_present("summary");
+    let action_chunks = options.opt_present("chunks");
+    let actions = Actions {
+        quiet,
+        info: options.opt_present("info") || summary
+            || !(action_cue || action_raw || action_chunks),
+        summary,
+        chunks: action_chunks,
+        output_format,
+        color,
+        detect_gaps: options.opt_present("detect-gaps") || cue_gaps,
+        analyze: options.opt_present("analyze"),
+        hidden: options.opt_present("hidden")
+            || options.opt_present("extract-hidden"),
+        extract_hidden: options.opt_present("extract-hidden"),
+        accuraterip: options.opt_present("accuraterip"),
+        manifest: options.opt_present("manifest"),
+        provenance: options.opt_present("provenance"),
+        cue_gaps,
+        cue: action_cue,
+        raw: action_raw,
+        interactive,
+        musicbrainz,
+        checksums,
+        verify_writes: options.opt_present("verify-writes"),
+        loudness: options.opt_present("loudness"),
+        split_tracks: options.opt_present("split"),
+        gap_mode,
+        track_cues: options.opt_present("track-cues"),
+        cd_text_language,
+        session,
+        strict: options.opt_present("strict"),
+        cue_options,
+        extraction_options,
+    };
+
+    // Get input NRG image names, with their output directories
+    let output_dir = options.opt_str("output-dir").map(PathBuf::from);
+    let mut images = Vec::new();
+    if let Some(dir) = options.opt_str("recursive") {
+        let dir = PathBuf::from(dir);
+        let found = match find_images(&dir) {
+            Ok(found) => found,
+            Err(err) => {
+                error!("Can't search \"{}\": {}", dir.display(), err);
+                return EXIT_IO;
+            },
+        };
+        for relative_path in found {
+            // Keep the relative directory structure in the output directory
+            let relative_dir = relative_path.parent()
+                .map(PathBuf::from)
+                .unwrap_or_default();
+            let out_dir = output_dir.as_ref()
+                .map_or(relative_dir.clone(), |out| out.join(&relative_dir));
+            images.push((dir.join(relative_path), out_dir));
+        }
+    }
+    match expand_images(&options.free) {
+        Ok(paths) => images.extend(paths.into_iter().map(|path| {
+            (path, output_dir.clone().unwrap_or_default())
+        })),
+        Err(status) => return status,
+    }
+    if images.is_empty() {
+        // We need at least one input file!
+        eprint!("{}", usage(&prog_name, &opts));
+        return EXIT_USAGE;
+    }
+    if images.len() > 1 && (options.opt_present("cue-output")
+                            || options.opt_present("raw-output")) {
+        error!("--cue-output and --raw-output require a single image");
+        return EXIT_USAGE;
+    }
+
+    if actions.raw || actions.extract_hidden {
+        handle_interrupts(&cancellation);
+    }
+
+    // Process all the images, even if one fails, and return the status of
+    // the first failure; stop at the first one interrupted
+    let mut status = EXIT_SUCCESS;
+    for (img_path, out_dir) in images {
+        let img_status = rip_image(&img_path.to_string_lossy(), &out_dir,
+                                   &actions);
+        if status == EXIT_SUCCESS {
+            status = img_status;
+        }
+        if cancellation.is_cancelled() {
+            return EXIT_CANCELLED;
+        }
+    }
+    status
+}
+
+
+/// Makes Ctrl-C cancel the extractions through `cancellation`, so that
+/// the file being written is removed; a second Ctrl-C exits immediately.
+fn handle_interrupts(cancellation: &CancellationToken) {
+    *INTERRUPT_TOKEN.lock().unwrap_or_else(|err| err.into_inner()) =
+        Some(cancellation.clone());
+    install_interrupt_handler();
+}
+
+
+/// Installs the Ctrl-C handler, once. It cancels the token set by
+/// `handle_interrupts()` if it isn't cancelled yet, and otherwise removes
+/// the copy of the standard input and exits.
+fn install_interrupt_handler() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let result = ctrlc::set_handler(|| {
+            let token = INTERRUPT_TOKEN.lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .clone();
+            match token {
+                Some(ref token) if !token.is_cancelled() => {
+                    warn!("Interrupted, cancelling the extraction");
+                    token.cancel();
+                },
+                _ => {
+                    remove_stdin_image();
+                    process::exit(EXIT_CANCELLED);
+                },
+            }
+        });
+        if let Err(err) = result {
+            warn!("Can't handle Ctrl-C: {}", err);
+        }
+    });
+}
+
+
+/// Sets up the logger according to the -q and -v options, if they could be
+/// parsed.
+///
+/// Returns true if -q is present.
+fn init_logger(parsed_options: &Result<Matches, Fail>) -> bool {
+    let quiet = parsed_options.as_ref()
+        .is_ok_and(|options| options.opt_present("quiet"));
+    let verbosity = parsed_options.as_ref()
+        .map_or(0, |options| options.opt_count("verbose"));
+    let log_level = match verbosity {
+        _ if quiet => LevelFilter::Error,
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log_level);
+    }
+    quiet
+}
+
+
+/// Parses the value of the -f option.
+///
+/// Returns None if it is invalid.
+fn parse_output_format(options: &Matches) -> Option<OutputFormat> {
+    match options.opt_str("format") {
+        None => Some(OutputFormat::Text),
+        Some(ref format) if format == "text" => Some(OutputFormat::Text),
+        Some(ref format) if format == "json" => Some(OutputFormat::Json),
+        Some(ref format) if format == "tsv" => Some(OutputFormat::Tsv),
+        Some(format) => {
+            error!("Unknown format \"{}\"", format);
+            None
+        },
+    }
+}
+
+
+/// Tells whether the information should be colored, according to the
+/// "color" option: "auto" colors it if the standard output is a terminal
+/// and the NO_COLOR environment variable is not set.
+///
+/// Returns None if the option's value is invalid.
+fn parse_color(options: &Matches) -> Option<bool> {
+    match options.opt_str("color") {
+        None => Some(auto_color()),
+        Some(ref when) if when == "auto" => Some(auto_color()),
+        Some(ref when) if when == "always" => Some(true),
+        Some(ref when) if when == "never" => Some(false),
+        Some(when) => {
+            error!("Unknown color mode \"{}\"", when);
+            None
+        },
+    }
+}
+
+
+/// Tells whether the information should be colored by default.
+fn auto_color() -> bool {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|var| !var.is_empty());
+    !no_color && io::stdout().is_terminal()
+}
+
+
+/// Expands the glob patterns found in `args`; the other arguments are
+/// returned as is.
+///
+/// Returns None if a pattern is invalid.
+fn expand_patterns(args: &[String]) -> Option<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for arg in args {
+        if !arg.contains(['*', '?', '[']) {
+            paths.push(PathBuf::from(arg));
+            continue;
+        }
+        match glob::glob(arg) {
+            Ok(matches) => paths.extend(matches.filter_map(Result::ok)),
+            Err(err) => {
+                error!("Invalid pattern \"{}\": {}", arg, err);
+                return None;
+            },
+        }
+    }
+    Some(paths)
+}
+
+
+/// Expands the glob patterns found in `args`, and replaces "-" with a copy of
+/// the image read from the standard input.
+///
+/// Returns the exit status in case of error.
+fn expand_images(args: &[String]) -> Result<Vec<PathBuf>, i32> {
+    let mut paths = Vec::new();
+    for arg in args {
+        if arg == "-" {
+            paths.push(image_path(arg)?);
+        } else {
+            paths.extend(expand_patterns(std::slice::from_ref(arg))
+                         .ok_or(EXIT_USAGE)?);
+        }
+    }
+    Ok(paths)
+}
+
+
+/// Returns the path of the image `arg`, which is copied from the standard
+/// input to a temporary file if it is "-", so that it can be seeked.
+///
+/// The copy is named "stdin.nrg", which names the files extracted from it,
+/// in a new private directory; it is removed by `remove_stdin_image()`,
+/// including when the copy is interrupted by Ctrl-C. Returns the exit status
+/// in case of error.
+fn image_path(arg: &str) -> Result<PathBuf, i32> {
+    if arg != "-" {
+        return Ok(PathBuf::from(arg));
+    }
+
+    let mut stdin_image = STDIN_IMAGE.lock().unwrap_or_else(|err| {
+        err.into_inner()
+    });
+    if stdin_image.is_some() {
+        error!("The standard input can only be read once");
+        return Err(EXIT_USAGE);
+    }
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        error!("Refusing to read an image from a terminal");
+        return Err(EXIT_USAGE);
+    }
+
+    let dir = match create_private_dir() {
+        Ok(dir) => dir,
+        Err(err) => {
+            error!("Can't create a temporary directory: {}", err);
+            return Err(EXIT_IO);
+        },
+    };
+    let path = dir.join("stdin.nrg");
+    *stdin_image = Some(dir);
+    // Let the Ctrl-C handler remove the copy
+    drop(stdin_image);
+    install_interrupt_handler();
+
+    info!("Copying the standard input to \"{}\"", path.display());
+    let copy = OpenOptions::new().write(true).create_new(true).open(&path)
+        .and_then(|mut fd| io::copy(&mut stdin.lock(), &mut fd));
+    match copy {
+        Ok(_) => Ok(path),
+        Err(err) => {
+            error!("Can't copy the standard input to \"{}\": {}",
+                   path.display(), err);
+            Err(EXIT_IO)
+        },
+    }
+}
+
+
+/// Creates a new directory, readable only by the user, with a random name
+/// in the temporary directory. The creation fails rather than reusing an
+/// existing directory, which another user could have prepared.
+fn create_private_dir() -> io::Result<PathBuf> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    builder.mode(0o700);
+    let mut attempts = 0;
+    loop {
+        let random = RandomState::new().build_hasher().finish();
+        let dir = env::temp_dir().join(format!("nrgrip-{}-{:016x}",
+                                               process::id(), random));
+        match builder.create(&dir) {
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists
+                && attempts < 10 => attempts += 1,
+            result => return result.map(|()| dir),
+        }
+    }
+}
+
+
+/// Removes the copy of the image read from the standard input, if any.
+fn remove_stdin_image() {
+    let stdin_image = STDIN_IMAGE.lock().unwrap_or_else(|err| {
+        err.into_inner()
+    });
+    if let Some(ref dir) = *stdin_image {
+        if let Err(err) = fs::remove_dir_all(dir) {
+            warn!("Can't remove \"{}\": {}", dir.display(), err);
+        }
+    }
+}
+
+
+/// Runs the verify subcommand with the arguments `args`.
+fn verify_main(prog_name: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optflag("H", "hash",
+                 "compute the SHA-1 of the audio data");
+    opts.optopt("f", "format",
+                "format of the report: text (default), json or tsv",
+                "FORMAT");
+    opts.optflag("q", "quiet",
+                 "only print the report and the errors");
+    opts.optflagmulti("v", "verbose",
+                      "print more diagnostics (can be repeated)");
+    opts.optflag("h", "help",
+                 "print this help message");
+
+    let brief = format!("{} verify - check the consistency of NRG images
+
+Usage:
+    {} verify [options] <image.nrg>...", PRETTY_PROGNAME, prog_name);
+
+    let parsed_options = opts.parse(args);
+    let quiet = init_logger(&parsed_options);
+    let options = match parsed_options {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    if options.opt_present("help") {
+        print!("{}", opts.usage(&brief));
+        return EXIT_SUCCESS;
+    }
+    let output_format = match parse_output_format(&options) {
+        Some(format) => format,
+        None => return EXIT_USAGE,
+    };
+    let images = match expand_images(&options.free) {
+        Ok(ref images) if images.is_empty() => {
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+        Ok(images) => images,
+        Err(status) => return status,
+    };
+
+    let mut status = EXIT_SUCCESS;
+    for img_path in images {
+        let img_path = img_path.to_string_lossy();
+        if !quiet && output_format == OutputFormat::Text {
+            println!("NRG image path: \"{}\"", img_path);
+        }
+        let mut report = Report::new(output_format, &img_path);
+        let img_status = verify_image(&img_path, options.opt_present("hash"),
+                                      &mut report);
+        report.finish(img_status);
+        if status == EXIT_SUCCESS {
+            status = img_status;
+        }
+    }
+    status
+}
+
+
+/// Verifies the image `img_path` and adds the results to `report`.
+///
+/// Returns the exit status.
+fn verify_image(img_path: &str, hash_audio: bool, report: &mut Report)
+                -> i32 {
+    let mut fd = match File::open(img_path) {
+        Ok(fd) => fd,
+        Err(err) => {
+            error!("Can't open image file \"{}\": {}", img_path, err);
+            return EXIT_IO;
+        },
+    };
+    match verify::verify(&mut fd, hash_audio) {
+        Ok(verification) => {
+            report.verification(&verification);
+            if verification.passed() {
+                EXIT_SUCCESS
+            } else {
+                EXIT_FORMAT
+            }
+        },
+        Err(err) => {
+            error!("Error verifying \"{}\": {}", img_path, err);
+            exit_status(&err)
+        },
+    }
+}
+
+
+/// Runs the compare subcommand with the arguments `args`.
+fn compare_main(prog_name: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optopt("O", "search-offset",
+                "search the offset of the second image up to SAMPLES \
+                 samples away (at most 220500)",
+                "SAMPLES");
+    opts.optopt("f", "format",
+                "format of the report: text (default), json or tsv",
+                "FORMAT");
+    opts.optflag("q", "quiet",
+                 "only print the report and the errors");
+    opts.optflagmulti("v", "verbose",
+                      "print more diagnostics (can be repeated)");
+    opts.optflag("h", "help",
+                 "print this help message");
+
+    let brief = format!("{} compare - compare the audio of two images
+
+Usage:
+    {} compare [options] <image.nrg> <image.nrg|image.bin|sheet.cue>",
+                        PRETTY_PROGNAME, prog_name);
+
+    let parsed_options = opts.parse(args);
+    let quiet = init_logger(&parsed_options);
+    let options = match parsed_options {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    if options.opt_present("help") {
+        print!("{}", opts.usage(&brief));
+        return EXIT_SUCCESS;
+    }
+    let output_format = match parse_output_format(&options) {
+        Some(format) => format,
+        None => return EXIT_USAGE,
+    };
+    let max_offset = match options.opt_str("search-offset") {
+        None => 0,
+        Some(samples) => match samples.parse() {
+            Ok(samples) if samples <= compare::MAX_SEARCH_OFFSET => samples,
+            _ => {
+                error!("Invalid number of samples \"{}\"", samples);
+                eprint!("{}", opts.usage(&brief));
+                return EXIT_USAGE;
+            },
+        },
+    };
+    let (a_path, b_path) = match options.free.as_slice() {
+        [a_path, b_path] => (a_path, b_path),
+        _ => {
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+
+    if !quiet && output_format == OutputFormat::Text {
+        println!("Comparing \"{}\" with \"{}\"...", a_path, b_path);
+    }
+    let mut report = Report::new(output_format, a_path);
+    let status = compare_images(a_path, b_path, max_offset, &mut report);
+    report.finish(status);
+    status
+}
+
+
+/// Compares the images `a_path` and `b_path` and adds the results to
+/// `report`.
+///
+/// The images whose extension is ".cue" are read through their cue sheet,
+/// the other ones whose extension isn't ".nrg" are read as raw images.
+///
+/// Returns the exit status.
+fn compare_images(a_path: &str, b_path: &str, max_offset: u32,
+                  report: &mut Report) -> i32 {
+    let (mut a_fd, a) = match open_compared_image(a_path) {
+        Ok(image) => image,
+        Err(status) => return status,
+    };
+    let (mut b_fd, b) = match open_compared_image(b_path) {
+        Ok(image) => image,
+        Err(status) => return status,
+    };
+    match compare::compare(&mut a_fd, &a, &mut b_fd, &b, max_offset) {
+        Ok(comparison) => {
+            report.comparison(b_path, &comparison);
+            if comparison.same_audio() {
+                EXIT_SUCCESS
+            } else {
+                EXIT_DIFFERENT
+            }
+        },
+        Err(err) => {
+            error!("Error comparing \"{}\" with \"{}\": {}",
+                   a_path, b_path, err);
+            exit_status(&err)
+        },
+    }
+}
+
+
+/// Opens an image to compare and locates its audio.
+///
+/// Returns the exit status if it fails.
+fn open_compared_image(img_path: &str) -> Result<(File, NrgAudioLayout), i32> {
+    let extension = Path::new(img_path).extension()
+        .map(|extension| extension.to_ascii_lowercase());
+    if extension.as_ref().is_some_and(|extension| extension == "cue") {
+        let sheet = cue_sheet::parse(Path::new(img_path)).map_err(|err| {
+            error!("Error reading \"{}\": {}", img_path, err);
+            exit_status(&err)
+        })?;
+        let fd = File::open(&sheet.audio_path).map_err(|err| {
+            error!("Can't open audio file \"{}\": {}",
+                   sheet.audio_path.display(), err);
+            EXIT_IO
+        })?;
+        return Ok((fd, NrgAudioLayout::from_tracks(sheet.tracks)));
+    }
+
+    let mut fd = File::open(img_path).map_err(|err| {
+        error!("Can't open image file \"{}\": {}", img_path, err);
+        EXIT_IO
+    })?;
+    let layout = if extension.is_some_and(|extension| extension == "nrg") {
+        let metadata = metadata::read_nrg_metadata(&mut fd).map_err(|err| {
+            error!("Error reading \"{}\": {}", img_path, err);
+            exit_status(&err)
+        })?;
+        NrgAudioLayout::from_metadata(&metadata).map_err(|err| {
+            error!("Error reading \"{}\": {}", img_path, err);
+            exit_status(&err)
+        })?
+    } else {
+        let size = fd.metadata().map_err(|err| {
+            error!("Can't read the size of \"{}\": {}", img_path, err);
+            EXIT_IO
+        })?.len();
+        NrgAudioLayout::raw(size)
+    };
+    Ok((fd, layout))
+}
+
+
+/// Runs the convert subcommand with the arguments `args`.
+fn convert_main(prog_name: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optopt("t", "to",
+                "format of the converted images: bincue, iso or nrg",
+                "FORMAT");
+    opts.optflag("S", "no-strip-subchannel",
+                 "don't strip the 96-bit subchannel if present");
+    opts.optopt("o", "output-dir",
+                "write the converted images in DIR instead of the current \
+                 directory",
+                "DIR");
+    opts.optopt("f", "format",
+                "format of the displayed information: text (default), json \
+                 or tsv",
+                "FORMAT");
+    opts.optflag("q", "quiet",
+                 "only print the requested information and the errors");
+    opts.optflagmulti("v", "verbose",
+                      "print more diagnostics (can be repeated)");
+    opts.optflag("h", "help",
+                 "print this help message");
+
+    let brief = format!("{} convert - convert NRG images to other formats
+
+Usage:
+    {} convert --to FORMAT [options] <image.nrg>...",
+                        PRETTY_PROGNAME, prog_name);
+
+    let parsed_options = opts.parse(args);
+    let quiet = init_logger(&parsed_options);
+    let options = match parsed_options {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    if options.opt_present("help") {
+        print!("{}", opts.usage(&brief));
+        return EXIT_SUCCESS;
+    }
+    let output_format = match parse_output_format(&options) {
+        Some(format) => format,
+        None => return EXIT_USAGE,
+    };
+    let image_format = match options.opt_str("to") {
+        Some(ref format) if format == "bincue" => ImageFormat::BinCue,
+        Some(ref format) if format == "iso" => ImageFormat::Iso,
+        Some(ref format) if format == "nrg" => ImageFormat::Nrg,
+        Some(format) => {
+            error!("Unknown image format \"{}\"", format);
+            return EXIT_USAGE;
+        },
+        None => {
+            error!("The target format (--to) is mandatory");
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    let images = match expand_images(&options.free) {
+        Ok(ref images) if images.is_empty() => {
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+        Ok(images) => images,
+        Err(status) => return status,
+    };
+    let out_dir = options.opt_str("output-dir")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    if !out_dir.as_os_str().is_empty() {
+        if let Err(err) = fs::create_dir_all(&out_dir) {
+            error!("Can't create directory \"{}\": {}",
+                   out_dir.display(), err);
+            return EXIT_IO;
+        }
+    }
+    let strip_subchannel = !options.opt_present("no-strip-subchannel");
+
+    let mut status = EXIT_SUCCESS;
+    for img_path in images {
+        let img_path = img_path.to_string_lossy();
+        if !quiet && output_format == OutputFormat::Text {
+            println!("Converting \"{}\"...", img_path);
+        }
+        let mut report = Report::new(output_format, &img_path);
+        let img_status = convert_image(&img_path, image_format, &out_dir,
+                                       strip_subchannel, &mut report);
+        report.finish(img_status);
+        if status == EXIT_SUCCESS {
+            status = img_status;
+        }
+    }
+    status
+}
+
+
+/// Converts the image `img_path` to `format`, in `out_dir`, and adds the
+/// files written to `report`.
+///
+/// Returns the exit status.
+fn convert_image(img_path: &str, format: ImageFormat, out_dir: &Path,
+                 strip_subchannel: bool, report: &mut Report) -> i32 {
+    let mut fd = match File::open(img_path) {
+        Ok(fd) => fd,
+        Err(err) => {
+            error!("Can't open image file \"{}\": {}", img_path, err);
+            return EXIT_IO;
+        },
+    };
+    let metadata = match metadata::read_nrg_metadata(&mut fd) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            error!("Error reading \"{}\": {}", img_path, err);
+            return exit_status(&err);
+        },
+    };
+
+    let out_base = match Path::new(img_path).file_stem() {
+        Some(stem) => out_dir.join(stem),
+        None => {
+            error!("Invalid file name: {}", img_path);
+            return EXIT_USAGE;
+        },
+    };
+    match convert::convert(&mut fd, img_path, &metadata, format, &out_base,
+                           strip_subchannel) {
+        Ok(files) => {
+            report.files(&files, true);
+            EXIT_SUCCESS
+        },
+        Err(err) => {
+            error!("Error converting \"{}\": {}", img_path, err);
+            exit_status(&err)
+        },
+    }
+}
+
+
+/// Runs the split subcommand with the arguments `args`.
+fn split_main(prog_name: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optopt("t", "to",
+                "format of the session images: nrg (default) or bincue",
+                "FORMAT");
+    opts.optflag("S", "no-strip-subchannel",
+                 "don't strip the 96-bit subchannel if present (bincue)");
+    opts.optopt("o", "output-dir",
+                "write the session images in DIR instead of the current \
+                 directory",
+                "DIR");
+    opts.optopt("f", "format",
+                "format of the displayed information: text (default), json \
+                 or tsv",
+                "FORMAT");
+    opts.optflag("q", "quiet",
+                 "only print the requested information and the errors");
+    opts.optflagmulti("v", "verbose",
+                      "print more diagnostics (can be repeated)");
+    opts.optflag("h", "help",
+                 "print this help message");
+
+    let brief = format!("{} split - write each session of multi-session NRG \
+                         images as a standalone image
+
+Usage:
+    {} split [options] <image.nrg>...",
+                        PRETTY_PROGNAME, prog_name);
+
+    let parsed_options = opts.parse(args);
+    let quiet = init_logger(&parsed_options);
+    let options = match parsed_options {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    if options.opt_present("help") {
+        print!("{}", opts.usage(&brief));
+        return EXIT_SUCCESS;
+    }
+    let output_format = match parse_output_format(&options) {
+        Some(format) => format,
+        None => return EXIT_USAGE,
+    };
+    let image_format = match options.opt_str("to") {
+        None => ImageFormat::Nrg,
+        Some(ref format) if format == "nrg" => ImageFormat::Nrg,
+        Some(ref format) if format == "bincue" => ImageFormat::BinCue,
+        Some(format) => {
+            error!("Unknown image format \"{}\"", format);
+            return EXIT_USAGE;
+        },
+    };
+    let images = match expand_images(&options.free) {
+        Ok(ref images) if images.is_empty() => {
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+        Ok(images) => images,
+        Err(status) => return status,
+    };
+    let out_dir = options.opt_str("output-dir")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    if !out_dir.as_os_str().is_empty() {
+        if let Err(err) = fs::create_dir_all(&out_dir) {
+            error!("Can't create directory \"{}\": {}",
+                   out_dir.display(), err);
+            return EXIT_IO;
+        }
+    }
+    let strip_subchannel = !options.opt_present("no-strip-subchannel");
+
+    let mut status = EXIT_SUCCESS;
+    for img_path in images {
+        let img_path = img_path.to_string_lossy();
+        if !quiet && output_format == OutputFormat::Text {
+            println!("Splitting \"{}\"...", img_path);
+        }
+        let mut report = Report::new(output_format, &img_path);
+        let img_status = split_image(&img_path, image_format, &out_dir,
+                                     strip_subchannel, &mut report);
+        report.finish(img_status);
+        if status == EXIT_SUCCESS {
+            status = img_status;
+        }
+    }
+    status
+}
+
+
+/// Writes the sessions of the image `img_path` in `format` (NRG or BIN/CUE),
+/// in `out_dir`, and adds the files written to `report`.
+///
+/// The BIN/CUE files are converted from the NRG images of the sessions,
+/// which are removed afterwards.
+///
+/// Returns the exit status.
+fn split_image(img_path: &str, format: ImageFormat, out_dir: &Path,
+               strip_subchannel: bool, report: &mut Report) -> i32 {
+    let out_base = match Path::new(img_path).file_stem() {
+        Some(stem) => out_dir.join(stem),
+        None => {
+            error!("Invalid file name: {}", img_path);
+            return EXIT_USAGE;
+        },
+    };
+    let files = match sessions::split_file(Path::new(img_path), &out_base) {
+        Ok(files) => files,
+        Err(err) => {
+            error!("Error splitting \"{}\": {}", img_path, err);
+            return exit_status(&err);
+        },
+    };
+    if format == ImageFormat::Nrg {
+        report.files(&files, true);
+        return EXIT_SUCCESS;
+    }
+
+    let mut status = EXIT_SUCCESS;
+    for file in files {
+        let session_path = Path::new(&file.path);
+        let img_status = convert_image(&file.path, format, out_dir,
+                                       strip_subchannel, report);
+        if let Err(err) = fs::remove_file(session_path) {
+            warn!("Can't remove \"{}\": {}", file.path, err);
+        }
+        if status == EXIT_SUCCESS {
+            status = img_status;
+        }
+    }
+    status
+}
+
+
+/// Runs the dump-chunks subcommand with the arguments `args`.
+fn dump_chunks_main(prog_name: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optmulti("c", "chunk",
+                  "only dump the chunks whose ID is CHUNK (can be repeated)",
+                  "CHUNK");
+    opts.optopt("f", "format",
+                "format of the dump: text (default), json or tsv",
+                "FORMAT");
+    opts.optflag("q", "quiet",
+                 "only print the dump and the errors");
+    opts.optflagmulti("v", "verbose",
+                      "print more diagnostics (can be repeated)");
+    opts.optflag("h", "help",
+                 "print this help message");
+
+    let brief = format!("{} dump-chunks - dump the raw metadata chunks of NRG \
+                         images
+
+Usage:
+    {} dump-chunks [options] <image.nrg>...", PRETTY_PROGNAME, prog_name);
+
+    let parsed_options = opts.parse(args);
+    let quiet = init_logger(&parsed_options);
+    let options = match parsed_options {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    if options.opt_present("help") {
+        print!("{}", opts.usage(&brief));
+        return EXIT_SUCCESS;
+    }
+    let output_format = match parse_output_format(&options) {
+        Some(format) => format,
+        None => return EXIT_USAGE,
+    };
+    let images = match expand_images(&options.free) {
+        Ok(ref images) if images.is_empty() => {
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+        Ok(images) => images,
+        Err(status) => return status,
+    };
+    let chunk_ids = options.opt_strs("chunk");
+
+    let mut status = EXIT_SUCCESS;
+    for img_path in images {
+        let img_path = img_path.to_string_lossy();
+        if !quiet && output_format == OutputFormat::Text {
+            println!("NRG image path: \"{}\"", img_path);
+        }
+        let mut report = Report::new(output_format, &img_path);
+        let img_status = dump_chunks(&img_path, &chunk_ids, &mut report);
+        report.finish(img_status);
+        if status == EXIT_SUCCESS {
+            status = img_status;
+        }
+    }
+    status
+}
+
+
+/// Adds the raw chunks of the image `img_path` whose IDs are in `chunk_ids`
+/// (all the chunks if it is empty) to `report`.
+///
+/// Returns the exit status.
+fn dump_chunks(img_path: &str, chunk_ids: &[String], report: &mut Report)
+               -> i32 {
+    let mut fd = match File::open(img_path) {
+        Ok(fd) => fd,
+        Err(err) => {
+            error!("Can't open image file \"{}\": {}", img_path, err);
+            return EXIT_IO;
+        },
+    };
+    let chunks = match raw::read_raw_chunks(&mut fd) {
+        Ok(chunks) => chunks,
+        Err(err) => {
+            error!("Error reading \"{}\": {}", img_path, err);
+            return exit_status(&err);
+        },
+    };
+
+    let mut status = EXIT_SUCCESS;
+    for id in chunk_ids {
+        if !chunks.iter().any(|chunk| chunk.id.eq_ignore_ascii_case(id)) {
+            error!("No {} chunk in \"{}\"", id, img_path);
+            status = EXIT_USAGE;
+        }
+    }
+    let chunks: Vec<NrgRawChunk> = chunks.into_iter()
+        .filter(|chunk| chunk_ids.is_empty() || chunk_ids.iter()
+                .any(|id| chunk.id.eq_ignore_ascii_case(id)))
+        .collect();
+    report.chunks(&chunks);
+    status
+}
+
+
+/// Runs the play subcommand with the arguments `args`.
+fn play_main(prog_name: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optopt("t", "track",
+                "play track NUMBER instead of the first track",
+                "NUMBER");
+    opts.optflag("q", "quiet",
+                 "only print the errors");
+    opts.optflagmulti("v", "verbose",
+                      "print more diagnostics (can be repeated)");
+    opts.optflag("h", "help",
+                 "print this help message");
+
+    let brief = format!("{} play - play a track of an NRG image
+
+Usage:
+    {} play [options] <image.nrg>", PRETTY_PROGNAME, prog_name);
+
+    let parsed_options = opts.parse(args);
+    let quiet = init_logger(&parsed_options);
+    let options = match parsed_options {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    if options.opt_present("help") {
+        print!("{}", opts.usage(&brief));
+        return EXIT_SUCCESS;
+    }
+    let img_path = match options.free.as_slice() {
+        [img_path] => img_path,
+        _ => {
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    let track_number = match options.opt_str("track") {
+        None => None,
+        Some(number) => match number.parse::<u8>() {
+            Ok(number) => Some(number),
+            Err(_) => {
+                error!("Invalid track number: \"{}\"", number);
+                return EXIT_USAGE;
+            },
+        },
+    };
+    let img_path = match image_path(img_path) {
+        Ok(path) => path,
+        Err(status) => return status,
+    };
+    play_image(&img_path.to_string_lossy(), track_number, quiet)
+}
+
+
+/// Plays the track `track_number` (the first track if None) of the image
+/// `img_path`.
+///
+/// Returns the exit status.
+#[cfg(feature = "audio")]
+fn play_image(img_path: &str, track_number: Option<u8>, quiet: bool) -> i32 {
+    let mut fd = match File::open(img_path) {
+        Ok(fd) => fd,
+        Err(err) => {
+            error!("Can't open image file \"{}\": {}", img_path, err);
+            return EXIT_IO;
+        },
+    };
+    let metadata = match metadata::read_nrg_metadata(&mut fd) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            error!("Error reading \"{}\": {}", img_path, err);
+            return exit_status(&err);
+        },
+    };
+    let track = match track_number {
+        None => metadata.tracks().next(),
+        Some(number) => metadata.tracks().find(|track| track.number == number),
+    };
+    let track = match track {
+        Some(track) => track,
+        None => {
+            let err = NrgError::TrackNotFound(track_number.unwrap_or(1));
+            error!("Error playing \"{}\": {}", img_path, err);
+            return exit_status(&err);
+        },
+    };
+
+    if !quiet {
+        let title = track.title.as_ref()
+            .map_or_else(String::new, |title| format!(" \"{}\"", title));
+        println!("Playing track {}{} ({})", track.number, title,
+                 track.length());
+    }
+    match play::play_track(&mut fd, &track) {
+        Ok(()) => EXIT_SUCCESS,
+        Err(err) => {
+            error!("Error playing \"{}\": {}", img_path, err);
+            exit_status(&err)
+        },
+    }
+}
+
+
+/// Fails to play the image, since playback requires the audio feature.
+#[cfg(not(feature = "audio"))]
+fn play_image(_img_path: &str, _track_number: Option<u8>, _quiet: bool)
+              -> i32 {
+    error!("Playback is unavailable: {} was built without the audio feature",
+           PRETTY_PROGNAME);
+    EXIT_USAGE
+}
+
+
+/// Runs the tag subcommand with the arguments `args`.
+fn tag_main(prog_name: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optopt("t", "track",
+                "tag the single file given as track NUMBER",
+                "NUMBER");
+    opts.optopt("", "album",
+                "set the album's title to TITLE",
+                "TITLE");
+    opts.optopt("", "artist",
+                "set the artist to NAME",
+                "NAME");
+    opts.optopt("", "cd-text-language",
+                "take the titles, the album and the artist from the CD-Text \
+                 block in LANG (a language name or code)",
+                "LANG");
+    #[cfg(feature = "online")]
+    opts.optflag("M", "musicbrainz",
+                 "look up the titles, the album and the artist in the \
+                  MusicBrainz database");
+    opts.optflag("q", "quiet",
+                 "only print the errors");
+    opts.optflagmulti("v", "verbose",
+                      "print more diagnostics (can be repeated)");
+    opts.optflag("h", "help",
+                 "print this help message");
+
+    let brief = format!("{} tag - tag the files extracted from an NRG image
+
+Usage:
+    {} tag [options] <image.nrg> <file>...", PRETTY_PROGNAME, prog_name);
+
+    let parsed_options = opts.parse(args);
+    let quiet = init_logger(&parsed_options);
+    let options = match parsed_options {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    if options.opt_present("help") {
+        print!("{}", opts.usage(&brief));
+        return EXIT_SUCCESS;
+    }
+    let (img_path, files) = match options.free.split_first() {
+        Some((img_path, files)) if !files.is_empty() => (img_path, files),
+        _ => {
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    let files = match expand_patterns(files) {
+        Some(files) => files,
+        None => return EXIT_USAGE,
+    };
+    let img_path = match image_path(img_path) {
+        Ok(path) => path.to_string_lossy().into_owned(),
+        Err(status) => return status,
+    };
+    let track_number = match options.opt_str("track") {
+        None => None,
+        Some(number) => match number.parse::<u8>() {
+            Ok(number) if files.len() == 1 => Some(number),
+            Ok(_) => {
+                error!("A track number can only be given for a single file");
+                return EXIT_USAGE;
+            },
+            Err(_) => {
+                error!("Invalid track number: \"{}\"", number);
+                return EXIT_USAGE;
+            },
+        },
+    };
+
+    let mut fd = match File::open(&img_path) {
+        Ok(fd) => fd,
+        Err(err) => {
+            error!("Can't open image file \"{}\": {}", img_path, err);
+            return EXIT_IO;
+        },
+    };
+    let mut metadata = match metadata::read_nrg_metadata(&mut fd) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            error!("Error reading \"{}\": {}", img_path, err);
+            return exit_status(&err);
+        },
+    };
+    match cd_text_language(&options) {
+        Ok(language) => metadata.set_cd_text_language(language),
+        Err(status) => return status,
+    }
+    let mut album = options.opt_str("album");
+    let mut artist = options.opt_str("artist");
+    if cfg!(feature = "online") && options.opt_present("musicbrainz") {
+        if let Some((title, name)) = look_up_titles(&mut metadata) {
+            album = album.or(Some(title));
+            artist = artist.or(Some(name));
+        }
+    }
+
+    // Match the files with the tracks: one file per track in order, or a
+    // single file for the whole disc
+    let numbers: Vec<Option<u8>> = match track_number {
+        Some(number) => vec![Some(number)],
+        None if files.len() == metadata.tracks().count() =>
+            metadata.tracks().map(|track| Some(track.number)).collect(),
+        None if files.len() == 1 => vec![None],
+        None => {
+            error!("{} files given for the {} tracks of \"{}\"",
+                   files.len(), metadata.tracks().count(), img_path);
+            return EXIT_USAGE;
+        },
+    };
+
+    let mut status = EXIT_SUCCESS;
+    for (path, number) in files.iter().zip(numbers) {
+        if !quiet {
+            println!("Tagging \"{}\"...", path.display());
+        }
+        let result = NrgTags::from_metadata(&metadata, number)
+            .map(|tags| match album {
+                Some(ref album) => tags.album(album),
+                None => tags,
+            })
+            .map(|tags| match artist {
+                Some(ref artist) => tags.artist(artist),
+                None => tags,
+            })
+            .and_then(|tags| tags::write_tags(path, &tags));
+        if let Err(err) = result {
+            error!("Error tagging \"{}\": {}", path.display(), err);
+            if status == EXIT_SUCCESS {
+                status = exit_status(&err);
+            }
+        }
+    }
+    status
+}
+
+
+/// Runs the build subcommand with the arguments `args`.
+fn build_main(prog_name: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optopt("p", "pregap",
+                "pre-gap of the tracks built from WAV files: a single \
+                 MM:SS:FF length, or a comma-separated list of one length \
+                 per track (default 00:00:00)",
+                "LENGTHS");
+    opts.optflag("T", "cd-text",
+                 "write CD-Text, the tracks' titles being the WAV files' \
+                  names");
+    opts.optopt("", "title",
+                "set the CD-Text title of the disc (implies -T)",
+                "TITLE");
+    opts.optopt("", "performer",
+                "set the CD-Text performer of the disc (implies -T)",
+                "NAME");
+    opts.optflag("q", "quiet",
+                 "only print the errors");
+    opts.optflagmulti("v", "verbose",
+                      "print more diagnostics (can be repeated)");
+    opts.optflag("h", "help",
+                 "print this help message");
+
+    let brief = format!("{prettyprog} build - build an NRG image from a cue \
+sheet or WAV files
+
+Usage:
+    {prog} build [options] <sheet.cue> <image.nrg>
+    {prog} build [options] <track.wav>... <image.nrg>",
+                        prettyprog = PRETTY_PROGNAME, prog = prog_name);
+
+    let parsed_options = opts.parse(args);
+    let quiet = init_logger(&parsed_options);
+    let options = match parsed_options {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    if options.opt_present("help") {
+        print!("{}", opts.usage(&brief));
+        return EXIT_SUCCESS;
+    }
+    let (img_path, inputs) = match options.free.split_last() {
+        Some((img_path, inputs)) if !inputs.is_empty() =>
+            (Path::new(img_path), inputs),
+        _ => {
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    let inputs = match expand_patterns(inputs) {
+        Some(inputs) => inputs,
+        None => return EXIT_USAGE,
+    };
+    let cue_sheet = inputs.len() == 1 && inputs[0].extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("cue"));
+    let cd_text = options.opt_present("cd-text")
+        || options.opt_present("title") || options.opt_present("performer");
+    if cue_sheet && (cd_text || options.opt_present("pregap")) {
+        error!("The pre-gap and CD-Text options only apply to WAV files");
+        return EXIT_USAGE;
+    }
+
+    if !quiet {
+        println!("Building \"{}\"...", img_path.display());
+    }
+    let result = if cue_sheet {
+        writer::write_nrg_from_cue(&inputs[0], img_path)
+    } else {
+        let pregaps = match parse_pregaps(options.opt_str("pregap"),
+                                          inputs.len()) {
+            Some(pregaps) => pregaps,
+            None => {
+                error!("Invalid pre-gap lengths for {} tracks",
+                       inputs.len());
+                return EXIT_USAGE;
+            },
+        };
+        let tracks: Vec<NrgWavTrack> = inputs.iter().zip(pregaps)
+            .map(|(path, pregap)| NrgWavTrack { path: path.clone(), pregap })
+            .collect();
+        let cd_text = if cd_text {
+            let title = |path: &PathBuf| path.file_stem()
+                .map_or_else(String::new,
+                             |stem| stem.to_string_lossy().into_owned());
+            Some(NrgCdText {
+                title: options.opt_str("title").unwrap_or_default(),
+                performer: options.opt_str("performer").unwrap_or_default(),
+                tracks: inputs.iter().map(|path| NrgCdTextTrack {
+                    title: title(path),
+                    ..NrgCdTextTrack::default()
+                }).collect(),
+                ..NrgCdText::default()
+            })
+        } else {
+            None
+        };
+        writer::write_nrg_from_wavs(&tracks, cd_text, img_path)
+    };
+    match result {
+        Ok(size) => {
+            if !quiet {
+                println!("{} bytes written", size);
+            }
+            EXIT_SUCCESS
+        },
+        Err(err) => {
+            error!("Error building \"{}\": {}", img_path.display(), err);
+            exit_status(&err)
+        },
+    }
+}
+
+
+/// Runs the edit subcommand with the arguments `args`.
+fn edit_main(prog_name: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optopt("", "upc",
+                "set the disc's UPC/EAN to CODE (13 characters, empty to \
+                 clear it)",
+                "CODE");
+    opts.optmulti("", "isrc",
+                  "set the ISRC of track NUMBER to CODE (CC-XXX-YY-NNNNN, \
+                   hyphens optional, empty to clear it; can be repeated)",
+                  "NUMBER=CODE");
+    opts.optopt("", "cd-text",
+                "add CD-Text read from the TITLE and PERFORMER commands of \
+                 the cue sheet FILE",
+                "FILE");
+    opts.optflag("q", "quiet",
+                 "only print the errors");
+    opts.optflagmulti("v", "verbose",
+                      "print more diagnostics (can be repeated)");
+    opts.optflag("h", "help",
+                 "print this help message");
+
+    let brief = format!("{} edit - edit the UPC, ISRCs and CD-Text of an NRG \
+                         image
+
+Usage:
+    {} edit [options] <image.nrg>", PRETTY_PROGNAME, prog_name);
+
+    let parsed_options = opts.parse(args);
+    let quiet = init_logger(&parsed_options);
+    let options = match parsed_options {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    if options.opt_present("help") {
+        print!("{}", opts.usage(&brief));
+        return EXIT_SUCCESS;
+    }
+    let img_path = match options.free.as_slice() {
+        [img_path] => img_path,
+        _ => {
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+
+    let mut edit = NrgDaoxEdit::new();
+    if let Some(upc) = options.opt_str("upc") {
+        edit = edit.upc(&upc);
+    }
+    for isrc in options.opt_strs("isrc") {
+        let assignment = isrc.split_once('=').and_then(|(number, code)| {
+            number.trim().parse::<u8>().ok().map(|number| (number, code))
+        });
+        match assignment {
+            Some((number, code)) => edit = edit.isrc(number, code),
+            None => {
+                error!("Invalid ISRC assignment: \"{}\"", isrc);
+                return EXIT_USAGE;
+            },
+        }
+    }
+    let cd_text = match options.opt_str("cd-text") {
+        Some(cue_path) => match fs::read_to_string(&cue_path) {
+            Ok(text) => Some(NrgCdText::from_cue_sheet(&text)),
+            Err(err) => {
+                error!("Can't read cue sheet \"{}\": {}", cue_path, err);
+                return EXIT_IO;
+            },
+        },
+        None => None,
+    };
+    if edit.is_empty() && cd_text.is_none() {
+        error!("Nothing to edit");
+        eprint!("{}", opts.usage(&brief));
+        return EXIT_USAGE;
+    }
+
+    if !quiet {
+        println!("Editing \"{}\"...", img_path);
+    }
+    let result = fs::OpenOptions::new().read(true).write(true)
+        .open(img_path)
+        .map_err(NrgError::from)
+        .and_then(|mut fd| {
+            if !edit.is_empty() {
+                editor::edit_daox(&mut fd, &edit)?;
+            }
+            match cd_text {
+                Some(ref cd_text) => editor::add_cd_text(&mut fd, cd_text),
+                None => Ok(()),
+            }
+        });
+    match result {
+        Ok(()) => EXIT_SUCCESS,
+        Err(err) => {
+            error!("Error editing \"{}\": {}", img_path, err);
+            exit_status(&err)
+        },
+    }
+}
+
+
+/// Runs the repair subcommand with the arguments `args`.
+fn repair_main(prog_name: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optflag("q", "quiet",
+                 "only print the errors");
+    opts.optflagmulti("v", "verbose",
+                      "print more diagnostics (can be repeated)");
+    opts.optflag("h", "help",
+                 "print this help message");
+
+    let brief = format!("{} repair - repair an NRG image whose end was lost
+
+Usage:
+    {} repair [options] <damaged.nrg> <image.nrg>",
+                        PRETTY_PROGNAME, prog_name);
+
+    let parsed_options = opts.parse(args);
+    let quiet = init_logger(&parsed_options);
+    let options = match parsed_options {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    if options.opt_present("help") {
+        print!("{}", opts.usage(&brief));
+        return EXIT_SUCCESS;
+    }
+    let (img_path, out_path) = match options.free.as_slice() {
+        [img_path, out_path] => (Path::new(img_path), Path::new(out_path)),
+        _ => {
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+
+    let readable = File::open(img_path).map_err(NrgError::from)
+        .and_then(|mut fd| metadata::read_nrg_metadata(&mut fd));
+    if readable.is_ok() {
+        if !quiet {
+            println!("\"{}\" is not damaged, nothing to repair",
+                     img_path.display());
+        }
+        return EXIT_SUCCESS;
+    }
+
+    if !quiet {
+        println!("Repairing \"{}\"...", img_path.display());
+    }
+    match repair::repair_file(img_path, out_path) {
+        Ok(repair) => {
+            if !quiet {
+                println!("Recovered chunks: {}", repair.recovered.join(" "));
+                println!("Rebuilt chunks: {}", repair.rebuilt.join(" "));
+                println!("{} bytes written", repair.size);
+            }
+            EXIT_SUCCESS
+        },
+        Err(err) => {
+            error!("Error repairing \"{}\": {}", img_path.display(), err);
+            exit_status(&err)
+        },
+    }
+}
+
+
+/// Runs the upgrade subcommand with the arguments `args`.
+fn upgrade_main(prog_name: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optflag("q", "quiet",
+                 "only print the errors");
+    opts.optflagmulti("v", "verbose",
+                      "print more diagnostics (can be repeated)");
+    opts.optflag("h", "help",
+                 "print this help message");
+
+    let brief = format!("{} upgrade - convert an NRG v1 image to NRG v2
+
+Usage:
+    {} upgrade [options] <image-v1.nrg> <image.nrg>",
+                        PRETTY_PROGNAME, prog_name);
+
+    let parsed_options = opts.parse(args);
+    let quiet = init_logger(&parsed_options);
+    let options = match parsed_options {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+    if options.opt_present("help") {
+        print!("{}", opts.usage(&brief));
+        return EXIT_SUCCESS;
+    }
+    let (img_path, out_path) = match options.free.as_slice() {
+        [img_path, out_path] => (Path::new(img_path), Path::new(out_path)),
+        _ => {
+            eprint!("{}", opts.usage(&brief));
+            return EXIT_USAGE;
+        },
+    };
+
+    if !quiet {
+        println!("Converting \"{}\" to NRG v2...", img_path.display());
+    }
+    match upgrade::upgrade_file(img_path, out_path) {
+        Ok(size) => {
+            if !quiet {
+                println!("{} bytes written", size);
+            }
+            EXIT_SUCCESS
+        },
+        Err(err) => {
+            error!("Error converting \"{}\": {}", img_path.display(), err);
+            exit_status(&err)
+        },
+    }
+}
+
+
+/// Parses the pre-gap lengths of `count` tracks: a single MSF length for
+/// every track, or a comma-separated list of one length per track.
+///
+/// Returns the lengths in sectors, or None if `lengths` is invalid.
+fn parse_pregaps(lengths: Option<String>, count: usize) -> Option<Vec<u32>> {
+    let lengths = match lengths {
+        None => return Some(vec![0; count]),
+        Some(lengths) => lengths,
+    };
+    let pregaps = lengths.split(',')
+        .map(|length| Msf::parse(length.trim()))
+        .map(|msf| msf.map(|msf| msf.to_sectors()))
+        .collect::<Option<Vec<u32>>>()?;
+    match pregaps.len() {
+        1 => Some(vec![pregaps[0]; count]),
+        len if len == count => Some(pregaps),
+        _ => None,
+    }
+}
+
+
+/// Actions to be taken on each input image, as requested on the command
+/// line.
+struct Actions {
+    quiet: bool,
+    info: bool,
+    summary: bool,
+    chunks: bool,
+    output_format: OutputFormat,
+    color: bool,
+    detect_gaps: bool,
+    analyze: bool,
+    hidden: bool,
+    extract_hidden: bool,
+    accuraterip: bool,
+    manifest: bool,
+    provenance: bool,
+    cue_gaps: bool,
+    cue: bool,
+    raw: bool,
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    interactive: bool,
+    musicbrainz: bool,
+    checksums: bool,
+    verify_writes: bool,
+    loudness: bool,
+    split_tracks: bool,
+    gap_mode: GapMode,
+    track_cues: bool,
+    cd_text_language: Option<u8>,
+    session: Option<usize>,
+    strict: bool,
+    cue_options: CueSheetOptions,
+    extraction_options: ExtractionOptions,
+}
+
+impl Actions {
+    /// Tells whether the actions need the parsed metadata, i.e. whether any
+    /// action other than listing the chunks was requested.
+    fn need_metadata(&self) -> bool {
+        self.info || self.detect_gaps || self.analyze || self.hidden
+            || self.cue || self.raw || self.accuraterip || self.musicbrainz
+    }
+
+    /// Prints a progress message, unless quiet or printing machine-readable
+    /// output.
+    fn progress(&self, message: &str) {
+        if !self.quiet && self.output_format == OutputFormat::Text {
+            println!("{}", message);
+        }
+    }
+}
+
+
+/// Lists the NRG images located under `dir`, recursively.
+///
+/// The paths returned are relative to `dir`, and sorted.
+fn find_images(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut images = Vec::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(relative_dir) = dirs.pop() {
+        for entry in fs::read_dir(dir.join(&relative_dir))? {
+            let entry = entry?;
+            let relative_path = relative_dir.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                dirs.push(relative_path);
+            } else if relative_path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("nrg")) {
+                images.push(relative_path);
+            }
+        }
+    }
+    images.sort();
+    Ok(images)
+}
+
+
+/// Format of the information printed on the standard output.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+    Tsv,
+}
+
+
+/// Results of the actions taken on an image, printed in the requested
+/// format.
+///
+/// Text and TSV results are printed as soon as they are added; JSON results
+/// are gathered in a single object per image, printed by `finish()`.
+struct Report {
+    format: OutputFormat,
+    color: bool,
+    summary: bool,
+    members: Vec<(&'static str, JsonValue)>,
+}
+
+impl Report {
+    fn new(format: OutputFormat, img_path: &str) -> Report {
+        if format == OutputFormat::Tsv {
+            println!("{}", tsv::image_to_tsv(img_path));
+        }
+        Report {
+            format,
+            color: false,
+            summary: false,
+            members: vec![
+                ("schema_version", json::SCHEMA_VERSION.into()),
+                ("image", img_path.into()),
+            ],
+        }
+    }
+
+    /// Colors the text output if `color` is true.
+    fn color(mut self, color: bool) -> Report {
+        self.color = color;
+        self
+    }
+
+    /// Prints the text metadata as a summary if `summary` is true.
+    fn summary(mut self, summary: bool) -> Report {
+        self.summary = summary;
+        self
+    }
+
+    fn metadata(&mut self, metadata: &NrgMetadata) {
+        match self.format {
+            OutputFormat::Text if self.summary => {
+                print!("\n{}", text::summary_to_text(metadata, self.color));
+            },
+            OutputFormat::Text => {
+                print!("\n{}", text::metadata_to_text(metadata, self.color));
+            },
+            OutputFormat::Json => {
+                let metadata = json::metadata_to_json(metadata);
+                self.members.push(("metadata", metadata));
+            },
+            OutputFormat::Tsv => for record in tsv::metadata_to_tsv(metadata) {
+                println!("{}", record);
+            },
+        }
+    }
+
+    fn gaps(&mut self, gaps: &[NrgGap]) {
+        match self.format {
+            OutputFormat::Text => for gap in gaps {
+                println!("{}", gap);
+            },
+            OutputFormat::Json => {
+                let gaps = gaps.iter().map(json::gap_to_json).collect();
+                self.members.push(("gaps", JsonValue::Array(gaps)));
+            },
+            OutputFormat::Tsv => for gap in gaps {
+                println!("{}", tsv::gap_to_tsv(gap));
+            },
+        }
+    }
+
+    fn hidden_data(&mut self, hidden: Option<&NrgHiddenData>) {
+        match (self.format, hidden) {
+            (OutputFormat::Text, Some(hidden)) => println!("{}", hidden),
+            (OutputFormat::Text, None) =>
+                println!("No hidden data after the last track"),
+            (OutputFormat::Json, _) => {
+                let hidden = hidden.map_or(JsonValue::Null,
+                                           json::hidden_data_to_json);
+                self.members.push(("hidden_data", hidden));
+            },
+            (OutputFormat::Tsv, Some(hidden)) =>
+                println!("{}", tsv::hidden_data_to_tsv(hidden)),
+            (OutputFormat::Tsv, None) => (),
+        }
+    }
+
+    fn analyses(&mut self, analyses: &[NrgTrackAnalysis]) {
+        match self.format {
+            OutputFormat::Text => for analysis in analyses {
+                println!("{}", analysis);
+            },
+            OutputFormat::Json => {
+                let analyses = analyses.iter()
+                    .map(json::track_analysis_to_json)
+                    .collect();
+                self.members.push(("analysis", JsonValue::Array(analyses)));
+            },
+            OutputFormat::Tsv => for analysis in analyses {
+                println!("{}", tsv::track_analysis_to_tsv(analysis));
+            },
+        }
+    }
+
+    fn accuraterip(&mut self, tracks: &[NrgAccurateRipTrack]) {
+        match self.format {
+            OutputFormat::Text => for track in tracks {
+                println!("{}", track);
+            },
+            OutputFormat::Json => {
+                let tracks = tracks.iter()
+                    .map(json::accuraterip_to_json)
+                    .collect();
+                self.members.push(("accuraterip", JsonValue::Array(tracks)));
+            },
+            OutputFormat::Tsv => for track in tracks {
+                println!("{}", tsv::accuraterip_to_tsv(track));
+            },
+        }
+    }
+
+    /// Adds the extracted `files`, followed by the loudness of the album if
+    /// they were measured; in text format, they are printed only if
+    /// `verbose` is true.
+    fn files(&mut self, files: &[ExtractedFile], verbose: bool) {
+        match self.format {
+            OutputFormat::Text if verbose => for file in files {
+                println!("{}", file);
+            },
+            OutputFormat::Text => (),
+            OutputFormat::Json => {
+                let files = files.iter()
+                    .map(json::extracted_file_to_json)
+                    .collect();
+                self.members.push(("files", JsonValue::Array(files)));
+            },
+            OutputFormat::Tsv => for file in files {
+                println!("{}", tsv::extracted_file_to_tsv(file));
+            },
+        }
+
+        let tracks: Vec<NrgLoudness> = files.iter()
+            .filter_map(|file| file.loudness.clone())
+            .collect();
+        if tracks.len() < 2 {
+            return;
+        }
+        let album = NrgLoudness::album(&tracks);
+        match self.format {
+            OutputFormat::Text => println!("Album: loudness {}", album),
+            OutputFormat::Json => self.members.push(
+                ("album_loudness", json::loudness_to_json(&album))),
+            OutputFormat::Tsv => println!("{}", tsv::loudness_to_tsv(&album)),
+        }
+    }
+
+    fn verification(&mut self, verification: &NrgVerification) {
+        match self.format {
+            OutputFormat::Text => println!("{}", verification),
+            OutputFormat::Json => {
+                let verification = json::verification_to_json(verification);
+                self.members.push(("verification", verification));
+            },
+            OutputFormat::Tsv =>
+                for record in tsv::verification_to_tsv(verification) {
+                    println!("{}", record);
+                },
+        }
+    }
+
+    /// Adds the comparison of the image with the image `other_path`.
+    fn comparison(&mut self, other_path: &str, comparison: &NrgComparison) {
+        match self.format {
+            OutputFormat::Text => println!("{}", comparison),
+            OutputFormat::Json => {
+                self.members.push(("compared_with", other_path.into()));
+                let comparison = json::comparison_to_json(comparison);
+                self.members.push(("comparison", comparison));
+            },
+            OutputFormat::Tsv => {
+                println!("{}", TsvRecord::new("compared_with")
+                         .field(other_path));
+                for record in tsv::comparison_to_tsv(comparison) {
+                    println!("{}", record);
+                }
+            },
+        }
+    }
+
+    fn chunks(&mut self, chunks: &[NrgRawChunk]) {
+        match self.format {
+            OutputFormat::Text => for chunk in chunks {
+                println!("\n{}", chunk);
+            },
+            OutputFormat::Json => {
+                let chunks = chunks.iter().map(json::raw_chunk_to_json)
+                    .collect();
+                self.members.push(("chunks", JsonValue::Array(chunks)));
+            },
+            OutputFormat::Tsv => for chunk in chunks {
+                println!("{}", tsv::raw_chunk_to_tsv(chunk));
+            },
+        }
+    }
+
+    fn chunk_listing(&mut self, chunks: &[NrgRawChunk]) {
+        match self.format {
+            OutputFormat::Text => {
+                print!("\n{}", text::chunk_listing_to_text(chunks, self.color));
+            },
+            OutputFormat::Json => {
+                let chunks = chunks.iter().map(json::chunk_listing_to_json)
+                    .collect();
+                self.members.push(("chunk_listing", JsonValue::Array(chunks)));
+            },
+            OutputFormat::Tsv => for chunk in chunks {
+                println!("{}", tsv::chunk_listing_to_tsv(chunk));
+            },
+        }
+    }
+
+    /// Prints the remaining results, ending with the exit status of the
+    /// image.
+    fn finish(mut self, status: i32) {
+        match self.format {
+            OutputFormat::Text => (),
+            OutputFormat::Json => {
+                self.members.push(("status", status.into()));
+                println!("{:#}", JsonValue::object(self.members));
+            },
+            OutputFormat::Tsv =>
+                println!("{}", TsvRecord::new("status").field(status)),
+        }
+    }
+}
+
+
+/// Takes `actions` on the image `img_path`, writing the output files in
+/// `out_dir` (the current directory if it is empty), and prints the results.
+///
+/// Returns the exit status.
+fn rip_image(img_path: &str, out_dir: &Path, actions: &Actions) -> i32 {
+    let mut report = Report::new(actions.output_format, img_path)
+        .color(actions.color)
+        .summary(actions.summary);
+    let status = take_actions(img_path, out_dir, actions, &mut report);
+    report.finish(status);
+    status
+}
+
+
+/// Takes `actions` on the image `img_path`, adding the results to `report`.
+///
+/// Returns the exit status.
+fn take_actions(img_path: &str, out_dir: &Path, actions: &Actions,
+                report: &mut Report) -> i32 {
+    actions.progress(&format!("NRG image path: \"{}\"", img_path));
+
+    // Open the image file
+    let mut fd = match File::open(img_path) {
+        Ok(fd) => fd,
+        Err(err) => {
+            error!("Can't open image file \"{}\": {}", img_path, err);
+            return EXIT_IO;
+        },
+    };
+
+    // List the chunks if requested, before parsing them, so that the images
+    // with unknown chunks can be inspected
+    if actions.chunks {
+        match raw::read_raw_chunks(&mut fd) {
+            Ok(chunks) => report.chunk_listing(&chunks),
+            Err(err) => {
+                error!("Error reading \"{}\": {}", img_path, err);
+                return exit_status(&err);
+            },
+        }
+        if !actions.need_metadata() {
+            return EXIT_SUCCESS;
+        }
+    }
+
+    // Read the image's metadata, or the chosen session's
+    let options = MetadataOptions::new().strict(actions.strict);
+    let metadata = match actions.session {
+        None => metadata::read_nrg_metadata_with(&mut fd, &options),
+        Some(number) => sessions::read_session_metadata(&mut fd, number)
+            .and_then(|metadata| {
+                if actions.strict {
+                    verify::check_strict(&metadata)?;
+                }
+                Ok(metadata)
+            }),
+    };
+    let mut metadata = match metadata {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            error!("Error reading \"{}\": {}", img_path, err);
+            return exit_status(&err);
+        },
+    };
+    metadata.set_cd_text_language(actions.cd_text_language);
+
+    // Look up the titles online if requested
+    if actions.musicbrainz {
+        actions.progress("\nLooking up the disc in MusicBrainz...");
+        look_up_titles(&mut metadata);
+    }
+
+    // Display metadata if requested
+    if actions.info {
+        report.metadata(&metadata);
+    }
+
+    // Look for gaps if requested
+    let mut detected_gaps = Vec::new();
+    if actions.detect_gaps {
+        actions.progress("\nDetecting gaps...");
+        detected_gaps = match gaps::detect_gaps(&mut fd, &metadata) {
+            Ok(detected_gaps) => detected_gaps,
+            Err(err) => {
+                error!("Error detecting gaps: {}", err);
+                return exit_status(&err);
+            },
+        };
+        report.gaps(&detected_gaps);
+    }
+    if !actions.cue_gaps {
+        detected_gaps.clear();
+    }
+
+    // Analyze the tracks if requested
+    if actions.analyze {
+        actions.progress("\nAnalyzing tracks...");
+        match analysis::analyze_tracks(&mut fd, &metadata) {
+            Ok(analyses) => report.analyses(&analyses),
+            Err(err) => {
+                error!("Error analyzing tracks: {}", err);
+                return exit_status(&err);
+            },
+        }
+    }
+
+    // Look for hidden data if requested
+    let mut hidden_data = None;
+    if actions.hidden {
+        actions.progress("\nLooking for hidden data...");
+        hidden_data = match hidden::detect_hidden_data(&mut fd, &metadata) {
+            Ok(hidden_data) => hidden_data,
+            Err(err) => {
+                error!("Error looking for hidden data: {}", err);
+                return exit_status(&err);
+            },
+        };
+        report.hidden_data(hidden_data.as_ref());
+    }
+
+    // Let the user choose what to extract if requested
+    let extraction_options =
+        actions.extraction_options.clone().output_dir(out_dir);
+    let (extraction_options, split_tracks) =
+        match choose_tracks(&metadata, actions, extraction_options) {
+            Ok(Some(choice)) => choice,
+            Ok(None) => {
+                actions.progress("Cancelled.");
+                return EXIT_SUCCESS;
+            },
+            Err(err) => {
+                error!("Error choosing the tracks: {}", err);
+                return exit_status(&err);
+            },
+        };
+
+    // Create the output directory if needed
+    if (actions.cue || actions.raw || actions.extract_hidden)
+        && !out_dir.as_os_str().is_empty() {
+        if let Err(err) = fs::create_dir_all(out_dir) {
+            error!("Can't create directory \"{}\": {}",
+                   out_dir.display(), err);
+            return EXIT_IO;
+        }
+    }
+
+    // Read and write the cue sheet
+    let mut written_files = Vec::new();
+    if actions.cue {
+        actions.progress("\nExtracting cue sheet...");
+        let mut cue_options = actions.cue_options.clone().output_dir(out_dir);
+        if !split_tracks {
+            // Reference the audio file as it is (or would be) extracted
+            match extraction::output_file_name(img_path, &extraction_options,
+                                               None) {
+                Ok(audio_file) => cue_options =
+                    cue_options.audio_file(Path::new(&audio_file)),
+                Err(err) => {
+                    error!("Error writing cue sheet: {}", err);
+                    return exit_status(&err);
+                },
+            }
+        }
+        match cue_sheet::write_cue_sheet(img_path, &metadata, &detected_gaps,
+                                         &cue_options) {
+            Ok(cue_path) => written_files.push(cue_path),
+            Err(err) => {
+                error!("Error writing cue sheet: {}", err);
+                return exit_status(&err);
+            },
+        }
+        actions.progress("OK!");
+    }
+
+    // Extract raw audio data
+    let mut extracted_files = Vec::new();
+    if actions.raw {
+        actions.progress("\nExtracting raw audio data...");
+        let files = match extraction::extract(&mut fd, img_path, &metadata,
+                                              &extraction_options) {
+            Ok(files) => files,
+            Err(err) => {
+                error!("Error extracting raw audio data: {}", err);
+                return exit_status(&err);
+            },
+        };
+        report.files(&files, actions.checksums || actions.loudness
+                     || actions.verify_writes);
+        written_files.extend(files.iter()
+                             .map(|file| PathBuf::from(&file.path)));
+        let corrupted = files.iter()
+            .filter(|file| file.verified == Some(false))
+            .count();
+        if corrupted > 0 {
+            error!("{} extracted file(s) don't match the image's audio data",
+                   corrupted);
+            return EXIT_IO;
+        }
+        extracted_files.extend(files.iter().cloned());
+        if actions.track_cues && !split_tracks {
+            warn!("The tracks aren't split, no per-track cue sheets written");
+        } else if actions.track_cues {
+            match cue_sheet::write_track_cue_sheets(&metadata, &files,
+                                                    actions.gap_mode,
+                                                    &actions.cue_options) {
+                Ok(cue_paths) => written_files.extend(cue_paths),
+                Err(err) => {
+                    error!("Error writing the tracks' cue sheets: {}", err);
+                    return exit_status(&err);
+                },
+            }
+        }
+        actions.progress("OK!");
+    }
+
+    // Extract the hidden data if requested
+    if let (true, Some(hidden)) = (actions.extract_hidden, &hidden_data) {
+        actions.progress("\nExtracting hidden data...");
+        let file = match extraction::extract_hidden_data(
+            &mut fd, img_path, hidden, &extraction_options) {
+            Ok(file) => file,
+            Err(err) => {
+                error!("Error extracting hidden data: {}", err);
+                return exit_status(&err);
+            },
+        };
+        report.files(std::slice::from_ref(&file), actions.checksums);
+        written_files.push(PathBuf::from(&file.path));
+        extracted_files.push(file);
+        actions.progress("OK!");
+    }
+
+    // Write the provenance report of the extracted files if requested
+    if actions.provenance && !extracted_files.is_empty() {
+        actions.progress("\nWriting provenance report...");
+        match provenance::write_provenance(out_dir, img_path, &metadata,
+                                           &extraction_options,
+                                           &extracted_files) {
+            Ok(path) => written_files.push(path),
+            Err(err) => {
+                error!("Error writing provenance report: {}", err);
+                return exit_status(&err);
+            },
+        }
+        actions.progress("OK!");
+    }
+
+    // Write the manifest of the extracted files if requested
+    if actions.manifest && !written_files.is_empty() {
+        actions.progress("\nWriting SHA-256 manifest...");
+        if let Err(err) = manifest::write_manifest(out_dir, &written_files) {
+            error!("Error writing manifest: {}", err);
+            return exit_status(&err);
+        }
+        actions.progress("OK!");
+    }
+
+    // Compute the AccurateRip checksums if requested
+    if actions.accuraterip {
+        actions.progress("\nComputing AccurateRip checksums...");
+        let mut tracks = match accuraterip::compute_checksums(&mut fd,
+                                                              &metadata) {
+            Ok(tracks) => tracks,
+            Err(err) => {
+                error!("Error computing AccurateRip checksums: {}", err);
+                return exit_status(&err);
+            },
+        };
+        look_up_accuraterip(&metadata, &mut tracks);
+        report.accuraterip(&tracks);
+    }
+
+    EXIT_SUCCESS
+}
+
+
+/// Lets the user choose the tracks to extract and the audio format, if
+/// requested in `actions`.
+///
+/// Returns the updated `options`, and whether the tracks are split; None if
+/// the user cancels.
+#[cfg(feature = "tui")]
+fn choose_tracks(metadata: &NrgMetadata, actions: &Actions,
+                 options: ExtractionOptions)
+                 -> Result<Option<(ExtractionOptions, bool)>, NrgError> {
+    if !actions.interactive {
+        return Ok(Some((options, actions.split_tracks)));
+    }
+    Ok(tui::choose_tracks(metadata)?.map(|selection| {
+        (selection.apply(options), selection.split_tracks)
+    }))
+}
+
+#[cfg(not(feature = "tui"))]
+fn choose_tracks(_metadata: &NrgMetadata, actions: &Actions,
+                 options: ExtractionOptions)
+                 -> Result<Option<(ExtractionOptions, bool)>, NrgError> {
+    Ok(Some((options, actions.split_tracks)))
+}
+
+
+/// Looks up the disc of `metadata` in the MusicBrainz database, and sets
+/// the titles of its tracks to the ones of the first release found.
+///
+/// The lookup failures are only reported as warnings. Returns the title and
+/// the artist of the release, if found.
+#[cfg(feature = "online")]
+fn look_up_titles(metadata: &mut NrgMetadata) -> Option<(String, String)> {
+    let disc_id = match NrgDiscToc::from_metadata(metadata) {
+        Some(toc) => toc.musicbrainz_disc_id(),
+        None => {
+            warn!("Can't compute the disc ID to look up the disc in \
+                   MusicBrainz");
+            return None;
+        },
+    };
+    let cache_dir = musicbrainz::default_cache_dir();
+    let releases = match musicbrainz::lookup(&disc_id, cache_dir.as_deref()) {
+        Ok(releases) => releases,
+        Err(err) => {
+            warn!("Error looking up disc {} in MusicBrainz: {}", disc_id, err);
+            return None;
+        },
+    };
+    let release = match releases.first() {
+        Some(release) => release,
+        None => {
+            warn!("Disc {} not found in MusicBrainz", disc_id);
+            return None;
+        },
+    };
+    if releases.len() > 1 {
+        info!("{} releases hold disc {}, using the first one",
+              releases.len(), disc_id);
+    }
+    info!("MusicBrainz release: {}", release);
+    release.apply(metadata);
+    Some((release.title.clone(), release.artist.clone()))
+}
+
+#[cfg(not(feature = "online"))]
+fn look_up_titles(_metadata: &mut NrgMetadata) -> Option<(String, String)> {
+    None
+}
+
+
+/// Looks up the disc of `metadata` in the AccurateRip database, and sets the
+/// confidence of `tracks`.
+///
+/// The lookup failures are only reported as warnings.
+#[cfg(feature = "online")]
+fn look_up_accuraterip(metadata: &NrgMetadata,
+                       tracks: &mut [NrgAccurateRipTrack]) {
+    let toc = match NrgDiscToc::from_metadata(metadata) {
+        Some(toc) => toc,
+        None => {
+            warn!("Can't compute the disc ID to look up the disc in \
+                   AccurateRip");
+            return;
+        },
+    };
+    let ids = NrgAccurateRipIds::from_toc(&toc);
+    match accuraterip::lookup(&ids) {
+        Ok(ref pressings) if pressings.is_empty() =>
+            warn!("Disc {} not found in AccurateRip", ids),
+        Ok(pressings) =>
+            accuraterip::apply_database(tracks, toc.first_track, &pressings),
+        Err(err) =>
+            warn!("Error looking up disc {} in AccurateRip: {}", ids, err),
+    }
+}
+
+#[cfg(not(feature = "online"))]
+fn look_up_accuraterip(_metadata: &NrgMetadata,
+                       _tracks: &mut [NrgAccurateRipTrack]) {
+}
+
+
+/// Returns the exit status corresponding to the class of `err`.
+fn exit_status(err: &NrgError) -> i32 {
+    err.kind().exit_code()
+}
@@ -0,0 +1,192 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Interactive terminal interface to choose the tracks to extract and the
+//! output format.
+
+use std::io::{self, Write};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen,
+                          LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use nrgrip::error::NrgError;
+use nrgrip::extraction::{AudioFormat, ExtractionOptions};
+use nrgrip::metadata::metadata::NrgMetadata;
+use nrgrip::metadata::track::Track;
+
+
+/// Choices made in the interface.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TuiSelection {
+    pub tracks: Vec<u8>,
+    pub format: AudioFormat,
+    pub split_tracks: bool,
+}
+
+impl TuiSelection {
+    /// Applies the choices to `options`.
+    pub fn apply(&self, options: ExtractionOptions) -> ExtractionOptions {
+        options.tracks(&self.tracks)
+            .format(self.format)
+            .split_tracks(self.split_tracks)
+    }
+}
+
+
+/// Lists the tracks of `metadata` in the terminal and lets the user toggle
+/// the ones to extract, the audio format and the splitting of the tracks.
+///
+/// All the tracks are initially selected. Returns None if the user cancels,
+/// or if the image has no tracks.
+pub fn choose_tracks(metadata: &NrgMetadata)
+                     -> Result<Option<TuiSelection>, NrgError> {
+    let tracks: Vec<Track> = metadata.tracks().collect();
+    if tracks.is_empty() {
+        return Ok(None);
+    }
+    let mut picker = Picker::new(tracks);
+
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, EnterAlternateScreen, Hide)?;
+    let result = picker.run(&mut stdout);
+    execute!(stdout, Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+
+    Ok(result?)
+}
+
+
+/// State of the interface.
+#[derive(Debug)]
+struct Picker {
+    tracks: Vec<Track>,
+    selected: Vec<bool>,
+    cursor: usize,
+    format: AudioFormat,
+    split_tracks: bool,
+}
+
+impl Picker {
+    fn new(tracks: Vec<Track>) -> Picker {
+        Picker {
+            selected: vec![true; tracks.len()],
+            tracks,
+            cursor: 0,
+            format: AudioFormat::Raw,
+            split_tracks: true,
+        }
+    }
+
+    /// Handles the key presses until the user confirms or cancels.
+    fn run<W: Write>(&mut self, out: &mut W)
+                     -> io::Result<Option<TuiSelection>> {
+        loop {
+            self.draw(out)?;
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') =>
+                    self.cursor = self.cursor.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') =>
+                    self.cursor = (self.cursor + 1).min(self.tracks.len() - 1),
+                KeyCode::Char(' ') =>
+                    self.selected[self.cursor] = !self.selected[self.cursor],
+                KeyCode::Char('a') => {
+                    let all = self.selected.iter().all(|&selected| selected);
+                    for selected in &mut self.selected {
+                        *selected = !all;
+                    }
+                },
+                KeyCode::Char('f') => self.format = next_format(self.format),
+                KeyCode::Char('s') => self.split_tracks = !self.split_tracks,
+                KeyCode::Enter if self.selected.contains(&true) =>
+                    return Ok(Some(self.selection())),
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(None),
+                _ => (),
+            }
+        }
+    }
+
+    fn selection(&self) -> TuiSelection {
+        TuiSelection {
+            tracks: self.tracks.iter().zip(&self.selected)
+                .filter(|&(_, &selected)| selected)
+                .map(|(track, _)| track.number)
+                .collect(),
+            format: self.format,
+            split_tracks: self.split_tracks,
+        }
+    }
+
+    fn draw<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        queue!(out, Clear(ClearType::All), MoveTo(0, 0),
+               Print("Select the tracks to extract:"))?;
+
+        for (i, track) in self.tracks.iter().enumerate() {
+            let line = format!(
+                "{} [{}] {:02}  {}  {}",
+                if i == self.cursor { '>' } else { ' ' },
+                if self.selected[i] { 'x' } else { ' ' },
+                track.number,
+                track.length(),
+                track.title.as_ref().map_or("", String::as_str));
+            queue!(out, MoveTo(0, i as u16 + 2), Print(line))?;
+        }
+
+        let status = format!(
+            "Format: {} | Split: {}",
+            self.format.extension(),
+            if self.split_tracks { "yes" } else { "no" });
+        let help = "Space: toggle | a: toggle all | f: format | s: split | \
+                    Enter: extract | q: cancel";
+        let bottom = self.tracks.len() as u16 + 3;
+        queue!(out, MoveTo(0, bottom), Print(status),
+               MoveTo(0, bottom + 1), Print(help))?;
+        out.flush()
+    }
+}
+
+
+/// Returns the format following `format` in the cycle of the available
+/// formats.
+fn next_format(format: AudioFormat) -> AudioFormat {
+    let formats = [
+        AudioFormat::Raw,
+        AudioFormat::Wav,
+        AudioFormat::Flac,
+        #[cfg(feature = "opus")]
+        AudioFormat::Opus,
+        #[cfg(feature = "mp3")]
+        AudioFormat::Mp3,
+        #[cfg(feature = "vorbis")]
+        AudioFormat::Vorbis,
+    ];
+    let i = formats.iter().position(|&f| f == format).unwrap_or(0);
+    formats[(i + 1) % formats.len()]
+}
@@ -0,0 +1,19 @@
+// Looks for gaps in arbitrary bytes parsed as an NRG image, which exercises
+// the track offsets read from the DAOX chunk.
+
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate nrgrip;
+
+use std::io::Cursor;
+
+use nrgrip::{gaps, metadata};
+
+fuzz_target!(|data: &[u8]| {
+    let mut fd = Cursor::new(data);
+    if let Ok(metadata) = metadata::read_nrg_metadata(&mut fd) {
+        let _ = gaps::detect_gaps(&mut fd, &metadata);
+    }
+});
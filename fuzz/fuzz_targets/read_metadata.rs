@@ -0,0 +1,26 @@
+// Parses arbitrary bytes as an NRG image, and exercises the functions
+// working on the parsed metadata.
+
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate nrgrip;
+
+use nrgrip::disc_id::NrgDiscToc;
+use nrgrip::{json, metadata};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(metadata) = metadata::read_from_slice(data) {
+        let _ = metadata.to_string();
+        let _ = metadata.warnings();
+        for track in metadata.tracks() {
+            let _ = track.to_string();
+        }
+        let _ = json::metadata_to_json(&metadata).to_string();
+        if let Some(toc) = NrgDiscToc::from_metadata(&metadata) {
+            let _ = toc.cddb_disc_id();
+            let _ = toc.musicbrainz_disc_id();
+        }
+    }
+});
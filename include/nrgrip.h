@@ -0,0 +1,77 @@
+#ifndef NRGRIP_H
+#define NRGRIP_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#include <stdint.h>
+
+// An opened NRG image.
+typedef struct NrgImage NrgImage;
+
+// Information about a track, filled by `nrgrip_get_track_info()`.
+typedef struct NrgTrackInfo {
+  // Track number.
+  uint8_t number;
+  // Mode of the track, as found in the CUEX chunk.
+  uint8_t mode;
+  // Sector size in the image file, in bytes.
+  uint16_t sector_size;
+  // Position of the track's index #1, in sectors.
+  int32_t position_sectors;
+  // Length of the pre-gap, in sectors.
+  uint64_t pregap_sectors;
+  // Length of the track (excluding the pre-gap), in sectors.
+  uint64_t sector_count;
+} NrgTrackInfo;
+
+// Opens the NRG image at `path` and reads its metadata.
+//
+// Returns NULL on error.
+//
+// # Safety
+//
+// `path` must be a valid, NUL-terminated string.
+struct NrgImage *nrgrip_open(const char *path);
+
+// Closes an image opened by `nrgrip_open()`.
+//
+// # Safety
+//
+// `image` must be NULL or a pointer returned by `nrgrip_open()`, and must
+// not be used after this call.
+void nrgrip_close(struct NrgImage *image);
+
+// Returns the number of tracks of `image`, or -1 on error.
+//
+// # Safety
+//
+// `image` must be a pointer returned by `nrgrip_open()`.
+int nrgrip_get_track_count(const struct NrgImage *image);
+
+// Fills `info` with the information about the track at `index`.
+//
+// # Safety
+//
+// `image` must be a pointer returned by `nrgrip_open()`, and `info` must
+// point to a writable `NrgTrackInfo`.
+int nrgrip_get_track_info(const struct NrgImage *image, uint32_t index, struct NrgTrackInfo *info);
+
+// Writes the raw audio data of the track at `index` (from its index #1 to
+// its end) to the file `out_path`.
+//
+// The 96-byte sub-channel is stripped from the sectors, if present.
+//
+// # Safety
+//
+// `image` must be a pointer returned by `nrgrip_open()`, and `out_path` a
+// valid, NUL-terminated string.
+int nrgrip_extract_track(struct NrgImage *image, uint32_t index, const char *out_path);
+
+// Returns the description of the last error that occurred in the calling
+// thread, or NULL if there was none.
+//
+// The returned string is valid until the next call to an nrgrip function in
+// the same thread.
+const char *nrgrip_last_error(void);
+
+#endif  /* NRGRIP_H */
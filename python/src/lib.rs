@@ -0,0 +1,212 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Python bindings to NRGrip.
+//!
+//! The `nrgrip` Python module exposes the `Nrg` class:
+//!
+//! ```python
+//! import nrgrip
+//!
+//! image = nrgrip.Nrg.open("image.nrg")
+//! for track in image.tracks:
+//!     print(track.number, track.title, track.duration)
+//! image.extract(split=True, format="wav")
+//! ```
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+
+use nrgrip::error::NrgError;
+use nrgrip::extraction::{self, AudioFormat, ExtractionOptions, GapMode};
+use nrgrip::metadata::{self, metadata::NrgMetadata, track::Track};
+
+
+/// Converts `err` into an IOError if it comes from an I/O operation, or a
+/// ValueError otherwise.
+fn to_py_err(err: NrgError) -> PyErr {
+    let cause = err.get_context().map_or(&err, |context| &context.error);
+    match *cause {
+        NrgError::Io(_) => PyIOError::new_err(err.to_string()),
+        _ => PyValueError::new_err(err.to_string()),
+    }
+}
+
+
+/// An NRG image, opened with `Nrg.open()`.
+#[pyclass(name = "Nrg")]
+#[derive(Debug)]
+struct PyNrg {
+    path: PathBuf,
+    metadata: NrgMetadata,
+}
+
+#[pymethods]
+impl PyNrg {
+    /// Opens the NRG image at `path` and reads its metadata.
+    #[staticmethod]
+    fn open(path: PathBuf) -> PyResult<PyNrg> {
+        let mut fd = File::open(&path)?;
+        let metadata = metadata::read_nrg_metadata(&mut fd)
+            .map_err(to_py_err)?;
+        Ok(PyNrg { path, metadata })
+    }
+
+    /// Version of the NRG format.
+    #[getter]
+    fn nrg_version(&self) -> u8 {
+        self.metadata.nrg_version()
+    }
+
+    /// Length of the disc in sectors, from the start of the first track to
+    /// the end of the last one.
+    #[getter]
+    fn total_sectors(&self) -> u64 {
+        self.metadata.total_sectors()
+    }
+
+    /// Duration of the disc in seconds.
+    #[getter]
+    fn total_duration(&self) -> f64 {
+        self.metadata.total_duration()
+    }
+
+    /// List of the tracks of the image.
+    #[getter]
+    fn tracks(&self) -> Vec<PyTrack> {
+        self.metadata.tracks().map(PyTrack::from).collect()
+    }
+
+    /// Warnings raised while reading the metadata.
+    #[getter]
+    fn warnings(&self) -> Vec<String> {
+        self.metadata.warnings()
+    }
+
+    /// Extracts the audio data to the current directory, and returns the
+    /// paths of the files written.
+    ///
+    /// The arguments are the same as the nrgrip command's options: `format`
+    /// is "raw", "wav" or "flac", `gap_mode` is "append", "prepend" or
+    /// "discard", `offset` is in samples, `name` is a file name template,
+    /// and `tracks` is a list of track numbers to extract.
+    #[pyo3(signature = (split=false, format="raw", gap_mode="append",
+                        offset=0, name=None, strip_subchannel=true,
+                        tracks=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn extract(&self, split: bool, format: &str, gap_mode: &str, offset: i32,
+               name: Option<&str>, strip_subchannel: bool,
+               tracks: Option<Vec<u8>>)
+               -> PyResult<Vec<String>> {
+        let format = match format {
+            "raw" => AudioFormat::Raw,
+            "wav" => AudioFormat::Wav,
+            "flac" => AudioFormat::Flac,
+            _ => return Err(PyValueError::new_err(format!(
+                "Unknown audio format \"{}\"", format))),
+        };
+        let gap_mode = match gap_mode {
+            "append" => GapMode::Append,
+            "prepend" => GapMode::Prepend,
+            "discard" => GapMode::Discard,
+            _ => return Err(PyValueError::new_err(format!(
+                "Unknown gap mode \"{}\"", gap_mode))),
+        };
+
+        let mut options = ExtractionOptions::new()
+            .strip_subchannel(strip_subchannel)
+            .split_tracks(split)
+            .format(format)
+            .gap_mode(gap_mode)
+            .offset(offset);
+        if let Some(name) = name {
+            options = options.name_template(name);
+        }
+        if let Some(tracks) = tracks {
+            options = options.tracks(&tracks);
+        }
+
+        let mut fd = File::open(&self.path)?;
+        let files = extraction::extract(&mut fd, &self.path.to_string_lossy(),
+                                        &self.metadata, &options)
+            .map_err(to_py_err)?;
+        Ok(files.into_iter().map(|file| file.path).collect())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Nrg({:?})", self.path)
+    }
+}
+
+
+/// A track of an NRG image.
+#[pyclass(name = "Track", get_all, skip_from_py_object)]
+#[derive(Clone, Debug)]
+struct PyTrack {
+    number: u8,
+    mode: u8,
+    isrc: Option<String>,
+    title: Option<String>,
+    pregap_sectors: u64,
+    sector_count: u64,
+    byte_count: u64,
+    duration: f64,
+}
+
+impl From<Track> for PyTrack {
+    fn from(track: Track) -> PyTrack {
+        PyTrack {
+            number: track.number,
+            mode: track.mode,
+            pregap_sectors: track.pregap_sectors(),
+            sector_count: track.sector_count(),
+            byte_count: track.byte_count(),
+            duration: track.duration(),
+            isrc: track.isrc,
+            title: track.title,
+        }
+    }
+}
+
+#[pymethods]
+impl PyTrack {
+    fn __repr__(&self) -> String {
+        let title = match self.title {
+            Some(ref title) => format!("{:?}", title),
+            None => "None".to_string(),
+        };
+        format!("Track(number={}, title={}, sector_count={})",
+                self.number, title, self.sector_count)
+    }
+}
+
+
+#[pymodule]
+#[pyo3(name = "nrgrip")]
+fn nrgrip_python(module: &Bound<PyModule>) -> PyResult<()> {
+    module.add_class::<PyNrg>()?;
+    module.add_class::<PyTrack>()?;
+    Ok(())
+}
@@ -0,0 +1,317 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to compute the AccurateRip checksums of the audio tracks, and to
+//! compare them with the ones submitted to the AccurateRip database.
+//!
+//! A track whose checksum matches the ones of other rips of the same disc is
+//! very likely to be a bit-perfect copy; the number of matching submissions
+//! is its confidence. The database is only queried with the `online`
+//! feature.
+
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+use ::disc_id::NrgDiscToc;
+use ::error::NrgError;
+use ::metadata::metadata::NrgMetadata;
+use ::raw_audio::{RAW_SEC_SIZE, RAW96_SEC_SIZE, SAMPLE_SIZE};
+
+/// URL of the AccurateRip database.
+#[cfg(feature = "online")]
+const DATABASE_URL: &str = "http://www.accuraterip.com/accuraterip/";
+
+/// Maximum number of sectors read at once while computing the checksums.
+const SCAN_SECTORS: u64 = 75;
+
+/// Number of samples in a sector.
+const SECTOR_SAMPLES: u64 = 588;
+
+/// Number of samples left out at the start of the first track and at the end
+/// of the last one, which most drives can't read.
+const SKIPPED_SAMPLES: u64 = 5 * SECTOR_SAMPLES;
+
+/// Offset of the first sector of the program area, in sectors.
+const LEAD_IN_SECTORS: u32 = 150;
+
+/// Size of the header of a pressing in the database responses, and of each
+/// of its tracks.
+const PRESSING_HEADER_SIZE: usize = 13;
+const PRESSING_TRACK_SIZE: usize = 9;
+
+
+/// The identifiers of a disc in the AccurateRip database.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgAccurateRipIds {
+    pub track_count: u8,
+    pub id1: u32,
+    pub id2: u32,
+    pub cddb_id: u32,
+}
+
+impl NrgAccurateRipIds {
+    /// Computes the identifiers of the disc whose table of contents is
+    /// `toc`.
+    pub fn from_toc(toc: &NrgDiscToc) -> NrgAccurateRipIds {
+        let mut id1 = 0u32;
+        let mut id2 = 0u32;
+        let offsets = toc.offsets.iter().chain(Some(&toc.lead_out));
+        for (i, &offset) in offsets.enumerate() {
+            let lba = offset.saturating_sub(LEAD_IN_SECTORS);
+            id1 = id1.wrapping_add(lba);
+            id2 = id2.wrapping_add(lba.max(1).wrapping_mul(i as u32 + 1));
+        }
+        NrgAccurateRipIds {
+            track_count: toc.offsets.len() as u8,
+            id1,
+            id2,
+            cddb_id: toc.cddb_disc_id(),
+        }
+    }
+
+    /// Returns the URL of the disc in the AccurateRip database.
+    #[cfg(feature = "online")]
+    pub fn url(&self) -> String {
+        format!("{}{:x}/{:x}/{:x}/{}.bin", DATABASE_URL, self.id1 & 0xF,
+                self.id1 >> 4 & 0xF, self.id1 >> 8 & 0xF, self)
+    }
+}
+
+impl fmt::Display for NrgAccurateRipIds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "dBAR-{:03}-{:08x}-{:08x}-{:08x}", self.track_count,
+               self.id1, self.id2, self.cddb_id)
+    }
+}
+
+
+/// The AccurateRip checksums of an audio track.
+///
+/// `confidence` is the number of submissions to the database matching
+/// either checksum: None if the database wasn't queried, or doesn't know
+/// the disc.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgAccurateRipTrack {
+    pub track_number: u8,
+    pub crc_v1: u32,
+    pub crc_v2: u32,
+    pub confidence: Option<u32>,
+}
+
+impl NrgAccurateRipTrack {
+    /// Returns true if the track matches submissions to the database.
+    pub fn is_accurate(&self) -> bool {
+        self.confidence.is_some_and(|confidence| confidence > 0)
+    }
+}
+
+impl fmt::Display for NrgAccurateRipTrack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Track {:02}: AccurateRip v1 {:08X}, v2 {:08X}",
+               self.track_number, self.crc_v1, self.crc_v2)?;
+        match self.confidence {
+            None => Ok(()),
+            Some(0) => write!(f, ", no matching rip in the database"),
+            Some(confidence) =>
+                write!(f, ", accurately ripped (confidence {})", confidence),
+        }
+    }
+}
+
+
+/// A pressing of a disc in the AccurateRip database, with the checksums and
+/// the confidence of each of its tracks.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NrgAccurateRipPressing {
+    pub tracks: Vec<NrgAccurateRipEntry>,
+}
+
+/// The checksum of a track of an `NrgAccurateRipPressing`, and the number
+/// of submissions holding it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgAccurateRipEntry {
+    pub confidence: u8,
+    pub crc: u32,
+}
+
+
+/// Computes the AccurateRip checksums of the audio tracks of the NRG image
+/// `in_fd`.
+///
+/// - `in_fd` is the handler to the NRG image file.
+/// - `metadata` is the metadata extracted from `in_fd` by nrgrip::metadata.
+///
+/// Each audio track is read from its index #1 to its end; the data tracks
+/// are skipped. The first five sectors of the first track and the last five
+/// sectors of the last track are left out, as AccurateRip does.
+pub fn compute_checksums<R: Read + Seek>(in_fd: &mut R,
+                                         metadata: &NrgMetadata)
+                                         -> Result<Vec<NrgAccurateRipTrack>,
+                                                   NrgError> {
+    let sector_size = metadata.sector_size();
+    if sector_size != RAW_SEC_SIZE && sector_size != RAW96_SEC_SIZE {
+        return Err(NrgError::NrgFormat(format!(
+            "Can't compute the checksums of {}-byte sectors", sector_size)));
+    }
+
+    let tracks: Vec<_> = metadata.tracks()
+        .filter(|track| !track.is_data())
+        .collect();
+    let mut checksums = Vec::new();
+    for (i, track) in tracks.iter().enumerate() {
+        let checksum = checksum_track(in_fd, track.number, track.index1,
+                                      track.track_end, sector_size, i == 0,
+                                      i + 1 == tracks.len())?;
+        debug!("{}", checksum);
+        checksums.push(checksum);
+    }
+    Ok(checksums)
+}
+
+
+/// Computes the checksums of the sectors from the byte `start` to the byte
+/// `end`.
+fn checksum_track<R: Read + Seek>(in_fd: &mut R, track_number: u8,
+                                  start: u64, end: u64, sector_size: u16,
+                                  first: bool, last: bool)
+                                  -> Result<NrgAccurateRipTrack, NrgError> {
+    let sector_size = u64::from(sector_size);
+    let samples = end.saturating_sub(start) / sector_size * SECTOR_SAMPLES;
+    let check_from = if first { SKIPPED_SAMPLES } else { 1 };
+    let check_to = if last {
+        samples.saturating_sub(SKIPPED_SAMPLES)
+    } else {
+        samples
+    };
+
+    let mut buf = Vec::new();
+    let mut crc_v1 = 0u32;
+    let mut crc_v2 = 0u32;
+    let mut position = 1u64;
+    let mut pos = start;
+
+    in_fd.seek(SeekFrom::Start(start))?;
+    while pos + sector_size <= end {
+        let nb_sectors = ((end - pos) / sector_size).min(SCAN_SECTORS);
+        buf.resize((nb_sectors * sector_size) as usize, 0);
+        in_fd.read_exact(&mut buf)?;
+        pos += nb_sectors * sector_size;
+
+        // Ignore the sub-channel data
+        for sector in buf.chunks(sector_size as usize) {
+            for sample in sector[..RAW_SEC_SIZE as usize]
+                .chunks(SAMPLE_SIZE) {
+                if position >= check_from && position <= check_to {
+                    let value = u32::from_le_bytes([sample[0], sample[1],
+                                                    sample[2], sample[3]]);
+                    let product = u64::from(value) * position;
+                    crc_v1 = crc_v1.wrapping_add(product as u32);
+                    crc_v2 = crc_v2.wrapping_add(product as u32)
+                        .wrapping_add((product >> 32) as u32);
+                }
+                position += 1;
+            }
+        }
+    }
+
+    Ok(NrgAccurateRipTrack {
+        track_number,
+        crc_v1,
+        crc_v2,
+        confidence: None,
+    })
+}
+
+
+/// Parses a response of the AccurateRip database, made of one or more
+/// pressings of the disc.
+pub fn parse_database(data: &[u8])
+                      -> Result<Vec<NrgAccurateRipPressing>, NrgError> {
+    let mut pressings = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        if rest.len() < PRESSING_HEADER_SIZE {
+            return Err(truncated_response());
+        }
+        let track_count = usize::from(rest[0]);
+        let size = PRESSING_HEADER_SIZE + track_count * PRESSING_TRACK_SIZE;
+        if rest.len() < size {
+            return Err(truncated_response());
+        }
+        let tracks = rest[PRESSING_HEADER_SIZE..size]
+            .chunks(PRESSING_TRACK_SIZE)
+            .map(|track| NrgAccurateRipEntry {
+                confidence: track[0],
+                crc: u32::from_le_bytes([track[1], track[2], track[3],
+                                         track[4]]),
+            })
+            .collect();
+        pressings.push(NrgAccurateRipPressing { tracks });
+        rest = &rest[size..];
+    }
+    Ok(pressings)
+}
+
+
+fn truncated_response() -> NrgError {
+    NrgError::Online("truncated AccurateRip response".to_string())
+}
+
+
+/// Sets the confidence of `tracks` to the sum of the confidences of the
+/// entries of `pressings` matching their checksums.
+///
+/// The tracks are matched with the entries by their position on the disc,
+/// whose first track is `first_track`.
+pub fn apply_database(tracks: &mut [NrgAccurateRipTrack], first_track: u8,
+                      pressings: &[NrgAccurateRipPressing]) {
+    for track in tracks {
+        let index = usize::from(track.track_number.saturating_sub(first_track));
+        let confidence = pressings.iter()
+            .filter_map(|pressing| pressing.tracks.get(index))
+            .filter(|entry| entry.crc == track.crc_v1
+                    || entry.crc == track.crc_v2)
+            .map(|entry| u32::from(entry.confidence))
+            .sum();
+        track.confidence = Some(confidence);
+    }
+}
+
+
+/// Looks up the disc `ids` in the AccurateRip database.
+///
+/// Returns its pressings; the discs absent from the database yield an empty
+/// list.
+#[cfg(feature = "online")]
+pub fn lookup(ids: &NrgAccurateRipIds)
+              -> Result<Vec<NrgAccurateRipPressing>, NrgError> {
+    let url = ids.url();
+    info!("Looking up {}", url);
+    let response = match ureq::get(&url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(404, _)) => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut data = Vec::new();
+    response.into_reader().read_to_end(&mut data)?;
+    parse_database(&data)
+}
@@ -0,0 +1,186 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to analyze the audio content of the tracks: the digital silence
+//! at their edges, the duration of the audio in between, and the levels.
+
+use std::fmt;
+use std::io::{Read, Seek};
+
+use ::error::NrgError;
+use ::metadata::metadata::NrgMetadata;
+use ::raw_audio::{RAW_SEC_SIZE, RAW96_SEC_SIZE, SAMPLE_RATE};
+use ::samples::NrgSamples;
+
+/// Full scale of the 16-bit samples, and level from which they are
+/// considered clipped.
+const FULL_SCALE: f64 = 32_768.0;
+const CLIP_LEVEL: u32 = 32_767;
+
+
+/// Analysis of the audio content of a track, from its index #1 to its end.
+///
+/// The lengths are in samples; a sample is silent if both of its channels
+/// are null. `peak` is the largest absolute value of the channels' samples,
+/// `rms` the root mean square of all of them, and `clipped_samples` the
+/// number of channel samples at full scale.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NrgTrackAnalysis {
+    pub track_number: u8,
+    pub samples: u64,
+    pub leading_silence: u64,
+    pub trailing_silence: u64,
+    pub peak: u32,
+    pub rms: f64,
+    pub clipped_samples: u64,
+}
+
+impl NrgTrackAnalysis {
+    /// Returns true if the track is entirely silent.
+    pub fn is_silent(&self) -> bool {
+        self.leading_silence == self.samples
+    }
+
+    /// Returns the number of samples between the leading and the trailing
+    /// silence.
+    pub fn audio_samples(&self) -> u64 {
+        self.samples - self.leading_silence - self.trailing_silence
+    }
+
+    /// Returns the peak level, in dBFS (minus infinity if silent).
+    pub fn peak_dbfs(&self) -> f64 {
+        20.0 * (f64::from(self.peak) / FULL_SCALE).log10()
+    }
+
+    /// Returns the RMS level, in dBFS (minus infinity if silent).
+    pub fn rms_dbfs(&self) -> f64 {
+        20.0 * (self.rms / FULL_SCALE).log10()
+    }
+}
+
+impl fmt::Display for NrgTrackAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Track {:02}: ", self.track_number)?;
+        if self.is_silent() {
+            return write!(f, "silent ({})", Duration(self.samples));
+        }
+        write!(f, "{} of audio, {} of silence at the start, {} at the end, \
+                   peak {:.1} dBFS, RMS {:.1} dBFS",
+               Duration(self.audio_samples()),
+               Duration(self.leading_silence),
+               Duration(self.trailing_silence),
+               self.peak_dbfs(), self.rms_dbfs())?;
+        if self.clipped_samples > 0 {
+            write!(f, ", {} clipped samples", self.clipped_samples)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Number of samples, displayed as minutes, seconds and hundredths.
+struct Duration(u64);
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hundredths = self.0 * 100 / u64::from(SAMPLE_RATE);
+        write!(f, "{}:{:02}.{:02}", hundredths / 6000, hundredths / 100 % 60,
+               hundredths % 100)
+    }
+}
+
+
+/// Analyzes the audio tracks of the NRG image `in_fd`.
+///
+/// - `in_fd` is the handler to the NRG image file.
+/// - `metadata` is the metadata extracted from `in_fd` by nrgrip::metadata.
+///
+/// Each audio track is read from its index #1 to its end; the data tracks
+/// are skipped.
+pub fn analyze_tracks<R: Read + Seek>(in_fd: &mut R, metadata: &NrgMetadata)
+                                      -> Result<Vec<NrgTrackAnalysis>,
+                                                NrgError> {
+    let sector_size = metadata.sector_size();
+    if sector_size != RAW_SEC_SIZE && sector_size != RAW96_SEC_SIZE {
+        return Err(NrgError::NrgFormat(format!(
+            "Can't analyze the audio in {}-byte sectors", sector_size)));
+    }
+
+    let mut analyses = Vec::new();
+    for track in metadata.tracks().filter(|track| !track.is_data()) {
+        let analysis = analyze_track(in_fd, track.number, track.index1,
+                                     track.track_end, sector_size)?;
+        debug!("{}", analysis);
+        analyses.push(analysis);
+    }
+    Ok(analyses)
+}
+
+
+/// Analyzes the audio of the sectors from the byte `start` to the byte
+/// `end`.
+fn analyze_track<R: Read + Seek>(in_fd: &mut R, track_number: u8,
+                                 start: u64, end: u64, sector_size: u16)
+                                 -> Result<NrgTrackAnalysis, NrgError> {
+    let mut samples = 0;
+    let mut first_sound = None;
+    let mut last_sound = 0;
+    let mut peak = 0;
+    let mut sum_squares = 0f64;
+    let mut clipped_samples = 0;
+
+    for sample in NrgSamples::new(in_fd, start, end, sector_size)? {
+        let sample = sample?;
+        if sample != [0, 0] {
+            first_sound.get_or_insert(samples);
+            last_sound = samples + 1;
+        }
+        for &value in &sample {
+            let level = u32::from(value.unsigned_abs());
+            peak = peak.max(level);
+            sum_squares += f64::from(level).powi(2);
+            if level >= CLIP_LEVEL {
+                clipped_samples += 1;
+            }
+        }
+        samples += 1;
+    }
+
+    let (leading_silence, trailing_silence) = match first_sound {
+        None => (samples, 0),
+        Some(first_sound) => (first_sound, samples - last_sound),
+    };
+    let rms = if samples == 0 {
+        0.0
+    } else {
+        (sum_squares / (2 * samples) as f64).sqrt()
+    };
+    Ok(NrgTrackAnalysis {
+        track_number,
+        samples,
+        leading_silence,
+        trailing_silence,
+        peak,
+        rms,
+        clipped_samples,
+    })
+}
@@ -0,0 +1,204 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Asynchronous variants of the metadata reading and audio extraction
+//! functions, for use within a tokio runtime.
+//!
+//! The parsing and extraction code is synchronous; the functions of this
+//! module run it on tokio's blocking thread pool, so that the runtime's
+//! worker threads are never blocked. Like tokio::task::spawn_blocking(),
+//! they panic if called outside of a tokio runtime.
+
+use std::cmp;
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio;
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::task::JoinHandle;
+
+use ::error::NrgError;
+use ::extraction::{self, ExtractedFile, ExtractionOptions};
+use ::metadata::{self, metadata::NrgMetadata, track::Track};
+use ::raw_audio::{RAW_SEC_SIZE, RAW96_SEC_SIZE};
+
+
+/// Future resolving to the result of a function run on tokio's blocking
+/// thread pool.
+#[derive(Debug)]
+pub struct BlockingTask<T> {
+    handle: JoinHandle<Result<T, NrgError>>,
+}
+
+impl<T> BlockingTask<T> where T: Send + 'static {
+    fn spawn<F>(task: F) -> BlockingTask<T>
+        where F: FnOnce() -> Result<T, NrgError> + Send + 'static {
+        BlockingTask { handle: tokio::task::spawn_blocking(task) }
+    }
+}
+
+impl<T> Future for BlockingTask<T> {
+    type Output = Result<T, NrgError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match Pin::new(&mut self.handle).poll(cx) {
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(io::Error::other(err)
+                                                     .into())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+
+/// Reads the metadata of the NRG image at `img_path`.
+///
+/// This is the asynchronous variant of nrgrip::metadata::read_nrg_metadata().
+pub fn read_nrg_metadata_async(img_path: PathBuf)
+                               -> BlockingTask<NrgMetadata> {
+    BlockingTask::spawn(move || {
+        let mut fd = File::open(&img_path)?;
+        metadata::read_nrg_metadata(&mut fd)
+    })
+}
+
+
+/// Extracts the audio data from the NRG image at `img_path`.
+///
+/// This is the asynchronous variant of nrgrip::extraction::extract(); the
+/// image's metadata is read first.
+pub fn extract_async(img_path: PathBuf, options: ExtractionOptions)
+                     -> BlockingTask<Vec<ExtractedFile>> {
+    BlockingTask::spawn(move || {
+        let mut fd = File::open(&img_path)?;
+        let metadata = metadata::read_nrg_metadata(&mut fd)?;
+        extraction::extract(&mut fd, &img_path.to_string_lossy(), &metadata,
+                            &options)
+    })
+}
+
+
+/// Opens a stream reading the audio data of `track` (from its index #1 to its
+/// end) in the NRG image at `img_path`.
+///
+/// If `strip_subchannel` is true, the 96-byte sub-channel is left out of the
+/// stream, if present.
+pub fn open_track_stream(img_path: PathBuf, track: Track,
+                         strip_subchannel: bool)
+                         -> BlockingTask<TrackStream> {
+    BlockingTask::spawn(move || {
+        let mut fd = File::open(&img_path)?;
+        fd.seek(SeekFrom::Start(track.index1))?;
+        TrackStream::new(tokio::fs::File::from_std(fd), &track,
+                         strip_subchannel)
+    })
+}
+
+
+/// Stream of the audio data of a track, implementing `AsyncRead`.
+///
+/// The image file is read one sector at a time.
+#[derive(Debug)]
+pub struct TrackStream {
+    fd: tokio::fs::File,
+    sector: Vec<u8>,
+    audio_size: usize,
+    filled: usize,
+    served: usize,
+    sectors_left: u64,
+}
+
+impl TrackStream {
+    fn new(fd: tokio::fs::File, track: &Track, strip_subchannel: bool)
+           -> Result<TrackStream, NrgError> {
+        let sector_size = track.sector_size as usize;
+        if sector_size == 0 {
+            return Err(NrgError::NrgFormat(format!(
+                "Unknown sector size for track {}", track.number)));
+        }
+        let audio_size =
+            if strip_subchannel && track.sector_size == RAW96_SEC_SIZE {
+                RAW_SEC_SIZE as usize
+            } else {
+                sector_size
+            };
+
+        Ok(TrackStream {
+            fd,
+            sector: vec![0u8; sector_size],
+            audio_size,
+            // The current (non-existent) sector is fully read and served
+            filled: sector_size,
+            served: audio_size,
+            sectors_left: track.sector_count(),
+        })
+    }
+}
+
+impl AsyncRead for TrackStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf)
+                 -> Poll<io::Result<()>> {
+        let stream = self.get_mut();
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        loop {
+            let sector_full = stream.filled == stream.sector.len();
+
+            // Serve the audio data of the current sector
+            if sector_full && stream.served < stream.audio_size {
+                let end = cmp::min(stream.audio_size,
+                                   stream.served + buf.remaining());
+                buf.put_slice(&stream.sector[stream.served..end]);
+                stream.served = end;
+                return Poll::Ready(Ok(()));
+            }
+
+            // Move on to the next sector
+            if sector_full {
+                if stream.sectors_left == 0 {
+                    return Poll::Ready(Ok(()));
+                }
+                stream.sectors_left -= 1;
+                stream.filled = 0;
+                stream.served = 0;
+            }
+
+            let mut sector_buf =
+                ReadBuf::new(&mut stream.sector[stream.filled..]);
+            match Pin::new(&mut stream.fd).poll_read(cx, &mut sector_buf) {
+                Poll::Ready(Ok(())) => (),
+                other => return other,
+            }
+            let nbytes = sector_buf.filled().len();
+            if nbytes == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            stream.filled += nbytes;
+        }
+    }
+}
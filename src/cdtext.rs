@@ -0,0 +1,640 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! CD-Text information, as stored in the CDTX chunk of NRG images.
+//!
+//! CD-Text is made of 18-byte packs: a pack type, a track number, a
+//! sequence number, the position of the pack's first character in its
+//! string, 12 bytes of text and a CRC. The strings of a given type (e.g. the
+//! titles) are stored one after the other, null-terminated, starting with
+//! the disc's one; the last packs give the number of packs of each type.
+//! The genre packs only describe the disc: a genre code followed by a
+//! string.
+//!
+//! A disc can hold up to 8 blocks of packs, each in its own language; the
+//! size information of a block lists the languages of all of them.
+//! `parse_packs()` reads all the blocks, while `to_packs()` writes a single
+//! English block in ISO 8859-1 characters.
+
+use std::collections::BTreeMap;
+
+use ::error::NrgError;
+use ::writer::split_cue_line;
+
+
+/// Size of a CD-Text pack.
+pub const PACK_SIZE: usize = 18;
+
+/// Size of the text held by a pack.
+const PACK_TEXT_SIZE: usize = 12;
+
+/// Pack type of the titles.
+const PACK_TITLE: u8 = 0x80;
+/// Pack type of the performers.
+const PACK_PERFORMER: u8 = 0x81;
+/// Pack type of the songwriters.
+const PACK_SONGWRITER: u8 = 0x82;
+/// Pack type of the composers.
+const PACK_COMPOSER: u8 = 0x83;
+/// Pack type of the arrangers.
+const PACK_ARRANGER: u8 = 0x84;
+/// Pack type of the messages.
+const PACK_MESSAGE: u8 = 0x85;
+/// Pack type of the genre.
+const PACK_GENRE: u8 = 0x87;
+/// Pack type of the UPC/EAN of the disc and the ISRCs of the tracks.
+const PACK_UPC_ISRC: u8 = 0x8E;
+/// Pack type of the size information.
+const PACK_SIZE_INFO: u8 = 0x8F;
+
+/// Flag of the packs holding double-byte characters.
+const PACK_DBCC: u8 = 0x80;
+/// Character code of the double-byte blocks (Shift-JIS).
+const CHARSET_MS_JIS: u8 = 0x80;
+
+/// Pack types of the strings describing the disc and each track, in order.
+const TEXT_PACK_TYPES: [u8; 7] = [
+    PACK_TITLE, PACK_PERFORMER, PACK_SONGWRITER, PACK_COMPOSER,
+    PACK_ARRANGER, PACK_MESSAGE, PACK_UPC_ISRC,
+];
+
+/// Language code of English, used for the first block.
+const LANGUAGE_ENGLISH: u8 = 0x09;
+
+
+/// Names of the language codes, as defined by EBU Tech 3264.
+const LANGUAGES: [(u8, &str); 47] = [
+    (0x00, "Unknown"), (0x01, "Albanian"), (0x02, "Breton"),
+    (0x03, "Catalan"), (0x04, "Croatian"), (0x05, "Welsh"),
+    (0x06, "Czech"), (0x07, "Danish"), (0x08, "German"),
+    (0x09, "English"), (0x0A, "Spanish"), (0x0B, "Esperanto"),
+    (0x0C, "Estonian"), (0x0D, "Basque"), (0x0E, "Faroese"),
+    (0x0F, "French"), (0x10, "Frisian"), (0x11, "Irish"),
+    (0x12, "Gaelic"), (0x13, "Galician"), (0x14, "Icelandic"),
+    (0x15, "Italian"), (0x16, "Lappish"), (0x17, "Latin"),
+    (0x18, "Latvian"), (0x19, "Luxembourgian"), (0x1A, "Lithuanian"),
+    (0x1B, "Hungarian"), (0x1C, "Maltese"), (0x1D, "Dutch"),
+    (0x1E, "Norwegian"), (0x1F, "Occitan"), (0x20, "Polish"),
+    (0x21, "Portuguese"), (0x22, "Romanian"), (0x23, "Romansh"),
+    (0x24, "Serbian"), (0x25, "Slovak"), (0x26, "Slovenian"),
+    (0x27, "Finnish"), (0x28, "Swedish"), (0x29, "Turkish"),
+    (0x2A, "Flemish"), (0x2B, "Walloon"), (0x56, "Russian"),
+    (0x69, "Japanese"), (0x75, "Chinese"),
+];
+
+/// Names of the genre codes.
+const GENRES: [&str; 28] = [
+    "", "Not Defined", "Adult Contemporary", "Alternative Rock",
+    "Children's Music", "Classical", "Contemporary Christian", "Country",
+    "Dance", "Easy Listening", "Erotic", "Folk", "Gospel", "Hip Hop",
+    "Jazz", "Latin", "Musical", "New Age", "Opera", "Operetta",
+    "Pop Music", "Rap", "Reggae", "Rock Music", "Rhythm & Blues",
+    "Sound Effects", "Spoken Word", "World Music",
+];
+
+
+/// CD-Text information of a disc and its tracks.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NrgCdText {
+    /// Title of the disc.
+    pub title: String,
+    /// Performer of the disc.
+    pub performer: String,
+    /// Songwriter of the disc.
+    pub songwriter: String,
+    /// Composer of the disc.
+    pub composer: String,
+    /// Arranger of the disc.
+    pub arranger: String,
+    /// Message from the content provider or the artist.
+    pub message: String,
+    /// Genre code of the disc (0 if unused).
+    pub genre_code: u16,
+    /// Genre of the disc, supplementing the code.
+    pub genre: String,
+    /// UPC/EAN of the disc.
+    pub upc: String,
+    /// Information of the tracks, in order.
+    pub tracks: Vec<NrgCdTextTrack>,
+}
+
+/// CD-Text information of a track.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NrgCdTextTrack {
+    /// Title of the track.
+    pub title: String,
+    /// Performer of the track.
+    pub performer: String,
+    /// Songwriter of the track.
+    pub songwriter: String,
+    /// Composer of the track.
+    pub composer: String,
+    /// Arranger of the track.
+    pub arranger: String,
+    /// Message about the track.
+    pub message: String,
+    /// ISRC of the track.
+    pub isrc: String,
+}
+
+impl NrgCdTextTrack {
+    /// Returns the string of the pack type `pack_type`.
+    fn field(&self, pack_type: u8) -> Option<&String> {
+        match pack_type {
+            PACK_TITLE => Some(&self.title),
+            PACK_PERFORMER => Some(&self.performer),
+            PACK_SONGWRITER => Some(&self.songwriter),
+            PACK_COMPOSER => Some(&self.composer),
+            PACK_ARRANGER => Some(&self.arranger),
+            PACK_MESSAGE => Some(&self.message),
+            PACK_UPC_ISRC => Some(&self.isrc),
+            _ => None,
+        }
+    }
+
+    fn field_mut(&mut self, pack_type: u8) -> Option<&mut String> {
+        match pack_type {
+            PACK_TITLE => Some(&mut self.title),
+            PACK_PERFORMER => Some(&mut self.performer),
+            PACK_SONGWRITER => Some(&mut self.songwriter),
+            PACK_COMPOSER => Some(&mut self.composer),
+            PACK_ARRANGER => Some(&mut self.arranger),
+            PACK_MESSAGE => Some(&mut self.message),
+            PACK_UPC_ISRC => Some(&mut self.isrc),
+            _ => None,
+        }
+    }
+}
+
+impl NrgCdText {
+    pub fn new() -> NrgCdText {
+        NrgCdText::default()
+    }
+
+    /// Reads the CD-Text information of the cue sheet `text`: the TITLE,
+    /// PERFORMER and SONGWRITER commands found before the first TRACK
+    /// command describe the disc, the following ones the current track. The
+    /// CATALOG command gives the UPC/EAN, and the ISRC commands the tracks'
+    /// ISRCs. The other commands are ignored.
+    pub fn from_cue_sheet(text: &str) -> NrgCdText {
+        let mut cd_text = NrgCdText::new();
+        for line in text.lines() {
+            let words = split_cue_line(line);
+            let command = words.first().map(|word| word.to_uppercase());
+            let value = match words.get(1) {
+                Some(value) => value.clone(),
+                None => continue,
+            };
+            let track = cd_text.tracks.last_mut();
+            match (command.as_deref(), track) {
+                (Some("TRACK"), _) =>
+                    cd_text.tracks.push(NrgCdTextTrack::default()),
+                (Some("TITLE"), Some(track)) => track.title = value,
+                (Some("TITLE"), None) => cd_text.title = value,
+                (Some("PERFORMER"), Some(track)) => track.performer = value,
+                (Some("PERFORMER"), None) => cd_text.performer = value,
+                (Some("SONGWRITER"), Some(track)) => track.songwriter = value,
+                (Some("SONGWRITER"), None) => cd_text.songwriter = value,
+                (Some("ISRC"), Some(track)) => track.isrc = value,
+                (Some("CATALOG"), _) => cd_text.upc = value,
+                _ => {},
+            }
+        }
+        cd_text
+    }
+
+    /// Tells whether all the strings are empty and the genre code is
+    /// unused.
+    pub fn is_empty(&self) -> bool {
+        self.genre_code == 0 && self.genre.is_empty()
+            && TEXT_PACK_TYPES.iter().all(|&pack_type| {
+                self.field(pack_type).is_none_or(|field| field.is_empty())
+                    && self.tracks.iter().all(|track| {
+                        track.field(pack_type)
+                            .is_none_or(|field| field.is_empty())
+                    })
+            })
+    }
+
+    /// Returns the genre of the disc: its genre string, or else the name of
+    /// its genre code, if any.
+    pub fn genre_name(&self) -> Option<String> {
+        if !self.genre.is_empty() {
+            return Some(self.genre.clone());
+        }
+        GENRES.get(usize::from(self.genre_code))
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string())
+    }
+
+    /// Returns the disc's string of the pack type `pack_type`.
+    fn field(&self, pack_type: u8) -> Option<&String> {
+        match pack_type {
+            PACK_TITLE => Some(&self.title),
+            PACK_PERFORMER => Some(&self.performer),
+            PACK_SONGWRITER => Some(&self.songwriter),
+            PACK_COMPOSER => Some(&self.composer),
+            PACK_ARRANGER => Some(&self.arranger),
+            PACK_MESSAGE => Some(&self.message),
+            PACK_UPC_ISRC => Some(&self.upc),
+            _ => None,
+        }
+    }
+
+    fn field_mut(&mut self, pack_type: u8) -> Option<&mut String> {
+        match pack_type {
+            PACK_TITLE => Some(&mut self.title),
+            PACK_PERFORMER => Some(&mut self.performer),
+            PACK_SONGWRITER => Some(&mut self.songwriter),
+            PACK_COMPOSER => Some(&mut self.composer),
+            PACK_ARRANGER => Some(&mut self.arranger),
+            PACK_MESSAGE => Some(&mut self.message),
+            PACK_UPC_ISRC => Some(&mut self.upc),
+            _ => None,
+        }
+    }
+
+    /// Encodes this information as CD-Text packs, the tracks being numbered
+    /// from `first_track`.
+    ///
+    /// The types whose strings are all empty are omitted. Characters
+    /// outside ISO 8859-1 are replaced with question marks.
+    pub fn to_packs(&self, first_track: u8) -> Result<Vec<u8>, NrgError> {
+        let nb_tracks = self.tracks.len();
+        if nb_tracks == 0 || usize::from(first_track) + nb_tracks > 100 {
+            return Err(NrgError::NrgFormat(format!(
+                "Invalid CD-Text track numbers: {} tracks starting at {}",
+                nb_tracks, first_track)));
+        }
+
+        let mut packs = Vec::new();
+        let mut counts = [0u8; 16];
+        for &pack_type in &TEXT_PACK_TYPES {
+            let strings = self.strings(pack_type);
+            if strings.iter().all(|string| string.is_empty()) {
+                continue;
+            }
+            let before = packs.len();
+            text_packs(pack_type, &strings, first_track, &mut packs);
+            counts[usize::from(pack_type & 0x0F)] =
+                ((packs.len() - before) / PACK_SIZE) as u8;
+        }
+        if self.genre_code != 0 || !self.genre.is_empty() {
+            let mut genre = self.genre_code.to_be_bytes().to_vec();
+            genre.extend(self.genre.chars().map(latin1));
+            let before = packs.len();
+            text_packs(PACK_GENRE, &[genre], first_track, &mut packs);
+            counts[usize::from(PACK_GENRE & 0x0F)] =
+                ((packs.len() - before) / PACK_SIZE) as u8;
+        }
+
+        // Size information
+        let nb_packs = packs.len() / PACK_SIZE + 3;
+        if nb_packs > 256 {
+            return Err(NrgError::NrgFormat(format!(
+                "Too much CD-Text: {} packs", nb_packs)));
+        }
+        counts[usize::from(PACK_SIZE_INFO & 0x0F)] = 3;
+        let mut size_info = vec![0x00, first_track,
+                                 first_track + (nb_tracks - 1) as u8, 0x00];
+        size_info.extend_from_slice(&counts);
+        size_info.extend_from_slice(&[(nb_packs - 1) as u8, 0, 0, 0,
+                                      0, 0, 0, 0]);
+        size_info.extend_from_slice(&[LANGUAGE_ENGLISH, 0, 0, 0,
+                                      0, 0, 0, 0]);
+        for (i, text) in size_info.chunks(PACK_TEXT_SIZE).enumerate() {
+            push_pack(&mut packs, PACK_SIZE_INFO, i as u8, 0, text);
+        }
+
+        Ok(packs)
+    }
+
+    /// Returns the strings of the pack type `pack_type`, encoded in ISO
+    /// 8859-1: the disc's one, followed by the tracks' ones.
+    fn strings(&self, pack_type: u8) -> Vec<Vec<u8>> {
+        let disc = self.field(pack_type);
+        let tracks = self.tracks.iter().map(|track| track.field(pack_type));
+        Some(disc).into_iter().chain(tracks)
+            .map(|string| string.map_or_else(Vec::new, |string| {
+                string.chars().map(latin1).collect()
+            }))
+            .collect()
+    }
+}
+
+
+/// A block of CD-Text information, in a given language.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NrgCdTextBlock {
+    /// Number of the block (0 to 7).
+    pub number: u8,
+    /// Language code of the block (e.g. 0x09 for English).
+    pub language: u8,
+    /// Information held by the block.
+    pub text: NrgCdText,
+}
+
+impl NrgCdTextBlock {
+    /// Returns the name of the block's language, or `None` if the language
+    /// code is unknown.
+    pub fn language_name(&self) -> Option<&'static str> {
+        language_name(self.language)
+    }
+}
+
+
+/// Returns the name of the language code `code`, or `None` if it is
+/// unknown.
+pub fn language_name(code: u8) -> Option<&'static str> {
+    LANGUAGES.iter()
+        .find(|&&(language, _)| language == code)
+        .map(|&(_, name)| name)
+}
+
+
+/// Parses a language given either by its name (e.g. "French", case
+/// insensitive) or by its code, in decimal or prefixed with "0x".
+pub fn parse_language(language: &str) -> Result<u8, NrgError> {
+    let code = match language.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => language.parse().ok(),
+    };
+    code.or_else(|| LANGUAGES.iter()
+                 .find(|&&(_, name)| name.eq_ignore_ascii_case(language))
+                 .map(|&(code, _)| code))
+        .ok_or_else(|| NrgError::NrgFormat(format!(
+            "Unknown CD-Text language \"{}\"", language)))
+}
+
+
+/// Returns the block of `blocks` in the language `language`, or the first
+/// block if there is none or if `language` is `None`.
+pub fn select_block(blocks: &[NrgCdTextBlock], language: Option<u8>)
+                    -> Option<&NrgCdTextBlock> {
+    language
+        .and_then(|language| {
+            blocks.iter().find(|block| block.language == language)
+        })
+        .or_else(|| blocks.first())
+}
+
+
+/// Text of a given type gathered from the packs of a block.
+#[derive(Default)]
+struct PackText {
+    /// Track number of the first pack, i.e. of the first string.
+    first_track: u8,
+    /// Whether the characters are double-byte ones.
+    dbcc: bool,
+    /// Concatenated text of the packs.
+    bytes: Vec<u8>,
+}
+
+/// Packs of a block, by type.
+#[derive(Default)]
+struct PackBlock {
+    texts: BTreeMap<u8, PackText>,
+    size_info: Vec<u8>,
+}
+
+
+/// Parses the CD-Text packs `packs`, e.g. the payload of a CDTX chunk, and
+/// returns their blocks, ordered by number.
+///
+/// The packs of unknown types are ignored. The CRCs are not checked. The
+/// text of the ISO 8859-1 and ASCII blocks is decoded; in double-byte
+/// (Shift-JIS) blocks, only the ASCII characters are, the other ones being
+/// replaced with U+FFFD.
+pub fn parse_packs(packs: &[u8]) -> Result<Vec<NrgCdTextBlock>, NrgError> {
+    if !packs.len().is_multiple_of(PACK_SIZE) {
+        return Err(NrgError::NrgFormat(format!(
+            "Invalid CD-Text size: {} bytes is not a multiple of {}",
+            packs.len(), PACK_SIZE)));
+    }
+
+    let mut blocks: BTreeMap<u8, PackBlock> = BTreeMap::new();
+    for pack in packs.chunks(PACK_SIZE) {
+        let pack_type = pack[0];
+        let block = blocks.entry((pack[3] >> 4) & 0x07).or_default();
+        let text = &pack[4..4 + PACK_TEXT_SIZE];
+        if pack_type == PACK_SIZE_INFO {
+            block.size_info.extend_from_slice(text);
+        } else if pack_type & 0xF0 == 0x80 {
+            let texts = block.texts.entry(pack_type).or_insert_with(|| {
+                PackText {
+                    first_track: pack[1] & 0x7F,
+                    dbcc: pack[3] & PACK_DBCC != 0,
+                    bytes: Vec::new(),
+                }
+            });
+            texts.bytes.extend_from_slice(text);
+        }
+    }
+
+    // The languages of all the blocks are listed in the size information of
+    // each block: use the first one found
+    let languages = blocks.values()
+        .map(|block| &block.size_info)
+        .find(|size_info| size_info.len() >= 36)
+        .map(|size_info| size_info[28..36].to_vec())
+        .unwrap_or_default();
+
+    Ok(blocks.into_iter().map(|(number, block)| {
+        let language = languages.get(usize::from(number)).cloned()
+            .unwrap_or(if number == 0 { LANGUAGE_ENGLISH } else { 0 });
+        NrgCdTextBlock {
+            number,
+            language,
+            text: block_text(&block),
+        }
+    }).collect())
+}
+
+
+/// Builds the information held by the packs of `block`.
+fn block_text(block: &PackBlock) -> NrgCdText {
+    let charset = block.size_info.first().cloned().unwrap_or(0);
+    let (mut first_track, mut last_track) = match block.size_info.get(1..3) {
+        Some(tracks) => (tracks[0], tracks[1]),
+        None => (0, 0),
+    };
+    if first_track == 0 || last_track < first_track {
+        // No size information: use the track numbers of the packs, a disc
+        // having at most 99 tracks
+        let numbers = block.texts.values()
+            .map(|text| text.first_track)
+            .filter(|&number| number > 0);
+        first_track = numbers.min().unwrap_or(1);
+        last_track = block.texts.values()
+            .map(|text| usize::from(text.first_track)
+                 + text_strings(text, charset).len().saturating_sub(1))
+            .max()
+            .map_or(0, |last| last.min(99) as u8)
+            .max(first_track);
+    }
+
+    let nb_tracks = usize::from(last_track - first_track) + 1;
+    let mut cd_text = NrgCdText {
+        tracks: vec![NrgCdTextTrack::default(); nb_tracks],
+        ..NrgCdText::default()
+    };
+    for (&pack_type, text) in &block.texts {
+        if pack_type == PACK_GENRE {
+            // A genre code, followed by a string
+            if let Some(code) = text.bytes.get(..2) {
+                cd_text.genre_code = u16::from_be_bytes([code[0], code[1]]);
+                let genre = PackText {
+                    bytes: text.bytes[2..].to_vec(),
+                    ..*text
+                };
+                cd_text.genre = text_strings(&genre, charset).into_iter()
+                    .next().unwrap_or_default();
+            }
+            continue;
+        }
+        for (i, string) in text_strings(text, charset).into_iter()
+            .enumerate() {
+            let number = usize::from(text.first_track) + i;
+            let field = if number == 0 {
+                cd_text.field_mut(pack_type)
+            } else if number < usize::from(first_track) {
+                continue;
+            } else {
+                cd_text.tracks.get_mut(number - usize::from(first_track))
+                    .and_then(|track| track.field_mut(pack_type))
+            };
+            if let Some(field) = field {
+                *field = string;
+            }
+        }
+    }
+    cd_text
+}
+
+
+/// Splits the text `text` into its null-terminated strings, decoded
+/// according to the character code `charset`.
+///
+/// The double-byte strings end with two null bytes. A string made of a
+/// single tab character means that it is identical to the previous one.
+fn text_strings(text: &PackText, charset: u8) -> Vec<String> {
+    let dbcc = text.dbcc || charset == CHARSET_MS_JIS;
+    let bytes = &text.bytes;
+    let mut strings: Vec<String> = Vec::new();
+    let mut string = String::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if byte == 0 {
+            if string == "\t" {
+                string = strings.last().cloned().unwrap_or_default();
+            }
+            strings.push(string);
+            string = String::new();
+            i += if dbcc { 2 } else { 1 };
+            continue;
+        }
+        string.push(match byte {
+            0x81..=0x9F | 0xE0..=0xFC if dbcc => {
+                // Lead byte of a two-byte Shift-JIS character
+                i += 1;
+                char::REPLACEMENT_CHARACTER
+            },
+            0x80.. if dbcc => char::REPLACEMENT_CHARACTER,
+            _ => char::from(byte),
+        });
+        i += 1;
+    }
+    if !string.is_empty() {
+        strings.push(string);
+    }
+
+    // Drop the padding following the last string
+    while strings.last().is_some_and(|string| string.is_empty()) {
+        strings.pop();
+    }
+    strings
+}
+
+
+/// Appends the packs holding the encoded `strings` (the disc's one and the
+/// tracks' ones) to `packs`.
+fn text_packs(pack_type: u8, strings: &[Vec<u8>], first_track: u8,
+              packs: &mut Vec<u8>) {
+    // Concatenate the strings, remembering which track each byte belongs
+    // to and its position in the string
+    let mut text = Vec::new();
+    let mut owners = Vec::new();
+    for (i, string) in strings.iter().enumerate() {
+        let track = if i == 0 { 0 } else { first_track + (i - 1) as u8 };
+        let bytes = string.iter().cloned().chain(Some(0));
+        for (position, byte) in bytes.enumerate() {
+            text.push(byte);
+            owners.push((track, position.min(15) as u8));
+        }
+    }
+
+    for (i, chunk) in text.chunks(PACK_TEXT_SIZE).enumerate() {
+        let (track, position) = owners[i * PACK_TEXT_SIZE];
+        push_pack(packs, pack_type, track, position, chunk);
+    }
+}
+
+
+/// Encodes `c` in ISO 8859-1, or as a question mark if it can't be.
+fn latin1(c: char) -> u8 {
+    match c as u32 {
+        0 | 0x100.. => b'?',
+        code => code as u8,
+    }
+}
+
+
+/// Appends a pack to `packs`, `text` being padded with null bytes.
+fn push_pack(packs: &mut Vec<u8>, pack_type: u8, track: u8, position: u8,
+             text: &[u8]) {
+    let sequence = (packs.len() / PACK_SIZE) as u8;
+    let mut pack = [0u8; PACK_SIZE];
+    pack[..4].copy_from_slice(&[pack_type, track, sequence, position]);
+    pack[4..4 + text.len()].copy_from_slice(text);
+    let crc = crc16(&pack[..16]);
+    pack[16..].copy_from_slice(&crc.to_be_bytes());
+    packs.extend_from_slice(&pack);
+}
+
+
+/// Computes the CRC of a CD-Text pack: CRC-16/CCITT (polynomial 0x1021,
+/// initial value 0), inverted.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    !crc
+}
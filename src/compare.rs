@@ -0,0 +1,432 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to compare the audio content of two images, e.g. to validate a
+//! conversion or to find duplicates in a collection.
+//!
+//! The audio of an image runs from the first track's index #1 to the end of
+//! the last track; the sub-channel data of its sectors is ignored. The
+//! images are compared sector by sector, once the second one is shifted by
+//! the offset found by `compare()`, if any.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+use ::error::NrgError;
+use ::metadata::metadata::NrgMetadata;
+use ::metadata::track::Track;
+use ::msf::Msf;
+use ::raw_audio::{RAW_SEC_SIZE, RAW96_SEC_SIZE, SAMPLE_RATE, SAMPLE_SIZE};
+
+
+/// Number of sectors compared at once.
+const CHUNK_SECTORS: u64 = 75;
+
+/// Maximum number of sectors of the first image scanned for the audio
+/// searched in the second one.
+const SEARCH_SECTORS: u64 = 60 * 75;
+
+/// Largest offset searched by `compare()`, in samples: 5 seconds.
+pub const MAX_SEARCH_OFFSET: u32 = 5 * SAMPLE_RATE;
+
+
+/// Location of the audio in an image.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgAudioLayout {
+    /// Byte position of the first track's index #1.
+    pub start: u64,
+    /// Byte position of the end of the last track.
+    pub end: u64,
+    pub sector_size: u16,
+    /// Tracks of the image, empty if it has no table of contents.
+    pub tracks: Vec<Track>,
+}
+
+impl NrgAudioLayout {
+    /// Returns the layout of an NRG image, given its metadata.
+    ///
+    /// Fails if the audio ends past the end of the image file.
+    pub fn from_metadata(metadata: &NrgMetadata)
+                         -> Result<NrgAudioLayout, NrgError> {
+        let end = metadata.last_audio_byte();
+        if end > metadata.file_size() {
+            return Err(NrgError::NrgFormat(format!(
+                "The audio ends at byte {}, past the end of the image ({} \
+                 bytes)", end, metadata.file_size())));
+        }
+        Ok(NrgAudioLayout {
+            start: metadata.first_audio_byte(),
+            end,
+            sector_size: metadata.sector_size(),
+            tracks: metadata.tracks().collect(),
+        })
+    }
+
+    /// Returns the layout of the audio file of an external cue sheet, whose
+    /// tracks are `tracks` (see nrgrip::cue_sheet::parse()).
+    pub fn from_tracks(tracks: Vec<Track>) -> NrgAudioLayout {
+        NrgAudioLayout {
+            start: tracks.first().map_or(0, |track| track.index1),
+            end: tracks.last().map_or(0, |track| track.track_end),
+            sector_size: RAW_SEC_SIZE,
+            tracks,
+        }
+    }
+
+    /// Returns the layout of a raw image of `size` bytes, made of 2352-byte
+    /// sectors and without a table of contents, such as the BIN file of a
+    /// BIN/CUE image.
+    pub fn raw(size: u64) -> NrgAudioLayout {
+        NrgAudioLayout {
+            start: 0,
+            end: size,
+            sector_size: RAW_SEC_SIZE,
+            tracks: Vec::new(),
+        }
+    }
+
+    /// Returns the number of whole sectors of audio.
+    pub fn sectors(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+            .checked_div(u64::from(self.sector_size))
+            .unwrap_or(0)
+    }
+}
+
+
+/// Run of consecutive sectors differing between the two images.
+///
+/// `first_sector` is counted from the start of the audio of the first
+/// image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgDifference {
+    pub first_sector: u64,
+    pub sectors: u64,
+}
+
+impl fmt::Display for NrgDifference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {} sector{}",
+               Msf::from_sectors(self.first_sector as u32), self.sectors,
+               if self.sectors > 1 { "s" } else { "" })
+    }
+}
+
+
+/// Results of `compare()`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgComparison {
+    /// Differences between the tables of contents of the images, empty if
+    /// either of them has none.
+    pub toc_differences: Vec<String>,
+    /// Offset of the audio of the second image relatively to the first one,
+    /// in samples.
+    pub offset: i64,
+    /// Number of sectors of audio of the first and the second image.
+    pub sectors: (u64, u64),
+    /// Runs of sectors differing between the images.
+    pub differences: Vec<NrgDifference>,
+}
+
+impl NrgComparison {
+    /// Returns the number of sectors differing between the images.
+    pub fn differing_sectors(&self) -> u64 {
+        self.differences.iter().map(|difference| difference.sectors).sum()
+    }
+
+    /// Returns true if both images hold the same audio, once the second one
+    /// is shifted by `offset`.
+    pub fn same_audio(&self) -> bool {
+        self.sectors.0 == self.sectors.1 && self.differences.is_empty()
+    }
+
+    /// Returns true if both images hold the same audio at the same position,
+    /// with the same table of contents.
+    pub fn identical(&self) -> bool {
+        self.same_audio() && self.offset == 0
+            && self.toc_differences.is_empty()
+    }
+
+    /// Returns the result of the comparison: "IDENTICAL", "SAME AUDIO" if
+    /// only the offset or the tables of contents differ, or "DIFFERENT".
+    pub fn result(&self) -> &'static str {
+        if self.identical() {
+            "IDENTICAL"
+        } else if self.same_audio() {
+            "SAME AUDIO"
+        } else {
+            "DIFFERENT"
+        }
+    }
+}
+
+impl fmt::Display for NrgComparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.toc_differences.is_empty() {
+            write!(f, "Table of contents:")?;
+            for difference in &self.toc_differences {
+                write!(f, "\n\t- {}", difference)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "Lengths: {} and {} sectors", self.sectors.0,
+                 self.sectors.1)?;
+        writeln!(f, "Offset: {:+} samples", self.offset)?;
+        write!(f, "Differing sectors: {}", self.differing_sectors())?;
+        for difference in &self.differences {
+            write!(f, "\n\t- {}", difference)?;
+        }
+        write!(f, "\nResult: {}", self.result())
+    }
+}
+
+
+/// Compares the audio of the images `a_fd` and `b_fd`, whose audio is
+/// located by `a` and `b`.
+///
+/// If `max_offset` isn't 0, the first non-silent sector of the first image
+/// is searched in the second one, up to `max_offset` samples away from its
+/// position (at most `MAX_SEARCH_OFFSET`); the smallest offset at which it
+/// is found is then applied to the second image. The offset is 0 if the
+/// sector can't be found.
+///
+/// The sectors of the first image are compared with the second image's; the
+/// bytes of the second image beyond its audio are deemed null.
+pub fn compare<A, B>(a_fd: &mut A, a: &NrgAudioLayout,
+                     b_fd: &mut B, b: &NrgAudioLayout, max_offset: u32)
+                     -> Result<NrgComparison, NrgError>
+    where A: Read + Seek,
+          B: Read + Seek {
+    let mut a_audio = AudioReader::new(a_fd, a)?;
+    let mut b_audio = AudioReader::new(b_fd, b)?;
+
+    if max_offset > MAX_SEARCH_OFFSET {
+        warn!("Searching the offset within {} samples only",
+              MAX_SEARCH_OFFSET);
+    }
+    let max_offset = max_offset.min(MAX_SEARCH_OFFSET);
+    let offset = if max_offset > 0 {
+        find_offset(&mut a_audio, &mut b_audio, max_offset)?
+    } else {
+        0
+    };
+
+    let sector_bytes = RAW_SEC_SIZE as usize;
+    let mut a_buf = vec![0u8; CHUNK_SECTORS as usize * sector_bytes];
+    let mut b_buf = a_buf.clone();
+    let mut differences: Vec<NrgDifference> = Vec::new();
+    let mut sector = 0;
+    while sector < a_audio.sectors {
+        let nb_sectors = (a_audio.sectors - sector).min(CHUNK_SECTORS);
+        let len = nb_sectors as usize * sector_bytes;
+        let pos = (sector * u64::from(RAW_SEC_SIZE)) as i64;
+        a_audio.read(pos, &mut a_buf[..len])?;
+        b_audio.read(pos + offset * SAMPLE_SIZE as i64, &mut b_buf[..len])?;
+
+        let pairs = a_buf[..len].chunks(sector_bytes)
+            .zip(b_buf.chunks(sector_bytes));
+        for (i, (a_sector, b_sector)) in pairs.enumerate() {
+            if a_sector == b_sector {
+                continue;
+            }
+            let number = sector + i as u64;
+            match differences.last_mut() {
+                Some(difference)
+                    if difference.first_sector + difference.sectors
+                        == number => difference.sectors += 1,
+                _ => differences.push(NrgDifference {
+                    first_sector: number,
+                    sectors: 1,
+                }),
+            }
+        }
+        sector += nb_sectors;
+    }
+
+    Ok(NrgComparison {
+        toc_differences: compare_tocs(&a.tracks, &b.tracks),
+        offset,
+        sectors: (a_audio.sectors, b_audio.sectors),
+        differences,
+    })
+}
+
+
+/// Returns the offset of the second image relatively to the first one, in
+/// samples.
+fn find_offset<A, B>(a: &mut AudioReader<A>, b: &mut AudioReader<B>,
+                     max_offset: u32) -> Result<i64, NrgError>
+    where A: Read + Seek,
+          B: Read + Seek {
+    let sector_bytes = RAW_SEC_SIZE as usize;
+    let mut reference = vec![0u8; sector_bytes];
+    let mut pos = None;
+    for sector in 0..a.sectors.min(SEARCH_SECTORS) {
+        let sector_pos = (sector * u64::from(RAW_SEC_SIZE)) as i64;
+        a.read(sector_pos, &mut reference)?;
+        if reference.iter().any(|&b| b != 0) {
+            pos = Some(sector_pos);
+            break;
+        }
+    }
+    let pos = match pos {
+        Some(pos) => pos,
+        None => {
+            warn!("No audio found to search the offset");
+            return Ok(0);
+        },
+    };
+
+    let margin = i64::from(max_offset) * SAMPLE_SIZE as i64;
+    let mut window = vec![0u8; sector_bytes + 2 * margin as usize];
+    b.read(pos - margin, &mut window)?;
+    for distance in 0..=i64::from(max_offset) {
+        for &offset in &[distance, -distance] {
+            let start = (margin + offset * SAMPLE_SIZE as i64) as usize;
+            if window[start..start + sector_bytes] == reference[..] {
+                debug!("Audio found at offset {:+} samples", offset);
+                return Ok(offset);
+            }
+        }
+    }
+    warn!("No matching audio found within {} samples", max_offset);
+    Ok(0)
+}
+
+
+/// Lists the differences between the tables of contents `a` and `b`.
+///
+/// The pre-gap of the first track isn't compared: NRG images hold its
+/// 2 seconds of silence, which BIN files usually leave out.
+fn compare_tocs(a: &[Track], b: &[Track]) -> Vec<String> {
+    let mut differences = Vec::new();
+    if a.is_empty() || b.is_empty() {
+        return differences;
+    }
+    if a.len() != b.len() {
+        differences.push(format!("{} tracks in the first image, {} in the \
+                                  second", a.len(), b.len()));
+    }
+
+    let kind = |track: &Track| if track.is_data() { "data" } else { "audio" };
+    for (i, (a, b)) in a.iter().zip(b).enumerate() {
+        if a.is_data() != b.is_data() {
+            differences.push(format!(
+                "Track {:02}: {} in the first image, {} in the second",
+                a.number, kind(a), kind(b)));
+        }
+        if i > 0 && a.pregap_sectors() != b.pregap_sectors() {
+            differences.push(format!(
+                "Track {:02}: pre-gap of {} sectors in the first image, {} \
+                 in the second", a.number, a.pregap_sectors(),
+                b.pregap_sectors()));
+        }
+        if a.sector_count() != b.sector_count() {
+            differences.push(format!(
+                "Track {:02}: {} sectors in the first image, {} in the \
+                 second", a.number, a.sector_count(), b.sector_count()));
+        }
+    }
+    differences
+}
+
+
+/// Reader of the audio of an image, leaving out the sub-channel data.
+struct AudioReader<'a, R: 'a> {
+    fd: &'a mut R,
+    start: u64,
+    sector_size: u16,
+    sectors: u64,
+    /// Length of the audio without the sub-channel data, in bytes.
+    audio_len: i64,
+}
+
+impl<'a, R: Read + Seek> AudioReader<'a, R> {
+    fn new(fd: &'a mut R, layout: &NrgAudioLayout)
+           -> Result<AudioReader<'a, R>, NrgError> {
+        if layout.sector_size != RAW_SEC_SIZE
+            && layout.sector_size != RAW96_SEC_SIZE {
+            return Err(NrgError::NrgFormat(format!(
+                "Can't compare the audio in {}-byte sectors",
+                layout.sector_size)));
+        }
+        // Every position computed while reading must fit in an i64
+        let sectors = layout.sectors();
+        let too_long = || NrgError::NrgFormat(format!(
+            "The audio is too long: {} sectors", sectors));
+        let audio_len = sectors.checked_mul(u64::from(RAW_SEC_SIZE))
+            .and_then(|len| i64::try_from(len).ok())
+            .ok_or_else(too_long)?;
+        sectors.checked_mul(u64::from(layout.sector_size))
+            .and_then(|len| len.checked_add(layout.start))
+            .filter(|&end| i64::try_from(end).is_ok())
+            .ok_or_else(too_long)?;
+        Ok(AudioReader {
+            fd,
+            start: layout.start,
+            sector_size: layout.sector_size,
+            sectors,
+            audio_len,
+        })
+    }
+
+    /// Fills `buf` with the audio from the byte `pos`, counted from the start
+    /// of the audio; the bytes out of the audio are null.
+    fn read(&mut self, pos: i64, buf: &mut [u8]) -> Result<(), NrgError> {
+        let audio_sector = i64::from(RAW_SEC_SIZE);
+        let audio_len = self.audio_len;
+        let mut done = 0;
+        while done < buf.len() {
+            let pos = pos.checked_add(done as i64)
+                .ok_or_else(|| NrgError::NrgFormat(format!(
+                    "Invalid audio position {} + {}", pos, done)))?;
+            let remaining = buf.len() - done;
+            if pos < 0 || pos >= audio_len {
+                let nbytes = if pos < 0 {
+                    (-pos as usize).min(remaining)
+                } else {
+                    remaining
+                };
+                buf[done..done + nbytes].iter_mut().for_each(|b| *b = 0);
+                done += nbytes;
+                continue;
+            }
+
+            // Sectors without sub-channel data are contiguous
+            let available = if self.sector_size == RAW_SEC_SIZE {
+                audio_len - pos
+            } else {
+                audio_sector - pos % audio_sector
+            };
+            let nbytes = (available as usize).min(remaining);
+            // Bounded by the checks of new(), as pos < audio_len
+            let sector = pos / audio_sector;
+            self.fd.seek(SeekFrom::Start(
+                self.start + (sector * i64::from(self.sector_size)
+                              + pos % audio_sector) as u64))?;
+            self.fd.read_exact(&mut buf[done..done + nbytes])?;
+            done += nbytes;
+        }
+        Ok(())
+    }
+}
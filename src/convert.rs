@@ -0,0 +1,312 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to convert NRG images to other image formats.
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use ::cue_sheet::{self, CueSheetOptions};
+use ::error::NrgError;
+use ::extraction::{self, ExtractedFile, ExtractionOptions};
+use ::metadata::metadata::NrgMetadata;
+use ::metadata::track::Track;
+use ::raw_audio::RAW_SEC_SIZE;
+use ::sectors::{self, NrgSectorRepair, FORM2_SUBMODE, SYNC_PATTERN};
+use ::writer;
+
+/// Size of the user data of a data sector (Mode 1 or Mode 2 Form 1).
+const ISO_SEC_SIZE: usize = 2048;
+/// Size of a Mode 2 sector without its sync pattern and header.
+const MODE2_SEC_SIZE: usize = 2336;
+/// Position of the first volume descriptor in an ISO-9660 image.
+const ISO_VD_OFFSET: u64 = 16 * ISO_SEC_SIZE as u64;
+
+
+/// Target formats of `convert()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// BIN file holding the raw sectors, with a cue sheet describing the
+    /// tracks.
+    BinCue,
+    /// ISO-9660 file holding the 2048-byte user data of a single data track.
+    Iso,
+    /// NRG image without the subchannel of its 2448-byte sectors.
+    Nrg,
+}
+
+impl ImageFormat {
+    /// Returns the file name extensions of the files written in this format.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match *self {
+            ImageFormat::BinCue => &["bin", "cue"],
+            ImageFormat::Iso => &["iso"],
+            ImageFormat::Nrg => &["nrg"],
+        }
+    }
+}
+
+
+/// Converts an NRG image to another image format.
+///
+/// - `in_fd` is the handler to the NRG image file.
+/// - `img_path` is the name of the input NRG file.
+/// - `metadata` is the metadata extracted from `img_path` by nrgrip::metadata.
+/// - `format` is the target format.
+/// - `out_base` is the path of the files to write, without extension; the
+///   extensions of `format` are appended to it.
+/// - if `strip_subchannel` is true, the 96-bit subchannel is stripped from
+///   the sectors, if present.
+///
+/// In BIN/CUE format, the BIN file starts at the first track's index #1,
+/// like the audio data written by nrgrip::extraction.
+///
+/// The ISO format is only available for images holding a single data track;
+/// the sync pattern, header, sub-header and error correction data of the raw
+/// sectors are stripped, as well as the subchannel, regardless of
+/// `strip_subchannel`. The damaged Mode 1 sectors are repaired with their
+/// ECC if possible (see nrgrip::sectors::repair_sector()); the repaired and
+/// the irrecoverable sectors are logged.
+///
+/// The NRG format is only available for images holding tracks with 2448-byte
+/// sectors, which are written with 2352-byte sectors; the rest of the image
+/// is copied as is, see nrgrip::writer::write_stripped_copy().
+///
+/// Returns the list of files written.
+pub fn convert(in_fd: &mut File, img_path: &str, metadata: &NrgMetadata,
+               format: ImageFormat, out_base: &Path, strip_subchannel: bool)
+               -> Result<Vec<ExtractedFile>, NrgError> {
+    match format {
+        ImageFormat::BinCue =>
+            to_bin_cue(in_fd, img_path, metadata, out_base, strip_subchannel),
+        ImageFormat::Iso => to_iso(in_fd, metadata, out_base),
+        ImageFormat::Nrg => to_nrg(in_fd, img_path, metadata, out_base),
+    }
+}
+
+
+fn to_bin_cue(in_fd: &mut File, img_path: &str, metadata: &NrgMetadata,
+              out_base: &Path, strip_subchannel: bool)
+              -> Result<Vec<ExtractedFile>, NrgError> {
+    let bin_path = add_extension(out_base, "bin");
+    let cue_path = add_extension(out_base, "cue");
+
+    let cue_options = CueSheetOptions::new()
+        .output_path(&cue_path)
+        .audio_file(&bin_path);
+    cue_sheet::write_cue_sheet(img_path, metadata, &[], &cue_options)?;
+
+    let options = ExtractionOptions::new()
+        .strip_subchannel(strip_subchannel)
+        .output_path(&bin_path);
+    extraction::extract(in_fd, img_path, metadata, &options)
+}
+
+
+fn to_iso(in_fd: &mut File, metadata: &NrgMetadata, out_base: &Path)
+          -> Result<Vec<ExtractedFile>, NrgError> {
+    let tracks: Vec<Track> = metadata.tracks().collect();
+    let track = match tracks.as_slice() {
+        [track] => track,
+        _ => return Err(NrgError::NrgFormat(format!(
+            "ISO conversion requires a single data track ({} tracks found)",
+            tracks.len()))),
+    };
+    if !track.is_data() && track.mode != 0 {
+        return Err(NrgError::NrgFormat(format!(
+            "Track {} is not a data track", track.number)));
+    }
+    let sector_size = usize::from(track.sector_size);
+    let range = track.byte_range();
+    if sector_size < ISO_SEC_SIZE
+        || !(range.end - range.start).is_multiple_of(sector_size as u64) {
+        return Err(NrgError::NrgFormat(format!(
+            "Invalid data track: {} bytes of {}-byte sectors",
+            range.end - range.start, sector_size)));
+    }
+
+    let iso_path = add_extension(out_base, "iso");
+    info!("Writing ISO image to \"{}\"", iso_path.to_string_lossy());
+    in_fd.seek(SeekFrom::Start(range.start))?;
+    let mut in_fd = BufReader::new(in_fd);
+    let mut out_fd = BufWriter::new(File::create(&iso_path)?);
+    let mut sector = vec![0u8; sector_size];
+    let mut data_bytes = 0;
+    let mut pos = range.start;
+    let mut lba = track.position_sectors;
+    let mut repaired = Vec::new();
+    let mut irrecoverable = Vec::new();
+    while pos < range.end {
+        in_fd.read_exact(&mut sector)?;
+        if sector_size >= usize::from(RAW_SEC_SIZE) {
+            if let Some(problem) = sectors::check_sector(&sector, lba) {
+                match sectors::repair_sector(&mut sector, lba) {
+                    NrgSectorRepair::Repaired => {
+                        info!("Sector {}: {}, repaired", lba, problem);
+                        repaired.push(lba);
+                    },
+                    _ => {
+                        warn!("Sector {}: {}", lba, problem);
+                        irrecoverable.push(lba);
+                    },
+                }
+            }
+        }
+        let offset = user_data_offset(&sector, pos)?;
+        out_fd.write_all(&sector[offset..offset + ISO_SEC_SIZE])?;
+        if data_bytes == ISO_VD_OFFSET {
+            check_volume_descriptor(&sector[offset..]);
+        }
+        data_bytes += ISO_SEC_SIZE as u64;
+        pos += sector_size as u64;
+        lba += 1;
+    }
+    out_fd.flush()?;
+    if !repaired.is_empty() {
+        warn!("{} sectors repaired with their ECC: {}", repaired.len(),
+              list_sectors(&repaired));
+    }
+    if !irrecoverable.is_empty() {
+        warn!("{} sectors can't be repaired: {}", irrecoverable.len(),
+              list_sectors(&irrecoverable));
+    }
+
+    Ok(vec![ExtractedFile {
+        path: iso_path.to_string_lossy().into_owned(),
+        track_number: Some(track.number),
+        audio_bytes: data_bytes,
+        source_ranges: vec![range],
+        sectors: data_bytes / ISO_SEC_SIZE as u64,
+        crc32: None,
+        loudness: None,
+        verified: None,
+        bad_sectors: Vec::new(),
+    }])
+}
+
+
+fn to_nrg(in_fd: &mut File, img_path: &str, metadata: &NrgMetadata,
+          out_base: &Path)
+          -> Result<Vec<ExtractedFile>, NrgError> {
+    let nrg_path = add_extension(out_base, "nrg");
+    let same_file = match (fs::canonicalize(img_path),
+                           fs::canonicalize(&nrg_path)) {
+        (Ok(img_path), Ok(nrg_path)) => img_path == nrg_path,
+        _ => false,
+    };
+    if same_file {
+        return Err(NrgError::FileName(
+            "Input and output file are identical".to_string()));
+    }
+
+    info!("Writing NRG image to \"{}\"", nrg_path.to_string_lossy());
+    in_fd.seek(SeekFrom::Start(0))?;
+    let mut in_fd = BufReader::new(in_fd);
+    let mut out_fd = BufWriter::new(File::create(&nrg_path)?);
+    writer::write_stripped_copy(&mut in_fd, &mut out_fd)?;
+    out_fd.flush()?;
+
+    let sectors = metadata.tracks().map(|track| track.sector_count()).sum();
+    let image_range = 0..metadata.file_size();
+    Ok(vec![ExtractedFile {
+        path: nrg_path.to_string_lossy().into_owned(),
+        track_number: None,
+        audio_bytes: sectors * u64::from(RAW_SEC_SIZE),
+        source_ranges: vec![image_range],
+        sectors,
+        crc32: None,
+        loudness: None,
+        verified: None,
+        bad_sectors: Vec::new(),
+    }])
+}
+
+
+/// Returns the offset of the 2048 bytes of user data in `sector`, which is
+/// located at the byte `pos` of the image file.
+///
+/// Mode 2 sectors must be Form 1 sectors, since Form 2 sectors hold 2324
+/// bytes of user data that don't fit in an ISO image.
+fn user_data_offset(sector: &[u8], pos: u64) -> Result<usize, NrgError> {
+    let (mode, subheader) = match sector.len() {
+        ISO_SEC_SIZE => return Ok(0),
+        MODE2_SEC_SIZE => (2, 0),
+        len if len >= usize::from(RAW_SEC_SIZE) => {
+            if sector[..12] != SYNC_PATTERN {
+                return Err(NrgError::NrgFormat(format!(
+                    "No sync pattern in the sector at byte {}", pos)));
+            }
+            (sector[15], 16)
+        },
+        len => return Err(NrgError::NrgFormat(format!(
+            "Unsupported sector size: {} bytes", len))),
+    };
+    match mode {
+        1 => Ok(16),
+        2 if sector[subheader + 2] & FORM2_SUBMODE == 0 => Ok(subheader + 8),
+        2 => Err(NrgError::NrgFormat(format!(
+            "Mode 2 Form 2 sector at byte {} can't be written to an ISO image",
+            pos))),
+        _ => Err(NrgError::NrgFormat(format!(
+            "Unsupported sector mode {} at byte {}", mode, pos))),
+    }
+}
+
+
+/// Formats the list of sector addresses `lbas`, merging the runs of
+/// consecutive sectors.
+pub(crate) fn list_sectors(lbas: &[i32]) -> String {
+    let mut runs: Vec<(i32, i32)> = Vec::new();
+    for &lba in lbas {
+        match runs.last_mut() {
+            Some(&mut (_, ref mut last)) if *last + 1 == lba => *last = lba,
+            _ => runs.push((lba, lba)),
+        }
+    }
+    runs.iter()
+        .map(|&(first, last)| if first == last {
+            first.to_string()
+        } else {
+            format!("{} to {}", first, last)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+
+/// Warns if `data` doesn't start with an ISO-9660 volume descriptor.
+fn check_volume_descriptor(data: &[u8]) {
+    if &data[1..6] != b"CD001" {
+        warn!("No ISO-9660 volume descriptor found, the ISO image may not \
+               be mountable");
+    }
+}
+
+
+/// Appends `.extension` to `path`, keeping any dot already in its name.
+fn add_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
@@ -20,37 +20,121 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
 // IN THE SOFTWARE.
 
-//! Module to extract the cue sheet from the NRG metadata.
+//! Module to extract the cue sheet from the NRG metadata, and to read
+//! external cue sheets into the same track model.
 
+use std::env;
 use std::io::Write;
-use std::ffi::OsStr;
-use std::fs::File;
-use std::path::PathBuf;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
 
+use ::cdtext::NrgCdText;
 use ::error::NrgError;
+use ::extraction::{ExtractedFile, GapMode};
 use ::metadata::metadata::NrgMetadata;
-use ::metadata::cuex::NrgCuexTrack;
-use ::metadata::afnm::NrgAfnmTrack;
+use ::msf::Msf;
+use ::metadata::toc::{NrgToc, NrgTocIndex, NrgTocTrack};
+use ::metadata::track::Track;
+use ::gaps::NrgGap;
+use ::disc_id::NrgDiscToc;
+use ::raw_audio::RAW_SEC_SIZE;
+use ::unicode;
+use ::writer::NrgCueSheet;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+
+/// Options controlling `write_cue_sheet()`, built with chained setters.
+#[derive(Clone, Debug, Default)]
+pub struct CueSheetOptions {
+    rem: bool,
+    ascii: bool,
+    output_dir: Option<PathBuf>,
+    output_path: Option<PathBuf>,
+    audio_file: Option<PathBuf>,
+}
+
+impl CueSheetOptions {
+    /// Returns the default options: no REM lines, and the cue sheet written
+    /// in the current directory.
+    pub fn new() -> CueSheetOptions {
+        CueSheetOptions::default()
+    }
+
+    /// Writes REM lines holding the disc identifiers and the name of the
+    /// program at the beginning of the cue sheet.
+    pub fn rem_lines(mut self, rem: bool) -> CueSheetOptions {
+        self.rem = rem;
+        self
+    }
+
+    /// Transliterates the titles and performers to ASCII, with
+    /// `nrgrip::unicode::to_ascii()`, for the players that only handle
+    /// ASCII cue sheets. The file names are kept as is.
+    pub fn ascii(mut self, ascii: bool) -> CueSheetOptions {
+        self.ascii = ascii;
+        self
+    }
+
+    /// Writes the cue sheet in `dir` instead of the current directory.
+    pub fn output_dir(mut self, dir: &Path) -> CueSheetOptions {
+        self.output_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    /// Writes the cue sheet to `path`, instead of a file named after the
+    /// image in the output directory.
+    pub fn output_path(mut self, path: &Path) -> CueSheetOptions {
+        self.output_path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Sets the path of the audio file referenced by the cue sheet.
+    ///
+    /// If the audio file is in the same directory as the cue sheet, only its
+    /// name is written in the cue sheet; otherwise, its absolute path is
+    /// written.
+    /// A ".mp3" file is declared as `MP3`, an AIFF file as `AIFF`, a WAV file
+    /// or a file of another common audio format (e.g. ".flac" or ".opus") as
+    /// `WAVE`, and any other file as `BINARY`. The default is the image's
+    /// base name with a ".raw" extension, in the cue sheet's directory.
+    pub fn audio_file(mut self, path: &Path) -> CueSheetOptions {
+        self.audio_file = Some(path.to_path_buf());
+        self
+    }
+
+    /// Returns `text` normalized (see nrgrip::unicode), transliterated if
+    /// requested, and quoted with `quote_value()`.
+    fn text(&self, text: &str) -> String {
+        if self.ascii {
+            quote_value(&unicode::to_ascii(text))
+        } else {
+            quote_value(&unicode::nfc(text))
+        }
+    }
+}
 
 
 /// Writes the cue sheet for `img_path` into a file.
 ///
 /// - `img_path` is the name of the input NRG file.
 /// - `metadata` is the metadata extracted from `img_path` by nrgrip::metadata.
+/// - `gaps` is a list of gaps detected by nrgrip::gaps; a track's gap, if
+///   present in this list, replaces the index #0 found in the CUEX chunk.
+/// - `options` controls the contents and location of the cue sheet.
 ///
-/// The output file's name will be `img_path`'s base name stripped for its
-/// extension (if any), with a ".cue" extension.
-pub fn write_cue_sheet(img_path: &str, metadata: &NrgMetadata)
-                       -> Result<(), NrgError> {
+/// Unless an output path is set in `options`, the output file's name will be
+/// `img_path`'s base name stripped for its extension (if any), with a ".cue"
+/// extension. Returns the path of the cue sheet written.
+pub fn write_cue_sheet(img_path: &str, metadata: &NrgMetadata,
+                       gaps: &[NrgGap], options: &CueSheetOptions)
+                       -> Result<PathBuf, NrgError> {
     // Make sure we have a cue sheet in the metadata
-    let cuex_tracks = match metadata.cuex_chunk {
-        None => return Err(NrgError::NoNrgCue),
-        Some(ref chunk) => &chunk.tracks,
-    };
-     let cuex_titles = match metadata.afnm_chunk {
-        None => return Err(NrgError::NoNrgCue),
-        Some(ref chunk) => &chunk.tracks,
-    };
+    if metadata.cuex_chunk().is_none() {
+        return Err(NrgError::NoNrgCue);
+    }
+    let toc = metadata.toc();
+
     // Get the image's base name
     let img_name = PathBuf::from(img_path);
     let img_name = match img_name.file_name() {
@@ -59,93 +143,369 @@ pub fn write_cue_sheet(img_path: &str, metadata: &NrgMetadata)
     };
 
     // Set the cue sheet file's name
-    let mut cue_name = PathBuf::from(img_name);
-    if cue_name.extension().unwrap_or(OsStr::new("")) == "cue" {
-        // img_path's extension was already .cue: problem!
+    let cue_name = match options.output_path {
+        Some(ref path) => path.clone(),
+        None => {
+            let mut cue_name = PathBuf::from(img_name);
+            cue_name.set_extension("cue");
+            match options.output_dir {
+                Some(ref dir) => dir.join(cue_name),
+                None => cue_name,
+            }
+        },
+    };
+    if cue_name == Path::new(img_path) {
         return Err(NrgError::FileName("Input and output file are identical"
                                       .to_string()));
-
     }
-    cue_name.set_extension("cue");
 
-    // Set the raw audio file's name
-    let mut raw_name = cue_name.clone();
-    raw_name.set_extension("raw");
+    // Set the audio file's name, relative to the cue sheet
+    let audio_name = match options.audio_file {
+        Some(ref path) if path.parent() == cue_name.parent() =>
+            path.file_name().map_or_else(|| path.clone(), PathBuf::from),
+        Some(ref path) => env::current_dir()?.join(path),
+        None => {
+            let mut raw_name = PathBuf::from(img_name);
+            raw_name.set_extension("raw");
+            raw_name
+        },
+    };
 
     // Write cue sheet
-    let mut fd = try!(File::create(cue_name));
-    try!(writeln!(fd, "FILE \"{}\" BINARY", raw_name.to_string_lossy()));
-    try!(write_cue_tracks(&mut fd, cuex_tracks, cuex_titles));
+    info!("Writing cue sheet to \"{}\"", cue_name.to_string_lossy());
+    let mut fd = File::create(&cue_name)?;
+    if options.rem {
+        write_cue_rem(&mut fd, metadata)?;
+    }
+    let cd_text = metadata.cd_text();
+    write_cue_disc_text(&mut fd, cd_text, options)?;
+    write_cue_file(&mut fd, &audio_name)?;
+    write_cue_tracks(&mut fd, &toc, gaps, cd_text, options)?;
 
-    Ok(())
+    Ok(cue_name)
 }
 
 
-/// Writes a list of cue tracks to `fd`.
-fn write_cue_tracks(fd: &mut File, cuex_tracks: &Vec<NrgCuexTrack>, afnm_tracks: &Vec<NrgAfnmTrack>)
-                   -> Result<(), NrgError> {
-    let mut index0_pos = -1; // position of the last index #0 encountered
-    for track in cuex_tracks {
-        try!(write_cue_track(fd, track, &mut index0_pos, afnm_tracks));
+/// Writes one cue sheet per track file in `files`, as returned by
+/// nrgrip::extraction::extract() when splitting the tracks, for the systems
+/// that ingest the tracks one by one.
+///
+/// - `metadata` is the metadata of the image the files were extracted from.
+/// - `gap_mode` is the placement of the pre-gaps the files were extracted
+///   with: the indexes are positioned from the start of each file, which
+///   holds the track's pre-gap (as `INDEX 00 00:00:00`) only if it was
+///   prepended.
+/// - `options` controls the REM lines and the transliteration; the output
+///   paths are ignored.
+///
+/// Each cue sheet is written next to its audio file, with the same name and
+/// a ".cue" extension, and references only this file and this track. The
+/// files without a track number are skipped. Returns the paths of the cue
+/// sheets written.
+pub fn write_track_cue_sheets(metadata: &NrgMetadata, files: &[ExtractedFile],
+                              gap_mode: GapMode, options: &CueSheetOptions)
+                              -> Result<Vec<PathBuf>, NrgError> {
+    let toc = metadata.toc();
+    let cd_text = metadata.cd_text();
+    let mut cue_paths = Vec::new();
+    for file in files {
+        let number = match file.track_number {
+            Some(number) => number,
+            None => continue,
+        };
+        let toc_track = toc.track(number)
+            .ok_or(NrgError::TrackNotFound(number))?;
+        let index1 = toc_track.index(1)
+            .ok_or_else(|| NrgError::NrgFormat(format!(
+                "Track {} has no index #1", number)))?;
+
+        // The first track's pre-gap is never extracted
+        let first = toc.tracks.first()
+            .is_some_and(|first| first.track.number == number);
+        let start = match (gap_mode, toc_track.index(0)) {
+            (GapMode::Prepend, Some(index0)) if !first
+                && index0.position_sectors < index1.position_sectors =>
+                index0.position_sectors,
+            _ => index1.position_sectors,
+        };
+
+        let audio_path = Path::new(&file.path);
+        let cue_path = audio_path.with_extension("cue");
+        info!("Writing cue sheet to \"{}\"", cue_path.to_string_lossy());
+        let mut fd = File::create(&cue_path)?;
+        if options.rem {
+            write_cue_rem(&mut fd, metadata)?;
+        }
+        write_cue_disc_text(&mut fd, cd_text, options)?;
+        let audio_name = audio_path.file_name()
+            .map_or_else(|| audio_path.to_path_buf(), PathBuf::from);
+        write_cue_file(&mut fd, &audio_name)?;
+        writeln!(fd, "  TRACK {:02} AUDIO", number)?;
+        if let Some(ref title) = toc_track.track.title {
+            writeln!(fd, "    TITLE {}", options.text(title))?;
+        }
+        let position = toc.tracks.iter()
+            .position(|track| track.track.number == number);
+        write_cue_performer(&mut fd, track_performer(cd_text, position),
+                            options)?;
+        for index in &toc_track.indexes {
+            if index.position_sectors >= start {
+                write_cue_index(&mut fd, index.number,
+                                index.position_sectors - start)?;
+            }
+        }
+        cue_paths.push(cue_path);
     }
+    Ok(cue_paths)
+}
+
+
+/// Writes the FILE command referencing `audio_name` to `fd`.
+///
+/// A ".mp3" file is declared as `MP3`, an AIFF file as `AIFF`, a WAV file or
+/// a file of another common audio format as `WAVE`, and any other file as
+/// `BINARY`.
+fn write_cue_file(fd: &mut File, audio_name: &Path) -> Result<(), NrgError> {
+    let extension = audio_name.extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    let audio_type = match extension.as_deref() {
+        Some("mp3") => "MP3",
+        Some("aif") | Some("aiff") => "AIFF",
+        Some("wav") | Some("flac") | Some("ogg") | Some("opus")
+            | Some("m4a") | Some("wv") | Some("ape") => "WAVE",
+        _ => "BINARY",
+    };
+    let audio_name = audio_name.to_string_lossy();
+    if audio_name.contains('"') {
+        warn!("The cue sheet can't reference \"{}\" exactly: its double \
+               quotes are replaced with single quotes", audio_name);
+    }
+    writeln!(fd, "FILE {} {}", quote_value(&audio_name), audio_type)?;
     Ok(())
 }
 
 
-/// Writes a cue track's info to `fd`.
+/// Quotes `value` for a cue sheet command such as TITLE, PERFORMER or FILE.
 ///
-/// `index0_pos` should be negative when this function is first called.
-fn write_cue_track(fd: &mut File, track: &NrgCuexTrack, index0_pos: &mut i32, afnm_tracks: &Vec<NrgAfnmTrack>)
-                   -> Result<(), NrgError> {
-    // Ignore lead-in and lead-out areas
-    if track.track_number == 0 || track.track_number == 0xAA {
-        return Ok(());
+/// Cue sheets have no escape sequences: the value is enclosed in double
+/// quotes, the double quotes it contains being replaced with single quotes
+/// and its control characters (e.g. line breaks) with spaces. The other
+/// characters, including the non-ASCII ones, are kept as is, the cue sheet
+/// being written in UTF-8.
+pub fn quote_value(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    quoted.extend(value.chars().map(|c| match c {
+        '"' => '\'',
+        c if c.is_control() => ' ',
+        c => c,
+    }));
+    quoted.push('"');
+    quoted
+}
+
+
+/// Writes the REM lines identifying the disc to `fd`.
+///
+/// The disc IDs are omitted if the table of contents can't be computed from
+/// the metadata.
+fn write_cue_rem(fd: &mut File, metadata: &NrgMetadata)
+                 -> Result<(), NrgError> {
+    if let Some(toc) = NrgDiscToc::from_metadata(metadata) {
+        writeln!(fd, "REM DISCID {:08X}", toc.cddb_disc_id())?;
+        writeln!(fd, "REM MUSICBRAINZ_DISCID {}", toc.musicbrainz_disc_id())?;
     }
+    writeln!(fd, "REM GENERATED-BY nrgrip v{}", VERSION)?;
+    Ok(())
+}
+
+
+/// Writes the disc's title and performer of the CD-Text information
+/// `cd_text`, if any, to `fd`.
+fn write_cue_disc_text(fd: &mut File, cd_text: Option<&NrgCdText>,
+                       options: &CueSheetOptions) -> Result<(), NrgError> {
+    let cd_text = match cd_text {
+        Some(cd_text) => cd_text,
+        None => return Ok(()),
+    };
+    if !cd_text.performer.is_empty() {
+        writeln!(fd, "PERFORMER {}", options.text(&cd_text.performer))?;
+    }
+    if !cd_text.title.is_empty() {
+        writeln!(fd, "TITLE {}", options.text(&cd_text.title))?;
+    }
+    Ok(())
+}
+
 
-    // Ignore negative positions. This should happen only for track 1, index 0
-    // and for the lead-in area (which we already skipped).
-    if track.position_sectors < 0 {
-        return Ok(());
+/// Returns the performer of the `position`-th track in the CD-Text
+/// information `cd_text`, if any.
+fn track_performer(cd_text: Option<&NrgCdText>, position: Option<usize>)
+                   -> Option<&str> {
+    let track = cd_text?.tracks.get(position?)?;
+    Some(track.performer.as_str()).filter(|performer| !performer.is_empty())
+}
+
+
+/// Writes a track's PERFORMER command to `fd`, if `performer` is set.
+fn write_cue_performer(fd: &mut File, performer: Option<&str>,
+                       options: &CueSheetOptions) -> Result<(), NrgError> {
+    if let Some(performer) = performer {
+        writeln!(fd, "    PERFORMER {}", options.text(performer))?;
     }
+    Ok(())
+}
+
 
-    // Store/skip index0
-    if track.index_number == 0 {
-        *index0_pos = track.position_sectors;
-        return Ok(());
+/// Writes the tracks of the table of contents `toc` to `fd`, with the
+/// performers of the CD-Text information `cd_text`.
+fn write_cue_tracks(fd: &mut File, toc: &NrgToc, gaps: &[NrgGap],
+                    cd_text: Option<&NrgCdText>, options: &CueSheetOptions)
+                    -> Result<(), NrgError> {
+    for (i, track) in toc.tracks.iter().enumerate() {
+        let performer = track_performer(cd_text, Some(i));
+        write_cue_track(fd, track, gaps, performer, options)?;
     }
+    Ok(())
+}
+
+
+/// Writes a cue track's info to `fd`, with all its indexes.
+///
+/// If `gaps` contains a gap for the track, it is used instead of the track's
+/// index #0. The track's `performer`, if any, follows its title.
+///
+/// The part of a pre-gap located before the start of the audio file (i.e.
+/// at a negative position, as track 1's 2-second pre-gap) is written as a
+/// PREGAP command, so that the track's timing is kept when the disc is
+/// burned again.
+fn write_cue_track(fd: &mut File, toc_track: &NrgTocTrack, gaps: &[NrgGap],
+                   performer: Option<&str>, options: &CueSheetOptions)
+                   -> Result<(), NrgError> {
+    // Ignore the negative positions of the indexes other than #0, which
+    // shouldn't happen
+    let number = toc_track.track.number;
+    let indexes: Vec<&NrgTocIndex> = toc_track.indexes.iter()
+        .filter(|index| index.number > 0 && index.position_sectors >= 0)
+        .collect();
+    let start = match indexes.first() {
+        Some(index) => index.position_sectors,
+        None => return Ok(()),
+    };
 
     // Write track info
-    try!(writeln!(fd, "  TRACK {:02} AUDIO", track.track_number));
-    try!(writeln!(fd, "    TITLE {:?}", afnm_tracks[ (track.track_number -1) as usize].name.replace(".wav", "")));
-    
-    // Write index0 if we stored it and it's before the current index's
-    // position (i.e., it indicates a pre-gap)
-    if *index0_pos >= 0 && *index0_pos < track.position_sectors {
-        try!(write_cue_index(fd, 0, *index0_pos));
+    writeln!(fd, "  TRACK {:02} AUDIO", number)?;
+    if let Some(ref title) = toc_track.track.title {
+        writeln!(fd, "    TITLE {}", options.text(title))?;
     }
+    write_cue_performer(fd, performer, options)?;
 
-    // Reset index0 (even if we didn't write it, because it only applies to the
-    // current track)
-    *index0_pos = -1;
+    // Take index0 from the detected gap, if any
+    let index0_pos = match gaps.iter().find(|gap| gap.track_number == number)
+    {
+        Some(gap) => Some(start.saturating_sub(gap.sectors as i32)),
+        None => toc_track.index(0).map(|index| index.position_sectors),
+    };
+
+    // Write index0 if it's before the first index's position (i.e., it
+    // indicates a pre-gap), the part before the audio file as a PREGAP
+    // command
+    match index0_pos {
+        Some(index0) if index0 < 0 => {
+            writeln!(fd, "    PREGAP {}", Msf::from_sectors(-index0 as u32))?;
+            if start > 0 {
+                write_cue_index(fd, 0, 0)?;
+            }
+        },
+        Some(index0) if index0 < start => write_cue_index(fd, 0, index0)?,
+        _ => {},
+    }
 
-    // Write current index
-    write_cue_index(fd, track.index_number, track.position_sectors)
+    // Write the other indexes, in order
+    for index in indexes {
+        write_cue_index(fd, index.number, index.position_sectors)?;
+    }
+    Ok(())
 }
 
 
 /// Writes a cue index's info to `fd`.
 fn write_cue_index(fd: &mut File, index: u8, position_sectors: i32)
                    -> Result<(), NrgError> {
-    assert!(position_sectors >= 0);
-
-    // Audio CDs are played at a 75 sectors per second rate:
-    let mut seconds: u32 = position_sectors as u32 / 75;
-    let remaining_sectors = position_sectors % 75;
-    let minutes = seconds / 60;
-    seconds %= 60;
+    if position_sectors < 0 {
+        return Err(NrgError::NrgFormat(format!(
+            "Negative index position: {} sectors", position_sectors)));
+    }
 
-    try!(writeln!(fd, "    INDEX {:02} {:02}:{:02}:{:02}",
-                  index, minutes, seconds, remaining_sectors));
+    writeln!(fd, "    INDEX {:02} {}",
+             index, Msf::from_sectors(position_sectors as u32))?;
 
     Ok(())
 }
+
+
+/// An external cue sheet read by `parse()`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgExternalCueSheet {
+    /// Path of the audio file named in the cue sheet.
+    pub audio_path: PathBuf,
+    /// UPC/EAN of the disc, or an empty string.
+    pub catalog: String,
+    /// The tracks, whose byte positions are positions in the audio file.
+    pub tracks: Vec<Track>,
+}
+
+
+/// Reads the cue sheet `cue_path` into the track model of the NRG images.
+///
+/// The cue sheets handled are the ones nrgrip::writer::NrgCueSheet can
+/// parse: a single binary file of audio tracks. The audio file is looked for
+/// relative to the cue sheet's directory; its size gives the end of the last
+/// track.
+///
+/// The first track starts at the beginning of the audio file, the other
+/// ones at their index #0, if any, or #1. The titles are taken from the
+/// TITLE commands, and the positions of index #1 count the sectors from the
+/// first track's index #1, including the silence of the PREGAP commands.
+pub fn parse(cue_path: &Path) -> Result<NrgExternalCueSheet, NrgError> {
+    let text = fs::read_to_string(cue_path)?;
+    let sheet = NrgCueSheet::parse(&text)?;
+    let audio_path = cue_path.parent().unwrap_or_else(|| Path::new(""))
+        .join(&sheet.file);
+    let disc = sheet.disc(fs::metadata(&audio_path)?.len())?;
+    let cd_text = NrgCdText::from_cue_sheet(&text);
+
+    let sector_size = u64::from(RAW_SEC_SIZE);
+    let mut tracks = Vec::new();
+    let mut start = 0;
+    let mut position = 0;
+    for (i, track) in disc.tracks.iter().enumerate() {
+        let index1 = start + u64::from(track.index1) * sector_size;
+        let track_end = start + track.audio_size;
+        if i > 0 {
+            position += track.pregap as i32 + track.index1 as i32;
+        }
+        let title = cd_text.tracks.get(i)
+            .map(|cd_text| cd_text.title.clone())
+            .filter(|title| !title.is_empty());
+        tracks.push(Track {
+            number: disc.first_track + i as u8,
+            mode: track.mode,
+            isrc: Some(track.isrc.clone()).filter(|isrc| !isrc.is_empty()),
+            title,
+            position_sectors: position,
+            sector_size: RAW_SEC_SIZE,
+            index0: start,
+            index1,
+            track_end,
+        });
+        position += ((track_end - index1) / sector_size) as i32;
+        start = track_end;
+    }
+
+    Ok(NrgExternalCueSheet {
+        audio_path,
+        catalog: disc.upc,
+        tracks,
+    })
+}
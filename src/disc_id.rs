@@ -0,0 +1,142 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to compute the identifiers of a disc from its table of contents.
+
+use sha1::{Digest, Sha1};
+
+use ::metadata::metadata::NrgMetadata;
+
+/// Offset of the first sector of the program area, in sectors (2 seconds).
+const LEAD_IN_SECTORS: i32 = 150;
+
+
+/// Table of contents of a disc, as needed to compute its identifiers.
+///
+/// The track offsets and the lead-out offset are absolute positions in
+/// sectors, i.e. they include the 150 sectors of the lead-in area.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgDiscToc {
+    pub first_track: u8,
+    pub offsets: Vec<u32>,
+    pub lead_out: u32,
+}
+
+impl NrgDiscToc {
+    /// Builds the table of contents from the CUEX chunk of `metadata`.
+    ///
+    /// The position of index #1 is used as each track's offset. Returns
+    /// `None` if there is no CUEX chunk, or if it lacks the tracks or the
+    /// lead-out area.
+    pub fn from_metadata(metadata: &NrgMetadata) -> Option<NrgDiscToc> {
+        let cuex_chunk = metadata.cuex_chunk()?;
+
+        let mut first_track = None;
+        let mut offsets = Vec::new();
+        let mut lead_out = None;
+        for track in cuex_chunk.tracks() {
+            if track.track_number() == 0 || track.index_number() != 1 {
+                continue;
+            }
+            let offset = (track.position_sectors() + LEAD_IN_SECTORS) as u32;
+            if track.track_number() == 0xAA {
+                lead_out = Some(offset);
+            } else {
+                first_track.get_or_insert(track.track_number());
+                offsets.push(offset);
+            }
+        }
+
+        if offsets.is_empty() {
+            return None;
+        }
+        Some(NrgDiscToc {
+            first_track: first_track?,
+            offsets,
+            lead_out: lead_out?,
+        })
+    }
+
+    /// Returns the number of the last track.
+    pub fn last_track(&self) -> u8 {
+        (usize::from(self.first_track) + self.offsets.len() - 1) as u8
+    }
+
+    /// Computes the freedb/CDDB disc ID.
+    pub fn cddb_disc_id(&self) -> u32 {
+        // Audio CDs are played at a 75 sectors per second rate:
+        let checksum: u32 = self.offsets.iter()
+            .map(|offset| digit_sum(offset / 75))
+            .sum();
+        let length = (self.lead_out / 75).saturating_sub(self.offsets[0] / 75);
+        (checksum % 0xFF) << 24 | length << 8 | self.offsets.len() as u32
+    }
+
+    /// Computes the MusicBrainz disc ID.
+    pub fn musicbrainz_disc_id(&self) -> String {
+        let mut toc = format!("{:02X}{:02X}{:08X}",
+                              self.first_track, self.last_track(),
+                              self.lead_out);
+        for i in 0..99 {
+            toc.push_str(&format!("{:08X}",
+                                  self.offsets.get(i).cloned().unwrap_or(0)));
+        }
+
+        let digest = Sha1::digest(toc.as_bytes());
+        musicbrainz_base64(&digest)
+    }
+}
+
+
+/// Returns the sum of the decimal digits of `n`.
+fn digit_sum(mut n: u32) -> u32 {
+    let mut sum = 0;
+    while n > 0 {
+        sum += n % 10;
+        n /= 10;
+    }
+    sum
+}
+
+
+/// Encodes `bytes` in base64, using the URL-safe variant defined by
+/// MusicBrainz (`.`, `_` and `-` instead of `+`, `/` and `=`).
+fn musicbrainz_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ\
+                              abcdefghijklmnopqrstuvwxyz0123456789._";
+    let mut encoded = String::new();
+    for group in bytes.chunks(3) {
+        let mut buf = [0u8; 3];
+        buf[..group.len()].copy_from_slice(group);
+        let n = u32::from(buf[0]) << 16 | u32::from(buf[1]) << 8
+            | u32::from(buf[2]);
+        for i in 0..4 {
+            if i <= group.len() {
+                let sextet = (n >> (18 - 6 * i)) & 0x3F;
+                encoded.push(ALPHABET[sextet as usize] as char);
+            } else {
+                encoded.push('-');
+            }
+        }
+    }
+    encoded
+}
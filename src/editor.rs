@@ -0,0 +1,173 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to edit the metadata of NRG images in place.
+//!
+//! The UPC and ISRCs stored in the DAOX chunk are fixed-size fields, so
+//! changing them leaves the size of the chunks and the footer unchanged: only
+//! the bytes of the fields are rewritten. Adding CD-Text, on the other hand,
+//! inserts a CDTX chunk: the chunks following it and the footer are written
+//! again further in the file.
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use ::cdtext::NrgCdText;
+use ::error::NrgError;
+use ::isrc::Isrc;
+use ::metadata;
+use ::metadata::raw;
+use ::writer::{self, is_code, padded, DAOX_HEADER_SIZE, DAOX_TRACK_SIZE};
+
+
+/// Position of the UPC in the DAOX payload.
+const DAOX_UPC_OFFSET: usize = 4;
+/// Size of the UPC field.
+const UPC_SIZE: usize = 13;
+/// Position of the first track number in the DAOX payload.
+const DAOX_FIRST_TRACK_OFFSET: usize = 20;
+/// Size of the ISRC field.
+const ISRC_SIZE: usize = 12;
+
+
+/// Changes to apply to the DAOX chunk of an image.
+///
+/// An empty UPC or ISRC clears the field.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NrgDaoxEdit {
+    upc: Option<String>,
+    isrcs: Vec<(u8, String)>,
+}
+
+impl NrgDaoxEdit {
+    pub fn new() -> NrgDaoxEdit {
+        NrgDaoxEdit::default()
+    }
+
+    /// Sets the UPC/EAN of the disc (13 characters).
+    pub fn upc(mut self, upc: &str) -> NrgDaoxEdit {
+        self.upc = Some(upc.to_string());
+        self
+    }
+
+    /// Sets the ISRC of the track `track_number` (12 characters, or 15 with
+    /// hyphens between its elements), or clears it if `isrc` is empty.
+    pub fn isrc(mut self, track_number: u8, isrc: &str) -> NrgDaoxEdit {
+        self.isrcs.push((track_number, isrc.to_string()));
+        self
+    }
+
+    /// Tells whether there are no changes to apply.
+    pub fn is_empty(&self) -> bool {
+        self.upc.is_none() && self.isrcs.is_empty()
+    }
+}
+
+
+/// Applies `edit` to the DAOX chunk of the NRG image `fd`, in place.
+///
+/// Nothing is written if a code is invalid or a track is not found.
+pub fn edit_daox<F: Read + Write + Seek>(fd: &mut F, edit: &NrgDaoxEdit)
+                                         -> Result<(), NrgError> {
+    let chunk = raw::read_raw_chunks(fd)?.into_iter()
+        .find(|chunk| chunk.id == "DAOX")
+        .ok_or_else(|| NrgError::NrgFormat("No DAOX chunk".to_string()))?;
+    let size = chunk.payload.len();
+    if size < DAOX_HEADER_SIZE
+        || !(size - DAOX_HEADER_SIZE).is_multiple_of(DAOX_TRACK_SIZE) {
+        return Err(NrgError::NrgChunkSize("DAOX".to_string(), size as u32));
+    }
+    let first_track = chunk.payload[DAOX_FIRST_TRACK_OFFSET];
+    let nb_tracks = (size - DAOX_HEADER_SIZE) / DAOX_TRACK_SIZE;
+
+    // Check all the changes before writing anything
+    let mut fields = Vec::new();
+    if let Some(ref upc) = edit.upc {
+        if !upc.is_empty() && !is_code(upc, UPC_SIZE) {
+            return Err(NrgError::NrgFormat(format!(
+                "Invalid UPC: \"{}\"", upc)));
+        }
+        fields.push((DAOX_UPC_OFFSET, padded(upc, UPC_SIZE)));
+    }
+    for &(track_number, ref isrc) in &edit.isrcs {
+        let isrc = if isrc.is_empty() {
+            String::new()
+        } else {
+            format!("{:#}", Isrc::parse(isrc)?)
+        };
+        let index = usize::from(track_number.wrapping_sub(first_track));
+        if track_number < first_track || index >= nb_tracks {
+            return Err(NrgError::TrackNotFound(track_number));
+        }
+        let offset = DAOX_HEADER_SIZE + index * DAOX_TRACK_SIZE;
+        fields.push((offset, padded(&isrc, ISRC_SIZE)));
+    }
+
+    for (offset, bytes) in fields {
+        debug!("Writing {:?} at DAOX offset {}", bytes, offset);
+        fd.seek(SeekFrom::Start(chunk.payload_offset() + offset as u64))?;
+        fd.write_all(&bytes)?;
+    }
+    fd.flush()?;
+    Ok(())
+}
+
+
+/// Adds the CD-Text `cd_text` to the NRG image `fd`, in place.
+///
+/// The CDTX chunk is inserted after the CUEX and DAOX chunks. The image must
+/// not hold CD-Text already, and `cd_text` must describe all its tracks;
+/// nothing is written otherwise.
+pub fn add_cd_text<F: Read + Write + Seek>(fd: &mut F, cd_text: &NrgCdText)
+                                           -> Result<(), NrgError> {
+    let metadata = metadata::read_nrg_metadata(fd)?;
+    let chunks = raw::read_raw_chunks(fd)?;
+    if chunks.iter().any(|chunk| chunk.id == "CDTX") {
+        return Err(NrgError::NrgFormat(
+            "The image already holds CD-Text".to_string()));
+    }
+    if cd_text.is_empty() {
+        return Err(NrgError::NrgFormat("No CD-Text to add".to_string()));
+    }
+    let nb_tracks = metadata.tracks().count();
+    if cd_text.tracks.len() != nb_tracks {
+        return Err(NrgError::NrgFormat(format!(
+            "CD-Text of {} tracks for an image of {} tracks",
+            cd_text.tracks.len(), nb_tracks)));
+    }
+    let first_track = metadata.tracks().next().map_or(1, |track| track.number);
+    let packs = cd_text.to_packs(first_track)?;
+
+    // Write the CDTX chunk in place of the chunk following CUEX and DAOX,
+    // then the rest of the chunks (including END!) and the footer
+    let index = chunks.iter()
+        .rposition(|chunk| chunk.id == "CUEX" || chunk.id == "DAOX")
+        .map_or(0, |index| index + 1);
+    debug!("Writing the CDTX chunk at offset {}", chunks[index].offset);
+    fd.seek(SeekFrom::Start(chunks[index].offset))?;
+    writer::write_chunk(fd, "CDTX", &packs)?;
+    for chunk in &chunks[index..] {
+        writer::write_chunk(fd, &chunk.id, &chunk.payload)?;
+    }
+    writer::write_footer(fd, metadata.chunk_offset())?;
+    fd.flush()?;
+    Ok(())
+}
@@ -0,0 +1,156 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! De-emphasis of the audio tracks recorded with pre-emphasis.
+//!
+//! The treble of such tracks is boosted by the standard 50/15 µs
+//! pre-emphasis, which players are expected to undo on playback. The
+//! `DeEmphasisWriter` applies the inverse filter to 16-bit little-endian
+//! stereo samples at 44.1 kHz, i.e. CD audio.
+
+use std::io::{self, Write};
+
+use ::raw_audio::{SAMPLE_RATE, SAMPLE_SIZE};
+
+/// Time constants of the CD pre-emphasis, in seconds.
+const TAU_POLE: f64 = 50e-6;
+const TAU_ZERO: f64 = 15e-6;
+
+
+/// State of the filter for one channel: the previous input and output
+/// samples.
+#[derive(Clone, Copy, Debug)]
+struct ChannelState {
+    x1: f64,
+    y1: f64,
+}
+
+
+/// Writer applying the de-emphasis filter to the CD audio written through
+/// it.
+///
+/// The filter is a first-order IIR filter, obtained from the analog filter
+/// (1 + 15 µs·s) / (1 + 50 µs·s) by the bilinear transform: it leaves the
+/// low frequencies unchanged and attenuates the high frequencies by up to
+/// 10.5 dB.
+#[derive(Debug)]
+pub struct DeEmphasisWriter<W: Write> {
+    inner: W,
+    enabled: bool,
+    b0: f64,
+    b1: f64,
+    a1: f64,
+    state: Option<[ChannelState; 2]>,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> DeEmphasisWriter<W> {
+    pub fn new(inner: W) -> DeEmphasisWriter<W> {
+        let k = 2.0 * f64::from(SAMPLE_RATE);
+        let norm = 1.0 + TAU_POLE * k;
+        DeEmphasisWriter {
+            inner,
+            enabled: true,
+            b0: (1.0 + TAU_ZERO * k) / norm,
+            b1: (1.0 - TAU_ZERO * k) / norm,
+            a1: (1.0 - TAU_POLE * k) / norm,
+            state: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Enables or disables the filter; the data written while it is
+    /// disabled is passed through unchanged.
+    ///
+    /// The filter restarts from the first sample written after it is
+    /// enabled again.
+    pub fn set_enabled(&mut self, enabled: bool) -> io::Result<()> {
+        if enabled == self.enabled {
+            return Ok(());
+        }
+        self.write_pending()?;
+        self.enabled = enabled;
+        self.state = None;
+        Ok(())
+    }
+
+    /// Writes the last incomplete sample unchanged, if any, and returns the
+    /// underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_pending()?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_pending(&mut self) -> io::Result<()> {
+        self.inner.write_all(&self.pending)?;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Filters the complete frames of `self.pending` into `out`, leaving the
+    /// last incomplete frame in `self.pending`.
+    fn filter_pending(&mut self, out: &mut Vec<u8>) {
+        let nb_frames = self.pending.len() / SAMPLE_SIZE;
+        let mut state = self.state;
+        for frame in self.pending[..nb_frames * SAMPLE_SIZE]
+            .chunks(SAMPLE_SIZE) {
+            let left = f64::from(i16::from_le_bytes([frame[0], frame[1]]));
+            let right = f64::from(i16::from_le_bytes([frame[2], frame[3]]));
+
+            // Start in the steady state of the first sample
+            let channels = state.get_or_insert([
+                ChannelState { x1: left, y1: left },
+                ChannelState { x1: right, y1: right },
+            ]);
+            for (channel, x) in channels.iter_mut().zip([left, right]) {
+                let y = self.b0 * x + self.b1 * channel.x1
+                    - self.a1 * channel.y1;
+                channel.x1 = x;
+                channel.y1 = y;
+                let sample = y.round()
+                    .clamp(f64::from(i16::MIN), f64::from(i16::MAX)) as i16;
+                out.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+        self.state = state;
+        self.pending.drain(..nb_frames * SAMPLE_SIZE);
+    }
+}
+
+
+impl<W: Write> Write for DeEmphasisWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.enabled {
+            return self.inner.write(buf);
+        }
+        self.pending.extend_from_slice(buf);
+        let mut out = Vec::with_capacity(self.pending.len());
+        self.filter_pending(&mut out);
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
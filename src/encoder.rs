@@ -0,0 +1,127 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to encode the extracted audio with an external program, such as
+//! `flac`, `lame` or `opusenc`, so that any format can be produced without
+//! linking codec libraries.
+
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+
+use ::error::NrgError;
+
+/// Placeholder replaced by the path of the output file in the arguments.
+const OUTPUT_PLACEHOLDER: &str = "{out}";
+
+
+/// An external encoder, run once per output file with the audio streamed to
+/// its standard input as a WAV file.
+///
+/// The command is split on whitespace, without shell interpretation, and
+/// `{out}` is replaced by the path of the output file in its arguments:
+///
+/// ```
+/// use nrgrip::encoder::ExternalEncoder;
+///
+/// let encoder = ExternalEncoder::parse("lame -V2 - {out}")
+///     .unwrap()
+///     .extension("mp3");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternalEncoder {
+    program: String,
+    args: Vec<String>,
+    extension: String,
+}
+
+impl ExternalEncoder {
+    /// Parses `command`, which must contain `{out}`.
+    ///
+    /// The extension of the output files is the name of the program (e.g.
+    /// "flac" for `flac -s -o {out} -`), unless set by `extension()`.
+    pub fn parse(command: &str) -> Result<ExternalEncoder, NrgError> {
+        let mut words = command.split_whitespace().map(str::to_string);
+        let program = words.next().ok_or_else(|| NrgError::Encoder(
+            "Empty encoder command".to_string()))?;
+        let args: Vec<String> = words.collect();
+        if !args.iter().any(|arg| arg.contains(OUTPUT_PLACEHOLDER)) {
+            return Err(NrgError::Encoder(format!(
+                "The encoder command must contain {}", OUTPUT_PLACEHOLDER)));
+        }
+        let extension = Path::new(&program).file_stem()
+            .map_or_else(String::new,
+                         |stem| stem.to_string_lossy().into_owned());
+        Ok(ExternalEncoder { program, args, extension })
+    }
+
+    /// Sets the extension of the output files.
+    pub fn extension(mut self, extension: &str) -> ExternalEncoder {
+        self.extension = extension.to_string();
+        self
+    }
+
+    /// Returns the extension of the output files.
+    pub fn get_extension(&self) -> &str {
+        &self.extension
+    }
+
+    /// Returns the command, its words separated by single spaces.
+    pub fn command(&self) -> String {
+        let mut command = self.program.clone();
+        for arg in &self.args {
+            command.push(' ');
+            command.push_str(arg);
+        }
+        command
+    }
+
+    /// Starts the encoder writing to `out_path`, with a pipe to its standard
+    /// input.
+    ///
+    /// Its standard output is discarded, and its standard error inherited.
+    pub fn spawn(&self, out_path: &str) -> Result<Child, NrgError> {
+        let args = self.args.iter()
+            .map(|arg| arg.replace(OUTPUT_PLACEHOLDER, out_path));
+        info!("Running encoder \"{}\" to write \"{}\"", self.program,
+              out_path);
+        Command::new(&self.program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|err| NrgError::Encoder(format!(
+                "Can't run \"{}\": {}", self.program, err)))
+    }
+
+    /// Waits for the encoder `child`, started by `spawn()`, to exit, and
+    /// checks that it succeeded.
+    ///
+    /// Its standard input must be closed beforehand.
+    pub fn wait(&self, mut child: Child) -> Result<(), NrgError> {
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(NrgError::Encoder(format!(
+                "\"{}\" failed ({})", self.program, status)));
+        }
+        Ok(())
+    }
+}
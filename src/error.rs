@@ -28,67 +28,236 @@ use std::fmt;
 use std::io;
 
 
+/// Error returned by NRGrip.
+///
+/// The enum is non-exhaustive, so that new variants can be added without
+/// breaking the matches of its users. The underlying error, if any, is
+/// returned by `source()`.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum NrgError {
     Io(io::Error),
     String(ffi::IntoStringError),
     NrgFormat(String),
+    NotNrg(String),
+    UnsupportedVersion(u8),
     NrgChunkId(String),
+    NrgChunkSize(String, u32),
     NoNrgCue,
     FileName(String),
     AudioReadError,
     AudioWriteError,
+    AudioLength { expected: u64, copied: u64 },
+    TrackNotFound(u8),
+    SectorNotFound(i32),
+    AudioDevice(String),
+    CueSheet(String),
+    WavFormat(String),
+    Online(String),
+    Encoder(String),
+    /// Error of a library used for the online lookups or the encoding,
+    /// returned by `source()`; `context` tells what failed, e.g. "Online
+    /// lookup error".
+    Library {
+        context: &'static str,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    Cancelled,
+    Anomalies(Vec<String>),
+    Context(Box<NrgErrorContext>),
+}
+
+impl NrgError {
+    /// Wraps this error with the context in which it occurred.
+    ///
+    /// - `chunk_id` is the ID of the chunk being parsed, if any.
+    /// - `offset` is the offset in the image file at which the failing
+    ///   operation started.
+    /// - `operation` describes the failing operation, e.g. "parsing DAOX
+    ///   track 3".
+    ///
+    /// An error that already has a context is returned unchanged, so that the
+    /// innermost (most precise) context is kept.
+    pub fn context(self, chunk_id: Option<&str>, offset: u64, operation: String)
+                   -> NrgError {
+        if let NrgError::Context(_) = self {
+            return self;
+        }
+        NrgError::Context(Box::new(NrgErrorContext {
+            chunk_id: chunk_id.map(str::to_string),
+            offset,
+            operation,
+            error: self,
+        }))
+    }
+
+    /// Returns the category of this error, or of the error wrapped by its
+    /// context.
+    pub fn kind(&self) -> NrgErrorKind {
+        match *self {
+            NrgError::Context(ref context) => context.error.kind(),
+            // A short read means that the image is truncated
+            NrgError::Io(ref err)
+                if err.kind() == io::ErrorKind::UnexpectedEof =>
+                NrgErrorKind::Format,
+            NrgError::Io(_) | NrgError::AudioDevice(_) | NrgError::Online(_)
+                | NrgError::Encoder(_) | NrgError::Library { .. } =>
+                NrgErrorKind::Io,
+            NrgError::NotNrg(_) => NrgErrorKind::NotNrg,
+            NrgError::UnsupportedVersion(_) =>
+                NrgErrorKind::UnsupportedVersion,
+            NrgError::AudioLength { .. } => NrgErrorKind::Partial,
+            NrgError::Cancelled => NrgErrorKind::Cancelled,
+            NrgError::FileName(_) | NrgError::TrackNotFound(_) =>
+                NrgErrorKind::Usage,
+            _ => NrgErrorKind::Format,
+        }
+    }
+
+    /// Returns the context of this error, if any.
+    pub fn get_context(&self) -> Option<&NrgErrorContext> {
+        match *self {
+            NrgError::Context(ref context) => Some(context),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for NrgError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            NrgError::Io(ref err)
+                if err.kind() == io::ErrorKind::UnexpectedEof =>
+                write!(f, "short read"),
             NrgError::Io(ref err) => err.fmt(f),
             NrgError::String(ref err) => err.fmt(f),
             NrgError::NrgFormat(ref err) =>
                 write!(f, "NRG format error: {}", err),
+            NrgError::NotNrg(ref err) =>
+                write!(f, "Not an NRG image: {}", err),
+            NrgError::UnsupportedVersion(version) =>
+                write!(f, "NRG v{} format is not handled", version),
             NrgError::NrgChunkId(ref err) =>
                 write!(f, "NRG chunk ID unknown: {}", err),
+            NrgError::NrgChunkSize(ref id, size) =>
+                write!(f, "NRG chunk size invalid: {} bytes for {}", size, id),
             NrgError::NoNrgCue => write!(f, "NRG cue sheet chunk absent"),
             NrgError::FileName(ref err) =>
                 write!(f, "Invalid file name: {}", err),
             NrgError::AudioReadError => write!(f, "Error reading raw audio"),
             NrgError::AudioWriteError => write!(f, "Error writing raw audio"),
+            NrgError::AudioLength { expected, copied } =>
+                write!(f, "Raw audio length mismatch: {} bytes expected, \
+                           {} bytes copied", expected, copied),
+            NrgError::TrackNotFound(number) =>
+                write!(f, "Track {} not found in the image", number),
+            NrgError::SectorNotFound(lba) =>
+                write!(f, "Sector {} not found in the image", lba),
+            NrgError::AudioDevice(ref err) =>
+                write!(f, "Audio device error: {}", err),
+            NrgError::CueSheet(ref err) =>
+                write!(f, "Cue sheet error: {}", err),
+            NrgError::WavFormat(ref err) =>
+                write!(f, "WAV format error: {}", err),
+            NrgError::Online(ref err) =>
+                write!(f, "Online lookup error: {}", err),
+            NrgError::Encoder(ref err) =>
+                write!(f, "Encoder error: {}", err),
+            NrgError::Library { context, ref source } =>
+                write!(f, "{}: {}", context, source),
+            NrgError::Cancelled => write!(f, "Operation cancelled"),
+            NrgError::Anomalies(ref anomalies) =>
+                write!(f, "Questionable image rejected in strict mode: {}",
+                       anomalies.join("; ")),
+            NrgError::Context(ref context) => context.fmt(f),
         }
     }
 }
 
 impl Error for NrgError {
-    fn description(&self) -> &str {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match *self {
-            NrgError::Io(ref err) => err.description(),
-            NrgError::String(ref err) => err.description(),
-            NrgError::NrgFormat(_) => "NRG format",
-            NrgError::NrgChunkId(_) => "NRG chunk ID",
-            NrgError::NoNrgCue => "No NRG cue",
-            NrgError::FileName(_) => "File name",
-            NrgError::AudioReadError => "Audio read error",
-            NrgError::AudioWriteError => "Audio write error",
+            NrgError::Io(ref err) => Some(err),
+            NrgError::String(ref err) => Some(err),
+            NrgError::Library { ref source, .. } => Some(&**source),
+            NrgError::Context(ref context) => Some(&context.error),
+            NrgError::NrgFormat(_) | NrgError::NotNrg(_)
+                | NrgError::UnsupportedVersion(_) | NrgError::NrgChunkId(_)
+                | NrgError::NrgChunkSize(..) | NrgError::NoNrgCue
+                | NrgError::FileName(_) | NrgError::AudioReadError
+                | NrgError::AudioWriteError | NrgError::AudioLength { .. }
+                | NrgError::TrackNotFound(_) | NrgError::SectorNotFound(_)
+                | NrgError::AudioDevice(_) | NrgError::CueSheet(_)
+                | NrgError::WavFormat(_) | NrgError::Online(_)
+                | NrgError::Encoder(_) | NrgError::Cancelled
+                | NrgError::Anomalies(_) => None,
         }
     }
+}
 
-    fn cause(&self) -> Option<&Error> {
-        match *self {
-            NrgError::Io(ref err) => Some(err),
-            NrgError::String(ref err) => Some(err),
-            NrgError::NrgFormat(_) => None,
-            NrgError::NrgChunkId(_) => None,
-            NrgError::NoNrgCue => None,
-            NrgError::FileName(_) => None,
-            NrgError::AudioReadError => None,
-            NrgError::AudioWriteError => None,
+
+/// Category of an `NrgError`, as returned by `NrgError::kind()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NrgErrorKind {
+    /// Invalid request, e.g. a track absent from the image or an invalid
+    /// file name.
+    Usage,
+    /// Error reading or writing a file, using the audio device, running an
+    /// encoder or querying an online database.
+    Io,
+    /// The file is not an NRG image.
+    NotNrg,
+    /// The NRG image is in a version of the format that isn't handled.
+    UnsupportedVersion,
+    /// Invalid, truncated or unsupported NRG image, cue sheet or WAV file.
+    Format,
+    /// Audio data missing from the image.
+    Partial,
+    /// The operation was cancelled through its `CancellationToken`.
+    Cancelled,
+}
+
+impl NrgErrorKind {
+    /// Returns the exit status of the command line for this category of
+    /// errors, so that the scripts can tell the failures apart.
+    pub const fn exit_code(self) -> i32 {
+        match self {
+            NrgErrorKind::Usage => 1,
+            NrgErrorKind::NotNrg => 2,
+            NrgErrorKind::UnsupportedVersion => 3,
+            NrgErrorKind::Io => 4,
+            NrgErrorKind::Partial => 5,
+            NrgErrorKind::Format => 6,
+            // 128 + SIGINT, as the shells report an interruption
+            NrgErrorKind::Cancelled => 130,
         }
     }
 }
 
+
+/// Context in which an `NrgError` occurred.
+#[derive(Debug)]
+pub struct NrgErrorContext {
+    pub chunk_id: Option<String>,
+    pub offset: u64,
+    pub operation: String,
+    pub error: NrgError,
+}
+
+impl fmt::Display for NrgErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset 0x{:X} while {}",
+               self.error, self.offset, self.operation)
+    }
+}
+
+/// I/O errors, including the errors of the writers (encoders, pipeline
+/// stages), which carry an `NrgError` through their `io::Error`: the latter
+/// is returned as is.
 impl From<io::Error> for NrgError {
     fn from(err: io::Error) -> NrgError {
-        NrgError::Io(err)
+        err.downcast::<NrgError>().unwrap_or_else(NrgError::Io)
     }
 }
 
@@ -97,3 +266,43 @@ impl From<ffi::IntoStringError> for NrgError {
         NrgError::String(err)
     }
 }
+
+/// Errors of a buffered writer flushed by `into_inner()`.
+impl<W> From<io::IntoInnerError<W>> for NrgError {
+    fn from(err: io::IntoInnerError<W>) -> NrgError {
+        NrgError::Io(err.into_error())
+    }
+}
+
+/// Errors of the Opus encoder.
+#[cfg(feature = "opus")]
+impl From<audiopus::Error> for NrgError {
+    fn from(err: audiopus::Error) -> NrgError {
+        NrgError::Library {
+            context: "Encoder error: Opus",
+            source: Box::new(err),
+        }
+    }
+}
+
+/// Errors of the HTTP requests to the online databases.
+#[cfg(feature = "online")]
+impl From<ureq::Error> for NrgError {
+    fn from(err: ureq::Error) -> NrgError {
+        NrgError::Library {
+            context: "Online lookup error",
+            source: Box::new(err),
+        }
+    }
+}
+
+/// Errors of the JSON responses of the online databases.
+#[cfg(feature = "online")]
+impl From<serde_json::Error> for NrgError {
+    fn from(err: serde_json::Error) -> NrgError {
+        NrgError::Library {
+            context: "Online lookup error: invalid response",
+            source: Box::new(err),
+        }
+    }
+}
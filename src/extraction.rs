@@ -0,0 +1,1627 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to extract the audio data from an NRG image file, as configured by
+//! a set of `ExtractionOptions`.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::ChildStdin;
+use std::sync::Arc;
+use std::thread;
+
+use ::cancel::CancellationToken;
+use ::convert;
+use ::checksums::{Crc32, Crc32Writer};
+use ::emphasis::DeEmphasisWriter;
+use ::encoder::ExternalEncoder;
+use ::error::{NrgError, NrgErrorKind};
+use ::flac::{FlacCueTrack, FlacWriter};
+use ::hidden::NrgHiddenData;
+use ::json::JsonValue;
+use ::loudness::{LoudnessMeter, NrgLoudness};
+use ::metadata::metadata::NrgMetadata;
+use ::metadata::track::Track;
+use ::metrics::{Metrics, MetricsHandle};
+use ::pipeline::PipeWriter;
+use ::raw_audio::{self, CopyControl, RAW_SEC_SIZE, RAW96_SEC_SIZE,
+                  SAMPLE_SIZE};
+use ::unicode;
+use ::wav;
+#[cfg(feature = "opus")]
+use ::opus::{self, OpusWriter};
+#[cfg(feature = "mp3")]
+use ::mp3::{Mp3Bitrate, Mp3Writer};
+#[cfg(feature = "vorbis")]
+use ::vorbis::{self, VorbisWriter};
+#[cfg(any(feature = "opus", feature = "mp3", feature = "vorbis"))]
+use ::tags::NrgTags;
+
+
+/// Format of the extracted audio files.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AudioFormat {
+    /// Raw audio data, as stored in the image.
+    Raw,
+    /// WAV file (16-bit, 44.1 kHz PCM).
+    Wav,
+    /// FLAC file, with a seek table; a single file for the whole disc
+    /// embeds the cue sheet in a CUESHEET block.
+    Flac,
+    /// Ogg Opus file, resampled to 48 kHz, holding the tags of the track or
+    /// the disc.
+    #[cfg(feature = "opus")]
+    Opus,
+    /// MP3 file, holding the tags of the track or the disc in an ID3v2 tag.
+    #[cfg(feature = "mp3")]
+    Mp3,
+    /// Ogg Vorbis file, holding the tags of the track or the disc.
+    #[cfg(feature = "vorbis")]
+    Vorbis,
+}
+
+impl AudioFormat {
+    /// Returns the file name extension of this format.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            AudioFormat::Raw => "raw",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+            #[cfg(feature = "opus")]
+            AudioFormat::Opus => "opus",
+            #[cfg(feature = "mp3")]
+            AudioFormat::Mp3 => "mp3",
+            #[cfg(feature = "vorbis")]
+            AudioFormat::Vorbis => "ogg",
+        }
+    }
+}
+
+
+/// Placement of the tracks' pre-gaps when the tracks are split.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GapMode {
+    /// Each pre-gap is appended to the end of the previous track.
+    Append,
+    /// Each pre-gap is prepended to the beginning of its own track.
+    Prepend,
+    /// The pre-gaps are left out: each track is cut exactly at its index #1
+    /// and its end, as most rippers do.
+    Discard,
+}
+
+
+/// Channels of the extracted audio.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChannelMode {
+    /// The stereo channels, as stored in the image.
+    Stereo,
+    /// The stereo channels, left and right swapped.
+    Swapped,
+    /// A single channel, the average of the left and right ones.
+    Mono,
+}
+
+impl ChannelMode {
+    /// Returns the number of channels of the extracted audio.
+    pub fn channels(&self) -> u16 {
+        match *self {
+            ChannelMode::Stereo | ChannelMode::Swapped => 2,
+            ChannelMode::Mono => 1,
+        }
+    }
+}
+
+
+/// Options controlling `extract()`, built with chained setters:
+///
+/// ```
+/// use nrgrip::extraction::{AudioFormat, ExtractionOptions};
+///
+/// let options = ExtractionOptions::new()
+///     .split_tracks(true)
+///     .format(AudioFormat::Wav)
+///     .checksums(true);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ExtractionOptions {
+    strip_subchannel: bool,
+    split_tracks: bool,
+    format: AudioFormat,
+    gap_mode: GapMode,
+    channel_mode: ChannelMode,
+    offset: i32,
+    name_template: Option<String>,
+    ascii_names: bool,
+    checksums: bool,
+    verify: bool,
+    atomic: bool,
+    ignore_errors: bool,
+    de_emphasis: bool,
+    loudness: bool,
+    buffer_size: usize,
+    rate_limit: u64,
+    sector_size: u16,
+    tracks: Option<Vec<u8>>,
+    encoder: Option<ExternalEncoder>,
+    #[cfg(feature = "opus")]
+    opus_bitrate: u32,
+    #[cfg(feature = "mp3")]
+    mp3_bitrate: Mp3Bitrate,
+    #[cfg(feature = "vorbis")]
+    vorbis_quality: f32,
+    output_dir: Option<PathBuf>,
+    output_path: Option<PathBuf>,
+    cancellation: CancellationToken,
+    metrics: MetricsHandle,
+}
+
+impl ExtractionOptions {
+    /// Returns the default options: the whole audio data is extracted to a
+    /// single raw file, with the sub-channel stripped, and no checksums.
+    pub fn new() -> ExtractionOptions {
+        ExtractionOptions {
+            strip_subchannel: true,
+            split_tracks: false,
+            format: AudioFormat::Raw,
+            gap_mode: GapMode::Append,
+            channel_mode: ChannelMode::Stereo,
+            offset: 0,
+            name_template: None,
+            ascii_names: false,
+            checksums: false,
+            verify: false,
+            atomic: true,
+            ignore_errors: false,
+            de_emphasis: false,
+            loudness: false,
+            buffer_size: raw_audio::DEFAULT_BUFFER_SIZE,
+            rate_limit: 0,
+            sector_size: RAW_SEC_SIZE,
+            tracks: None,
+            encoder: None,
+            #[cfg(feature = "opus")]
+            opus_bitrate: opus::DEFAULT_BITRATE,
+            #[cfg(feature = "mp3")]
+            mp3_bitrate: Mp3Bitrate::default(),
+            #[cfg(feature = "vorbis")]
+            vorbis_quality: vorbis::DEFAULT_QUALITY,
+            output_dir: None,
+            output_path: None,
+            cancellation: CancellationToken::new(),
+            metrics: MetricsHandle::default(),
+        }
+    }
+
+    /// Strips the 96-byte sub-channel from the sectors, if present.
+    pub fn strip_subchannel(mut self, strip: bool) -> ExtractionOptions {
+        self.strip_subchannel = strip;
+        self
+    }
+
+    /// Writes one file per track instead of a single file.
+    pub fn split_tracks(mut self, split: bool) -> ExtractionOptions {
+        self.split_tracks = split;
+        self
+    }
+
+    /// Sets the format of the output files.
+    pub fn format(mut self, format: AudioFormat) -> ExtractionOptions {
+        self.format = format;
+        self
+    }
+
+    /// Sets the placement of the pre-gaps when splitting the tracks.
+    pub fn gap_mode(mut self, gap_mode: GapMode) -> ExtractionOptions {
+        self.gap_mode = gap_mode;
+        self
+    }
+
+    /// Sets the channels of the extracted audio.
+    ///
+    /// Other modes than stereo require the audio sectors to be 2352-byte
+    /// long (possibly after stripping the sub-channel).
+    pub fn channel_mode(mut self, mode: ChannelMode) -> ExtractionOptions {
+        self.channel_mode = mode;
+        self
+    }
+
+    /// Shifts the extracted audio by `offset` samples.
+    ///
+    /// With a positive offset, the audio is read `offset` samples later than
+    /// the positions indicated by the metadata; with a negative offset, it is
+    /// read earlier. Samples outside of the image's audio data are replaced
+    /// with silence. This requires the audio sectors to be 2352-byte long
+    /// (possibly after stripping the sub-channel).
+    pub fn offset(mut self, offset: i32) -> ExtractionOptions {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets the name of the output files, without extension.
+    ///
+    /// In `template`, `{image}` is replaced with the image's base name
+    /// (without extension), and `{track}` with the two-digit track number;
+    /// the latter is mandatory when the tracks are split. The default is
+    /// `{image}`, or `{image}-{track}` when splitting.
+    pub fn name_template(mut self, template: &str) -> ExtractionOptions {
+        self.name_template = Some(template.to_string());
+        self
+    }
+
+    /// Transliterates the titles to ASCII in the file names, with
+    /// `nrgrip::unicode::to_ascii()`.
+    pub fn ascii_names(mut self, ascii: bool) -> ExtractionOptions {
+        self.ascii_names = ascii;
+        self
+    }
+
+    /// Computes the CRC-32 of the audio data written to each file.
+    pub fn checksums(mut self, checksums: bool) -> ExtractionOptions {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Reads each file back once written, and checks that it holds the
+    /// bytes written to it, so that the write errors of flaky drives don't
+    /// go unnoticed; `ExtractedFile::verified` tells the result.
+    ///
+    /// The file is synced to the disk before being read back. This requires
+    /// the raw or WAV format, without an external encoder. When the files
+    /// are written atomically (see `atomic()`), a file that doesn't match is
+    /// removed instead of being renamed, and the extraction fails.
+    pub fn verify(mut self, verify: bool) -> ExtractionOptions {
+        self.verify = verify;
+        self
+    }
+
+    /// Writes each file under a temporary name in the same directory, and
+    /// renames it once complete and synced to the disk, so that a file with
+    /// its final name is never truncated. This is the default; without it,
+    /// the files are written directly, and an interrupted extraction leaves
+    /// truncated files behind.
+    pub fn atomic(mut self, atomic: bool) -> ExtractionOptions {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Replaces the sectors that can't be read with silence instead of
+    /// failing, so that most of a partially unreadable image can still be
+    /// salvaged. The addresses of these sectors are logged, and listed in
+    /// `ExtractedFile::bad_sectors`.
+    pub fn ignore_errors(mut self, ignore: bool) -> ExtractionOptions {
+        self.ignore_errors = ignore;
+        self
+    }
+
+    /// Applies the de-emphasis filter to the tracks flagged with
+    /// pre-emphasis.
+    ///
+    /// This requires the audio sectors to be 2352-byte long (possibly after
+    /// stripping the sub-channel).
+    pub fn de_emphasis(mut self, de_emphasis: bool) -> ExtractionOptions {
+        self.de_emphasis = de_emphasis;
+        self
+    }
+
+    /// Measures the loudness and the peak of the audio data written to each
+    /// file.
+    ///
+    /// This requires the audio sectors to be 2352-byte long (possibly after
+    /// stripping the sub-channel).
+    pub fn loudness(mut self, loudness: bool) -> ExtractionOptions {
+        self.loudness = loudness;
+        self
+    }
+
+    /// Sets the size of the buffer used to copy the audio data, in bytes.
+    ///
+    /// Larger buffers mean fewer system calls, which speeds up the copy on
+    /// network file systems; smaller ones suit the devices with little
+    /// memory. Sizes below one sector are raised to one sector. The default
+    /// is `nrgrip::raw_audio::DEFAULT_BUFFER_SIZE`.
+    pub fn buffer_size(mut self, size: usize) -> ExtractionOptions {
+        self.buffer_size = size.max(usize::from(RAW_SEC_SIZE));
+        self
+    }
+
+    /// Limits the speed at which the image is read, in bytes per second, so
+    /// that an extraction from a NAS or a shared disk leaves some bandwidth
+    /// to the other programs. The default, 0, means no limit.
+    pub fn rate_limit(mut self, bytes_per_second: u64) -> ExtractionOptions {
+        self.rate_limit = bytes_per_second;
+        self
+    }
+
+    /// Returns a control for the copy loops of an image made of sectors of
+    /// `sector_size` bytes, following these options.
+    fn copy_control(&self, sector_size: u64) -> CopyControl {
+        let control = CopyControl::new(self.buffer_size, &self.cancellation,
+                                       self.rate_limit)
+            .metrics(&self.metrics);
+        if self.ignore_errors {
+            control.ignore_errors(sector_size)
+        } else {
+            control
+        }
+    }
+
+    /// Sets the size of the sectors carved by `extract_range()`, in bytes.
+    /// The default is 2352 bytes; `extract()` ignores it, as it reads the
+    /// sector size from the metadata.
+    pub fn sector_size(mut self, size: u16) -> ExtractionOptions {
+        self.sector_size = size;
+        self
+    }
+
+    /// Extracts only the tracks numbered `tracks`, instead of the whole audio
+    /// data.
+    ///
+    /// Each track is extracted with its pre-gap placed according to the gap
+    /// mode, as when splitting. Unless the tracks are split, the selected
+    /// tracks are concatenated in a single file, in the image's order.
+    pub fn tracks(mut self, tracks: &[u8]) -> ExtractionOptions {
+        self.tracks = Some(tracks.to_vec());
+        self
+    }
+
+    /// Encodes the output files with `encoder`, which receives the audio as
+    /// a WAV stream; the format set by `format()` is then ignored, and the
+    /// files get the encoder's extension.
+    pub fn encoder(mut self, encoder: ExternalEncoder) -> ExtractionOptions {
+        self.encoder = Some(encoder);
+        self
+    }
+
+    /// Sets the bitrate of the Opus files, in kbit/s. The default is
+    /// `nrgrip::opus::DEFAULT_BITRATE`.
+    #[cfg(feature = "opus")]
+    pub fn opus_bitrate(mut self, bitrate: u32) -> ExtractionOptions {
+        self.opus_bitrate = bitrate;
+        self
+    }
+
+    /// Sets the bitrate of the MP3 files. The default is the variable
+    /// bitrate of quality 2.
+    #[cfg(feature = "mp3")]
+    pub fn mp3_bitrate(mut self, bitrate: Mp3Bitrate) -> ExtractionOptions {
+        self.mp3_bitrate = bitrate;
+        self
+    }
+
+    /// Sets the quality of the Vorbis files, from -1 to 10 as oggenc's `-q`
+    /// option. The default is `nrgrip::vorbis::DEFAULT_QUALITY`.
+    #[cfg(feature = "vorbis")]
+    pub fn vorbis_quality(mut self, quality: f32) -> ExtractionOptions {
+        self.vorbis_quality = quality;
+        self
+    }
+
+    /// Writes the output files in `dir` instead of the current directory.
+    pub fn output_dir(mut self, dir: &Path) -> ExtractionOptions {
+        self.output_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    /// Writes the audio data to `path`, instead of a name built from the name
+    /// template in the output directory.
+    ///
+    /// `path` must include the file's extension. This can't be used when the
+    /// tracks are split.
+    pub fn output_path(mut self, path: &Path) -> ExtractionOptions {
+        self.output_path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Aborts the extraction when `token` is cancelled: the copy stops at the
+    /// next buffer, the file being written is removed, and `extract()` or
+    /// `extract_range()` fails with `NrgError::Cancelled`.
+    pub fn cancellation(mut self, token: &CancellationToken)
+                        -> ExtractionOptions {
+        self.cancellation = token.clone();
+        self
+    }
+
+    /// Reports the statistics of `extract()` and `extract_range()` to
+    /// `metrics` as the copy progresses (see nrgrip::metrics).
+    pub fn metrics(mut self, metrics: Arc<dyn Metrics>) -> ExtractionOptions {
+        self.metrics = MetricsHandle::new(metrics);
+        self
+    }
+
+    /// Describes the options as a JSON object, for the provenance report
+    /// (see nrgrip::provenance).
+    ///
+    /// The output paths, the cancellation token and the metrics receiver are
+    /// left out.
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::object(vec![
+            ("strip_subchannel", self.strip_subchannel.into()),
+            ("split_tracks", self.split_tracks.into()),
+            ("format", self.format.extension().into()),
+            ("gap_mode", format!("{:?}", self.gap_mode).to_lowercase().into()),
+            ("channel_mode",
+             format!("{:?}", self.channel_mode).to_lowercase().into()),
+            ("offset", self.offset.into()),
+            ("name_template", self.name_template.clone().into()),
+            ("ascii_names", self.ascii_names.into()),
+            ("checksums", self.checksums.into()),
+            ("verify", self.verify.into()),
+            ("atomic", self.atomic.into()),
+            ("ignore_errors", self.ignore_errors.into()),
+            ("de_emphasis", self.de_emphasis.into()),
+            ("loudness", self.loudness.into()),
+            ("tracks", self.tracks.clone().into()),
+            ("encoder", self.encoder.as_ref()
+             .map(ExternalEncoder::command).into()),
+            ("bitrate", self.bitrate().into()),
+        ])
+    }
+
+    /// Describes the bitrate of the encoded output format, if any.
+    fn bitrate(&self) -> Option<String> {
+        #[cfg(feature = "opus")]
+        if self.format == AudioFormat::Opus {
+            return Some(format!("{} kbit/s", self.opus_bitrate));
+        }
+        #[cfg(feature = "mp3")]
+        if self.format == AudioFormat::Mp3 {
+            return Some(match self.mp3_bitrate {
+                Mp3Bitrate::Constant(kbps) => format!("{} kbit/s", kbps),
+                Mp3Bitrate::Variable(quality) => format!("V{}", quality),
+            });
+        }
+        #[cfg(feature = "vorbis")]
+        if self.format == AudioFormat::Vorbis {
+            return Some(format!("q{}", self.vorbis_quality));
+        }
+        None
+    }
+}
+
+impl Default for ExtractionOptions {
+    fn default() -> ExtractionOptions {
+        ExtractionOptions::new()
+    }
+}
+
+
+/// Description of a file written by `extract()`.
+#[derive(Clone, Debug)]
+pub struct ExtractedFile {
+    pub path: String,
+    pub track_number: Option<u8>,
+    pub audio_bytes: u64,
+    /// Byte ranges of the image the data was read from, in order.
+    pub source_ranges: Vec<Range<u64>>,
+    /// Number of sectors of the image the data was read from.
+    pub sectors: u64,
+    pub crc32: Option<u32>,
+    pub loudness: Option<NrgLoudness>,
+    /// Whether the file read back matched the data written, if verified.
+    pub verified: Option<bool>,
+    /// Addresses of the sectors that couldn't be read and were replaced by
+    /// silence (see ExtractionOptions::ignore_errors()).
+    pub bad_sectors: Vec<i32>,
+}
+
+impl fmt::Display for ExtractedFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\": {} Bytes of audio data", self.path,
+               self.audio_bytes)?;
+        if let Some(crc32) = self.crc32 {
+            write!(f, ", CRC32 {:08X}", crc32)?;
+        }
+        if let Some(ref loudness) = self.loudness {
+            write!(f, ", loudness {}", loudness)?;
+        }
+        match self.verified {
+            Some(true) => write!(f, ", verified")?,
+            Some(false) => write!(f, ", VERIFICATION FAILED")?,
+            None => (),
+        }
+        if !self.bad_sectors.is_empty() {
+            write!(f, ", {} unreadable sectors replaced by silence",
+                   self.bad_sectors.len())?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Extracts the audio data from an NRG image.
+///
+/// - `in_fd` is the handler to the NRG image file.
+/// - `img_path` is the name of the input NRG file.
+/// - `metadata` is the metadata extracted from `img_path` by nrgrip::metadata.
+/// - `options` controls what is extracted and how.
+///
+/// The audio data starts at the first track's index #1; the first track's
+/// pre-gap is never extracted. The output files are written in the options'
+/// output directory (the current directory by default), with names derived
+/// from `img_path`.
+///
+/// Each file is produced by a pipeline of three threads (see
+/// nrgrip::pipeline): the first one reads the image and applies the
+/// de-emphasis, the second one transforms the channels and computes the
+/// checksum and the loudness, and the last one writes the file.
+///
+/// Returns the list of files written.
+pub fn extract(in_fd: &mut File, img_path: &str, metadata: &NrgMetadata,
+               options: &ExtractionOptions)
+               -> Result<Vec<ExtractedFile>, NrgError> {
+    let _timer = options.metrics.start();
+    let tracks: Vec<Track> = metadata.tracks().collect();
+    let layout = AudioLayout::new(metadata, &tracks,
+                                  options.strip_subchannel)?;
+    let pcm = layout.audio_sector_size == u64::from(RAW_SEC_SIZE);
+    if options.offset != 0 && !pcm {
+        return Err(NrgError::NrgFormat(
+            "Offset correction requires 2352-byte audio sectors".to_string()));
+    }
+    if options.encoder.is_some() && !pcm {
+        return Err(NrgError::NrgFormat(
+            "Encoding requires 2352-byte audio sectors".to_string()));
+    }
+    if options.format != AudioFormat::Raw && !pcm {
+        return Err(NrgError::NrgFormat(format!(
+            "{} output requires 2352-byte audio sectors",
+            options.format.extension().to_uppercase())));
+    }
+    if options.channel_mode != ChannelMode::Stereo && !pcm {
+        return Err(NrgError::NrgFormat(
+            "Channel transforms require 2352-byte audio sectors".to_string()));
+    }
+    if options.loudness && !pcm {
+        return Err(NrgError::NrgFormat(
+            "Loudness measurement requires 2352-byte audio sectors"
+                .to_string()));
+    }
+    if options.de_emphasis && !pcm {
+        return Err(NrgError::NrgFormat(
+            "De-emphasis requires 2352-byte audio sectors".to_string()));
+    }
+    if options.verify && (options.encoder.is_some()
+                          || (options.format != AudioFormat::Raw
+                              && options.format != AudioFormat::Wav)) {
+        return Err(NrgError::NrgFormat(
+            "Verification requires raw or WAV output".to_string()));
+    }
+
+    // Parts of the audio stream to be de-emphasized
+    let mut emphasized = Vec::new();
+    for track in tracks.iter().filter(|track| track.has_pre_emphasis()) {
+        if options.tracks.as_ref()
+            .is_some_and(|selection| !selection.contains(&track.number)) {
+            continue;
+        }
+        if options.de_emphasis {
+            info!("Applying de-emphasis to track {}", track.number);
+            emphasized.push(Segment {
+                start: layout.to_audio(track.index0),
+                end: layout.to_audio(track.track_end),
+            });
+        } else {
+            info!("Track {} has pre-emphasis, extracted without \
+                   de-emphasis", track.number);
+        }
+    }
+
+    let shift = i64::from(options.offset) * SAMPLE_SIZE as i64;
+
+    enter_span!("nrg_extract", image = img_path);
+    let mut files = Vec::new();
+    for output in plan_outputs(metadata, &tracks, &layout, options)? {
+        options.cancellation.check()?;
+        let track = output.track_number
+            .and_then(|number| tracks.iter().find(|t| t.number == number));
+        let path = output_file_name(img_path, options, track)?;
+        let path = unique_path(path, &files);
+        let source_ranges: Vec<Range<u64>> = output.segments.iter()
+            .map(|segment| layout.to_file(segment.start)
+                 ..layout.to_file(segment.end))
+            .collect();
+        enter_span!("nrg_output", track = output.track_number,
+                    path = path.as_str(),
+                    offset = source_ranges.first().map(|range| range.start));
+        let channels = options.channel_mode.channels();
+        let audio_bytes = output.len() / 2 * u64::from(channels);
+        info!("Extracting {} bytes of audio data to \"{}\"",
+              audio_bytes, path);
+        let write_path = if options.atomic {
+            temporary_path(&path)
+        } else {
+            path.clone()
+        };
+
+        let mut child = None;
+        let file_writer = if let Some(ref encoder) = options.encoder {
+            let mut encoder_process = encoder.spawn(&write_path)?;
+            let stdin = encoder_process.stdin.take()
+                .ok_or(NrgError::AudioWriteError)?;
+            child = Some(encoder_process);
+            FileWriter::Encoder(stdin)
+        } else {
+            match options.format {
+                AudioFormat::Raw => FileWriter::Plain(
+                    Crc32Writer::new(File::create(&write_path)?)),
+                AudioFormat::Wav => {
+                    let mut out_fd =
+                        Crc32Writer::new(File::create(&write_path)?);
+                    out_fd.write_all(&wav::wav_header(audio_bytes,
+                                                      channels)?)?;
+                    FileWriter::Plain(out_fd)
+                },
+                AudioFormat::Flac => {
+                    // The whole disc, described by an embedded cue sheet
+                    let cue_tracks = if output.track_number.is_none()
+                        && options.tracks.is_none() {
+                        flac_cue_tracks(&tracks, &layout, &output, options)
+                    } else {
+                        Vec::new()
+                    };
+                    let samples = output.len() / SAMPLE_SIZE as u64;
+                    FileWriter::Flac(Box::new(FlacWriter::new(
+                        File::create(&write_path)?, channels, samples,
+                        &cue_tracks)?))
+                },
+                #[cfg(feature = "opus")]
+                AudioFormat::Opus => {
+                    let tags = NrgTags::from_metadata(metadata,
+                                                      output.track_number)?;
+                    FileWriter::Opus(Box::new(OpusWriter::new(
+                        File::create(&write_path)?, channels,
+                        options.opus_bitrate, &tags)?))
+                },
+                #[cfg(feature = "mp3")]
+                AudioFormat::Mp3 => {
+                    let tags = NrgTags::from_metadata(metadata,
+                                                      output.track_number)?;
+                    FileWriter::Mp3(Box::new(Mp3Writer::new(
+                        File::create(&write_path)?, channels,
+                        options.mp3_bitrate, &tags)?))
+                },
+                #[cfg(feature = "vorbis")]
+                AudioFormat::Vorbis => {
+                    let tags = NrgTags::from_metadata(metadata,
+                                                      output.track_number)?;
+                    FileWriter::Vorbis(Box::new(VorbisWriter::new(
+                        File::create(&write_path)?, channels,
+                        options.vorbis_quality, &tags)?))
+                },
+            }
+        };
+
+        let result = thread::scope(|scope| -> Result<_, NrgError> {
+            // Writing stage, including the encoding
+            let mut file_writer = PipeWriter::new(scope, file_writer);
+            if options.encoder.is_some() {
+                file_writer.write_all(&wav::wav_header(audio_bytes,
+                                                       channels)?)?;
+            }
+            // Transform stage: channels, checksum and loudness
+            let measuring_writer = MeasuringWriter {
+                inner: file_writer,
+                crc: options.checksums.then(Crc32::new),
+                meter: options.loudness.then(|| match options.channel_mode {
+                    ChannelMode::Mono => LoudnessMeter::mono(),
+                    _ => LoudnessMeter::new(),
+                }),
+            };
+            let channel_writer = ChannelWriter::new(measuring_writer,
+                                                    options.channel_mode);
+            let mut writer = PipeWriter::new(scope, channel_writer);
+            // Reading stage, including the de-emphasis
+            let mut control = options.copy_control(layout.sector_size);
+            copy_output(in_fd, &mut writer, &layout, &output, shift,
+                        &emphasized, &mut control)?;
+            debug!("Read {} bytes at {:.1} MB/s", control.bytes_read(),
+                   control.throughput() / 1e6);
+            trace_event!(debug, bytes_read = control.bytes_read(),
+                         throughput = control.throughput(),
+                         "Read the audio data");
+
+            let MeasuringWriter { inner: file_writer, crc, meter } =
+                writer.finish()?.finish()?;
+            let written_crc = file_writer.finish()?.finish()?;
+            Ok((crc, meter, written_crc, control.bad_sectors().to_vec()))
+        });
+
+        // The encoder's failure explains a broken pipe
+        if let (Some(encoder), Some(child)) = (&options.encoder, child) {
+            let status = encoder.wait(child);
+            let broken_pipe = match result {
+                Err(NrgError::Io(ref err)) =>
+                    err.kind() == io::ErrorKind::BrokenPipe,
+                _ => false,
+            };
+            if result.is_ok() || broken_pipe {
+                status?;
+            }
+        }
+        trace_error!(result, "Extraction failed");
+        // Don't leave a truncated file behind
+        let cancelled = result.as_ref()
+            .is_err_and(|err| err.kind() == NrgErrorKind::Cancelled);
+        if result.is_err() && (options.atomic || cancelled) {
+            info!("Extraction failed, removing \"{}\"", write_path);
+            if let Err(err) = fs::remove_file(&write_path) {
+                warn!("Can't remove \"{}\": {}", write_path, err);
+            }
+        }
+        let (crc, meter, written_crc, bad_offsets) = result?;
+        let bad_sectors: Vec<i32> = bad_offsets.iter()
+            .filter_map(|&offset| sector_lba(&tracks, offset))
+            .collect();
+        if !bad_sectors.is_empty() {
+            warn!("{} unreadable sectors replaced by silence in \"{}\": {}",
+                  bad_sectors.len(), path, convert::list_sectors(&bad_sectors));
+        }
+        if options.atomic || options.verify {
+            OpenOptions::new().write(true).open(&write_path)?.sync_all()?;
+        }
+        let verified = match written_crc {
+            Some(crc) if options.verify =>
+                Some(verify_file(&write_path, crc)?),
+            _ => None,
+        };
+        if options.atomic {
+            // A corrupted file must not get a complete-looking name
+            if verified == Some(false) {
+                info!("Verification failed, removing \"{}\"", write_path);
+                fs::remove_file(&write_path)?;
+                return Err(NrgError::Io(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("\"{}\" doesn't hold the data written to it",
+                            write_path))));
+            }
+            debug!("Renaming \"{}\" to \"{}\"", write_path, path);
+            fs::rename(&write_path, &path)?;
+        }
+
+        let sectors = source_ranges.iter()
+            .map(|range| (range.end - range.start) / layout.sector_size)
+            .sum();
+        files.push(ExtractedFile {
+            path,
+            track_number: output.track_number,
+            audio_bytes,
+            source_ranges,
+            sectors,
+            crc32: crc.map(|crc| crc.value()),
+            loudness: meter.map(|meter| meter.value()),
+            verified,
+            bad_sectors,
+        });
+    }
+
+    Ok(files)
+}
+
+
+/// Writer of an output file, encoding the audio data if needed.
+enum FileWriter {
+    /// Raw or WAV file, whose CRC-32 is computed to verify it.
+    Plain(Crc32Writer<File>),
+    Flac(Box<FlacWriter<File>>),
+    #[cfg(feature = "opus")]
+    Opus(Box<OpusWriter<File>>),
+    #[cfg(feature = "mp3")]
+    Mp3(Box<Mp3Writer<File>>),
+    #[cfg(feature = "vorbis")]
+    Vorbis(Box<VorbisWriter<File>>),
+    /// Standard input of an external encoder.
+    Encoder(ChildStdin),
+}
+
+impl FileWriter {
+    /// Completes the file; the encoder's input is closed.
+    ///
+    /// Returns the CRC-32 of the bytes written to a plain file.
+    fn finish(self) -> Result<Option<u32>, NrgError> {
+        match self {
+            FileWriter::Plain(writer) => return Ok(Some(writer.crc32())),
+            FileWriter::Flac(writer) => {
+                writer.finish()?;
+            },
+            #[cfg(feature = "opus")]
+            FileWriter::Opus(writer) => {
+                writer.finish()?;
+            },
+            #[cfg(feature = "mp3")]
+            FileWriter::Mp3(writer) => {
+                writer.finish()?;
+            },
+            #[cfg(feature = "vorbis")]
+            FileWriter::Vorbis(writer) => {
+                writer.finish()?;
+            },
+            FileWriter::Encoder(_) => (),
+        }
+        Ok(None)
+    }
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            FileWriter::Plain(ref mut out_fd) => out_fd.write(buf),
+            FileWriter::Flac(ref mut writer) => writer.write(buf),
+            #[cfg(feature = "opus")]
+            FileWriter::Opus(ref mut writer) => writer.write(buf),
+            #[cfg(feature = "mp3")]
+            FileWriter::Mp3(ref mut writer) => writer.write(buf),
+            #[cfg(feature = "vorbis")]
+            FileWriter::Vorbis(ref mut writer) => writer.write(buf),
+            FileWriter::Encoder(ref mut stdin) => stdin.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            FileWriter::Plain(ref mut out_fd) => out_fd.flush(),
+            FileWriter::Flac(ref mut writer) => writer.flush(),
+            #[cfg(feature = "opus")]
+            FileWriter::Opus(ref mut writer) => writer.flush(),
+            #[cfg(feature = "mp3")]
+            FileWriter::Mp3(ref mut writer) => writer.flush(),
+            #[cfg(feature = "vorbis")]
+            FileWriter::Vorbis(ref mut writer) => writer.flush(),
+            FileWriter::Encoder(ref mut stdin) => stdin.flush(),
+        }
+    }
+}
+
+
+/// Lists the tracks of `output`, the whole audio stream, for the cue sheet
+/// of a FLAC file.
+///
+/// The positions are counted in samples from the beginning of `output`; the
+/// first track's pre-gap is not part of it.
+fn flac_cue_tracks(tracks: &[Track], layout: &AudioLayout, output: &Output,
+                   options: &ExtractionOptions) -> Vec<FlacCueTrack> {
+    let start = output.segments.first().map_or(0, |segment| segment.start);
+    let end = start + output.len();
+    tracks.iter()
+        .filter(|track| layout.to_audio(track.index1) < end)
+        .map(|track| {
+            let index0 = layout.to_audio(track.index0);
+            let index1 = layout.to_audio(track.index1);
+            FlacCueTrack {
+                number: track.number,
+                isrc: track.isrc.clone(),
+                audio: !track.is_data(),
+                pre_emphasis: track.has_pre_emphasis()
+                    && !options.de_emphasis,
+                index0: (index0 >= start && index0 < index1)
+                    .then(|| (index0 - start) / SAMPLE_SIZE as u64),
+                index1: index1.saturating_sub(start) / SAMPLE_SIZE as u64,
+            }
+        })
+        .collect()
+}
+
+
+/// Writer computing the checksum and the loudness of the data written
+/// through it, if requested.
+struct MeasuringWriter<W> {
+    inner: W,
+    crc: Option<Crc32>,
+    meter: Option<LoudnessMeter>,
+}
+
+impl<W: Write> Write for MeasuringWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let nbytes = self.inner.write(buf)?;
+        if let Some(ref mut crc) = self.crc {
+            crc.update(&buf[..nbytes]);
+        }
+        if let Some(ref mut meter) = self.meter {
+            meter.update(&buf[..nbytes]);
+        }
+        Ok(nbytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+
+/// Writer transforming the stereo audio written through it according to a
+/// `ChannelMode`; stereo audio is passed through as is.
+struct ChannelWriter<W> {
+    inner: W,
+    mode: ChannelMode,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> ChannelWriter<W> {
+    fn new(inner: W, mode: ChannelMode) -> ChannelWriter<W> {
+        ChannelWriter { inner, mode, pending: Vec::new() }
+    }
+
+    /// Checks that no incomplete sample is left, and returns the inner
+    /// writer.
+    fn finish(self) -> Result<W, NrgError> {
+        if !self.pending.is_empty() {
+            return Err(NrgError::NrgFormat(
+                "Incomplete sample in the audio data".to_string()));
+        }
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for ChannelWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.mode == ChannelMode::Stereo {
+            self.inner.write_all(buf)?;
+            return Ok(buf.len());
+        }
+        self.pending.extend_from_slice(buf);
+        let nb_frames = self.pending.len() / SAMPLE_SIZE;
+        let mut out = Vec::with_capacity(nb_frames * SAMPLE_SIZE);
+        for frame in self.pending[..nb_frames * SAMPLE_SIZE]
+            .chunks(SAMPLE_SIZE) {
+            match self.mode {
+                ChannelMode::Stereo => out.extend_from_slice(frame),
+                ChannelMode::Swapped => {
+                    out.extend_from_slice(&frame[2..]);
+                    out.extend_from_slice(&frame[..2]);
+                },
+                ChannelMode::Mono => {
+                    let left = i16::from_le_bytes([frame[0], frame[1]]);
+                    let right = i16::from_le_bytes([frame[2], frame[3]]);
+                    let mono = (i32::from(left) + i32::from(right)) >> 1;
+                    out.extend_from_slice(&(mono as i16).to_le_bytes());
+                },
+            }
+        }
+        self.pending.drain(..nb_frames * SAMPLE_SIZE);
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+
+/// Correspondence between the byte positions in the image file and the byte
+/// positions in the extracted audio stream, which lacks the sub-channel data
+/// if it is stripped.
+///
+/// Position 0 of the audio stream is the first track's index #0.
+#[derive(Debug)]
+struct AudioLayout {
+    base: u64,
+    sector_size: u64,
+    audio_sector_size: u64,
+    length: u64,
+}
+
+impl AudioLayout {
+    fn new(metadata: &NrgMetadata, tracks: &[Track], strip_subchannel: bool)
+           -> Result<AudioLayout, NrgError> {
+        let sector_size = metadata.sector_size();
+        let first_track = match tracks.first() {
+            Some(track) if sector_size != 0 => track,
+            _ => return Err(NrgError::NrgFormat(
+                "Unknown sector size (no DAOX tracks)".to_string())),
+        };
+        if metadata.last_audio_byte() < metadata.first_audio_byte() {
+            return Err(NrgError::NrgFormat(
+                "Audio data ends before it starts".to_string()));
+        }
+
+        let audio_sector_size =
+            if strip_subchannel && sector_size == RAW96_SEC_SIZE {
+                RAW_SEC_SIZE
+            } else {
+                sector_size
+            };
+
+        let mut layout = AudioLayout {
+            base: first_track.index0,
+            sector_size: u64::from(sector_size),
+            audio_sector_size: u64::from(audio_sector_size),
+            length: 0,
+        };
+        layout.length = layout.to_audio(metadata.last_audio_byte());
+        Ok(layout)
+    }
+
+    fn is_stripped(&self) -> bool {
+        self.audio_sector_size != self.sector_size
+    }
+
+    /// Converts a position in the image file into a position in the audio
+    /// stream.
+    fn to_audio(&self, file_pos: u64) -> u64 {
+        let pos = file_pos.saturating_sub(self.base);
+        pos / self.sector_size * self.audio_sector_size
+            + (pos % self.sector_size).min(self.audio_sector_size)
+    }
+
+    /// Converts a position in the audio stream into a position in the image
+    /// file.
+    fn to_file(&self, audio_pos: u64) -> u64 {
+        self.base + audio_pos / self.audio_sector_size * self.sector_size
+            + audio_pos % self.audio_sector_size
+    }
+}
+
+
+/// Range of the audio stream.
+#[derive(Debug)]
+struct Segment {
+    start: u64,
+    end: u64,
+}
+
+impl Segment {
+    fn len(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+
+/// Ranges of the audio stream to be written, in order, to one output file.
+#[derive(Debug)]
+struct Output {
+    track_number: Option<u8>,
+    segments: Vec<Segment>,
+}
+
+impl Output {
+    fn len(&self) -> u64 {
+        self.segments.iter().map(Segment::len).sum()
+    }
+}
+
+
+/// Lists the output files to be written, with the ranges of the audio stream
+/// they contain.
+fn plan_outputs(metadata: &NrgMetadata, tracks: &[Track],
+                layout: &AudioLayout, options: &ExtractionOptions)
+                -> Result<Vec<Output>, NrgError> {
+    if let Some(ref selection) = options.tracks {
+        if let Some(&number) = selection.iter()
+            .find(|&&number| !tracks.iter().any(|t| t.number == number)) {
+            return Err(NrgError::TrackNotFound(number));
+        }
+    }
+
+    let selected = |track: &Track| options.tracks.as_ref()
+        .is_none_or(|selection| selection.contains(&track.number));
+
+    if !options.split_tracks {
+        let segments = if options.tracks.is_none() {
+            vec![Segment {
+                start: layout.to_audio(metadata.first_audio_byte()),
+                end: layout.to_audio(metadata.last_audio_byte()),
+            }]
+        } else {
+            plan_track_segments(tracks, layout, options.gap_mode)
+                .into_iter()
+                .filter(|&(track, _)| selected(track))
+                .map(|(_, segment)| segment)
+                .collect()
+        };
+        return Ok(vec![Output { track_number: None, segments }]);
+    }
+
+    Ok(plan_track_segments(tracks, layout, options.gap_mode)
+       .into_iter()
+       .filter(|&(track, _)| selected(track))
+       .map(|(track, segment)| Output {
+           track_number: Some(track.number),
+           segments: vec![segment],
+       })
+       .collect())
+}
+
+
+/// Lists the ranges of the audio stream covered by each track, including
+/// the pre-gaps placed according to `gap_mode`.
+fn plan_track_segments<'a>(tracks: &'a [Track], layout: &AudioLayout,
+                           gap_mode: GapMode)
+                           -> Vec<(&'a Track, Segment)> {
+    let mut segments = Vec::new();
+    for (i, track) in tracks.iter().enumerate() {
+        let (start, end) = match gap_mode {
+            GapMode::Append => {
+                let end = tracks.get(i + 1)
+                    .map_or(track.track_end, |next| next.index1);
+                (track.index1, end)
+            },
+            GapMode::Prepend if i == 0 => (track.index1, track.track_end),
+            GapMode::Prepend => (track.index0, track.track_end),
+            GapMode::Discard => (track.index1, track.track_end),
+        };
+        segments.push((track, Segment {
+            start: layout.to_audio(start),
+            end: layout.to_audio(end),
+        }));
+    }
+    segments
+}
+
+
+/// Copies the segments of `output` to `out_fd`, shifted by `shift` bytes,
+/// applying the de-emphasis filter to the parts of the audio stream listed
+/// in `emphasized`.
+///
+/// The audio data is copied through the buffer of `control`, which may
+/// cancel or throttle the copy.
+fn copy_output<W: Write>(in_fd: &mut File, out_fd: &mut W,
+                         layout: &AudioLayout, output: &Output, shift: i64,
+                         emphasized: &[Segment], control: &mut CopyControl)
+                         -> Result<(), NrgError> {
+    if emphasized.is_empty() {
+        for segment in &output.segments {
+            copy_segment(in_fd, out_fd, layout, segment, shift, control)?;
+        }
+        return Ok(());
+    }
+
+    let mut writer = DeEmphasisWriter::new(out_fd);
+    for segment in &output.segments {
+        for (part, filtered) in split_segment(segment, emphasized) {
+            writer.set_enabled(filtered)?;
+            copy_segment(in_fd, &mut writer, layout, &part, shift,
+                         control)?;
+        }
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+
+/// Splits `segment` at the boundaries of the ordered `ranges`, telling
+/// whether each part lies in one of them.
+fn split_segment(segment: &Segment, ranges: &[Segment])
+                 -> Vec<(Segment, bool)> {
+    let mut parts = Vec::new();
+    let mut pos = segment.start;
+    for range in ranges {
+        let start = range.start.max(pos);
+        let end = range.end.min(segment.end);
+        if start >= end {
+            continue;
+        }
+        if pos < start {
+            parts.push((Segment { start: pos, end: start }, false));
+        }
+        parts.push((Segment { start, end }, true));
+        pos = end;
+    }
+    if pos < segment.end {
+        parts.push((Segment { start: pos, end: segment.end }, false));
+    }
+    parts
+}
+
+
+/// Copies `segment` of the audio stream to `out_fd`, shifted by `shift`
+/// bytes.
+///
+/// The bytes located outside of the audio stream after the shift are
+/// replaced with zeros.
+fn copy_segment<W: Write>(in_fd: &mut File, out_fd: &mut W,
+                          layout: &AudioLayout, segment: &Segment, shift: i64,
+                          control: &mut CopyControl)
+                          -> Result<(), NrgError> {
+    let start = segment.start as i64 + shift;
+    let end = segment.end as i64 + shift;
+    let length = layout.length as i64;
+
+    // Silence before the audio stream
+    if start < 0 {
+        write_zeros(out_fd, (end.min(0) - start) as u64)?;
+    }
+
+    let from = start.max(0).min(length);
+    let to = end.max(0).min(length);
+    if from < to {
+        copy_audio(in_fd, out_fd, layout, from as u64, to as u64, control)?;
+    }
+
+    // Silence after the audio stream
+    if end > length {
+        write_zeros(out_fd, (end - start.max(length)) as u64)?;
+    }
+
+    Ok(())
+}
+
+
+/// Carves the bytes `start` (included) to `end` (excluded) of an NRG image
+/// to `out_fd`, without relying on its metadata.
+///
+/// This is meant to recover the audio data from the images whose table of
+/// contents is broken. The range is widened to whole sectors of the options'
+/// sector size, counted from the start of the image, where NRG images store
+/// their data; the sub-channel of 2448-byte sectors is stripped unless the
+/// options say otherwise. Only the options' buffer size, rate limit,
+/// cancellation, metrics, sector size, sub-channel stripping and read error
+/// handling apply: the audio is written raw.
+///
+/// Returns the range of bytes of the image actually copied.
+pub fn extract_range<R, W>(in_fd: &mut R, start: u64, end: u64,
+                           out_fd: &mut W, options: &ExtractionOptions)
+                           -> Result<Range<u64>, NrgError>
+    where R: Read + Seek,
+          W: Write {
+    if start > end {
+        return Err(NrgError::NrgFormat(
+            format!("Invalid byte range {}..{}", start, end)));
+    }
+    if options.sector_size == 0 {
+        return Err(NrgError::NrgFormat("Invalid sector size 0".to_string()));
+    }
+
+    let _timer = options.metrics.start();
+    let sector_size = u64::from(options.sector_size);
+    let start = start / sector_size * sector_size;
+    let end = end.div_ceil(sector_size) * sector_size;
+    let count = end - start;
+
+    in_fd.seek(SeekFrom::Start(start))?;
+    let mut control = options.copy_control(sector_size);
+    let bytes_read = if options.strip_subchannel
+        && options.sector_size == RAW96_SEC_SIZE {
+        raw_audio::copy_raw96_audio(in_fd, out_fd, count, &mut control)?
+    } else {
+        raw_audio::copy_raw_audio(in_fd, out_fd, count, &mut control)?
+    };
+    check_length(count, bytes_read)?;
+    if !control.bad_sectors().is_empty() {
+        let offsets: Vec<String> = control.bad_sectors().iter()
+            .map(u64::to_string)
+            .collect();
+        warn!("{} unreadable sectors replaced by silence, at offsets {}",
+              offsets.len(), offsets.join(", "));
+    }
+
+    Ok(start..end)
+}
+
+
+/// Extracts the data `hidden` found after the last track of the NRG image
+/// `img_path` (see nrgrip::hidden), open as `in_fd`, to "IMAGE-hidden.raw"
+/// in the options' output directory (or in the current directory).
+///
+/// The data is carved by `extract_range()` with the sector size of
+/// `hidden`, the sub-channel being stripped as set in `options`. The file is
+/// removed if the extraction is cancelled.
+pub fn extract_hidden_data(in_fd: &mut File, img_path: &str,
+                           hidden: &NrgHiddenData,
+                           options: &ExtractionOptions)
+                           -> Result<ExtractedFile, NrgError> {
+    let stem = Path::new(img_path).file_stem()
+        .ok_or_else(|| NrgError::FileName(img_path.to_string()))?;
+    let mut name = stem.to_os_string();
+    name.push("-hidden.raw");
+    let path = match options.output_dir {
+        Some(ref dir) => dir.join(name),
+        None => PathBuf::from(name),
+    };
+
+    let mut out_fd = BufWriter::new(File::create(&path)?);
+    let options = options.clone().sector_size(hidden.sector_size);
+    let result = extract_range(in_fd, hidden.range.start, hidden.range.end,
+                               &mut out_fd, &options);
+    if let Err(err) = result {
+        if err.kind() == NrgErrorKind::Cancelled {
+            drop(out_fd);
+            let _ = fs::remove_file(&path);
+        }
+        return Err(err);
+    }
+    out_fd.flush()?;
+    Ok(ExtractedFile {
+        path: path.to_string_lossy().into_owned(),
+        track_number: None,
+        audio_bytes: fs::metadata(&path)?.len(),
+        source_ranges: vec![hidden.range.clone()],
+        sectors: hidden.sector_count(),
+        crc32: None,
+        loudness: None,
+        verified: None,
+        bad_sectors: Vec::new(),
+    })
+}
+
+
+/// Copies the bytes `from` (included) to `to` (excluded) of the audio stream
+/// to `out_fd`.
+fn copy_audio<W: Write>(in_fd: &mut File, out_fd: &mut W,
+                        layout: &AudioLayout, from: u64, to: u64,
+                        control: &mut CopyControl)
+                        -> Result<(), NrgError> {
+    if !layout.is_stripped() {
+        in_fd.seek(SeekFrom::Start(layout.to_file(from)))?;
+        let count = to - from;
+        let bytes_read = raw_audio::copy_raw_audio(in_fd, out_fd, count,
+                                                   control)?;
+        return check_length(count, bytes_read);
+    }
+
+    let audio_sector_size = layout.audio_sector_size;
+    let mut pos = from;
+
+    // Beginning of the first sector, if it is incomplete
+    if !pos.is_multiple_of(audio_sector_size) {
+        let end = ((pos / audio_sector_size + 1) * audio_sector_size).min(to);
+        copy_partial_sector(in_fd, out_fd, layout, pos, end, control)?;
+        pos = end;
+    }
+
+    // Whole sectors
+    let nb_sectors = (to - pos) / audio_sector_size;
+    if nb_sectors > 0 {
+        in_fd.seek(SeekFrom::Start(layout.to_file(pos)))?;
+        let count = nb_sectors * layout.sector_size;
+        let bytes_read = raw_audio::copy_raw96_audio(in_fd, out_fd, count,
+                                                     control)?;
+        check_length(count, bytes_read)?;
+        pos += nb_sectors * audio_sector_size;
+    }
+
+    // End of the last sector, if it is incomplete
+    if pos < to {
+        copy_partial_sector(in_fd, out_fd, layout, pos, to, control)?;
+    }
+
+    Ok(())
+}
+
+
+/// Copies the bytes `from` to `to` of the audio stream, which must be
+/// located in the same sector, to `out_fd`, reading them through `control`.
+fn copy_partial_sector<W: Write>(in_fd: &mut File, out_fd: &mut W,
+                                 layout: &AudioLayout, from: u64, to: u64,
+                                 control: &mut CopyControl)
+                                 -> Result<(), NrgError> {
+    let mut buf = vec![0u8; (to - from) as usize];
+    in_fd.seek(SeekFrom::Start(layout.to_file(from)))?;
+    control.read(in_fd, &mut buf)?;
+    out_fd.write_all(&buf)?;
+    Ok(())
+}
+
+
+/// Returns the address of the sector of `tracks` located at `offset` in the
+/// image, or None if no track holds it.
+fn sector_lba(tracks: &[Track], offset: u64) -> Option<i32> {
+    let track = tracks.iter()
+        .find(|track| (track.index0..track.track_end).contains(&offset))?;
+    let delta = (offset as i64 - track.index1 as i64)
+        .div_euclid(i64::from(track.sector_size));
+    Some(track.position_sectors + delta as i32)
+}
+
+
+/// Writes `count` null bytes to `out_fd`.
+fn write_zeros<W: Write>(out_fd: &mut W, mut count: u64)
+                         -> Result<(), NrgError> {
+    let zeros = [0u8; RAW_SEC_SIZE as usize];
+    while count > 0 {
+        let nbytes = count.min(zeros.len() as u64);
+        out_fd.write_all(&zeros[..nbytes as usize])?;
+        count -= nbytes;
+    }
+    Ok(())
+}
+
+
+/// Returns the temporary name under which `extract()` writes the file
+/// `path`: the same name in the same directory, hidden, and with ".part"
+/// before the extension, which the external encoders may need.
+fn temporary_path(path: &str) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!(".{}.part.{}", stem, ext.to_string_lossy()),
+        None => format!(".{}.part", stem),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+
+/// Reads the file `path` back, and tells whether its CRC-32 is `crc`.
+fn verify_file(path: &str, crc: u32) -> Result<bool, NrgError> {
+    let mut reader = Crc32Writer::new(io::sink());
+    io::copy(&mut File::open(path)?, &mut reader)?;
+    if reader.crc32() != crc {
+        warn!("\"{}\" doesn't hold the data written to it", path);
+        return Ok(false);
+    }
+    info!("\"{}\" verified", path);
+    Ok(true)
+}
+
+
+fn check_length(expected: u64, copied: u64) -> Result<(), NrgError> {
+    if expected != copied {
+        return Err(NrgError::AudioLength { expected, copied });
+    }
+    Ok(())
+}
+
+
+/// Returns the name of the file written by `extract()` from the NRG image
+/// `img_path` for `track`, or for the whole audio data if `track` is None.
+///
+/// Unless an output path is set in `options`, the name is built from the
+/// options' name template, with the extension of the options' format, in the
+/// options' output directory (or in the current directory). The track's
+/// title is normalized (see nrgrip::unicode), transliterated to ASCII if
+/// requested, and sanitized with `sanitize_file_name()`; if two tracks have
+/// the same title, `extract()` adds a counter to the name of the second
+/// file.
+pub fn output_file_name(img_path: &str, options: &ExtractionOptions,
+                        track: Option<&Track>)
+                        -> Result<String, NrgError> {
+    if let Some(ref path) = options.output_path {
+        if options.split_tracks {
+            return Err(NrgError::FileName(
+                "An output path can't be used to split the tracks"
+                    .to_string()));
+        }
+        if path == Path::new(img_path) {
+            return Err(NrgError::FileName(
+                "Input and output file are identical".to_string()));
+        }
+        return Ok(path.to_string_lossy().into_owned());
+    }
+
+    let template = match options.name_template {
+        Some(ref template) => template.as_str(),
+        None if options.split_tracks => "{image}-{track}",
+        None => "{image}",
+    };
+    if options.split_tracks && !template.contains("{track}")
+        && !template.contains("{title}") {
+        return Err(NrgError::FileName(
+            "The name template must contain {track} or {title} to split the \
+             tracks".to_string()));
+    }
+
+    let img_name = PathBuf::from(img_path);
+    let img_stem = img_name.file_stem()
+        .ok_or_else(|| NrgError::FileName(img_path.to_string()))?
+        .to_string_lossy();
+
+    let mut name = template.replace("{image}", &img_stem);
+    if let Some(track) = track {
+        let title = match track.title {
+            Some(ref title) if options.ascii_names =>
+                sanitize_file_name(&unicode::to_ascii(title)),
+            Some(ref title) => sanitize_file_name(&unicode::nfc(title)),
+            None => String::new(),
+        };
+        let title = if title.is_empty() {
+            format!("Track {:02}", track.number)
+        } else {
+            title
+        };
+        name = name.replace("{track}", &format!("{:02}", track.number))
+            .replace("{title}", &title);
+    }
+    name.push('.');
+    name.push_str(options.encoder.as_ref()
+                  .map_or(options.format.extension(),
+                          ExternalEncoder::get_extension));
+
+    // Make sure the new name and the original name are different
+    if img_name.file_name().is_some_and(|img| img == name.as_str()) {
+        return Err(NrgError::FileName("Input and output file are identical"
+                                      .to_string()));
+    }
+
+    match options.output_dir {
+        Some(ref dir) => Ok(dir.join(name).to_string_lossy().into_owned()),
+        None => Ok(name),
+    }
+}
+
+
+/// Maximum length of a sanitized file name, in bytes, leaving room for a
+/// counter and an extension within the 255-byte limit of most file systems.
+const MAX_FILE_NAME_LEN: usize = 200;
+
+
+/// Turns `name` into a file name valid on the common file systems.
+///
+/// The characters reserved on Windows and the control characters are replaced
+/// with underscores, the leading spaces and the trailing spaces and dots are
+/// removed, the names reserved on Windows (such as "CON" or "LPT1") get an
+/// underscore inserted before their extension, and the name is truncated to
+/// 200 bytes. The result is empty if `name` holds only spaces and dots.
+pub fn sanitize_file_name(name: &str) -> String {
+    let mut sanitized: String = name.chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    if sanitized.len() > MAX_FILE_NAME_LEN {
+        let mut end = MAX_FILE_NAME_LEN;
+        while !sanitized.is_char_boundary(end) {
+            end -= 1;
+        }
+        sanitized.truncate(end);
+    }
+    let sanitized = sanitized.trim_start_matches(' ')
+        .trim_end_matches(['.', ' ']);
+
+    // Reserved names are forbidden even with an extension
+    let base_len = sanitized.find('.').unwrap_or(sanitized.len());
+    let base = sanitized[..base_len].trim_end();
+    let reserved = ["CON", "PRN", "AUX", "NUL"].iter()
+        .any(|name| base.eq_ignore_ascii_case(name))
+        || (base.len() == 4
+            && (base.as_bytes()[..3].eq_ignore_ascii_case(b"COM")
+                || base.as_bytes()[..3].eq_ignore_ascii_case(b"LPT"))
+            && base.as_bytes()[3].is_ascii_digit()
+            && base.as_bytes()[3] != b'0');
+    if reserved {
+        format!("{}_{}", &sanitized[..base_len], &sanitized[base_len..])
+    } else {
+        sanitized.to_string()
+    }
+}
+
+
+/// Returns `path`, or `path` with a counter inserted before its extension if
+/// it is already used by one of `files`.
+fn unique_path(path: String, files: &[ExtractedFile]) -> String {
+    let is_used = |candidate: &str| files.iter()
+        .any(|file| file.path == candidate);
+    if !is_used(&path) {
+        return path;
+    }
+
+    let original = PathBuf::from(&path);
+    let stem = original.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = original.extension()
+        .map_or_else(String::new, |ext| format!(".{}", ext.to_string_lossy()));
+    let mut counter = 2;
+    loop {
+        let candidate = original
+            .with_file_name(format!("{} ({}){}", stem, counter, extension))
+            .to_string_lossy()
+            .into_owned();
+        if !is_used(&candidate) {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
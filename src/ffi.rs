@@ -0,0 +1,285 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! C bindings to the library.
+//!
+//! An image is opened with `nrgrip_open()`, which returns an opaque handle
+//! to be released with `nrgrip_close()`. The other functions return 0 on
+//! success and -1 on error; in that case, `nrgrip_last_error()` returns a
+//! description of the error. The tracks are designated by their index in the
+//! image, starting at 0. A panic never unwinds into the caller: it is
+//! reported as an error.
+//!
+//! The C header `include/nrgrip.h` is generated from this module with
+//! cbindgen.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use ::error::NrgError;
+use ::metadata::{self, track::Track};
+use ::raw_audio::{self, CopyControl, RAW96_SEC_SIZE};
+
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error<E: ToString>(err: E) {
+    let message = CString::new(err.to_string().replace('\0', " "))
+        .unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Runs `f`, returning `on_panic` instead of unwinding into the caller if
+/// it panics, the panic message being stored as the last error.
+fn catch_panic<T, F: FnOnce() -> T>(on_panic: T, f: F) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload.downcast_ref::<&str>().map(|m| m.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown cause".to_string());
+        set_last_error(format!("Panic: {}", message));
+        on_panic
+    })
+}
+
+/// Converts `result` into a return code, storing the error if any.
+fn return_code(result: Result<(), NrgError>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            set_last_error(err);
+            -1
+        },
+    }
+}
+
+
+/// An opened NRG image.
+#[derive(Debug)]
+pub struct NrgImage {
+    fd: File,
+    tracks: Vec<Track>,
+}
+
+impl NrgImage {
+    fn track(&self, index: u32) -> Result<&Track, NrgError> {
+        self.tracks.get(index as usize).ok_or_else(|| NrgError::NrgFormat(
+            format!("No track with index {}", index)))
+    }
+}
+
+
+/// Information about a track, filled by `nrgrip_get_track_info()`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct NrgTrackInfo {
+    /// Track number.
+    pub number: u8,
+    /// Mode of the track, as found in the CUEX chunk.
+    pub mode: u8,
+    /// Sector size in the image file, in bytes.
+    pub sector_size: u16,
+    /// Position of the track's index #1, in sectors.
+    pub position_sectors: i32,
+    /// Length of the pre-gap, in sectors.
+    pub pregap_sectors: u64,
+    /// Length of the track (excluding the pre-gap), in sectors.
+    pub sector_count: u64,
+}
+
+
+/// Opens the NRG image at `path` and reads its metadata.
+///
+/// Returns NULL on error.
+///
+/// # Safety
+///
+/// `path` must be a valid, NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn nrgrip_open(path: *const c_char) -> *mut NrgImage {
+    catch_panic(ptr::null_mut(), || {
+        if path.is_null() {
+            set_last_error("NULL path");
+            return ptr::null_mut();
+        }
+        let path = CStr::from_ptr(path).to_string_lossy().into_owned();
+
+        let open = || -> Result<NrgImage, NrgError> {
+            let mut fd = File::open(&path)?;
+            let metadata = metadata::read_nrg_metadata(&mut fd)?;
+            let tracks = metadata.tracks().collect();
+            Ok(NrgImage { fd, tracks })
+        };
+
+        match open() {
+            Ok(image) => Box::into_raw(Box::new(image)),
+            Err(err) => {
+                set_last_error(err);
+                ptr::null_mut()
+            },
+        }
+    })
+}
+
+
+/// Closes an image opened by `nrgrip_open()`.
+///
+/// # Safety
+///
+/// `image` must be NULL or a pointer returned by `nrgrip_open()`, and must
+/// not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn nrgrip_close(image: *mut NrgImage) {
+    catch_panic((), || {
+        if !image.is_null() {
+            drop(Box::from_raw(image));
+        }
+    })
+}
+
+
+/// Returns the number of tracks of `image`, or -1 on error.
+///
+/// # Safety
+///
+/// `image` must be a pointer returned by `nrgrip_open()`.
+#[no_mangle]
+pub unsafe extern "C" fn nrgrip_get_track_count(image: *const NrgImage)
+                                                -> c_int {
+    catch_panic(-1, || {
+        match image.as_ref() {
+            Some(image) => image.tracks.len() as c_int,
+            None => {
+                set_last_error("NULL image");
+                -1
+            },
+        }
+    })
+}
+
+
+/// Fills `info` with the information about the track at `index`.
+///
+/// # Safety
+///
+/// `image` must be a pointer returned by `nrgrip_open()`, and `info` must
+/// point to a writable `NrgTrackInfo`.
+#[no_mangle]
+pub unsafe extern "C" fn nrgrip_get_track_info(image: *const NrgImage,
+                                               index: u32,
+                                               info: *mut NrgTrackInfo)
+                                               -> c_int {
+    catch_panic(-1, || {
+        let (image, info) = match (image.as_ref(), info.as_mut()) {
+            (Some(image), Some(info)) => (image, info),
+            _ => {
+                set_last_error("NULL image or track info");
+                return -1;
+            },
+        };
+
+        return_code(image.track(index).map(|track| {
+            *info = NrgTrackInfo {
+                number: track.number,
+                mode: track.mode,
+                sector_size: track.sector_size,
+                position_sectors: track.position_sectors,
+                pregap_sectors: track.pregap_sectors(),
+                sector_count: track.sector_count(),
+            };
+        }))
+    })
+}
+
+
+/// Writes the raw audio data of the track at `index` (from its index #1 to
+/// its end) to the file `out_path`.
+///
+/// The 96-byte sub-channel is stripped from the sectors, if present.
+///
+/// # Safety
+///
+/// `image` must be a pointer returned by `nrgrip_open()`, and `out_path` a
+/// valid, NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn nrgrip_extract_track(image: *mut NrgImage,
+                                              index: u32,
+                                              out_path: *const c_char)
+                                              -> c_int {
+    catch_panic(-1, || {
+        let image = match image.as_mut() {
+            Some(image) if !out_path.is_null() => image,
+            _ => {
+                set_last_error("NULL image or output path");
+                return -1;
+            },
+        };
+        let out_path = CStr::from_ptr(out_path).to_string_lossy().into_owned();
+
+        return_code(extract_track(image, index, &out_path))
+    })
+}
+
+fn extract_track(image: &mut NrgImage, index: u32, out_path: &str)
+                 -> Result<(), NrgError> {
+    let track = image.track(index)?.clone();
+    let count = track.track_end.checked_sub(track.index1).ok_or_else(
+        || NrgError::NrgFormat("Track ends before it starts".to_string()))?;
+
+    image.fd.seek(SeekFrom::Start(track.index1))?;
+    let mut out_fd = File::create(out_path)?;
+    let bytes_read = if track.sector_size == RAW96_SEC_SIZE {
+        raw_audio::copy_raw96_audio(&mut image.fd, &mut out_fd, count,
+                                    &mut CopyControl::unlimited())?
+    } else {
+        raw_audio::copy_raw_audio(&mut image.fd, &mut out_fd, count,
+                                  &mut CopyControl::unlimited())?
+    };
+
+    if bytes_read != count {
+        return Err(NrgError::AudioLength { expected: count,
+                                           copied: bytes_read });
+    }
+    Ok(())
+}
+
+
+/// Returns the description of the last error that occurred in the calling
+/// thread, or NULL if there was none.
+///
+/// The returned string is valid until the next call to an nrgrip function in
+/// the same thread.
+#[no_mangle]
+pub extern "C" fn nrgrip_last_error() -> *const c_char {
+    catch_panic(ptr::null(), || {
+        LAST_ERROR.with(|last| match *last.borrow() {
+            Some(ref message) => message.as_ptr(),
+            None => ptr::null(),
+        })
+    })
+}
@@ -0,0 +1,730 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to encode CD audio into FLAC streams.
+//!
+//! `FlacWriter` encodes the 16-bit little-endian PCM samples written through
+//! it, using the fixed predictors of the format and Rice-coded residuals.
+//! The STREAMINFO block (including the MD5 of the samples) and the
+//! SEEKTABLE block are completed once the stream is finished; a CUESHEET
+//! block can be embedded to describe the tracks of a whole-disc file.
+
+use std::io::{self, Seek, SeekFrom, Write};
+
+use md5::{Digest, Md5};
+
+use ::error::NrgError;
+use ::raw_audio::SAMPLE_RATE;
+
+/// Number of samples per channel in each frame.
+const BLOCK_SIZE: usize = 4608;
+/// Number of bits per sample.
+const BITS_PER_SAMPLE: u32 = 16;
+/// Number of samples between two seek points (10 seconds).
+const SEEK_INTERVAL: u64 = 10 * SAMPLE_RATE as u64;
+/// Number of samples in a CD sector.
+const SECTOR_SAMPLES: u64 = 588;
+/// Number of samples in the lead-in of a CD.
+const LEAD_IN_SAMPLES: u64 = 2 * SAMPLE_RATE as u64;
+/// Largest Rice parameter encodable with 4 bits (15 is the escape code).
+const MAX_RICE_PARAMETER: u32 = 14;
+/// Largest partition order tried for the residuals.
+const MAX_PARTITION_ORDER: u32 = 8;
+
+/// Types of the metadata blocks.
+const STREAMINFO: u8 = 0;
+const SEEKTABLE: u8 = 3;
+const CUESHEET: u8 = 5;
+
+/// Channel assignments of the frames.
+const INDEPENDENT_MONO: u32 = 0;
+const INDEPENDENT_STEREO: u32 = 1;
+const LEFT_SIDE: u32 = 8;
+const RIGHT_SIDE: u32 = 9;
+const MID_SIDE: u32 = 10;
+
+/// Lookup table of the CRC-8 of the frame headers.
+const CRC8_TABLE: [u8; 256] = make_crc8_table();
+/// Lookup table of the CRC-16 of the frames.
+const CRC16_TABLE: [u16; 256] = make_crc16_table();
+
+
+/// Builds the lookup table of the CRC-8 for the polynomial 0x07.
+const fn make_crc8_table() -> [u8; 256] {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+
+/// Builds the lookup table of the CRC-16 for the polynomial 0x8005.
+const fn make_crc16_table() -> [u16; 256] {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |crc, &b| CRC8_TABLE[usize::from(crc ^ b)])
+}
+
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, &b| {
+        (crc << 8) ^ CRC16_TABLE[usize::from((crc >> 8) as u8 ^ b)]
+    })
+}
+
+
+/// A track of the cue sheet embedded in a FLAC stream.
+///
+/// The positions are counted in samples from the beginning of the stream,
+/// and must fall on sector boundaries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlacCueTrack {
+    pub number: u8,
+    pub isrc: Option<String>,
+    /// False for a data track.
+    pub audio: bool,
+    pub pre_emphasis: bool,
+    /// Position of index #0, if the track has a pre-gap.
+    pub index0: Option<u64>,
+    /// Position of index #1.
+    pub index1: u64,
+}
+
+
+/// Writer encoding the CD audio written through it into a FLAC stream.
+///
+/// The data written must be 44.1 kHz, 16-bit little-endian PCM samples, with
+/// the channels interleaved. `finish()` must be called once all the samples
+/// are written, to complete the stream's metadata.
+#[derive(Debug)]
+pub struct FlacWriter<W> {
+    inner: W,
+    channels: usize,
+    total_samples: u64,
+    /// Position of the STREAMINFO payload in `inner`.
+    streaminfo_pos: u64,
+    /// Position of the SEEKTABLE payload in `inner`.
+    seektable_pos: u64,
+    /// Seek points: first sample, offset from the first frame and number of
+    /// samples of the frames holding the seek targets.
+    seek_points: Vec<(u64, u64, u16)>,
+    /// Incomplete frame.
+    pending: Vec<u8>,
+    samples: u64,
+    frame_number: u32,
+    /// Number of bytes of the frames written so far.
+    frames_size: u64,
+    min_frame_size: u32,
+    max_frame_size: u32,
+    md5: Md5,
+}
+
+impl<W: Write + Seek> FlacWriter<W> {
+    /// Starts a FLAC stream of `total_samples` samples per channel, in
+    /// `channels` channels (1 or 2), at the current position of `inner`.
+    ///
+    /// If `cue_tracks` is not empty, the tracks are described in a CUESHEET
+    /// block, the lead-out being at the end of the stream.
+    pub fn new(mut inner: W, channels: u16, total_samples: u64,
+               cue_tracks: &[FlacCueTrack])
+               -> Result<FlacWriter<W>, NrgError> {
+        if channels != 1 && channels != 2 {
+            return Err(NrgError::NrgFormat(format!(
+                "Can't encode {} channels in FLAC", channels)));
+        }
+        if total_samples >= 1 << 36 {
+            return Err(NrgError::NrgFormat(
+                "Audio data too large for a FLAC stream".to_string()));
+        }
+        let seek_point_count = total_samples.div_ceil(SEEK_INTERVAL);
+        let cue_sheet = if cue_tracks.is_empty() {
+            None
+        } else {
+            Some(cue_sheet_block(cue_tracks, total_samples)?)
+        };
+
+        let start = inner.stream_position()?;
+        inner.write_all(b"fLaC")?;
+        write_block_header(&mut inner, STREAMINFO, false, 34)?;
+        let streaminfo_pos = start + 8;
+        inner.write_all(&[0; 34])?;
+        let seektable_size = seek_point_count as usize * 18;
+        write_block_header(&mut inner, SEEKTABLE, cue_sheet.is_none(),
+                           seektable_size)?;
+        let seektable_pos = streaminfo_pos + 34 + 4;
+        inner.write_all(&vec![0; seektable_size])?;
+        if let Some(block) = cue_sheet {
+            write_block_header(&mut inner, CUESHEET, true, block.len())?;
+            inner.write_all(&block)?;
+        }
+
+        Ok(FlacWriter {
+            inner,
+            channels: usize::from(channels),
+            total_samples,
+            streaminfo_pos,
+            seektable_pos,
+            seek_points: Vec::with_capacity(seek_point_count as usize),
+            pending: Vec::new(),
+            samples: 0,
+            frame_number: 0,
+            frames_size: 0,
+            min_frame_size: u32::MAX,
+            max_frame_size: 0,
+            md5: Md5::new(),
+        })
+    }
+
+    /// Encodes the last frame, completes the metadata of the stream and
+    /// returns the inner writer, positioned at the end of the stream.
+    ///
+    /// Returns an error if the number of samples written differs from the
+    /// one announced to `new()`.
+    pub fn finish(mut self) -> Result<W, NrgError> {
+        if !self.pending.len().is_multiple_of(2 * self.channels) {
+            return Err(NrgError::NrgFormat(
+                "Incomplete sample in the audio data".to_string()));
+        }
+        if !self.pending.is_empty() {
+            let pending = std::mem::take(&mut self.pending);
+            self.write_frame(&pending)?;
+        }
+        if self.samples != self.total_samples {
+            return Err(NrgError::NrgFormat(format!(
+                "FLAC stream of {} samples instead of {}",
+                self.samples, self.total_samples)));
+        }
+
+        let end = self.inner.stream_position()?;
+        self.inner.seek(SeekFrom::Start(self.streaminfo_pos))?;
+        let streaminfo = self.streaminfo();
+        self.inner.write_all(&streaminfo)?;
+        self.inner.seek(SeekFrom::Start(self.seektable_pos))?;
+        let mut seektable = Vec::new();
+        for &(sample, offset, samples) in &self.seek_points {
+            seektable.extend_from_slice(&sample.to_be_bytes());
+            seektable.extend_from_slice(&offset.to_be_bytes());
+            seektable.extend_from_slice(&samples.to_be_bytes());
+        }
+        self.inner.write_all(&seektable)?;
+        self.inner.seek(SeekFrom::Start(end))?;
+        Ok(self.inner)
+    }
+
+    /// Returns the payload of the STREAMINFO block.
+    fn streaminfo(&self) -> Vec<u8> {
+        let mut bits = BitWriter::new();
+        bits.write(BLOCK_SIZE as u32, 16); // minimum block size
+        bits.write(BLOCK_SIZE as u32, 16); // maximum block size
+        let min_frame_size = if self.frame_number == 0 {
+            0
+        } else {
+            self.min_frame_size
+        };
+        bits.write(min_frame_size, 24);
+        bits.write(self.max_frame_size, 24);
+        bits.write(SAMPLE_RATE, 20);
+        bits.write(self.channels as u32 - 1, 3);
+        bits.write(BITS_PER_SAMPLE - 1, 5);
+        bits.write((self.samples >> 32) as u32, 4);
+        bits.write(self.samples as u32, 32);
+        let mut payload = bits.into_bytes();
+        payload.extend_from_slice(&self.md5.clone().finalize());
+        payload
+    }
+
+    /// Encodes `data`, a block of interleaved samples, into a frame.
+    fn write_frame(&mut self, data: &[u8]) -> io::Result<()> {
+        let block_size = data.len() / (2 * self.channels);
+        let mut channels = vec![Vec::with_capacity(block_size);
+                                self.channels];
+        for (i, bytes) in data.chunks(2).enumerate() {
+            let sample = i16::from_le_bytes([bytes[0], bytes[1]]);
+            channels[i % self.channels].push(i32::from(sample));
+        }
+        let frame = encode_frame(&channels, self.frame_number);
+
+        // Seek point for the targets falling in this frame
+        let target = self.seek_points.len() as u64 * SEEK_INTERVAL;
+        if target < self.samples + block_size as u64 {
+            self.seek_points.push((self.samples, self.frames_size,
+                                   block_size as u16));
+        }
+
+        self.inner.write_all(&frame)?;
+        self.samples += block_size as u64;
+        self.frame_number += 1;
+        self.frames_size += frame.len() as u64;
+        self.min_frame_size = self.min_frame_size.min(frame.len() as u32);
+        self.max_frame_size = self.max_frame_size.max(frame.len() as u32);
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> Write for FlacWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.samples + (self.pending.len() + buf.len()) as u64
+            / (2 * self.channels as u64) > self.total_samples {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "Too many samples for the FLAC stream"));
+        }
+        self.md5.update(buf);
+        self.pending.extend_from_slice(buf);
+        let frame_bytes = BLOCK_SIZE * 2 * self.channels;
+        let nb_frames = self.pending.len() / frame_bytes;
+        let pending = std::mem::take(&mut self.pending);
+        let mut result = Ok(());
+        for data in pending.chunks(frame_bytes).take(nb_frames) {
+            result = self.write_frame(data);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.pending = pending;
+        self.pending.drain(..nb_frames * frame_bytes);
+        result.map(|()| buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+
+/// Writes the header of a metadata block of `size` bytes.
+fn write_block_header<W: Write>(out: &mut W, block_type: u8, last: bool,
+                                size: usize) -> io::Result<()> {
+    let flag = if last { 0x80 } else { 0 };
+    let size = (size as u32).to_be_bytes();
+    out.write_all(&[flag | block_type, size[1], size[2], size[3]])
+}
+
+
+/// Builds the payload of the CUESHEET block describing `tracks`, followed by
+/// the lead-out at `total_samples`.
+fn cue_sheet_block(tracks: &[FlacCueTrack], total_samples: u64)
+                   -> Result<Vec<u8>, NrgError> {
+    let positions = tracks.iter()
+        .flat_map(|track| track.index0.into_iter().chain(Some(track.index1)))
+        .chain(Some(total_samples));
+    if positions.clone().any(|pos| pos % SECTOR_SAMPLES != 0)
+        || positions.clone().zip(positions.skip(1)).any(|(a, b)| a > b) {
+        return Err(NrgError::NrgFormat(
+            "The tracks of the FLAC cue sheet must be ordered and aligned \
+             on sectors".to_string()));
+    }
+
+    let mut block = vec![0; 128]; // media catalog number
+    block.extend_from_slice(&LEAD_IN_SAMPLES.to_be_bytes());
+    block.push(0x80); // CD-DA
+    block.extend_from_slice(&[0; 258]);
+    block.push(tracks.len() as u8 + 1);
+    for track in tracks {
+        let start = track.index0.unwrap_or(track.index1);
+        block.extend_from_slice(&start.to_be_bytes());
+        block.push(track.number);
+        let mut isrc = [0u8; 12];
+        if let Some(ref code) = track.isrc {
+            let code = code.as_bytes();
+            let len = code.len().min(isrc.len());
+            isrc[..len].copy_from_slice(&code[..len]);
+        }
+        block.extend_from_slice(&isrc);
+        let mut flags = 0;
+        if !track.audio {
+            flags |= 0x80;
+        }
+        if track.pre_emphasis {
+            flags |= 0x40;
+        }
+        block.push(flags);
+        block.extend_from_slice(&[0; 13]);
+        let mut indexes = Vec::new();
+        if let Some(index0) = track.index0 {
+            indexes.push((0, index0));
+        }
+        indexes.push((1, track.index1));
+        block.push(indexes.len() as u8);
+        for (number, pos) in indexes {
+            block.extend_from_slice(&(pos - start).to_be_bytes());
+            block.push(number);
+            block.extend_from_slice(&[0; 3]);
+        }
+    }
+
+    // Lead-out
+    block.extend_from_slice(&total_samples.to_be_bytes());
+    block.push(170);
+    block.extend_from_slice(&[0; 12 + 1 + 13 + 1]);
+    Ok(block)
+}
+
+
+/// Encodes a frame made of `channels`, each holding the same number of
+/// samples.
+fn encode_frame(channels: &[Vec<i32>], frame_number: u32) -> Vec<u8> {
+    let block_size = channels[0].len();
+    let mut bits = BitWriter::new();
+
+    // Side and mid channels of stereo frames
+    let (side, mid): (Vec<i32>, Vec<i32>) = match *channels {
+        [ref left, ref right] => left.iter().zip(right)
+            .map(|(l, r)| (l - r, (l + r) >> 1))
+            .unzip(),
+        _ => (Vec::new(), Vec::new()),
+    };
+
+    // Choose the channel assignment yielding the smallest subframes
+    let (assignment, subframes) = if channels.len() == 1 {
+        (INDEPENDENT_MONO, vec![Subframe::best(&channels[0], BITS_PER_SAMPLE)])
+    } else {
+        let (left, right) = (&channels[0], &channels[1]);
+        let left = Subframe::best(left, BITS_PER_SAMPLE);
+        let right = Subframe::best(right, BITS_PER_SAMPLE);
+        let side = Subframe::best(&side, BITS_PER_SAMPLE + 1);
+        let mid = Subframe::best(&mid, BITS_PER_SAMPLE);
+        let candidates = [
+            (INDEPENDENT_STEREO, left.bits + right.bits),
+            (LEFT_SIDE, left.bits + side.bits),
+            (RIGHT_SIDE, side.bits + right.bits),
+            (MID_SIDE, mid.bits + side.bits),
+        ];
+        let assignment = candidates.iter()
+            .min_by_key(|&&(_, bits)| bits)
+            .map_or(INDEPENDENT_STEREO, |&(assignment, _)| assignment);
+        let subframes = match assignment {
+            LEFT_SIDE => vec![left, side],
+            RIGHT_SIDE => vec![side, right],
+            MID_SIDE => vec![mid, side],
+            _ => vec![left, right],
+        };
+        (assignment, subframes)
+    };
+
+    // Header
+    bits.write(0b1111_1111_1111_1000, 16); // sync code, fixed block size
+    let size_code = match block_size {
+        BLOCK_SIZE => 0b0101,
+        1..=256 => 0b0110,
+        _ => 0b0111,
+    };
+    bits.write(size_code, 4);
+    bits.write(0b1001, 4); // 44.1 kHz
+    bits.write(assignment, 4);
+    bits.write(0b100, 3); // 16 bits per sample
+    bits.write(0, 1);
+    bits.write_utf8(frame_number);
+    match size_code {
+        0b0110 => bits.write(block_size as u32 - 1, 8),
+        0b0111 => bits.write(block_size as u32 - 1, 16),
+        _ => (),
+    }
+    let crc = crc8(bits.bytes());
+    bits.write(u32::from(crc), 8);
+
+    for subframe in &subframes {
+        subframe.encode(&mut bits);
+    }
+    bits.align();
+    let crc = crc16(bits.bytes());
+    bits.write(u32::from(crc), 16);
+    bits.into_bytes()
+}
+
+
+/// Encoding chosen for the samples of a channel.
+struct Subframe<'a> {
+    samples: &'a [i32],
+    /// Number of bits per sample.
+    bps: u32,
+    kind: SubframeKind,
+    /// Estimated size of the subframe, in bits.
+    bits: u64,
+}
+
+enum SubframeKind {
+    Constant,
+    Verbatim,
+    /// Fixed predictor of the given order, with the residuals' partition
+    /// order and Rice parameters.
+    Fixed(usize, Vec<i32>, u32, Vec<u32>),
+}
+
+impl<'a> Subframe<'a> {
+    /// Chooses the smallest encoding of `samples`, coded on `bps` bits.
+    fn best(samples: &'a [i32], bps: u32) -> Subframe<'a> {
+        let subframe = |kind, bits| Subframe { samples, bps, kind, bits };
+        if samples.iter().all(|&sample| sample == samples[0]) {
+            return subframe(SubframeKind::Constant, 8 + u64::from(bps));
+        }
+
+        let mut best = subframe(SubframeKind::Verbatim,
+                                8 + samples.len() as u64 * u64::from(bps));
+        for order in 0..=4.min(samples.len() - 1) {
+            let residuals = fixed_residuals(samples, order);
+            let (partition_order, parameters, rice_bits) =
+                rice_partitions(&residuals, samples.len(), order);
+            let bits = 8 + order as u64 * u64::from(bps) + 2 + 4 + rice_bits;
+            if bits < best.bits {
+                best = subframe(SubframeKind::Fixed(order, residuals,
+                                                    partition_order,
+                                                    parameters),
+                                bits);
+            }
+        }
+        best
+    }
+
+    fn encode(&self, bits: &mut BitWriter) {
+        match self.kind {
+            SubframeKind::Constant => {
+                bits.write(0, 8);
+                bits.write_signed(self.samples[0], self.bps);
+            },
+            SubframeKind::Verbatim => {
+                bits.write(0b10, 8);
+                for &sample in self.samples {
+                    bits.write_signed(sample, self.bps);
+                }
+            },
+            SubframeKind::Fixed(order, ref residuals, partition_order,
+                                ref parameters) => {
+                bits.write((0b1000 | order as u32) << 1, 8);
+                for &sample in &self.samples[..order] {
+                    bits.write_signed(sample, self.bps);
+                }
+                bits.write(0, 2); // 4-bit Rice parameters
+                bits.write(partition_order, 4);
+                let partition_size = self.samples.len() >> partition_order;
+                let mut start = 0;
+                for (i, &parameter) in parameters.iter().enumerate() {
+                    let end = (i + 1) * partition_size - order;
+                    bits.write(parameter, 4);
+                    for &residual in &residuals[start..end] {
+                        bits.write_rice(residual, parameter);
+                    }
+                    start = end;
+                }
+            },
+        }
+    }
+}
+
+
+/// Computes the residuals of the fixed predictor of `order` (0 to 4).
+fn fixed_residuals(samples: &[i32], order: usize) -> Vec<i32> {
+    samples.windows(order + 1)
+        .map(|w| match order {
+            0 => w[0],
+            1 => w[1] - w[0],
+            2 => w[2] - 2 * w[1] + w[0],
+            3 => w[3] - 3 * w[2] + 3 * w[1] - w[0],
+            _ => w[4] - 4 * w[3] + 6 * w[2] - 4 * w[1] + w[0],
+        })
+        .collect()
+}
+
+
+/// Maps a signed residual to the unsigned value coded by the Rice code.
+fn fold(residual: i32) -> u32 {
+    ((residual << 1) ^ (residual >> 31)) as u32
+}
+
+
+/// Chooses the partition order and the Rice parameters of the residuals of
+/// a block of `block_size` samples predicted with `order` warm-up samples.
+///
+/// Returns the partition order, the parameter of each partition and the
+/// estimated size of the coded residuals in bits.
+fn rice_partitions(residuals: &[i32], block_size: usize, order: usize)
+                   -> (u32, Vec<u32>, u64) {
+    // Sums of the folded residuals for the finest partitions, merged
+    // pairwise for the coarser ones
+    let mut max_order = 0;
+    while max_order < MAX_PARTITION_ORDER
+        && block_size.is_multiple_of(2 << max_order)
+        && block_size >> (max_order + 1) > order {
+        max_order += 1;
+    }
+    let partition_size = block_size >> max_order;
+    let mut sums: Vec<(u64, u64)> = (0..1 << max_order)
+        .map(|i| {
+            let start = (i * partition_size).saturating_sub(order);
+            let end = (i + 1) * partition_size - order;
+            let sum = residuals[start..end].iter()
+                .map(|&residual| u64::from(fold(residual)))
+                .sum();
+            (sum, (end - start) as u64)
+        })
+        .collect();
+
+    let mut best: Option<(u32, Vec<u32>, u64)> = None;
+    for partition_order in (0..=max_order).rev() {
+        let mut parameters = Vec::with_capacity(sums.len());
+        let mut bits = 0;
+        for &(sum, count) in &sums {
+            let (parameter, partition_bits) = rice_parameter(sum, count);
+            parameters.push(parameter);
+            bits += 4 + partition_bits;
+        }
+        if best.as_ref().is_none_or(|&(_, _, best_bits)| bits < best_bits) {
+            best = Some((partition_order, parameters, bits));
+        }
+        sums = sums.chunks(2)
+            .map(|pair| pair.iter()
+                 .fold((0, 0), |(sum, count), &(s, c)| (sum + s, count + c)))
+            .collect();
+    }
+    best.unwrap_or((0, vec![0], 0))
+}
+
+
+/// Chooses the Rice parameter of a partition of `count` residuals whose
+/// folded values add up to `sum`, and estimates the size of the partition
+/// in bits.
+fn rice_parameter(sum: u64, count: u64) -> (u32, u64) {
+    let estimate = |parameter: u32| {
+        count * (u64::from(parameter) + 1) + (sum >> parameter)
+    };
+    if count == 0 {
+        return (0, 0);
+    }
+    let mean = sum / count;
+    let parameter = (64 - mean.leading_zeros()).min(MAX_RICE_PARAMETER);
+    let candidates = [parameter.saturating_sub(1), parameter];
+    candidates.iter()
+        .map(|&parameter| (parameter, estimate(parameter)))
+        .min_by_key(|&(_, bits)| bits)
+        .unwrap_or((parameter, estimate(parameter)))
+}
+
+
+/// Writer of a bit stream, most significant bits first.
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    nbits: u32,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter { bytes: Vec::new(), acc: 0, nbits: 0 }
+    }
+
+    /// Writes the `count` (up to 32) low bits of `value`.
+    fn write(&mut self, value: u32, count: u32) {
+        if count == 0 {
+            return;
+        }
+        let mask = u64::MAX >> (64 - count);
+        self.acc = (self.acc << count) | (u64::from(value) & mask);
+        self.nbits += count;
+        while self.nbits >= 8 {
+            self.nbits -= 8;
+            self.bytes.push((self.acc >> self.nbits) as u8);
+        }
+    }
+
+    /// Writes `value` in two's complement on `count` bits.
+    fn write_signed(&mut self, value: i32, count: u32) {
+        self.write(value as u32, count);
+    }
+
+    /// Writes `residual` with the Rice code of `parameter`.
+    fn write_rice(&mut self, residual: i32, parameter: u32) {
+        let value = fold(residual);
+        let mut quotient = value >> parameter;
+        while quotient >= 32 {
+            self.write(0, 32);
+            quotient -= 32;
+        }
+        self.write(1, quotient + 1);
+        self.write(value, parameter);
+    }
+
+    /// Writes `value` with the UTF-8-like coding of the frame numbers.
+    fn write_utf8(&mut self, value: u32) {
+        if value < 0x80 {
+            self.write(value, 8);
+            return;
+        }
+        let nbytes = match value {
+            0..=0x7FF => 2,
+            0x800..=0xFFFF => 3,
+            0x1_0000..=0x1F_FFFF => 4,
+            0x20_0000..=0x3FF_FFFF => 5,
+            _ => 6,
+        };
+        let prefix = (0xFF00 >> nbytes) & 0xFF;
+        self.write(prefix | (value >> (6 * (nbytes - 1))), 8);
+        for i in (0..nbytes - 1).rev() {
+            self.write(0x80 | ((value >> (6 * i)) & 0x3F), 8);
+        }
+    }
+
+    /// Pads the stream with zeros up to a byte boundary.
+    fn align(&mut self) {
+        if self.nbits > 0 {
+            self.write(0, 8 - self.nbits);
+        }
+    }
+
+    /// Returns the complete bytes written so far.
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the bytes written, the stream being padded to a byte
+    /// boundary.
+    fn into_bytes(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
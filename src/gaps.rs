@@ -0,0 +1,129 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to detect the gaps between tracks by looking for digital silence.
+
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+use ::error::NrgError;
+use ::metadata::metadata::NrgMetadata;
+use ::msf::Msf;
+use ::raw_audio::{RAW_SEC_SIZE, RAW96_SEC_SIZE};
+
+/// Maximum number of sectors read at once while scanning for silence.
+const SCAN_SECTORS: u64 = 75;
+
+
+/// A gap detected before the start (index #1) of a track.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgGap {
+    pub track_number: u8,
+    pub sectors: u32,
+}
+
+impl fmt::Display for NrgGap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Track {:02}: {} sectors ({}) of silence before index 1",
+               self.track_number, self.sectors, Msf::from_sectors(self.sectors))
+    }
+}
+
+
+/// Scans the track boundaries of the NRG image `in_fd` for digital silence.
+///
+/// - `in_fd` is the handler to the NRG image file.
+/// - `metadata` is the metadata extracted from `in_fd` by nrgrip::metadata.
+///
+/// For every track but the first one, the sectors preceding the start of the
+/// track (as indicated by the DAOX chunk) are read backwards until a sector
+/// containing a non-zero sample is found. The number of silent sectors is
+/// returned as the detected gap for that track; it can be 0.
+///
+/// Returns an empty list if the image has no DAOX tracks.
+pub fn detect_gaps<R: Read + Seek>(in_fd: &mut R, metadata: &NrgMetadata)
+                                   -> Result<Vec<NrgGap>, NrgError> {
+    let mut gaps = Vec::new();
+    let daox_chunk = match metadata.daox_chunk() {
+        None => return Ok(gaps),
+        Some(chunk) => chunk,
+    };
+
+    let sector_size = metadata.sector_size();
+    if sector_size != RAW_SEC_SIZE && sector_size != RAW96_SEC_SIZE {
+        return Err(NrgError::NrgFormat(format!(
+            "Can't look for silence in {}-byte sectors", sector_size)));
+    }
+
+    let first_track = if daox_chunk.first_track() == 0 {
+        1
+    } else {
+        daox_chunk.first_track()
+    };
+
+    for (i, pair) in daox_chunk.tracks().windows(2).enumerate() {
+        let sectors = count_silent_sectors(in_fd, pair[0].index1(),
+                                           pair[1].index1(), sector_size)?;
+        let gap = NrgGap {
+            track_number: (usize::from(first_track) + i + 1) as u8,
+            sectors,
+        };
+        debug!("{}", gap);
+        gaps.push(gap);
+    }
+
+    Ok(gaps)
+}
+
+
+/// Counts the silent sectors located right before the byte `end`.
+///
+/// The sectors are read backwards from `end`, without going further than the
+/// byte `start`.
+fn count_silent_sectors<R: Read + Seek>(in_fd: &mut R, start: u64, end: u64,
+                                        sector_size: u16)
+                                        -> Result<u32, NrgError> {
+    let sector_size = u64::from(sector_size);
+    let mut buf = Vec::new();
+    let mut silent_sectors = 0;
+    let mut pos = end;
+
+    while pos >= start + sector_size {
+        let nb_sectors = ((pos - start) / sector_size).min(SCAN_SECTORS);
+        pos -= nb_sectors * sector_size;
+
+        buf.resize((nb_sectors * sector_size) as usize, 0);
+        in_fd.seek(SeekFrom::Start(pos))?;
+        in_fd.read_exact(&mut buf)?;
+
+        // Look at the sectors in reverse order, ignoring the sub-channel data
+        for sector in buf.chunks(sector_size as usize).rev() {
+            let audio = &sector[..RAW_SEC_SIZE as usize];
+            if audio.iter().any(|&b| b != 0) {
+                return Ok(silent_sectors);
+            }
+            silent_sectors += 1;
+        }
+    }
+
+    Ok(silent_sectors)
+}
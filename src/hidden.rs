@@ -0,0 +1,121 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to detect the data hidden between the end of the last track and
+//! the chunks of an NRG image.
+//!
+//! The audio data of the image ends at the end of the last DAOX track, and
+//! the chunks start at the chunk offset; both are usually the same byte.
+//! Some images carry data in between, e.g. material past the lead-out,
+//! that no track covers and that is therefore never extracted.
+
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+
+use ::error::NrgError;
+use ::metadata::metadata::NrgMetadata;
+use ::msf::Msf;
+use ::raw_audio::{RAW_SEC_SIZE, RAW96_SEC_SIZE};
+
+/// Size of the blocks read while scanning the hidden data, in bytes.
+const SCAN_SIZE: usize = 75 * RAW96_SEC_SIZE as usize;
+
+
+/// Data found between the end of the last track and the chunks.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgHiddenData {
+    /// Byte range of the data in the image file.
+    pub range: Range<u64>,
+    /// Size of the sectors of the image, in bytes.
+    pub sector_size: u16,
+    /// Number of non-zero bytes in the range, the sub-channel excluded.
+    pub nonzero_bytes: u64,
+}
+
+impl NrgHiddenData {
+    /// Returns the length of the data, in whole sectors.
+    pub fn sector_count(&self) -> u64 {
+        (self.range.end - self.range.start) / u64::from(self.sector_size)
+    }
+}
+
+impl fmt::Display for NrgHiddenData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Hidden data after the last track: {} sectors ({}), \
+                   bytes {} to {}, {} non-zero bytes",
+               self.sector_count(),
+               Msf::from_sectors(self.sector_count() as u32),
+               self.range.start, self.range.end, self.nonzero_bytes)
+    }
+}
+
+
+/// Looks for non-zero data between the end of the last track and the chunks
+/// of the NRG image `in_fd`.
+///
+/// - `in_fd` is the handler to the NRG image file.
+/// - `metadata` is the metadata extracted from `in_fd` by nrgrip::metadata.
+///
+/// Returns None if the chunks follow the last track, or if the bytes in
+/// between are all zeros (the sub-channel of 2448-byte sectors excluded).
+pub fn detect_hidden_data<R: Read + Seek>(in_fd: &mut R,
+                                          metadata: &NrgMetadata)
+                                          -> Result<Option<NrgHiddenData>,
+                                                    NrgError> {
+    let range = metadata.last_audio_byte()..metadata.chunk_offset();
+    if range.start >= range.end {
+        return Ok(None);
+    }
+
+    let sector_size = metadata.sector_size();
+    if sector_size != RAW_SEC_SIZE && sector_size != RAW96_SEC_SIZE {
+        return Err(NrgError::NrgFormat(format!(
+            "Can't look for hidden data in {}-byte sectors", sector_size)));
+    }
+    // The sub-channel follows the 2352 bytes of audio of each sector
+    let audio_size = u64::from(RAW_SEC_SIZE);
+
+    let mut buf = vec![0u8; SCAN_SIZE];
+    let mut nonzero_bytes = 0;
+    let mut pos = range.start;
+    in_fd.seek(SeekFrom::Start(pos))?;
+    while pos < range.end {
+        let len = (range.end - pos).min(SCAN_SIZE as u64) as usize;
+        in_fd.read_exact(&mut buf[..len])?;
+        nonzero_bytes += buf[..len].iter().enumerate()
+            .filter(|&(i, &byte)| {
+                byte != 0
+                    && (pos - range.start + i as u64) % u64::from(sector_size)
+                        < audio_size
+            })
+            .count() as u64;
+        pos += len as u64;
+    }
+
+    if nonzero_bytes == 0 {
+        return Ok(None);
+    }
+    let hidden = NrgHiddenData { range, sector_size, nonzero_bytes };
+    debug!("{}", hidden);
+    Ok(Some(hidden))
+}
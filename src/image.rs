@@ -0,0 +1,158 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to read an NRG image sector by sector.
+//!
+//! Sectors are addressed by their logical block address (LBA): the first
+//! track's index #1 is at LBA 0, and its pre-gap at the negative addresses
+//! before it. `NrgImage` maps these addresses to the positions of the
+//! sectors in the image file.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+use ::error::NrgError;
+use ::metadata;
+use ::metadata::metadata::NrgMetadata;
+use ::metadata::track::Track;
+use ::raw_audio::{RAW_SEC_SIZE, RAW96_SEC_SIZE};
+
+/// Size of the sub-channel stored after the main channel of a sector, in
+/// bytes.
+pub const SUBCHANNEL_SIZE: usize = 96;
+
+
+/// A sector read from an image.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgSector {
+    /// Logical block address of the sector.
+    pub lba: i32,
+    /// Number of the track holding the sector.
+    pub track_number: u8,
+    /// Main channel of the sector: 2352 bytes of audio, or a raw data
+    /// sector.
+    pub data: [u8; RAW_SEC_SIZE as usize],
+    /// Sub-channel of the sector, if the image stores it.
+    pub subchannel: Option<[u8; SUBCHANNEL_SIZE]>,
+}
+
+
+/// An NRG image, with its metadata, giving random access to its sectors.
+#[derive(Debug)]
+pub struct NrgImage<R> {
+    fd: R,
+    metadata: NrgMetadata,
+    tracks: Vec<Track>,
+}
+
+impl NrgImage<File> {
+    /// Opens the image `path` and reads its metadata.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<NrgImage<File>, NrgError> {
+        NrgImage::new(File::open(path)?)
+    }
+}
+
+impl<R: Read + Seek> NrgImage<R> {
+    /// Reads the metadata of the image `fd`.
+    pub fn new(mut fd: R) -> Result<NrgImage<R>, NrgError> {
+        let metadata = metadata::read_nrg_metadata(&mut fd)?;
+        Ok(NrgImage::with_metadata(fd, metadata))
+    }
+
+    /// Wraps the image `fd`, whose metadata was already read.
+    pub fn with_metadata(fd: R, metadata: NrgMetadata) -> NrgImage<R> {
+        let tracks = metadata.tracks().collect();
+        NrgImage { fd, metadata, tracks }
+    }
+
+    /// Returns the metadata of the image.
+    pub fn metadata(&self) -> &NrgMetadata {
+        &self.metadata
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.fd
+    }
+
+    /// Returns the range of the addresses of the sectors stored in the
+    /// image, from the start of the first track's pre-gap to the end of the
+    /// last track.
+    pub fn lba_range(&self) -> Range<i32> {
+        let start = self.tracks.first()
+            .map_or(0, |track| track_range(track).start);
+        let end = self.tracks.last()
+            .map_or(0, |track| track_range(track).end);
+        start..end
+    }
+
+    /// Returns the position of the sector `lba` in the image file, with the
+    /// track holding it, or None if it isn't stored in the image.
+    pub fn sector_offset(&self, lba: i32) -> Option<(u64, &Track)> {
+        let track = self.tracks.iter()
+            .find(|track| track_range(track).contains(&lba))?;
+        let delta = i64::from(lba - track.position_sectors)
+            * i64::from(track.sector_size);
+        let offset = track.index1.checked_add_signed(delta)?;
+        Some((offset, track))
+    }
+
+    /// Reads the sector `lba`.
+    ///
+    /// The tracks must be stored as raw sectors of 2352 bytes, possibly
+    /// followed by their sub-channel.
+    pub fn read_sector(&mut self, lba: i32) -> Result<NrgSector, NrgError> {
+        let (offset, track_number, sector_size) = self.sector_offset(lba)
+            .map(|(offset, track)| (offset, track.number, track.sector_size))
+            .ok_or(NrgError::SectorNotFound(lba))?;
+        if sector_size != RAW_SEC_SIZE && sector_size != RAW96_SEC_SIZE {
+            return Err(NrgError::NrgFormat(format!(
+                "Track {} has {}-byte sectors, which aren't raw sectors",
+                track_number, sector_size)));
+        }
+
+        let mut sector = NrgSector {
+            lba,
+            track_number,
+            data: [0; RAW_SEC_SIZE as usize],
+            subchannel: None,
+        };
+        self.fd.seek(SeekFrom::Start(offset))?;
+        self.fd.read_exact(&mut sector.data)?;
+        if sector_size == RAW96_SEC_SIZE {
+            let mut subchannel = [0; SUBCHANNEL_SIZE];
+            self.fd.read_exact(&mut subchannel)?;
+            sector.subchannel = Some(subchannel);
+        }
+        Ok(sector)
+    }
+}
+
+
+/// Returns the range of the addresses of `track`'s sectors, pre-gap
+/// included.
+fn track_range(track: &Track) -> Range<i32> {
+    let start = track.position_sectors - track.pregap_sectors() as i32;
+    start..track.position_sectors + track.sector_count() as i32
+}
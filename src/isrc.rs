@@ -0,0 +1,155 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to parse and validate International Standard Recording Codes.
+//!
+//! An ISRC is made of 12 characters: a two-letter country code, a
+//! three-character registrant code, the last two digits of the year of
+//! reference and a five-digit designation code, e.g. "FRZ039800212". It is
+//! often written with hyphens between its elements ("FR-Z03-98-00212").
+
+use std::fmt;
+
+use ::error::NrgError;
+
+
+/// A structurally valid ISRC, broken down into its elements.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Isrc {
+    country_code: String,
+    registrant_code: String,
+    year: u8,
+    designation: u32,
+}
+
+impl Isrc {
+    /// Parses `code`, with or without the hyphens between its elements.
+    ///
+    /// The letters must be upper case, as in the DAOX chunk and in cue
+    /// sheets.
+    pub fn parse(code: &str) -> Result<Isrc, NrgError> {
+        parse_elements(code).map_err(|reason| NrgError::NrgFormat(format!(
+            "Invalid ISRC \"{}\": {}", code, reason)))
+    }
+
+    /// Returns the country code (ISO 3166-1 alpha-2, or a code allocated by
+    /// the IFPI such as "QM").
+    pub fn country_code(&self) -> &str {
+        &self.country_code
+    }
+
+    /// Returns the registrant code, identifying the issuer of the ISRC.
+    pub fn registrant_code(&self) -> &str {
+        &self.registrant_code
+    }
+
+    /// Returns the last two digits of the year in which the ISRC was
+    /// allocated.
+    pub fn year(&self) -> u8 {
+        self.year
+    }
+
+    /// Returns the designation code, identifying the recording among those
+    /// of the registrant for the year.
+    pub fn designation(&self) -> u32 {
+        self.designation
+    }
+
+    /// Returns the reason why this ISRC looks like a placeholder rather
+    /// than an allocated code, if it does: an all-zero registrant or
+    /// designation code.
+    pub fn bogus_reason(&self) -> Option<&'static str> {
+        if self.registrant_code == "000" {
+            Some("the registrant code is 000")
+        } else if self.designation == 0 {
+            Some("the designation code is 00000")
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Isrc {
+    /// Writes the ISRC with hyphens between its elements; the alternate
+    /// form (`{:#}`) writes it without hyphens.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let separator = if f.alternate() { "" } else { "-" };
+        write!(f, "{1}{0}{2}{0}{3:02}{0}{4:05}", separator, self.country_code,
+               self.registrant_code, self.year, self.designation)
+    }
+}
+
+
+/// Returns the problem with `code`, if it isn't a valid ISRC or looks
+/// bogus (see `Isrc::bogus_reason()`).
+pub(crate) fn problem(code: &str) -> Option<String> {
+    match parse_elements(code) {
+        Ok(isrc) => isrc.bogus_reason().map(|reason| format!(
+            "ISRC \"{}\" looks bogus: {}", code, reason)),
+        Err(reason) => Some(format!("Invalid ISRC \"{}\": {}", code, reason)),
+    }
+}
+
+
+fn parse_elements(code: &str) -> Result<Isrc, &'static str> {
+    let compact: String = if code.len() == 15 {
+        let hyphens = code.char_indices()
+            .filter(|&(_, c)| c == '-')
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        if hyphens != [2, 6, 9] {
+            return Err("misplaced hyphens");
+        }
+        code.chars().filter(|&c| c != '-').collect()
+    } else {
+        code.to_string()
+    };
+    if compact.len() != 12 || !compact.is_ascii() {
+        return Err("not 12 characters long");
+    }
+
+    let (country_code, rest) = compact.split_at(2);
+    let (registrant_code, rest) = rest.split_at(3);
+    let (year, designation) = rest.split_at(2);
+    if !country_code.bytes().all(|b| b.is_ascii_uppercase()) {
+        return Err("the country code isn't made of two upper case letters");
+    }
+    if !registrant_code.bytes()
+        .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit()) {
+        return Err("the registrant code isn't made of upper case letters \
+                    and digits");
+    }
+    if !year.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("the year isn't made of two digits");
+    }
+    if !designation.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("the designation code isn't made of five digits");
+    }
+
+    Ok(Isrc {
+        country_code: country_code.to_string(),
+        registrant_code: registrant_code.to_string(),
+        year: year.parse().map_err(|_| "invalid year")?,
+        designation: designation.parse()
+            .map_err(|_| "invalid designation code")?,
+    })
+}
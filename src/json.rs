@@ -0,0 +1,586 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Minimal JSON document model, used to produce machine-readable output.
+//!
+//! The documents follow the schema identified by `SCHEMA_VERSION`, shared
+//! with the TSV output: within a version, members and record fields may be
+//! added, but are never renamed, removed, reordered or given another type.
+
+use std::fmt;
+
+use ::accuraterip::NrgAccurateRipTrack;
+use ::analysis::NrgTrackAnalysis;
+use ::cdtext::NrgCdText;
+use ::compare::NrgComparison;
+use ::disc_id::NrgDiscToc;
+#[cfg(feature = "fs")]
+use ::extraction::ExtractedFile;
+use ::gaps::NrgGap;
+use ::hidden::NrgHiddenData;
+use ::isrc::Isrc;
+use ::loudness::NrgLoudness;
+use ::metadata::metadata::NrgMetadata;
+use ::metadata::raw::NrgRawChunk;
+use ::metadata::track::Track;
+use ::red_book::{self, NrgRedBookWarning};
+use ::verify::NrgVerification;
+
+
+/// Version of the schema of the JSON and TSV output, incremented whenever a
+/// change breaks the compatibility guarantee described above.
+pub const SCHEMA_VERSION: u32 = 1;
+
+
+/// A JSON value.
+///
+/// Objects keep their members in insertion order, so that the output is
+/// stable. `Display` writes the value in compact form; the alternate flag
+/// (`{:#}`) pretty-prints it with two-space indentation.
+#[derive(Clone, Debug)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Builds an object from a list of `(name, value)` pairs.
+    pub fn object(members: Vec<(&str, JsonValue)>) -> JsonValue {
+        JsonValue::Object(members.into_iter()
+                          .map(|(name, value)| (name.to_string(), value))
+                          .collect())
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        match *self {
+            JsonValue::Null => write!(f, "null"),
+            JsonValue::Bool(b) => write!(f, "{}", b),
+            JsonValue::Int(i) => write!(f, "{}", i),
+            JsonValue::UInt(i) => write!(f, "{}", i),
+            JsonValue::Float(x) if x.is_finite() => write!(f, "{}", x),
+            JsonValue::Float(_) => write!(f, "null"),
+            JsonValue::String(ref s) => write_string(f, s),
+            JsonValue::Array(ref values) => {
+                if values.is_empty() {
+                    return write!(f, "[]");
+                }
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_newline(f, indent + 1)?;
+                    value.write(f, indent + 1)?;
+                }
+                write_newline(f, indent)?;
+                write!(f, "]")
+            },
+            JsonValue::Object(ref members) => {
+                if members.is_empty() {
+                    return write!(f, "{{}}");
+                }
+                write!(f, "{{")?;
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_newline(f, indent + 1)?;
+                    write_string(f, name)?;
+                    write!(f, ":")?;
+                    if f.alternate() {
+                        write!(f, " ")?;
+                    }
+                    value.write(f, indent + 1)?;
+                }
+                write_newline(f, indent)?;
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(b: bool) -> JsonValue {
+        JsonValue::Bool(b)
+    }
+}
+
+impl From<u8> for JsonValue {
+    fn from(i: u8) -> JsonValue {
+        JsonValue::Int(i64::from(i))
+    }
+}
+
+impl From<u16> for JsonValue {
+    fn from(i: u16) -> JsonValue {
+        JsonValue::Int(i64::from(i))
+    }
+}
+
+impl From<u32> for JsonValue {
+    fn from(i: u32) -> JsonValue {
+        JsonValue::Int(i64::from(i))
+    }
+}
+
+impl From<i32> for JsonValue {
+    fn from(i: i32) -> JsonValue {
+        JsonValue::Int(i64::from(i))
+    }
+}
+
+impl From<u64> for JsonValue {
+    fn from(i: u64) -> JsonValue {
+        JsonValue::UInt(i)
+    }
+}
+
+impl From<f64> for JsonValue {
+    fn from(x: f64) -> JsonValue {
+        JsonValue::Float(x)
+    }
+}
+
+impl<'a> From<&'a str> for JsonValue {
+    fn from(s: &'a str) -> JsonValue {
+        JsonValue::String(s.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(s: String) -> JsonValue {
+        JsonValue::String(s)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(option: Option<T>) -> JsonValue {
+        option.map_or(JsonValue::Null, Into::into)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Vec<T>> for JsonValue {
+    fn from(values: Vec<T>) -> JsonValue {
+        JsonValue::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+
+/// Writes `s` as a JSON string literal.
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+
+/// Starts a new line indented `indent` times, if pretty-printing.
+fn write_newline(f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+    if f.alternate() {
+        write!(f, "\n{:1$}", "", indent * 2)?;
+    }
+    Ok(())
+}
+
+
+/// Converts `metadata` into a JSON document.
+///
+/// Besides the contents of the parsed chunks, the document includes the
+/// merged track list, the disc's length and identifiers, the warnings
+/// raised by the metadata, and the Red Book limits exceeded by the image.
+pub fn metadata_to_json(metadata: &NrgMetadata) -> JsonValue {
+    let tracks: Vec<JsonValue> = metadata.tracks()
+        .map(|track| track_to_json(&track))
+        .collect();
+    JsonValue::object(vec![
+        ("file_size", metadata.file_size().into()),
+        ("nrg_version", metadata.nrg_version().into()),
+        ("chunk_offset", metadata.chunk_offset().into()),
+        ("sector_size", metadata.sector_size().into()),
+        ("disc", disc_to_json(metadata)),
+        ("tracks", JsonValue::Array(tracks)),
+        ("chunks", chunks_to_json(metadata)),
+        ("skipped_chunks", metadata.skipped_chunk_ids().into()),
+        ("skipped_chunk_regions", JsonValue::Array(metadata.skipped_chunks()
+            .iter()
+            .map(|chunk| JsonValue::object(vec![
+                ("id", chunk.id.as_str().into()),
+                ("offset", chunk.offset.into()),
+                ("size", chunk.size.into()),
+            ]))
+            .collect())),
+        ("warnings", metadata.warnings().into()),
+        ("red_book_warnings", red_book_to_json(&red_book::check(metadata))),
+    ])
+}
+
+
+/// Builds the computed information about the whole disc.
+fn disc_to_json(metadata: &NrgMetadata) -> JsonValue {
+    let toc = NrgDiscToc::from_metadata(metadata);
+    JsonValue::object(vec![
+        ("length_sectors", metadata.total_sectors().into()),
+        ("length_seconds", metadata.total_duration().into()),
+        ("length_msf", metadata.total_length().to_string().into()),
+        ("cddb_disc_id", toc.as_ref().map(|toc| {
+            format!("{:08X}", toc.cddb_disc_id())
+        }).into()),
+        ("musicbrainz_disc_id", toc.as_ref().map(|toc| {
+            toc.musicbrainz_disc_id()
+        }).into()),
+    ])
+}
+
+
+fn track_to_json(track: &Track) -> JsonValue {
+    JsonValue::object(vec![
+        ("number", track.number.into()),
+        ("mode", track.mode.into()),
+        ("isrc", track.isrc.clone().into()),
+        ("isrc_elements", track.isrc.as_ref()
+         .and_then(|isrc| Isrc::parse(isrc).ok())
+         .map_or(JsonValue::Null, |isrc| isrc_to_json(&isrc))),
+        ("title", track.title.clone().into()),
+        ("position_sectors", track.position_sectors.into()),
+        ("pregap_sectors", track.pregap_sectors().into()),
+        ("length_sectors", track.sector_count().into()),
+        ("length_seconds", track.duration().into()),
+        ("length_msf", track.length().to_string().into()),
+        ("length_bytes", track.byte_count().into()),
+        ("sector_size", track.sector_size.into()),
+        ("index0", track.index0.into()),
+        ("index1", track.index1.into()),
+        ("track_end", track.track_end.into()),
+    ])
+}
+
+
+/// Converts the elements of an ISRC into a JSON object.
+fn isrc_to_json(isrc: &Isrc) -> JsonValue {
+    JsonValue::object(vec![
+        ("country_code", isrc.country_code().into()),
+        ("registrant_code", isrc.registrant_code().into()),
+        ("year", isrc.year().into()),
+        ("designation", isrc.designation().into()),
+    ])
+}
+
+
+/// Converts a detected gap into a JSON object.
+pub fn gap_to_json(gap: &NrgGap) -> JsonValue {
+    JsonValue::object(vec![
+        ("track_number", gap.track_number.into()),
+        ("sectors", gap.sectors.into()),
+    ])
+}
+
+
+/// Converts the data hidden after the last track into a JSON object.
+pub fn hidden_data_to_json(hidden: &NrgHiddenData) -> JsonValue {
+    JsonValue::object(vec![
+        ("start", hidden.range.start.into()),
+        ("end", hidden.range.end.into()),
+        ("sector_size", hidden.sector_size.into()),
+        ("sectors", hidden.sector_count().into()),
+        ("nonzero_bytes", hidden.nonzero_bytes.into()),
+    ])
+}
+
+
+/// Converts the analysis of a track into a JSON object; the lengths are in
+/// samples, the levels in dBFS (null if the track is silent).
+pub fn track_analysis_to_json(analysis: &NrgTrackAnalysis) -> JsonValue {
+    JsonValue::object(vec![
+        ("track_number", analysis.track_number.into()),
+        ("samples", analysis.samples.into()),
+        ("leading_silence", analysis.leading_silence.into()),
+        ("trailing_silence", analysis.trailing_silence.into()),
+        ("audio_samples", analysis.audio_samples().into()),
+        ("silent", analysis.is_silent().into()),
+        ("peak_dbfs", analysis.peak_dbfs().into()),
+        ("rms_dbfs", analysis.rms_dbfs().into()),
+        ("clipped_samples", analysis.clipped_samples.into()),
+    ])
+}
+
+
+/// Converts the AccurateRip checksums of a track into a JSON object.
+pub fn accuraterip_to_json(track: &NrgAccurateRipTrack) -> JsonValue {
+    JsonValue::object(vec![
+        ("track_number", track.track_number.into()),
+        ("crc_v1", format!("{:08X}", track.crc_v1).into()),
+        ("crc_v2", format!("{:08X}", track.crc_v2).into()),
+        ("confidence", track.confidence.into()),
+        ("accurate", track.is_accurate().into()),
+    ])
+}
+
+
+/// Converts the description of an extracted file into a JSON object.
+#[cfg(feature = "fs")]
+pub fn extracted_file_to_json(file: &ExtractedFile) -> JsonValue {
+    JsonValue::object(vec![
+        ("path", file.path.as_str().into()),
+        ("track_number", file.track_number.into()),
+        ("audio_bytes", file.audio_bytes.into()),
+        ("source_ranges", JsonValue::Array(file.source_ranges.iter()
+            .map(|range| JsonValue::object(vec![
+                ("start", range.start.into()),
+                ("end", range.end.into()),
+            ]))
+            .collect())),
+        ("sectors", file.sectors.into()),
+        ("crc32", file.crc32.map(|crc32| format!("{:08X}", crc32)).into()),
+        ("loudness", file.loudness.as_ref().map(loudness_to_json).into()),
+        ("verified", file.verified.into()),
+        ("bad_sectors", file.bad_sectors.clone().into()),
+    ])
+}
+
+
+/// Converts a loudness measurement into a JSON object, with the integrated
+/// loudness in LUFS, the ReplayGain gain in dB, and the sample peak.
+pub fn loudness_to_json(loudness: &NrgLoudness) -> JsonValue {
+    JsonValue::object(vec![
+        ("integrated", loudness.integrated.into()),
+        ("replaygain_gain", loudness.replaygain_gain().into()),
+        ("peak", loudness.peak.into()),
+    ])
+}
+
+
+/// Converts the results of nrgrip::verify::verify() into a JSON object.
+pub fn verification_to_json(verification: &NrgVerification) -> JsonValue {
+    let checks = verification.checks.iter().map(|check| JsonValue::object(vec![
+        ("name", check.name.into()),
+        ("passed", check.passed().into()),
+        ("problems", check.problems.clone().into()),
+    ])).collect();
+    let regions = verification.suspicious_regions.iter()
+        .map(|region| JsonValue::object(vec![
+            ("track_number", region.track_number.into()),
+            ("first_sector", region.first_sector.into()),
+            ("sectors", region.sectors.into()),
+            ("kind", region.kind.to_string().into()),
+        ])).collect();
+    JsonValue::object(vec![
+        ("checks", JsonValue::Array(checks)),
+        ("suspicious_regions", JsonValue::Array(regions)),
+        ("red_book_warnings",
+         red_book_to_json(&verification.red_book_warnings)),
+        ("audio_sha1", verification.audio_sha1.clone().into()),
+        ("passed", verification.passed().into()),
+    ])
+}
+
+
+/// Converts Red Book warnings into an array of objects holding the kind of
+/// the warning, the track number and the length in sectors (null if not
+/// relevant), and the description of the warning.
+fn red_book_to_json(warnings: &[NrgRedBookWarning]) -> JsonValue {
+    JsonValue::Array(warnings.iter()
+        .map(|warning| JsonValue::object(vec![
+            ("kind", warning.kind().into()),
+            ("track_number", warning.track_number().into()),
+            ("length_sectors",
+             warning.length().map(|length| length.to_sectors()).into()),
+            ("message", warning.to_string().into()),
+        ]))
+        .collect())
+}
+
+
+/// Converts the results of nrgrip::compare::compare() into a JSON object.
+pub fn comparison_to_json(comparison: &NrgComparison) -> JsonValue {
+    let differences = comparison.differences.iter()
+        .map(|difference| JsonValue::object(vec![
+            ("first_sector", difference.first_sector.into()),
+            ("sectors", difference.sectors.into()),
+        ])).collect();
+    JsonValue::object(vec![
+        ("toc_differences", comparison.toc_differences.clone().into()),
+        ("sectors", vec![comparison.sectors.0, comparison.sectors.1].into()),
+        ("offset", JsonValue::Int(comparison.offset)),
+        ("differing_sectors", comparison.differing_sectors().into()),
+        ("differences", JsonValue::Array(differences)),
+        ("result", comparison.result().into()),
+    ])
+}
+
+
+/// Converts a raw chunk into a JSON object, whose payload is a hexadecimal
+/// string.
+pub fn raw_chunk_to_json(chunk: &NrgRawChunk) -> JsonValue {
+    JsonValue::object(vec![
+        ("id", chunk.id.as_str().into()),
+        ("offset", chunk.offset.into()),
+        ("size", (chunk.payload.len() as u64).into()),
+        ("payload", hex_string(&chunk.payload).into()),
+    ])
+}
+
+
+/// Converts a raw chunk into a JSON object listing it, without its payload:
+/// its ID, offset, size and status ("parsed", "skipped" or "unknown").
+pub fn chunk_listing_to_json(chunk: &NrgRawChunk) -> JsonValue {
+    JsonValue::object(vec![
+        ("id", chunk.id.as_str().into()),
+        ("offset", chunk.offset.into()),
+        ("size", (chunk.payload.len() as u64).into()),
+        ("status", chunk.status().to_string().into()),
+    ])
+}
+
+
+/// Converts the CD-Text information of a block into a JSON object.
+fn cd_text_to_json(cd_text: &NrgCdText) -> JsonValue {
+    let tracks = cd_text.tracks.iter().map(|track| JsonValue::object(vec![
+        ("title", track.title.as_str().into()),
+        ("performer", track.performer.as_str().into()),
+        ("songwriter", track.songwriter.as_str().into()),
+        ("composer", track.composer.as_str().into()),
+        ("arranger", track.arranger.as_str().into()),
+        ("message", track.message.as_str().into()),
+        ("isrc", track.isrc.as_str().into()),
+    ])).collect();
+    JsonValue::object(vec![
+        ("title", cd_text.title.as_str().into()),
+        ("performer", cd_text.performer.as_str().into()),
+        ("songwriter", cd_text.songwriter.as_str().into()),
+        ("composer", cd_text.composer.as_str().into()),
+        ("arranger", cd_text.arranger.as_str().into()),
+        ("message", cd_text.message.as_str().into()),
+        ("genre_code", cd_text.genre_code.into()),
+        ("genre", cd_text.genre_name().into()),
+        ("upc", cd_text.upc.as_str().into()),
+        ("tracks", JsonValue::Array(tracks)),
+    ])
+}
+
+
+/// Builds an object holding the raw contents of the parsed chunks.
+fn chunks_to_json(metadata: &NrgMetadata) -> JsonValue {
+    let mut chunks = Vec::new();
+
+    if let Some(chunk) = metadata.cuex_chunk() {
+        let tracks = chunk.tracks().iter().map(|track| JsonValue::object(vec![
+            ("mode", track.mode().into()),
+            ("track_number", track.track_number().into()),
+            ("index_number", track.index_number().into()),
+            ("padding", track.padding().into()),
+            ("position_sectors", track.position_sectors().into()),
+        ])).collect();
+        chunks.push(("CUEX", JsonValue::object(vec![
+            ("size", chunk.size().into()),
+            ("tracks", JsonValue::Array(tracks)),
+        ])));
+    }
+
+    if let Some(chunk) = metadata.daox_chunk() {
+        let tracks = chunk.tracks().iter().map(|track| JsonValue::object(vec![
+            ("isrc", track.isrc().into()),
+            ("sector_size", track.sector_size().into()),
+            ("data_mode", track.data_mode().into()),
+            ("unknown", track.unknown().into()),
+            ("index0", track.index0().into()),
+            ("index1", track.index1().into()),
+            ("track_end", track.track_end().into()),
+        ])).collect();
+        chunks.push(("DAOX", JsonValue::object(vec![
+            ("size", chunk.size().into()),
+            ("size2", chunk.size2().into()),
+            ("upc", chunk.upc().into()),
+            ("padding", chunk.padding().into()),
+            ("toc_type", chunk.toc_type().into()),
+            ("first_track", chunk.first_track().into()),
+            ("last_track", chunk.last_track().into()),
+            ("tracks", JsonValue::Array(tracks)),
+        ])));
+    }
+
+    if let Some(chunk) = metadata.cdtx_chunk() {
+        let blocks = chunk.blocks().iter().map(|block| JsonValue::object(vec![
+            ("number", block.number.into()),
+            ("language", block.language.into()),
+            ("language_name", block.language_name().into()),
+            ("text", cd_text_to_json(&block.text)),
+        ])).collect();
+        chunks.push(("CDTX", JsonValue::object(vec![
+            ("size", chunk.size().into()),
+            ("blocks", JsonValue::Array(blocks)),
+        ])));
+    }
+
+    if let Some(chunk) = metadata.sinf_chunk() {
+        chunks.push(("SINF", JsonValue::object(vec![
+            ("size", chunk.size().into()),
+            ("nb_tracks", chunk.nb_tracks().into()),
+        ])));
+    }
+
+    if let Some(chunk) = metadata.mtyp_chunk() {
+        chunks.push(("MTYP", JsonValue::object(vec![
+            ("size", chunk.size().into()),
+            ("unknown", chunk.unknown().into()),
+        ])));
+    }
+
+    if let Some(chunk) = metadata.afnm_chunk() {
+        let names: Vec<JsonValue> = chunk.tracks().iter()
+            .map(|track| track.name().into())
+            .collect();
+        chunks.push(("AFNM", JsonValue::object(vec![
+            ("size", chunk.size().into()),
+            ("tracks", JsonValue::Array(names)),
+        ])));
+    }
+
+    JsonValue::object(chunks)
+}
+
+
+/// Returns the hexadecimal representation of `bytes`.
+pub(crate) fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
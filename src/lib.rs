@@ -21,6 +21,11 @@
 // IN THE SOFTWARE.
 
 //! Extracts audio data and metadata from an NRG image of an audio CD.
+//!
+//! The metadata parser works on any `Read + Seek` source, such as an
+//! in-memory `std::io::Cursor`. The modules writing files (cue sheet and audio
+//! extraction) depend on the `fs` feature, enabled by default; disable it to
+//! build the parser for targets without a file system, such as wasm32.
 
 #![warn(box_pointers,
         missing_copy_implementations,
@@ -33,7 +38,93 @@
         unused_qualifications,
         variant_size_differences)]
 
+#[macro_use]
+extern crate log;
+extern crate md5;
+extern crate sha1;
+extern crate unicode_normalization;
+#[cfg(feature = "audio")]
+extern crate cpal;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "fs")]
+extern crate sha2;
+#[cfg(feature = "online")]
+extern crate serde_json;
+#[cfg(feature = "opus")]
+extern crate audiopus;
+#[cfg(any(feature = "opus", feature = "vorbis"))]
+extern crate ogg;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+
+#[macro_use]
+mod trace;
+
 pub mod error;
 pub mod metadata;
+pub mod msf;
+#[cfg(feature = "fs")]
 pub mod cue_sheet;
 pub mod raw_audio;
+pub mod gaps;
+pub mod hidden;
+pub mod cancel;
+pub mod analysis;
+pub mod sectors;
+pub mod disc_id;
+pub mod json;
+pub mod tsv;
+pub mod text;
+pub mod verify;
+pub mod red_book;
+pub mod isrc;
+pub mod compare;
+pub mod checksums;
+pub mod accuraterip;
+pub mod emphasis;
+pub mod loudness;
+pub mod wav;
+pub mod flac;
+pub mod resample;
+pub mod cdtext;
+pub mod unicode;
+#[cfg(feature = "fs")]
+pub mod extraction;
+#[cfg(feature = "fs")]
+pub mod metrics;
+#[cfg(feature = "fs")]
+pub mod pipeline;
+#[cfg(feature = "fs")]
+pub mod encoder;
+#[cfg(feature = "opus")]
+pub mod opus;
+#[cfg(feature = "mp3")]
+pub mod mp3;
+#[cfg(feature = "vorbis")]
+pub mod vorbis;
+#[cfg(all(feature = "fs", any(unix, windows)))]
+pub mod reader;
+pub mod image;
+pub mod samples;
+#[cfg(feature = "fs")]
+pub mod convert;
+#[cfg(feature = "fs")]
+pub mod tags;
+#[cfg(feature = "fs")]
+pub mod manifest;
+#[cfg(feature = "fs")]
+pub mod provenance;
+pub mod writer;
+pub mod editor;
+pub mod repair;
+pub mod upgrade;
+pub mod sessions;
+#[cfg(feature = "tokio")]
+pub mod async_io;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "audio")]
+pub mod play;
+#[cfg(feature = "online")]
+pub mod musicbrainz;
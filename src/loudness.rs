@@ -0,0 +1,254 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Loudness and peak measurement of CD audio, following EBU R128 (ITU-R
+//! BS.1770), from which the ReplayGain 2.0 values are derived.
+//!
+//! The `LoudnessMeter` is fed with 16-bit little-endian stereo (or mono)
+//! samples at 44.1 kHz, and yields an `NrgLoudness`; the loudness of an
+//! album is computed from the measurements of its tracks.
+
+use std::f64::consts::PI;
+use std::fmt;
+
+use ::raw_audio::SAMPLE_RATE;
+
+/// Loudness targeted by ReplayGain 2.0, in LUFS.
+pub const REPLAYGAIN_REFERENCE: f64 = -18.0;
+
+/// The gating blocks are 400 ms long, and overlap by 75 %: they are made of
+/// four 100 ms sub-blocks.
+const SUB_BLOCK_FRAMES: u32 = 4410;
+const BLOCK_SUB_BLOCKS: usize = 4;
+
+/// Gates of the integrated loudness: absolute in LUFS, relative in LU.
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+
+/// Samples are 16-bit long.
+const SAMPLE_SIZE: usize = 2;
+
+
+/// Second-order IIR filter.
+#[derive(Clone, Copy, Debug)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Biquad {
+        Biquad { b, a, x: [0.0; 2], y: [0.0; 2] }
+    }
+
+    /// High-shelf stage of the K-weighting filter, modelling the acoustic
+    /// effect of the head.
+    fn shelf() -> Biquad {
+        let f0 = 1_681.974_450_955_533;
+        let gain = 3.999_843_853_973_347;
+        let q = 0.707_175_236_955_419_6;
+        let k = (PI * f0 / f64::from(SAMPLE_RATE)).tan();
+        let vh = 10f64.powf(gain / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        let a0 = 1.0 + k / q + k * k;
+        Biquad::new([(vh + vb * k / q + k * k) / a0,
+                     2.0 * (k * k - vh) / a0,
+                     (vh - vb * k / q + k * k) / a0],
+                    [2.0 * (k * k - 1.0) / a0,
+                     (1.0 - k / q + k * k) / a0])
+    }
+
+    /// High-pass stage of the K-weighting filter (RLB weighting).
+    fn high_pass() -> Biquad {
+        let f0 = 38.135_470_876_024_44;
+        let q = 0.500_327_037_323_877_3;
+        let k = (PI * f0 / f64::from(SAMPLE_RATE)).tan();
+        let a0 = 1.0 + k / q + k * k;
+        Biquad::new([1.0, -2.0, 1.0],
+                    [2.0 * (k * k - 1.0) / a0,
+                     (1.0 - k / q + k * k) / a0])
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0] - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+
+/// Measures the loudness and the peak of the audio data fed to it.
+#[derive(Clone, Debug)]
+pub struct LoudnessMeter {
+    filters: Vec<[Biquad; 2]>,
+    weight: f64,
+    pending: Vec<u8>,
+    energy: f64,
+    frames: u32,
+    sub_blocks: Vec<f64>,
+    peak: f64,
+}
+
+impl LoudnessMeter {
+    /// Returns a meter for stereo audio.
+    pub fn new() -> LoudnessMeter {
+        LoudnessMeter::with_channels(2, 1.0)
+    }
+
+    /// Returns a meter for mono audio, measured as if it was played on both
+    /// loudspeakers.
+    pub fn mono() -> LoudnessMeter {
+        LoudnessMeter::with_channels(1, 2.0)
+    }
+
+    fn with_channels(channels: usize, weight: f64) -> LoudnessMeter {
+        let filters = [Biquad::shelf(), Biquad::high_pass()];
+        LoudnessMeter {
+            filters: vec![filters; channels],
+            weight,
+            pending: Vec::new(),
+            energy: 0.0,
+            frames: 0,
+            sub_blocks: Vec::new(),
+            peak: 0.0,
+        }
+    }
+
+    /// Adds `bytes` to the measured audio data.
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+        let frame_size = SAMPLE_SIZE * self.filters.len();
+        let nb_frames = self.pending.len() / frame_size;
+        for frame in self.pending[..nb_frames * frame_size]
+            .chunks(frame_size) {
+            for (filters, sample) in self.filters.iter_mut()
+                .zip(frame.chunks(SAMPLE_SIZE)) {
+                let sample = i16::from_le_bytes([sample[0], sample[1]]);
+                let x = f64::from(sample) / 32_768.0;
+                self.peak = self.peak.max(x.abs());
+                let shelved = filters[0].process(x);
+                let y = filters[1].process(shelved);
+                self.energy += self.weight * y * y;
+            }
+            self.frames += 1;
+            if self.frames == SUB_BLOCK_FRAMES {
+                self.sub_blocks.push(self.energy);
+                self.energy = 0.0;
+                self.frames = 0;
+            }
+        }
+        self.pending.drain(..nb_frames * frame_size);
+    }
+
+    /// Returns the measurement of the audio data added so far.
+    ///
+    /// The samples following the last complete 100 ms sub-block are only
+    /// taken into account for the peak.
+    pub fn value(&self) -> NrgLoudness {
+        let block_frames = f64::from(SUB_BLOCK_FRAMES)
+            * BLOCK_SUB_BLOCKS as f64;
+        let blocks: Vec<f64> = self.sub_blocks.windows(BLOCK_SUB_BLOCKS)
+            .map(|block| block.iter().sum::<f64>() / block_frames)
+            .collect();
+        NrgLoudness {
+            integrated: gated_loudness(&blocks),
+            peak: self.peak,
+            blocks,
+        }
+    }
+}
+
+impl Default for LoudnessMeter {
+    fn default() -> LoudnessMeter {
+        LoudnessMeter::new()
+    }
+}
+
+
+/// Loudness and peak of a track or an album.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NrgLoudness {
+    /// Integrated loudness, in LUFS; None if the audio is silent or shorter
+    /// than 400 ms.
+    pub integrated: Option<f64>,
+    /// Sample peak, relative to the full scale.
+    pub peak: f64,
+    /// Mean square of the gating blocks.
+    blocks: Vec<f64>,
+}
+
+impl NrgLoudness {
+    /// Computes the loudness of an album from the measurements of its
+    /// tracks, as if they were measured as a whole.
+    pub fn album(tracks: &[NrgLoudness]) -> NrgLoudness {
+        let blocks: Vec<f64> = tracks.iter()
+            .flat_map(|track| track.blocks.iter().cloned())
+            .collect();
+        NrgLoudness {
+            integrated: gated_loudness(&blocks),
+            peak: tracks.iter().map(|track| track.peak).fold(0.0, f64::max),
+            blocks,
+        }
+    }
+
+    /// Returns the ReplayGain 2.0 gain, in dB.
+    pub fn replaygain_gain(&self) -> Option<f64> {
+        self.integrated.map(|loudness| REPLAYGAIN_REFERENCE - loudness)
+    }
+}
+
+impl fmt::Display for NrgLoudness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.integrated, self.replaygain_gain()) {
+            (Some(loudness), Some(gain)) =>
+                write!(f, "{:.1} LUFS (gain {:+.2} dB)", loudness, gain)?,
+            _ => write!(f, "n/a")?,
+        }
+        write!(f, ", peak {:.6}", self.peak)
+    }
+}
+
+
+/// Returns the loudness of a block whose mean square is `energy`, in LUFS.
+fn block_loudness(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+
+/// Computes the integrated loudness of the gating `blocks`, in LUFS.
+fn gated_loudness(blocks: &[f64]) -> Option<f64> {
+    let mean_loudness = |threshold: f64| {
+        let gated: Vec<f64> = blocks.iter().cloned()
+            .filter(|&energy| block_loudness(energy) > threshold)
+            .collect();
+        if gated.is_empty() {
+            return None;
+        }
+        Some(block_loudness(gated.iter().sum::<f64>() / gated.len() as f64))
+    };
+    let relative_gate = mean_loudness(ABSOLUTE_GATE)? + RELATIVE_GATE;
+    mean_loudness(relative_gate.max(ABSOLUTE_GATE))
+}
@@ -0,0 +1,98 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to write a SHA-256 manifest of the files produced from an image,
+//! so that their integrity can be checked later with `sha256sum -c`.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use ::error::NrgError;
+
+/// Name of the manifest file, written in the output directory.
+pub const MANIFEST_NAME: &str = "manifest.sha256";
+
+
+/// Writes the SHA-256 of `files` to the manifest of the directory `dir`
+/// (the current directory if it is empty), and returns its path.
+///
+/// The manifest uses the format of `sha256sum`: one line per file, with its
+/// hash in hexadecimal, two spaces and its path relative to `dir`. The
+/// entries of an existing manifest are kept, unless they name one of
+/// `files`, so that the images extracted to the same directory share its
+/// manifest.
+pub fn write_manifest(dir: &Path, files: &[PathBuf])
+                      -> Result<PathBuf, NrgError> {
+    let manifest_path = dir.join(MANIFEST_NAME);
+
+    let mut entries = Vec::new();
+    for file in files {
+        let hash = hash_file(file)?;
+        debug!("SHA-256 of \"{}\": {}", file.display(), hash);
+        entries.push((hash, relative_name(dir, file)?));
+    }
+
+    let mut lines: Vec<String> = match fs::read_to_string(&manifest_path) {
+        Ok(contents) => contents.lines()
+            .filter(|line| {
+                let name = line.split_once("  ").map(|(_, name)| name);
+                !entries.iter().any(|entry| Some(entry.1.as_str()) == name)
+            })
+            .map(str::to_string)
+            .collect(),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err.into()),
+    };
+    lines.extend(entries.into_iter()
+                 .map(|(hash, name)| format!("{}  {}", hash, name)));
+
+    info!("Writing manifest to \"{}\"", manifest_path.display());
+    let mut fd = File::create(&manifest_path)?;
+    for line in lines {
+        writeln!(fd, "{}", line)?;
+    }
+    Ok(manifest_path)
+}
+
+
+/// Returns the path of `file` relative to `dir`, or its absolute path if it
+/// is outside `dir`.
+fn relative_name(dir: &Path, file: &Path) -> Result<String, NrgError> {
+    let name = match file.strip_prefix(dir) {
+        Ok(name) => name.to_path_buf(),
+        Err(_) if file.is_absolute() => file.to_path_buf(),
+        Err(_) => env::current_dir()?.join(file),
+    };
+    Ok(name.to_string_lossy().into_owned())
+}
+
+
+/// Returns the SHA-256 of the contents of `path`, in hexadecimal.
+pub(crate) fn hash_file(path: &Path) -> Result<String, NrgError> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
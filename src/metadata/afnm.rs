@@ -23,78 +23,86 @@
 //! NRG AFNM chunk data structure and associated functions.
 
 use std::fmt;
-use std::fs::File;
 use std::io::prelude::*;
-use std::path::Path;
 
 use ::error::NrgError;
 use super::readers::*;
 
 
-#[derive( Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct NrgAfnm {
-    pub size: u32,
-    pub tracks: Vec<NrgAfnmTrack>,
+    pub(super) size: u32,
+    pub(super) tracks: Vec<NrgAfnmTrack>,
 }
 
 impl NrgAfnm {
     pub fn new() -> NrgAfnm {
-        NrgAfnm {
-            size: 0,
-            tracks: Vec::new(),
-        }
+        NrgAfnm::default()
+    }
+
+    /// Returns the size of the chunk, in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the audio file names of the tracks.
+    pub fn tracks(&self) -> &[NrgAfnmTrack] {
+        &self.tracks
     }
 }
 
 impl fmt::Display for NrgAfnm {
  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(write!(f, "Chunk ID: AFNM\n\
-                        Chunk size: {} Bytes", self.size));
+        write!(f, "Chunk ID: AFNM\n\
+                   Chunk size: {} Bytes", self.size)?;
         if self.tracks.is_empty() {
-            try!(write!(f, "\nNo AFNM tracks!"));
+            write!(f, "\nNo AFNM tracks!")?;
         } else {
             for track in &self.tracks {
-                try!(write!(f, "\n\
-                                Track:\n\
-                                {}", track));
+                write!(f, "\n\
+                           Track:\n\
+                           {}", track)?;
             }
         }
         Ok(())
     }
 }
 
-#[derive( Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct NrgAfnmTrack {
-    pub name: String,
+    pub(super) name: String,
 }
 
 impl NrgAfnmTrack {
     pub fn new() -> NrgAfnmTrack {
-        NrgAfnmTrack {
-            name: String::new(),
-        }
+        NrgAfnmTrack::default()
+    }
+
+    /// Returns the name of the audio file the track was burnt from.
+    pub fn name(&self) -> &str {
+        &self.name
     }
 }
 
 impl fmt::Display for NrgAfnmTrack {
  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(write!(f, "Chunk name: {} \n", self.name));
+        write!(f, "Chunk name: {} \n", self.name)?;
         Ok(())
     }
 }
 
 /// Reads the Media Type (?) chunk (AFNM).
-pub fn read_nrg_afnm(fd: &mut File) -> Result<NrgAfnm, NrgError> {
+pub fn read_nrg_afnm<R: Read>(fd: &mut R) -> Result<NrgAfnm, NrgError> {
     let mut chunk = NrgAfnm::new();
-    chunk.size = try!(read_u32(fd));
+    chunk.size = read_chunk_size(fd, "AFNM")?;
     let mut bytes_read = 0;
-    let mut name = String::new();;
+    let mut name = String::new();
     let mut track = NrgAfnmTrack::new();
     while bytes_read < chunk.size{
         let mut buffer = [0; 1];
-        try!(fd.read_exact(&mut buffer));
+        fd.read_exact(&mut buffer)?;
         if buffer[0] == 0 {
-            println!("{:?}", name);
+            debug!("AFNM track name: {:?}", name);
             track.name = name;
             chunk.tracks.push(track);
             track = NrgAfnmTrack::new();
@@ -104,6 +112,5 @@ pub fn read_nrg_afnm(fd: &mut File) -> Result<NrgAfnm, NrgError> {
         }
         bytes_read+=1;
     }
-    assert_eq!(bytes_read, chunk.size);
     Ok(chunk)
 }
@@ -0,0 +1,119 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! NRG CDTX chunk data structure and associated functions.
+
+use std::fmt;
+use std::io::Read;
+
+use ::cdtext::{self, NrgCdText, NrgCdTextBlock};
+use ::error::NrgError;
+use super::readers::read_chunk_size;
+
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NrgCdtx {
+    pub(super) size: u32,
+    pub(super) blocks: Vec<NrgCdTextBlock>,
+}
+
+impl NrgCdtx {
+    pub fn new() -> NrgCdtx {
+        NrgCdtx::default()
+    }
+
+    /// Returns the size of the chunk, in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the CD-Text blocks, one per language, ordered by number.
+    pub fn blocks(&self) -> &[NrgCdTextBlock] {
+        &self.blocks
+    }
+
+    /// Returns the CD-Text information in the language `language`, or in
+    /// the language of the first block if there is no such block or if
+    /// `language` is `None`.
+    pub fn text(&self, language: Option<u8>) -> Option<&NrgCdText> {
+        cdtext::select_block(&self.blocks, language).map(|block| &block.text)
+    }
+}
+
+impl fmt::Display for NrgCdtx {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Chunk ID: CDTX\n\
+                   Chunk description: CD-Text\n\
+                   Chunk size: {} Bytes", self.size)?;
+        for block in &self.blocks {
+            let text = &block.text;
+            write!(f, "\nBlock {}: language 0x{:02X} ({})\n\
+                       \tDisc:",
+                   block.number, block.language,
+                   block.language_name().unwrap_or("unknown"))?;
+            write_fields(f, &[
+                ("Title", &text.title),
+                ("Performer", &text.performer),
+                ("Songwriter", &text.songwriter),
+                ("Composer", &text.composer),
+                ("Arranger", &text.arranger),
+                ("Message", &text.message),
+                ("Genre", &text.genre_name().unwrap_or_default()),
+                ("UPC/EAN", &text.upc),
+            ])?;
+            for (i, track) in text.tracks.iter().enumerate() {
+                write!(f, "\n\tTrack #{}:", i + 1)?;
+                write_fields(f, &[
+                    ("Title", &track.title),
+                    ("Performer", &track.performer),
+                    ("Songwriter", &track.songwriter),
+                    ("Composer", &track.composer),
+                    ("Arranger", &track.arranger),
+                    ("Message", &track.message),
+                    ("ISRC", &track.isrc),
+                ])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Writes the non-empty `fields`, as names and values, to `f`.
+fn write_fields(f: &mut fmt::Formatter, fields: &[(&str, &str)])
+                -> fmt::Result {
+    for &(name, value) in fields.iter().filter(|field| !field.1.is_empty()) {
+        write!(f, "\n\t\t{}: \"{}\"", name, value)?;
+    }
+    Ok(())
+}
+
+
+/// Reads the CD-Text chunk (CDTX).
+pub fn read_nrg_cdtx<R: Read>(fd: &mut R) -> Result<NrgCdtx, NrgError> {
+    let mut chunk = NrgCdtx::new();
+    chunk.size = read_chunk_size(fd, "CDTX")?;
+    let mut packs = vec![0u8; chunk.size as usize];
+    fd.read_exact(&mut packs)?;
+    chunk.blocks = cdtext::parse_packs(&packs)?;
+    Ok(chunk)
+}
@@ -23,39 +23,48 @@
 //! NRG CUEX chunk data structure and associated functions.
 
 use std::fmt;
-use std::fs::File;
+use std::io::{Read, Seek};
 
 use ::error::NrgError;
+use ::msf::Msf;
 use super::readers::*;
 
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct NrgCuex {
-    pub size: u32,
-    pub tracks: Vec<NrgCuexTrack>,
+    pub(super) size: u32,
+    pub(super) tracks: Vec<NrgCuexTrack>,
 }
 
 impl NrgCuex {
     pub fn new() -> NrgCuex {
-        NrgCuex {
-            size: 0,
-            tracks: Vec::new(),
-        }
+        NrgCuex::default()
+    }
+
+    /// Returns the size of the chunk, in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the entries of the cue sheet, including the lead-in and lead-out
+    /// areas.
+    pub fn tracks(&self) -> &[NrgCuexTrack] {
+        &self.tracks
     }
 }
 
 impl fmt::Display for NrgCuex {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(write!(f, "Chunk ID: CUEX\n\
-                        Chunk description: Cue Sheet\n\
-                        Chunk size: {} Bytes", self.size));
+        write!(f, "Chunk ID: CUEX\n\
+                   Chunk description: Cue Sheet\n\
+                   Chunk size: {} Bytes", self.size)?;
         if self.tracks.is_empty() {
-            try!(write!(f, "\nNo CUEX tracks!"));
+            write!(f, "\nNo CUEX tracks!")?;
         } else {
             for track in &self.tracks {
-                try!(write!(f, "\n\
-                                Track:\n\
-                                {}", track));
+                write!(f, "\n\
+                           Track:\n\
+                           {}", track)?;
             }
         }
         Ok(())
@@ -63,51 +72,73 @@ impl fmt::Display for NrgCuex {
 }
 
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct NrgCuexTrack {
-    pub mode: u8,
-    pub track_number: u8,
-    pub index_number: u8,
-    pub padding: u8,
-    pub position_sectors: i32,
+    pub(super) mode: u8,
+    pub(super) track_number: u8,
+    pub(super) index_number: u8,
+    pub(super) padding: u8,
+    pub(super) position_sectors: i32,
 }
 
 impl NrgCuexTrack {
     pub fn new() -> NrgCuexTrack {
-        NrgCuexTrack {
-            mode: 0,
-            track_number: 0,
-            index_number: 0,
-            padding: 0,
-            position_sectors: 0,
-        }
+        NrgCuexTrack::default()
+    }
+
+    /// Returns the mode of the track (0x01 for audio, 0x21 for non
+    /// copyright-protected audio, 0x41 for data).
+    pub fn mode(&self) -> u8 {
+        self.mode
+    }
+
+    /// Returns the track number (0 for the lead-in area, 0xAA for the lead-out
+    /// area).
+    pub fn track_number(&self) -> u8 {
+        self.track_number
+    }
+
+    /// Returns the index number.
+    pub fn index_number(&self) -> u8 {
+        self.index_number
+    }
+
+    /// Returns the padding byte (should be 0).
+    pub fn padding(&self) -> u8 {
+        self.padding
+    }
+
+    /// Returns the position of the index, in sectors, relative to track 1
+    /// index 1.
+    pub fn position_sectors(&self) -> i32 {
+        self.position_sectors
     }
 }
 
 impl fmt::Display for NrgCuexTrack {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(writeln!(f, "\tMode: 0x{:02X}", self.mode));
+        writeln!(f, "\tMode: 0x{:02X}", self.mode)?;
 
-        try!(write!(f, "\tTrack number: "));
+        write!(f, "\tTrack number: ")?;
         if self.track_number == 0 {
-            try!(writeln!(f, "0 (lead-in area)"));
+            writeln!(f, "0 (lead-in area)")?;
         } else if self.track_number == 0xAA {
-            try!(writeln!(f, "0xAA (lead-out area)"));
+            writeln!(f, "0xAA (lead-out area)")?;
         } else {
-            try!(writeln!(f, "{}", self.track_number));
+            writeln!(f, "{}", self.track_number)?;
         }
 
-        try!(writeln!(f, "\tIndex number: {}", self.index_number));
+        writeln!(f, "\tIndex number: {}", self.index_number)?;
 
         if self.padding != 0 {
-            try!(writeln!(f, "\tPadding: {} (Warning: should be 0!)",
-                          self.padding));
+            writeln!(f, "\tPadding: {} (Warning: should be 0!)",
+                     self.padding)?;
         }
 
-        // Audio CDs are played at a 75 sectors per second rate:
-        let position_seconds: f64 = (self.position_sectors as f64) / 75.0;
-        write!(f, "\tPosition: {} sectors ({:.2} seconds)",
-               self.position_sectors, position_seconds)
+        let sign = if self.position_sectors < 0 { "-" } else { "" };
+        write!(f, "\tPosition: {} sectors ({}{})",
+               self.position_sectors, sign,
+               Msf::from_sectors(self.position_sectors.unsigned_abs()))
     }
 }
 
@@ -129,19 +160,25 @@ impl fmt::Display for NrgCuexTrack {
 ///
 /// - one last track block like the ones above, for the lead-out area
 ///   (optional?)
-pub fn read_nrg_cuex(fd: &mut File) -> Result<NrgCuex, NrgError> {
+pub fn read_nrg_cuex<R: Read + Seek>(fd: &mut R)
+                                     -> Result<NrgCuex, NrgError> {
     let mut chunk = NrgCuex::new();
-    chunk.size = try!(read_u32(fd));
+    chunk.size = read_chunk_size(fd, "CUEX")?;
+    if !chunk.size.is_multiple_of(8) {
+        return Err(NrgError::NrgChunkSize("CUEX".to_string(), chunk.size));
+    }
     let mut bytes_read = 0;
 
     // Read all the 8-byte track info
     while bytes_read < chunk.size {
-        chunk.tracks.push(try!(read_nrg_cuex_track(fd)));
+        let entry_number = chunk.tracks.len() + 1;
+        chunk.tracks.push(read_with_context(
+            fd, Some("CUEX"),
+            || format!("parsing CUEX track block {}", entry_number),
+            read_nrg_cuex_track)?);
         bytes_read += 8;
     }
 
-    assert_eq!(bytes_read, chunk.size);
-
     Ok(chunk)
 }
 
@@ -150,12 +187,13 @@ pub fn read_nrg_cuex(fd: &mut File) -> Result<NrgCuex, NrgError> {
 ///
 /// See the documentation for read_nrg_cuex() for the format of the track
 /// blocks.
-fn read_nrg_cuex_track(fd: &mut File) -> Result<NrgCuexTrack, NrgError> {
+fn read_nrg_cuex_track<R: Read>(fd: &mut R)
+                                -> Result<NrgCuexTrack, NrgError> {
     let mut track = NrgCuexTrack::new();
-    track.mode = try!(read_u8(fd));
-    track.track_number = try!(read_u8_bcd(fd));
-    track.index_number = try!(read_u8_bcd(fd));
-    track.padding = try!(read_u8(fd));
-    track.position_sectors = try!(read_u32(fd)) as i32;
+    track.mode = read_u8(fd)?;
+    track.track_number = read_u8_bcd(fd)?;
+    track.index_number = read_u8_bcd(fd)?;
+    track.padding = read_u8(fd)?;
+    track.position_sectors = read_u32(fd)? as i32;
     Ok(track)
 }
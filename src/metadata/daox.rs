@@ -23,70 +23,110 @@
 //! NRG DAOX chunk data structure and associated functions.
 
 use std::fmt;
-use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
 
 use ::error::NrgError;
 use super::readers::*;
 
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct NrgDaox {
-    pub size: u32,
-    pub size2: u32,
-    pub upc: String,
-    pub padding: u8,
-    pub toc_type: u16,
-    pub first_track: u8,
-    pub last_track: u8,
-    pub tracks: Vec<NrgDaoxTrack>,
+    pub(super) size: u32,
+    pub(super) size2: u32,
+    pub(super) size2_little_endian: bool,
+    pub(super) upc: String,
+    pub(super) padding: u8,
+    pub(super) toc_type: u16,
+    pub(super) first_track: u8,
+    pub(super) last_track: u8,
+    pub(super) tracks: Vec<NrgDaoxTrack>,
 }
 
 impl NrgDaox {
     pub fn new() -> NrgDaox {
-        NrgDaox {
-            size: 0,
-            size2: 0,
-            upc: String::new(),
-            padding: 0,
-            toc_type: 0,
-            first_track: 0,
-            last_track: 0,
-            tracks: Vec::new(),
-        }
+        NrgDaox::default()
+    }
+
+    /// Returns the size of the chunk, in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the second size field of the chunk, decoded as little endian
+    /// if `is_size2_little_endian()`.
+    pub fn size2(&self) -> u32 {
+        self.size2
+    }
+
+    /// Tells whether the second size field is written in little endian, as
+    /// it sometimes is, instead of big endian.
+    pub fn is_size2_little_endian(&self) -> bool {
+        self.size2_little_endian
+    }
+
+    /// Returns the UPC of the disc (empty if absent).
+    pub fn upc(&self) -> &str {
+        &self.upc
+    }
+
+    /// Returns the padding byte (should be 0).
+    pub fn padding(&self) -> u8 {
+        self.padding
+    }
+
+    /// Returns the TOC type.
+    pub fn toc_type(&self) -> u16 {
+        self.toc_type
+    }
+
+    /// Returns the first track in the session.
+    pub fn first_track(&self) -> u8 {
+        self.first_track
+    }
+
+    /// Returns the last track in the session.
+    pub fn last_track(&self) -> u8 {
+        self.last_track
+    }
+
+    /// Returns the track blocks.
+    pub fn tracks(&self) -> &[NrgDaoxTrack] {
+        &self.tracks
     }
 }
 
 impl fmt::Display for NrgDaox {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(writeln!(f, "Chunk ID: DAOX\n\
-                          Chunk description: DAO (Disc At Once) Information\n\
-                          Chunk size: {} Bytes\n\
-                          Chunk size 2: {}\n\
-                          UPC: \"{}\"",
-                      self.size,
-                      self.size2,
-                      self.upc));
+        writeln!(f, "Chunk ID: DAOX\n\
+                     Chunk description: DAO (Disc At Once) Information\n\
+                     Chunk size: {} Bytes\n\
+                     Chunk size 2: {}{}\n\
+                     UPC: \"{}\"",
+                 self.size,
+                 self.size2,
+                 if self.size2_little_endian { " (little endian)" } else { "" },
+                 self.upc)?;
 
         if self.padding != 0 {
-            try!(writeln!(f, "Padding: {} (Warning: should be 0!)",
-                          self.padding));
+            writeln!(f, "Padding: {} (Warning: should be 0!)",
+                     self.padding)?;
         }
 
-        try!(write!(f, "TOC type: 0x{:04X}\n\
-                        First track in the session: {}\n\
-                        Last track in the session: {}",
-                    self.toc_type,
-                    self.first_track,
-                    self.last_track));
+        write!(f, "TOC type: 0x{:04X}\n\
+                   First track in the session: {}\n\
+                   Last track in the session: {}",
+               self.toc_type,
+               self.first_track,
+               self.last_track)?;
 
         if self.tracks.is_empty() {
-            try!(write!(f, "\nNo DAOX tracks!"));
+            write!(f, "\nNo DAOX tracks!")?;
         } else {
             let mut i = 1;
             for track in &self.tracks {
-                try!(write!(f, "\n\
-                                Track {:02}:\n\
-                                {}", i, track));
+                write!(f, "\n\
+                           Track {:02}:\n\
+                           {}", i, track)?;
                 i += 1;
             }
         }
@@ -96,44 +136,73 @@ impl fmt::Display for NrgDaox {
 }
 
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct NrgDaoxTrack {
-    pub isrc: String,
-    pub sector_size: u16,
-    pub data_mode: u16,
-    pub unknown: u16,
-    pub index0: u64,
-    pub index1: u64,
-    pub track_end: u64,
+    pub(super) isrc: String,
+    pub(super) sector_size: u16,
+    pub(super) data_mode: u16,
+    pub(super) unknown: u16,
+    pub(super) index0: u64,
+    pub(super) index1: u64,
+    pub(super) track_end: u64,
 }
 
 impl NrgDaoxTrack {
     pub fn new() -> NrgDaoxTrack {
-        NrgDaoxTrack {
-            isrc: String::new(),
-            sector_size: 0,
-            data_mode: 0,
-            unknown: 0,
-            index0: 0,
-            index1: 0,
-            track_end: 0,
-        }
+        NrgDaoxTrack::default()
+    }
+
+    /// Returns the ISRC of the track (empty if absent).
+    pub fn isrc(&self) -> &str {
+        &self.isrc
+    }
+
+    /// Returns the sector size in the image file, in bytes.
+    pub fn sector_size(&self) -> u16 {
+        self.sector_size
+    }
+
+    /// Returns the mode of the data in the image file.
+    pub fn data_mode(&self) -> u16 {
+        self.data_mode
+    }
+
+    /// Returns the unknown field (should be 0x0001).
+    pub fn unknown(&self) -> u16 {
+        self.unknown
+    }
+
+    /// Returns the position of the pre-gap in the image file, in bytes.
+    pub fn index0(&self) -> u64 {
+        self.index0
+    }
+
+    /// Returns the position of the start of the track in the image file, in
+    /// bytes.
+    pub fn index1(&self) -> u64 {
+        self.index1
+    }
+
+    /// Returns the position of the end of the track + 1 in the image file, in
+    /// bytes.
+    pub fn track_end(&self) -> u64 {
+        self.track_end
     }
 }
 
 impl fmt::Display for NrgDaoxTrack {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(writeln!(f, "\tISRC: \"{}\"\n\
-                          \tSector size in the image file: {} Bytes\n\
-                          \tMode of the data in the image file: 0x{:04X}",
-                      self.isrc,
-                      self.sector_size,
-                      self.data_mode));
+        writeln!(f, "\tISRC: \"{}\"\n\
+                     \tSector size in the image file: {} Bytes\n\
+                     \tMode of the data in the image file: 0x{:04X}",
+                 self.isrc,
+                 self.sector_size,
+                 self.data_mode)?;
 
         if self.unknown != 0x0001 {
-            try!(writeln!(f, "\tUnknown field: 0x{:04X} \
-                              (Warning: should be 0x0001!)",
-                          self.unknown));
+            writeln!(f, "\tUnknown field: 0x{:04X} \
+                         (Warning: should be 0x0001!)",
+                     self.unknown)?;
         }
 
         write!(f, "\tIndex0 (Pre-gap): {} Bytes\n\
@@ -166,35 +235,51 @@ impl fmt::Display for NrgDaoxTrack {
 /// - 8 B: Index0 (Pre-gap) (bytes)
 /// - 8 B: Index1 (Start of track) (bytes)
 /// - 8 B: End of track + 1 (bytes)
-pub fn read_nrg_daox(fd: &mut File) -> Result<NrgDaox, NrgError> {
+pub fn read_nrg_daox<R: Read + Seek>(fd: &mut R)
+                                     -> Result<NrgDaox, NrgError> {
     let mut chunk = NrgDaox::new();
-    chunk.size = try!(read_u32(fd));
+    chunk.size = read_chunk_size(fd, "DAOX")?;
+    if chunk.size < 22 || !(chunk.size - 22).is_multiple_of(42) {
+        return Err(NrgError::NrgChunkSize("DAOX".to_string(), chunk.size));
+    }
     let mut bytes_read = 0;
 
-    chunk.size2 = try!(read_u32(fd));
+    // The second size is sometimes little endian: it is then decoded as such
+    // if that matches the first size
+    chunk.size2 = read_u32(fd)?;
+    if chunk.size2 != chunk.size {
+        fd.seek(SeekFrom::Current(-4))?;
+        let size2 = read_u32_le(fd)?;
+        if size2 == chunk.size {
+            chunk.size2 = size2;
+            chunk.size2_little_endian = true;
+        }
+    }
     bytes_read += 4; // 32 bits
 
-    chunk.upc = try!(read_sized_string(fd, 13));
+    chunk.upc = read_sized_string(fd, 13)?;
     bytes_read += 13;
 
-    chunk.padding = try!(read_u8(fd));
+    chunk.padding = read_u8(fd)?;
     bytes_read += 1;
 
-    chunk.toc_type = try!(read_u16(fd));
+    chunk.toc_type = read_u16(fd)?;
     bytes_read += 2;
 
-    chunk.first_track = try!(read_u8(fd));
-    chunk.last_track = try!(read_u8(fd));
+    chunk.first_track = read_u8(fd)?;
+    chunk.last_track = read_u8(fd)?;
     bytes_read += 2;
 
     // Read all the 42-byte track info
     while bytes_read < chunk.size {
-        chunk.tracks.push(try!(read_nrg_daox_track(fd)));
+        let track_number = chunk.tracks.len() + 1;
+        chunk.tracks.push(read_with_context(
+            fd, Some("DAOX"),
+            || format!("parsing DAOX track {}", track_number),
+            read_nrg_daox_track)?);
         bytes_read += 42;
     }
 
-    assert_eq!(bytes_read, chunk.size);
-
     Ok(chunk)
 }
 
@@ -203,14 +288,15 @@ pub fn read_nrg_daox(fd: &mut File) -> Result<NrgDaox, NrgError> {
 ///
 /// See the documentation for read_nrg_daox() for the format of the track
 /// blocks.
-fn read_nrg_daox_track(fd: &mut File) -> Result<NrgDaoxTrack, NrgError> {
+fn read_nrg_daox_track<R: Read>(fd: &mut R)
+                                -> Result<NrgDaoxTrack, NrgError> {
     let mut track = NrgDaoxTrack::new();
-    track.isrc = try!(read_sized_string(fd, 12));
-    track.sector_size = try!(read_u16(fd));
-    track.data_mode = try!(read_u16(fd));
-    track.unknown = try!(read_u16(fd));
-    track.index0 = try!(read_u64(fd));
-    track.index1 = try!(read_u64(fd));
-    track.track_end = try!(read_u64(fd));
+    track.isrc = read_sized_string(fd, 12)?;
+    track.sector_size = read_u16(fd)?;
+    track.data_mode = read_u16(fd)?;
+    track.unknown = read_u16(fd)?;
+    track.index0 = read_u64(fd)?;
+    track.index1 = read_u64(fd)?;
+    track.track_end = read_u64(fd)?;
     Ok(track)
 }
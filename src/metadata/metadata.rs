@@ -23,40 +23,105 @@
 //! NRG metadata structure, storing the contents of all of the NRG chunks.
 
 use std::fmt;
+use std::ops::Range;
+
+use ::cdtext::NrgCdText;
+use ::isrc;
+use ::msf::Msf;
 
 use super::cuex::NrgCuex;
 use super::daox::NrgDaox;
 use super::sinf::NrgSinf;
 use super::mtyp::NrgMtyp;
 use super::afnm::NrgAfnm;
+use super::cdtx::NrgCdtx;
+use super::toc::NrgToc;
+use super::track::{self, Track};
 
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct NrgMetadata {
-    pub file_size: u64,
-    pub nrg_version: u8,
-    pub chunk_offset: u64,
-    pub cuex_chunk: Option<NrgCuex>,
-    pub daox_chunk: Option<NrgDaox>,
-    pub sinf_chunk: Option<NrgSinf>,
-    pub mtyp_chunk: Option<NrgMtyp>,
-    pub afnm_chunk: Option<NrgAfnm>,
-    pub skipped_chunks: Vec<String>,
+    pub(super) file_size: u64,
+    pub(super) nrg_version: u8,
+    pub(super) chunk_offset: u64,
+    pub(super) cuex_chunk: Option<NrgCuex>,
+    pub(super) daox_chunk: Option<NrgDaox>,
+    pub(super) cdtx_chunk: Option<NrgCdtx>,
+    pub(super) sinf_chunk: Option<NrgSinf>,
+    pub(super) mtyp_chunk: Option<NrgMtyp>,
+    pub(super) afnm_chunk: Option<NrgAfnm>,
+    pub(super) skipped_chunks: Vec<NrgSkippedChunk>,
+    pub(super) titles: Vec<String>,
+    pub(super) cd_text_language: Option<u8>,
 }
 
 impl NrgMetadata {
     pub fn new() -> NrgMetadata {
-        NrgMetadata {
-            file_size: 0,
-            nrg_version: 0,
-            chunk_offset: 0,
-            cuex_chunk: None,
-            daox_chunk: None,
-            sinf_chunk: None,
-            mtyp_chunk: None,
-            afnm_chunk: None,
-            skipped_chunks: Vec::new(),
-        }
+        NrgMetadata::default()
+    }
+
+    /// Returns the size of the image file, in bytes.
+    pub fn file_size(&self) -> u64 {
+        self.file_size
+    }
+
+    /// Returns the version of the NRG format (1 or 2).
+    pub fn nrg_version(&self) -> u8 {
+        self.nrg_version
+    }
+
+    /// Returns the offset of the first chunk in the image file.
+    pub fn chunk_offset(&self) -> u64 {
+        self.chunk_offset
+    }
+
+    /// Returns the cue sheet (CUEX chunk), if present.
+    pub fn cuex_chunk(&self) -> Option<&NrgCuex> {
+        self.cuex_chunk.as_ref()
+    }
+
+    /// Returns the Disc-At-Once information (DAOX chunk), if present.
+    pub fn daox_chunk(&self) -> Option<&NrgDaox> {
+        self.daox_chunk.as_ref()
+    }
+
+    /// Returns the CD-Text blocks (CDTX chunk), if present.
+    pub fn cdtx_chunk(&self) -> Option<&NrgCdtx> {
+        self.cdtx_chunk.as_ref()
+    }
+
+    /// Returns the CD-Text information of the image, if any, in the
+    /// language set with `set_cd_text_language()` or else in the language
+    /// of the first block.
+    pub fn cd_text(&self) -> Option<&NrgCdText> {
+        self.cdtx_chunk.as_ref()
+            .and_then(|chunk| chunk.text(self.cd_text_language))
+    }
+
+    /// Returns the session information (SINF chunk), if present.
+    pub fn sinf_chunk(&self) -> Option<&NrgSinf> {
+        self.sinf_chunk.as_ref()
+    }
+
+    /// Returns the media type (MTYP chunk), if present.
+    pub fn mtyp_chunk(&self) -> Option<&NrgMtyp> {
+        self.mtyp_chunk.as_ref()
+    }
+
+    /// Returns the audio file names (AFNM chunk), if present.
+    pub fn afnm_chunk(&self) -> Option<&NrgAfnm> {
+        self.afnm_chunk.as_ref()
+    }
+
+    /// Returns the chunks present in the image but not parsed, in the order
+    /// of the image.
+    pub fn skipped_chunks(&self) -> &[NrgSkippedChunk] {
+        &self.skipped_chunks
+    }
+
+    /// Returns the IDs of the chunks present in the image but not parsed.
+    pub fn skipped_chunk_ids(&self) -> Vec<&str> {
+        self.skipped_chunks.iter().map(|chunk| chunk.id.as_str()).collect()
     }
 
     /// Returns the index1 of the first DAOX track in `metadata`, or 0 if there
@@ -88,6 +153,143 @@ impl NrgMetadata {
         self.chunk_offset
     }
 
+    /// Sets the titles of the tracks, e.g. looked up online, in place of the
+    /// file names of the AFNM chunk.
+    ///
+    /// The n-th title is the title of the n-th track; the empty titles are
+    /// ignored.
+    pub fn set_titles(&mut self, titles: &[String]) {
+        self.titles = titles.to_vec();
+    }
+
+    /// Sets the preferred language of the CD-Text information, as a
+    /// language code (e.g. 0x09 for English); `None` selects the first
+    /// block.
+    pub fn set_cd_text_language(&mut self, language: Option<u8>) {
+        self.cd_text_language = language;
+    }
+
+    /// Returns an iterator over the tracks of this image.
+    ///
+    /// Each `Track` merges the data of the DAOX chunk with the matching CUEX
+    /// and AFNM entries. No tracks are returned if there is no DAOX chunk.
+    pub fn tracks(&self) -> impl Iterator<Item = Track> {
+        track::read_tracks(self).into_iter()
+    }
+
+    /// Returns the range of bytes of track number `number` in the image
+    /// file, from its index #1 (included) to the end of its last whole
+    /// sector (excluded), or `None` if there is no such track.
+    ///
+    /// A trailing partial sector, which would mean that the DAOX chunk is
+    /// inconsistent, is left out, so that the range always holds
+    /// `track_sector_count(number)` sectors.
+    pub fn track_byte_range(&self, number: u8) -> Option<Range<u64>> {
+        self.track(number).map(|track| {
+            let length = track.sector_count() * u64::from(track.sector_size);
+            track.index1..track.index1 + length
+        })
+    }
+
+    /// Returns the length of track number `number` (excluding its pre-gap),
+    /// in sectors, or `None` if there is no such track.
+    pub fn track_sector_count(&self, number: u8) -> Option<u64> {
+        self.track(number).map(|track| track.sector_count())
+    }
+
+    /// Returns the table of contents of this image: its tracks, with all
+    /// their indexes.
+    pub fn toc(&self) -> NrgToc {
+        NrgToc::from_metadata(self)
+    }
+
+    fn track(&self, number: u8) -> Option<Track> {
+        self.tracks().find(|track| track.number == number)
+    }
+
+    /// Returns the length of the disc, in sectors: the lengths of the tracks
+    /// and the pre-gaps between them, i.e. from index #1 of the first track
+    /// to the end of the last one, as the disc's running time shown by CD
+    /// players.
+    ///
+    /// The tracks may have different sector sizes (e.g. a data track
+    /// followed by audio tracks). Returns 0 if there are no DAOX tracks.
+    pub fn total_sectors(&self) -> u64 {
+        self.tracks().enumerate()
+            .map(|(i, track)| match i {
+                0 => track.sector_count(),
+                _ => track.pregap_sectors() + track.sector_count(),
+            })
+            .sum()
+    }
+
+    /// Returns the length of the disc, as defined by `total_sectors()`.
+    pub fn total_length(&self) -> Msf {
+        Msf::from_sectors(self.total_sectors() as u32)
+    }
+
+    /// Returns the duration of the disc, as defined by `total_sectors()`, in
+    /// seconds.
+    pub fn total_duration(&self) -> f64 {
+        self.total_length().as_seconds()
+    }
+
+    /// Returns the list of anomalies found in the metadata.
+    ///
+    /// These are the unexpected values that are flagged as warnings when the
+    /// metadata is displayed.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if let Some(ref chunk) = self.cuex_chunk {
+            if chunk.tracks.is_empty() {
+                warnings.push("No CUEX tracks".to_string());
+            }
+            for track in &chunk.tracks {
+                if track.padding != 0 {
+                    warnings.push(format!(
+                        "CUEX track {}, index {}: padding is {} \
+                         (should be 0)",
+                        track.track_number, track.index_number,
+                        track.padding));
+                }
+            }
+        }
+
+        if let Some(ref chunk) = self.daox_chunk {
+            if chunk.padding != 0 {
+                warnings.push(format!("DAOX padding is {} (should be 0)",
+                                      chunk.padding));
+            }
+            if chunk.tracks.is_empty() {
+                warnings.push("No DAOX tracks".to_string());
+            }
+            for (i, track) in chunk.tracks.iter().enumerate() {
+                if track.unknown != 0x0001 {
+                    warnings.push(format!(
+                        "DAOX track {:02}: unknown field is 0x{:04X} \
+                         (should be 0x0001)",
+                        i + 1, track.unknown));
+                }
+                if track.isrc.is_empty() {
+                    continue;
+                }
+                if let Some(problem) = isrc::problem(&track.isrc) {
+                    warnings.push(format!("DAOX track {:02}: {}",
+                                          i + 1, problem));
+                }
+            }
+        }
+
+        if let Some(ref chunk) = self.afnm_chunk {
+            if chunk.tracks.is_empty() {
+                warnings.push("No AFNM tracks".to_string());
+            }
+        }
+
+        warnings
+    }
+
     /// Returns the sector size of this image.
     ///
     /// This information is retrieved from the first DAOX track only; it is
@@ -106,44 +308,88 @@ impl NrgMetadata {
 
 impl fmt::Display for NrgMetadata {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(write!(f, "Image size: {} Bytes\n\
-                        NRG format version: {}\n\
-                        First NRG chunk offset: {}",
-                    self.file_size,
-                    self.nrg_version,
-                    self.chunk_offset,
-        ));
+        write!(f, "Image size: {} Bytes\n\
+                   NRG format version: {}\n\
+                   First NRG chunk offset: {}\n\
+                   Disc length: {} sectors ({})",
+               self.file_size,
+               self.nrg_version,
+               self.chunk_offset,
+               self.total_sectors(),
+               self.total_length(),
+        )?;
         match self.cuex_chunk {
             None => {},
-            Some(ref chunk) => try!(write!(f, "\n\n\
-                                               {}", chunk)),
+            Some(ref chunk) => write!(f, "\n\n\
+                                          {}", chunk)?,
         }
         match self.daox_chunk {
             None => {},
-            Some(ref chunk) => try!(write!(f, "\n\n\
-                                               {}", chunk)),
+            Some(ref chunk) => write!(f, "\n\n\
+                                          {}", chunk)?,
+        }
+        match self.cdtx_chunk {
+            None => {},
+            Some(ref chunk) => write!(f, "\n\n\
+                                          {}", chunk)?,
         }
         match self.sinf_chunk {
             None => {},
-            Some(ref chunk) => try!(write!(f, "\n\n\
-                                               {}", chunk)),
+            Some(ref chunk) => write!(f, "\n\n\
+                                          {}", chunk)?,
         }
         match self.mtyp_chunk {
             None => {},
-            Some(ref chunk) => try!(write!(f, "\n\n\
-                                               {}", chunk)),
+            Some(ref chunk) => write!(f, "\n\n\
+                                          {}", chunk)?,
         }
         match self.afnm_chunk {
             None => {},
-            Some(ref chunk) => try!(write!(f, "\n\n\
-                                               {}", chunk)),
+            Some(ref chunk) => write!(f, "\n\n\
+                                          {}", chunk)?,
+        }
+        for track in self.tracks() {
+            write!(f, "\n\n\
+                       Track {:02}:\n\
+                       {}", track.number, track)?;
         }
         if !self.skipped_chunks.is_empty() {
-            try!(write!(f, "\n\nUnhandled NRG chunks present in this image:"));
-            for chunk_id in &self.skipped_chunks {
-                try!(write!(f, " {}", chunk_id));
+            write!(f, "\n\nUnhandled NRG chunks present in this image:")?;
+            for chunk in &self.skipped_chunks {
+                write!(f, "\n{}", chunk)?;
             }
         }
         Ok(())
     }
 }
+
+
+/// A chunk present in an NRG image but skipped by read_nrg_metadata(), as
+/// nrgrip doesn't parse it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgSkippedChunk {
+    /// The chunk ID, e.g. "ETN2".
+    pub id: String,
+    /// The position of the chunk ID in the image file, in bytes.
+    pub offset: u64,
+    /// The size of the chunk's payload, in bytes.
+    pub size: u32,
+    /// The payload of the chunk, if kept (see
+    /// nrgrip::metadata::MetadataOptions::keep_skipped_payloads()).
+    pub payload: Option<Vec<u8>>,
+}
+
+impl NrgSkippedChunk {
+    /// Returns the bytes of the image file holding the chunk, including its
+    /// ID and size.
+    pub fn byte_range(&self) -> Range<u64> {
+        self.offset..self.offset + 8 + u64::from(self.size)
+    }
+}
+
+impl fmt::Display for NrgSkippedChunk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} chunk at offset {} (0x{:X}), {} Bytes", self.id,
+               self.offset, self.offset, self.size)
+    }
+}
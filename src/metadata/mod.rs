@@ -22,23 +22,69 @@
 
 //! Module to read and store the metadata from an NRG image file.
 
-use std::fs::File;
-use std::io::{Seek, SeekFrom};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
 
 use ::error::NrgError;
 
 pub mod metadata;
 pub mod cuex;
-mod daox;
-mod sinf;
-mod mtyp;
+pub mod daox;
+pub mod sinf;
+pub mod mtyp;
 pub mod afnm;
-mod readers;
+pub mod cdtx;
+pub mod track;
+pub mod toc;
+pub mod raw;
+pub mod readers;
 
-use self::metadata::NrgMetadata;
+use self::metadata::{NrgMetadata, NrgSkippedChunk};
 use self::readers::*;
 
 
+/// IDs of the chunks parsed by read_nrg_metadata().
+pub(crate) const PARSED_CHUNK_IDS: [&str; 7] =
+    ["CUEX", "DAOX", "CDTX", "SINF", "MTYP", "AFNM", "END!"];
+
+/// IDs of the chunks known to appear in NRG v2 images, but skipped by
+/// read_nrg_metadata().
+pub(crate) const SKIPPED_CHUNK_IDS: [&str; 5] =
+    ["ETN2", "DINF", "TOCT", "RELO", "VOLM"];
+
+
+/// Options controlling `read_nrg_metadata_with()`, built with chained
+/// setters.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetadataOptions {
+    keep_skipped_payloads: bool,
+    strict: bool,
+}
+
+impl MetadataOptions {
+    /// Returns the default options, those of `read_nrg_metadata()`.
+    pub fn new() -> MetadataOptions {
+        MetadataOptions::default()
+    }
+
+    /// Keeps the payload of the chunks skipped because they aren't parsed
+    /// (see NrgMetadata::skipped_chunks()); only their position and size are
+    /// recorded by default.
+    pub fn keep_skipped_payloads(mut self, keep: bool) -> MetadataOptions {
+        self.keep_skipped_payloads = keep;
+        self
+    }
+
+    /// Enables the strict mode, in which the anomalies that are otherwise
+    /// logged as warnings make the reading fail with `NrgError::Anomalies`
+    /// (see nrgrip::verify::anomalies()). Archival pipelines can use it to
+    /// reject questionable images.
+    pub fn strict(mut self, strict: bool) -> MetadataOptions {
+        self.strict = strict;
+        self
+    }
+}
+
+
 /// Reads the metadata chunks from an open NRG image file `fd`.
 ///
 /// `fd`'s offset can be anywhere when this function is called: it will be reset
@@ -47,27 +93,99 @@ use self::readers::*;
 /// In case of success, `fd`'s offset will be left after the "END!" string of
 /// the NRG footer. Otherwise, the offset is undefined and should be reset by
 /// the caller if any additional reading operations are to be done.
-pub fn read_nrg_metadata(fd: &mut File) -> Result<NrgMetadata, NrgError> {
+pub fn read_nrg_metadata<R: Read + Seek>(fd: &mut R)
+                                         -> Result<NrgMetadata, NrgError> {
+    read_nrg_metadata_with(fd, &MetadataOptions::new())
+}
+
+
+/// Reads the metadata chunks from an open NRG image file `fd`, as
+/// `read_nrg_metadata()` does, with `options`.
+pub fn read_nrg_metadata_with<R: Read + Seek>(fd: &mut R,
+                                              options: &MetadataOptions)
+                                              -> Result<NrgMetadata, NrgError> {
+    enter_span!("nrg_metadata");
     let mut nm = NrgMetadata::new();
 
     // Get the file size
-    nm.file_size = try!(fd.seek(SeekFrom::End(0)));
+    nm.file_size = fd.seek(SeekFrom::End(0))?;
 
     // Get the NRG format from the footer
-    nm.nrg_version = try!(read_nrg_version(fd, nm.file_size));
+    nm.nrg_version = read_with_context(
+        fd, None, || "reading the NRG footer".to_string(),
+        |fd| read_nrg_version(fd, nm.file_size))?;
     if nm.nrg_version != 2 {
         // We handle only NRG v2
-        return Err(NrgError::NrgFormat(
-            "NRG v1 format is not handled".to_string()));
+        return Err(NrgError::UnsupportedVersion(nm.nrg_version));
     }
 
     // Read the first chunk offset
-    nm.chunk_offset = try!(read_u64(fd));
+    nm.chunk_offset = read_with_context(
+        fd, None, || "reading the first chunk offset".to_string(),
+        read_u64)?;
 
     // Read all the chunks
-    try!(fd.seek(SeekFrom::Start(nm.chunk_offset)));
-    try!(read_nrg_chunks(fd, &mut nm));
+    debug!("NRG v{} image, first chunk at offset {}",
+           nm.nrg_version, nm.chunk_offset);
+    trace_event!(debug, version = nm.nrg_version,
+                 chunk_offset = nm.chunk_offset, "Read the NRG footer");
+    // The chunks are made of many small fields: read them through a buffer,
+    // then leave fd's offset after the "END!" chunk ID as documented
+    fd.seek(SeekFrom::Start(nm.chunk_offset))?;
+    let mut buf_fd = BufReader::new(&mut *fd);
+    read_nrg_chunks(&mut buf_fd, &mut nm, options)?;
+    let end_offset = buf_fd.stream_position()?;
+    fd.seek(SeekFrom::Start(end_offset))?;
+
+    if options.strict {
+        ::verify::check_strict(&nm)?;
+    }
+    for warning in nm.warnings() {
+        warn!("{}", warning);
+    }
+
+    Ok(nm)
+}
+
+
+/// Reads the metadata chunks from an NRG image held in memory.
+pub fn read_from_slice(bytes: &[u8]) -> Result<NrgMetadata, NrgError> {
+    read_nrg_metadata(&mut Cursor::new(bytes))
+}
+
+
+/// Parses the metadata chunks `chunks`, as read by nrgrip::metadata::raw,
+/// of an NRG v2 image of `file_size` bytes whose first chunk is at
+/// `chunk_offset`.
+///
+/// The chunks don't need to be contiguous in the image, nor to end with an
+/// "END!" chunk.
+pub(crate) fn read_raw_metadata(chunks: &[raw::NrgRawChunk], file_size: u64,
+                                chunk_offset: u64)
+                                -> Result<NrgMetadata, NrgError> {
+    let mut bytes = Vec::new();
+    // Positions of the chunks in `bytes` and in the image
+    let mut offsets = Vec::new();
+    for chunk in chunks.iter().filter(|chunk| chunk.id != "END!") {
+        offsets.push((bytes.len() as u64, chunk.offset));
+        bytes.extend(chunk.id.as_bytes());
+        bytes.extend(&(chunk.payload.len() as u32).to_be_bytes());
+        bytes.extend(&chunk.payload);
+    }
+    bytes.extend(b"END!");
 
+    let mut nm = NrgMetadata::new();
+    nm.file_size = file_size;
+    nm.nrg_version = 2;
+    nm.chunk_offset = chunk_offset;
+    read_nrg_chunks(&mut Cursor::new(bytes), &mut nm,
+                    &MetadataOptions::new())?;
+    for skipped in &mut nm.skipped_chunks {
+        if let Some(&(_, offset)) = offsets.iter()
+            .find(|&&(pos, _)| pos == skipped.offset) {
+            skipped.offset = offset;
+        }
+    }
     Ok(nm)
 }
 
@@ -80,47 +198,71 @@ pub fn read_nrg_metadata(fd: &mut File) -> Result<NrgMetadata, NrgError> {
 /// The offset is left after the main chunk ID, therefore the calling function
 /// can read the first data chunk's offset (32 bits for NRG v1 or 64 bits for
 /// NRG v2) directly without seeking.
-pub fn read_nrg_version(fd: &mut File, file_size: u64) -> Result<u8, NrgError> {
+pub fn read_nrg_version<R: Read + Seek>(fd: &mut R, file_size: u64)
+                                        -> Result<u8, NrgError> {
     if file_size < 12 {
         // Input file too small
-        return Err(NrgError::NrgFormat(
-            "Input file is to small to be an NRG image".to_string()));
+        return Err(NrgError::NotNrg(
+            "the file is too small to be an NRG image".to_string()));
     }
 
     // In NRG v2, the main footer is on the last 12 bytes
-    try!(fd.seek(SeekFrom::End(-12)));
-    let chunk_id = try!(read_nrg_chunk_id(fd));
+    fd.seek(SeekFrom::End(-12))?;
+    let chunk_id = read_nrg_chunk_id(fd)?;
     if chunk_id == "NER5" {
         return Ok(2); // NRG v2
     }
 
     // In NRG v1, the main footer is on the last 8 bytes; since we just read 4
     // bytes after seeking 12 bytes before the end, the offset is right
-    let chunk_id = try!(read_nrg_chunk_id(fd));
+    let chunk_id = read_nrg_chunk_id(fd)?;
     if chunk_id == "NERO" {
         return Ok(1); // NRG v1
     }
 
-    Err(NrgError::NrgFormat("Unknown format".to_string()))
+    Err(NrgError::NotNrg("no NRG footer".to_string()))
 }
 
 
 /// Reads all the available NRG chunks.
-///
-/// Returns the number of chunks read.
-fn read_nrg_chunks(fd: &mut File, nm: &mut NrgMetadata) -> Result<(), NrgError> {
+fn read_nrg_chunks<R: Read + Seek>(fd: &mut R, nm: &mut NrgMetadata,
+                                   options: &MetadataOptions)
+                                   -> Result<(), NrgError> {
     loop {
-        let chunk_id = try!(read_nrg_chunk_id(fd));
+        let offset = fd.stream_position()?;
+        let chunk_id = read_with_context(
+            fd, None, || "reading a chunk ID".to_string(),
+            read_nrg_chunk_id)?;
+        debug!("Reading {} chunk", chunk_id);
+        let id = Some(chunk_id.as_str());
+        let operation = || format!("parsing {} chunk", chunk_id);
         match chunk_id.as_ref() {
             "END!" => break,
-            "CUEX" => nm.cuex_chunk = Some(try!(cuex::read_nrg_cuex(fd))),
-            "DAOX" => nm.daox_chunk = Some(try!(daox::read_nrg_daox(fd))),
-            "SINF" => nm.sinf_chunk = Some(try!(sinf::read_nrg_sinf(fd))),
-            "MTYP" => nm.mtyp_chunk = Some(try!(mtyp::read_nrg_mtyp(fd))),
-            "AFNM" => nm.afnm_chunk = Some(try!(afnm::read_nrg_afnm(fd))),
-            "CDTX" | "ETN2" | "DINF" | "TOCT" | "RELO" | "VOLM" => {
-                try!(skip_chunk(fd));
-                nm.skipped_chunks.push(chunk_id);
+            "CUEX" => nm.cuex_chunk = Some(
+                read_with_context(fd, id, operation, cuex::read_nrg_cuex)?),
+            "DAOX" => nm.daox_chunk = Some(
+                read_with_context(fd, id, operation, daox::read_nrg_daox)?),
+            "CDTX" => nm.cdtx_chunk = Some(
+                read_with_context(fd, id, operation, cdtx::read_nrg_cdtx)?),
+            "SINF" => nm.sinf_chunk = Some(
+                read_with_context(fd, id, operation, sinf::read_nrg_sinf)?),
+            "MTYP" => nm.mtyp_chunk = Some(
+                read_with_context(fd, id, operation, mtyp::read_nrg_mtyp)?),
+            "AFNM" => nm.afnm_chunk = Some(
+                read_with_context(fd, id, operation, afnm::read_nrg_afnm)?),
+            skipped if SKIPPED_CHUNK_IDS.contains(&skipped) => {
+                let (size, payload) = read_with_context(
+                    fd, id, operation,
+                    |fd| skip_chunk(fd, &chunk_id,
+                                    options.keep_skipped_payloads))?;
+                info!("Skipped unhandled {} chunk ({} bytes at offset {})",
+                      chunk_id, size, offset);
+                nm.skipped_chunks.push(NrgSkippedChunk {
+                    id: chunk_id,
+                    offset,
+                    size,
+                    payload,
+                });
             },
             _      => return Err(NrgError::NrgChunkId(chunk_id)),
         }
@@ -130,14 +272,22 @@ fn read_nrg_chunks(fd: &mut File, nm: &mut NrgMetadata) -> Result<(), NrgError>
 
 
 /// Reads an NRG chunk ID (i.e. a 4-byte string) from `fd`.
-fn read_nrg_chunk_id(fd: &mut File) -> Result<String, NrgError> {
+fn read_nrg_chunk_id<R: Read>(fd: &mut R) -> Result<String, NrgError> {
     read_sized_string(fd, 4)
 }
 
 
-/// Skips a chunk.
-fn skip_chunk(fd: &mut File) -> Result<(), NrgError> {
-    let chunk_size = try!(read_u32(fd));
-    try!(fd.seek(SeekFrom::Current(chunk_size as i64)));
-    Ok(())
+/// Skips the chunk `chunk_id`, or reads its payload if `keep` is true.
+///
+/// Returns the size of the payload, and the payload if kept.
+fn skip_chunk<R: Read + Seek>(fd: &mut R, chunk_id: &str, keep: bool)
+                              -> Result<(u32, Option<Vec<u8>>), NrgError> {
+    let chunk_size = read_chunk_size(fd, chunk_id)?;
+    if keep {
+        let mut payload = vec![0; chunk_size as usize];
+        fd.read_exact(&mut payload)?;
+        return Ok((chunk_size, Some(payload)));
+    }
+    fd.seek(SeekFrom::Current(i64::from(chunk_size)))?;
+    Ok((chunk_size, None))
 }
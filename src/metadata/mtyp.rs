@@ -23,24 +23,31 @@
 //! NRG MTYP chunk data structure and associated functions.
 
 use std::fmt;
-use std::fs::File;
+use std::io::Read;
 
 use ::error::NrgError;
-use super::readers::read_u32;
+use super::readers::{read_chunk_size, read_u32};
 
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct NrgMtyp {
-    pub size: u32,
-    pub unknown: u32,
+    pub(super) size: u32,
+    pub(super) unknown: u32,
 }
 
 impl NrgMtyp {
     pub fn new() -> NrgMtyp {
-        NrgMtyp {
-            size: 0,
-            unknown: 0,
-        }
+        NrgMtyp::default()
+    }
+
+    /// Returns the size of the chunk, in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the media type (meaning unknown).
+    pub fn unknown(&self) -> u32 {
+        self.unknown
     }
 }
 
@@ -57,9 +64,9 @@ impl fmt::Display for NrgMtyp {
 
 
 /// Reads the Media Type (?) chunk (MTYP).
-pub fn read_nrg_mtyp(fd: &mut File) -> Result<NrgMtyp, NrgError> {
+pub fn read_nrg_mtyp<R: Read>(fd: &mut R) -> Result<NrgMtyp, NrgError> {
     let mut chunk = NrgMtyp::new();
-    chunk.size = try!(read_u32(fd));
-    chunk.unknown = try!(read_u32(fd));
+    chunk.size = read_chunk_size(fd, "MTYP")?;
+    chunk.unknown = read_u32(fd)?;
     Ok(chunk)
 }
@@ -0,0 +1,161 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Raw NRG chunks, read without parsing their payload.
+
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+use ::error::NrgError;
+use super::{read_nrg_version, PARSED_CHUNK_IDS, SKIPPED_CHUNK_IDS};
+use super::readers::{read_chunk_size, read_u32, read_u64};
+
+
+/// A metadata chunk of an NRG image, as stored in the image file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgRawChunk {
+    /// The chunk ID, e.g. "DAOX".
+    pub id: String,
+    /// The position of the chunk ID in the image file, in bytes.
+    pub offset: u64,
+    /// The contents of the chunk following its ID and size.
+    pub payload: Vec<u8>,
+}
+
+impl NrgRawChunk {
+    /// Returns the position of the payload in the image file, in bytes.
+    pub fn payload_offset(&self) -> u64 {
+        self.offset + 8
+    }
+
+    /// Returns how nrgrip::metadata::read_nrg_metadata() handles the chunk.
+    pub fn status(&self) -> NrgChunkStatus {
+        NrgChunkStatus::of(&self.id)
+    }
+}
+
+impl fmt::Display for NrgRawChunk {
+    /// Formats the chunk as a hexdump of its payload, with 16 bytes per line
+    /// prefixed by their position in the image file.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Chunk ID: {}\n\
+                   Chunk offset: {} (0x{:X})\n\
+                   Chunk size: {} Bytes",
+               self.id, self.offset, self.offset, self.payload.len())?;
+
+        let mut offset = self.payload_offset();
+        for line in self.payload.chunks(16) {
+            write!(f, "\n{:08X} ", offset)?;
+            for (i, byte) in line.iter().enumerate() {
+                let sep = if i == 8 { "  " } else { " " };
+                write!(f, "{}{:02X}", sep, byte)?;
+            }
+            let padding = 3 * (16 - line.len()) + usize::from(line.len() <= 8);
+            write!(f, "{:1$}  |", "", padding)?;
+            for &byte in line {
+                let c = if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                };
+                write!(f, "{}", c)?;
+            }
+            write!(f, "|")?;
+            offset += line.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+
+/// How nrgrip::metadata::read_nrg_metadata() handles a chunk.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NrgChunkStatus {
+    /// The chunk is parsed into the metadata.
+    Parsed,
+    /// The chunk is known, but skipped.
+    Skipped,
+    /// The chunk is unknown: reading the metadata fails.
+    Unknown,
+}
+
+impl NrgChunkStatus {
+    /// Returns the status of the chunks whose ID is `id`.
+    pub fn of(id: &str) -> NrgChunkStatus {
+        if PARSED_CHUNK_IDS.contains(&id) {
+            NrgChunkStatus::Parsed
+        } else if SKIPPED_CHUNK_IDS.contains(&id) {
+            NrgChunkStatus::Skipped
+        } else {
+            NrgChunkStatus::Unknown
+        }
+    }
+}
+
+impl fmt::Display for NrgChunkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            NrgChunkStatus::Parsed => "parsed",
+            NrgChunkStatus::Skipped => "skipped",
+            NrgChunkStatus::Unknown => "unknown",
+        })
+    }
+}
+
+
+/// Reads the metadata chunks of an open NRG image file `fd`, without parsing
+/// them, up to and including the "END!" chunk.
+///
+/// Unlike nrgrip::metadata::read_nrg_metadata(), this function accepts
+/// unknown chunk IDs, which makes it suitable to inspect unsupported images.
+/// NRG v1 images, whose chunks are stored the same way, are accepted too.
+pub fn read_raw_chunks<R: Read + Seek>(fd: &mut R)
+                                       -> Result<Vec<NrgRawChunk>, NrgError> {
+    let file_size = fd.seek(SeekFrom::End(0))?;
+    let mut offset = match read_nrg_version(fd, file_size)? {
+        1 => u64::from(read_u32(fd)?),
+        _ => read_u64(fd)?,
+    };
+
+    let mut chunks = Vec::new();
+    loop {
+        if offset.saturating_add(8) > file_size {
+            return Err(NrgError::NrgFormat(format!(
+                "Chunk at offset {} is past the end of the file", offset)));
+        }
+        fd.seek(SeekFrom::Start(offset))?;
+        let mut id = [0u8; 4];
+        fd.read_exact(&mut id)?;
+        let id = String::from_utf8_lossy(&id).into_owned();
+        let size = read_chunk_size(fd, &id)?;
+        let mut payload = vec![0u8; size as usize];
+        fd.read_exact(&mut payload)?;
+
+        let end = id == "END!";
+        chunks.push(NrgRawChunk { id, offset, payload });
+        if end {
+            break;
+        }
+        offset += 8 + u64::from(size);
+    }
+    Ok(chunks)
+}
@@ -20,25 +20,70 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
 // IN THE SOFTWARE.
 
-//! Miscellaneous functions to read fixed-size data from a file.
+//! Functions to read fixed-size data from a file, as stored in the NRG
+//! images.
+//!
+//! The integers are big endian unless the function's name ends with `_le`.
+//! The functions read exactly the bytes of the value from any `Read`
+//! implementor, without buffering; an early end of the input is reported
+//! as an I/O error of kind `UnexpectedEof`.
 
 use std::ffi::CString;
-use std::fs::File;
-use std::io::Read;
-use std::mem;
+use std::io::{Read, Seek};
 
 use ::error::NrgError;
 
 
+/// Calls `read` on `fd`, adding context to the error returned, if any.
+///
+/// The context holds `chunk_id`, the offset of `fd` before `read` is called,
+/// and the description of the operation returned by `operation`.
+pub fn read_with_context<T, R, F, O>(fd: &mut R, chunk_id: Option<&str>,
+                                     operation: O, read: F)
+                                     -> Result<T, NrgError>
+    where R: Read + Seek,
+          F: FnOnce(&mut R) -> Result<T, NrgError>,
+          O: FnOnce() -> String {
+    let offset = fd.stream_position()?;
+    enter_span!("nrg_chunk", chunk = chunk_id, offset = offset);
+    let result = read(fd)
+        .map_err(|err| err.context(chunk_id, offset, operation()));
+    trace_error!(result, "Can't read the metadata");
+    result
+}
+
+
+/// Maximum size of a metadata chunk, in bytes.
+///
+/// The largest chunks found in audio CD images are a few kilobytes long; this
+/// limit protects against malformed images claiming huge chunk sizes.
+pub const MAX_CHUNK_SIZE: u32 = 1 << 20;
+
+
+/// Reads the size of the chunk `chunk_id` from `fd`.
+///
+/// Returns an error if the size exceeds MAX_CHUNK_SIZE.
+pub fn read_chunk_size<R: Read>(fd: &mut R, chunk_id: &str)
+                                -> Result<u32, NrgError> {
+    let size = read_u32(fd)?;
+    if size > MAX_CHUNK_SIZE {
+        return Err(NrgError::NrgChunkSize(chunk_id.to_string(), size));
+    }
+    Ok(size)
+}
+
+
 /// Reads a String of `size` bytes from `fd`.
 ///
-/// The string will be truncated at the first null byte encountered; therefore,
-/// its length may be less than `size` characters.
-pub fn read_sized_string(fd: &mut File, size: usize)
+/// All the `size` bytes are read, but the string will be truncated at the
+/// first null byte encountered; therefore, its length may be less than
+/// `size` characters. Returns an `NrgError::String` error if the bytes kept
+/// are not valid UTF-8.
+pub fn read_sized_string<R: Read>(fd: &mut R, size: usize)
                          -> Result<String, NrgError> {
     // Read size bytes
     let mut bytes = vec!(0u8; size);
-    try!(fd.read_exact(&mut bytes));
+    fd.read_exact(&mut bytes)?;
 
     // Truncate the vector at the first null byte
     let mut i: usize = 0;
@@ -55,56 +100,63 @@ pub fn read_sized_string(fd: &mut File, size: usize)
 }
 
 
-/// Reads a 64-bit unsigned integer from `fd`.
-pub fn read_u64(fd: &mut File) -> Result<u64, NrgError> {
+/// Reads a big-endian 64-bit unsigned integer from `fd`.
+pub fn read_u64<R: Read>(fd: &mut R) -> Result<u64, NrgError> {
     let mut buf = [0u8; 8];
-    try!(fd.read_exact(&mut buf));
-    let i: u64;
-    unsafe {
-        i = mem::transmute(buf);
-    }
-    Ok(u64::from_be(i))
+    fd.read_exact(&mut buf)?;
+    Ok(u64::from_be_bytes(buf))
 }
 
 
-/// Reads a 32-bit unsigned integer from `fd`.
-pub fn read_u32(fd: &mut File) -> Result<u32, NrgError> {
+/// Reads a big-endian 32-bit unsigned integer from `fd`.
+pub fn read_u32<R: Read>(fd: &mut R) -> Result<u32, NrgError> {
     let mut buf = [0u8; 4];
-    try!(fd.read_exact(&mut buf));
-    let i: u32;
-    unsafe {
-        i = mem::transmute(buf);
-    }
-    Ok(u32::from_be(i))
+    fd.read_exact(&mut buf)?;
+    Ok(u32::from_be_bytes(buf))
+}
+
+
+/// Reads a little-endian 32-bit unsigned integer from `fd`.
+pub fn read_u32_le<R: Read>(fd: &mut R) -> Result<u32, NrgError> {
+    let mut buf = [0u8; 4];
+    fd.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
 }
 
 
-/// Reads a 16-bit unsigned integer from `fd`.
-pub fn read_u16(fd: &mut File) -> Result<u16, NrgError> {
+/// Reads a big-endian 16-bit unsigned integer from `fd`.
+pub fn read_u16<R: Read>(fd: &mut R) -> Result<u16, NrgError> {
     let mut buf = [0u8; 2];
-    try!(fd.read_exact(&mut buf));
-    let i: u16;
-    unsafe {
-        i = mem::transmute(buf);
-    }
-    Ok(u16::from_be(i))
+    fd.read_exact(&mut buf)?;
+    Ok(u16::from_be_bytes(buf))
+}
+
+
+/// Reads a little-endian 16-bit unsigned integer from `fd`.
+pub fn read_u16_le<R: Read>(fd: &mut R) -> Result<u16, NrgError> {
+    let mut buf = [0u8; 2];
+    fd.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
 }
 
 
 /// Reads an unsigned byte from `fd`.
-pub fn read_u8(fd: &mut File) -> Result<u8, NrgError> {
+pub fn read_u8<R: Read>(fd: &mut R) -> Result<u8, NrgError> {
     let mut buf = [0u8; 1];
-    try!(fd.read_exact(&mut buf));
+    fd.read_exact(&mut buf)?;
     Ok(buf[0])
 }
 
 
 /// Reads a BCD-encoded byte from `fd`.
 ///
-/// If the decoded value is more than 99, which is not a valid binary-coded
-/// decimal value, the byte read is returned as is, without decoding.
-pub fn read_u8_bcd(fd: &mut File) -> Result<u8, NrgError> {
-    let byte = try!(read_u8(fd));
+/// The high nibble holds the tens and the low nibble the units, e.g. 0x42
+/// is decoded as 42. If the decoded value is more than 99, which is not a
+/// valid binary-coded decimal value, the byte read is returned as is,
+/// without decoding: this keeps the lead-out track number 0xAA. Units above
+/// 9 are not checked, e.g. 0x1A is decoded as 20.
+pub fn read_u8_bcd<R: Read>(fd: &mut R) -> Result<u8, NrgError> {
+    let byte = read_u8(fd)?;
     let tens = (byte >> 4) * 10;
     let units = (byte << 4) >> 4;
     let value = tens + units;
@@ -23,24 +23,31 @@
 //! NRG SINF chunk data structure and associated functions.
 
 use std::fmt;
-use std::fs::File;
+use std::io::Read;
 
 use ::error::NrgError;
-use super::readers::read_u32;
+use super::readers::{read_chunk_size, read_u32};
 
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct NrgSinf {
-    pub size: u32,
-    pub nb_tracks: u32,
+    pub(super) size: u32,
+    pub(super) nb_tracks: u32,
 }
 
 impl NrgSinf {
     pub fn new() -> NrgSinf {
-        NrgSinf {
-            size: 0,
-            nb_tracks: 0,
-        }
+        NrgSinf::default()
+    }
+
+    /// Returns the size of the chunk, in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the number of tracks in the session.
+    pub fn nb_tracks(&self) -> u32 {
+        self.nb_tracks
     }
 }
 
@@ -57,9 +64,9 @@ impl fmt::Display for NrgSinf {
 
 
 /// Reads the NRG Session Information chunk (SINF).
-pub fn read_nrg_sinf(fd: &mut File) -> Result<NrgSinf, NrgError> {
+pub fn read_nrg_sinf<R: Read>(fd: &mut R) -> Result<NrgSinf, NrgError> {
     let mut chunk = NrgSinf::new();
-    chunk.size = try!(read_u32(fd));
-    chunk.nb_tracks = try!(read_u32(fd));
+    chunk.size = read_chunk_size(fd, "SINF")?;
+    chunk.nb_tracks = read_u32(fd)?;
     Ok(chunk)
 }
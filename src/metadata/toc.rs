@@ -0,0 +1,135 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Table of contents of an image, reconciling the sector positions of the
+//! CUEX chunk with the byte positions of the DAOX chunk.
+//!
+//! Only the disc-at-once images are described: the track-at-once images
+//! (ETN2 chunk) are not parsed.
+
+use super::metadata::NrgMetadata;
+use super::track::Track;
+
+
+/// Track number of the lead-out area in the CUEX entries.
+const LEAD_OUT_TRACK: u8 = 0xAA;
+
+
+/// The table of contents of an image: its tracks, with all their indexes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgToc {
+    /// The tracks, in the order of the DAOX chunk.
+    pub tracks: Vec<NrgTocTrack>,
+    /// Position of the lead-out area, in sectors, if the CUEX chunk holds
+    /// it.
+    pub lead_out_sectors: Option<i32>,
+}
+
+impl NrgToc {
+    /// Builds the table of contents of `metadata`.
+    ///
+    /// The tracks are the ones of `NrgMetadata::tracks()`, i.e. of the DAOX
+    /// chunk. Their indexes are read from the CUEX chunk; the tracks absent
+    /// from it get an index #1 at index1 of the DAOX chunk, preceded by an
+    /// index #0 if they have a pre-gap.
+    pub fn from_metadata(metadata: &NrgMetadata) -> NrgToc {
+        let entries = metadata.cuex_chunk().map_or(&[][..], |chunk| {
+            chunk.tracks()
+        });
+
+        let mut tracks = Vec::new();
+        for track in metadata.tracks() {
+            let mut indexes: Vec<NrgTocIndex> = entries.iter()
+                .filter(|entry| entry.track_number() == track.number)
+                .map(|entry| NrgTocIndex::new(&track, entry.index_number(),
+                                              entry.position_sectors()))
+                .collect();
+            if indexes.is_empty() {
+                let pregap = track.pregap_sectors() as i32;
+                if pregap > 0 {
+                    indexes.push(NrgTocIndex::new(
+                        &track, 0, track.position_sectors - pregap));
+                }
+                indexes.push(NrgTocIndex::new(&track, 1,
+                                              track.position_sectors));
+            }
+            tracks.push(NrgTocTrack { track, indexes });
+        }
+
+        let lead_out_sectors = entries.iter()
+            .find(|entry| entry.track_number() == LEAD_OUT_TRACK)
+            .map(|entry| entry.position_sectors());
+        NrgToc { tracks, lead_out_sectors }
+    }
+
+    /// Returns the track numbered `number`, if any.
+    pub fn track(&self, number: u8) -> Option<&NrgTocTrack> {
+        self.tracks.iter().find(|track| track.track.number == number)
+    }
+}
+
+
+/// A track of the table of contents.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgTocTrack {
+    /// The track, as merged from the chunks.
+    pub track: Track,
+    /// The indexes of the track, in the order of the CUEX chunk.
+    pub indexes: Vec<NrgTocIndex>,
+}
+
+impl NrgTocTrack {
+    /// Returns the index numbered `number`, if any.
+    pub fn index(&self, number: u8) -> Option<&NrgTocIndex> {
+        self.indexes.iter().find(|index| index.number == number)
+    }
+}
+
+
+/// An index of a track, located both on the disc and in the image file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgTocIndex {
+    /// Number of the index: 0 for the pre-gap, 1 for the start of the
+    /// track, and up to 99.
+    pub number: u8,
+    /// Position of the index on the disc, in sectors (track 1's index #1
+    /// being at 0).
+    pub position_sectors: i32,
+    /// Position of the index in the image file, in bytes, deduced from the
+    /// track's index1 and sector size. The positions before the start of the
+    /// file are clamped to 0.
+    pub byte_offset: u64,
+}
+
+impl NrgTocIndex {
+    fn new(track: &Track, number: u8, position_sectors: i32) -> NrgTocIndex {
+        let sectors = i64::from(position_sectors)
+            - i64::from(track.position_sectors);
+        let offset = track.index1 as i64
+            + sectors * i64::from(track.sector_size);
+        NrgTocIndex {
+            number,
+            position_sectors,
+            byte_offset: offset.max(0) as u64,
+        }
+    }
+}
@@ -0,0 +1,230 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Track data structure, merging the information of the CUEX, DAOX and AFNM
+//! chunks.
+
+use std::fmt;
+use std::io::{Read, Seek};
+use std::ops::Range;
+
+use ::error::NrgError;
+use ::isrc::Isrc;
+use ::msf::Msf;
+use ::samples::NrgSamples;
+use ::unicode;
+use super::metadata::NrgMetadata;
+
+
+/// A track of the image.
+///
+/// `mode` and `position_sectors` come from the CUEX chunk and are 0 when the
+/// track is absent from it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Track {
+    pub number: u8,
+    pub mode: u8,
+    pub isrc: Option<String>,
+    pub title: Option<String>,
+    pub position_sectors: i32,
+    pub sector_size: u16,
+    pub index0: u64,
+    pub index1: u64,
+    pub track_end: u64,
+}
+
+impl Track {
+    /// Returns the range of bytes of the track in the image file, from index1
+    /// (included) to the end of the track (excluded).
+    pub fn byte_range(&self) -> Range<u64> {
+        self.index1..self.track_end
+    }
+
+    /// Returns the range of bytes of the track's pre-gap in the image file,
+    /// from index0 (included) to index1 (excluded).
+    pub fn pregap_byte_range(&self) -> Range<u64> {
+        self.index0..self.index1
+    }
+
+    /// Returns true if the CUEX chunk flags the track as a data track.
+    pub fn is_data(&self) -> bool {
+        self.mode & 0x40 != 0
+    }
+
+    /// Returns true if the CUEX chunk flags the track as an audio track
+    /// recorded with pre-emphasis.
+    pub fn has_pre_emphasis(&self) -> bool {
+        !self.is_data() && self.mode & 0x10 != 0
+    }
+
+    /// Returns the length of the pre-gap, in sectors.
+    pub fn pregap_sectors(&self) -> u64 {
+        self.sectors(self.index1.saturating_sub(self.index0))
+    }
+
+    /// Returns the length of the track (excluding the pre-gap), in sectors.
+    pub fn sector_count(&self) -> u64 {
+        self.sectors(self.track_end.saturating_sub(self.index1))
+    }
+
+    /// Returns the length of the track (excluding the pre-gap), in bytes of
+    /// the image file.
+    pub fn byte_count(&self) -> u64 {
+        self.track_end.saturating_sub(self.index1)
+    }
+
+    /// Returns the length of the pre-gap.
+    pub fn pregap_length(&self) -> Msf {
+        Msf::from_bytes(self.index1.saturating_sub(self.index0),
+                        self.sector_size)
+    }
+
+    /// Returns the length of the track (excluding the pre-gap).
+    pub fn length(&self) -> Msf {
+        Msf::from_bytes(self.track_end.saturating_sub(self.index1),
+                        self.sector_size)
+    }
+
+    /// Returns the duration of the track (excluding the pre-gap), in seconds.
+    pub fn duration(&self) -> f64 {
+        self.length().as_seconds()
+    }
+
+    /// Returns an iterator over the stereo samples of the track (excluding
+    /// the pre-gap), read from `fd`, the image holding it.
+    ///
+    /// Fails if the track is a data track, or isn't stored as raw sectors.
+    pub fn samples<R: Read + Seek>(&self, fd: R)
+                                   -> Result<NrgSamples<R>, NrgError> {
+        if self.is_data() {
+            return Err(NrgError::NrgFormat(format!(
+                "Track {} is a data track", self.number)));
+        }
+        NrgSamples::new(fd, self.index1, self.track_end, self.sector_size)
+    }
+
+    fn sectors(&self, bytes: u64) -> u64 {
+        if self.sector_size == 0 {
+            return 0;
+        }
+        bytes / u64::from(self.sector_size)
+    }
+}
+
+impl fmt::Display for Track {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "\tMode: 0x{:02X}", self.mode)?;
+        if let Some(ref isrc) = self.isrc {
+            match Isrc::parse(isrc) {
+                Ok(parsed) =>
+                    writeln!(f, "\tISRC: \"{}\" (country {}, registrant {}, \
+                                 year {:02}, designation {:05})",
+                             isrc, parsed.country_code(),
+                             parsed.registrant_code(), parsed.year(),
+                             parsed.designation())?,
+                Err(_) => writeln!(f, "\tISRC: \"{}\" (invalid)", isrc)?,
+            }
+        }
+        if let Some(ref title) = self.title {
+            writeln!(f, "\tTitle: \"{}\"", title)?;
+        }
+        write!(f, "\tPre-gap: {} sectors ({})\n\
+                   \tLength: {} sectors ({}, {} Bytes)\n\
+                   \tBytes: {} to {}",
+               self.pregap_sectors(),
+               self.pregap_length(),
+               self.sector_count(),
+               self.length(),
+               self.byte_count(),
+               self.index1,
+               self.track_end)
+    }
+}
+
+
+/// Builds the list of tracks of `metadata`.
+///
+/// The tracks are listed from the DAOX chunk; the number of the n-th track is
+/// deduced from the DAOX first track number. The mode and position are then
+/// looked up in the CUEX chunk (index #1 of the same track number), and the
+/// title in the titles set with `NrgMetadata::set_titles()`, or else in the
+/// CD-Text information (in the preferred language), or else in the AFNM
+/// chunk (n-th audio file name, without the ".wav" extension), normalized
+/// with `nrgrip::unicode::nfc()`. The ISRC is
+/// taken from the DAOX chunk, or else from the CD-Text information.
+pub fn read_tracks(metadata: &NrgMetadata) -> Vec<Track> {
+    let daox_chunk = match metadata.daox_chunk {
+        None => return Vec::new(),
+        Some(ref chunk) => chunk,
+    };
+    let first_track = if daox_chunk.first_track == 0 {
+        1
+    } else {
+        daox_chunk.first_track
+    };
+
+    let mut tracks = Vec::new();
+    for (i, daox_track) in daox_chunk.tracks.iter().enumerate() {
+        let number = (usize::from(first_track) + i) as u8;
+
+        let cuex_track = metadata.cuex_chunk.as_ref().and_then(|chunk| {
+            chunk.tracks.iter().find(|track| {
+                track.track_number == number && track.index_number == 1
+            })
+        });
+
+        let cd_text_track = metadata.cd_text()
+            .and_then(|cd_text| cd_text.tracks.get(i));
+        let title = metadata.titles.get(i)
+            .filter(|title| !title.is_empty())
+            .cloned()
+            .or_else(|| cd_text_track
+                     .map(|track| track.title.clone())
+                     .filter(|title| !title.is_empty()))
+            .or_else(|| metadata.afnm_chunk.as_ref()
+                     .and_then(|chunk| chunk.tracks.get(i))
+                     .map(|track| track.name.replace(".wav", "")))
+            .map(|title| unicode::nfc(&title));
+
+        let isrc = if daox_track.isrc.is_empty() {
+            cd_text_track.map(|track| track.isrc.clone())
+                .filter(|isrc| !isrc.is_empty())
+        } else {
+            Some(daox_track.isrc.clone())
+        };
+
+        tracks.push(Track {
+            number,
+            mode: cuex_track.map_or(0, |track| track.mode),
+            isrc,
+            title,
+            position_sectors: cuex_track.map_or(0, |track| {
+                track.position_sectors
+            }),
+            sector_size: daox_track.sector_size,
+            index0: daox_track.index0,
+            index1: daox_track.index1,
+            track_end: daox_track.track_end,
+        });
+    }
+    tracks
+}
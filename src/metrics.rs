@@ -0,0 +1,231 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Statistics of the audio extraction, reported as it progresses.
+//!
+//! An application embedding NRGrip, e.g. a GUI ripper, passes a `Metrics`
+//! receiver to `ExtractionOptions::metrics()`; the copy loops then report
+//! the bytes read from the image, the bytes of audio data written and the
+//! sectors whose sub-channel was stripped, after each buffer. The receiver
+//! is called from the thread reading the image, so it must be quick, and
+//! it may be shared with another thread displaying the statistics, as
+//! `MetricsCounters` is.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+
+/// Receiver of the statistics of the extraction.
+///
+/// All the methods do nothing by default.
+pub trait Metrics: Send + Sync {
+    /// Called when an extraction starts.
+    fn started(&self) {}
+
+    /// Called after `bytes` were read from the image.
+    fn bytes_read(&self, bytes: u64) {
+        let _ = bytes;
+    }
+
+    /// Called after `bytes` of audio data were written to the output,
+    /// before any channel transform or encoding.
+    fn bytes_written(&self, bytes: u64) {
+        let _ = bytes;
+    }
+
+    /// Called after the sub-channel of `sectors` sectors was stripped.
+    fn sectors_stripped(&self, sectors: u64) {
+        let _ = sectors;
+    }
+
+    /// Called when an extraction ends, successfully or not, `elapsed` after
+    /// it started.
+    fn finished(&self, elapsed: Duration) {
+        let _ = elapsed;
+    }
+}
+
+
+/// Statistics of an extraction, as returned by
+/// `MetricsCounters::snapshot()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub sectors_stripped: u64,
+    /// Time elapsed since the start of the last extraction, or its duration
+    /// once it is finished.
+    pub elapsed: Duration,
+}
+
+impl MetricsSnapshot {
+    /// Returns the average throughput of the reads, in bytes per second.
+    pub fn read_throughput(&self) -> f64 {
+        let elapsed = self.elapsed.as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        self.bytes_read as f64 / elapsed
+    }
+}
+
+
+/// `Metrics` receiver adding up the statistics, which another thread can
+/// read at any time with `snapshot()`.
+///
+/// The counters add up the statistics of all the extractions they receive;
+/// the elapsed time is that of the last one.
+#[derive(Debug, Default)]
+pub struct MetricsCounters {
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    sectors_stripped: AtomicU64,
+    timing: Mutex<Timing>,
+}
+
+/// Timing of the last extraction received by `MetricsCounters`.
+#[derive(Clone, Copy, Debug, Default)]
+enum Timing {
+    #[default]
+    NotStarted,
+    Running(Instant),
+    Finished(Duration),
+}
+
+impl MetricsCounters {
+    /// Returns counters set to zero.
+    pub fn new() -> MetricsCounters {
+        MetricsCounters::default()
+    }
+
+    /// Returns the current values of the counters.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let elapsed = match self.timing() {
+            Timing::NotStarted => Duration::default(),
+            Timing::Running(start) => start.elapsed(),
+            Timing::Finished(duration) => duration,
+        };
+        MetricsSnapshot {
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            sectors_stripped: self.sectors_stripped.load(Ordering::Relaxed),
+            elapsed,
+        }
+    }
+
+    fn timing(&self) -> Timing {
+        *self.timing.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn set_timing(&self, timing: Timing) {
+        *self.timing.lock().unwrap_or_else(|err| err.into_inner()) = timing;
+    }
+}
+
+impl Metrics for MetricsCounters {
+    fn started(&self) {
+        self.set_timing(Timing::Running(Instant::now()));
+    }
+
+    fn bytes_read(&self, bytes: u64) {
+        self.bytes_read.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn bytes_written(&self, bytes: u64) {
+        self.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn sectors_stripped(&self, sectors: u64) {
+        self.sectors_stripped.fetch_add(sectors, Ordering::Relaxed);
+    }
+
+    fn finished(&self, elapsed: Duration) {
+        self.set_timing(Timing::Finished(elapsed));
+    }
+}
+
+
+/// Optional `Metrics` receiver of the extraction options, to which the
+/// statistics are forwarded.
+#[derive(Clone, Default)]
+pub(crate) struct MetricsHandle {
+    metrics: Option<Arc<dyn Metrics>>,
+}
+
+impl MetricsHandle {
+    pub(crate) fn new(metrics: Arc<dyn Metrics>) -> MetricsHandle {
+        MetricsHandle { metrics: Some(metrics) }
+    }
+
+    /// Reports the start of an extraction, whose end is reported when the
+    /// returned timer is dropped.
+    pub(crate) fn start(&self) -> MetricsTimer {
+        if let Some(ref metrics) = self.metrics {
+            metrics.started();
+        }
+        MetricsTimer { handle: self.clone(), start: Instant::now() }
+    }
+
+    pub(crate) fn bytes_read(&self, bytes: u64) {
+        if let Some(ref metrics) = self.metrics {
+            metrics.bytes_read(bytes);
+        }
+    }
+
+    pub(crate) fn bytes_written(&self, bytes: u64) {
+        if let Some(ref metrics) = self.metrics {
+            metrics.bytes_written(bytes);
+        }
+    }
+
+    pub(crate) fn sectors_stripped(&self, sectors: u64) {
+        if let Some(ref metrics) = self.metrics {
+            metrics.sectors_stripped(sectors);
+        }
+    }
+}
+
+impl fmt::Debug for MetricsHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.metrics {
+            Some(_) => write!(f, "MetricsHandle(Some(..))"),
+            None => write!(f, "MetricsHandle(None)"),
+        }
+    }
+}
+
+
+/// Reports the end of an extraction when dropped.
+pub(crate) struct MetricsTimer {
+    handle: MetricsHandle,
+    start: Instant,
+}
+
+impl Drop for MetricsTimer {
+    fn drop(&mut self) {
+        if let Some(ref metrics) = self.handle.metrics {
+            metrics.finished(self.start.elapsed());
+        }
+    }
+}
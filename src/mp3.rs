@@ -0,0 +1,367 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to encode CD audio into MP3 streams, through libmp3lame (LAME).
+//!
+//! `Mp3Writer` encodes the 16-bit little-endian PCM samples written through
+//! it, at a constant bitrate or with LAME's variable bitrate modes. The tags
+//! describing the audio are written in an ID3v2 tag at the beginning of the
+//! stream, followed by the LAME tag frame, which holds the length of the
+//! stream and the gapless playback information.
+
+use std::fmt;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::os::raw::{c_int, c_uchar, c_ushort};
+use std::ptr;
+
+use ::error::NrgError;
+use ::raw_audio::SAMPLE_RATE;
+use ::tags::NrgTags;
+
+/// Bitrates of MPEG-1 Layer III, in kbit/s.
+pub const BITRATES: [u32; 14] =
+    [32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+/// Best (lowest) variable bitrate quality.
+pub const BEST_VBR_QUALITY: u32 = 0;
+/// Worst (highest) variable bitrate quality.
+pub const WORST_VBR_QUALITY: u32 = 9;
+
+/// Number of samples per channel encoded at once.
+const CHUNK_SAMPLES: usize = 8192;
+/// Size of the buffer receiving the last frames, as required by LAME.
+const FLUSH_BUFFER_SIZE: usize = 7200;
+
+/// Values of LAME's vbr_mode.
+const VBR_OFF: c_int = 0;
+const VBR_MTRH: c_int = 4;
+/// Values of LAME's MPEG_mode.
+const JOINT_STEREO: c_int = 1;
+const MONO: c_int = 3;
+
+
+/// Opaque LAME encoder.
+#[repr(C)]
+struct LameGlobalFlags {
+    _private: [u8; 0],
+}
+
+/// LAME encoder, released when dropped.
+struct Lame(*mut LameGlobalFlags);
+
+// The encoder is only used through `&mut Mp3Writer`, and isn't bound to the
+// thread that created it.
+unsafe impl Send for Lame {}
+
+impl Drop for Lame {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { lame_close(self.0) };
+        }
+    }
+}
+
+#[link(name = "mp3lame")]
+extern "C" {
+    fn lame_init() -> *mut LameGlobalFlags;
+    fn lame_close(gfp: *mut LameGlobalFlags) -> c_int;
+    fn lame_set_num_channels(gfp: *mut LameGlobalFlags, channels: c_int)
+                             -> c_int;
+    fn lame_set_in_samplerate(gfp: *mut LameGlobalFlags, rate: c_int)
+                              -> c_int;
+    fn lame_set_mode(gfp: *mut LameGlobalFlags, mode: c_int) -> c_int;
+    fn lame_set_VBR(gfp: *mut LameGlobalFlags, mode: c_int) -> c_int;
+    fn lame_set_VBR_q(gfp: *mut LameGlobalFlags, quality: c_int) -> c_int;
+    fn lame_set_brate(gfp: *mut LameGlobalFlags, bitrate: c_int) -> c_int;
+    fn lame_set_write_id3tag_automatic(gfp: *mut LameGlobalFlags,
+                                       automatic: c_int);
+    fn lame_init_params(gfp: *mut LameGlobalFlags) -> c_int;
+    fn lame_encode_buffer(gfp: *mut LameGlobalFlags, left: *const i16,
+                          right: *const i16, samples: c_int,
+                          mp3buf: *mut c_uchar, mp3buf_size: c_int)
+                          -> c_int;
+    fn lame_encode_flush(gfp: *mut LameGlobalFlags, mp3buf: *mut c_uchar,
+                         size: c_int) -> c_int;
+    fn lame_get_lametag_frame(gfp: *const LameGlobalFlags,
+                              buffer: *mut c_uchar, size: usize) -> usize;
+    fn lame_get_id3v2_tag(gfp: *mut LameGlobalFlags, buffer: *mut c_uchar,
+                          size: usize) -> usize;
+    fn id3tag_init(gfp: *mut LameGlobalFlags);
+    fn id3tag_v2_only(gfp: *mut LameGlobalFlags);
+    fn id3tag_set_fieldvalue_utf16(gfp: *mut LameGlobalFlags,
+                                   fieldvalue: *const c_ushort) -> c_int;
+}
+
+
+/// Bitrate of an MP3 stream.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mp3Bitrate {
+    /// Constant bitrate, in kbit/s (one of `BITRATES`).
+    Constant(u32),
+    /// Variable bitrate, from 0 (best quality) to 9 (smallest files), as
+    /// LAME's `-V` option.
+    Variable(u32),
+}
+
+impl Default for Mp3Bitrate {
+    /// Returns the variable bitrate of quality 2, i.e. about 190 kbit/s.
+    fn default() -> Mp3Bitrate {
+        Mp3Bitrate::Variable(2)
+    }
+}
+
+
+/// Writer encoding the CD audio written through it into an MP3 stream.
+///
+/// The data written must be 44.1 kHz, 16-bit little-endian PCM samples, with
+/// the channels interleaved. `finish()` must be called once all the samples
+/// are written, to encode the last frames and complete the LAME tag.
+pub struct Mp3Writer<W> {
+    inner: W,
+    lame: Lame,
+    channels: usize,
+    /// Position of the first MP3 frame in `inner`, which is replaced with
+    /// the LAME tag frame at the end.
+    frames_pos: u64,
+    /// Incomplete chunk of samples.
+    pending: Vec<u8>,
+    /// Encoded frames.
+    mp3: Vec<u8>,
+}
+
+impl<W: Write + Seek> Mp3Writer<W> {
+    /// Starts an MP3 stream in `channels` channels (1 or 2), at the current
+    /// position of `inner`, encoded at `bitrate` and described by `tags`.
+    pub fn new(mut inner: W, channels: u16, bitrate: Mp3Bitrate,
+               tags: &NrgTags) -> Result<Mp3Writer<W>, NrgError> {
+        let mode = match channels {
+            1 => MONO,
+            2 => JOINT_STEREO,
+            _ => return Err(NrgError::NrgFormat(format!(
+                "Can't encode {} channels in MP3", channels))),
+        };
+        match bitrate {
+            Mp3Bitrate::Constant(kbps) if !BITRATES.contains(&kbps) =>
+                return Err(NrgError::Encoder(format!(
+                    "Invalid MP3 bitrate: {} kbit/s", kbps))),
+            Mp3Bitrate::Variable(quality) if quality > WORST_VBR_QUALITY =>
+                return Err(NrgError::Encoder(format!(
+                    "Invalid MP3 VBR quality: {}", quality))),
+            _ => (),
+        }
+
+        let lame = Lame(unsafe { lame_init() });
+        if lame.0.is_null() {
+            return Err(NrgError::Encoder(
+                "Can't initialize the MP3 encoder".to_string()));
+        }
+        unsafe {
+            lame_set_num_channels(lame.0, c_int::from(channels));
+            lame_set_in_samplerate(lame.0, SAMPLE_RATE as c_int);
+            lame_set_mode(lame.0, mode);
+            match bitrate {
+                Mp3Bitrate::Constant(kbps) => {
+                    lame_set_VBR(lame.0, VBR_OFF);
+                    lame_set_brate(lame.0, kbps as c_int);
+                },
+                Mp3Bitrate::Variable(quality) => {
+                    lame_set_VBR(lame.0, VBR_MTRH);
+                    lame_set_VBR_q(lame.0, quality as c_int);
+                },
+            }
+            lame_set_write_id3tag_automatic(lame.0, 0);
+            id3tag_init(lame.0);
+            id3tag_v2_only(lame.0);
+        }
+        for field in id3_fields(tags) {
+            let value: Vec<u16> = Some(0xFEFF)
+                .into_iter()
+                .chain(field.encode_utf16())
+                .chain(Some(0))
+                .collect();
+            let result = unsafe {
+                id3tag_set_fieldvalue_utf16(lame.0, value.as_ptr())
+            };
+            if result != 0 {
+                return Err(NrgError::Encoder(format!(
+                    "Can't write the ID3 tag \"{}\"", field)));
+            }
+        }
+        if unsafe { lame_init_params(lame.0) } < 0 {
+            return Err(NrgError::Encoder(
+                "Invalid parameters for the MP3 encoder".to_string()));
+        }
+
+        // ID3v2 tag
+        let size = unsafe { lame_get_id3v2_tag(lame.0, ptr::null_mut(), 0) };
+        let mut tag = vec![0u8; size];
+        unsafe { lame_get_id3v2_tag(lame.0, tag.as_mut_ptr(), size) };
+        inner.write_all(&tag)?;
+        let frames_pos = inner.stream_position()?;
+
+        Ok(Mp3Writer {
+            inner,
+            lame,
+            channels: usize::from(channels),
+            frames_pos,
+            pending: Vec::new(),
+            mp3: Vec::new(),
+        })
+    }
+
+    /// Encodes the last frames, writes the LAME tag frame and returns the
+    /// inner writer, positioned at the end of the stream.
+    pub fn finish(mut self) -> Result<W, NrgError> {
+        if !self.pending.len().is_multiple_of(2 * self.channels) {
+            return Err(NrgError::NrgFormat(
+                "Incomplete sample in the audio data".to_string()));
+        }
+        let pending = std::mem::take(&mut self.pending);
+        self.encode(&pending)?;
+
+        let mut buffer = vec![0u8; FLUSH_BUFFER_SIZE];
+        let size = unsafe {
+            lame_encode_flush(self.lame.0, buffer.as_mut_ptr(),
+                              buffer.len() as c_int)
+        };
+        if size < 0 {
+            return Err(lame_error(size).into());
+        }
+        self.inner.write_all(&buffer[..size as usize])?;
+
+        // The LAME tag frame replaces the empty first frame
+        let size = unsafe {
+            lame_get_lametag_frame(self.lame.0, ptr::null_mut(), 0)
+        };
+        if size > 0 {
+            let mut frame = vec![0u8; size];
+            unsafe {
+                lame_get_lametag_frame(self.lame.0, frame.as_mut_ptr(), size)
+            };
+            let end = self.inner.stream_position()?;
+            self.inner.seek(SeekFrom::Start(self.frames_pos))?;
+            self.inner.write_all(&frame)?;
+            self.inner.seek(SeekFrom::Start(end))?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    /// Encodes `pcm`, made of complete samples, and writes the frames.
+    fn encode(&mut self, pcm: &[u8]) -> io::Result<()> {
+        for chunk in pcm.chunks(CHUNK_SAMPLES * 2 * self.channels) {
+            // Deinterleave the channels
+            let samples = chunk.len() / (2 * self.channels);
+            let mut left = Vec::with_capacity(samples);
+            let mut right = Vec::with_capacity(samples);
+            for frame in chunk.chunks_exact(2 * self.channels) {
+                left.push(i16::from_le_bytes([frame[0], frame[1]]));
+                if self.channels == 2 {
+                    right.push(i16::from_le_bytes([frame[2], frame[3]]));
+                }
+            }
+            if self.channels == 1 {
+                right.clone_from(&left);
+            }
+
+            // Worst case given by LAME
+            self.mp3.resize(samples * 5 / 4 + FLUSH_BUFFER_SIZE, 0);
+            let size = unsafe {
+                lame_encode_buffer(self.lame.0, left.as_ptr(), right.as_ptr(),
+                                   samples as c_int, self.mp3.as_mut_ptr(),
+                                   self.mp3.len() as c_int)
+            };
+            if size < 0 {
+                return Err(lame_error(size));
+            }
+            self.inner.write_all(&self.mp3[..size as usize])?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> Write for Mp3Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let chunk_bytes = CHUNK_SAMPLES * 2 * self.channels;
+        let complete = self.pending.len() / chunk_bytes * chunk_bytes;
+        let pending = std::mem::take(&mut self.pending);
+        let result = self.encode(&pending[..complete]);
+        self.pending = pending;
+        self.pending.drain(..complete);
+        result.map(|()| buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W> fmt::Debug for Mp3Writer<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mp3Writer")
+            .field("channels", &self.channels)
+            .field("frames_pos", &self.frames_pos)
+            .finish_non_exhaustive()
+    }
+}
+
+
+/// Converts `tags` into ID3v2 frames, in the "ID=value" form of LAME.
+fn id3_fields(tags: &NrgTags) -> Vec<String> {
+    let mut fields = Vec::new();
+    if let Some(number) = tags.track_number {
+        fields.push(match tags.track_total {
+            Some(total) => format!("TRCK={}/{}", number, total),
+            None => format!("TRCK={}", number),
+        });
+    }
+    let frames = [
+        ("TIT2=", &tags.title),
+        ("TALB=", &tags.album),
+        ("TPE1=", &tags.artist),
+        ("TEXT=", &tags.songwriter),
+        ("TCOM=", &tags.composer),
+        ("TXXX=ARRANGER=", &tags.arranger),
+        ("TCON=", &tags.genre),
+        ("TXXX=COMMENT=", &tags.comment),
+        ("TSRC=", &tags.isrc),
+        ("TXXX=BARCODE=", &tags.upc),
+        ("TXXX=DISCID=", &tags.cddb_disc_id),
+        ("TXXX=MusicBrainz Disc Id=", &tags.musicbrainz_disc_id),
+    ];
+    fields.extend(frames.iter()
+                  .filter_map(|&(frame, value)| value.as_ref()
+                              .map(|value| format!("{}{}", frame, value))));
+    fields
+}
+
+
+fn lame_error(code: c_int) -> io::Error {
+    let reason = match code {
+        -1 => "output buffer too small",
+        -2 => "out of memory",
+        -3 => "parameters not initialized",
+        -4 => "psychoacoustic problem",
+        _ => "unknown error",
+    };
+    io::Error::other(NrgError::Encoder(format!("MP3 encoder: {}", reason)))
+}
@@ -0,0 +1,105 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Minutes/seconds/frames (MSF) time positions, as used on audio CDs.
+
+use std::fmt;
+
+
+/// Number of frames (sectors) per second on an audio CD.
+pub const FRAMES_PER_SECOND: u32 = 75;
+
+
+/// A time position or duration on an audio CD, in minutes, seconds and
+/// frames.
+///
+/// A frame is one 1/75th of a second, i.e. one sector of the disc.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Msf {
+    pub minutes: u32,
+    pub seconds: u8,
+    pub frames: u8,
+}
+
+impl Msf {
+    pub fn new(minutes: u32, seconds: u8, frames: u8) -> Msf {
+        Msf { minutes, seconds, frames }
+    }
+
+    /// Converts a number of sectors into an MSF position.
+    pub fn from_sectors(sectors: u32) -> Msf {
+        let seconds = sectors / FRAMES_PER_SECOND;
+        Msf {
+            minutes: seconds / 60,
+            seconds: (seconds % 60) as u8,
+            frames: (sectors % FRAMES_PER_SECOND) as u8,
+        }
+    }
+
+    /// Parses an MSF position written as "MM:SS:FF", as in cue sheets.
+    ///
+    /// Returns None if `text` is not a valid position.
+    pub fn parse(text: &str) -> Option<Msf> {
+        let mut fields = text.split(':');
+        let minutes = fields.next()?.parse().ok()?;
+        let seconds = fields.next()?.parse().ok()?;
+        let frames = fields.next()?.parse().ok()?;
+        if fields.next().is_some() || seconds >= 60
+            || u32::from(frames) >= FRAMES_PER_SECOND {
+            return None;
+        }
+        Some(Msf { minutes, seconds, frames })
+    }
+
+    /// Converts a byte offset into an MSF position, given the size of the
+    /// sectors.
+    ///
+    /// Incomplete sectors are ignored. Returns 00:00:00 if `sector_size` is
+    /// 0.
+    pub fn from_bytes(bytes: u64, sector_size: u16) -> Msf {
+        let sectors = bytes.checked_div(u64::from(sector_size)).unwrap_or(0);
+        Msf::from_sectors(sectors as u32)
+    }
+
+    /// Returns the number of sectors corresponding to this position.
+    pub fn to_sectors(&self) -> u32 {
+        (self.minutes * 60 + u32::from(self.seconds)) * FRAMES_PER_SECOND
+            + u32::from(self.frames)
+    }
+
+    /// Returns the byte offset corresponding to this position, given the
+    /// size of the sectors.
+    pub fn to_bytes(&self, sector_size: u16) -> u64 {
+        u64::from(self.to_sectors()) * u64::from(sector_size)
+    }
+
+    /// Returns this position in seconds.
+    pub fn as_seconds(&self) -> f64 {
+        f64::from(self.to_sectors()) / f64::from(FRAMES_PER_SECOND)
+    }
+}
+
+impl fmt::Display for Msf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.minutes, self.seconds, self.frames)
+    }
+}
@@ -0,0 +1,221 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to look up the titles of a disc in the MusicBrainz database, from
+//! its MusicBrainz DiscID (see nrgrip::disc_id).
+//!
+//! The responses of the MusicBrainz web service are kept in a cache
+//! directory, so that a disc is looked up online only once and can then be
+//! looked up offline.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use ::error::NrgError;
+use ::metadata::metadata::NrgMetadata;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
+
+/// URL of the DiscID lookups of the MusicBrainz web service.
+const DISC_ID_URL: &str = "https://musicbrainz.org/ws/2/discid/";
+
+/// Minimum interval between two requests, as required by MusicBrainz.
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time of the last request sent to MusicBrainz.
+static LAST_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+
+/// A release of the MusicBrainz database holding the disc looked up.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NrgRelease {
+    /// MusicBrainz identifier (MBID) of the release.
+    pub id: String,
+    pub title: String,
+    pub artist: String,
+    /// The tracks of the disc, in order.
+    pub tracks: Vec<NrgReleaseTrack>,
+}
+
+/// A track of an `NrgRelease`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NrgReleaseTrack {
+    pub title: String,
+    pub artist: String,
+}
+
+impl NrgRelease {
+    /// Sets the titles of the tracks of `metadata` to the titles of this
+    /// release, so that they are used in the tags, the names of the
+    /// extracted files and the cue sheet.
+    pub fn apply(&self, metadata: &mut NrgMetadata) {
+        let titles: Vec<String> = self.tracks.iter()
+            .map(|track| track.title.clone())
+            .collect();
+        metadata.set_titles(&titles);
+    }
+}
+
+impl fmt::Display for NrgRelease {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {} ({})", self.artist, self.title, self.id)
+    }
+}
+
+
+/// Returns the default cache directory: "nrgrip/musicbrainz" in
+/// `$XDG_CACHE_HOME`, or else in `$HOME/.cache`.
+///
+/// Returns None if neither variable is set.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let cache = env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME")
+                 .filter(|dir| !dir.is_empty())
+                 .map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache.join("nrgrip").join("musicbrainz"))
+}
+
+
+/// Looks up the releases holding the disc whose MusicBrainz DiscID is
+/// `disc_id`.
+///
+/// If `cache_dir` is given, the response cached in it is used if there is
+/// one; otherwise, the MusicBrainz web service is queried and its response
+/// is stored in `cache_dir`. The discs absent from the database yield an
+/// empty list, and aren't cached.
+pub fn lookup(disc_id: &str, cache_dir: Option<&Path>)
+              -> Result<Vec<NrgRelease>, NrgError> {
+    let cache_path = cache_dir.map(|dir| dir.join(format!("{}.json", disc_id)));
+    if let Some(ref path) = cache_path {
+        if path.exists() {
+            debug!("Reading the cached response \"{}\"", path.display());
+            return parse_releases(&fs::read_to_string(path)?, disc_id);
+        }
+    }
+
+    let response = match request(disc_id)? {
+        Some(response) => response,
+        None => return Ok(Vec::new()),
+    };
+    let releases = parse_releases(&response, disc_id)?;
+    if let Some(ref path) = cache_path {
+        let written = path.parent().map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, &response));
+        if let Err(err) = written {
+            warn!("Can't cache the response in \"{}\": {}",
+                  path.display(), err);
+        }
+    }
+    Ok(releases)
+}
+
+
+/// Queries the MusicBrainz web service for the disc `disc_id`.
+///
+/// Returns the JSON response, or None if the disc isn't in the database.
+fn request(disc_id: &str) -> Result<Option<String>, NrgError> {
+    {
+        let mut last_request = LAST_REQUEST.lock()
+            .unwrap_or_else(|err| err.into_inner());
+        if let Some(last_request) = *last_request {
+            let elapsed = last_request.elapsed();
+            if elapsed < REQUEST_INTERVAL {
+                thread::sleep(REQUEST_INTERVAL - elapsed);
+            }
+        }
+        *last_request = Some(Instant::now());
+    }
+
+    let url = format!("{}{}?inc=artist-credits+recordings&fmt=json",
+                      DISC_ID_URL, disc_id);
+    info!("Looking up {}", url);
+    let user_agent = format!("NRGrip/{} ( {} )", VERSION, AUTHORS);
+    let response = ureq::get(&url)
+        .set("User-Agent", &user_agent)
+        .set("Accept", "application/json")
+        .call();
+    match response {
+        Ok(response) => Ok(Some(response.into_string()?)),
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+
+/// Parses the JSON response of a DiscID lookup.
+///
+/// The tracks of a release are the ones of its medium holding the disc
+/// `disc_id`; the releases without such a medium are skipped.
+pub fn parse_releases(json: &str, disc_id: &str)
+                      -> Result<Vec<NrgRelease>, NrgError> {
+    let response: Value = serde_json::from_str(json)?;
+    let mut releases = Vec::new();
+    for release in response["releases"].as_array().into_iter().flatten() {
+        let medium = release["media"].as_array().into_iter().flatten()
+            .find(|medium| {
+                medium["discs"].as_array().into_iter().flatten()
+                    .any(|disc| disc["id"] == disc_id)
+            });
+        let medium = match medium {
+            Some(medium) => medium,
+            None => continue,
+        };
+        let tracks = medium["tracks"].as_array().into_iter().flatten()
+            .map(|track| NrgReleaseTrack {
+                title: string(&track["title"]),
+                artist: artist_credit(&track["artist-credit"]),
+            })
+            .collect();
+        releases.push(NrgRelease {
+            id: string(&release["id"]),
+            title: string(&release["title"]),
+            artist: artist_credit(&release["artist-credit"]),
+            tracks,
+        });
+    }
+    Ok(releases)
+}
+
+
+/// Returns the string `value`, or an empty string if it isn't a string.
+fn string(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+
+/// Returns the artists of an artist credit, joined as credited.
+fn artist_credit(credit: &Value) -> String {
+    credit.as_array().into_iter().flatten()
+        .map(|artist| format!("{}{}", string(&artist["name"]),
+                              string(&artist["joinphrase"])))
+        .collect()
+}
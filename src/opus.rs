@@ -0,0 +1,224 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to encode CD audio into Ogg Opus streams.
+//!
+//! `OpusWriter` resamples the 16-bit little-endian PCM samples written
+//! through it to 48 kHz, the sample rate of Opus, and encodes them into
+//! 20 ms packets with libopus. The tags describing the audio are stored in
+//! the OpusTags header.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use audiopus::coder::Encoder;
+use audiopus::{Application, Bitrate, Channels, SampleRate, Signal};
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+
+use ::error::NrgError;
+use ::raw_audio::SAMPLE_RATE;
+use ::resample::Resampler;
+use ::tags::{self, NrgTags};
+
+/// Default bitrate of the streams, in kbit/s.
+pub const DEFAULT_BITRATE: u32 = 128;
+/// Range of the bitrates supported by Opus, in kbit/s.
+pub const MIN_BITRATE: u32 = 6;
+pub const MAX_BITRATE: u32 = 510;
+
+/// Sample rate of the Opus streams, in Hz.
+const OPUS_RATE: u32 = 48000;
+/// Number of samples per channel in each packet (20 ms).
+const FRAME_SIZE: usize = 960;
+/// Largest size of an encoded packet, as recommended by libopus.
+const MAX_PACKET_SIZE: usize = 4000;
+/// Serial number of the Ogg logical stream.
+const SERIAL: u32 = 0x4E52_4750;
+
+
+/// Writer encoding the CD audio written through it into an Ogg Opus stream.
+///
+/// The data written must be 44.1 kHz, 16-bit little-endian PCM samples, with
+/// the channels interleaved. `finish()` must be called once all the samples
+/// are written, to encode the last packet and end the stream.
+pub struct OpusWriter<W: Write> {
+    packets: PacketWriter<W>,
+    encoder: Encoder,
+    resampler: Resampler,
+    channels: usize,
+    /// Incomplete frame.
+    pending: Vec<u8>,
+    /// Resampled samples not encoded yet.
+    samples: Vec<f32>,
+    /// Number of samples per channel written.
+    input_samples: u64,
+    /// Number of samples per channel to be skipped by the decoders, at
+    /// 48 kHz.
+    pre_skip: u64,
+    /// Number of samples per channel encoded, at 48 kHz.
+    encoded: u64,
+    /// Last packet encoded and its granule position, written once it is
+    /// known whether it ends the stream.
+    last_packet: Option<(Vec<u8>, u64)>,
+}
+
+impl<W: Write> OpusWriter<W> {
+    /// Starts an Opus stream in `channels` channels (1 or 2), encoded at
+    /// `bitrate` kbit/s (variable bitrate), described by `tags`.
+    pub fn new(inner: W, channels: u16, bitrate: u32, tags: &NrgTags)
+               -> Result<OpusWriter<W>, NrgError> {
+        let opus_channels = match channels {
+            1 => Channels::Mono,
+            2 => Channels::Stereo,
+            _ => return Err(NrgError::NrgFormat(format!(
+                "Can't encode {} channels in Opus", channels))),
+        };
+        if !(MIN_BITRATE..=MAX_BITRATE).contains(&bitrate) {
+            return Err(NrgError::Encoder(format!(
+                "Opus bitrate must be between {} and {} kbit/s",
+                MIN_BITRATE, MAX_BITRATE)));
+        }
+        let mut encoder = Encoder::new(SampleRate::Hz48000, opus_channels,
+                                       Application::Audio)?;
+        encoder.set_bitrate(Bitrate::BitsPerSecond(bitrate as i32 * 1000))?;
+        encoder.set_signal(Signal::Music)?;
+        let pre_skip = encoder.lookahead()?;
+
+        // Identification header
+        let mut head = Vec::with_capacity(19);
+        head.extend_from_slice(b"OpusHead");
+        head.push(1);
+        head.push(channels as u8);
+        head.extend_from_slice(&(pre_skip as u16).to_le_bytes());
+        head.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+        head.extend_from_slice(&0i16.to_le_bytes());
+        head.push(0);
+
+        // Comment header
+        let mut comments = b"OpusTags".to_vec();
+        comments.extend_from_slice(&tags::vorbis_comments(tags));
+
+        let mut packets = PacketWriter::new(inner);
+        for header in [head, comments] {
+            packets.write_packet(header.into_boxed_slice(), SERIAL,
+                                 PacketWriteEndInfo::EndPage, 0)?;
+        }
+
+        Ok(OpusWriter {
+            packets,
+            encoder,
+            resampler: Resampler::new(SAMPLE_RATE, OPUS_RATE,
+                                      usize::from(channels)),
+            channels: usize::from(channels),
+            pending: Vec::new(),
+            samples: Vec::new(),
+            input_samples: 0,
+            pre_skip: u64::from(pre_skip),
+            encoded: 0,
+            last_packet: None,
+        })
+    }
+
+    /// Encodes the last packets, ends the stream and returns the inner
+    /// writer.
+    pub fn finish(mut self) -> Result<W, NrgError> {
+        if !self.pending.is_empty() {
+            return Err(NrgError::NrgFormat(
+                "Incomplete sample in the audio data".to_string()));
+        }
+        self.resampler.flush(&mut self.samples);
+
+        // The decoders drop the pre-skip and the padding of the last packet
+        let end = self.pre_skip + (self.input_samples * u64::from(OPUS_RATE))
+            .div_ceil(u64::from(SAMPLE_RATE));
+        loop {
+            self.encode_packets()?;
+            if self.encoded >= end {
+                break;
+            }
+            self.samples.resize(FRAME_SIZE * self.channels, 0.0);
+        }
+
+        if let Some((packet, _)) = self.last_packet.take() {
+            self.packets.write_packet(packet.into_boxed_slice(), SERIAL,
+                                      PacketWriteEndInfo::EndStream, end)?;
+        }
+        let mut inner = self.packets.into_inner();
+        inner.flush()?;
+        Ok(inner)
+    }
+
+    /// Encodes the complete packets of resampled samples.
+    fn encode_packets(&mut self) -> io::Result<()> {
+        let packet_len = FRAME_SIZE * self.channels;
+        let nb_packets = self.samples.len() / packet_len;
+        for i in 0..nb_packets {
+            let frame = &self.samples[i * packet_len..][..packet_len];
+            let mut packet = vec![0u8; MAX_PACKET_SIZE];
+            let size = self.encoder.encode_float(frame, &mut packet)
+                .map_err(|err| io::Error::other(NrgError::from(err)))?;
+            packet.truncate(size);
+            self.encoded += FRAME_SIZE as u64;
+
+            let previous = self.last_packet.replace((packet, self.encoded));
+            if let Some((packet, granule)) = previous {
+                self.packets.write_packet(packet.into_boxed_slice(), SERIAL,
+                                          PacketWriteEndInfo::NormalPacket,
+                                          granule)?;
+            }
+        }
+        self.samples.drain(..nb_packets * packet_len);
+        Ok(())
+    }
+}
+
+impl<W: Write> fmt::Debug for OpusWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OpusWriter")
+            .field("channels", &self.channels)
+            .field("input_samples", &self.input_samples)
+            .field("pre_skip", &self.pre_skip)
+            .field("encoded", &self.encoded)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: Write> Write for OpusWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let frame_bytes = 2 * self.channels;
+        let complete = self.pending.len() / frame_bytes * frame_bytes;
+        let input: Vec<i16> = self.pending[..complete].chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        self.pending.drain(..complete);
+        self.input_samples += (complete / frame_bytes) as u64;
+        self.resampler.process(&input, &mut self.samples);
+        self.encode_packets()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.packets.inner_mut().flush()
+    }
+}
+
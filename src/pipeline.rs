@@ -0,0 +1,115 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to run the stages of the audio extraction in separate threads,
+//! connected by bounded channels, so that reading the image, transforming
+//! and measuring the audio, and writing the output files overlap instead of
+//! waiting for each other.
+
+use std::io::{self, Write};
+use std::panic;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{Scope, ScopedJoinHandle};
+
+/// Number of buffers that can be queued between two stages.
+const QUEUE_DEPTH: usize = 4;
+
+
+/// Writer handing the data written to it over to a thread, which writes it
+/// to the inner writer.
+///
+/// The data is copied into buffers sent through a bounded channel: writing
+/// blocks while the thread is `QUEUE_DEPTH` buffers behind. The buffers are
+/// sent back once written, so that they are reused. If the thread fails,
+/// the next write returns its error.
+#[derive(Debug)]
+pub struct PipeWriter<'scope, W> {
+    sender: Option<SyncSender<Vec<u8>>>,
+    recycled: Receiver<Vec<u8>>,
+    thread: Option<ScopedJoinHandle<'scope, io::Result<W>>>,
+}
+
+impl<'scope, W: Write + Send + 'scope> PipeWriter<'scope, W> {
+    /// Spawns the thread writing to `inner` in `scope`.
+    pub fn new<'env>(scope: &'scope Scope<'scope, 'env>, mut inner: W)
+                     -> PipeWriter<'scope, W> {
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(QUEUE_DEPTH);
+        let (recycler, recycled) = mpsc::sync_channel(QUEUE_DEPTH);
+        let thread = scope.spawn(move || {
+            for buf in receiver {
+                inner.write_all(&buf)?;
+                // Drop the buffer if enough of them are waiting for reuse
+                let _ = recycler.try_send(buf);
+            }
+            inner.flush()?;
+            Ok(inner)
+        });
+        PipeWriter {
+            sender: Some(sender),
+            recycled,
+            thread: Some(thread),
+        }
+    }
+
+    /// Waits for the thread to write all the data, and returns the inner
+    /// writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.sender = None;
+        self.join()
+    }
+
+    fn join(&mut self) -> io::Result<W> {
+        match self.thread.take() {
+            Some(thread) => thread.join()
+                .unwrap_or_else(|payload| panic::resume_unwind(payload)),
+            None => Err(io::Error::new(io::ErrorKind::BrokenPipe,
+                                       "The pipeline stage has stopped")),
+        }
+    }
+}
+
+impl<'scope, W: Write + Send + 'scope> Write for PipeWriter<'scope, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let sender = match self.sender {
+            Some(ref sender) => sender,
+            None => return self.join().map(|_| 0),
+        };
+        let mut data = self.recycled.try_recv().unwrap_or_default();
+        data.clear();
+        data.extend_from_slice(buf);
+        if sender.send(data).is_ok() {
+            return Ok(buf.len());
+        }
+
+        // The thread stopped on an error
+        self.sender = None;
+        self.join().map(|_| 0)
+    }
+
+    /// Does nothing: the data is flushed by `finish()`.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
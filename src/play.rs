@@ -0,0 +1,158 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to play the tracks of an NRG image on the default audio device.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use cpal::{FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+use ::error::NrgError;
+use ::metadata::track::Track;
+use ::raw_audio::{RAW_SEC_SIZE, SAMPLE_RATE};
+use ::wav::{WavReader, WAV_HEADER_SIZE};
+
+/// Number of sectors buffered ahead of the audio device (two seconds).
+const BUFFERED_SECTORS: usize = 150;
+
+
+/// Plays `track`, read from the NRG image `fd`, on the default audio device.
+///
+/// The audio data is streamed from the image, from the track's index #1 to
+/// its end; the sub-channel is skipped if present. This function returns
+/// once the whole track has been played.
+pub fn play_track<R: Read + Seek>(fd: &mut R, track: &Track)
+                                  -> Result<(), NrgError> {
+    // Skip the WAV header, only the samples are played
+    let mut wav = WavReader::new(fd, track)?;
+    wav.seek(SeekFrom::Start(WAV_HEADER_SIZE))?;
+
+    let (sender, receiver) = mpsc::sync_channel(BUFFERED_SECTORS);
+    let done = Arc::new(AtomicBool::new(false));
+    let stream = open_stream(receiver, Arc::clone(&done))?;
+    stream.play().map_err(device_error)?;
+
+    // Send the samples sector by sector, until the end of the track or until
+    // the stream stops
+    let mut sector = vec![0u8; usize::from(RAW_SEC_SIZE)];
+    let mut pos = WAV_HEADER_SIZE;
+    while pos < wav.len() {
+        wav.read_exact(&mut sector)?;
+        let samples = sector.chunks(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        if sender.send(samples).is_err() {
+            break;
+        }
+        pos += u64::from(RAW_SEC_SIZE);
+    }
+    drop(sender);
+
+    while !done.load(Ordering::Acquire) {
+        thread::sleep(Duration::from_millis(50));
+    }
+    Ok(())
+}
+
+
+/// Opens an output stream on the default audio device, playing the samples
+/// received from `receiver` and setting `done` once they are exhausted.
+fn open_stream(receiver: Receiver<Vec<i16>>, done: Arc<AtomicBool>)
+               -> Result<Stream, NrgError> {
+    let device = cpal::default_host().default_output_device()
+        .ok_or_else(|| NrgError::AudioDevice(
+            "No default output device".to_string()))?;
+
+    // Look for a 44.1 kHz stereo configuration, with samples that can be
+    // converted from 16-bit integers
+    let sample_format = device.supported_output_configs()
+        .map_err(device_error)?
+        .filter(|config| config.channels() == 2
+                && config.min_sample_rate().0 <= SAMPLE_RATE
+                && config.max_sample_rate().0 >= SAMPLE_RATE)
+        .map(|config| config.sample_format())
+        .min_by_key(|format| match *format {
+            SampleFormat::I16 => 0,
+            SampleFormat::F32 => 1,
+            _ => 2,
+        });
+    let config = StreamConfig {
+        channels: 2,
+        sample_rate: cpal::SampleRate(SAMPLE_RATE),
+        buffer_size: cpal::BufferSize::Default,
+    };
+    match sample_format {
+        Some(SampleFormat::I16) =>
+            build_stream::<i16>(&device, &config, receiver, done),
+        Some(SampleFormat::F32) =>
+            build_stream::<f32>(&device, &config, receiver, done),
+        _ => Err(NrgError::AudioDevice(
+            "The output device doesn't support 44.1 kHz stereo audio \
+             in 16-bit integer or 32-bit float samples".to_string())),
+    }
+}
+
+
+/// Builds an output stream of samples of type `T` on `device`.
+fn build_stream<T>(device: &cpal::Device, config: &StreamConfig,
+                   receiver: Receiver<Vec<i16>>, done: Arc<AtomicBool>)
+                   -> Result<Stream, NrgError>
+    where T: SizedSample + FromSample<i16> {
+    let mut block = Vec::new();
+    let mut pos = 0;
+    let data_callback = move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+        for sample in data.iter_mut() {
+            if pos == block.len() {
+                match receiver.try_recv() {
+                    Ok(samples) => {
+                        block = samples;
+                        pos = 0;
+                    },
+                    // Buffer underrun or end of the track: play silence
+                    Err(err) => {
+                        if err == TryRecvError::Disconnected {
+                            done.store(true, Ordering::Release);
+                        }
+                        *sample = T::EQUILIBRIUM;
+                        continue;
+                    },
+                }
+            }
+            *sample = T::from_sample(block[pos]);
+            pos += 1;
+        }
+    };
+    let error_callback = |err| error!("Audio stream error: {}", err);
+    device.build_output_stream(config, data_callback, error_callback, None)
+        .map_err(device_error)
+}
+
+
+fn device_error<E: ToString>(err: E) -> NrgError {
+    NrgError::AudioDevice(err.to_string())
+}
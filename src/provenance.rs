@@ -0,0 +1,117 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to write the provenance report of an extraction: a JSON document
+//! listing the files written from an NRG image, the byte ranges of the image
+//! their data was read from, their checksums, the extraction options and
+//! the warnings raised by the image.
+//!
+//! The report follows the versioning of the JSON output (see nrgrip::json),
+//! and is meant for the tools archiving the extracted files; the log stays
+//! the human-readable account of the extraction.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use ::disc_id::NrgDiscToc;
+use ::error::NrgError;
+use ::extraction::{ExtractedFile, ExtractionOptions};
+use ::json::{self, JsonValue};
+use ::manifest;
+use ::metadata::metadata::NrgMetadata;
+use ::red_book;
+
+/// Suffix of the report's name, appended to the image's file stem.
+pub const PROVENANCE_SUFFIX: &str = ".provenance.json";
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+
+/// Builds the provenance report of the extraction of `files` from the image
+/// `img_path`, described by `metadata`, with `options`.
+///
+/// Each file is described by nrgrip::json::extracted_file_to_json(), with
+/// its size and SHA-256 on top; the files must therefore still exist. The
+/// warnings are those of the metadata, the Red Book limits exceeded by the
+/// image, and the files whose verification failed.
+pub fn provenance_to_json(img_path: &str, metadata: &NrgMetadata,
+                          options: &ExtractionOptions,
+                          files: &[ExtractedFile])
+                          -> Result<JsonValue, NrgError> {
+    let mut file_values = Vec::new();
+    for file in files {
+        let mut value = json::extracted_file_to_json(file);
+        if let JsonValue::Object(ref mut members) = value {
+            let path = Path::new(&file.path);
+            members.push(("size".to_string(),
+                          fs::metadata(path)?.len().into()));
+            members.push(("sha256".to_string(),
+                          manifest::hash_file(path)?.into()));
+        }
+        file_values.push(value);
+    }
+
+    let mut warnings = metadata.warnings();
+    warnings.extend(red_book::check(metadata).iter()
+                    .map(ToString::to_string));
+    warnings.extend(files.iter()
+                    .filter(|file| file.verified == Some(false))
+                    .map(|file| format!("\"{}\" doesn't match the image's \
+                                         audio data", file.path)));
+
+    let toc = NrgDiscToc::from_metadata(metadata);
+    Ok(JsonValue::object(vec![
+        ("schema_version", json::SCHEMA_VERSION.into()),
+        ("generator", format!("nrgrip {}", VERSION).into()),
+        ("image", JsonValue::object(vec![
+            ("path", img_path.into()),
+            ("file_size", metadata.file_size().into()),
+            ("sector_size", metadata.sector_size().into()),
+            ("musicbrainz_disc_id", toc.as_ref()
+             .map(NrgDiscToc::musicbrainz_disc_id).into()),
+        ])),
+        ("options", options.to_json()),
+        ("files", JsonValue::Array(file_values)),
+        ("warnings", warnings.into()),
+    ]))
+}
+
+
+/// Writes the provenance report built by provenance_to_json() to
+/// "IMAGE.provenance.json" in the directory `dir` (the current directory if
+/// it is empty), and returns its path.
+pub fn write_provenance(dir: &Path, img_path: &str, metadata: &NrgMetadata,
+                        options: &ExtractionOptions, files: &[ExtractedFile])
+                        -> Result<PathBuf, NrgError> {
+    let stem = Path::new(img_path).file_stem()
+        .ok_or_else(|| NrgError::FileName(img_path.to_string()))?;
+    let mut name = stem.to_os_string();
+    name.push(PROVENANCE_SUFFIX);
+    let path = dir.join(name);
+
+    let report = provenance_to_json(img_path, metadata, options, files)?;
+    info!("Writing provenance report to \"{}\"", path.display());
+    let mut fd = File::create(&path)?;
+    writeln!(fd, "{:#}", report)?;
+    Ok(path)
+}
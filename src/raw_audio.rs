@@ -22,15 +22,42 @@
 
 //! Module to extract the raw audio data from an NRG image file.
 
+#[cfg(feature = "fs")]
 use std::fs::File;
-use std::io::{Seek, SeekFrom, Read, Write};
-use std::path::PathBuf;
+#[cfg(feature = "fs")]
+use std::io::{self, Seek, SeekFrom, Read, Write};
+#[cfg(feature = "fs")]
+use std::thread;
+#[cfg(feature = "fs")]
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "fs")]
+use ::cancel::CancellationToken;
+#[cfg(feature = "fs")]
 use ::error::NrgError;
+#[cfg(feature = "fs")]
+use ::extraction::{extract, ExtractionOptions};
+#[cfg(feature = "fs")]
 use ::metadata::metadata::NrgMetadata;
+#[cfg(feature = "fs")]
+use ::metrics::MetricsHandle;
 
-const RAW_SEC_SIZE: u16 = 2352;
-const RAW96_SEC_SIZE: u16 = 2448;
+/// Size of a standard audio CD sector, in bytes.
+pub const RAW_SEC_SIZE: u16 = 2352;
+/// Size of an audio CD sector including the 96-byte sub-channel, in bytes.
+pub const RAW96_SEC_SIZE: u16 = 2448;
+/// Sampling rate of audio CDs, in Hz.
+pub const SAMPLE_RATE: u32 = 44_100;
+/// Size of a 16-bit stereo sample, in bytes.
+pub const SAMPLE_SIZE: usize = 4;
+
+/// Default size of the buffer used to copy the audio data, in bytes: 2048
+/// sectors (~4.6 MiB), or 128 sectors (~294 KiB) on the 32-bit and Android
+/// targets, whose devices often have little memory.
+#[cfg(not(any(target_pointer_width = "32", target_os = "android")))]
+pub const DEFAULT_BUFFER_SIZE: usize = RAW_SEC_SIZE as usize * 2048;
+#[cfg(any(target_pointer_width = "32", target_os = "android"))]
+pub const DEFAULT_BUFFER_SIZE: usize = RAW_SEC_SIZE as usize * 128;
 
 
 /// Extracts the raw audio data from an NRG image.
@@ -40,76 +67,221 @@ const RAW96_SEC_SIZE: u16 = 2448;
 /// - `metadata` is the metadata extracted from `img_path` by nrgrip::metadata.
 ///
 /// The output file's name is derived from `img_path`.
+///
+/// This is a shortcut for nrgrip::extraction::extract() with the default
+/// options, apart from `strip_subchannel`.
+#[cfg(feature = "fs")]
 pub fn extract_nrg_raw_audio(in_fd: &mut File,
                              img_path: &str,
                              metadata: &NrgMetadata,
                              strip_subchannel: bool)
                              -> Result<(), NrgError> {
-    // Seek to the first audio byte
-    let first_audio_byte = metadata.first_audio_byte();
-    try!(in_fd.seek(SeekFrom::Start(first_audio_byte)));
-
-    // Open output file
-    let audio_name = try!(make_output_file_name(img_path));
-    let mut out_fd = try!(File::create(audio_name));
-
-    // Copy the audio data
-    let count = metadata.last_audio_byte() - first_audio_byte;
-    if metadata.sector_size() == 0 {
-        return Err(NrgError::AudioReadError);
-    }
-    let bytes_read =
-        if strip_subchannel && metadata.sector_size() == RAW96_SEC_SIZE {
-            try!(copy_raw96_audio(in_fd, &mut out_fd, count))
-        } else {
-            try!(copy_raw_audio(in_fd, &mut out_fd, count))
-        };
-
-    assert_eq!(count, bytes_read);
+    let options = ExtractionOptions::new().strip_subchannel(strip_subchannel);
+    extract(in_fd, img_path, metadata, &options)?;
     Ok(())
 }
 
 
-/// Reads `count` bytes from `in_fd` and write them to `out_fd`.
-///
-/// The offsets of `in_fd` and `out_fd` are not reset prior to reading and
-/// writing.
-///
-/// Returns the number of bytes read/written.
-fn copy_raw_audio(in_fd: &mut File, out_fd: &mut File, count: u64)
-                  -> Result<u64, NrgError> {
-    // The buffer size (~4,6 MiB) is a multiple of the standard audio CD sector
-    // size, i.e. 2352 bytes (it doesn't have to be, though).
-    const BUF_SIZE: usize = RAW_SEC_SIZE as usize * 1024 * 2;
+/// State of the copy loops: the size of their buffer, the token cancelling
+/// them, the limit of their throughput, the receiver of their statistics,
+/// and the handling of the read errors.
+#[cfg(feature = "fs")]
+#[derive(Debug)]
+pub(crate) struct CopyControl {
+    buffer_size: usize,
+    cancel: CancellationToken,
+    /// Maximum throughput, in bytes per second; 0 for no limit.
+    rate_limit: u64,
+    start: Instant,
+    bytes_read: u64,
+    metrics: MetricsHandle,
+    /// Size of the sectors replaced by silence when they can't be read, or
+    /// None to fail on read errors.
+    salvaged_sector_size: Option<u64>,
+    /// Offsets in the image of the sectors replaced by silence.
+    bad_sectors: Vec<u64>,
+}
 
-    // Read/write audio data
-    let mut bytes_read = 0;
-    while bytes_read + BUF_SIZE as u64 <= count {
-        let mut audio_buf = [0u8; BUF_SIZE];
+#[cfg(feature = "fs")]
+impl CopyControl {
+    /// Longest sleep of a throttled copy, so that a cancellation is still
+    /// handled promptly.
+    const MAX_SLEEP: Duration = Duration::from_millis(100);
+
+    /// Returns a control for copies through a buffer of `buffer_size` bytes,
+    /// stopped when `cancel` is cancelled and reading at most `rate_limit`
+    /// bytes per second (0 for no limit).
+    pub(crate) fn new(buffer_size: usize, cancel: &CancellationToken,
+                      rate_limit: u64)
+                      -> CopyControl {
+        CopyControl {
+            buffer_size: buffer_size.max(1),
+            cancel: cancel.clone(),
+            rate_limit,
+            start: Instant::now(),
+            bytes_read: 0,
+            metrics: MetricsHandle::default(),
+            salvaged_sector_size: None,
+            bad_sectors: Vec::new(),
+        }
+    }
+
+    /// Replaces the sectors of `sector_size` bytes that can't be read with
+    /// silence, instead of failing. The sectors are counted from the start
+    /// of the image, where NRG images store their data.
+    pub(crate) fn ignore_errors(mut self, sector_size: u64) -> CopyControl {
+        self.salvaged_sector_size = Some(sector_size.max(1));
+        self
+    }
+
+    /// Returns the offsets in the image of the sectors replaced by silence,
+    /// in the order they were read.
+    pub(crate) fn bad_sectors(&self) -> &[u64] {
+        &self.bad_sectors
+    }
+
+    /// Reports the statistics of the copies to `metrics`.
+    pub(crate) fn metrics(mut self, metrics: &MetricsHandle) -> CopyControl {
+        self.metrics = metrics.clone();
+        self
+    }
+
+    /// Returns a control for copies through a buffer of the default size,
+    /// neither cancellable nor throttled.
+    pub(crate) fn unlimited() -> CopyControl {
+        CopyControl::new(DEFAULT_BUFFER_SIZE, &CancellationToken::new(), 0)
+    }
+
+    /// Returns the number of bytes read from the image since the control was
+    /// created.
+    pub(crate) fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Returns the average throughput of the copies since the control was
+    /// created, in bytes per second.
+    pub(crate) fn throughput(&self) -> f64 {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        self.bytes_read as f64 / elapsed
+    }
 
-        let mut nbytes = try!(in_fd.read(&mut audio_buf));
-        if nbytes != BUF_SIZE {
-            return Err(NrgError::AudioReadError);
+    /// Fails if the copy is cancelled, and otherwise waits until the bytes
+    /// read so far fit in the rate limit.
+    fn before_read(&self) -> Result<(), NrgError> {
+        self.cancel.check()?;
+        if self.rate_limit == 0 {
+            return Ok(());
         }
-        bytes_read += nbytes as u64;
+        let due = Duration::from_secs_f64(self.bytes_read as f64
+                                          / self.rate_limit as f64);
+        loop {
+            let elapsed = self.start.elapsed();
+            if elapsed >= due {
+                break;
+            }
+            thread::sleep((due - elapsed).min(Self::MAX_SLEEP));
+            self.cancel.check()?;
+        }
+        Ok(())
+    }
+
+    /// Fills `buf` from `in_fd`, as read_audio() does.
+    ///
+    /// If the read fails while the errors are ignored, the sectors covered
+    /// by `buf` are read again one by one, and those that can't be read are
+    /// replaced by zeros; `in_fd`'s offset is then left after `buf`'s data.
+    pub(crate) fn read<R: Read + Seek>(&mut self, in_fd: &mut R,
+                                       buf: &mut [u8])
+                                       -> Result<(), NrgError> {
+        let start = in_fd.stream_position()?;
+        let err = match read_audio(in_fd, buf) {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        let sector_size = match self.salvaged_sector_size {
+            Some(sector_size) => sector_size,
+            None => return Err(err),
+        };
+        debug!("Error reading {} bytes at offset {}, reading them sector by \
+                sector: {}", buf.len(), start, err);
 
-        nbytes = try!(out_fd.write(&audio_buf));
-        if nbytes != BUF_SIZE {
-            return Err(NrgError::AudioWriteError);
+        let end = start + buf.len() as u64;
+        let mut pos = start;
+        while pos < end {
+            let sector = pos / sector_size * sector_size;
+            let next = (sector + sector_size).min(end);
+            let part = &mut buf[(pos - start) as usize
+                                ..(next - start) as usize];
+            in_fd.seek(SeekFrom::Start(pos))?;
+            if let Err(err) = read_audio(in_fd, part) {
+                debug!("Sector at offset {} replaced by silence: {}",
+                       sector, err);
+                part.fill(0);
+                if self.bad_sectors.last() != Some(&sector) {
+                    self.bad_sectors.push(sector);
+                }
+            }
+            pos = next;
         }
+        in_fd.seek(SeekFrom::Start(end))?;
+        Ok(())
     }
 
-    // Read/write the last bytes
-    let remaining: usize = (count - bytes_read) as usize;
-    let mut audio_buf = vec![0u8; remaining];
-    let mut nbytes = try!(in_fd.read(&mut audio_buf));
-    if nbytes != remaining {
-        return Err(NrgError::AudioReadError);
+    /// Records that `nbytes` were read from the image.
+    fn after_read(&mut self, nbytes: u64) {
+        self.bytes_read += nbytes;
+        self.metrics.bytes_read(nbytes);
     }
-    bytes_read += nbytes as u64;
-    nbytes = try!(out_fd.write(&audio_buf));
-    if nbytes != remaining {
-        return Err(NrgError::AudioWriteError);
+
+    /// Records that `nbytes` were written to the output, after the
+    /// sub-channel of `stripped` sectors was stripped.
+    fn after_write(&self, nbytes: u64, stripped: u64) {
+        self.metrics.bytes_written(nbytes);
+        if stripped > 0 {
+            self.metrics.sectors_stripped(stripped);
+        }
+    }
+}
+
+
+/// Reads `count` bytes from `in_fd` and write them to `out_fd`, through the
+/// buffer of `control`.
+///
+/// The offsets of `in_fd` and `out_fd` are not reset prior to reading and
+/// writing. The copy is cancelled and throttled by `control`, before each
+/// buffer is read; the sectors that can't be read are replaced by silence if
+/// `control` ignores the errors.
+///
+/// Returns the number of bytes read/written.
+#[cfg(feature = "fs")]
+pub(crate) fn copy_raw_audio<R, W>(in_fd: &mut R, out_fd: &mut W, count: u64,
+                                   control: &mut CopyControl)
+                                   -> Result<u64, NrgError>
+    where R: Read + Seek,
+          W: Write {
+    let start = in_fd.stream_position()?;
+    let context = |err: NrgError, bytes_read| {
+        err.context(None, start + bytes_read, "copying audio data".to_string())
+    };
+
+    // Read/write audio data. The buffer is allocated on the heap, as it is
+    // too large for the stack of some threads.
+    let mut audio_buf = vec![0u8; control.buffer_size];
+    let mut bytes_read = 0;
+    while bytes_read < count {
+        let nbytes = (count - bytes_read).min(audio_buf.len() as u64);
+        let chunk = &mut audio_buf[..nbytes as usize];
+        control.before_read()
+            .and_then(|()| control.read(in_fd, chunk))
+            .map_err(|err| context(err, bytes_read))?;
+        control.after_read(nbytes);
+        write_audio(out_fd, chunk)
+            .map_err(|err| context(err, bytes_read))?;
+        control.after_write(nbytes, 0);
+        bytes_read += nbytes;
     }
 
     Ok(bytes_read)
@@ -117,57 +289,87 @@ fn copy_raw_audio(in_fd: &mut File, out_fd: &mut File, count: u64)
 
 
 /// Reads `count` bytes from `in_fd` and write them to `out_fd` after stripping
-/// the sub-channel bytes.
+/// the sub-channel bytes, through about the buffer of `control`.
 ///
-/// `in_fd` is read by chunks of 2448 bytes, then the first 2352 bytes are
-/// written to `out_fd`, leaving out the 96 sub-channel bytes.
+/// `in_fd` is read by batches of 2448-byte sectors, as many as fit in the
+/// buffer (at least one); the first 2352 bytes of each sector are then
+/// compacted at the start of the buffer, leaving out the 96 sub-channel
+/// bytes, and written to `out_fd` at once.
 ///
 /// The offsets of `in_fd` and `out_fd` are not reset prior to reading and
-/// writing.
+/// writing. The copy is cancelled and throttled by `control`, before each
+/// batch is read; the sectors that can't be read are replaced by silence if
+/// `control` ignores the errors.
 ///
-/// Returns the number of bytes read (not written).
-fn copy_raw96_audio(in_fd: &mut File, out_fd: &mut File, count: u64)
-                    -> Result<u64, NrgError> {
-    const IN_BUF_SIZE: usize = RAW96_SEC_SIZE as usize;
-    const OUT_BUF_SIZE: usize = RAW_SEC_SIZE as usize;
+/// Returns the number of bytes read, i.e. `count` rounded up to a whole
+/// number of sectors.
+#[cfg(feature = "fs")]
+pub(crate) fn copy_raw96_audio<R, W>(in_fd: &mut R, out_fd: &mut W,
+                                     count: u64, control: &mut CopyControl)
+                                     -> Result<u64, NrgError>
+    where R: Read + Seek,
+          W: Write {
+    const IN_SEC_SIZE: usize = RAW96_SEC_SIZE as usize;
+    const OUT_SEC_SIZE: usize = RAW_SEC_SIZE as usize;
+
+    let start = in_fd.stream_position()?;
+    let context = |err: NrgError, bytes_read| {
+        err.context(None, start + bytes_read,
+                    "stripping the sub-channel".to_string())
+    };
 
     // Read/write audio data
+    let batch_sectors = (control.buffer_size / IN_SEC_SIZE).max(1);
+    let mut audio_buf = vec![0u8; batch_sectors * IN_SEC_SIZE];
     let mut bytes_read = 0;
     while bytes_read < count {
-        let mut audio_buf = vec![0u8; IN_BUF_SIZE];
+        let remaining = (count - bytes_read).div_ceil(IN_SEC_SIZE as u64);
+        let nb_sectors = (remaining as usize).min(batch_sectors);
+        let batch = &mut audio_buf[..nb_sectors * IN_SEC_SIZE];
 
-        let mut nbytes = try!(in_fd.read(&mut audio_buf));
-        if nbytes != IN_BUF_SIZE {
-            return Err(NrgError::AudioReadError);
-        }
-        bytes_read += nbytes as u64;
+        control.before_read()
+            .and_then(|()| control.read(in_fd, batch))
+            .map_err(|err| context(err, bytes_read))?;
+        control.after_read(batch.len() as u64);
 
-        audio_buf.truncate(OUT_BUF_SIZE);
-        nbytes = try!(out_fd.write(&audio_buf));
-        if nbytes != OUT_BUF_SIZE {
-            return Err(NrgError::AudioWriteError);
+        // The first sector's audio is already in place
+        for i in 1..nb_sectors {
+            batch.copy_within(i * IN_SEC_SIZE..i * IN_SEC_SIZE + OUT_SEC_SIZE,
+                              i * OUT_SEC_SIZE);
         }
+        let out_size = nb_sectors * OUT_SEC_SIZE;
+        write_audio(out_fd, &batch[..out_size])
+            .map_err(|err| context(err, bytes_read))?;
+        control.after_write(out_size as u64, nb_sectors as u64);
+        bytes_read += (nb_sectors * IN_SEC_SIZE) as u64;
     }
 
     Ok(bytes_read)
 }
 
 
-/// Generates the output file's name from the NRG image's name.
+/// Fills `buf` from `in_fd`.
 ///
-/// The output file's name will be `img_path`'s base name stripped for its
-/// extension (if any), with a ".raw" extension.
-fn make_output_file_name(img_path: &str) -> Result<String, NrgError> {
-    let mut name = PathBuf::from(img_path);
-    name.set_extension("raw");
-    let name = try!(name.file_name().ok_or(
-        NrgError::FileName(name.to_string_lossy().into_owned())));
-
-    // Make sure the new name and the original name are different
-    if name == img_path {
-        return Err(NrgError::FileName("Input and output file are identical"
-                                      .to_string()));
-    }
+/// Short reads, which are legal on pipes and network file systems, and
+/// interrupted reads are retried; reaching the end of the file before `buf`
+/// is full is an `AudioReadError`.
+#[cfg(feature = "fs")]
+fn read_audio<R: Read>(in_fd: &mut R, buf: &mut [u8]) -> Result<(), NrgError> {
+    in_fd.read_exact(buf).map_err(|err| match err.kind() {
+        io::ErrorKind::UnexpectedEof => NrgError::AudioReadError,
+        _ => err.into(),
+    })
+}
+
 
-    Ok(name.to_string_lossy().into_owned())
+/// Writes the whole of `buf` to `out_fd`.
+///
+/// Short and interrupted writes are retried; an output that stops accepting
+/// bytes is an `AudioWriteError`.
+#[cfg(feature = "fs")]
+fn write_audio<W: Write>(out_fd: &mut W, buf: &[u8]) -> Result<(), NrgError> {
+    out_fd.write_all(buf).map_err(|err| match err.kind() {
+        io::ErrorKind::WriteZero => NrgError::AudioWriteError,
+        _ => err.into(),
+    })
 }
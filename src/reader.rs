@@ -0,0 +1,142 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to share an open NRG image between threads.
+//!
+//! An `NrgReader` reads the image with positional reads, which don't move
+//! any shared cursor: several threads can read different parts of the image
+//! from the same handle concurrently. Each thread gets its own position
+//! through an `NrgCursor`, which implements `Read + Seek` and thus works with
+//! the parsers of this crate.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+#[cfg(unix)]
+use std::os::unix::fs::FileExt;
+#[cfg(windows)]
+use std::os::windows::fs::FileExt;
+use std::path::Path;
+
+use ::error::NrgError;
+
+
+/// Thread-safe handle to an NRG image, read with positional reads.
+#[derive(Debug)]
+pub struct NrgReader {
+    file: File,
+}
+
+impl NrgReader {
+    /// Opens the image `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<NrgReader, NrgError> {
+        Ok(NrgReader::new(File::open(path)?))
+    }
+
+    /// Wraps the open image `file`; its cursor isn't used.
+    pub fn new(file: File) -> NrgReader {
+        NrgReader { file }
+    }
+
+    /// Returns the size of the image, in bytes.
+    pub fn len(&self) -> io::Result<u64> {
+        Ok(self.file.metadata()?.len())
+    }
+
+    /// Returns true if the image is empty.
+    pub fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Reads bytes from the position `offset` into `buf`, and returns the
+    /// number of bytes read.
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        #[cfg(unix)]
+        return self.file.read_at(buf, offset);
+        #[cfg(windows)]
+        return self.file.seek_read(buf, offset);
+    }
+
+    /// Reads exactly `buf.len()` bytes from the position `offset` into
+    /// `buf`.
+    pub fn read_exact_at(&self, mut buf: &mut [u8], mut offset: u64)
+                         -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(buf, offset) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(nbytes) => {
+                    buf = &mut buf[nbytes..];
+                    offset += nbytes as u64;
+                },
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a cursor reading the image from its start.
+    pub fn cursor(&self) -> NrgCursor<'_> {
+        NrgCursor { reader: self, pos: 0 }
+    }
+}
+
+impl From<File> for NrgReader {
+    fn from(file: File) -> NrgReader {
+        NrgReader::new(file)
+    }
+}
+
+
+/// Position of a reader in an `NrgReader`'s image, independent of the other
+/// cursors.
+#[derive(Clone, Debug)]
+pub struct NrgCursor<'a> {
+    reader: &'a NrgReader,
+    pos: u64,
+}
+
+impl<'a> Read for NrgCursor<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let nbytes = self.reader.read_at(buf, self.pos)?;
+        self.pos += nbytes as u64;
+        Ok(nbytes)
+    }
+}
+
+impl<'a> Seek for NrgCursor<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, delta) = match pos {
+            SeekFrom::Start(offset) => {
+                self.pos = offset;
+                return Ok(offset);
+            },
+            SeekFrom::End(delta) => (self.reader.len()?, delta),
+            SeekFrom::Current(delta) => (self.pos, delta),
+        };
+        self.pos = base.checked_add_signed(delta).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput,
+                           "Invalid seek to a negative or overflowing \
+                            position")
+        })?;
+        Ok(self.pos)
+    }
+}
@@ -0,0 +1,137 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to check an image against the limits of the Red Book (the audio
+//! CD standard), which images of questionable burns often exceed.
+
+use std::fmt;
+
+use ::metadata::metadata::NrgMetadata;
+use ::msf::{Msf, FRAMES_PER_SECOND};
+
+/// Capacity of a 74-minute disc, in sectors.
+const SECTORS_74_MINUTES: u64 = 74 * 60 * FRAMES_PER_SECOND as u64;
+/// Capacity of an 80-minute disc, in sectors.
+const SECTORS_80_MINUTES: u64 = 80 * 60 * FRAMES_PER_SECOND as u64;
+/// Minimum length of a track (4 seconds), in sectors.
+const MIN_TRACK_SECTORS: u64 = 4 * FRAMES_PER_SECOND as u64;
+/// Maximum number of tracks on a disc.
+const MAX_TRACKS: usize = 99;
+
+
+/// A Red Book limit exceeded by an image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NrgRedBookWarning {
+    /// The disc, of the given length, is longer than 74 minutes (the
+    /// capacity of the original CD) but fits on an 80-minute disc.
+    LongDisc(Msf),
+    /// The disc, of the given length, is longer than 80 minutes: it was
+    /// overburned.
+    Overburned(Msf),
+    /// A track (excluding its pre-gap) is shorter than 4 seconds.
+    ShortTrack { track_number: u8, length: Msf },
+    /// The disc has more than 99 tracks.
+    TooManyTracks(usize),
+}
+
+impl NrgRedBookWarning {
+    /// Returns the identifier of the kind of warning: "long_disc",
+    /// "overburned", "short_track" or "too_many_tracks".
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            NrgRedBookWarning::LongDisc(_) => "long_disc",
+            NrgRedBookWarning::Overburned(_) => "overburned",
+            NrgRedBookWarning::ShortTrack { .. } => "short_track",
+            NrgRedBookWarning::TooManyTracks(_) => "too_many_tracks",
+        }
+    }
+
+    /// Returns the number of the track concerned, if any.
+    pub fn track_number(&self) -> Option<u8> {
+        match *self {
+            NrgRedBookWarning::ShortTrack { track_number, .. } =>
+                Some(track_number),
+            _ => None,
+        }
+    }
+
+    /// Returns the length of the disc or the track concerned, if any.
+    pub fn length(&self) -> Option<Msf> {
+        match *self {
+            NrgRedBookWarning::LongDisc(length)
+                | NrgRedBookWarning::Overburned(length)
+                | NrgRedBookWarning::ShortTrack { length, .. } =>
+                Some(length),
+            NrgRedBookWarning::TooManyTracks(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for NrgRedBookWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NrgRedBookWarning::LongDisc(length) =>
+                write!(f, "Disc length {} exceeds 74 minutes", length),
+            NrgRedBookWarning::Overburned(length) =>
+                write!(f, "Disc length {} exceeds 80 minutes (overburned)",
+                       length),
+            NrgRedBookWarning::ShortTrack { track_number, length } =>
+                write!(f, "Track {:02} is shorter than 4 seconds ({})",
+                       track_number, length),
+            NrgRedBookWarning::TooManyTracks(count) =>
+                write!(f, "{} tracks (more than {})", count, MAX_TRACKS),
+        }
+    }
+}
+
+
+/// Checks `metadata` against the limits of the Red Book: a disc length of
+/// 74 minutes (80 minutes for the longest discs), a minimum track length of
+/// 4 seconds and a maximum of 99 tracks.
+///
+/// The disc's length includes the first track's pre-gap, as the capacity of
+/// a disc does.
+pub fn check(metadata: &NrgMetadata) -> Vec<NrgRedBookWarning> {
+    let mut warnings = Vec::new();
+    let tracks: Vec<_> = metadata.tracks().collect();
+
+    let sectors = metadata.total_sectors()
+        + tracks.first().map_or(0, |track| track.pregap_sectors());
+    let length = Msf::from_sectors(sectors as u32);
+    if sectors > SECTORS_80_MINUTES {
+        warnings.push(NrgRedBookWarning::Overburned(length));
+    } else if sectors > SECTORS_74_MINUTES {
+        warnings.push(NrgRedBookWarning::LongDisc(length));
+    }
+
+    if tracks.len() > MAX_TRACKS {
+        warnings.push(NrgRedBookWarning::TooManyTracks(tracks.len()));
+    }
+
+    warnings.extend(tracks.iter()
+                    .filter(|track| track.sector_count() < MIN_TRACK_SECTORS)
+                    .map(|track| NrgRedBookWarning::ShortTrack {
+                        track_number: track.number,
+                        length: track.length(),
+                    }));
+    warnings
+}
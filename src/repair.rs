@@ -0,0 +1,375 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to repair NRG images whose end was lost, e.g. by an interrupted
+//! copy or download.
+//!
+//! The metadata chunks of a damaged image are located by scanning its end
+//! for their IDs, then the image is copied with a rebuilt chunk area: the
+//! complete chunks found are kept, the missing CUEX, DAOX, SINF and MTYP
+//! chunks are rebuilt, and the "END!" chunk and the NRG v2 footer are
+//! appended.
+
+#[cfg(feature = "fs")]
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "fs")]
+use std::io::{BufReader, BufWriter};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use ::error::NrgError;
+use ::metadata::raw::NrgRawChunk;
+use ::raw_audio::RAW_SEC_SIZE;
+use ::writer::{self, NrgDisc, NrgDiscTrack, TrackLayout, AUDIO_MODE,
+               FIRST_PREGAP_SECTORS};
+
+
+/// Number of bytes scanned at the end of the image to find the chunks.
+const SCAN_SIZE: u64 = 1024 * 1024;
+/// IDs of the chunks that can be found in an NRG v2 image.
+const CHUNK_IDS: [&str; 12] = ["CUEX", "DAOX", "CDTX", "ETN2", "SINF",
+                               "MTYP", "AFNM", "DINF", "TOCT", "RELO",
+                               "VOLM", "END!"];
+/// Maximum size of a chunk, above which it is deemed corrupted.
+const MAX_CHUNK_SIZE: u32 = 64 * 1024;
+/// Size of a CUEX entry.
+const CUEX_ENTRY_SIZE: usize = 8;
+
+
+/// The chunks found by `scan_chunks()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NrgScan {
+    /// Position of the first chunk, which is also the end of the audio
+    /// data, or None if no chunk was found.
+    pub chunk_offset: Option<u64>,
+    /// The complete chunks found, in order, without the "END!" chunk.
+    pub chunks: Vec<NrgRawChunk>,
+    /// Whether the "END!" chunk was found.
+    pub end_found: bool,
+}
+
+
+/// The result of `write_repaired_copy()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NrgRepair {
+    /// Size of the data copied from the damaged image, in bytes.
+    pub audio_size: u64,
+    /// IDs of the chunks recovered from the damaged image.
+    pub recovered: Vec<String>,
+    /// IDs of the chunks rebuilt.
+    pub rebuilt: Vec<String>,
+    /// Size of the repaired image, in bytes.
+    pub size: u64,
+}
+
+
+/// Scans the end of the image `fd` for its metadata chunks, regardless of
+/// its footer.
+///
+/// The chunk area is the first sequence of chunks starting with a CUEX or
+/// DAOX chunk that runs until an "END!" chunk, a footer or the end of the
+/// file, possibly cutting the last chunk short.
+pub fn scan_chunks<R: Read + Seek>(fd: &mut R) -> Result<NrgScan, NrgError> {
+    let file_size = fd.seek(SeekFrom::End(0))?;
+    let start = file_size.saturating_sub(SCAN_SIZE);
+    fd.seek(SeekFrom::Start(start))?;
+    let mut buf = Vec::new();
+    fd.read_to_end(&mut buf)?;
+
+    for pos in 0..buf.len().saturating_sub(3) {
+        let id = &buf[pos..pos + 4];
+        if id != b"CUEX" && id != b"DAOX" {
+            continue;
+        }
+        if let Some(scan) = scan_chunk_area(&buf, start, pos) {
+            debug!("Found the chunk area at offset {}", start + pos as u64);
+            return Ok(scan);
+        }
+    }
+    Ok(NrgScan { chunk_offset: None, chunks: Vec::new(), end_found: false })
+}
+
+
+/// Reads the sequence of chunks starting at `pos` in `buf`, which holds the
+/// end of the image from the byte `start`.
+///
+/// Returns None if the sequence holds an unknown or corrupted chunk.
+fn scan_chunk_area(buf: &[u8], start: u64, pos: usize) -> Option<NrgScan> {
+    let mut scan = NrgScan {
+        chunk_offset: Some(start + pos as u64),
+        chunks: Vec::new(),
+        end_found: false,
+    };
+    let mut pos = pos;
+    while pos + 8 <= buf.len() {
+        let id = String::from_utf8_lossy(&buf[pos..pos + 4]).into_owned();
+        if id == "NER5" && pos + 12 == buf.len() {
+            break;
+        }
+        let mut size = [0u8; 4];
+        size.copy_from_slice(&buf[pos + 4..pos + 8]);
+        let size = u32::from_be_bytes(size);
+        if !CHUNK_IDS.contains(&id.as_str()) || !valid_chunk_size(&id, size) {
+            return None;
+        }
+        if id == "END!" {
+            scan.end_found = true;
+            break;
+        }
+        let end = pos + 8 + size as usize;
+        if end > buf.len() {
+            debug!("{} chunk cut short at offset {}", id, start + pos as u64);
+            break;
+        }
+        scan.chunks.push(NrgRawChunk {
+            id,
+            offset: start + pos as u64,
+            payload: buf[pos + 8..end].to_vec(),
+        });
+        pos = end;
+    }
+    Some(scan)
+}
+
+
+/// Tells whether `size` is a plausible size for a chunk `id`.
+fn valid_chunk_size(id: &str, size: u32) -> bool {
+    let entries = CUEX_ENTRY_SIZE as u32;
+    let daox_header = writer::DAOX_HEADER_SIZE as u32;
+    let daox_track = writer::DAOX_TRACK_SIZE as u32;
+    match id {
+        "END!" => size == 0,
+        "SINF" | "MTYP" => size == 4,
+        "CUEX" => size.is_multiple_of(entries) && size <= entries * 200,
+        "DAOX" => size >= daox_header
+            && (size - daox_header).is_multiple_of(daox_track)
+            && size <= daox_header + daox_track * 99,
+        _ => size <= MAX_CHUNK_SIZE,
+    }
+}
+
+
+/// Writes a repaired copy of the damaged NRG image `in_fd` to `out`.
+///
+/// The data located before the chunk area found by `scan_chunks()` is
+/// copied as is, followed by the complete chunks found and the rebuilt
+/// ones:
+///
+/// - a missing DAOX chunk is rebuilt from the CUEX chunk, for audio images
+///   made of 2352-byte sectors;
+/// - if neither the CUEX nor the DAOX chunk was found, the whole image is
+///   deemed to be a single audio track made of 2352-byte sectors (the
+///   incomplete last sector is dropped), whose first 2 seconds are its
+///   pre-gap;
+/// - the missing SINF and MTYP chunks are rebuilt from the track count.
+pub fn write_repaired_copy<R: Read + Seek, W: Write>(in_fd: &mut R,
+                                                     out: &mut W)
+                                                     -> Result<NrgRepair,
+                                                               NrgError> {
+    let scan = scan_chunks(in_fd)?;
+    let mut chunks = scan.chunks;
+    let mut rebuilt = Vec::new();
+    let has_chunk = |chunks: &[NrgRawChunk], id: &str| {
+        chunks.iter().any(|chunk| chunk.id == id)
+    };
+
+    let audio_size = match scan.chunk_offset {
+        Some(offset) => offset,
+        None => {
+            let file_size = in_fd.seek(SeekFrom::End(0))?;
+            file_size - file_size % u64::from(RAW_SEC_SIZE)
+        },
+    };
+    if !has_chunk(&chunks, "CUEX") && !has_chunk(&chunks, "DAOX") {
+        let (cuex, daox) = single_track_chunks(audio_size)?;
+        chunks.insert(0, raw_chunk("DAOX", daox));
+        chunks.insert(0, raw_chunk("CUEX", cuex));
+        rebuilt.extend(vec!["CUEX".to_string(), "DAOX".to_string()]);
+    } else if !has_chunk(&chunks, "DAOX") {
+        let cuex = chunks.iter().position(|chunk| chunk.id == "CUEX")
+            .unwrap_or_default();
+        let daox = daox_from_cuex(&chunks[cuex].payload, audio_size)?;
+        chunks.insert(cuex + 1, raw_chunk("DAOX", daox));
+        rebuilt.push("DAOX".to_string());
+    }
+    if !has_chunk(&chunks, "SINF") {
+        let daox_size = chunks.iter().find(|chunk| chunk.id == "DAOX")
+            .map_or(0, |chunk| chunk.payload.len());
+        let track_count = (daox_size.saturating_sub(writer::DAOX_HEADER_SIZE)
+                           / writer::DAOX_TRACK_SIZE) as u32;
+        chunks.push(raw_chunk("SINF", track_count.to_be_bytes().to_vec()));
+        rebuilt.push("SINF".to_string());
+    }
+    if !has_chunk(&chunks, "MTYP") {
+        chunks.push(raw_chunk("MTYP", 1u32.to_be_bytes().to_vec()));
+        rebuilt.push("MTYP".to_string());
+    }
+    rebuilt.push("END!".to_string());
+
+    // Copy the audio data and write the chunks
+    in_fd.seek(SeekFrom::Start(0))?;
+    let copied = io::copy(&mut in_fd.take(audio_size), out)?;
+    if copied != audio_size {
+        return Err(NrgError::AudioLength { expected: audio_size, copied });
+    }
+    let mut size = copied;
+    for chunk in &chunks {
+        size += writer::write_chunk(out, &chunk.id, &chunk.payload)?;
+    }
+    size += writer::write_chunk(out, "END!", &[])?;
+    size += writer::write_footer(out, audio_size)?;
+
+    let recovered = chunks.into_iter()
+        .map(|chunk| chunk.id)
+        .filter(|id| !rebuilt.contains(id))
+        .collect();
+    Ok(NrgRepair { audio_size, recovered, rebuilt, size })
+}
+
+
+/// Writes a repaired copy of the damaged NRG image `img_path` to
+/// `out_path`, see `write_repaired_copy()`.
+#[cfg(feature = "fs")]
+pub fn repair_file(img_path: &Path, out_path: &Path)
+                   -> Result<NrgRepair, NrgError> {
+    let same_file = match (fs::canonicalize(img_path),
+                           fs::canonicalize(out_path)) {
+        (Ok(img_path), Ok(out_path)) => img_path == out_path,
+        _ => false,
+    };
+    if same_file {
+        return Err(NrgError::FileName(
+            "Input and output file are identical".to_string()));
+    }
+
+    let mut in_fd = BufReader::new(File::open(img_path)?);
+    let mut out_fd = BufWriter::new(File::create(out_path)?);
+    let repair = write_repaired_copy(&mut in_fd, &mut out_fd)?;
+    out_fd.flush()?;
+    Ok(repair)
+}
+
+
+/// Returns a chunk to write, whose offset is unknown yet.
+fn raw_chunk(id: &str, payload: Vec<u8>) -> NrgRawChunk {
+    NrgRawChunk { id: id.to_string(), offset: 0, payload }
+}
+
+
+/// Returns the CUEX and DAOX payloads of a single audio track made of the
+/// first `audio_size` bytes of the image.
+fn single_track_chunks(audio_size: u64)
+                       -> Result<(Vec<u8>, Vec<u8>), NrgError> {
+    let sectors = audio_size / u64::from(RAW_SEC_SIZE);
+    let pregap = u64::from(FIRST_PREGAP_SECTORS);
+    if sectors <= pregap {
+        return Err(NrgError::NrgFormat(format!(
+            "No audio data to repair: {} sectors", sectors)));
+    }
+    let disc = audio_disc(1, 1);
+    let layouts = [TrackLayout { index0: 0, index1: pregap, end: sectors }];
+    Ok((writer::cuex_payload(&disc, &layouts),
+        writer::daox_payload(&disc, &layouts)))
+}
+
+
+/// Returns the DAOX payload matching the CUEX chunk `payload`, for an audio
+/// image made of 2352-byte sectors whose audio data ends at `audio_size`.
+fn daox_from_cuex(payload: &[u8], audio_size: u64)
+                  -> Result<Vec<u8>, NrgError> {
+    let error = |problem: &str| NrgError::NrgFormat(format!(
+        "Can't rebuild the DAOX chunk: {}", problem));
+
+    // Positions of the indexes of each track, in sectors from the start of
+    // the image
+    let mut tracks: Vec<(u8, Option<u64>, Option<u64>)> = Vec::new();
+    let mut lead_out = None;
+    for entry in payload.chunks(CUEX_ENTRY_SIZE) {
+        let mut position = [0u8; 4];
+        position.copy_from_slice(&entry[4..]);
+        let position = i64::from(i32::from_be_bytes(position))
+            + i64::from(FIRST_PREGAP_SECTORS);
+        if position < 0 {
+            return Err(error("negative position"));
+        }
+        let position = position as u64;
+        match (entry[1], from_bcd(entry[2])) {
+            (0, _) => continue,
+            (0xAA, _) => lead_out = Some(position),
+            (_, index) if index > 1 => continue,
+            (track, index) => {
+                let number = from_bcd(track);
+                if tracks.last().map(|t| t.0) != Some(number) {
+                    tracks.push((number, None, None));
+                }
+                let last = tracks.len() - 1;
+                if index == 0 {
+                    tracks[last].1 = Some(position);
+                } else {
+                    tracks[last].2 = Some(position);
+                }
+            },
+        }
+    }
+
+    let lead_out = lead_out.ok_or_else(|| error("no lead-out"))?;
+    if lead_out * u64::from(RAW_SEC_SIZE) != audio_size {
+        return Err(error("the image is not made of 2352-byte sectors"));
+    }
+    let first_track = tracks.first().map_or(0, |track| track.0);
+    if tracks.is_empty() || first_track == 0 {
+        return Err(error("no tracks"));
+    }
+    let mut layouts = Vec::new();
+    for (i, &(number, index0, index1)) in tracks.iter().enumerate() {
+        let index1 = index1.ok_or_else(|| error("missing index #1"))?;
+        let index0 = index0.unwrap_or(index1);
+        let end = tracks.get(i + 1)
+            .and_then(|next| next.1.or(next.2))
+            .unwrap_or(lead_out);
+        if number != first_track + i as u8 || index0 > index1
+            || index1 > end {
+            return Err(error("invalid tracks"));
+        }
+        layouts.push(TrackLayout { index0, index1, end });
+    }
+    let disc = audio_disc(first_track, tracks.len());
+    Ok(writer::daox_payload(&disc, &layouts))
+}
+
+
+/// Returns an audio disc of `count` tracks, starting at `first_track`.
+fn audio_disc(first_track: u8, count: usize) -> NrgDisc {
+    let track = NrgDiscTrack { mode: AUDIO_MODE, ..NrgDiscTrack::default() };
+    NrgDisc {
+        first_track,
+        tracks: vec![track; count],
+        ..NrgDisc::default()
+    }
+}
+
+
+/// Decodes the binary-coded decimal `value`.
+fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
@@ -0,0 +1,173 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to convert the sample rate of the audio data, e.g. from the
+//! 44.1 kHz of CD audio to the 48 kHz required by some codecs.
+
+use std::f64::consts::PI;
+
+/// Number of input samples on each side of the interpolated position taken
+/// into account by the filter.
+const HALF_TAPS: usize = 24;
+/// Cut-off frequency of the low-pass filter, relative to the Nyquist
+/// frequency of the lower of the two rates.
+const CUTOFF: f64 = 0.91;
+
+
+/// Converter of a stream of 16-bit samples from one sample rate to another,
+/// through a windowed-sinc interpolation filter.
+///
+/// The samples are interleaved, and converted to floating-point samples in
+/// [-1, 1]. The output is aligned with the input: output sample `n` is the
+/// input signal at the time of sample `n`, without added delay.
+#[derive(Clone, Debug)]
+pub struct Resampler {
+    channels: usize,
+    /// Number of phases of the filter: the output rate divided by the
+    /// greatest common divisor of the rates.
+    up: u64,
+    /// The input rate divided by the greatest common divisor of the rates.
+    down: u64,
+    /// Coefficients of the filter, `2 * HALF_TAPS` for each phase.
+    filters: Vec<f32>,
+    /// Input frames not consumed yet, interleaved.
+    buffer: Vec<f32>,
+    /// Index of the first frame of `buffer` in the input stream, negative
+    /// at the beginning (the stream is preceded by silence).
+    buffer_start: i64,
+    input_frames: u64,
+    output_frames: u64,
+}
+
+impl Resampler {
+    /// Builds a converter of `channels` channels from `input_rate` to
+    /// `output_rate` (in Hz).
+    pub fn new(input_rate: u32, output_rate: u32, channels: usize)
+               -> Resampler {
+        let divisor = gcd(u64::from(input_rate), u64::from(output_rate));
+        let up = u64::from(output_rate) / divisor;
+        let down = u64::from(input_rate) / divisor;
+
+        // Cut-off frequency, in cycles per input sample (without conversion,
+        // the filter is the identity)
+        let cutoff = if up == down {
+            0.5
+        } else {
+            CUTOFF * 0.5 * (up as f64 / down as f64).min(1.0)
+        };
+        let mut filters = Vec::with_capacity(up as usize * 2 * HALF_TAPS);
+        for phase in 0..up {
+            let fraction = phase as f64 / up as f64;
+            let taps: Vec<f64> = (0..2 * HALF_TAPS)
+                .map(|k| {
+                    let t = k as f64 - (HALF_TAPS - 1) as f64 - fraction;
+                    let window = 0.42
+                        + 0.5 * (PI * t / HALF_TAPS as f64).cos()
+                        + 0.08 * (2.0 * PI * t / HALF_TAPS as f64).cos();
+                    let x = 2.0 * cutoff * t;
+                    let sinc = if x == 0.0 {
+                        1.0
+                    } else {
+                        (PI * x).sin() / (PI * x)
+                    };
+                    sinc * window
+                })
+                .collect();
+            // Unity gain for each phase
+            let sum: f64 = taps.iter().sum();
+            filters.extend(taps.iter().map(|tap| (tap / sum) as f32));
+        }
+
+        Resampler {
+            channels,
+            up,
+            down,
+            filters,
+            buffer: vec![0.0; (HALF_TAPS - 1) * channels],
+            buffer_start: -(HALF_TAPS as i64 - 1),
+            input_frames: 0,
+            output_frames: 0,
+        }
+    }
+
+    /// Converts `input`, appending the samples that can be computed to
+    /// `output`.
+    ///
+    /// An incomplete frame at the end of `input` is ignored.
+    pub fn process(&mut self, input: &[i16], output: &mut Vec<f32>) {
+        let frames = input.len() / self.channels;
+        self.buffer.extend(input[..frames * self.channels].iter()
+                           .map(|&sample| f32::from(sample) / 32768.0));
+        self.input_frames += frames as u64;
+        self.convert(output, None);
+    }
+
+    /// Converts the end of the stream, appending the last samples to
+    /// `output`, so that it holds the input's duration at the output rate.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        self.buffer.extend(vec![0.0; (HALF_TAPS + 1) * self.channels]);
+        let total = (self.input_frames * self.up).div_ceil(self.down);
+        self.convert(output, Some(total));
+    }
+
+    /// Computes the output frames whose input frames are buffered, up to
+    /// `limit` frames in total.
+    fn convert(&mut self, output: &mut Vec<f32>, limit: Option<u64>) {
+        let buffered = (self.buffer.len() / self.channels) as i64;
+        let taps = 2 * HALF_TAPS;
+        loop {
+            if limit.is_some_and(|limit| self.output_frames >= limit) {
+                break;
+            }
+            let position = self.output_frames * self.down;
+            let index = (position / self.up) as i64;
+            let first = index - (HALF_TAPS as i64 - 1) - self.buffer_start;
+            if first + taps as i64 > buffered {
+                break;
+            }
+            let phase = (position % self.up) as usize;
+            let filter = &self.filters[phase * taps..][..taps];
+            let frames = &self.buffer[first as usize * self.channels..]
+                [..taps * self.channels];
+            for channel in 0..self.channels {
+                let sample: f32 = filter.iter()
+                    .zip(frames[channel..].iter().step_by(self.channels))
+                    .map(|(tap, sample)| tap * sample)
+                    .sum();
+                output.push(sample);
+            }
+            self.output_frames += 1;
+        }
+
+        // Drop the frames that won't be used anymore
+        let next = (self.output_frames * self.down / self.up) as i64;
+        let used = (next - (HALF_TAPS as i64 - 1) - self.buffer_start)
+            .clamp(0, buffered);
+        self.buffer.drain(..used as usize * self.channels);
+        self.buffer_start += used;
+    }
+}
+
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
@@ -0,0 +1,139 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to decode the audio data of an image into stereo samples.
+//!
+//! Audio CD sectors hold 588 samples of 16-bit little-endian signed PCM,
+//! the left channel first. `NrgSamples` reads them from the image, skipping
+//! the sub-channel data if the image has any, so that the analyses don't
+//! have to deal with the byte layout.
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use ::error::NrgError;
+use ::raw_audio::{RAW_SEC_SIZE, RAW96_SEC_SIZE, SAMPLE_SIZE};
+
+/// Maximum number of sectors read at once.
+const READ_SECTORS: u64 = 75;
+
+
+/// Iterator over the stereo samples (left, right) of a range of sectors of
+/// an image, returned by nrgrip::metadata::track::Track::samples().
+///
+/// An I/O error is yielded once, and ends the iteration.
+#[derive(Debug)]
+pub struct NrgSamples<R> {
+    fd: R,
+    pos: u64,
+    end: u64,
+    sector_size: u64,
+    buf: Vec<u8>,
+    buf_pos: usize,
+}
+
+impl<R: Read + Seek> NrgSamples<R> {
+    /// Reads the samples of the image `fd` from the byte `start` to the byte
+    /// `end`, in sectors of `sector_size` bytes (2352, or 2448 with the
+    /// sub-channel).
+    ///
+    /// A partial sector at the end of the range is ignored.
+    pub fn new(mut fd: R, start: u64, end: u64, sector_size: u16)
+               -> Result<NrgSamples<R>, NrgError> {
+        if sector_size != RAW_SEC_SIZE && sector_size != RAW96_SEC_SIZE {
+            return Err(NrgError::NrgFormat(format!(
+                "Can't decode the audio in {}-byte sectors", sector_size)));
+        }
+        fd.seek(SeekFrom::Start(start))?;
+        Ok(NrgSamples {
+            fd,
+            pos: start,
+            end: end.max(start),
+            sector_size: u64::from(sector_size),
+            buf: Vec::new(),
+            buf_pos: 0,
+        })
+    }
+
+    /// Returns the number of samples left.
+    pub fn remaining(&self) -> u64 {
+        let buffered = (self.buf.len() - self.buf_pos) / SAMPLE_SIZE;
+        let sectors = (self.end - self.pos) / self.sector_size;
+        let samples_per_sector = u64::from(RAW_SEC_SIZE) / SAMPLE_SIZE as u64;
+        buffered as u64 + sectors * samples_per_sector
+    }
+
+    /// Reads the next sectors into the buffer, without their sub-channel.
+    ///
+    /// Returns false at the end of the range.
+    fn fill_buffer(&mut self) -> io::Result<bool> {
+        let nb_sectors = ((self.end - self.pos) / self.sector_size)
+            .min(READ_SECTORS);
+        if nb_sectors == 0 {
+            return Ok(false);
+        }
+        self.buf.resize((nb_sectors * self.sector_size) as usize, 0);
+        if let Err(err) = self.fd.read_exact(&mut self.buf) {
+            self.pos = self.end;
+            self.buf.clear();
+            return Err(err);
+        }
+        self.pos += nb_sectors * self.sector_size;
+
+        if self.sector_size != u64::from(RAW_SEC_SIZE) {
+            let sector_size = self.sector_size as usize;
+            let audio_size = RAW_SEC_SIZE as usize;
+            for i in 1..nb_sectors as usize {
+                let start = i * sector_size;
+                self.buf.copy_within(start..start + audio_size,
+                                     i * audio_size);
+            }
+            self.buf.truncate(nb_sectors as usize * audio_size);
+        }
+        self.buf_pos = 0;
+        Ok(true)
+    }
+}
+
+impl<R: Read + Seek> Iterator for NrgSamples<R> {
+    type Item = io::Result<[i16; 2]>;
+
+    fn next(&mut self) -> Option<io::Result<[i16; 2]>> {
+        if self.buf_pos >= self.buf.len() {
+            match self.fill_buffer() {
+                Ok(true) => (),
+                Ok(false) => return None,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        let bytes = &self.buf[self.buf_pos..self.buf_pos + SAMPLE_SIZE];
+        self.buf_pos += SAMPLE_SIZE;
+        Some(Ok([i16::from_le_bytes([bytes[0], bytes[1]]),
+                 i16::from_le_bytes([bytes[2], bytes[3]])]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // A read error may end the iteration before the unbuffered samples
+        let buffered = (self.buf.len() - self.buf_pos) / SAMPLE_SIZE;
+        (buffered, usize::try_from(self.remaining()).ok())
+    }
+}
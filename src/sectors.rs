@@ -0,0 +1,398 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to validate the raw data sectors of the data tracks: their sync
+//! pattern, the address in their header, and their error detection code
+//! (EDC); the Mode 1 sectors can be repaired with their error correction code
+//! (ECC).
+
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+use ::error::NrgError;
+use ::metadata::metadata::NrgMetadata;
+use ::msf::Msf;
+use ::raw_audio::RAW_SEC_SIZE;
+
+/// Sync pattern starting raw data sectors.
+pub const SYNC_PATTERN: [u8; 12] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+                                    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+/// Bit of the Mode 2 sub-header's sub-mode byte flagging Form 2 sectors.
+pub const FORM2_SUBMODE: u8 = 0x20;
+
+/// Maximum number of sectors read at once while scanning a track.
+const SCAN_SECTORS: u64 = 75;
+
+/// Lookup table of the EDC, a CRC-32 whose reversed polynomial is
+/// 0xD8018001.
+const EDC_TABLE: [u32; 256] = make_edc_table();
+
+/// Powers of the primitive element of GF(2^8), whose polynomial is
+/// x^8 + x^4 + x^3 + x^2 + 1, and their logarithms.
+const GF_EXP: [u8; 256] = make_gf_exp();
+const GF_LOG: [u8; 256] = make_gf_log();
+
+/// Offset of the bytes covered by the ECC in a sector (after the sync
+/// pattern), and number of bytes covered by the P and Q parity.
+const ECC_START: usize = 12;
+const P_SIZE: usize = 2064;
+const Q_SIZE: usize = 2236;
+
+/// Number of rounds of P and Q corrections attempted on a sector.
+const ECC_ROUNDS: usize = 3;
+
+
+const fn make_edc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut edc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            edc = if edc & 1 != 0 {
+                (edc >> 1) ^ 0xD801_8001
+            } else {
+                edc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = edc;
+        i += 1;
+    }
+    table
+}
+
+
+const fn make_gf_exp() -> [u8; 256] {
+    let mut table = [0u8; 256];
+    let mut x: u32 = 1;
+    let mut i = 0;
+    while i < 256 {
+        table[i] = x as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= 0x11D;
+        }
+        i += 1;
+    }
+    table
+}
+
+
+const fn make_gf_log() -> [u8; 256] {
+    let exp = make_gf_exp();
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 255 {
+        table[exp[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+}
+
+
+/// Multiplies `x` by the `n`-th power of the primitive element.
+fn gf_mul_exp(x: u8, n: usize) -> u8 {
+    if x == 0 {
+        return 0;
+    }
+    GF_EXP[(usize::from(GF_LOG[usize::from(x)]) + n) % 255]
+}
+
+
+/// Computes the EDC of `bytes`.
+pub fn edc(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |edc, &b| {
+        (edc >> 8) ^ EDC_TABLE[((edc ^ u32::from(b)) & 0xFF) as usize]
+    })
+}
+
+
+/// Problem found in a raw data sector.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NrgSectorProblem {
+    /// The sector doesn't start with the sync pattern.
+    Sync,
+    /// The header holds another address than the sector's; the address
+    /// found, in BCD.
+    Address([u8; 3]),
+    /// The header holds an unknown mode.
+    Mode(u8),
+    /// The EDC doesn't match the contents of the sector.
+    Edc,
+}
+
+impl fmt::Display for NrgSectorProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NrgSectorProblem::Sync => write!(f, "no sync pattern"),
+            NrgSectorProblem::Address(address) =>
+                write!(f, "wrong address {:02X}:{:02X}:{:02X} in the header",
+                       address[0], address[1], address[2]),
+            NrgSectorProblem::Mode(mode) => write!(f, "unknown mode {}", mode),
+            NrgSectorProblem::Edc => write!(f, "EDC mismatch"),
+        }
+    }
+}
+
+
+/// A damaged sector of a data track.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgBadSector {
+    pub track_number: u8,
+    pub lba: i32,
+    pub problem: NrgSectorProblem,
+}
+
+impl fmt::Display for NrgBadSector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Track {:02}, sector {}: {}", self.track_number, self.lba,
+               self.problem)
+    }
+}
+
+
+/// Checks the raw data `sector` whose logical block address is `lba`.
+///
+/// Only the first 2352 bytes of `sector` are looked at. Mode 0 sectors are
+/// not checked further than their header, nor the Mode 2 Form 2 sectors
+/// whose optional EDC is null.
+pub fn check_sector(sector: &[u8], lba: i32)
+                    -> Option<NrgSectorProblem> {
+    if sector[..12] != SYNC_PATTERN {
+        return Some(NrgSectorProblem::Sync);
+    }
+
+    let bcd = |b: u8| (b >> 4) * 10 + (b & 0x0F);
+    let address = Msf::new(u32::from(bcd(sector[12])), bcd(sector[13]),
+                           bcd(sector[14]));
+    if i64::from(address.to_sectors()) != i64::from(lba) + 150 {
+        return Some(NrgSectorProblem::Address([sector[12], sector[13],
+                                               sector[14]]));
+    }
+
+    // Range of the bytes covered by the EDC, which follows them, and
+    // whether the EDC is optional
+    let (edc_range, optional) = match sector[15] {
+        0 => return None,
+        1 => (0..2064, false),
+        2 if sector[18] & FORM2_SUBMODE == 0 => (16..2072, false),
+        2 => (16..2348, true),
+        mode => return Some(NrgSectorProblem::Mode(mode)),
+    };
+    let stored = &sector[edc_range.end..edc_range.end + 4];
+    let stored = u32::from_le_bytes([stored[0], stored[1], stored[2],
+                                     stored[3]]);
+    if optional && stored == 0 {
+        return None;
+    }
+    if edc(&sector[edc_range]) != stored {
+        return Some(NrgSectorProblem::Edc);
+    }
+    None
+}
+
+
+/// The two Reed-Solomon codes of the ECC.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Parity {
+    /// 86 codewords of 24 bytes, plus 2 P parity bytes.
+    P,
+    /// 52 codewords of 43 bytes, plus 2 Q parity bytes.
+    Q,
+}
+
+impl Parity {
+    fn codewords(self) -> usize {
+        match self {
+            Parity::P => 86,
+            Parity::Q => 52,
+        }
+    }
+
+    /// Returns the positions of the bytes of `codeword`, parity included,
+    /// relatively to `ECC_START`.
+    fn positions(self, codeword: usize) -> Vec<usize> {
+        let (symbols, start, step, size) = match self {
+            Parity::P => (24, codeword, 86, P_SIZE),
+            Parity::Q => (43, (codeword >> 1) * 86 + (codeword & 1), 88,
+                          Q_SIZE),
+        };
+        let mut positions: Vec<usize> = (0..symbols)
+            .map(|i| (start + i * step) % size)
+            .collect();
+        positions.push(size + codeword);
+        positions.push(size + self.codewords() + codeword);
+        positions
+    }
+
+    /// Computes the syndromes of `codeword` in `data`: the sum of its bytes,
+    /// and the sum of its bytes weighted by decreasing powers of the
+    /// primitive element, down to 1 for the last one.
+    fn syndromes(self, data: &[u8], codeword: usize) -> (u8, u8) {
+        let positions = self.positions(codeword);
+        let n = positions.len();
+        positions.iter().enumerate()
+            .fold((0, 0), |(s0, s1), (i, &pos)| {
+                (s0 ^ data[pos], s1 ^ gf_mul_exp(data[pos], n - 1 - i))
+            })
+    }
+
+    /// Writes the parity bytes of all the codewords of `data`.
+    fn generate(self, data: &mut [u8]) {
+        for codeword in 0..self.codewords() {
+            let positions = self.positions(codeword);
+            let (p0, p1) = (positions[positions.len() - 2],
+                            positions[positions.len() - 1]);
+            data[p0] = 0;
+            data[p1] = 0;
+            // With null parity bytes, the syndromes are those of the data:
+            // the parity x, y must satisfy x + y = s0 and a.x + y = s1
+            let (s0, s1) = self.syndromes(data, codeword);
+            let x = gf_mul_exp(s0 ^ s1, 255 - usize::from(GF_LOG[3]));
+            data[p0] = x;
+            data[p1] = s0 ^ x;
+        }
+    }
+
+    /// Corrects the codewords of `data` holding a single wrong byte; those
+    /// holding more errors are left unchanged.
+    fn correct(self, data: &mut [u8]) {
+        for codeword in 0..self.codewords() {
+            let (s0, s1) = self.syndromes(data, codeword);
+            if s0 == 0 || s1 == 0 {
+                continue;
+            }
+            // A single error e at position i gives s0 = e and
+            // s1 = e.a^(n - 1 - i)
+            let positions = self.positions(codeword);
+            let n = positions.len();
+            let power = (usize::from(GF_LOG[usize::from(s1)]) + 255
+                         - usize::from(GF_LOG[usize::from(s0)])) % 255;
+            if power < n {
+                data[positions[n - 1 - power]] ^= s0;
+            }
+        }
+    }
+}
+
+
+/// Writes the P and Q parity of the Mode 1 `sector`, whose EDC must be set.
+pub fn write_ecc(sector: &mut [u8]) {
+    let data = &mut sector[ECC_START..usize::from(RAW_SEC_SIZE)];
+    Parity::P.generate(data);
+    Parity::Q.generate(data);
+}
+
+
+/// Outcome of `repair_sector()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NrgSectorRepair {
+    /// The sector has no problem.
+    Intact,
+    /// The sector's errors were corrected.
+    Repaired,
+    /// The sector's errors can't be corrected.
+    Irrecoverable,
+}
+
+
+/// Repairs the raw data `sector` whose logical block address is `lba`, if
+/// it has a problem.
+///
+/// Only Mode 1 sectors can be repaired: their sync pattern is restored,
+/// and the bytes wrong in only one of their P or Q codewords are corrected,
+/// until the sector is valid. `sector` is left unchanged if it can't be
+/// repaired.
+pub fn repair_sector(sector: &mut [u8], lba: i32) -> NrgSectorRepair {
+    if check_sector(sector, lba).is_none() {
+        return NrgSectorRepair::Intact;
+    }
+
+    let mut repaired = sector[..usize::from(RAW_SEC_SIZE)].to_vec();
+    repaired[..12].copy_from_slice(&SYNC_PATTERN);
+    let mut valid = false;
+    for _ in 0..ECC_ROUNDS {
+        Parity::P.correct(&mut repaired[ECC_START..]);
+        Parity::Q.correct(&mut repaired[ECC_START..]);
+        valid = repaired[15] == 1 && check_sector(&repaired, lba).is_none();
+        if valid {
+            break;
+        }
+    }
+
+    if !valid {
+        return NrgSectorRepair::Irrecoverable;
+    }
+    sector[..repaired.len()].copy_from_slice(&repaired);
+    NrgSectorRepair::Repaired
+}
+
+
+/// Checks the raw sectors of the data tracks of the NRG image `in_fd`.
+///
+/// - `in_fd` is the handler to the NRG image file.
+/// - `metadata` is the metadata extracted from `in_fd` by nrgrip::metadata.
+///
+/// The data tracks are read from their index #0 to their end; those whose
+/// sectors are shorter than 2352 bytes, without sync pattern nor header,
+/// are skipped.
+///
+/// Returns the damaged sectors, in order.
+pub fn check_data_sectors<R: Read + Seek>(in_fd: &mut R,
+                                          metadata: &NrgMetadata)
+                                          -> Result<Vec<NrgBadSector>,
+                                                    NrgError> {
+    let mut bad_sectors = Vec::new();
+    let mut buf = Vec::new();
+    for track in metadata.tracks().filter(|track| track.is_data()) {
+        let sector_size = u64::from(track.sector_size);
+        if sector_size < u64::from(RAW_SEC_SIZE) {
+            continue;
+        }
+
+        let first_lba = i64::from(track.position_sectors)
+            - ((track.index1 - track.index0) / sector_size) as i64;
+        let nb_sectors = (track.track_end - track.index0) / sector_size;
+        in_fd.seek(SeekFrom::Start(track.index0))?;
+        let mut sector_index = 0;
+        while sector_index < nb_sectors {
+            let count = (nb_sectors - sector_index).min(SCAN_SECTORS);
+            buf.resize((count * sector_size) as usize, 0);
+            in_fd.read_exact(&mut buf)?;
+            for sector in buf.chunks(sector_size as usize) {
+                let lba = (first_lba + sector_index as i64) as i32;
+                if let Some(problem) = check_sector(sector, lba) {
+                    let bad_sector = NrgBadSector {
+                        track_number: track.number,
+                        lba,
+                        problem,
+                    };
+                    debug!("{}", bad_sector);
+                    bad_sectors.push(bad_sector);
+                }
+                sector_index += 1;
+            }
+        }
+    }
+    Ok(bad_sectors)
+}
@@ -0,0 +1,391 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to read the sessions of multi-session NRG images, and write each
+//! of them as a standalone image.
+//!
+//! Each session of a disc-at-once image is described by its own CUEX and
+//! DAOX chunks (and possibly a CDTX chunk), followed at the end of the chunk
+//! area by one SINF chunk per session; the other chunks describe the whole
+//! disc.
+
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::convert::TryFrom;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "fs")]
+use std::io::{BufReader, BufWriter};
+use std::ops::Range;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use ::error::NrgError;
+#[cfg(feature = "fs")]
+use ::extraction::ExtractedFile;
+use ::metadata;
+use ::metadata::metadata::NrgMetadata;
+use ::metadata::raw::{self, NrgRawChunk};
+use ::writer::{self, DAOX_HEADER_SIZE, DAOX_TRACK_SIZE,
+               FIRST_PREGAP_SECTORS};
+
+
+/// Size of a CUEX entry.
+const CUEX_ENTRY_SIZE: usize = 8;
+/// Track number of the lead-in area in the CUEX entries.
+const LEAD_IN_TRACK: u8 = 0x00;
+
+
+/// A session of an NRG image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NrgSession {
+    /// Number of the session, starting from 1.
+    pub number: usize,
+    /// The chunks to write in the standalone image of the session: its
+    /// CUEX, DAOX and CDTX chunks, and the chunks describing the whole disc
+    /// (e.g. MTYP), without the SINF and "END!" chunks.
+    pub chunks: Vec<NrgRawChunk>,
+    /// Byte range of the session's tracks in the image.
+    pub data_range: Range<u64>,
+    /// Number of tracks of the session.
+    pub track_count: u32,
+}
+
+
+/// Reads the sessions of the NRG image `fd`.
+///
+/// Only disc-at-once sessions (CUEX and DAOX chunks) are handled.
+pub fn read_sessions<R: Read + Seek>(fd: &mut R)
+                                     -> Result<Vec<NrgSession>, NrgError> {
+    group_sessions(raw::read_raw_chunks(fd)?)
+}
+
+
+/// Reads the metadata of the session numbered `number` of the NRG image
+/// `fd`.
+///
+/// The metadata holds the session's CUEX and DAOX chunks, the chunks
+/// describing the whole disc, a SINF chunk, and the AFNM file names of the
+/// session's tracks. The CUEX positions are moved as in the image written by
+/// `write_session()`, so that the cue sheets match the session's audio; the
+/// DAOX byte positions are kept, so that the session's tracks are extracted
+/// from `fd` like those of a single-session image.
+pub fn read_session_metadata<R: Read + Seek>(fd: &mut R, number: usize)
+                                             -> Result<NrgMetadata, NrgError> {
+    let file_size = fd.seek(SeekFrom::End(0))?;
+    let chunks = raw::read_raw_chunks(fd)?;
+    let chunk_offset = chunks.first().map_or(file_size, |chunk| chunk.offset);
+    let afnm = chunks.iter().find(|chunk| chunk.id == "AFNM").cloned();
+    let sessions = group_sessions(chunks)?;
+    let session = sessions.iter().find(|session| session.number == number)
+        .ok_or_else(|| NrgError::NrgFormat(format!(
+            "No session {} in the image ({} sessions)", number,
+            sessions.len())))?;
+
+    let start = session.data_range.start;
+    let daox = session.chunks.iter().find(|chunk| chunk.id == "DAOX")
+        .ok_or_else(|| NrgError::NrgFormat("No DAOX chunk".to_string()))?;
+    let first = daox_tracks(daox)?.remove(0);
+    let mut session_chunks = Vec::new();
+    for chunk in &session.chunks {
+        let mut chunk = chunk.clone();
+        if chunk.id == "CUEX" {
+            chunk.payload = move_cuex(&chunk, &first, start)?;
+        }
+        session_chunks.push(chunk);
+    }
+    session_chunks.push(NrgRawChunk {
+        id: "SINF".to_string(),
+        offset: 0,
+        payload: session.track_count.to_be_bytes().to_vec(),
+    });
+
+    // Keep the file names of the session's tracks
+    if let Some(mut afnm) = afnm {
+        let previous: u32 = sessions.iter()
+            .take_while(|other| other.number != number)
+            .map(|other| other.track_count)
+            .sum();
+        let mut payload = Vec::new();
+        for name in afnm.payload.split(|&byte| byte == 0)
+            .skip(previous as usize)
+            .take(session.track_count as usize) {
+            payload.extend(name);
+            payload.push(0);
+        }
+        afnm.payload = payload;
+        session_chunks.push(afnm);
+    }
+
+    metadata::read_raw_metadata(&session_chunks, file_size, chunk_offset)
+}
+
+
+/// Groups the chunks `chunks` of an NRG image by session.
+fn group_sessions(chunks: Vec<NrgRawChunk>)
+                  -> Result<Vec<NrgSession>, NrgError> {
+    let mut groups: Vec<Vec<NrgRawChunk>> = Vec::new();
+    let mut shared = Vec::new();
+    for chunk in chunks {
+        match chunk.id.as_str() {
+            "CUEX" | "DAOX" => {
+                let new_session = groups.last().is_none_or(|group| {
+                    group.iter().any(|other| {
+                        other.id == chunk.id
+                            || (chunk.id == "CUEX" && other.id == "DAOX")
+                    })
+                });
+                if new_session {
+                    groups.push(Vec::new());
+                }
+                groups.last_mut().unwrap().push(chunk);
+            },
+            "CDTX" if !groups.is_empty() =>
+                groups.last_mut().unwrap().push(chunk),
+            "ETN2" => return Err(NrgError::NrgFormat(
+                "Track-at-once sessions (ETN2 chunk) are not handled"
+                    .to_string())),
+            // The file names of the AFNM chunk cover the tracks of all the
+            // sessions
+            "SINF" | "AFNM" | "END!" => {},
+            _ => shared.push(chunk),
+        }
+    }
+
+    let mut sessions = Vec::new();
+    for (i, mut chunks) in groups.into_iter().enumerate() {
+        let number = i + 1;
+        let daox = chunks.iter().find(|chunk| chunk.id == "DAOX")
+            .ok_or_else(|| NrgError::NrgFormat(format!(
+                "No DAOX chunk in session {}", number)))?;
+        let tracks = daox_tracks(daox)?;
+        let data_range = match (tracks.first(), tracks.last()) {
+            (Some(first), Some(last)) => first.index0..last.end,
+            _ => return Err(NrgError::NrgFormat(format!(
+                "No tracks in session {}", number))),
+        };
+        let track_count = tracks.len() as u32;
+        chunks.extend(shared.iter().cloned());
+        sessions.push(NrgSession { number, chunks, data_range, track_count });
+    }
+    Ok(sessions)
+}
+
+
+/// Writes the session `session` of the NRG image `in_fd` to `out`, as a
+/// standalone NRG image.
+///
+/// The data of the session's tracks is copied to the start of the image,
+/// and the positions of the CUEX and DAOX chunks are moved accordingly: the
+/// first track keeps its number, but starts at the beginning of the disc
+/// like the tracks of a first session.
+///
+/// Returns the size of the image written, in bytes.
+pub fn write_session<R: Read + Seek, W: Write>(in_fd: &mut R,
+                                               session: &NrgSession,
+                                               out: &mut W)
+                                               -> Result<u64, NrgError> {
+    let start = session.data_range.start;
+    let data_size = session.data_range.end.checked_sub(start)
+        .ok_or_else(|| NrgError::NrgFormat(format!(
+            "Invalid data range {:?} of session {}", session.data_range,
+            session.number)))?;
+    in_fd.seek(SeekFrom::Start(start))?;
+    let copied = io::copy(&mut in_fd.take(data_size), out)?;
+    if copied != data_size {
+        return Err(NrgError::AudioLength { expected: data_size, copied });
+    }
+
+    let daox = session.chunks.iter().find(|chunk| chunk.id == "DAOX")
+        .ok_or_else(|| NrgError::NrgFormat("No DAOX chunk".to_string()))?;
+    let first = daox_tracks(daox)?.remove(0);
+    let mut size = copied;
+    for chunk in &session.chunks {
+        let payload = match chunk.id.as_str() {
+            "CUEX" => move_cuex(chunk, &first, start)?,
+            "DAOX" => move_daox(chunk, start)?,
+            _ => chunk.payload.clone(),
+        };
+        size += writer::write_chunk(out, &chunk.id, &payload)?;
+    }
+    size += writer::write_chunk(out, "SINF",
+                                &session.track_count.to_be_bytes())?;
+    size += writer::write_chunk(out, "END!", &[])?;
+    size += writer::write_footer(out, copied)?;
+    Ok(size)
+}
+
+
+/// Writes each session of the NRG image `img_path` as a standalone image,
+/// named after `out_base` followed by "-session", the session number and
+/// the ".nrg" extension.
+///
+/// Returns the list of files written.
+#[cfg(feature = "fs")]
+pub fn split_file(img_path: &Path, out_base: &Path)
+                  -> Result<Vec<ExtractedFile>, NrgError> {
+    let mut in_fd = BufReader::new(File::open(img_path)?);
+    let sessions = read_sessions(&mut in_fd)?;
+    if sessions.len() < 2 {
+        return Err(NrgError::NrgFormat(
+            "The image holds a single session".to_string()));
+    }
+
+    let mut files = Vec::new();
+    for session in &sessions {
+        let mut path = out_base.as_os_str().to_os_string();
+        path.push(format!("-session{}.nrg", session.number));
+        info!("Writing session {} to \"{}\"", session.number,
+              path.to_string_lossy());
+        let mut out_fd = BufWriter::new(File::create(&path)?);
+        write_session(&mut in_fd, session, &mut out_fd)?;
+        out_fd.flush()?;
+        files.push(ExtractedFile {
+            path: path.to_string_lossy().into_owned(),
+            track_number: None,
+            audio_bytes: session.data_range.end - session.data_range.start,
+            source_ranges: vec![session.data_range.clone()],
+            sectors: session_sectors(session)?,
+            crc32: None,
+            loudness: None,
+            verified: None,
+            bad_sectors: Vec::new(),
+        });
+    }
+    Ok(files)
+}
+
+
+/// Returns the number of sectors of the tracks of `session`.
+#[cfg(feature = "fs")]
+fn session_sectors(session: &NrgSession) -> Result<u64, NrgError> {
+    let mut sectors = 0;
+    for chunk in session.chunks.iter().filter(|chunk| chunk.id == "DAOX") {
+        for track in daox_tracks(chunk)? {
+            sectors += (track.end - track.index0)
+                / u64::from(track.sector_size);
+        }
+    }
+    Ok(sectors)
+}
+
+
+/// Positions of a track read from a DAOX chunk.
+struct DaoxTrack {
+    sector_size: u16,
+    index0: u64,
+    index1: u64,
+    end: u64,
+}
+
+
+/// Reads the tracks of the DAOX chunk `chunk`.
+fn daox_tracks(chunk: &NrgRawChunk) -> Result<Vec<DaoxTrack>, NrgError> {
+    let size = chunk.payload.len();
+    if size < DAOX_HEADER_SIZE
+        || !(size - DAOX_HEADER_SIZE).is_multiple_of(DAOX_TRACK_SIZE) {
+        return Err(NrgError::NrgChunkSize("DAOX".to_string(), size as u32));
+    }
+    let tracks = chunk.payload[DAOX_HEADER_SIZE..].chunks(DAOX_TRACK_SIZE)
+        .map(|block| DaoxTrack {
+            sector_size: u16::from_be_bytes([block[12], block[13]]),
+            index0: be_u64(&block[18..26]),
+            index1: be_u64(&block[26..34]),
+            end: be_u64(&block[34..]),
+        })
+        .collect::<Vec<_>>();
+    // The positions are subtracted from each other when the session is
+    // moved: they must be ordered
+    let sorted = tracks.windows(2).all(|pair| pair[0].end <= pair[1].index0)
+        && tracks.iter().all(|track| track.index0 <= track.index1
+                             && track.index1 <= track.end);
+    if !sorted || tracks.iter().any(|track| track.sector_size == 0) {
+        return Err(NrgError::NrgFormat(
+            "Invalid DAOX track positions".to_string()));
+    }
+    Ok(tracks)
+}
+
+
+/// Reads a big-endian 64-bit integer.
+fn be_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    u64::from_be_bytes(buf)
+}
+
+
+/// Returns the payload of the DAOX chunk `chunk`, with byte positions moved
+/// back by `start`.
+fn move_daox(chunk: &NrgRawChunk, start: u64) -> Result<Vec<u8>, NrgError> {
+    daox_tracks(chunk)?;
+    let mut payload = chunk.payload.clone();
+    for block in payload[DAOX_HEADER_SIZE..].chunks_mut(DAOX_TRACK_SIZE) {
+        for pos in [18, 26, 34] {
+            let moved = be_u64(&block[pos..pos + 8]).checked_sub(start)
+                .ok_or_else(|| NrgError::NrgFormat(format!(
+                    "DAOX position before the session's start {}", start)))?;
+            block[pos..pos + 8].copy_from_slice(&moved.to_be_bytes());
+        }
+    }
+    Ok(payload)
+}
+
+
+/// Returns the payload of the CUEX chunk `chunk`, with positions moved so
+/// that the session's first track, `first`, starts at the beginning of the
+/// disc (position -150), its data being located at the byte `start` of
+/// the image.
+fn move_cuex(chunk: &NrgRawChunk, first: &DaoxTrack, start: u64)
+             -> Result<Vec<u8>, NrgError> {
+    if !chunk.payload.len().is_multiple_of(CUEX_ENTRY_SIZE) {
+        return Err(NrgError::NrgChunkSize("CUEX".to_string(),
+                                          chunk.payload.len() as u32));
+    }
+    let pregap = FIRST_PREGAP_SECTORS as i32;
+    let index1 = chunk.payload.chunks(CUEX_ENTRY_SIZE)
+        .find(|entry| entry[1] != LEAD_IN_TRACK && entry[2] == 0x01)
+        .map(|entry| i32::from_be_bytes([entry[4], entry[5], entry[6],
+                                         entry[7]]))
+        .ok_or_else(|| NrgError::NrgFormat(
+            "No index #1 in the CUEX chunk".to_string()))?;
+    let invalid = || NrgError::NrgFormat(
+        "Invalid CUEX positions in the session".to_string());
+    let moved_index1 = first.index1.checked_sub(start)
+        .map(|offset| offset / u64::from(first.sector_size))
+        .and_then(|sectors| i32::try_from(sectors).ok())
+        .and_then(|sectors| sectors.checked_sub(pregap))
+        .ok_or_else(invalid)?;
+    let shift = index1.checked_sub(moved_index1).ok_or_else(invalid)?;
+
+    let mut payload = chunk.payload.clone();
+    for entry in payload.chunks_mut(CUEX_ENTRY_SIZE) {
+        let position = if entry[1] == LEAD_IN_TRACK {
+            -pregap
+        } else {
+            i32::from_be_bytes([entry[4], entry[5], entry[6], entry[7]])
+                .checked_sub(shift)
+                .ok_or_else(invalid)?
+        };
+        entry[4..].copy_from_slice(&position.to_be_bytes());
+    }
+    Ok(payload)
+}
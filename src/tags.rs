@@ -0,0 +1,445 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to write tags describing the tracks into the extracted audio
+//! files.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use ::disc_id::NrgDiscToc;
+use ::error::NrgError;
+use ::metadata::metadata::NrgMetadata;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Type of the FLAC metadata blocks holding Vorbis comments.
+const FLAC_VORBIS_COMMENT: u8 = 4;
+/// Maximum size of a FLAC metadata block.
+const FLAC_MAX_BLOCK_SIZE: usize = 0xFF_FFFF;
+
+
+/// Tags describing an extracted file, i.e. a track or the whole disc.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NrgTags {
+    pub track_number: Option<u8>,
+    pub track_total: Option<u8>,
+    pub title: Option<String>,
+    pub album: Option<String>,
+    pub artist: Option<String>,
+    pub songwriter: Option<String>,
+    pub composer: Option<String>,
+    pub arranger: Option<String>,
+    pub genre: Option<String>,
+    pub comment: Option<String>,
+    pub isrc: Option<String>,
+    pub upc: Option<String>,
+    pub cddb_disc_id: Option<String>,
+    pub musicbrainz_disc_id: Option<String>,
+}
+
+impl NrgTags {
+    /// Builds the tags of the track `track_number` of `metadata`, or of the
+    /// whole disc if `track_number` is None.
+    ///
+    /// The title is the track's title (see nrgrip::metadata::track). The
+    /// album, artist, songwriter, composer, arranger, comment (the CD-Text
+    /// message), genre and UPC/EAN are taken from the CD-Text information,
+    /// in the preferred language, if the image holds any; otherwise the
+    /// album and artist must be set separately. A track's CD-Text strings
+    /// take precedence over the disc's ones.
+    pub fn from_metadata(metadata: &NrgMetadata, track_number: Option<u8>)
+                         -> Result<NrgTags, NrgError> {
+        let mut tags = NrgTags {
+            track_total: Some(metadata.tracks().count() as u8),
+            ..NrgTags::default()
+        };
+        if let Some(toc) = NrgDiscToc::from_metadata(metadata) {
+            tags.cddb_disc_id = Some(format!("{:08X}", toc.cddb_disc_id()));
+            tags.musicbrainz_disc_id = Some(toc.musicbrainz_disc_id());
+        }
+        let cd_text = metadata.cd_text();
+        if let Some(cd_text) = cd_text {
+            tags.album = non_empty(&cd_text.title);
+            tags.artist = non_empty(&cd_text.performer);
+            tags.songwriter = non_empty(&cd_text.songwriter);
+            tags.composer = non_empty(&cd_text.composer);
+            tags.arranger = non_empty(&cd_text.arranger);
+            tags.comment = non_empty(&cd_text.message);
+            tags.genre = cd_text.genre_name();
+            tags.upc = non_empty(&cd_text.upc);
+        }
+        if let Some(number) = track_number {
+            let (position, track) = metadata.tracks().enumerate()
+                .find(|(_, track)| track.number == number)
+                .ok_or(NrgError::TrackNotFound(number))?;
+            if let Some(text) = cd_text
+                .and_then(|cd_text| cd_text.tracks.get(position)) {
+                let fields = [
+                    (&mut tags.artist, &text.performer),
+                    (&mut tags.songwriter, &text.songwriter),
+                    (&mut tags.composer, &text.composer),
+                    (&mut tags.arranger, &text.arranger),
+                    (&mut tags.comment, &text.message),
+                ];
+                for (tag, value) in fields {
+                    if !value.is_empty() {
+                        *tag = Some(value.clone());
+                    }
+                }
+            }
+            tags.track_number = Some(number);
+            tags.title = track.title;
+            tags.isrc = track.isrc;
+        }
+        Ok(tags)
+    }
+
+    /// Sets the album's title.
+    pub fn album(mut self, album: &str) -> NrgTags {
+        self.album = Some(album.to_string());
+        self
+    }
+
+    /// Sets the artist.
+    pub fn artist(mut self, artist: &str) -> NrgTags {
+        self.artist = Some(artist.to_string());
+        self
+    }
+
+    /// Returns the tags that are set, as Vorbis comment field names and
+    /// values.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let fields = [
+            ("TRACKNUMBER", self.track_number.map(|n| n.to_string())),
+            ("TRACKTOTAL", self.track_total.map(|n| n.to_string())),
+            ("TITLE", self.title.clone()),
+            ("ALBUM", self.album.clone()),
+            ("ARTIST", self.artist.clone()),
+            ("LYRICIST", self.songwriter.clone()),
+            ("COMPOSER", self.composer.clone()),
+            ("ARRANGER", self.arranger.clone()),
+            ("GENRE", self.genre.clone()),
+            ("COMMENT", self.comment.clone()),
+            ("ISRC", self.isrc.clone()),
+            ("BARCODE", self.upc.clone()),
+            ("DISCID", self.cddb_disc_id.clone()),
+            ("MUSICBRAINZ_DISCID", self.musicbrainz_disc_id.clone()),
+        ];
+        fields.iter().cloned()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .collect()
+    }
+}
+
+
+/// Returns `string`, or `None` if it is empty.
+fn non_empty(string: &str) -> Option<String> {
+    Some(string.to_string()).filter(|string| !string.is_empty())
+}
+
+
+/// Writes `tags` into the audio file `path`, replacing the tags of the same
+/// names that it already holds.
+///
+/// WAV files are tagged with a RIFF INFO list, which must be the last chunk
+/// of the file if it is already present. FLAC files are tagged with Vorbis
+/// comments; the file is rewritten.
+pub fn write_tags(path: &Path, tags: &NrgTags) -> Result<(), NrgError> {
+    let extension = path.extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("wav") => write_riff_info(path, tags),
+        Some("flac") => write_vorbis_comments(path, tags),
+        _ => Err(NrgError::FileName(format!(
+            "Can't tag \"{}\": only WAV and FLAC files are supported",
+            path.display()))),
+    }
+}
+
+
+/// Writes `tags` in the INFO list of the WAV file `path`.
+fn write_riff_info(path: &Path, tags: &NrgTags) -> Result<(), NrgError> {
+    let mut fd = OpenOptions::new().read(true).write(true).open(path)?;
+    let mut header = [0u8; 12];
+    fd.read_exact(&mut header)?;
+    if &header[..4] != b"RIFF" || &header[8..] != b"WAVE" {
+        return Err(NrgError::NrgFormat(format!(
+            "\"{}\" is not a WAV file", path.display())));
+    }
+    let file_size = fd.seek(SeekFrom::End(0))?;
+
+    // Look for an existing INFO list, which must be the last chunk
+    let mut pos = 12;
+    let mut info = None;
+    while pos + 8 <= file_size {
+        if info.is_some() {
+            return Err(NrgError::NrgFormat(format!(
+                "The INFO list of \"{}\" is not the last chunk",
+                path.display())));
+        }
+        let (id, size) = read_riff_chunk_header(&mut fd, pos)?;
+        if &id == b"LIST" && size >= 4
+            && pos + 8 + u64::from(size) <= file_size {
+            let mut payload = vec![0u8; size as usize];
+            fd.read_exact(&mut payload)?;
+            if &payload[..4] == b"INFO" {
+                info = Some((pos, payload));
+            }
+        }
+        pos += 8 + u64::from(size) + u64::from(size & 1);
+    }
+    // The new list replaces the existing one, or is appended (after the
+    // padding byte of the last chunk, if it is missing)
+    let (end, info) = match info {
+        Some((offset, payload)) => (offset, Some(payload)),
+        None => (pos, None),
+    };
+    if end > file_size + 1 {
+        return Err(NrgError::NrgFormat(format!(
+            "\"{}\" is truncated", path.display())));
+    }
+
+    // Keep the entries we don't set
+    let entries = riff_info_entries(tags);
+    let mut list = b"INFO".to_vec();
+    if let Some(payload) = info {
+        let mut pos = 4;
+        while pos + 8 <= payload.len() {
+            let id = &payload[pos..pos + 4];
+            let size = u32::from_le_bytes([payload[pos + 4], payload[pos + 5],
+                                           payload[pos + 6], payload[pos + 7]])
+                as usize;
+            let next = (pos + 8 + size + (size & 1)).min(payload.len());
+            if !entries.iter().any(|&(entry_id, _)| entry_id == id) {
+                list.extend_from_slice(&payload[pos..next]);
+                if (next - pos) % 2 == 1 {
+                    list.push(0);
+                }
+            }
+            pos = next;
+        }
+    }
+    for (id, value) in entries {
+        let size = value.len() + 1;
+        list.extend_from_slice(id);
+        list.extend_from_slice(&(size as u32).to_le_bytes());
+        list.extend_from_slice(value.as_bytes());
+        list.push(0);
+        if size % 2 == 1 {
+            list.push(0);
+        }
+    }
+
+    // The RIFF size excludes the RIFF chunk's ID and size, but includes the
+    // header of the new list
+    let riff_size = end + list.len() as u64;
+    if riff_size > u64::from(u32::MAX) {
+        return Err(NrgError::NrgFormat(format!(
+            "\"{}\" is too large for its tags", path.display())));
+    }
+    fd.set_len(end)?;
+    fd.seek(SeekFrom::Start(end))?;
+    fd.write_all(b"LIST")?;
+    fd.write_all(&(list.len() as u32).to_le_bytes())?;
+    fd.write_all(&list)?;
+    fd.seek(SeekFrom::Start(4))?;
+    fd.write_all(&(riff_size as u32).to_le_bytes())?;
+    Ok(())
+}
+
+
+/// Reads the ID and size of the RIFF chunk at `pos` in `fd`.
+fn read_riff_chunk_header(fd: &mut File, pos: u64)
+                          -> Result<([u8; 4], u32), NrgError> {
+    let mut header = [0u8; 8];
+    fd.seek(SeekFrom::Start(pos))?;
+    fd.read_exact(&mut header)?;
+    let id = [header[0], header[1], header[2], header[3]];
+    let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    Ok((id, size))
+}
+
+
+/// Returns the RIFF INFO entries corresponding to `tags`.
+///
+/// The disc IDs, which have no standard INFO entry, are written in the
+/// comment entry, after the comment tag if any.
+fn riff_info_entries(tags: &NrgTags) -> Vec<(&'static [u8; 4], String)> {
+    let mut entries = Vec::new();
+    let mut comments = Vec::new();
+    for (name, value) in tags.fields() {
+        match name {
+            "TRACKNUMBER" => entries.push((b"ITRK", value)),
+            "TITLE" => entries.push((b"INAM", value)),
+            "ALBUM" => entries.push((b"IPRD", value)),
+            "ARTIST" => entries.push((b"IART", value)),
+            "ISRC" => entries.push((b"ISRC", value)),
+            "GENRE" => entries.push((b"IGNR", value)),
+            "COMPOSER" => entries.push((b"IMUS", value)),
+            "COMMENT" => comments.insert(0, value),
+            "DISCID" | "MUSICBRAINZ_DISCID" =>
+                comments.push(format!("{}={}", name, value)),
+            _ => (),
+        }
+    }
+    if !comments.is_empty() {
+        entries.push((b"ICMT", comments.join("; ")));
+    }
+    entries
+}
+
+
+/// Writes `tags` in the Vorbis comments of the FLAC file `path`.
+///
+/// The file is rewritten through a temporary file in the same directory.
+fn write_vorbis_comments(path: &Path, tags: &NrgTags)
+                         -> Result<(), NrgError> {
+    let mut in_fd = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 4];
+    in_fd.read_exact(&mut magic)?;
+    if &magic != b"fLaC" {
+        return Err(NrgError::NrgFormat(format!(
+            "\"{}\" is not a FLAC file", path.display())));
+    }
+
+    // Read the metadata blocks
+    let mut blocks = Vec::new();
+    loop {
+        let mut header = [0u8; 4];
+        in_fd.read_exact(&mut header)?;
+        let size = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+        let mut payload = vec![0u8; size as usize];
+        in_fd.read_exact(&mut payload)?;
+        blocks.push((header[0] & 0x7F, payload));
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+
+    // Merge the existing comments with the tags
+    let fields = tags.fields();
+    let (vendor, mut comments) = match blocks.iter()
+        .find(|&&(block_type, _)| block_type == FLAC_VORBIS_COMMENT) {
+        Some((_, payload)) => parse_vorbis_comments(payload)
+            .ok_or_else(|| NrgError::NrgFormat(format!(
+                "Invalid Vorbis comments in \"{}\"", path.display())))?,
+        None => (format!("nrgrip v{}", VERSION), Vec::new()),
+    };
+    comments.retain(|comment| {
+        let name = comment.split('=').next().unwrap_or("");
+        !fields.iter().any(|&(field, _)| field.eq_ignore_ascii_case(name))
+    });
+    comments.extend(fields.iter()
+                    .map(|&(name, ref value)| format!("{}={}", name, value)));
+    let payload = build_vorbis_comments(&vendor, &comments);
+    if payload.len() > FLAC_MAX_BLOCK_SIZE {
+        return Err(NrgError::NrgFormat(format!(
+            "Tags too large for \"{}\"", path.display())));
+    }
+
+    // Replace the comments, which follow the STREAMINFO block if absent
+    let position = blocks.iter()
+        .position(|&(block_type, _)| block_type == FLAC_VORBIS_COMMENT)
+        .unwrap_or(1);
+    blocks.retain(|&(block_type, _)| block_type != FLAC_VORBIS_COMMENT);
+    blocks.insert(position.min(blocks.len()),
+                  (FLAC_VORBIS_COMMENT, payload));
+
+    let mut tmp_path = path.as_os_str().to_os_string();
+    tmp_path.push(".tmp");
+    let mut out_fd = BufWriter::new(File::create(&tmp_path)?);
+    out_fd.write_all(b"fLaC")?;
+    let last = blocks.len() - 1;
+    for (i, &(block_type, ref payload)) in blocks.iter().enumerate() {
+        let flag = if i == last { 0x80 } else { 0 };
+        let size = (payload.len() as u32).to_be_bytes();
+        out_fd.write_all(&[block_type | flag, size[1], size[2], size[3]])?;
+        out_fd.write_all(payload)?;
+    }
+    io::copy(&mut in_fd, &mut out_fd)?;
+    out_fd.flush()?;
+    drop(out_fd);
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+
+/// Parses the payload of a Vorbis comment block into the vendor string and
+/// the list of comments.
+///
+/// Returns None if the payload is invalid.
+fn parse_vorbis_comments(payload: &[u8]) -> Option<(String, Vec<String>)> {
+    let mut pos = 0;
+    let vendor = read_vorbis_string(payload, &mut pos)?;
+    let count = read_u32_le(payload, &mut pos)?;
+    let mut comments = Vec::new();
+    for _ in 0..count {
+        comments.push(read_vorbis_string(payload, &mut pos)?);
+    }
+    Some((vendor, comments))
+}
+
+
+/// Reads a length-prefixed string at `pos` in `payload`, and moves `pos`
+/// after it.
+fn read_vorbis_string(payload: &[u8], pos: &mut usize) -> Option<String> {
+    let size = read_u32_le(payload, pos)? as usize;
+    let bytes = payload.get(*pos..pos.checked_add(size)?)?;
+    *pos += size;
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+
+/// Reads a little-endian 32-bit integer at `pos` in `payload`, and moves
+/// `pos` after it.
+fn read_u32_le(payload: &[u8], pos: &mut usize) -> Option<u32> {
+    let bytes = payload.get(*pos..*pos + 4)?;
+    *pos += 4;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+
+/// Builds the payload of a Vorbis comment block holding `tags`, for a new
+/// stream.
+#[cfg(any(feature = "opus", feature = "vorbis"))]
+pub(crate) fn vorbis_comments(tags: &NrgTags) -> Vec<u8> {
+    let comments: Vec<String> = tags.fields().iter()
+        .map(|&(name, ref value)| format!("{}={}", name, value))
+        .collect();
+    build_vorbis_comments(&format!("nrgrip v{}", VERSION), &comments)
+}
+
+
+/// Builds the payload of a Vorbis comment block.
+fn build_vorbis_comments(vendor: &str, comments: &[String]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    payload.extend_from_slice(vendor.as_bytes());
+    payload.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        payload.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        payload.extend_from_slice(comment.as_bytes());
+    }
+    payload
+}
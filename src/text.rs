@@ -0,0 +1,522 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Human-readable output: the information about an image laid out as
+//! aligned tables, optionally colored with ANSI escape sequences.
+//!
+//! Unexpected values are highlighted in yellow, and missing data in red, so
+//! that they stand out in long listings.
+
+use ::isrc::{self, Isrc};
+use ::metadata::cuex::NrgCuex;
+use ::metadata::daox::NrgDaox;
+use ::metadata::metadata::NrgMetadata;
+use ::metadata::raw::{NrgChunkStatus, NrgRawChunk};
+use ::msf::Msf;
+use ::red_book;
+
+
+/// Indentation of the lines below a section heading.
+const INDENT: &str = "  ";
+
+/// Separator between the columns of a table.
+const SEPARATOR: &str = "  ";
+
+
+/// Style in which a piece of text is displayed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// Ordinary text.
+    Plain,
+    /// Section headings and column titles (bold).
+    Heading,
+    /// Unexpected values (yellow).
+    Warning,
+    /// Missing data (red).
+    Error,
+}
+
+impl Style {
+    /// Returns `text` in this style, wrapped in ANSI escape sequences if
+    /// `color` is true.
+    pub fn paint(self, text: &str, color: bool) -> String {
+        let code = match self {
+            Style::Plain => return text.to_string(),
+            Style::Heading => "1",
+            Style::Warning => "33",
+            Style::Error => "1;31",
+        };
+        if color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+
+/// Alignment of a table column.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+
+/// A table whose columns are padded to the same width.
+///
+/// The column titles are only printed if at least one of them is not empty;
+/// the width of a column is measured on the text of its cells, regardless
+/// of their style.
+#[derive(Clone, Debug, Default)]
+pub struct TextTable {
+    columns: Vec<(String, Align)>,
+    rows: Vec<Vec<(String, Style)>>,
+}
+
+impl TextTable {
+    /// Creates a table without columns.
+    pub fn new() -> TextTable {
+        TextTable::default()
+    }
+
+    /// Appends a column to the table.
+    pub fn column(mut self, title: &str, align: Align) -> TextTable {
+        self.columns.push((title.to_string(), align));
+        self
+    }
+
+    /// Appends a row to the table. Missing cells are left empty, and extra
+    /// cells are ignored.
+    pub fn row(&mut self, cells: Vec<(String, Style)>) {
+        self.rows.push(cells);
+    }
+
+    /// Renders the table, each line being indented and terminated by a
+    /// newline.
+    pub fn render(&self, color: bool) -> String {
+        let mut widths: Vec<usize> = self.columns.iter()
+            .map(|(title, _)| title.chars().count())
+            .collect();
+        for row in &self.rows {
+            for (width, (text, _)) in widths.iter_mut().zip(row) {
+                *width = (*width).max(text.chars().count());
+            }
+        }
+
+        let mut lines = Vec::new();
+        if self.columns.iter().any(|(title, _)| !title.is_empty()) {
+            lines.push(self.columns.iter()
+                       .map(|(title, _)| (title.clone(), Style::Heading))
+                       .collect());
+        }
+        lines.extend(self.rows.iter().cloned());
+
+        let mut text = String::new();
+        for line in lines {
+            let mut rendered = String::from(INDENT);
+            for (i, &(_, align)) in self.columns.iter().enumerate() {
+                let (cell, style) = line.get(i).cloned()
+                    .unwrap_or((String::new(), Style::Plain));
+                let padding = " ".repeat(widths[i] - cell.chars().count());
+                if i > 0 {
+                    rendered.push_str(SEPARATOR);
+                }
+                if align == Align::Right {
+                    rendered.push_str(&padding);
+                }
+                rendered.push_str(&style.paint(&cell, color));
+                if align == Align::Left {
+                    rendered.push_str(&padding);
+                }
+            }
+            text.push_str(rendered.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+
+/// Returns a plain table cell holding `value`.
+fn plain<T: ToString>(value: T) -> (String, Style) {
+    (value.to_string(), Style::Plain)
+}
+
+
+/// Returns a table cell holding `value`, highlighted as a warning if
+/// `value` is not the `expected` one.
+fn checked<T: PartialEq>(text: String, value: T, expected: T)
+                         -> (String, Style) {
+    let style = if value == expected { Style::Plain } else { Style::Warning };
+    (text, style)
+}
+
+
+/// Returns a two-column table listing the given fields, each label being
+/// followed by a colon.
+fn fields(fields: Vec<(&str, (String, Style))>) -> TextTable {
+    let mut table = TextTable::new()
+        .column("", Align::Left)
+        .column("", Align::Left);
+    for (label, value) in fields {
+        table.row(vec![plain(format!("{}:", label)), value]);
+    }
+    table
+}
+
+
+/// Returns the heading of a chunk section.
+fn heading(id: &str, description: &str, size: u32, color: bool) -> String {
+    let heading = format!("{} chunk ({}), {} Bytes", id, description, size);
+    format!("\n{}\n", Style::Heading.paint(&heading, color))
+}
+
+
+/// Returns the line reporting that a chunk has no tracks.
+fn no_tracks(id: &str, color: bool) -> String {
+    let text = format!("No {} tracks!", id);
+    format!("{}{}\n", INDENT, Style::Error.paint(&text, color))
+}
+
+
+/// Renders `metadata` as aligned tables: the image properties, one section
+/// per chunk, the tracks with their lengths, the elements of their ISRCs,
+/// the unhandled chunks, and the warnings raised by the metadata followed by
+/// the Red Book limits exceeded by the image.
+///
+/// Escape sequences are only used if `color` is true. Each line is
+/// terminated by a newline.
+pub fn metadata_to_text(metadata: &NrgMetadata, color: bool) -> String {
+    let mut text = format!("{}\n", Style::Heading.paint("Image", color));
+    text.push_str(&fields(vec![
+        ("Image size", plain(format!("{} Bytes", metadata.file_size()))),
+        ("NRG format version", plain(metadata.nrg_version())),
+        ("First NRG chunk offset", plain(metadata.chunk_offset())),
+        ("Disc length", plain(format!("{} sectors ({})",
+                                      metadata.total_sectors(),
+                                      metadata.total_length()))),
+    ]).render(color));
+
+    if let Some(chunk) = metadata.cuex_chunk() {
+        text.push_str(&cuex_to_text(chunk, color));
+    }
+    if let Some(chunk) = metadata.daox_chunk() {
+        text.push_str(&daox_to_text(chunk, color));
+    }
+    if let Some(chunk) = metadata.sinf_chunk() {
+        text.push_str(&heading("SINF", "Session Information", chunk.size(),
+                               color));
+        text.push_str(&fields(vec![
+            ("Tracks in the session", plain(chunk.nb_tracks())),
+        ]).render(color));
+    }
+    if let Some(chunk) = metadata.mtyp_chunk() {
+        text.push_str(&heading("MTYP", "Media Type", chunk.size(), color));
+        text.push_str(&fields(vec![
+            ("Unknown field", plain(format!("0x{:04X}", chunk.unknown()))),
+        ]).render(color));
+    }
+    if let Some(chunk) = metadata.afnm_chunk() {
+        text.push_str(&heading("AFNM", "Audio File Names", chunk.size(),
+                               color));
+        if chunk.tracks().is_empty() {
+            text.push_str(&no_tracks("AFNM", color));
+        } else {
+            let mut table = TextTable::new()
+                .column("Track", Align::Right)
+                .column("File name", Align::Left);
+            for (i, track) in chunk.tracks().iter().enumerate() {
+                table.row(vec![plain(i + 1), plain(track.name())]);
+            }
+            text.push_str(&table.render(color));
+        }
+    }
+
+    if metadata.tracks().next().is_some() {
+        text.push_str(&tracks_to_text(metadata, color));
+    }
+    text.push_str(&isrcs_to_text(metadata, color));
+
+    if !metadata.skipped_chunks().is_empty() {
+        let skipped = metadata.skipped_chunks().iter()
+            .map(|chunk| format!("{} ({} bytes at {})", chunk.id, chunk.size,
+                                 chunk.offset))
+            .collect::<Vec<_>>()
+            .join(", ");
+        text.push_str(&format!("\nUnhandled chunks: {}\n",
+                               Style::Warning.paint(&skipped, color)));
+    }
+
+    text.push_str(&warnings_to_text(metadata, color));
+    text
+}
+
+
+/// Renders `metadata` as a summary: the disc's length, one line per track
+/// with its number, its type, the position of its index #1 from the start of
+/// the disc, its length, its ISRC and its title, and the warnings as in
+/// `metadata_to_text()`.
+///
+/// Escape sequences are only used if `color` is true. Each line is
+/// terminated by a newline.
+pub fn summary_to_text(metadata: &NrgMetadata, color: bool) -> String {
+    let mut text = fields(vec![
+        ("Disc length", plain(format!("{} sectors ({})",
+                                      metadata.total_sectors(),
+                                      metadata.total_length()))),
+    ]).render(color);
+
+    let mut table = TextTable::new()
+        .column("Track", Align::Right)
+        .column("Type", Align::Left)
+        .column("Start", Align::Right)
+        .column("Length", Align::Right)
+        .column("ISRC", Align::Left)
+        .column("Title", Align::Left);
+    let mut empty = true;
+    for track in metadata.tracks() {
+        let start = Msf::from_sectors(track.position_sectors.max(0) as u32);
+        let isrc = match track.isrc {
+            Some(ref isrc) if isrc::problem(isrc).is_some() =>
+                (isrc.clone(), Style::Warning),
+            Some(ref isrc) => plain(isrc),
+            None => plain("-"),
+        };
+        table.row(vec![
+            plain(format!("{:02}", track.number)),
+            plain(if track.is_data() { "Data" } else { "Audio" }),
+            plain(start),
+            plain(track.length()),
+            isrc,
+            plain(track.title.as_ref().map_or("-", String::as_str)),
+        ]);
+        empty = false;
+    }
+    if empty {
+        text.push_str(&no_tracks("DAOX", color));
+    } else {
+        text.push('\n');
+        text.push_str(&table.render(color));
+    }
+
+    text.push_str(&warnings_to_text(metadata, color));
+    text
+}
+
+
+/// Renders a table listing `chunks`, with their ID, offset, size and status
+/// (parsed, skipped or unknown).
+///
+/// Escape sequences are only used if `color` is true. Each line is
+/// terminated by a newline.
+pub fn chunk_listing_to_text(chunks: &[NrgRawChunk], color: bool) -> String {
+    let mut table = TextTable::new()
+        .column("Chunk", Align::Left)
+        .column("Offset", Align::Right)
+        .column("Size", Align::Right)
+        .column("Status", Align::Left);
+    for chunk in chunks {
+        let status = chunk.status();
+        let style = match status {
+            NrgChunkStatus::Parsed => Style::Plain,
+            NrgChunkStatus::Skipped => Style::Warning,
+            NrgChunkStatus::Unknown => Style::Error,
+        };
+        table.row(vec![
+            plain(&chunk.id),
+            plain(chunk.offset),
+            plain(chunk.payload.len()),
+            (status.to_string(), style),
+        ]);
+    }
+    table.render(color)
+}
+
+
+/// Renders the warnings raised by `metadata` followed by the Red Book limits
+/// exceeded by the image, or nothing if there are none.
+fn warnings_to_text(metadata: &NrgMetadata, color: bool) -> String {
+    let mut warnings = metadata.warnings();
+    warnings.extend(red_book::check(metadata).iter()
+                    .map(ToString::to_string));
+    if warnings.is_empty() {
+        return String::new();
+    }
+    let mut text = format!("\n{}\n", Style::Heading.paint("Warnings", color));
+    for warning in warnings {
+        text.push_str(&format!("{}{}\n", INDENT,
+                               Style::Warning.paint(&warning, color)));
+    }
+    text
+}
+
+
+/// Renders the tracks of `metadata` with their lengths, in sectors, MSF and
+/// bytes.
+fn tracks_to_text(metadata: &NrgMetadata, color: bool) -> String {
+    let mut table = TextTable::new()
+        .column("Track", Align::Right)
+        .column("Pre-gap", Align::Right)
+        .column("Sectors", Align::Right)
+        .column("Length", Align::Right)
+        .column("Bytes", Align::Right);
+    for track in metadata.tracks() {
+        table.row(vec![
+            plain(format!("{:02}", track.number)),
+            plain(track.pregap_length()),
+            plain(track.sector_count()),
+            plain(track.length()),
+            plain(track.byte_count()),
+        ]);
+    }
+    format!("\n{}\n{}", Style::Heading.paint("Tracks", color),
+            table.render(color))
+}
+
+
+/// Renders the elements of the valid ISRCs of the tracks of `metadata`, or
+/// nothing if there are none.
+fn isrcs_to_text(metadata: &NrgMetadata, color: bool) -> String {
+    let mut table = TextTable::new()
+        .column("Track", Align::Right)
+        .column("ISRC", Align::Left)
+        .column("Country", Align::Left)
+        .column("Registrant", Align::Left)
+        .column("Year", Align::Right)
+        .column("Designation", Align::Right);
+    let mut empty = true;
+    for track in metadata.tracks() {
+        let isrc = match track.isrc.as_ref().map(|isrc| Isrc::parse(isrc)) {
+            Some(Ok(isrc)) => isrc,
+            _ => continue,
+        };
+        table.row(vec![
+            plain(format!("{:02}", track.number)),
+            plain(&isrc),
+            plain(isrc.country_code()),
+            plain(isrc.registrant_code()),
+            plain(format!("{:02}", isrc.year())),
+            plain(format!("{:05}", isrc.designation())),
+        ]);
+        empty = false;
+    }
+    if empty {
+        return String::new();
+    }
+    format!("\n{}\n{}", Style::Heading.paint("ISRCs", color),
+            table.render(color))
+}
+
+
+fn cuex_to_text(chunk: &NrgCuex, color: bool) -> String {
+    let mut text = heading("CUEX", "Cue Sheet", chunk.size(), color);
+    if chunk.tracks().is_empty() {
+        text.push_str(&no_tracks("CUEX", color));
+        return text;
+    }
+
+    let mut table = TextTable::new()
+        .column("Track", Align::Left)
+        .column("Index", Align::Right)
+        .column("Mode", Align::Left)
+        .column("Padding", Align::Right)
+        .column("Position", Align::Right)
+        .column("MSF", Align::Right);
+    for track in chunk.tracks() {
+        let number = match track.track_number() {
+            0 => "lead-in".to_string(),
+            0xAA => "lead-out".to_string(),
+            number => number.to_string(),
+        };
+        let position = track.position_sectors();
+        let sign = if position < 0 { "-" } else { "" };
+        table.row(vec![
+            plain(number),
+            plain(track.index_number()),
+            plain(format!("0x{:02X}", track.mode())),
+            checked(track.padding().to_string(), track.padding(), 0),
+            plain(position),
+            plain(format!("{}{}", sign,
+                          Msf::from_sectors(position.unsigned_abs()))),
+        ]);
+    }
+    text.push_str(&table.render(color));
+    text
+}
+
+
+fn daox_to_text(chunk: &NrgDaox, color: bool) -> String {
+    let mut text = heading("DAOX", "DAO Information", chunk.size(), color);
+    let upc = if chunk.upc().is_empty() { "-" } else { chunk.upc() };
+    let size2 = if chunk.is_size2_little_endian() {
+        format!("{} (little endian)", chunk.size2())
+    } else {
+        chunk.size2().to_string()
+    };
+    text.push_str(&fields(vec![
+        ("Chunk size 2", plain(size2)),
+        ("UPC", plain(upc)),
+        ("Padding", checked(chunk.padding().to_string(), chunk.padding(), 0)),
+        ("TOC type", plain(format!("0x{:04X}", chunk.toc_type()))),
+        ("First track", plain(chunk.first_track())),
+        ("Last track", plain(chunk.last_track())),
+    ]).render(color));
+
+    if chunk.tracks().is_empty() {
+        text.push_str(&no_tracks("DAOX", color));
+        return text;
+    }
+
+    let mut table = TextTable::new()
+        .column("Track", Align::Right)
+        .column("ISRC", Align::Left)
+        .column("Sector size", Align::Right)
+        .column("Mode", Align::Left)
+        .column("Unknown", Align::Left)
+        .column("Index0", Align::Right)
+        .column("Index1", Align::Right)
+        .column("End", Align::Right);
+    for (i, track) in chunk.tracks().iter().enumerate() {
+        let isrc = if track.isrc().is_empty() {
+            plain("-")
+        } else if isrc::problem(track.isrc()).is_some() {
+            (track.isrc().to_string(), Style::Warning)
+        } else {
+            plain(track.isrc())
+        };
+        table.row(vec![
+            plain(format!("{:02}", i + 1)),
+            isrc,
+            plain(track.sector_size()),
+            plain(format!("0x{:04X}", track.data_mode())),
+            checked(format!("0x{:04X}", track.unknown()), track.unknown(),
+                    0x0001),
+            plain(track.index0()),
+            plain(track.index1()),
+            plain(track.track_end()),
+        ]);
+    }
+    text.push('\n');
+    text.push_str(&table.render(color));
+    text
+}
@@ -0,0 +1,58 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Macros instrumenting the library with `tracing` spans and events.
+//!
+//! With the `tracing` feature, the metadata parsing and the extraction are
+//! covered by spans (one per chunk, one per output file) holding the chunk
+//! ID or the track number and the offset in the image, so that a subscriber
+//! can time them and relate a failure to its position. Without the feature,
+//! the macros expand to nothing and their arguments aren't evaluated.
+
+/// Enters a `tracing` span named `$name` until the end of the enclosing
+/// block, with the given fields.
+macro_rules! enter_span {
+    ($name:expr $(, $field:ident = $value:expr)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::info_span!($name $(, $field = $value)*)
+            .entered();
+    };
+}
+
+/// Records a `tracing` event at the level `$level` (e.g. `debug`), in the
+/// current span.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::$level!($($arg)+);
+    };
+}
+
+/// Records an error event in the current span if `$result` is an error.
+macro_rules! trace_error {
+    ($result:expr, $message:expr) => {
+        #[cfg(feature = "tracing")]
+        if let Err(ref err) = $result {
+            ::tracing::error!(error = %err, $message);
+        }
+    };
+}
@@ -0,0 +1,343 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Tab-separated values output, used to produce line-oriented
+//! machine-readable output.
+//!
+//! Each record is a line whose first field is the kind of the record (e.g.
+//! "track" or "file"), followed by fields specific to that kind. Absent
+//! values are written as empty fields. New fields are only ever appended to
+//! a record, following nrgrip::json::SCHEMA_VERSION.
+
+use std::fmt;
+
+use ::accuraterip::NrgAccurateRipTrack;
+use ::analysis::NrgTrackAnalysis;
+use ::compare::NrgComparison;
+use ::gaps::NrgGap;
+use ::hidden::NrgHiddenData;
+use ::json::{hex_string, SCHEMA_VERSION};
+use ::loudness::NrgLoudness;
+use ::metadata::metadata::NrgMetadata;
+use ::metadata::raw::NrgRawChunk;
+use ::metadata::track::Track;
+use ::red_book::{self, NrgRedBookWarning};
+use ::verify::NrgVerification;
+#[cfg(feature = "fs")]
+use ::extraction::ExtractedFile;
+
+
+/// A TSV record.
+///
+/// `Display` writes the fields separated by tabs, without a final newline;
+/// backslashes, tabs and line breaks in the fields are escaped as `\\`,
+/// `\t`, `\n` and `\r`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TsvRecord {
+    fields: Vec<String>,
+}
+
+impl TsvRecord {
+    /// Starts a record of the given kind.
+    pub fn new(kind: &str) -> TsvRecord {
+        TsvRecord { fields: vec![kind.to_string()] }
+    }
+
+    /// Appends a field to the record.
+    pub fn field<T: ToString>(mut self, value: T) -> TsvRecord {
+        self.fields.push(value.to_string());
+        self
+    }
+
+    /// Appends a field to the record, left empty if `value` is None.
+    pub fn optional_field<T: ToString>(self, value: Option<T>) -> TsvRecord {
+        let value = value.map_or_else(String::new, |value| value.to_string());
+        self.field(value)
+    }
+
+    /// Returns the fields of the record, the first one being its kind.
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+}
+
+impl fmt::Display for TsvRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, "\t")?;
+            }
+            for c in field.chars() {
+                match c {
+                    '\\' => write!(f, "\\\\")?,
+                    '\t' => write!(f, "\\t")?,
+                    '\n' => write!(f, "\\n")?,
+                    '\r' => write!(f, "\\r")?,
+                    c => write!(f, "{}", c)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Returns the "image" record starting the records of an image, whose
+/// fields are its path and the version of the schema of the output.
+pub fn image_to_tsv(img_path: &str) -> TsvRecord {
+    TsvRecord::new("image")
+        .field(img_path)
+        .field(SCHEMA_VERSION)
+}
+
+
+/// Converts `metadata` into TSV records: one "track" record per track,
+/// followed by one "warning" record per warning raised by the metadata, and
+/// one "red_book" record per Red Book limit exceeded by the image (see
+/// `red_book_to_tsv()`).
+///
+/// The fields of a "track" record are the number, mode, title, ISRC, pre-gap
+/// length and length (in sectors), and the byte positions of index #0,
+/// index #1 and the end of the track in the image file.
+pub fn metadata_to_tsv(metadata: &NrgMetadata) -> Vec<TsvRecord> {
+    let mut records: Vec<TsvRecord> = metadata.tracks()
+        .map(|track| track_to_tsv(&track))
+        .collect();
+    records.extend(metadata.warnings().into_iter()
+                   .map(|warning| TsvRecord::new("warning").field(warning)));
+    records.extend(red_book::check(metadata).iter().map(red_book_to_tsv));
+    records
+}
+
+
+/// Converts a Red Book warning into a "red_book" record, whose fields are
+/// the kind of the warning, the track number and the length in sectors
+/// (empty if not relevant), and the description of the warning.
+pub fn red_book_to_tsv(warning: &NrgRedBookWarning) -> TsvRecord {
+    TsvRecord::new("red_book")
+        .field(warning.kind())
+        .optional_field(warning.track_number())
+        .optional_field(warning.length().map(|length| length.to_sectors()))
+        .field(warning)
+}
+
+
+fn track_to_tsv(track: &Track) -> TsvRecord {
+    TsvRecord::new("track")
+        .field(track.number)
+        .field(track.mode)
+        .optional_field(track.title.as_ref())
+        .optional_field(track.isrc.as_ref())
+        .field(track.pregap_sectors())
+        .field(track.sector_count())
+        .field(track.index0)
+        .field(track.index1)
+        .field(track.track_end)
+}
+
+
+/// Converts a detected gap into a "gap" record, whose fields are the track
+/// number and the length of the gap in sectors.
+pub fn gap_to_tsv(gap: &NrgGap) -> TsvRecord {
+    TsvRecord::new("gap")
+        .field(gap.track_number)
+        .field(gap.sectors)
+}
+
+
+/// Converts the data hidden after the last track into a "hidden_data"
+/// record, whose fields are its first and end bytes in the image, its length
+/// in sectors and its number of non-zero bytes.
+pub fn hidden_data_to_tsv(hidden: &NrgHiddenData) -> TsvRecord {
+    TsvRecord::new("hidden_data")
+        .field(hidden.range.start)
+        .field(hidden.range.end)
+        .field(hidden.sector_count())
+        .field(hidden.nonzero_bytes)
+}
+
+
+/// Converts the analysis of a track into an "analysis" record, whose fields
+/// are the track number, its length, the lengths of the leading silence,
+/// the trailing silence and the audio in between (in samples), "silent" or
+/// "audio", the peak and RMS levels in dBFS (empty if silent) and the number
+/// of clipped samples.
+pub fn track_analysis_to_tsv(analysis: &NrgTrackAnalysis) -> TsvRecord {
+    TsvRecord::new("analysis")
+        .field(analysis.track_number)
+        .field(analysis.samples)
+        .field(analysis.leading_silence)
+        .field(analysis.trailing_silence)
+        .field(analysis.audio_samples())
+        .field(if analysis.is_silent() { "silent" } else { "audio" })
+        .optional_field(level(analysis.peak_dbfs()))
+        .optional_field(level(analysis.rms_dbfs()))
+        .field(analysis.clipped_samples)
+}
+
+
+/// Formats a level in dBFS, None if it is minus infinity.
+fn level(dbfs: f64) -> Option<String> {
+    if dbfs.is_finite() {
+        Some(format!("{:.2}", dbfs))
+    } else {
+        None
+    }
+}
+
+
+/// Converts the AccurateRip checksums of a track into an "accuraterip"
+/// record, whose fields are the track number, the v1 and v2 checksums (in
+/// hexadecimal) and the confidence (empty if the database wasn't queried).
+pub fn accuraterip_to_tsv(track: &NrgAccurateRipTrack) -> TsvRecord {
+    TsvRecord::new("accuraterip")
+        .field(track.track_number)
+        .field(format!("{:08X}", track.crc_v1))
+        .field(format!("{:08X}", track.crc_v2))
+        .optional_field(track.confidence)
+}
+
+
+/// Converts the description of an extracted file into a "file" record, whose
+/// fields are the path, the track number, the length of the audio data in
+/// bytes, the CRC-32 of the audio data (in hexadecimal), the loudness
+/// fields described in `loudness_to_tsv()`, and whether the file was
+/// verified successfully ("true" or "false", empty if not verified).
+#[cfg(feature = "fs")]
+pub fn extracted_file_to_tsv(file: &ExtractedFile) -> TsvRecord {
+    let record = TsvRecord::new("file")
+        .field(&file.path)
+        .optional_field(file.track_number)
+        .field(file.audio_bytes)
+        .optional_field(file.crc32.map(|crc32| format!("{:08X}", crc32)));
+    loudness_fields(record, file.loudness.as_ref())
+        .optional_field(file.verified)
+}
+
+
+/// Converts the loudness of an album into an "album_loudness" record, whose
+/// fields are the integrated loudness in LUFS, the ReplayGain gain in dB and
+/// the sample peak.
+pub fn loudness_to_tsv(loudness: &NrgLoudness) -> TsvRecord {
+    loudness_fields(TsvRecord::new("album_loudness"), Some(loudness))
+}
+
+
+fn loudness_fields(record: TsvRecord, loudness: Option<&NrgLoudness>)
+                   -> TsvRecord {
+    record
+        .optional_field(loudness.and_then(|l| l.integrated)
+                        .map(|lufs| format!("{:.2}", lufs)))
+        .optional_field(loudness.and_then(NrgLoudness::replaygain_gain)
+                        .map(|gain| format!("{:.2}", gain)))
+        .optional_field(loudness.map(|l| format!("{:.6}", l.peak)))
+}
+
+
+/// Converts a raw chunk into a "chunk" record, whose fields are the chunk ID,
+/// its offset, the size of its payload and the payload in hexadecimal.
+pub fn raw_chunk_to_tsv(chunk: &NrgRawChunk) -> TsvRecord {
+    TsvRecord::new("chunk")
+        .field(&chunk.id)
+        .field(chunk.offset)
+        .field(chunk.payload.len())
+        .field(hex_string(&chunk.payload))
+}
+
+
+/// Converts a raw chunk into a "chunk_listing" record, whose fields are the
+/// chunk ID, its offset, the size of its payload and its status ("parsed",
+/// "skipped" or "unknown").
+pub fn chunk_listing_to_tsv(chunk: &NrgRawChunk) -> TsvRecord {
+    TsvRecord::new("chunk_listing")
+        .field(&chunk.id)
+        .field(chunk.offset)
+        .field(chunk.payload.len())
+        .field(chunk.status())
+}
+
+
+/// Converts the results of nrgrip::verify::verify() into TSV records.
+///
+/// Each check yields a "check" record with its name and "OK", or one
+/// "check" record with its name, "FAILED" and the description of the problem
+/// per problem found. They are followed by one "suspicious" record per
+/// suspicious region, with the track number, the first sector (counted from
+/// index #1), the number of sectors and the kind of the region, by one
+/// "red_book" record per Red Book warning, then by an "audio_sha1" record
+/// if the SHA-1 was computed, and a "result" record holding "PASS" or
+/// "FAIL".
+pub fn verification_to_tsv(verification: &NrgVerification)
+                           -> Vec<TsvRecord> {
+    let mut records = Vec::new();
+    for check in &verification.checks {
+        if check.passed() {
+            records.push(TsvRecord::new("check").field(check.name).field("OK"));
+        }
+        for problem in &check.problems {
+            records.push(TsvRecord::new("check")
+                         .field(check.name)
+                         .field("FAILED")
+                         .field(problem));
+        }
+    }
+    for region in &verification.suspicious_regions {
+        records.push(TsvRecord::new("suspicious")
+                     .field(region.track_number)
+                     .field(region.first_sector)
+                     .field(region.sectors)
+                     .field(region.kind));
+    }
+    records.extend(verification.red_book_warnings.iter()
+                   .map(red_book_to_tsv));
+    if let Some(ref sha1) = verification.audio_sha1 {
+        records.push(TsvRecord::new("audio_sha1").field(sha1));
+    }
+    let result = if verification.passed() { "PASS" } else { "FAIL" };
+    records.push(TsvRecord::new("result").field(result));
+    records
+}
+
+
+/// Converts the results of nrgrip::compare::compare() into TSV records.
+///
+/// There is one "toc_difference" record per difference between the tables of
+/// contents, with its description, then a "sectors" record with the number
+/// of sectors of both images, an "offset" record with the offset in samples,
+/// one "difference" record per run of differing sectors, with its first
+/// sector and its length, and a "result" record holding "IDENTICAL", "SAME
+/// AUDIO" or "DIFFERENT".
+pub fn comparison_to_tsv(comparison: &NrgComparison) -> Vec<TsvRecord> {
+    let mut records: Vec<TsvRecord> = comparison.toc_differences.iter()
+        .map(|difference| TsvRecord::new("toc_difference").field(difference))
+        .collect();
+    records.push(TsvRecord::new("sectors")
+                 .field(comparison.sectors.0)
+                 .field(comparison.sectors.1));
+    records.push(TsvRecord::new("offset").field(comparison.offset));
+    records.extend(comparison.differences.iter()
+                   .map(|difference| TsvRecord::new("difference")
+                        .field(difference.first_sector)
+                        .field(difference.sectors)));
+    records.push(TsvRecord::new("result").field(comparison.result()));
+    records
+}
@@ -0,0 +1,74 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Normalization of the strings read from the images (CD-Text, AFNM file
+//! names) or looked up online, so that the file names and cue sheets built
+//! from them are consistent whatever the normalization of the source.
+//!
+//! The normalization is the one of the `unicode-normalization` crate; the
+//! transliteration to ASCII is restricted to the Latin letters and the usual
+//! punctuation, the other characters being replaced with question marks.
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+
+/// Non-decomposable letters and punctuation, with their ASCII equivalent.
+const TRANSLITERATIONS: [(char, &str); 28] = [
+    ('Æ', "AE"), ('æ', "ae"), ('Œ', "OE"), ('œ', "oe"), ('ß', "ss"),
+    ('Ø', "O"), ('ø', "o"), ('Đ', "D"), ('đ', "d"), ('Ð', "D"),
+    ('ð', "d"), ('Ł', "L"), ('ł', "l"), ('Þ', "Th"), ('þ', "th"),
+    ('ı', "i"),
+    ('\u{A0}', " "), ('«', "\""), ('»', "\""), ('\u{2018}', "'"),
+    ('\u{2019}', "'"), ('\u{201C}', "\""), ('\u{201D}', "\""),
+    ('\u{2013}', "-"), ('\u{2014}', "-"), ('\u{2026}', "..."),
+    ('×', "x"), ('\u{B7}', "."),
+];
+
+
+/// Normalizes `text` to the Unicode normalization form C (canonical
+/// decomposition followed by canonical composition).
+pub fn nfc(text: &str) -> String {
+    text.nfc().collect()
+}
+
+
+/// Transliterates `text` to ASCII: the diacritics of the Latin letters are
+/// removed, the ligatures and the typographic punctuation are replaced with
+/// their usual ASCII equivalents, and the other non-ASCII characters with
+/// question marks.
+pub fn to_ascii(text: &str) -> String {
+    let mut ascii = String::with_capacity(text.len());
+    for c in text.nfd() {
+        if c.is_ascii() {
+            ascii.push(c);
+        } else if is_combining_mark(c) {
+            continue;
+        } else if let Some(&(_, equivalent)) = TRANSLITERATIONS.iter()
+            .find(|&&(letter, _)| letter == c) {
+            ascii.push_str(equivalent);
+        } else {
+            ascii.push('?');
+        }
+    }
+    ascii
+}
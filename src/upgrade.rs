@@ -0,0 +1,199 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to convert NRG v1 images to NRG v2.
+//!
+//! NRG v1 images end with a "NERO" footer and store byte positions as 32-bit
+//! integers. Their CUES, DAOI and ETNF chunks are converted to the CUEX, DAOX
+//! and ETN2 chunks of NRG v2, which hold 64-bit positions; the other chunks
+//! are copied as is, and the "NER5" footer is written. The data preceding the
+//! chunks is copied unchanged, so the positions keep their values.
+
+#[cfg(feature = "fs")]
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "fs")]
+use std::io::{BufReader, BufWriter};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use ::error::NrgError;
+use ::metadata::raw::{self, NrgRawChunk};
+use ::metadata::read_nrg_version;
+use ::writer;
+
+
+/// Size of a CUES or CUEX entry.
+const CUE_ENTRY_SIZE: usize = 8;
+/// Size of the DAOI and DAOX headers, before the track blocks.
+const DAO_HEADER_SIZE: usize = 22;
+/// Size of a DAOI track block.
+const DAOI_TRACK_SIZE: usize = 30;
+/// Size of the fields of a DAOI track block preceding the byte positions.
+const DAOI_TRACK_FIELDS_SIZE: usize = 18;
+/// Size of an ETNF entry.
+const ETNF_ENTRY_SIZE: usize = 20;
+/// Size of the 32-bit fields of an ETNF entry following the byte positions.
+const ETNF_FIELDS_SIZE: usize = 12;
+/// Number of sectors preceding the position 0, i.e. the first track's
+/// 2-second pre-gap.
+const MSF_OFFSET: i32 = 150;
+
+
+/// Writes the NRG v2 version of the NRG v1 image `in_fd` to `out`.
+///
+/// Returns the size of the image written, in bytes.
+pub fn write_v2_copy<R: Read + Seek, W: Write>(in_fd: &mut R, out: &mut W)
+                                               -> Result<u64, NrgError> {
+    let file_size = in_fd.seek(SeekFrom::End(0))?;
+    if read_nrg_version(in_fd, file_size)? != 1 {
+        return Err(NrgError::NrgFormat(
+            "The image is not in NRG v1 format".to_string()));
+    }
+    let chunks = raw::read_raw_chunks(in_fd)?;
+    let chunk_offset = chunks[0].offset;
+
+    in_fd.seek(SeekFrom::Start(0))?;
+    let copied = io::copy(&mut in_fd.take(chunk_offset), out)?;
+    if copied != chunk_offset {
+        return Err(NrgError::AudioLength { expected: chunk_offset, copied });
+    }
+    let mut size = copied;
+    for chunk in &chunks {
+        let (id, payload) = match chunk.id.as_str() {
+            "CUES" => ("CUEX", cues_to_cuex(chunk)?),
+            "DAOI" => ("DAOX", daoi_to_daox(chunk)?),
+            "ETNF" => ("ETN2", etnf_to_etn2(chunk)?),
+            "SINF" | "MTYP" | "CDTX" | "AFNM" | "DINF" | "TOCT" | "RELO"
+                | "VOLM" | "END!" => (chunk.id.as_str(), chunk.payload.clone()),
+            _ => return Err(NrgError::NrgChunkId(chunk.id.clone())),
+        };
+        debug!("Writing {} chunk from {} chunk", id, chunk.id);
+        size += writer::write_chunk(out, id, &payload)?;
+    }
+    size += writer::write_footer(out, chunk_offset)?;
+    Ok(size)
+}
+
+
+/// Writes the NRG v2 version of the NRG v1 image `img_path` to `out_path`,
+/// see `write_v2_copy()`.
+#[cfg(feature = "fs")]
+pub fn upgrade_file(img_path: &Path, out_path: &Path)
+                    -> Result<u64, NrgError> {
+    let same_file = match (fs::canonicalize(img_path),
+                           fs::canonicalize(out_path)) {
+        (Ok(img_path), Ok(out_path)) => img_path == out_path,
+        _ => false,
+    };
+    if same_file {
+        return Err(NrgError::FileName(
+            "Input and output file are identical".to_string()));
+    }
+
+    let mut in_fd = BufReader::new(File::open(img_path)?);
+    let mut out_fd = BufWriter::new(File::create(out_path)?);
+    let size = write_v2_copy(&mut in_fd, &mut out_fd)?;
+    out_fd.flush()?;
+    Ok(size)
+}
+
+
+/// Returns an error about the size of `chunk` if `valid` is false.
+fn check_size(chunk: &NrgRawChunk, valid: bool) -> Result<(), NrgError> {
+    if valid {
+        Ok(())
+    } else {
+        Err(NrgError::NrgChunkSize(chunk.id.clone(),
+                                   chunk.payload.len() as u32))
+    }
+}
+
+
+/// Converts the payload of a CUES chunk to a CUEX payload.
+///
+/// The CUES entries have the same layout as the CUEX ones, except for their
+/// position: instead of a signed sector number, it is an MSF time stored in
+/// the last 3 bytes, including the first track's 2-second pre-gap.
+fn cues_to_cuex(chunk: &NrgRawChunk) -> Result<Vec<u8>, NrgError> {
+    check_size(chunk, chunk.payload.len().is_multiple_of(CUE_ENTRY_SIZE))?;
+    let mut payload = Vec::with_capacity(chunk.payload.len());
+    for entry in chunk.payload.chunks(CUE_ENTRY_SIZE) {
+        let (minutes, seconds, frames) = (entry[5], entry[6], entry[7]);
+        if seconds >= 60 || frames >= 75 {
+            return Err(NrgError::NrgFormat(format!(
+                "Invalid CUES position: {:02}:{:02}:{:02}",
+                minutes, seconds, frames)));
+        }
+        let sectors = (i32::from(minutes) * 60 + i32::from(seconds)) * 75
+            + i32::from(frames);
+        payload.extend(&entry[..4]);
+        payload.extend(&(sectors - MSF_OFFSET).to_be_bytes());
+    }
+    Ok(payload)
+}
+
+
+/// Converts the payload of a DAOI chunk to a DAOX payload, widening the
+/// byte positions of the tracks to 64 bits.
+fn daoi_to_daox(chunk: &NrgRawChunk) -> Result<Vec<u8>, NrgError> {
+    let size = chunk.payload.len();
+    check_size(chunk, size >= DAO_HEADER_SIZE
+               && (size - DAO_HEADER_SIZE).is_multiple_of(DAOI_TRACK_SIZE))?;
+    let nb_tracks = (size - DAO_HEADER_SIZE) / DAOI_TRACK_SIZE;
+    let daox_size = (writer::DAOX_HEADER_SIZE
+                     + writer::DAOX_TRACK_SIZE * nb_tracks) as u32;
+
+    let mut payload = Vec::with_capacity(daox_size as usize);
+    payload.extend(&daox_size.to_be_bytes());
+    payload.extend(&chunk.payload[4..DAO_HEADER_SIZE]);
+    for track in chunk.payload[DAO_HEADER_SIZE..].chunks(DAOI_TRACK_SIZE) {
+        payload.extend(&track[..DAOI_TRACK_FIELDS_SIZE]);
+        widen_positions(&track[DAOI_TRACK_FIELDS_SIZE..], &mut payload);
+    }
+    Ok(payload)
+}
+
+
+/// Converts the payload of an ETNF chunk to an ETN2 payload, widening the
+/// byte position and size of the tracks to 64 bits.
+fn etnf_to_etn2(chunk: &NrgRawChunk) -> Result<Vec<u8>, NrgError> {
+    check_size(chunk, chunk.payload.len().is_multiple_of(ETNF_ENTRY_SIZE))?;
+    let mut payload = Vec::new();
+    for entry in chunk.payload.chunks(ETNF_ENTRY_SIZE) {
+        let positions = ETNF_ENTRY_SIZE - ETNF_FIELDS_SIZE;
+        widen_positions(&entry[..positions], &mut payload);
+        payload.extend(&entry[positions..]);
+    }
+    Ok(payload)
+}
+
+
+/// Appends the 32-bit big-endian integers of `positions` to `payload`, as
+/// 64-bit integers.
+fn widen_positions(positions: &[u8], payload: &mut Vec<u8>) {
+    for position in positions.chunks(4) {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(position);
+        payload.extend(&u64::from(u32::from_be_bytes(bytes)).to_be_bytes());
+    }
+}
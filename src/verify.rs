@@ -0,0 +1,520 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to check the consistency of an NRG image, e.g. before archiving it.
+
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+use sha1::{Digest, Sha1};
+
+use ::error::NrgError;
+use ::metadata;
+use ::metadata::metadata::NrgMetadata;
+use ::metadata::track::Track;
+use ::msf::Msf;
+use ::raw_audio::{RAW_SEC_SIZE, RAW96_SEC_SIZE};
+use ::red_book::{self, NrgRedBookWarning};
+use ::sectors::{self, NrgBadSector};
+
+
+/// Maximum number of sectors read at once while scanning a track.
+const SCAN_SECTORS: u64 = 75;
+
+/// Minimum length of a run of zero-filled sectors for it to be reported.
+const MIN_ZERO_SECTORS: u32 = 8;
+
+
+/// Result of one of the checks run by `verify()`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgCheck {
+    pub name: &'static str,
+    pub problems: Vec<String>,
+}
+
+impl NrgCheck {
+    fn new(name: &'static str) -> NrgCheck {
+        NrgCheck { name, problems: Vec::new() }
+    }
+
+    /// Returns true if no problems were found.
+    pub fn passed(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for NrgCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.name,
+               if self.passed() { "OK" } else { "FAILED" })?;
+        for problem in &self.problems {
+            write!(f, "\n\t- {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Kind of a suspicious region of the audio.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NrgSuspicion {
+    /// Sectors holding only zero bytes.
+    ZeroFilled,
+    /// Sectors identical to the sector preceding them.
+    Repeated,
+}
+
+impl fmt::Display for NrgSuspicion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NrgSuspicion::ZeroFilled => write!(f, "zero-filled"),
+            NrgSuspicion::Repeated => write!(f, "repeated"),
+        }
+    }
+}
+
+
+/// Run of sectors of an audio track that may have been padded by the ripper
+/// in place of unreadable sectors.
+///
+/// `first_sector` is counted from the track's index #1.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgSuspiciousRegion {
+    pub track_number: u8,
+    pub first_sector: u32,
+    pub sectors: u32,
+    pub kind: NrgSuspicion,
+}
+
+impl fmt::Display for NrgSuspiciousRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Track {:02} at {}, {} sector{}: {}",
+               self.track_number, Msf::from_sectors(self.first_sector),
+               self.sectors, if self.sectors > 1 { "s" } else { "" },
+               self.kind)
+    }
+}
+
+
+/// Results of `verify()`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NrgVerification {
+    pub checks: Vec<NrgCheck>,
+    /// Suspicious regions of the audio tracks. They are reported for the
+    /// user to listen to, but don't make the verification fail.
+    pub suspicious_regions: Vec<NrgSuspiciousRegion>,
+    /// Red Book limits exceeded by the image. Like the suspicious regions,
+    /// they don't make the verification fail.
+    pub red_book_warnings: Vec<NrgRedBookWarning>,
+    /// SHA-1 of the audio data (from the first track's index #1 to the end of
+    /// the last track), in lowercase hexadecimal, if requested.
+    pub audio_sha1: Option<String>,
+}
+
+impl NrgVerification {
+    /// Returns true if all the checks passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(NrgCheck::passed)
+    }
+}
+
+impl fmt::Display for NrgVerification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{}", check)?;
+        }
+        if !self.suspicious_regions.is_empty() {
+            writeln!(f, "Suspicious regions:")?;
+            for region in &self.suspicious_regions {
+                writeln!(f, "\t- {}", region)?;
+            }
+        }
+        if !self.red_book_warnings.is_empty() {
+            writeln!(f, "Red Book warnings:")?;
+            for warning in &self.red_book_warnings {
+                writeln!(f, "\t- {}", warning)?;
+            }
+        }
+        if let Some(ref sha1) = self.audio_sha1 {
+            writeln!(f, "Audio SHA-1: {}", sha1)?;
+        }
+        write!(f, "Result: {}", if self.passed() { "PASS" } else { "FAIL" })
+    }
+}
+
+
+/// Checks the consistency of the NRG image `fd`.
+///
+/// The following checks are run:
+///
+/// - "metadata": the metadata can be parsed, and raises no warnings;
+/// - "chunks": the CUEX and DAOX chunks are present;
+/// - "cuex/daox": the track positions of the CUEX chunk match the byte
+///   positions of the DAOX chunk, and the number of tracks is consistent
+///   across the CUEX, DAOX and SINF chunks;
+/// - "byte ranges": every track's byte range is ordered, made of whole
+///   sectors, and located before the metadata chunks;
+/// - "data sectors": the raw sectors of the data tracks have a valid sync
+///   pattern, header and EDC (see nrgrip::sectors).
+///
+/// The later checks are skipped if the metadata can't be parsed, and the
+/// data sectors aren't checked unless the byte ranges are valid. If
+/// `hash_audio` is true and the byte ranges are valid, the SHA-1 of the audio
+/// data is computed.
+///
+/// When the byte ranges are valid, the audio tracks are also scanned for
+/// signs of read errors padded by the original ripper: runs of at least 8
+/// zero-filled sectors within a track (silence at the start or the end of a
+/// track is ordinary), and sectors repeating the contents of the previous
+/// one. They are listed in `suspicious_regions`. The Red Book limits
+/// exceeded by the image (see nrgrip::red_book) are listed in
+/// `red_book_warnings`.
+///
+/// An error is returned only if the image can't be read while checking the
+/// data sectors, scanning the audio or computing the SHA-1; parsing errors
+/// are reported as a failed check.
+pub fn verify<R: Read + Seek>(fd: &mut R, hash_audio: bool)
+                              -> Result<NrgVerification, NrgError> {
+    let mut report = NrgVerification {
+        checks: Vec::new(),
+        suspicious_regions: Vec::new(),
+        red_book_warnings: Vec::new(),
+        audio_sha1: None,
+    };
+
+    let mut check = NrgCheck::new("metadata");
+    let metadata = match metadata::read_nrg_metadata(fd) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            check.problems.push(err.to_string());
+            report.checks.push(check);
+            return Ok(report);
+        },
+    };
+    check.problems = metadata.warnings();
+    report.checks.push(check);
+    report.red_book_warnings = red_book::check(&metadata);
+
+    let tracks: Vec<Track> = metadata.tracks().collect();
+    report.checks.push(check_chunks(&metadata));
+    report.checks.push(check_positions(&metadata, &tracks));
+
+    let byte_ranges = check_byte_ranges(&metadata, &tracks);
+    let valid_ranges = byte_ranges.passed();
+    report.checks.push(byte_ranges);
+
+    if valid_ranges {
+        report.checks.push(check_data_sectors(fd, &metadata)?);
+        for track in tracks.iter().filter(|track| !track.is_data()) {
+            find_suspicious_regions(fd, track,
+                                    &mut report.suspicious_regions)?;
+        }
+    }
+
+    if hash_audio && valid_ranges {
+        report.audio_sha1 = Some(hash_range(fd, metadata.first_audio_byte(),
+                                            metadata.last_audio_byte())?);
+    }
+
+    Ok(report)
+}
+
+
+/// Returns the anomalies of `metadata` rejected in strict mode (see
+/// nrgrip::metadata::MetadataOptions::strict()): the warnings of
+/// `NrgMetadata::warnings()` (non-zero padding, unexpected "unknown" field
+/// values...), the chunks left unparsed, and the disagreements between the
+/// CUEX and DAOX chunks.
+pub fn anomalies(metadata: &NrgMetadata) -> Vec<String> {
+    let mut anomalies = metadata.warnings();
+    for chunk in metadata.skipped_chunks() {
+        anomalies.push(format!("Unparsed {}", chunk));
+    }
+    let tracks: Vec<Track> = metadata.tracks().collect();
+    anomalies.extend(check_positions(metadata, &tracks).problems);
+    anomalies
+}
+
+
+/// Fails with `NrgError::Anomalies` if `metadata` has any of the anomalies
+/// listed by `anomalies()`.
+pub fn check_strict(metadata: &NrgMetadata) -> Result<(), NrgError> {
+    let anomalies = anomalies(metadata);
+    if anomalies.is_empty() {
+        Ok(())
+    } else {
+        Err(NrgError::Anomalies(anomalies))
+    }
+}
+
+
+fn check_chunks(metadata: &NrgMetadata) -> NrgCheck {
+    let mut check = NrgCheck::new("chunks");
+    if metadata.cuex_chunk().is_none() {
+        check.problems.push("No CUEX chunk".to_string());
+    }
+    if metadata.daox_chunk().is_none() {
+        check.problems.push("No DAOX chunk".to_string());
+    }
+    check
+}
+
+
+/// Cross-checks the CUEX and DAOX chunks.
+///
+/// The byte position of a CUEX position is computed relatively to the first
+/// track's index #1.
+fn check_positions(metadata: &NrgMetadata, tracks: &[Track]) -> NrgCheck {
+    let mut check = NrgCheck::new("cuex/daox");
+    let (cuex, first) = match (metadata.cuex_chunk(), tracks.first()) {
+        (Some(cuex), Some(first)) => (cuex, first),
+        _ => return check,
+    };
+
+    if let Some(sinf) = metadata.sinf_chunk() {
+        if sinf.nb_tracks() as usize != tracks.len() {
+            check.problems.push(format!(
+                "SINF announces {} tracks, DAOX holds {}",
+                sinf.nb_tracks(), tracks.len()));
+        }
+    }
+
+    let sector_size = i64::from(first.sector_size);
+    let to_byte = |position: i32| {
+        first.index1 as i64
+            + (i64::from(position) - i64::from(first.position_sectors))
+            * sector_size
+    };
+
+    let mut cuex_tracks = 0;
+    for cuex_track in cuex.tracks() {
+        let number = cuex_track.track_number();
+        if number == 0 {
+            continue;
+        }
+        let expected = to_byte(cuex_track.position_sectors());
+
+        // Lead-out
+        if number == 0xAA {
+            let last = &tracks[tracks.len() - 1];
+            if expected != last.track_end as i64 {
+                check.problems.push(format!(
+                    "Lead-out at byte {} according to CUEX, but the last \
+                     track ends at byte {} according to DAOX",
+                    expected, last.track_end));
+            }
+            continue;
+        }
+
+        let track = match tracks.iter().find(|track| track.number == number) {
+            Some(track) => track,
+            None => {
+                check.problems.push(format!(
+                    "CUEX track {} is absent from DAOX", number));
+                continue;
+            },
+        };
+        let (index_name, actual) = match cuex_track.index_number() {
+            0 => ("index0", track.index0),
+            1 => {
+                cuex_tracks += 1;
+                ("index1", track.index1)
+            },
+            _ => continue,
+        };
+        if expected != actual as i64 {
+            check.problems.push(format!(
+                "Track {} {} at byte {} according to CUEX, but at byte {} \
+                 according to DAOX",
+                number, index_name, expected, actual));
+        }
+    }
+
+    if cuex_tracks != tracks.len() {
+        check.problems.push(format!(
+            "CUEX holds {} tracks, DAOX holds {}",
+            cuex_tracks, tracks.len()));
+    }
+
+    check
+}
+
+
+fn check_byte_ranges(metadata: &NrgMetadata, tracks: &[Track]) -> NrgCheck {
+    let mut check = NrgCheck::new("byte ranges");
+
+    if metadata.chunk_offset() > metadata.file_size() {
+        check.problems.push(format!(
+            "First chunk at byte {}, beyond the end of the file ({} bytes)",
+            metadata.chunk_offset(), metadata.file_size()));
+    }
+
+    let mut previous_end = 0;
+    for track in tracks {
+        let sector_size = u64::from(track.sector_size);
+        if !(previous_end <= track.index0
+             && track.index0 <= track.index1
+             && track.index1 <= track.track_end) {
+            check.problems.push(format!(
+                "Track {}: byte positions out of order ({}, {}, {})",
+                track.number, track.index0, track.index1, track.track_end));
+        } else if sector_size == 0
+            || !(track.track_end - track.index0).is_multiple_of(sector_size)
+            || !(track.index1 - track.index0).is_multiple_of(sector_size) {
+            check.problems.push(format!(
+                "Track {}: byte range not made of whole {}-byte sectors",
+                track.number, sector_size));
+        }
+        if track.track_end > metadata.chunk_offset() {
+            check.problems.push(format!(
+                "Track {} ends at byte {}, after the first chunk (byte {})",
+                track.number, track.track_end, metadata.chunk_offset()));
+        }
+        previous_end = track.track_end;
+    }
+
+    check
+}
+
+
+/// Lists the damaged sectors of the data tracks, merging the runs of
+/// consecutive sectors with the same problem.
+fn check_data_sectors<R: Read + Seek>(fd: &mut R, metadata: &NrgMetadata)
+                                      -> Result<NrgCheck, NrgError> {
+    let mut runs: Vec<(NrgBadSector, i32)> = Vec::new();
+    for bad_sector in sectors::check_data_sectors(fd, metadata)? {
+        if let Some(&mut (ref first, ref mut last)) = runs.last_mut() {
+            if first.track_number == bad_sector.track_number
+                && first.problem == bad_sector.problem
+                && *last + 1 == bad_sector.lba {
+                *last = bad_sector.lba;
+                continue;
+            }
+        }
+        runs.push((bad_sector, bad_sector.lba));
+    }
+
+    let mut check = NrgCheck::new("data sectors");
+    check.problems = runs.into_iter().map(|(first, last)| {
+        if first.lba == last {
+            first.to_string()
+        } else {
+            format!("Track {:02}, sectors {} to {}: {}",
+                    first.track_number, first.lba, last, first.problem)
+        }
+    }).collect();
+    Ok(check)
+}
+
+
+/// Scans the audio sectors of `track`, from its index #1 to its end, and
+/// appends the suspicious regions found to `regions`.
+///
+/// The sub-channel data is ignored. Nothing is reported for tracks whose
+/// sectors don't hold audio samples.
+fn find_suspicious_regions<R: Read + Seek>(
+    fd: &mut R, track: &Track, regions: &mut Vec<NrgSuspiciousRegion>)
+    -> Result<(), NrgError> {
+    if track.sector_size != RAW_SEC_SIZE
+        && track.sector_size != RAW96_SEC_SIZE {
+        return Ok(());
+    }
+    let sector_size = u64::from(track.sector_size);
+    let mut remaining = (track.track_end - track.index1) / sector_size;
+    let mut buf = Vec::new();
+    let mut previous = Vec::new();
+    let mut run: Option<NrgSuspiciousRegion> = None;
+    let mut sector_number = 0;
+
+    // Leading silence isn't suspicious, trailing silence is dropped at the
+    // end of the track
+    let mut keep = |run: NrgSuspiciousRegion| {
+        if run.kind == NrgSuspicion::Repeated
+            || (run.first_sector > 0 && run.sectors >= MIN_ZERO_SECTORS) {
+            debug!("{}", run);
+            regions.push(run);
+        }
+    };
+
+    fd.seek(SeekFrom::Start(track.index1))?;
+    while remaining > 0 {
+        let nb_sectors = remaining.min(SCAN_SECTORS);
+        buf.resize((nb_sectors * sector_size) as usize, 0);
+        fd.read_exact(&mut buf)?;
+        remaining -= nb_sectors;
+
+        for sector in buf.chunks(sector_size as usize) {
+            let audio = &sector[..RAW_SEC_SIZE as usize];
+            let kind = if audio.iter().all(|&b| b == 0) {
+                Some(NrgSuspicion::ZeroFilled)
+            } else if previous == audio {
+                Some(NrgSuspicion::Repeated)
+            } else {
+                None
+            };
+
+            match run {
+                Some(ref mut run) if Some(run.kind) == kind => run.sectors += 1,
+                _ => {
+                    if let Some(run) = run.take() {
+                        keep(run);
+                    }
+                    run = kind.map(|kind| NrgSuspiciousRegion {
+                        track_number: track.number,
+                        first_sector: sector_number,
+                        sectors: 1,
+                        kind,
+                    });
+                },
+            }
+
+            previous.clear();
+            previous.extend_from_slice(audio);
+            sector_number += 1;
+        }
+    }
+
+    if let Some(run) = run {
+        if run.kind == NrgSuspicion::Repeated {
+            keep(run);
+        }
+    }
+    Ok(())
+}
+
+
+/// Returns the SHA-1 of the bytes `start` (included) to `end` (excluded) of
+/// `fd`.
+fn hash_range<R: Read + Seek>(fd: &mut R, start: u64, end: u64)
+                              -> Result<String, NrgError> {
+    fd.seek(SeekFrom::Start(start))?;
+    let mut hasher = Sha1::new();
+    let mut buf = vec![0u8; 1 << 16];
+    let mut remaining = end.saturating_sub(start);
+    while remaining > 0 {
+        let nbytes = remaining.min(buf.len() as u64) as usize;
+        fd.read_exact(&mut buf[..nbytes])?;
+        hasher.update(&buf[..nbytes]);
+        remaining -= nbytes as u64;
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
@@ -0,0 +1,406 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to encode CD audio into Ogg Vorbis streams, through libvorbisenc.
+//!
+//! `VorbisWriter` encodes the 16-bit little-endian PCM samples written
+//! through it in libvorbis' variable bitrate mode, at the quality given on
+//! the scale of oggenc's `-q` option. The tags describing the audio are
+//! stored in the comment header.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::mem;
+use std::os::raw::{c_float, c_int, c_long, c_uchar, c_void};
+use std::ptr;
+use std::slice;
+
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+
+use ::error::NrgError;
+use ::raw_audio::SAMPLE_RATE;
+use ::tags::{self, NrgTags};
+
+/// Default quality of the streams (the default of oggenc).
+pub const DEFAULT_QUALITY: f32 = 3.0;
+/// Range of the qualities, from the smallest files to the best quality.
+pub const MIN_QUALITY: f32 = -1.0;
+pub const MAX_QUALITY: f32 = 10.0;
+
+/// Number of samples per channel analyzed at once.
+const CHUNK_SAMPLES: usize = 4096;
+/// Serial number of the Ogg logical stream.
+const SERIAL: u32 = 0x4E52_4756;
+
+
+/// `oggpack_buffer` of libogg.
+#[repr(C)]
+struct OggPackBuffer {
+    endbyte: c_long,
+    endbit: c_int,
+    buffer: *mut c_uchar,
+    ptr: *mut c_uchar,
+    storage: c_long,
+}
+
+/// `ogg_packet` of libogg.
+#[repr(C)]
+struct OggPacket {
+    packet: *mut c_uchar,
+    bytes: c_long,
+    b_o_s: c_long,
+    e_o_s: c_long,
+    granulepos: i64,
+    packetno: i64,
+}
+
+/// `vorbis_info` of libvorbis.
+#[repr(C)]
+struct VorbisInfo {
+    version: c_int,
+    channels: c_int,
+    rate: c_long,
+    bitrate_upper: c_long,
+    bitrate_nominal: c_long,
+    bitrate_lower: c_long,
+    bitrate_window: c_long,
+    codec_setup: *mut c_void,
+}
+
+/// `vorbis_comment` of libvorbis.
+#[repr(C)]
+struct VorbisComment {
+    user_comments: *mut *mut c_uchar,
+    comment_lengths: *mut c_int,
+    comments: c_int,
+    vendor: *mut c_uchar,
+}
+
+/// `vorbis_dsp_state` of libvorbis.
+#[repr(C)]
+struct VorbisDspState {
+    analysisp: c_int,
+    vi: *mut VorbisInfo,
+    pcm: *mut *mut c_float,
+    pcmret: *mut *mut c_float,
+    pcm_storage: c_int,
+    pcm_current: c_int,
+    pcm_returned: c_int,
+    preextrapolate: c_int,
+    eofflag: c_int,
+    lw: c_long,
+    w: c_long,
+    nw: c_long,
+    center_w: c_long,
+    granulepos: i64,
+    sequence: i64,
+    glue_bits: i64,
+    time_bits: i64,
+    floor_bits: i64,
+    res_bits: i64,
+    backend_state: *mut c_void,
+}
+
+/// `vorbis_block` of libvorbis.
+#[repr(C)]
+struct VorbisBlock {
+    pcm: *mut *mut c_float,
+    opb: OggPackBuffer,
+    lw: c_long,
+    w: c_long,
+    nw: c_long,
+    pcmend: c_int,
+    mode: c_int,
+    eofflag: c_int,
+    granulepos: i64,
+    sequence: i64,
+    vd: *mut VorbisDspState,
+    localstore: *mut c_void,
+    localtop: c_long,
+    localalloc: c_long,
+    totaluse: c_long,
+    reap: *mut c_void,
+    glue_bits: c_long,
+    time_bits: c_long,
+    floor_bits: c_long,
+    res_bits: c_long,
+    internal: *mut c_void,
+}
+
+#[link(name = "vorbisenc")]
+extern "C" {
+    fn vorbis_encode_init_vbr(vi: *mut VorbisInfo, channels: c_long,
+                              rate: c_long, base_quality: c_float) -> c_int;
+}
+
+#[link(name = "vorbis")]
+extern "C" {
+    fn vorbis_info_init(vi: *mut VorbisInfo);
+    fn vorbis_info_clear(vi: *mut VorbisInfo);
+    fn vorbis_comment_init(vc: *mut VorbisComment);
+    fn vorbis_comment_clear(vc: *mut VorbisComment);
+    fn vorbis_analysis_init(v: *mut VorbisDspState, vi: *mut VorbisInfo)
+                            -> c_int;
+    fn vorbis_dsp_clear(v: *mut VorbisDspState);
+    fn vorbis_block_init(v: *mut VorbisDspState, vb: *mut VorbisBlock)
+                         -> c_int;
+    fn vorbis_block_clear(vb: *mut VorbisBlock) -> c_int;
+    fn vorbis_analysis_headerout(v: *mut VorbisDspState,
+                                 vc: *mut VorbisComment,
+                                 op: *mut OggPacket,
+                                 op_comm: *mut OggPacket,
+                                 op_code: *mut OggPacket) -> c_int;
+    fn vorbis_analysis_buffer(v: *mut VorbisDspState, vals: c_int)
+                              -> *mut *mut c_float;
+    fn vorbis_analysis_wrote(v: *mut VorbisDspState, vals: c_int) -> c_int;
+    fn vorbis_analysis_blockout(v: *mut VorbisDspState, vb: *mut VorbisBlock)
+                                -> c_int;
+    fn vorbis_analysis(vb: *mut VorbisBlock, op: *mut OggPacket) -> c_int;
+    fn vorbis_bitrate_addblock(vb: *mut VorbisBlock) -> c_int;
+    fn vorbis_bitrate_flushpacket(vd: *mut VorbisDspState,
+                                  op: *mut OggPacket) -> c_int;
+}
+
+
+/// State of the encoder, boxed as libvorbis keeps pointers between its
+/// structures, and released when dropped.
+struct Vorbis {
+    info: VorbisInfo,
+    comment: VorbisComment,
+    dsp: VorbisDspState,
+    block: VorbisBlock,
+    /// Whether `dsp` and `block` are initialized.
+    analysis: bool,
+}
+
+// The encoder is only used through `&mut VorbisWriter`, and isn't bound to
+// the thread that created it.
+unsafe impl Send for Vorbis {}
+
+impl Drop for Vorbis {
+    fn drop(&mut self) {
+        unsafe {
+            if self.analysis {
+                vorbis_block_clear(&mut self.block);
+                vorbis_dsp_clear(&mut self.dsp);
+            }
+            vorbis_comment_clear(&mut self.comment);
+            vorbis_info_clear(&mut self.info);
+        }
+    }
+}
+
+
+/// Writer encoding the CD audio written through it into an Ogg Vorbis
+/// stream.
+///
+/// The data written must be 44.1 kHz, 16-bit little-endian PCM samples, with
+/// the channels interleaved. `finish()` must be called once all the samples
+/// are written, to encode the last packets and end the stream.
+pub struct VorbisWriter<W: Write> {
+    packets: PacketWriter<W>,
+    vorbis: Box<Vorbis>,
+    channels: usize,
+    /// Incomplete sample.
+    pending: Vec<u8>,
+}
+
+impl<W: Write> VorbisWriter<W> {
+    /// Starts a Vorbis stream in `channels` channels (1 or 2), encoded at
+    /// `quality` (from -1 to 10, as oggenc's `-q` option), described by
+    /// `tags`.
+    pub fn new(inner: W, channels: u16, quality: f32, tags: &NrgTags)
+               -> Result<VorbisWriter<W>, NrgError> {
+        if channels != 1 && channels != 2 {
+            return Err(NrgError::NrgFormat(format!(
+                "Can't encode {} channels in Vorbis", channels)));
+        }
+        if !(MIN_QUALITY..=MAX_QUALITY).contains(&quality) {
+            return Err(NrgError::Encoder(format!(
+                "Vorbis quality must be between {} and {}",
+                MIN_QUALITY, MAX_QUALITY)));
+        }
+
+        // The structures are plain integers and pointers, initialized by
+        // libvorbis
+        let mut vorbis: Box<Vorbis> = Box::new(unsafe { mem::zeroed() });
+        let mut head = empty_packet();
+        let mut comments = empty_packet();
+        let mut code = empty_packet();
+        unsafe {
+            vorbis_info_init(&mut vorbis.info);
+            vorbis_comment_init(&mut vorbis.comment);
+            if vorbis_encode_init_vbr(&mut vorbis.info, c_long::from(channels),
+                                      SAMPLE_RATE as c_long,
+                                      quality / 10.0) != 0 {
+                return Err(NrgError::Encoder(
+                    "Invalid parameters for the Vorbis encoder".to_string()));
+            }
+            let vorbis = &mut *vorbis;
+            if vorbis_analysis_init(&mut vorbis.dsp, &mut vorbis.info) != 0 {
+                return Err(NrgError::Encoder(
+                    "Can't initialize the Vorbis encoder".to_string()));
+            }
+            vorbis_block_init(&mut vorbis.dsp, &mut vorbis.block);
+            vorbis.analysis = true;
+            if vorbis_analysis_headerout(&mut vorbis.dsp, &mut vorbis.comment,
+                                         &mut head, &mut comments,
+                                         &mut code) != 0 {
+                return Err(NrgError::Encoder(
+                    "Can't build the Vorbis headers".to_string()));
+            }
+        }
+
+        // The comment header built by libvorbis is replaced with one holding
+        // the tags
+        let mut tags_header = b"\x03vorbis".to_vec();
+        tags_header.extend_from_slice(&tags::vorbis_comments(tags));
+        tags_header.push(1);
+
+        let mut packets = PacketWriter::new(inner);
+        packets.write_packet(packet_bytes(&head).into_boxed_slice(), SERIAL,
+                             PacketWriteEndInfo::EndPage, 0)?;
+        packets.write_packet(tags_header.into_boxed_slice(), SERIAL,
+                             PacketWriteEndInfo::NormalPacket, 0)?;
+        packets.write_packet(packet_bytes(&code).into_boxed_slice(), SERIAL,
+                             PacketWriteEndInfo::EndPage, 0)?;
+
+        Ok(VorbisWriter {
+            packets,
+            vorbis,
+            channels: usize::from(channels),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Encodes the last packets, ends the stream and returns the inner
+    /// writer.
+    pub fn finish(mut self) -> Result<W, NrgError> {
+        if !self.pending.is_empty() {
+            return Err(NrgError::NrgFormat(
+                "Incomplete sample in the audio data".to_string()));
+        }
+        unsafe { vorbis_analysis_wrote(&mut self.vorbis.dsp, 0) };
+        self.write_packets()?;
+        let mut inner = self.packets.into_inner();
+        inner.flush()?;
+        Ok(inner)
+    }
+
+    /// Submits `pcm`, made of complete samples, to the encoder and writes
+    /// the packets ready.
+    fn encode(&mut self, pcm: &[u8]) -> io::Result<()> {
+        for chunk in pcm.chunks(CHUNK_SAMPLES * 2 * self.channels) {
+            let samples = chunk.len() / (2 * self.channels);
+            unsafe {
+                let buffer = vorbis_analysis_buffer(&mut self.vorbis.dsp,
+                                                    samples as c_int);
+                let buffer = slice::from_raw_parts(buffer, self.channels);
+                for (channel, &samples_ptr) in buffer.iter().enumerate() {
+                    let out = slice::from_raw_parts_mut(samples_ptr, samples);
+                    for (i, sample) in out.iter_mut().enumerate() {
+                        let pos = (i * self.channels + channel) * 2;
+                        *sample = f32::from(i16::from_le_bytes(
+                            [chunk[pos], chunk[pos + 1]])) / 32768.0;
+                    }
+                }
+                vorbis_analysis_wrote(&mut self.vorbis.dsp, samples as c_int);
+            }
+            self.write_packets()?;
+        }
+        Ok(())
+    }
+
+    /// Analyzes the blocks of samples available and writes their packets.
+    fn write_packets(&mut self) -> io::Result<()> {
+        let vorbis = &mut *self.vorbis;
+        let mut packet = empty_packet();
+        unsafe {
+            while vorbis_analysis_blockout(&mut vorbis.dsp,
+                                           &mut vorbis.block) == 1 {
+                if vorbis_analysis(&mut vorbis.block, ptr::null_mut()) != 0
+                    || vorbis_bitrate_addblock(&mut vorbis.block) != 0 {
+                    return Err(io::Error::other(NrgError::Encoder(
+                        "Vorbis encoder: analysis failed".to_string())));
+                }
+                while vorbis_bitrate_flushpacket(&mut vorbis.dsp,
+                                                 &mut packet) == 1 {
+                    let end = if packet.e_o_s != 0 {
+                        PacketWriteEndInfo::EndStream
+                    } else {
+                        PacketWriteEndInfo::NormalPacket
+                    };
+                    self.packets.write_packet(
+                        packet_bytes(&packet).into_boxed_slice(), SERIAL, end,
+                        packet.granulepos as u64)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for VorbisWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let frame_bytes = 2 * self.channels;
+        let complete = self.pending.len() / frame_bytes * frame_bytes;
+        let pending = mem::take(&mut self.pending);
+        let result = self.encode(&pending[..complete]);
+        self.pending = pending;
+        self.pending.drain(..complete);
+        result.map(|()| buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.packets.inner_mut().flush()
+    }
+}
+
+impl<W: Write> fmt::Debug for VorbisWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VorbisWriter")
+            .field("channels", &self.channels)
+            .finish_non_exhaustive()
+    }
+}
+
+
+fn empty_packet() -> OggPacket {
+    OggPacket {
+        packet: ptr::null_mut(),
+        bytes: 0,
+        b_o_s: 0,
+        e_o_s: 0,
+        granulepos: 0,
+        packetno: 0,
+    }
+}
+
+/// Copies the data of `packet`, owned by libvorbis.
+fn packet_bytes(packet: &OggPacket) -> Vec<u8> {
+    if packet.packet.is_null() || packet.bytes <= 0 {
+        return Vec::new();
+    }
+    unsafe { slice::from_raw_parts(packet.packet, packet.bytes as usize) }
+        .to_vec()
+}
@@ -0,0 +1,195 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to read the tracks of an NRG image as WAV streams.
+//!
+//! A `WavReader` presents the audio of a track as a self-describing WAV file:
+//! a RIFF header, followed by the samples read on the fly from the image,
+//! without the sub-channel if present.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use ::error::NrgError;
+use ::metadata::track::Track;
+use ::raw_audio::{RAW_SEC_SIZE, RAW96_SEC_SIZE, SAMPLE_RATE};
+
+/// Size of the header of the WAV files, in bytes.
+pub const WAV_HEADER_SIZE: u64 = 44;
+
+
+/// Builds the header of a WAV file holding `data_size` bytes of CD audio
+/// with `channels` channels.
+///
+/// Returns an error if `data_size` exceeds the 4 GiB limit of the format.
+pub fn wav_header(data_size: u64, channels: u16)
+                  -> Result<[u8; WAV_HEADER_SIZE as usize], NrgError> {
+    if data_size > u64::from(u32::MAX - 36) {
+        return Err(NrgError::NrgFormat(
+            "Audio data too large for a WAV file".to_string()));
+    }
+    let data_size = data_size as u32;
+
+    let mut header = Vec::with_capacity(WAV_HEADER_SIZE as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(36 + data_size).to_le_bytes());
+    header.extend_from_slice(b"WAVE");
+    header.extend_from_slice(b"fmt ");
+    header.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    header.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    let block_align = 2 * channels;
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    header.extend_from_slice(&(SAMPLE_RATE * u32::from(block_align))
+                             .to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_size.to_le_bytes());
+
+    let mut bytes = [0u8; WAV_HEADER_SIZE as usize];
+    bytes.copy_from_slice(&header);
+    Ok(bytes)
+}
+
+
+/// Reader of a track of an NRG image as a stereo WAV file.
+///
+/// The audio data is the track's, from its index #1 to its end, read from
+/// the image as needed; the sub-channel of 2448-byte sectors is skipped.
+/// The stream can be seeked, e.g. to serve it as a file.
+#[derive(Debug)]
+pub struct WavReader<R> {
+    inner: R,
+    header: [u8; WAV_HEADER_SIZE as usize],
+    /// Byte position of the track's audio in the image.
+    start: u64,
+    sector_size: u64,
+    /// Length of the audio data, in bytes.
+    data_size: u64,
+    /// Position in the WAV stream.
+    pos: u64,
+    /// Position of `inner` if known, to avoid needless seeks.
+    inner_pos: Option<u64>,
+}
+
+impl<R: Read + Seek> WavReader<R> {
+    /// Reads `track` from the NRG image `inner`.
+    ///
+    /// Returns an error if `track` isn't an audio track made of 2352- or
+    /// 2448-byte sectors.
+    pub fn new(inner: R, track: &Track) -> Result<WavReader<R>, NrgError> {
+        if track.is_data() {
+            return Err(NrgError::NrgFormat(format!(
+                "Track {} is a data track", track.number)));
+        }
+        let sector_size = track.sector_size;
+        if sector_size != RAW_SEC_SIZE && sector_size != RAW96_SEC_SIZE {
+            return Err(NrgError::NrgFormat(format!(
+                "Can't read {}-byte sectors as WAV", sector_size)));
+        }
+        let data_size = track.sector_count() * u64::from(RAW_SEC_SIZE);
+        Ok(WavReader {
+            inner,
+            header: wav_header(data_size, 2)?,
+            start: track.index1,
+            sector_size: u64::from(sector_size),
+            data_size,
+            pos: 0,
+            inner_pos: None,
+        })
+    }
+
+    /// Returns the length of the WAV stream, header included, in bytes.
+    pub fn len(&self) -> u64 {
+        WAV_HEADER_SIZE + self.data_size
+    }
+
+    /// Returns true if the track holds no audio data; the stream still
+    /// holds its header.
+    pub fn is_empty(&self) -> bool {
+        self.data_size == 0
+    }
+
+    /// Returns the image read.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Seek> Read for WavReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos < WAV_HEADER_SIZE {
+            let header = &self.header[self.pos as usize..];
+            let nbytes = header.len().min(buf.len());
+            buf[..nbytes].copy_from_slice(&header[..nbytes]);
+            self.pos += nbytes as u64;
+            return Ok(nbytes);
+        }
+        if self.pos >= self.len() {
+            return Ok(0);
+        }
+
+        // Read up to the end of the current sector's audio
+        let audio_pos = self.pos - WAV_HEADER_SIZE;
+        let audio_sector_size = u64::from(RAW_SEC_SIZE);
+        let in_sector = audio_pos % audio_sector_size;
+        let file_pos = self.start + audio_pos / audio_sector_size
+            * self.sector_size + in_sector;
+        let nbytes = (audio_sector_size - in_sector)
+            .min(buf.len() as u64) as usize;
+        if self.inner_pos != Some(file_pos) {
+            self.inner.seek(SeekFrom::Start(file_pos))?;
+        }
+        let nbytes = match self.inner.read(&mut buf[..nbytes]) {
+            Ok(nbytes) => nbytes,
+            Err(err) => {
+                self.inner_pos = None;
+                return Err(err);
+            },
+        };
+        if nbytes == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.inner_pos = Some(file_pos + nbytes as u64);
+        self.pos += nbytes as u64;
+        Ok(nbytes)
+    }
+}
+
+impl<R: Read + Seek> Seek for WavReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, delta) = match pos {
+            SeekFrom::Start(offset) => {
+                self.pos = offset;
+                return Ok(offset);
+            },
+            SeekFrom::End(delta) => (self.len(), delta),
+            SeekFrom::Current(delta) => (self.pos, delta),
+        };
+        self.pos = base.checked_add_signed(delta).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput,
+                           "Invalid seek to a negative or overflowing \
+                            position")
+        })?;
+        Ok(self.pos)
+    }
+}
@@ -0,0 +1,796 @@
+// This file is part of the NRGrip project.
+//
+// Copyright (c) 2026 heartbleeded
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+
+//! Module to write NRG v2 images of audio CDs.
+//!
+//! An image is described by an `NrgDisc`, which lists the tracks and the
+//! length of their audio data, and is built from that raw audio data (as
+//! found in BIN files: 2352-byte sectors of 16-bit little-endian stereo
+//! samples). An `NrgDisc` can be obtained from a cue sheet, see
+//! `NrgCueSheet`, or from a list of WAV files, see `write_nrg_from_wavs()`.
+//!
+//! The images written hold the audio data, followed by CUEX, DAOX, CDTX (if
+//! there is CD-Text), SINF and MTYP chunks and the NRG v2 footer.
+//!
+//! Existing images can also be copied without the subchannel of their
+//! sectors, see `write_stripped_copy()`.
+
+use std::io::{self, Read, Write};
+#[cfg(feature = "fs")]
+use std::fs::{self, File};
+#[cfg(feature = "fs")]
+use std::io::{BufReader, BufWriter, Seek, SeekFrom};
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+
+use ::cdtext::NrgCdText;
+use ::error::NrgError;
+use ::isrc::Isrc;
+#[cfg(feature = "fs")]
+use ::metadata;
+#[cfg(feature = "fs")]
+use ::metadata::raw;
+use ::msf::Msf;
+use ::raw_audio::RAW_SEC_SIZE;
+#[cfg(feature = "fs")]
+use ::raw_audio::{self, CopyControl, RAW96_SEC_SIZE};
+
+
+/// CUEX mode of the audio tracks, without any control flag.
+pub const AUDIO_MODE: u8 = 0x01;
+
+/// DAOX data mode of the tracks made of 2352-byte audio sectors: the mode is
+/// the first byte of the field, followed by a null byte.
+const DAOX_AUDIO_MODE: u16 = 0x0700;
+
+/// Size of the DAOX header, before the track blocks.
+pub(crate) const DAOX_HEADER_SIZE: usize = 22;
+/// Size of a DAOX track block.
+pub(crate) const DAOX_TRACK_SIZE: usize = 42;
+
+/// Length of the pre-gap of the first track on an audio CD, in sectors.
+pub(crate) const FIRST_PREGAP_SECTORS: u32 = 150;
+
+/// Size of the buffer used to write silence.
+const SILENCE_BUF_SIZE: usize = RAW_SEC_SIZE as usize * 75;
+
+
+/// An audio CD to write as an NRG image.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NrgDisc {
+    /// UPC/EAN of the disc (13 digits), or an empty string.
+    pub upc: String,
+    /// Number of the first track.
+    pub first_track: u8,
+    /// The tracks, in order.
+    pub tracks: Vec<NrgDiscTrack>,
+    /// CD-Text information, if any.
+    pub cd_text: Option<NrgCdText>,
+}
+
+/// A track of an `NrgDisc`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NrgDiscTrack {
+    /// Mode of the track in the CUEX chunk: `AUDIO_MODE`, possibly combined
+    /// with the control flags 0x10 (pre-emphasis), 0x20 (digital copy
+    /// permitted) and 0x80 (four channels).
+    pub mode: u8,
+    /// ISRC of the track (12 characters, or 15 with hyphens between its
+    /// elements), or an empty string.
+    pub isrc: String,
+    /// Length of the silence inserted before the track's audio data, in
+    /// sectors.
+    pub pregap: u32,
+    /// Position of index #1 in the track's audio data, in sectors; the
+    /// audio data before it is part of the pre-gap.
+    pub index1: u32,
+    /// Length of the track's audio data, in bytes. An incomplete last
+    /// sector is padded with silence.
+    pub audio_size: u64,
+}
+
+impl NrgDiscTrack {
+    /// Returns the number of sectors of the track's audio data.
+    fn audio_sectors(&self) -> u64 {
+        self.audio_size.div_ceil(u64::from(RAW_SEC_SIZE))
+    }
+}
+
+
+/// Positions of a track in the image being written, in sectors from the
+/// start of the image.
+pub(crate) struct TrackLayout {
+    pub(crate) index0: u64,
+    pub(crate) index1: u64,
+    pub(crate) end: u64,
+}
+
+
+/// Writes the NRG v2 image of `disc` to `out`.
+///
+/// `audio` must provide the audio data of all the tracks, one after the
+/// other, as described by their `audio_size`.
+///
+/// The first track's index #1 is placed at least 2 seconds (150 sectors)
+/// after the start of the image, as on a real CD: the missing part of its
+/// pre-gap is filled with silence.
+///
+/// Returns the size of the image written, in bytes.
+pub fn write_nrg<R: Read, W: Write>(disc: &NrgDisc, audio: &mut R,
+                                    out: &mut W) -> Result<u64, NrgError> {
+    check_disc(disc)?;
+
+    // Write the audio data, with the pre-gaps
+    let sector_size = u64::from(RAW_SEC_SIZE);
+    let mut layouts = Vec::new();
+    let mut sectors = 0;
+    for (i, track) in disc.tracks.iter().enumerate() {
+        let mut pregap = u64::from(track.pregap);
+        if i == 0 {
+            pregap += u64::from(FIRST_PREGAP_SECTORS)
+                .saturating_sub(pregap + u64::from(track.index1));
+        }
+        write_silence(out, pregap * sector_size)?;
+
+        let copied = io::copy(&mut audio.by_ref().take(track.audio_size),
+                              out)?;
+        if copied != track.audio_size {
+            return Err(NrgError::AudioLength { expected: track.audio_size,
+                                               copied });
+        }
+        let padding = track.audio_sectors() * sector_size - track.audio_size;
+        write_silence(out, padding)?;
+
+        layouts.push(TrackLayout {
+            index0: sectors,
+            index1: sectors + pregap + u64::from(track.index1),
+            end: sectors + pregap + track.audio_sectors(),
+        });
+        sectors += pregap + track.audio_sectors();
+    }
+
+    // Write the chunks and the footer
+    let chunk_offset = sectors * sector_size;
+    let mut size = chunk_offset;
+    size += write_chunk(out, "CUEX", &cuex_payload(disc, &layouts))?;
+    size += write_chunk(out, "DAOX", &daox_payload(disc, &layouts))?;
+    if let Some(ref cd_text) = disc.cd_text {
+        size += write_chunk(out, "CDTX",
+                            &cd_text.to_packs(disc.first_track)?)?;
+    }
+    let nb_tracks = disc.tracks.len() as u32;
+    size += write_chunk(out, "SINF", &nb_tracks.to_be_bytes())?;
+    size += write_chunk(out, "MTYP", &1u32.to_be_bytes())?;
+    size += write_chunk(out, "END!", &[])?;
+    size += write_footer(out, chunk_offset)?;
+    Ok(size)
+}
+
+
+/// Checks that `disc` can be written as an NRG image.
+fn check_disc(disc: &NrgDisc) -> Result<(), NrgError> {
+    let end = usize::from(disc.first_track) + disc.tracks.len();
+    if disc.tracks.is_empty() || disc.first_track == 0 || end > 100 {
+        return Err(NrgError::NrgFormat(format!(
+            "Invalid track numbers: {} tracks starting at {}",
+            disc.tracks.len(), disc.first_track)));
+    }
+    if !disc.upc.is_empty() && !is_code(&disc.upc, 13) {
+        return Err(NrgError::NrgFormat(format!(
+            "Invalid UPC: \"{}\"", disc.upc)));
+    }
+    for track in &disc.tracks {
+        if !track.isrc.is_empty() {
+            Isrc::parse(&track.isrc)?;
+        }
+        if u64::from(track.index1) >= track.audio_sectors() {
+            return Err(NrgError::NrgFormat(format!(
+                "Index #1 at sector {} is past the {} sectors of the track",
+                track.index1, track.audio_sectors())));
+        }
+    }
+    Ok(())
+}
+
+
+/// Tells whether `code` is made of `len` ASCII letters and digits.
+pub(crate) fn is_code(code: &str, len: usize) -> bool {
+    code.len() == len && code.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+
+/// Writes `size` bytes of silence to `out`.
+fn write_silence<W: Write>(out: &mut W, size: u64) -> io::Result<()> {
+    let silence = [0u8; SILENCE_BUF_SIZE];
+    let mut remaining = size;
+    while remaining > 0 {
+        let len = remaining.min(SILENCE_BUF_SIZE as u64);
+        out.write_all(&silence[..len as usize])?;
+        remaining -= len;
+    }
+    Ok(())
+}
+
+
+/// Writes an NRG v2 chunk made of its ID, the size of `payload` and
+/// `payload` itself.
+///
+/// Returns the number of bytes written.
+pub fn write_chunk<W: Write>(out: &mut W, id: &str, payload: &[u8])
+                             -> Result<u64, NrgError> {
+    if id.len() != 4 {
+        return Err(NrgError::NrgChunkId(id.to_string()));
+    }
+    out.write_all(id.as_bytes())?;
+    out.write_all(&(payload.len() as u32).to_be_bytes())?;
+    out.write_all(payload)?;
+    Ok(8 + payload.len() as u64)
+}
+
+
+/// Writes the NRG v2 footer, pointing to the first chunk at
+/// `chunk_offset`.
+///
+/// Returns the number of bytes written.
+pub fn write_footer<W: Write>(out: &mut W, chunk_offset: u64)
+                              -> Result<u64, NrgError> {
+    out.write_all(b"NER5")?;
+    out.write_all(&chunk_offset.to_be_bytes())?;
+    Ok(12)
+}
+
+
+/// Returns the CUEX entry of an index, `track` being the BCD-encoded track
+/// number.
+fn cuex_entry(mode: u8, track: u8, index: u8, sectors: u64) -> [u8; 8] {
+    let position = sectors as i32 - FIRST_PREGAP_SECTORS as i32;
+    let mut entry = [mode, track, to_bcd(index), 0, 0, 0, 0, 0];
+    entry[4..].copy_from_slice(&position.to_be_bytes());
+    entry
+}
+
+
+/// Encodes `value` (less than 100) as a binary-coded decimal.
+fn to_bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+
+pub(crate) fn cuex_payload(disc: &NrgDisc, layouts: &[TrackLayout])
+                           -> Vec<u8> {
+    let first_mode = disc.tracks[0].mode;
+    let mut payload = cuex_entry(first_mode, 0, 0, 0).to_vec();
+    let numbers = disc.first_track..;
+    for (number, (track, layout)) in numbers.zip(disc.tracks.iter()
+                                                 .zip(layouts)) {
+        let bcd = to_bcd(number);
+        if layout.index0 < layout.index1 {
+            payload.extend(&cuex_entry(track.mode, bcd, 0, layout.index0));
+        }
+        payload.extend(&cuex_entry(track.mode, bcd, 1, layout.index1));
+    }
+    let last_mode = disc.tracks[disc.tracks.len() - 1].mode;
+    let lead_out = layouts[layouts.len() - 1].end;
+    payload.extend(&cuex_entry(last_mode, 0xAA, 1, lead_out));
+    payload
+}
+
+
+pub(crate) fn daox_payload(disc: &NrgDisc, layouts: &[TrackLayout])
+                           -> Vec<u8> {
+    let size = (DAOX_HEADER_SIZE + DAOX_TRACK_SIZE * disc.tracks.len()) as u32;
+    let last_track = disc.first_track + (disc.tracks.len() - 1) as u8;
+    let mut payload = Vec::with_capacity(size as usize - 4);
+    payload.extend(&size.to_be_bytes());
+    payload.extend(&padded(&disc.upc, 13));
+    payload.push(0); // padding
+    payload.extend(&[0, 0]); // TOC type
+    payload.push(disc.first_track);
+    payload.push(last_track);
+
+    let sector_size = u64::from(RAW_SEC_SIZE);
+    for (track, layout) in disc.tracks.iter().zip(layouts) {
+        let isrc = Isrc::parse(&track.isrc)
+            .map(|isrc| format!("{:#}", isrc))
+            .unwrap_or_default();
+        payload.extend(&padded(&isrc, 12));
+        payload.extend(&RAW_SEC_SIZE.to_be_bytes());
+        payload.extend(&DAOX_AUDIO_MODE.to_be_bytes());
+        payload.extend(&[0x00, 0x01]); // unknown
+        payload.extend(&(layout.index0 * sector_size).to_be_bytes());
+        payload.extend(&(layout.index1 * sector_size).to_be_bytes());
+        payload.extend(&(layout.end * sector_size).to_be_bytes());
+    }
+    payload
+}
+
+
+/// Returns `text` padded with null bytes to `len` bytes.
+pub(crate) fn padded(text: &str, len: usize) -> Vec<u8> {
+    let mut bytes = text.as_bytes().to_vec();
+    bytes.resize(len, 0);
+    bytes
+}
+
+
+/// The description of an audio CD read from a cue sheet.
+///
+/// Only cue sheets with a single binary file of audio tracks are handled.
+/// The CATALOG, FILE, FLAGS, INDEX, ISRC, PREGAP and TRACK commands are
+/// used; REM, TITLE, PERFORMER, SONGWRITER and CDTEXTFILE are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NrgCueSheet {
+    /// Name of the audio file, as written in the cue sheet.
+    pub file: String,
+    /// UPC/EAN of the disc, or an empty string.
+    pub catalog: String,
+    /// Number of the first track.
+    pub first_track: u8,
+    /// The tracks, in order.
+    pub tracks: Vec<NrgCueTrack>,
+}
+
+/// A track of an `NrgCueSheet`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NrgCueTrack {
+    /// CUEX mode of the track, including the flags of the FLAGS command.
+    pub mode: u8,
+    /// ISRC of the track, or an empty string.
+    pub isrc: String,
+    /// Length of the silence to insert before the track (PREGAP command),
+    /// in sectors.
+    pub pregap: u32,
+    /// Position of index #0 in the audio file, in sectors, if any.
+    pub index0: Option<u32>,
+    /// Position of index #1 in the audio file, in sectors.
+    pub index1: u32,
+}
+
+impl NrgCueSheet {
+    /// Parses the cue sheet `text`.
+    pub fn parse(text: &str) -> Result<NrgCueSheet, NrgError> {
+        let mut sheet = NrgCueSheet::default();
+        let mut index1_found = true;
+        let mut last_position = 0;
+        for (i, line) in text.lines().enumerate() {
+            let words = split_cue_line(line);
+            let error = |message: &str| NrgError::CueSheet(
+                format!("line {}: {}", i + 1, message));
+            let (command, args) = match words.split_first() {
+                Some((command, args)) => (command.to_uppercase(), args),
+                None => continue,
+            };
+            let in_track = !sheet.tracks.is_empty();
+            match (command.as_str(), args) {
+                ("REM", _) | ("TITLE", _) | ("PERFORMER", _)
+                    | ("SONGWRITER", _) | ("CDTEXTFILE", _) => {},
+                ("CATALOG", [catalog]) if !in_track =>
+                    sheet.catalog = catalog.clone(),
+                ("FILE", [file, kind]) if sheet.file.is_empty() => {
+                    if !kind.eq_ignore_ascii_case("BINARY") {
+                        return Err(error(&format!(
+                            "unsupported file type \"{}\"", kind)));
+                    }
+                    sheet.file = file.clone();
+                },
+                ("FILE", _) if !sheet.file.is_empty() =>
+                    return Err(error("only one FILE is supported")),
+                ("TRACK", [number, kind]) if !sheet.file.is_empty() => {
+                    if !index1_found {
+                        return Err(error("previous track without index 01"));
+                    }
+                    if !kind.eq_ignore_ascii_case("AUDIO") {
+                        return Err(error(&format!(
+                            "unsupported track type \"{}\"", kind)));
+                    }
+                    let number = match number.parse::<u8>() {
+                        Ok(number) if (1..=99).contains(&number) => number,
+                        _ => return Err(error("invalid track number")),
+                    };
+                    let expected =
+                        usize::from(sheet.first_track) + sheet.tracks.len();
+                    if !in_track {
+                        sheet.first_track = number;
+                    } else if usize::from(number) != expected {
+                        return Err(error("non-consecutive track number"));
+                    }
+                    sheet.tracks.push(NrgCueTrack {
+                        mode: AUDIO_MODE,
+                        ..NrgCueTrack::default()
+                    });
+                    index1_found = false;
+                },
+                ("FLAGS", flags) if in_track => for flag in flags {
+                    let track = sheet.tracks.last_mut().unwrap();
+                    match flag.to_uppercase().as_str() {
+                        "PRE" => track.mode |= 0x10,
+                        "DCP" => track.mode |= 0x20,
+                        "4CH" => track.mode |= 0x80,
+                        "SCMS" => {},
+                        _ => return Err(error(&format!(
+                            "unknown flag \"{}\"", flag))),
+                    }
+                },
+                ("ISRC", [isrc]) if in_track =>
+                    sheet.tracks.last_mut().unwrap().isrc = isrc.clone(),
+                ("PREGAP", [length]) if in_track && !index1_found => {
+                    let length = Msf::parse(length)
+                        .ok_or_else(|| error("invalid pre-gap length"))?;
+                    sheet.tracks.last_mut().unwrap().pregap =
+                        length.to_sectors();
+                },
+                ("INDEX", [number, position]) if in_track => {
+                    let position = Msf::parse(position)
+                        .ok_or_else(|| error("invalid index position"))?
+                        .to_sectors();
+                    if position < last_position {
+                        return Err(error("index position out of order"));
+                    }
+                    last_position = position;
+                    let track = sheet.tracks.last_mut().unwrap();
+                    match number.parse::<u8>() {
+                        Ok(0) if !index1_found && track.index0.is_none() =>
+                            track.index0 = Some(position),
+                        Ok(1) if !index1_found => {
+                            track.index1 = position;
+                            index1_found = true;
+                        },
+                        Ok(number) if index1_found && number > 1 =>
+                            debug!("Ignoring index {:02}", number),
+                        _ => return Err(error("unexpected index number")),
+                    }
+                },
+                ("POSTGAP", _) =>
+                    return Err(error("POSTGAP is not supported")),
+                _ => return Err(error(&format!(
+                    "unexpected {} command", command))),
+            }
+        }
+
+        if sheet.tracks.is_empty() {
+            return Err(NrgError::CueSheet("no tracks".to_string()));
+        }
+        if !index1_found {
+            return Err(NrgError::CueSheet(
+                "last track without index 01".to_string()));
+        }
+        Ok(sheet)
+    }
+
+    /// Describes the disc made of the tracks of this cue sheet, the audio
+    /// file being `audio_size` bytes long.
+    ///
+    /// The audio data preceding the first track's index #1 belongs to its
+    /// pre-gap; the other tracks start at their index #0, if any, or #1.
+    pub fn disc(&self, audio_size: u64) -> Result<NrgDisc, NrgError> {
+        let sector_size = u64::from(RAW_SEC_SIZE);
+        let mut tracks = Vec::new();
+        for (i, track) in self.tracks.iter().enumerate() {
+            let start = if i == 0 {
+                0
+            } else {
+                track.index0.unwrap_or(track.index1)
+            };
+            let end = match self.tracks.get(i + 1) {
+                Some(next) =>
+                    u64::from(next.index0.unwrap_or(next.index1)) * sector_size,
+                None => audio_size,
+            };
+            if end <= u64::from(track.index1) * sector_size {
+                return Err(NrgError::CueSheet(format!(
+                    "track {} ends before its index 01 (audio file of {} \
+                     bytes)", usize::from(self.first_track) + i,
+                    audio_size)));
+            }
+            tracks.push(NrgDiscTrack {
+                mode: track.mode,
+                isrc: track.isrc.clone(),
+                pregap: track.pregap,
+                index1: track.index1 - start,
+                audio_size: end - u64::from(start) * sector_size,
+            });
+        }
+        Ok(NrgDisc {
+            upc: self.catalog.clone(),
+            first_track: self.first_track,
+            tracks,
+            cd_text: None,
+        })
+    }
+}
+
+
+/// Splits a cue sheet line into words, a word being enclosed in double
+/// quotes if it contains spaces.
+pub(crate) fn split_cue_line(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            words.push(chars.by_ref().take_while(|&c| c != '"').collect());
+        } else {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                word.push(c);
+            }
+            words.push(word);
+        }
+    }
+    words
+}
+
+
+/// Writes the NRG image of the audio CD described by the cue sheet
+/// `cue_path` to `out_path`.
+///
+/// The audio file named in the cue sheet is looked for relative to the cue
+/// sheet's directory.
+///
+/// Returns the size of the image written, in bytes.
+#[cfg(feature = "fs")]
+pub fn write_nrg_from_cue(cue_path: &Path, out_path: &Path)
+                          -> Result<u64, NrgError> {
+    let sheet = NrgCueSheet::parse(&fs::read_to_string(cue_path)?)?;
+    let audio_path = cue_path.parent().unwrap_or_else(|| Path::new(""))
+        .join(&sheet.file);
+    if audio_path == out_path || cue_path == out_path {
+        return Err(NrgError::FileName("Input and output file are identical"
+                                      .to_string()));
+    }
+
+    let audio_fd = File::open(&audio_path)?;
+    let disc = sheet.disc(audio_fd.metadata()?.len())?;
+    info!("Writing NRG image to \"{}\"", out_path.display());
+    let mut out = BufWriter::new(File::create(out_path)?);
+    let size = write_nrg(&disc, &mut BufReader::new(audio_fd), &mut out)?;
+    out.flush()?;
+    Ok(size)
+}
+
+
+/// An audio track read from a WAV file, for `write_nrg_from_wavs()`.
+#[cfg(feature = "fs")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NrgWavTrack {
+    /// Path of the WAV file, holding 16-bit stereo PCM samples at 44.1 kHz.
+    pub path: PathBuf,
+    /// Length of the silence to insert before the track, in sectors.
+    pub pregap: u32,
+}
+
+
+/// Writes the NRG image of the audio CD made of `tracks` to `out_path`,
+/// with the CD-Text information `cd_text` if any.
+///
+/// The tracks are numbered from 1; a track's index #1 is at the end of its
+/// pre-gap, and an incomplete last sector is padded with silence.
+///
+/// Returns the size of the image written, in bytes.
+#[cfg(feature = "fs")]
+pub fn write_nrg_from_wavs(tracks: &[NrgWavTrack],
+                           cd_text: Option<NrgCdText>, out_path: &Path)
+                           -> Result<u64, NrgError> {
+    let mut disc = NrgDisc {
+        first_track: 1,
+        cd_text,
+        ..NrgDisc::default()
+    };
+    let mut audio: Box<dyn Read> = Box::new(io::empty());
+    for track in tracks {
+        if track.path == out_path {
+            return Err(NrgError::FileName(
+                "Input and output file are identical".to_string()));
+        }
+        let (fd, audio_size) = open_wav(&track.path)?;
+        audio = Box::new(audio.chain(BufReader::new(fd).take(audio_size)));
+        disc.tracks.push(NrgDiscTrack {
+            mode: AUDIO_MODE,
+            pregap: track.pregap,
+            audio_size,
+            ..NrgDiscTrack::default()
+        });
+    }
+
+    info!("Writing NRG image to \"{}\"", out_path.display());
+    let mut out = BufWriter::new(File::create(out_path)?);
+    let size = write_nrg(&disc, &mut audio, &mut out)?;
+    out.flush()?;
+    Ok(size)
+}
+
+
+/// Opens the WAV file `path` and checks that it holds CD audio.
+///
+/// Returns the file, positioned at the start of the samples, and the size
+/// of the samples in bytes.
+#[cfg(feature = "fs")]
+fn open_wav(path: &Path) -> Result<(File, u64), NrgError> {
+    let error = |message: &str| NrgError::WavFormat(
+        format!("\"{}\": {}", path.display(), message));
+    let mut fd = File::open(path)?;
+    let mut header = [0u8; 12];
+    fd.read_exact(&mut header)?;
+    if &header[..4] != b"RIFF" || &header[8..] != b"WAVE" {
+        return Err(error("not a WAV file"));
+    }
+
+    let mut format_found = false;
+    loop {
+        let mut chunk = [0u8; 8];
+        match fd.read_exact(&mut chunk) {
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof =>
+                return Err(error("no data chunk")),
+            result => result?,
+        }
+        let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6],
+                                       chunk[7]]);
+        match &chunk[..4] {
+            b"fmt " => {
+                let mut format = [0u8; 16];
+                if size < 16 {
+                    return Err(error("invalid format chunk"));
+                }
+                fd.read_exact(&mut format)?;
+                // Format tag 1 (PCM), 2 channels, 44100 Hz, 16 bits
+                if format[..8] != [1, 0, 2, 0, 0x44, 0xAC, 0, 0]
+                    || format[14..] != [16, 0] {
+                    return Err(error("not 16-bit stereo PCM at 44.1 kHz"));
+                }
+                format_found = true;
+                let skipped = i64::from(size) - 16 + i64::from(size % 2);
+                fd.seek(SeekFrom::Current(skipped))?;
+            },
+            b"data" if format_found => return Ok((fd, u64::from(size))),
+            b"data" => return Err(error("no format chunk before the data")),
+            _ => {
+                let skipped = i64::from(size) + i64::from(size % 2);
+                fd.seek(SeekFrom::Current(skipped))?;
+            },
+        }
+    }
+}
+
+
+/// Writes a copy of the NRG image `in_fd` to `out`, stripping the 96-byte
+/// subchannel from the tracks made of 2448-byte sectors.
+///
+/// The DAOX chunk is updated with the new sector sizes, data modes and byte
+/// positions of the tracks; the other chunks are copied as is, as well as
+/// any data located outside of the tracks. Images holding an ETN2 chunk are
+/// refused, since the byte positions it holds can't be updated.
+///
+/// Returns the size of the image written, in bytes.
+#[cfg(feature = "fs")]
+pub fn write_stripped_copy<R: Read + Seek, W: Write>(in_fd: &mut R,
+                                                     out: &mut W)
+                                                     -> Result<u64, NrgError> {
+    let metadata = metadata::read_nrg_metadata(in_fd)?;
+    let chunks = raw::read_raw_chunks(in_fd)?;
+    if chunks.iter().any(|chunk| chunk.id == "ETN2") {
+        return Err(NrgError::NrgFormat(
+            "Images with an ETN2 chunk can't be stripped".to_string()));
+    }
+    let daox = metadata.daox_chunk()
+        .ok_or_else(|| NrgError::NrgFormat("No DAOX chunk".to_string()))?;
+    if daox.tracks().iter().all(|track| track.sector_size() != RAW96_SEC_SIZE) {
+        return Err(NrgError::NrgFormat("No subchannel to strip".to_string()));
+    }
+
+    // Copy the audio data, remembering the new positions of the tracks
+    let mut positions = Vec::new();
+    let mut pos = 0;
+    let mut written = 0;
+    for (i, track) in daox.tracks().iter().enumerate() {
+        let (index0, index1, end) =
+            (track.index0(), track.index1(), track.track_end());
+        if index0 < pos || index0 > index1 || index1 > end
+            || end > metadata.chunk_offset() {
+            return Err(NrgError::NrgFormat(format!(
+                "Invalid byte range of DAOX track {}", i + 1)));
+        }
+        written += copy_bytes(in_fd, out, pos, index0 - pos)?;
+
+        let sector_size = u64::from(track.sector_size());
+        let new_index0 = written;
+        if sector_size == u64::from(RAW96_SEC_SIZE) {
+            if !(end - index0).is_multiple_of(sector_size)
+                || !(index1 - index0).is_multiple_of(sector_size) {
+                return Err(NrgError::NrgFormat(format!(
+                    "DAOX track {} is not made of whole sectors", i + 1)));
+            }
+            in_fd.seek(SeekFrom::Start(index0))?;
+            let sectors = raw_audio::copy_raw96_audio(
+                in_fd, out, end - index0, &mut CopyControl::unlimited())?
+                / sector_size;
+            written += sectors * u64::from(RAW_SEC_SIZE);
+            let pregap = (index1 - index0) / sector_size;
+            positions.push((new_index0,
+                            new_index0 + pregap * u64::from(RAW_SEC_SIZE),
+                            written));
+        } else {
+            written += copy_bytes(in_fd, out, index0, end - index0)?;
+            positions.push((new_index0, new_index0 + index1 - index0,
+                            written));
+        }
+        pos = end;
+    }
+    written += copy_bytes(in_fd, out, pos, metadata.chunk_offset() - pos)?;
+
+    // Copy the chunks, with the updated DAOX chunk
+    let chunk_offset = written;
+    for chunk in &chunks {
+        if chunk.id != "DAOX" {
+            written += write_chunk(out, &chunk.id, &chunk.payload)?;
+            continue;
+        }
+        let mut payload = chunk.payload.clone();
+        for (i, &(index0, index1, end)) in positions.iter().enumerate() {
+            let block = &mut payload[DAOX_HEADER_SIZE + i * DAOX_TRACK_SIZE..]
+                [..DAOX_TRACK_SIZE];
+            if block[12..14] == RAW96_SEC_SIZE.to_be_bytes() {
+                let mode = u16::from_be_bytes([block[14], block[15]]);
+                block[12..14].copy_from_slice(&RAW_SEC_SIZE.to_be_bytes());
+                block[14..16].copy_from_slice(
+                    &stripped_data_mode(mode).to_be_bytes());
+            }
+            block[18..26].copy_from_slice(&index0.to_be_bytes());
+            block[26..34].copy_from_slice(&index1.to_be_bytes());
+            block[34..].copy_from_slice(&end.to_be_bytes());
+        }
+        written += write_chunk(out, &chunk.id, &payload)?;
+    }
+    written += write_footer(out, chunk_offset)?;
+    Ok(written)
+}
+
+
+/// Copies `count` bytes from `in_fd`, starting at `pos`, to `out`.
+///
+/// Returns the number of bytes copied.
+#[cfg(feature = "fs")]
+fn copy_bytes<R: Read + Seek, W: Write>(in_fd: &mut R, out: &mut W,
+                                        pos: u64, count: u64)
+                                        -> Result<u64, NrgError> {
+    in_fd.seek(SeekFrom::Start(pos))?;
+    let copied = io::copy(&mut in_fd.take(count), out)?;
+    if copied != count {
+        return Err(NrgError::AudioLength { expected: count, copied });
+    }
+    Ok(copied)
+}
+
+
+/// Returns the DAOX data mode of 2352-byte sectors corresponding to the
+/// data mode `mode` of 2448-byte sectors (0x0F for Mode 1, 0x10 for audio
+/// and 0x11 for Mode 2); other modes are returned unchanged.
+#[cfg(feature = "fs")]
+fn stripped_data_mode(mode: u16) -> u16 {
+    match mode >> 8 {
+        0x0F => 0x0500 | (mode & 0xFF),
+        0x10 => DAOX_AUDIO_MODE | (mode & 0xFF),
+        0x11 => 0x0600 | (mode & 0xFF),
+        _ => mode,
+    }
+}
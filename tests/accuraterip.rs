@@ -0,0 +1,107 @@
+extern crate nrgrip;
+use nrgrip::accuraterip::{self, NrgAccurateRipIds, NrgAccurateRipTrack};
+use nrgrip::disc_id::NrgDiscToc;
+use nrgrip::metadata;
+use std::io::Cursor;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+/// Sets the sample at the 1-based `position` of the track starting at the
+/// byte `start`.
+fn set_sample(contents: &mut [u8], start: usize, position: usize,
+              value: u32) {
+    let offset = start + (position - 1) * 4;
+    contents[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[test]
+fn compute_checksums() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 10),
+                  TrackSpec::new(0, 10)];
+    let mut contents = common::build_nrg_v2(&tracks);
+    let (track1, track2, track3) =
+        (150 * SECTOR_SIZE, 160 * SECTOR_SIZE, 170 * SECTOR_SIZE);
+    contents[track1..track3 + 10 * SECTOR_SIZE].iter_mut()
+        .for_each(|b| *b = 0);
+
+    // The first 2939 samples of the first track are left out
+    set_sample(&mut contents, track1, 2939, 1);
+    set_sample(&mut contents, track1, 2940, 1);
+    // The v2 checksum adds the upper half of the products
+    set_sample(&mut contents, track2, 2, 0xFFFF_FFFF);
+    set_sample(&mut contents, track2, 3, 2);
+    // The last 2940 samples of the last track are left out
+    set_sample(&mut contents, track3, 2940, 1);
+    set_sample(&mut contents, track3, 2941, 1);
+
+    let mut fd = Cursor::new(contents);
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    let checksums = accuraterip::compute_checksums(&mut fd, &metadata)
+        .expect("compute_checksums()");
+    let crcs: Vec<(u8, u32, u32)> = checksums.iter()
+        .map(|track| (track.track_number, track.crc_v1, track.crc_v2))
+        .collect();
+    assert_eq!(crcs, [(1, 2940, 2940), (2, 4, 5), (3, 2940, 2940)]);
+    assert!(checksums.iter().all(|track| track.confidence.is_none()));
+    assert_eq!(checksums[1].to_string(),
+               "Track 02: AccurateRip v1 00000004, v2 00000005");
+}
+
+#[test]
+fn disc_ids() {
+    let toc = NrgDiscToc {
+        first_track: 1,
+        offsets: vec![150, 15363, 32314, 46592, 63414, 80489],
+        lead_out: 95462,
+    };
+    let ids = NrgAccurateRipIds::from_toc(&toc);
+    assert_eq!(ids, NrgAccurateRipIds {
+        track_count: 6,
+        id1: 0x513BE,
+        id2: 0x1B2231,
+        cddb_id: 0x3404F606,
+    });
+    assert_eq!(ids.to_string(), "dBAR-006-000513be-001b2231-3404f606");
+}
+
+#[test]
+fn apply_database() {
+    // Two pressings of a 2-track disc
+    let mut data = Vec::new();
+    for &(confidence, crc) in &[(7u8, 0x1111_1111u32), (3, 0x3333_3333)] {
+        data.push(2);
+        data.extend_from_slice(&[0; 12]);
+        for _ in 0..2 {
+            data.push(confidence);
+            data.extend_from_slice(&crc.to_le_bytes());
+            data.extend_from_slice(&[0; 4]);
+        }
+    }
+    let pressings = accuraterip::parse_database(&data)
+        .expect("parse_database()");
+    assert_eq!(pressings.len(), 2);
+    assert_eq!(pressings[1].tracks[0].confidence, 3);
+    assert_eq!(pressings[1].tracks[0].crc, 0x3333_3333);
+
+    let mut tracks = [
+        NrgAccurateRipTrack { track_number: 1, crc_v1: 0x1111_1111,
+                              crc_v2: 0x3333_3333, confidence: None },
+        NrgAccurateRipTrack { track_number: 2, crc_v1: 0x2222_2222,
+                              crc_v2: 0x2222_2222, confidence: None },
+    ];
+    accuraterip::apply_database(&mut tracks, 1, &pressings);
+    assert_eq!(tracks[0].confidence, Some(10));
+    assert!(tracks[0].is_accurate());
+    assert_eq!(tracks[1].confidence, Some(0));
+    assert!(!tracks[1].is_accurate());
+    assert_eq!(tracks[0].to_string(),
+               "Track 01: AccurateRip v1 11111111, v2 33333333, accurately \
+                ripped (confidence 10)");
+    assert_eq!(tracks[1].to_string(),
+               "Track 02: AccurateRip v1 22222222, v2 22222222, no matching \
+                rip in the database");
+
+    assert!(accuraterip::parse_database(&data[..20]).is_err());
+}
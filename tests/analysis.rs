@@ -0,0 +1,70 @@
+extern crate nrgrip;
+use nrgrip::analysis;
+use nrgrip::metadata;
+use std::io::Cursor;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+#[test]
+fn analyze_tracks() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 10),
+                  TrackSpec::new(0, 10)];
+    let mut contents = common::build_nrg_v2(&tracks);
+
+    // Track 2: 5 silent samples at the start, 1 sector at the end
+    let track2 = 160 * SECTOR_SIZE;
+    contents[track2..track2 + 20].iter_mut().for_each(|b| *b = 0);
+    contents[track2 + 9 * SECTOR_SIZE..track2 + 10 * SECTOR_SIZE]
+        .iter_mut().for_each(|b| *b = 0);
+    // Track 3: silent, but for the left channel of its first sample
+    let track3 = 170 * SECTOR_SIZE;
+    contents[track3 + 2..track3 + 10 * SECTOR_SIZE]
+        .iter_mut().for_each(|b| *b = 0);
+
+    let mut fd = Cursor::new(contents);
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    let analyses = analysis::analyze_tracks(&mut fd, &metadata)
+        .expect("analyze_tracks()");
+
+    let track_samples = 10 * SECTOR_SIZE as u64 / 4;
+    let lengths: Vec<(u64, u64, u64)> = analyses.iter()
+        .map(|a| (a.samples, a.leading_silence, a.trailing_silence))
+        .collect();
+    assert_eq!(lengths, [(track_samples, 0, 0),
+                         (track_samples, 5, 588),
+                         (track_samples, 0, track_samples - 1)]);
+    assert!(analyses.iter().all(|analysis| !analysis.is_silent()));
+    assert_eq!(analyses[1].audio_samples(), track_samples - 593);
+    assert!(analyses[1].to_string().starts_with(
+        "Track 02: 0:00.11 of audio, 0:00.00 of silence at the start, \
+         0:00.01 at the end, peak "));
+
+    // The pattern's samples are made of bytes 1 to 251
+    let peak = analyses[0].peak;
+    assert!(peak > 0x7F00 && peak < 32_768);
+    assert!(analyses[0].rms > 0.0 && analyses[0].rms < f64::from(peak));
+    assert_eq!(analyses[0].clipped_samples, 0);
+
+    // A silent track
+    let mut contents = fd.into_inner();
+    contents[track3..track3 + 2].iter_mut().for_each(|b| *b = 0);
+    let mut fd = Cursor::new(contents);
+    let analyses = analysis::analyze_tracks(&mut fd, &metadata)
+        .expect("analyze_tracks()");
+    assert!(analyses[2].is_silent());
+    assert_eq!(analyses[2].leading_silence, track_samples);
+    assert_eq!(analyses[2].to_string(), "Track 03: silent (0:00.13)");
+    assert_eq!(analyses[2].peak_dbfs(), f64::NEG_INFINITY);
+
+    // A clipped track
+    let mut contents = fd.into_inner();
+    contents[track3..track3 + 4].copy_from_slice(&[0xFF, 0x7F, 0x00, 0x80]);
+    let mut fd = Cursor::new(contents);
+    let analyses = analysis::analyze_tracks(&mut fd, &metadata)
+        .expect("analyze_tracks()");
+    assert_eq!(analyses[2].clipped_samples, 2);
+    assert_eq!(analyses[2].peak, 32_768);
+    assert_eq!(analyses[2].peak_dbfs(), 0.0);
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "tokio")]
+
+extern crate nrgrip;
+extern crate tokio;
+use nrgrip::async_io;
+use tokio::io::AsyncReadExt;
+use tokio::runtime::{Builder, Runtime};
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+fn runtime() -> Runtime {
+    Builder::new_current_thread().build()
+        .expect("Builder::build()")
+}
+
+#[test]
+fn read_track_stream() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("async.nrg", &contents);
+
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let metadata = runtime
+        .block_on(async_io::read_nrg_metadata_async(img.clone()))
+        .expect("read_nrg_metadata_async()");
+    let track = metadata.tracks().nth(1).expect("track 2");
+
+    let mut stream = runtime
+        .block_on(async_io::open_track_stream(img, track, true))
+        .expect("open_track_stream()");
+    let mut audio = Vec::new();
+    runtime.block_on(stream.read_to_end(&mut audio))
+        .expect("read_to_end()");
+
+    assert_eq!(audio, &contents[235 * SECTOR_SIZE..255 * SECTOR_SIZE]);
+}
@@ -0,0 +1,124 @@
+extern crate nrgrip;
+use nrgrip::cdtext::{self, NrgCdText, NrgCdTextTrack, PACK_SIZE};
+
+#[test]
+fn cd_text_packs() {
+    assert_eq!(cdtext::crc16(b"123456789"), !0x31C3);
+
+    let cd_text = NrgCdText {
+        title: "Album".to_string(),
+        performer: String::new(),
+        tracks: ["One", "Two"].iter().map(|title| NrgCdTextTrack {
+            title: title.to_string(),
+            ..NrgCdTextTrack::default()
+        }).collect(),
+        ..NrgCdText::default()
+    };
+    let packs = cd_text.to_packs(1).expect("to_packs()");
+
+    // Two title packs, no performer packs, and the size information
+    assert_eq!(packs.len(), 5 * PACK_SIZE);
+    assert_eq!(&packs[..16], b"\x80\x00\x00\x00Album\0One\0Tw");
+    assert_eq!(&packs[18..34], b"\x80\x02\x01\x02o\0\0\0\0\0\0\0\0\0\0\0");
+    assert_eq!(&packs[36..52],
+               b"\x8F\x00\x02\x00\x00\x01\x02\x00\x02\x00\x00\x00\
+                 \x00\x00\x00\x00");
+    assert_eq!(packs[3 * PACK_SIZE + 4 + 8], 4); // last sequence number
+    for pack in packs.chunks(PACK_SIZE) {
+        let crc = cdtext::crc16(&pack[..16]);
+        assert_eq!(&pack[16..], &crc.to_be_bytes());
+    }
+}
+
+/// Returns the packs of `cd_text` as block `block`, the languages of the
+/// blocks being `languages`.
+fn block_packs(cd_text: &NrgCdText, block: u8, languages: &[u8]) -> Vec<u8> {
+    let mut packs = cd_text.to_packs(1).expect("to_packs()");
+    let nb_packs = packs.len() / PACK_SIZE;
+    for (i, pack) in packs.chunks_mut(PACK_SIZE).enumerate() {
+        pack[3] |= block << 4;
+        if i == nb_packs - 1 {
+            pack[8..8 + languages.len()].copy_from_slice(languages);
+        }
+        let crc = cdtext::crc16(&pack[..16]);
+        pack[16..].copy_from_slice(&crc.to_be_bytes());
+    }
+    packs
+}
+
+#[test]
+fn cd_text_language_blocks() {
+    let english = NrgCdText::from_cue_sheet(
+        "TITLE Album\nPERFORMER Band\nTRACK 01 AUDIO\nTITLE One\n\
+         TRACK 02 AUDIO\nTITLE Two\n");
+    let french = NrgCdText::from_cue_sheet(
+        "TITLE Album\nPERFORMER Groupe\nTRACK 01 AUDIO\nTITLE Un\n\
+         TRACK 02 AUDIO\nTITLE Deux\n");
+    let mut packs = block_packs(&english, 0, &[0x09, 0x0F]);
+    packs.extend(block_packs(&french, 1, &[0x09, 0x0F]));
+
+    let blocks = cdtext::parse_packs(&packs).expect("parse_packs()");
+    assert_eq!(blocks.len(), 2);
+    assert_eq!((blocks[0].number, blocks[0].language), (0, 0x09));
+    assert_eq!(blocks[0].text, english);
+    assert_eq!(blocks[1].language_name(), Some("French"));
+    assert_eq!(blocks[1].text, french);
+
+    let french_code = cdtext::parse_language("french").unwrap();
+    assert_eq!(cdtext::parse_language("0x0F").unwrap(), french_code);
+    assert_eq!(cdtext::select_block(&blocks, Some(french_code)),
+               Some(&blocks[1]));
+    assert_eq!(cdtext::select_block(&blocks, Some(0x08)), Some(&blocks[0]));
+    assert_eq!(cdtext::select_block(&blocks, None), Some(&blocks[0]));
+    assert!(cdtext::parse_language("Klingon").is_err());
+
+    // A tab repeats the previous string
+    let mut tab = english.clone();
+    tab.tracks[1].title = "\t".to_string();
+    let packs = tab.to_packs(1).unwrap();
+    let blocks = cdtext::parse_packs(&packs).expect("parse_packs()");
+    assert_eq!(blocks[0].text.tracks[1].title, "One");
+    assert!(cdtext::parse_packs(&packs[1..]).is_err());
+}
+
+#[test]
+fn extended_cd_text_fields() {
+    let mut cd_text = NrgCdText::from_cue_sheet(
+        "CATALOG 0123456789012\nTITLE Album\nSONGWRITER \"Some One\"\n\
+         TRACK 01 AUDIO\nTITLE One\nISRC FRXYZ2600001\n\
+         TRACK 02 AUDIO\nTITLE Two\n");
+    assert_eq!(cd_text.upc, "0123456789012");
+    assert_eq!(cd_text.tracks[0].isrc, "FRXYZ2600001");
+    cd_text.composer = "Composer".to_string();
+    cd_text.tracks[1].arranger = "Arranger".to_string();
+    cd_text.tracks[1].message = "Bonus track".to_string();
+    cd_text.genre_code = 14;
+
+    let packs = cd_text.to_packs(1).expect("to_packs()");
+    let genre = packs.chunks(PACK_SIZE).find(|pack| pack[0] == 0x87)
+        .expect("genre pack");
+    assert_eq!(&genre[4..7], b"\x00\x0E\x00");
+    let blocks = cdtext::parse_packs(&packs).expect("parse_packs()");
+    assert_eq!(blocks[0].text, cd_text);
+    assert_eq!(cd_text.genre_name(), Some("Jazz".to_string()));
+    cd_text.genre = "Bebop".to_string();
+    assert_eq!(cd_text.genre_name(), Some("Bebop".to_string()));
+}
+
+#[test]
+fn cd_text_without_size_information() {
+    // Title packs of track 127 holding 132 strings, without size
+    // information
+    let text = b"a\0".repeat(132);
+    let mut packs = Vec::new();
+    for (i, chunk) in text.chunks(12).enumerate() {
+        let mut pack = vec![0x80, if i == 0 { 0x7F } else { 0 }, i as u8, 0];
+        pack.extend_from_slice(chunk);
+        pack.extend_from_slice(&[0, 0]);
+        packs.extend_from_slice(&pack);
+    }
+
+    let blocks = cdtext::parse_packs(&packs).expect("parse_packs()");
+    assert_eq!(blocks[0].text.tracks.len(), 1);
+    assert_eq!(blocks[0].text.tracks[0].title, "a");
+}
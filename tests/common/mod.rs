@@ -0,0 +1,175 @@
+// Helpers to build synthetic NRG images for the integration tests.
+
+#![allow(dead_code)]
+
+use std::convert::TryInto;
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+pub const SECTOR_SIZE: usize = 2352;
+
+/// Description of a track of a synthetic image.
+pub struct TrackSpec {
+    /// Number of pre-gap sectors, filled with silence.
+    pub pregap: usize,
+    /// Number of sectors after index 1, filled with a non-zero pattern.
+    pub length: usize,
+    /// Whether the pre-gap is declared in the CUEX chunk (index 0).
+    pub cuex_index0: bool,
+}
+
+impl TrackSpec {
+    pub fn new(pregap: usize, length: usize) -> TrackSpec {
+        TrackSpec { pregap, length, cuex_index0: true }
+    }
+}
+
+fn bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+/// Builds an NRG v2 audio image with 2352-byte sectors.
+pub fn build_nrg_v2(tracks: &[TrackSpec]) -> Vec<u8> {
+    let mut img = Vec::new();
+    let mut daox_tracks = Vec::new();
+    let mut cuex = Vec::new();
+
+    // Lead-in
+    cuex.extend_from_slice(&[0x01, 0x00, 0x00, 0x00]);
+    cuex.extend_from_slice(&(-150i32 as u32).to_be_bytes());
+
+    for (i, track) in tracks.iter().enumerate() {
+        let number = i as u8 + 1;
+        let index0 = img.len() as u64;
+        img.resize(img.len() + track.pregap * SECTOR_SIZE, 0);
+        let index1 = img.len() as u64;
+        for s in 0..track.length * SECTOR_SIZE {
+            img.push((s % 251) as u8 + 1);
+        }
+        let track_end = img.len() as u64;
+        daox_tracks.push((index0, index1, track_end));
+
+        let pos0 = (index0 / SECTOR_SIZE as u64) as i32 - 150;
+        let pos1 = (index1 / SECTOR_SIZE as u64) as i32 - 150;
+        if track.cuex_index0 {
+            cuex.extend_from_slice(&[0x01, bcd(number), 0x00, 0x00]);
+            cuex.extend_from_slice(&(pos0 as u32).to_be_bytes());
+        }
+        cuex.extend_from_slice(&[0x01, bcd(number), 0x01, 0x00]);
+        cuex.extend_from_slice(&(pos1 as u32).to_be_bytes());
+    }
+
+    // Lead-out
+    let lead_out = (img.len() / SECTOR_SIZE) as i32 - 150;
+    cuex.extend_from_slice(&[0x01, 0xAA, 0x01, 0x00]);
+    cuex.extend_from_slice(&(lead_out as u32).to_be_bytes());
+
+    let chunk_offset = img.len() as u64;
+
+    img.extend_from_slice(b"CUEX");
+    img.extend_from_slice(&(cuex.len() as u32).to_be_bytes());
+    img.extend_from_slice(&cuex);
+
+    let daox_size = 22 + 42 * tracks.len() as u32;
+    img.extend_from_slice(b"DAOX");
+    img.extend_from_slice(&daox_size.to_be_bytes());
+    img.extend_from_slice(&daox_size.to_be_bytes());
+    img.extend_from_slice(&[0u8; 13]); // UPC
+    img.push(0); // padding
+    img.extend_from_slice(&[0x00, 0x00]); // TOC type
+    img.push(1);
+    img.push(tracks.len() as u8);
+    for &(index0, index1, track_end) in &daox_tracks {
+        img.extend_from_slice(&[0u8; 12]); // ISRC
+        img.extend_from_slice(&(SECTOR_SIZE as u16).to_be_bytes());
+        img.extend_from_slice(&[0x07, 0x00]); // data mode
+        img.extend_from_slice(&[0x00, 0x01]); // unknown
+        img.extend_from_slice(&index0.to_be_bytes());
+        img.extend_from_slice(&index1.to_be_bytes());
+        img.extend_from_slice(&track_end.to_be_bytes());
+    }
+
+    img.extend_from_slice(b"SINF");
+    img.extend_from_slice(&4u32.to_be_bytes());
+    img.extend_from_slice(&(tracks.len() as u32).to_be_bytes());
+
+    img.extend_from_slice(b"MTYP");
+    img.extend_from_slice(&4u32.to_be_bytes());
+    img.extend_from_slice(&1u32.to_be_bytes());
+
+    let mut afnm = Vec::new();
+    for i in 0..tracks.len() {
+        afnm.extend_from_slice(format!("Track {}.wav", i + 1).as_bytes());
+        afnm.push(0);
+    }
+    img.extend_from_slice(b"AFNM");
+    img.extend_from_slice(&(afnm.len() as u32).to_be_bytes());
+    img.extend_from_slice(&afnm);
+
+    img.extend_from_slice(b"END!");
+    img.extend_from_slice(&0u32.to_be_bytes());
+
+    img.extend_from_slice(b"NER5");
+    img.extend_from_slice(&chunk_offset.to_be_bytes());
+
+    img
+}
+
+/// Turns an image built by common::build_nrg_v2() into an image with
+/// 2448-byte sectors, appending a subchannel to each sector.
+pub fn make_raw96_image(img: &[u8], sectors: usize) -> Vec<u8> {
+    let audio_size = sectors * SECTOR_SIZE;
+    let mut raw96 = Vec::new();
+    for sector in img[..audio_size].chunks(SECTOR_SIZE) {
+        raw96.extend_from_slice(sector);
+        raw96.extend_from_slice(&[0xEE; 96]);
+    }
+    let chunk_offset = raw96.len();
+    raw96.extend_from_slice(&img[audio_size..img.len() - 12]);
+
+    // Update the DAOX tracks: sector size, data mode and byte positions
+    let daox = raw96.windows(4).rposition(|id| id == b"DAOX").unwrap();
+    let tracks = u32::from_be_bytes(raw96[daox + 4..daox + 8]
+                                    .try_into().unwrap()) as usize / 42;
+    for i in 0..tracks {
+        let block = &mut raw96[daox + 30 + i * 42..][..42];
+        block[12..16].copy_from_slice(&[0x09, 0x90, 0x10, 0x00]);
+        for pos in (18..42).step_by(8) {
+            let old = u64::from_be_bytes(block[pos..pos + 8]
+                                         .try_into().unwrap());
+            let new = old / SECTOR_SIZE as u64 * 2448;
+            block[pos..pos + 8].copy_from_slice(&new.to_be_bytes());
+        }
+    }
+    raw96.extend_from_slice(b"NER5");
+    raw96.extend_from_slice(&(chunk_offset as u64).to_be_bytes());
+    raw96
+}
+
+/// Returns the metadata blocks of `flac`, as (type, payload) pairs.
+pub fn flac_blocks(flac: &[u8]) -> Vec<(u8, &[u8])> {
+    assert_eq!(&flac[..4], b"fLaC");
+    let mut blocks = Vec::new();
+    let mut pos = 4;
+    loop {
+        let header = &flac[pos..pos + 4];
+        let size = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+        blocks.push((header[0] & 0x7F, &flac[pos + 4..][..size as usize]));
+        pos += 4 + size as usize;
+        if header[0] & 0x80 != 0 {
+            return blocks;
+        }
+    }
+}
+
+/// Writes `contents` to a file named `name` in a temporary directory, and
+/// returns the file's path.
+pub fn write_temp_file(name: &str, contents: &[u8]) -> PathBuf {
+    let mut path = env::temp_dir();
+    path.push(format!("nrgrip-test-{}", name));
+    let mut fd = File::create(&path).expect("File::create()");
+    fd.write_all(contents).expect("fd.write_all()");
+    path
+}
@@ -0,0 +1,90 @@
+extern crate nrgrip;
+use nrgrip::compare::{self, NrgAudioLayout, NrgDifference};
+use nrgrip::metadata;
+use std::io::Cursor;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+#[test]
+fn compare_nrg_with_bin() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let img = common::build_nrg_v2(&tracks);
+    let nrg = NrgAudioLayout::from_metadata(
+        &metadata::read_nrg_metadata(&mut Cursor::new(&img))
+            .expect("read_nrg_metadata()"))
+        .expect("from_metadata()");
+    assert_eq!(nrg.sectors(), 105);
+
+    let mut bin = img[150 * SECTOR_SIZE..255 * SECTOR_SIZE].to_vec();
+    let raw = NrgAudioLayout::raw(bin.len() as u64);
+    let comparison = compare::compare(&mut Cursor::new(&img), &nrg,
+                                      &mut Cursor::new(&bin), &raw, 0)
+        .expect("compare()");
+    assert!(comparison.identical(), "{}", comparison);
+
+    // Damage sectors 3 and 4, and shift the audio by 5 samples
+    for b in &mut bin[3 * SECTOR_SIZE..5 * SECTOR_SIZE] {
+        *b ^= 0xFF;
+    }
+    bin.splice(..0, vec![0; 20]);
+    bin.truncate(105 * SECTOR_SIZE);
+    let comparison = compare::compare(&mut Cursor::new(&img), &nrg,
+                                      &mut Cursor::new(&bin), &raw, 10)
+        .expect("compare()");
+    assert_eq!(comparison.offset, 5);
+    assert_eq!(comparison.sectors, (105, 105));
+    assert_eq!(comparison.differences,
+               [NrgDifference { first_sector: 3, sectors: 2 },
+                NrgDifference { first_sector: 104, sectors: 1 }]);
+    assert_eq!(comparison.differing_sectors(), 3);
+    assert_eq!(comparison.result(), "DIFFERENT");
+}
+
+#[test]
+fn compare_tables_of_contents() {
+    let img = common::build_nrg_v2(&[TrackSpec::new(150, 10),
+                                     TrackSpec::new(75, 20)]);
+    let other = common::build_nrg_v2(&[TrackSpec::new(150, 40),
+                                       TrackSpec::new(75, 20)]);
+    let layout = |img: &[u8]| NrgAudioLayout::from_metadata(
+        &metadata::read_nrg_metadata(&mut Cursor::new(img))
+            .expect("read_nrg_metadata()"))
+        .expect("from_metadata()");
+
+    let comparison = compare::compare(&mut Cursor::new(&img), &layout(&img),
+                                      &mut Cursor::new(&other),
+                                      &layout(&other), 0)
+        .expect("compare()");
+    assert_eq!(comparison.toc_differences,
+               ["Track 01: 10 sectors in the first image, 40 in the second"]);
+    assert_eq!(comparison.sectors, (105, 135));
+    assert!(!comparison.same_audio());
+}
+
+#[test]
+fn compare_huge_layouts() {
+    // Image found by fuzzing: the track's audio is silent, and its DAOX
+    // track end lies far past the end of the file
+    let mut img = common::build_nrg_v2(&[TrackSpec::new(2, 0)]);
+    let daox = img.windows(4).position(|id| id == b"DAOX")
+        .expect("DAOX chunk");
+    img[daox + 64..daox + 72].copy_from_slice(&[0x7F, 0xFF, 0xFF, 0xFF,
+                                               0xFF, 0xFF, 0xFF, 0x00]);
+    let metadata = metadata::read_nrg_metadata(&mut Cursor::new(&img))
+        .expect("read_nrg_metadata()");
+    assert!(NrgAudioLayout::from_metadata(&metadata).is_err());
+
+    // The layouts built by hand are checked before reading
+    let huge = NrgAudioLayout { end: u64::MAX, ..NrgAudioLayout::raw(0) };
+    let raw = NrgAudioLayout::raw(img.len() as u64);
+    assert!(compare::compare(&mut Cursor::new(&img), &huge,
+                             &mut Cursor::new(&img), &raw, 10).is_err());
+
+    // The offset search is bounded
+    let comparison = compare::compare(&mut Cursor::new(&img), &raw,
+                                      &mut Cursor::new(&img), &raw,
+                                      4_000_000_000)
+        .expect("compare()");
+    assert!(comparison.identical(), "{}", comparison);
+}
@@ -0,0 +1,167 @@
+#![cfg(feature = "fs")]
+
+extern crate nrgrip;
+use nrgrip::convert::{self, ImageFormat};
+use nrgrip::metadata;
+use nrgrip::sectors;
+use std::env;
+use std::fs::{self, File};
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+#[test]
+fn convert_to_bin_cue() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("convert.nrg", &contents);
+    let img_path = img.to_string_lossy();
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let out_base = env::temp_dir().join("nrgrip-test-convert.out");
+    let files = convert::convert(&mut fd, &img_path, &metadata,
+                                 ImageFormat::BinCue, &out_base, true)
+        .expect("convert()");
+    assert_eq!(files.len(), 1);
+
+    let bin = fs::read(&files[0].path).expect("fs::read()");
+    assert_eq!(bin, &contents[150 * SECTOR_SIZE..255 * SECTOR_SIZE]);
+
+    let cue_path = env::temp_dir().join("nrgrip-test-convert.out.cue");
+    let cue = fs::read_to_string(&cue_path).expect("fs::read_to_string()");
+    assert!(cue.starts_with("FILE \"nrgrip-test-convert.out.bin\" BINARY\n"));
+    assert!(cue.ends_with("    INDEX 00 00:00:10\n    INDEX 01 00:01:10\n"));
+}
+
+/// Turns the single track of an image built by common::build_nrg_v2() into
+/// a Mode 1 data track.
+fn make_data_track(img: &mut [u8], sectors: usize) {
+    for i in 0..sectors {
+        let sector = &mut img[i * SECTOR_SIZE..(i + 1) * SECTOR_SIZE];
+        sector[..12].copy_from_slice(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+                                       0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]);
+        sector[15] = 1;
+    }
+    let cuex = sectors * SECTOR_SIZE + 8;
+    for entry in 0..4 {
+        img[cuex + entry * 8] = 0x41;
+    }
+}
+
+#[test]
+fn convert_to_iso() {
+    let tracks = [TrackSpec::new(0, 20)];
+    let mut contents = common::build_nrg_v2(&tracks);
+    let audio_img = common::write_temp_file("convert-audio.nrg", &contents);
+    make_data_track(&mut contents, 20);
+    let img = common::write_temp_file("convert-data.nrg", &contents);
+    let out_base = env::temp_dir().join("nrgrip-test-convert-data");
+
+    let mut fd = File::open(&audio_img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    assert!(convert::convert(&mut fd, &audio_img.to_string_lossy(),
+                             &metadata, ImageFormat::Iso, &out_base, true)
+            .is_err());
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    let files = convert::convert(&mut fd, &img.to_string_lossy(), &metadata,
+                                 ImageFormat::Iso, &out_base, true)
+        .expect("convert()");
+    assert_eq!(files[0].audio_bytes, 20 * 2048);
+
+    let iso = fs::read(&files[0].path).expect("fs::read()");
+    assert_eq!(iso.len(), 20 * 2048);
+    assert_eq!(&iso[..2048], &contents[16..16 + 2048]);
+    assert_eq!(&iso[19 * 2048..],
+               &contents[19 * SECTOR_SIZE + 16..19 * SECTOR_SIZE + 2064]);
+}
+
+#[test]
+fn convert_to_iso_with_repair() {
+    let tracks = [TrackSpec::new(0, 20)];
+    let mut contents = common::build_nrg_v2(&tracks);
+    make_data_track(&mut contents, 20);
+
+    // Make valid sectors: the first sector is at LBA -150
+    for i in 0..20 {
+        let sector = &mut contents[i * SECTOR_SIZE..(i + 1) * SECTOR_SIZE];
+        sector[12..15].copy_from_slice(&[0, 0, (i / 10 * 16 + i % 10) as u8]);
+        sector[2064..].iter_mut().for_each(|b| *b = 0);
+        let edc = sectors::edc(&sector[..2064]);
+        sector[2064..2068].copy_from_slice(&edc.to_le_bytes());
+        sectors::write_ecc(sector);
+    }
+    let original = contents.clone();
+
+    // Damage a byte of sector 3
+    contents[3 * SECTOR_SIZE + 1000] ^= 0xFF;
+    let img = common::write_temp_file("convert-repair.nrg", &contents);
+    let out_base = env::temp_dir().join("nrgrip-test-convert-repair");
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    let files = convert::convert(&mut fd, &img.to_string_lossy(), &metadata,
+                                 ImageFormat::Iso, &out_base, true)
+        .expect("convert()");
+
+    let iso = fs::read(&files[0].path).expect("fs::read()");
+    for (i, data) in iso.chunks(2048).enumerate() {
+        let start = i * SECTOR_SIZE + 16;
+        assert_eq!(data, &original[start..start + 2048]);
+    }
+}
+
+#[test]
+fn convert_to_nrg() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let raw96 = common::make_raw96_image(&contents, 255);
+    let img = common::write_temp_file("convert-raw96.nrg", &raw96);
+    let out_dir = env::temp_dir().join("nrgrip-test-convert-nrg");
+    fs::create_dir_all(&out_dir).expect("fs::create_dir_all()");
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    assert_eq!(metadata.sector_size(), 2448);
+    let files = convert::convert(&mut fd, &img.to_string_lossy(), &metadata,
+                                 ImageFormat::Nrg,
+                                 &out_dir.join("nrgrip-test-convert-raw96"),
+                                 true)
+        .expect("convert()");
+    assert_eq!(files[0].audio_bytes, 30 * SECTOR_SIZE as u64);
+
+    // Stripping the subchannel gives back the original image
+    let nrg = fs::read(&files[0].path).expect("fs::read()");
+    assert!(nrg == contents);
+
+    // Images without subchannel are refused, as well as overwriting the input
+    let mut fd = File::open(&files[0].path)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    assert!(convert::convert(&mut fd, &files[0].path, &metadata,
+                             ImageFormat::Nrg, &env::temp_dir().join("x"),
+                             true)
+            .is_err());
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    assert!(convert::convert(&mut fd, &img.to_string_lossy(), &metadata,
+                             ImageFormat::Nrg,
+                             &img.with_extension(""), true)
+            .is_err());
+}
@@ -0,0 +1,226 @@
+#![cfg(feature = "fs")]
+
+extern crate nrgrip;
+use nrgrip::cdtext::NrgCdText;
+use nrgrip::cue_sheet::{self, CueSheetOptions};
+use nrgrip::editor;
+use nrgrip::extraction::{ExtractedFile, GapMode};
+use nrgrip::metadata;
+use nrgrip::metadata::track::Track;
+use std::convert::TryInto;
+use std::env;
+use std::fs;
+use std::io::Cursor;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+const CUE_SHEET: &str = "CATALOG 0123456789012
+FILE \"nrgrip-test-cue-parse.bin\" BINARY
+  TRACK 01 AUDIO
+    TITLE \"First\"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    FLAGS PRE
+    ISRC FRXYZ2600001
+    INDEX 00 00:00:10
+    INDEX 01 00:00:30
+  TRACK 03 AUDIO
+    PREGAP 00:00:05
+    INDEX 01 00:01:00
+";
+
+#[test]
+fn parse_external_cue_sheet() {
+    let bin_path = common::write_temp_file("cue-parse.bin",
+                                           &vec![0; 100 * SECTOR_SIZE]);
+    let cue_path = common::write_temp_file("cue-parse.cue",
+                                           CUE_SHEET.as_bytes());
+
+    let sheet = cue_sheet::parse(&cue_path).expect("parse()");
+    assert_eq!(sheet.audio_path, bin_path);
+    assert_eq!(sheet.catalog, "0123456789012");
+
+    let sectors = |track: &Track| (track.index0 / SECTOR_SIZE as u64,
+                                   track.index1 / SECTOR_SIZE as u64,
+                                   track.track_end / SECTOR_SIZE as u64);
+    let positions: Vec<_> = sheet.tracks.iter().map(sectors).collect();
+    assert_eq!(positions, [(0, 0, 10), (10, 30, 75), (75, 75, 100)]);
+    let lbas: Vec<i32> = sheet.tracks.iter()
+        .map(|track| track.position_sectors)
+        .collect();
+    assert_eq!(lbas, [0, 30, 80]);
+
+    let track = &sheet.tracks[1];
+    assert_eq!(track.number, 2);
+    assert!(track.has_pre_emphasis());
+    assert_eq!(track.isrc.as_deref(), Some("FRXYZ2600001"));
+    assert_eq!(track.pregap_sectors(), 20);
+    assert_eq!(sheet.tracks[0].title.as_deref(), Some("First"));
+    assert_eq!(track.title, None);
+}
+
+#[test]
+fn write_first_track_pregap() {
+    let contents = common::build_nrg_v2(&[TrackSpec::new(150, 10),
+                                          TrackSpec::new(75, 20)]);
+    let metadata = metadata::read_from_slice(&contents)
+        .expect("read_from_slice()");
+    let cue_path = env::temp_dir().join("nrgrip-test-cue-pregap.cue");
+    let options = CueSheetOptions::new().output_path(&cue_path);
+    cue_sheet::write_cue_sheet("pregap.nrg", &metadata, &[], &options)
+        .expect("write_cue_sheet()");
+
+    let cue = fs::read_to_string(&cue_path).expect("fs::read_to_string()");
+    assert_eq!(cue, "FILE \"pregap.raw\" BINARY
+  TRACK 01 AUDIO
+    TITLE \"Track 1\"
+    PREGAP 00:02:00
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE \"Track 2\"
+    INDEX 00 00:00:10
+    INDEX 01 00:01:10
+");
+}
+
+#[test]
+fn write_all_indexes() {
+    let mut contents = common::build_nrg_v2(&[TrackSpec::new(150, 10),
+                                              TrackSpec::new(75, 20)]);
+    // Add an index #2 to track 2, after its index #1
+    let chunk_offset = u64::from_be_bytes(
+        contents[contents.len() - 8..].try_into().unwrap()) as usize;
+    let size = u32::from_be_bytes(
+        contents[chunk_offset + 4..chunk_offset + 8].try_into().unwrap());
+    contents[chunk_offset + 4..chunk_offset + 8]
+        .copy_from_slice(&(size + 8).to_be_bytes());
+    let entry = [0x01, 0x02, 0x02, 0x00, 0x00, 0x00, 0x00, 0x5A];
+    let pos = chunk_offset + 8 + 5 * 8;
+    contents.splice(pos..pos, entry.iter().cloned());
+
+    let metadata = metadata::read_from_slice(&contents)
+        .expect("read_from_slice()");
+    let cue_path = env::temp_dir().join("nrgrip-test-cue-indexes.cue");
+    let options = CueSheetOptions::new().output_path(&cue_path);
+    cue_sheet::write_cue_sheet("indexes.nrg", &metadata, &[], &options)
+        .expect("write_cue_sheet()");
+
+    let cue = fs::read_to_string(&cue_path).expect("fs::read_to_string()");
+    assert!(cue.ends_with("  TRACK 02 AUDIO
+    TITLE \"Track 2\"
+    INDEX 00 00:00:10
+    INDEX 01 00:01:10
+    INDEX 02 00:01:15
+"));
+}
+
+#[test]
+fn write_cd_text() {
+    let image = common::build_nrg_v2(&[TrackSpec::new(150, 10),
+                                       TrackSpec::new(75, 20)]);
+    let mut fd = Cursor::new(image);
+    let cd_text = NrgCdText::from_cue_sheet(
+        "TITLE Album\nPERFORMER Band\nTRACK 01 AUDIO\nTITLE One\n\
+         TRACK 02 AUDIO\nPERFORMER Guest\n");
+    editor::add_cd_text(&mut fd, &cd_text).expect("add_cd_text()");
+    let mut metadata = metadata::read_from_slice(fd.get_ref())
+        .expect("read_from_slice()");
+    // There is no French block: the first one is used
+    metadata.set_cd_text_language(Some(0x0F));
+
+    let cue_path = env::temp_dir().join("nrgrip-test-cue-cd-text.cue");
+    let options = CueSheetOptions::new().output_path(&cue_path);
+    cue_sheet::write_cue_sheet("cd-text.nrg", &metadata, &[], &options)
+        .expect("write_cue_sheet()");
+    let cue = fs::read_to_string(&cue_path).expect("fs::read_to_string()");
+    assert!(cue.starts_with("PERFORMER \"Band\"\nTITLE \"Album\"\n\
+                             FILE \"cd-text.raw\" BINARY\n\
+                             \x20 TRACK 01 AUDIO\n\
+                             \x20   TITLE \"One\"\n"));
+    assert!(cue.contains("  TRACK 02 AUDIO\n    TITLE \"Track 2\"\n\
+                          \x20   PERFORMER \"Guest\"\n"));
+}
+
+#[test]
+fn quote_values() {
+    assert_eq!(cue_sheet::quote_value("Plain"), "\"Plain\"");
+    assert_eq!(cue_sheet::quote_value("Say \"Hi\"; bye"),
+               "\"Say 'Hi'; bye\"");
+    assert_eq!(cue_sheet::quote_value("Café \\ Ça\nva"),
+               "\"Café \\ Ça va\"");
+
+    let image = common::build_nrg_v2(&[TrackSpec::new(150, 10)]);
+    let mut fd = Cursor::new(image);
+    let mut cd_text = NrgCdText::from_cue_sheet("TRACK 01 AUDIO\n");
+    cd_text.title = "L'été; \"live\"".to_string();
+    cd_text.tracks[0].title = "Señor \u{e9}".to_string();
+    editor::add_cd_text(&mut fd, &cd_text).expect("add_cd_text()");
+    let metadata = metadata::read_from_slice(fd.get_ref())
+        .expect("read_from_slice()");
+    let cue_path = env::temp_dir().join("nrgrip-test-cue-quotes.cue");
+    let options = CueSheetOptions::new().output_path(&cue_path)
+        .audio_file(&env::temp_dir().join("a \"b\".wav"));
+    cue_sheet::write_cue_sheet("quotes.nrg", &metadata, &[], &options)
+        .expect("write_cue_sheet()");
+
+    let cue = fs::read_to_string(&cue_path).expect("fs::read_to_string()");
+    assert!(cue.starts_with("TITLE \"L'été; 'live'\"\n\
+                             FILE \"a 'b'.wav\" WAVE\n\
+                             \x20 TRACK 01 AUDIO\n\
+                             \x20   TITLE \"Señor é\"\n"));
+    let parsed = NrgCdText::from_cue_sheet(&cue);
+    assert_eq!(parsed.title, "L'été; 'live'");
+    assert_eq!(parsed.tracks[0].title, "Señor é");
+
+    let options = options.ascii(true);
+    cue_sheet::write_cue_sheet("quotes.nrg", &metadata, &[], &options)
+        .expect("write_cue_sheet()");
+    let cue = fs::read_to_string(&cue_path).expect("fs::read_to_string()");
+    assert!(cue.starts_with("TITLE \"L'ete; 'live'\"\n"));
+    assert!(cue.contains("    TITLE \"Senor e\"\n"));
+}
+
+#[test]
+fn write_track_cue_sheets() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let metadata = metadata::read_from_slice(&common::build_nrg_v2(&tracks))
+        .expect("read_from_slice()");
+    let file = |number| ExtractedFile {
+        path: env::temp_dir()
+            .join(format!("nrgrip-test-track-cue-{:02}.wav", number))
+            .to_string_lossy().into_owned(),
+        track_number: Some(number),
+        audio_bytes: 0,
+        source_ranges: Vec::new(),
+        sectors: 0,
+        crc32: None,
+        loudness: None,
+        verified: None,
+        bad_sectors: Vec::new(),
+    };
+    let files = [file(1), file(2)];
+
+    // Track 2's pre-gap starts its file
+    let cue_paths = cue_sheet::write_track_cue_sheets(
+        &metadata, &files, GapMode::Prepend, &CueSheetOptions::new())
+        .expect("write_track_cue_sheets()");
+    assert_eq!(cue_paths.len(), 2);
+    assert_eq!(cue_paths[1], env::temp_dir()
+               .join("nrgrip-test-track-cue-02.cue"));
+    let cue = fs::read_to_string(&cue_paths[0]).expect("read_to_string()");
+    assert_eq!(cue, "FILE \"nrgrip-test-track-cue-01.wav\" WAVE\n\
+                     \x20 TRACK 01 AUDIO\n\
+                     \x20   TITLE \"Track 1\"\n\
+                     \x20   INDEX 01 00:00:00\n");
+    let cue = fs::read_to_string(&cue_paths[1]).expect("read_to_string()");
+    assert!(cue.ends_with("    INDEX 00 00:00:00\n    INDEX 01 00:01:00\n"));
+
+    // Track 2's pre-gap ends track 1's file
+    cue_sheet::write_track_cue_sheets(&metadata, &files, GapMode::Append,
+                                      &CueSheetOptions::new())
+        .expect("write_track_cue_sheets()");
+    let cue = fs::read_to_string(&cue_paths[1]).expect("read_to_string()");
+    assert!(cue.ends_with("  TRACK 02 AUDIO\n    TITLE \"Track 2\"\n\
+                           \x20   INDEX 01 00:00:00\n"));
+}
@@ -0,0 +1,48 @@
+extern crate nrgrip;
+use nrgrip::disc_id::NrgDiscToc;
+use nrgrip::metadata;
+use std::fs::File;
+
+mod common;
+use common::TrackSpec;
+
+#[test]
+fn musicbrainz_disc_id() {
+    // Example from the MusicBrainz documentation
+    let toc = NrgDiscToc {
+        first_track: 1,
+        offsets: vec![150, 15363, 32314, 46592, 63414, 80489],
+        lead_out: 95462,
+    };
+    assert_eq!(toc.last_track(), 6);
+    assert_eq!(toc.musicbrainz_disc_id(), "49HHV7Eb8UKF3aQiNmu1GR8vKTY-");
+}
+
+#[test]
+fn cddb_disc_id() {
+    let toc = NrgDiscToc {
+        first_track: 1,
+        offsets: vec![150, 15363, 32314, 46592, 63414, 80489],
+        lead_out: 95462,
+    };
+    // Digit sums: 2 + 2+0+4 + 4+3+0 + 6+2+1 + 8+4+5 + 1+0+7+3 = 52;
+    // length: 1272 - 2 = 1270 = 0x4F6 seconds
+    assert_eq!(toc.cddb_disc_id(), 0x3404F606);
+}
+
+#[test]
+fn toc_from_metadata() {
+    let tracks = [TrackSpec::new(150, 100), TrackSpec::new(75, 200)];
+    let img = common::write_temp_file("disc_id.nrg",
+                                      &common::build_nrg_v2(&tracks));
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let toc = NrgDiscToc::from_metadata(&metadata)
+        .expect("NrgDiscToc::from_metadata()");
+    assert_eq!(toc.first_track, 1);
+    assert_eq!(toc.offsets, vec![150, 325]);
+    assert_eq!(toc.lead_out, 525);
+}
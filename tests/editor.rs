@@ -0,0 +1,93 @@
+extern crate nrgrip;
+use nrgrip::cdtext::NrgCdText;
+use nrgrip::editor::{self, NrgDaoxEdit};
+use nrgrip::error::NrgError;
+use nrgrip::metadata;
+use nrgrip::metadata::raw;
+use std::io::Cursor;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+#[test]
+fn edit_upc_and_isrcs() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 10)];
+    let image = common::build_nrg_v2(&tracks);
+    let mut fd = Cursor::new(image.clone());
+
+    let edit = NrgDaoxEdit::new()
+        .upc("0123456789012")
+        .isrc(2, "FRXYZ2600001");
+    editor::edit_daox(&mut fd, &edit).expect("edit_daox()");
+    let edited = fd.into_inner();
+    assert_eq!(edited.len(), image.len());
+
+    let metadata = metadata::read_from_slice(&edited)
+        .expect("read_from_slice()");
+    let daox = metadata.daox_chunk().unwrap();
+    assert_eq!(daox.upc(), "0123456789012");
+    assert_eq!(daox.tracks()[0].isrc(), "");
+    assert_eq!(daox.tracks()[1].isrc(), "FRXYZ2600001");
+
+    // Clearing the fields restores the original image
+    let mut fd = Cursor::new(edited);
+    let edit = NrgDaoxEdit::new().upc("").isrc(2, "");
+    editor::edit_daox(&mut fd, &edit).expect("edit_daox()");
+    assert_eq!(fd.into_inner(), image);
+}
+
+#[test]
+fn edit_errors() {
+    let image = common::build_nrg_v2(&[TrackSpec::new(150, 10)]);
+    let mut fd = Cursor::new(image.clone());
+
+    // Nothing is written if any change is invalid
+    let edit = NrgDaoxEdit::new().upc("0123456789012").isrc(2, "FRXYZ2600001");
+    match editor::edit_daox(&mut fd, &edit) {
+        Err(NrgError::TrackNotFound(2)) => {},
+        other => panic!("unexpected result: {:?}", other),
+    }
+    let edit = NrgDaoxEdit::new().isrc(1, "too short");
+    assert!(editor::edit_daox(&mut fd, &edit).is_err());
+    assert_eq!(fd.into_inner(), image);
+}
+
+#[test]
+fn add_cd_text() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 10)];
+    let image = common::build_nrg_v2(&tracks);
+    let mut fd = Cursor::new(image.clone());
+
+    let cd_text = NrgCdText::from_cue_sheet(
+        "TITLE \"Album\"\nFILE \"a.wav\" WAVE\n  TRACK 01 AUDIO\n    \
+         TITLE One\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    \
+         PERFORMER \"Some One\"\n");
+    assert_eq!(cd_text.title, "Album");
+    assert_eq!(cd_text.tracks[1].performer, "Some One");
+    editor::add_cd_text(&mut fd, &cd_text).expect("add_cd_text()");
+    let edited = fd.into_inner();
+
+    let chunk_offset = 170 * SECTOR_SIZE;
+    assert_eq!(edited[..chunk_offset], image[..chunk_offset]);
+    let chunks = raw::read_raw_chunks(&mut Cursor::new(&edited))
+        .expect("read_raw_chunks()");
+    let ids: Vec<&str> = chunks.iter().map(|chunk| chunk.id.as_str())
+        .collect();
+    assert_eq!(ids, ["CUEX", "DAOX", "CDTX", "SINF", "MTYP", "AFNM", "END!"]);
+    assert_eq!(chunks[2].payload, cd_text.to_packs(1).unwrap());
+    let metadata = metadata::read_from_slice(&edited)
+        .expect("read_from_slice()");
+    assert!(metadata.skipped_chunks().is_empty());
+    assert_eq!(metadata.cd_text(), Some(&cd_text));
+    let titles: Vec<Option<String>> = metadata.tracks()
+        .map(|track| track.title).collect();
+    assert_eq!(titles, [Some("One".to_string()), Some("Track 2".to_string())]);
+
+    // CD-Text can't be added twice, nor with a wrong track count
+    let mut fd = Cursor::new(edited);
+    assert!(editor::add_cd_text(&mut fd, &cd_text).is_err());
+    let mut fd = Cursor::new(image.clone());
+    let cd_text = NrgCdText::from_cue_sheet("TITLE Album\nTRACK 01 AUDIO\n");
+    assert!(editor::add_cd_text(&mut fd, &cd_text).is_err());
+    assert_eq!(fd.into_inner(), image);
+}
@@ -0,0 +1,68 @@
+#![cfg(all(feature = "fs", unix))]
+
+extern crate nrgrip;
+use nrgrip::encoder::ExternalEncoder;
+use nrgrip::error::NrgError;
+use nrgrip::extraction::{self, ExtractionOptions};
+use nrgrip::metadata;
+use nrgrip::wav;
+use std::env;
+use std::fs::{self, File};
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+#[test]
+fn parse_command() {
+    let encoder = ExternalEncoder::parse("  flac -s -o {out} -")
+        .expect("parse()");
+    assert_eq!(encoder.get_extension(), "flac");
+    let encoder = ExternalEncoder::parse("/usr/bin/lame -V2 - {out}")
+        .expect("parse()");
+    assert_eq!(encoder.get_extension(), "lame");
+    assert_eq!(encoder.extension("mp3").get_extension(), "mp3");
+    assert!(ExternalEncoder::parse("flac -s -").is_err());
+    assert!(ExternalEncoder::parse(" ").is_err());
+}
+
+#[test]
+fn extract_through_encoder() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("encoder.nrg", &contents);
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    // The "encoder" copies the WAV stream as is
+    let template = env::temp_dir().join("nrgrip-test-encoder-{track}");
+    let encoder = ExternalEncoder::parse("cp /dev/stdin {out}")
+        .expect("parse()")
+        .extension("wav");
+    let options = ExtractionOptions::new()
+        .split_tracks(true)
+        .name_template(&template.to_string_lossy())
+        .encoder(encoder);
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+    assert_eq!(files.len(), 2);
+    assert!(files[1].path.ends_with("nrgrip-test-encoder-02.wav"));
+    let written = fs::read(&files[1].path).expect("fs::read()");
+    let audio = &contents[235 * SECTOR_SIZE..255 * SECTOR_SIZE];
+    assert_eq!(&written[..44],
+               &wav::wav_header(audio.len() as u64, 2).unwrap());
+    assert!(&written[44..] == audio);
+
+    // Failing and missing encoders
+    for command in &["false {out}", "nrgrip-missing-encoder {out}"] {
+        let encoder = ExternalEncoder::parse(command).expect("parse()");
+        let options = options.clone().encoder(encoder);
+        match extraction::extract(&mut fd, &img.to_string_lossy(),
+                                  &metadata, &options) {
+            Err(NrgError::Encoder(_)) => (),
+            result => panic!("{}: {:?}", command, result),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+extern crate nrgrip;
+use nrgrip::error::{NrgError, NrgErrorKind};
+use nrgrip::metadata;
+use std::error::Error;
+use std::fs::File;
+use std::io;
+
+mod common;
+
+#[test]
+fn short_read_context() {
+    // DAOX chunk announcing two tracks, but truncated in the second one
+    let mut img = Vec::new();
+    img.extend_from_slice(b"DAOX");
+    img.extend_from_slice(&(22u32 + 2 * 42).to_be_bytes());
+    img.extend_from_slice(&[0u8; 22]);
+    img.extend_from_slice(&[0u8; 42]);
+    img.extend_from_slice(&[0u8; 10]);
+    img.extend_from_slice(b"NER5");
+    img.extend_from_slice(&0u64.to_be_bytes());
+    let img = common::write_temp_file("errors.nrg", &img);
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let err = metadata::read_nrg_metadata(&mut fd)
+        .expect_err("read_nrg_metadata()");
+
+    let context = err.get_context()
+        .expect("get_context()");
+    assert_eq!(context.chunk_id, Some("DAOX".to_string()));
+    assert_eq!(context.offset, 0x48);
+    assert_eq!(err.to_string(),
+               "short read at offset 0x48 while parsing DAOX track 2");
+
+    // The context's error is the source, itself caused by the I/O error
+    let source = err.source().expect("source()");
+    assert_eq!(source.to_string(), "short read");
+    let io_error = source.source().expect("source()")
+        .downcast_ref::<io::Error>().expect("io::Error");
+    assert_eq!(io_error.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn invalid_chunk_size() {
+    let mut img = Vec::new();
+    img.extend_from_slice(b"CUEX");
+    img.extend_from_slice(&12u32.to_be_bytes());
+    img.extend_from_slice(&[0u8; 12]);
+    img.extend_from_slice(b"NER5");
+    img.extend_from_slice(&0u64.to_be_bytes());
+    let img = common::write_temp_file("chunk_size.nrg", &img);
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let err = metadata::read_nrg_metadata(&mut fd)
+        .expect_err("read_nrg_metadata()");
+    assert_eq!(err.to_string(),
+               "NRG chunk size invalid: 12 bytes for CUEX at offset 0x4 \
+                while parsing CUEX chunk");
+}
+
+#[test]
+fn huge_chunk_size() {
+    let mut img = Vec::new();
+    img.extend_from_slice(b"AFNM");
+    img.extend_from_slice(&0xFFFF_FFF0u32.to_be_bytes());
+    img.extend_from_slice(b"NER5");
+    img.extend_from_slice(&0u64.to_be_bytes());
+
+    let err = metadata::read_from_slice(&img)
+        .expect_err("read_from_slice()");
+    assert_eq!(err.to_string(),
+               "NRG chunk size invalid: 4294967280 bytes for AFNM at offset \
+                0x4 while parsing AFNM chunk");
+}
+
+#[test]
+fn error_kinds() {
+    let kind = |img: &[u8]| metadata::read_from_slice(img)
+        .expect_err("read_from_slice()")
+        .kind();
+    assert_eq!(kind(b"tiny"), NrgErrorKind::NotNrg);
+    assert_eq!(kind(&[0u8; 64]), NrgErrorKind::NotNrg);
+    let mut v1 = vec![0u8; 60];
+    v1.extend_from_slice(b"NERO");
+    v1.extend_from_slice(&0u32.to_be_bytes());
+    assert_eq!(kind(&v1), NrgErrorKind::UnsupportedVersion);
+
+    assert_eq!(NrgError::AudioLength { expected: 2, copied: 1 }.kind(),
+               NrgErrorKind::Partial);
+    assert_eq!(NrgError::TrackNotFound(3).kind(), NrgErrorKind::Usage);
+    let err = NrgError::from(io::Error::from(io::ErrorKind::NotFound));
+    assert_eq!(err.kind(), NrgErrorKind::Io);
+    // A short read, even with a context, means that the image is truncated
+    let err = NrgError::from(io::Error::from(io::ErrorKind::UnexpectedEof))
+        .context(None, 0, "reading".to_string());
+    assert_eq!(err.kind(), NrgErrorKind::Format);
+}
+
+#[test]
+fn exit_codes() {
+    let codes = [
+        (NrgErrorKind::Usage, 1),
+        (NrgErrorKind::NotNrg, 2),
+        (NrgErrorKind::UnsupportedVersion, 3),
+        (NrgErrorKind::Io, 4),
+        (NrgErrorKind::Partial, 5),
+        (NrgErrorKind::Format, 6),
+        (NrgErrorKind::Cancelled, 130),
+    ];
+    for &(kind, code) in &codes {
+        assert_eq!(kind.exit_code(), code, "{:?}", kind);
+    }
+}
+
+#[test]
+fn error_sources() {
+    // The errors of the libraries are kept as sources
+    let err = NrgError::Library {
+        context: "Online lookup error",
+        source: Box::new(io::Error::other("connection refused")),
+    };
+    assert_eq!(err.to_string(), "Online lookup error: connection refused");
+    assert_eq!(err.kind(), NrgErrorKind::Io);
+    assert_eq!(err.source().map(|source| source.to_string()),
+               Some("connection refused".to_string()));
+    assert!(NrgError::Online("no such disc".to_string()).source().is_none());
+
+    // The errors carried through io::Error by the writers come back as is
+    let err = NrgError::from(io::Error::other(NrgError::TrackNotFound(3)));
+    assert_eq!(err.kind(), NrgErrorKind::Usage);
+    assert_eq!(err.to_string(), "Track 3 not found in the image");
+}
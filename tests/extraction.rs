@@ -0,0 +1,775 @@
+#![cfg(feature = "fs")]
+
+extern crate claxon;
+extern crate nrgrip;
+use nrgrip::cancel::CancellationToken;
+use nrgrip::checksums::Crc32;
+use nrgrip::cue_sheet::{self, CueSheetOptions};
+use nrgrip::emphasis::DeEmphasisWriter;
+use nrgrip::error::NrgErrorKind;
+use nrgrip::extraction::{self, AudioFormat, ChannelMode, ExtractionOptions,
+                         GapMode};
+use nrgrip::metadata;
+use nrgrip::metadata::track::Track;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::time::{Duration, Instant};
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(bytes);
+    crc.value()
+}
+
+#[test]
+fn extract_split_wav() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20),
+                  TrackSpec::new(0, 30)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("split.nrg", &contents);
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let template = env::temp_dir().join("nrgrip-test-split-{track}");
+    let options = ExtractionOptions::new()
+        .split_tracks(true)
+        .format(AudioFormat::Wav)
+        .gap_mode(GapMode::Prepend)
+        .name_template(&template.to_string_lossy())
+        .checksums(true)
+        .loudness(true);
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+
+    // Track 1 without its pre-gap, tracks 2 and 3 with their pre-gaps
+    let bounds = [(150, 160), (160, 255), (255, 285)];
+    assert_eq!(files.len(), 3);
+    for (file, &(start, end)) in files.iter().zip(bounds.iter()) {
+        let audio = &contents[start * SECTOR_SIZE..end * SECTOR_SIZE];
+        assert!(file.path.ends_with(".wav"));
+        assert_eq!(file.audio_bytes, audio.len() as u64);
+        assert_eq!(file.crc32, Some(crc32(audio)));
+        assert!(file.loudness.as_ref().is_some_and(|l| l.peak > 0.0));
+
+        let written = fs::read(&file.path).expect("fs::read()");
+        assert_eq!(&written[..4], b"RIFF");
+        assert_eq!(&written[44..], audio);
+    }
+}
+
+#[test]
+fn extract_split_without_gaps() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20),
+                  TrackSpec::new(0, 30)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("split-no-gaps.nrg", &contents);
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let template = env::temp_dir().join("nrgrip-test-split-no-gaps-{track}");
+    let options = ExtractionOptions::new()
+        .split_tracks(true)
+        .gap_mode(GapMode::Discard)
+        .name_template(&template.to_string_lossy());
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+
+    // Track 2's pre-gap, from sector 160 to 235, is left out
+    let bounds = [(150, 160), (235, 255), (255, 285)];
+    assert_eq!(files.len(), 3);
+    for (file, &(start, end)) in files.iter().zip(bounds.iter()) {
+        let written = fs::read(&file.path).expect("fs::read()");
+        assert!(written == contents[start * SECTOR_SIZE..end * SECTOR_SIZE]);
+    }
+}
+
+#[test]
+fn extract_with_offset() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 10)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("offset.nrg", &contents);
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let template = env::temp_dir().join("nrgrip-test-offset");
+    let options = ExtractionOptions::new()
+        .offset(3)
+        .name_template(&template.to_string_lossy());
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+
+    // The audio is read 12 bytes later, and padded with silence at the end
+    let audio = &contents[150 * SECTOR_SIZE + 12..170 * SECTOR_SIZE];
+    let written = fs::read(&files[0].path).expect("fs::read()");
+    assert_eq!(written.len(), 20 * SECTOR_SIZE);
+    assert_eq!(&written[..audio.len()], audio);
+    assert_eq!(&written[audio.len()..], &[0u8; 12]);
+}
+
+#[test]
+fn extract_selected_tracks() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20),
+                  TrackSpec::new(0, 30)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("select.nrg", &contents);
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    // Tracks 1 and 3 concatenated, with the pre-gap of track 2 appended to
+    // track 1
+    let template = env::temp_dir().join("nrgrip-test-select");
+    let options = ExtractionOptions::new()
+        .tracks(&[1, 3])
+        .name_template(&template.to_string_lossy());
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+    assert_eq!(files.len(), 1);
+    let mut audio = contents[150 * SECTOR_SIZE..235 * SECTOR_SIZE].to_vec();
+    audio.extend_from_slice(&contents[255 * SECTOR_SIZE..285 * SECTOR_SIZE]);
+    assert_eq!(fs::read(&files[0].path).expect("fs::read()"), audio);
+
+    let options = options.tracks(&[4]);
+    let err = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                  &metadata, &options)
+        .expect_err("extract()");
+    assert_eq!(err.to_string(), "Track 4 not found in the image");
+}
+
+#[test]
+fn explicit_output_paths() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 10)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("paths.nrg", &contents);
+    let img_path = img.to_string_lossy();
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let audio_path = env::temp_dir().join("nrgrip-test-paths-audio.wav");
+    let options = ExtractionOptions::new()
+        .format(AudioFormat::Wav)
+        .output_path(&audio_path);
+    let files = extraction::extract(&mut fd, &img_path, &metadata, &options)
+        .expect("extract()");
+    assert_eq!(files[0].path, audio_path.to_string_lossy());
+    assert!(extraction::extract(&mut fd, &img_path, &metadata,
+                                &options.split_tracks(true)).is_err());
+
+    let cue_path = env::temp_dir().join("nrgrip-test-paths-sheet.cue");
+    let cue_options = CueSheetOptions::new()
+        .output_path(&cue_path)
+        .audio_file(&audio_path);
+    cue_sheet::write_cue_sheet(&img_path, &metadata, &[], &cue_options)
+        .expect("write_cue_sheet()");
+    let cue = fs::read_to_string(&cue_path).expect("fs::read_to_string()");
+    assert!(cue.starts_with(
+        "FILE \"nrgrip-test-paths-audio.wav\" WAVE\n"));
+}
+
+#[test]
+fn title_names() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 10),
+                  TrackSpec::new(0, 10)];
+    let mut contents = common::build_nrg_v2(&tracks);
+    // Give tracks 2 and 3 the same title
+    let afnm = contents.windows(11).position(|name| name == b"Track 3.wav")
+        .expect("AFNM entry");
+    contents[afnm + 6] = b'2';
+    let img = common::write_temp_file("titles.nrg", &contents);
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let template = env::temp_dir().join("nrgrip-test-{title}");
+    let options = ExtractionOptions::new()
+        .split_tracks(true)
+        .name_template(&template.to_string_lossy());
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+    let names: Vec<String> = files.iter()
+        .map(|file| file.path.rsplit(['/', '\\']).next().unwrap().to_string())
+        .collect();
+    assert_eq!(names, ["nrgrip-test-Track 1.raw", "nrgrip-test-Track 2.raw",
+                       "nrgrip-test-Track 2 (2).raw"]);
+}
+
+#[test]
+fn normalized_names() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 10)];
+    let mut metadata = metadata::read_from_slice(
+        &common::build_nrg_v2(&tracks)).expect("read_from_slice()");
+    metadata.set_titles(&["Cafe\u{301}".to_string(),
+                          "Ærø: \u{201C}Live\u{201D}".to_string()]);
+    let tracks: Vec<Track> = metadata.tracks().collect();
+    assert_eq!(tracks[0].title.as_deref(), Some("Café"));
+
+    let options = ExtractionOptions::new()
+        .split_tracks(true)
+        .name_template("{title}");
+    let name = |options: &ExtractionOptions, track| {
+        extraction::output_file_name("a.nrg", options, Some(track))
+            .expect("output_file_name()")
+    };
+    assert_eq!(name(&options, &tracks[0]), "Café.raw");
+    let options = options.ascii_names(true);
+    assert_eq!(name(&options, &tracks[0]), "Cafe.raw");
+    assert_eq!(name(&options, &tracks[1]), "AEro_ _Live_.raw");
+}
+
+#[test]
+fn sanitize_file_names() {
+    assert_eq!(extraction::sanitize_file_name("AC/DC: Live?"), "AC_DC_ Live_");
+    assert_eq!(extraction::sanitize_file_name(" Intro... "), "Intro");
+    assert_eq!(extraction::sanitize_file_name("tab\there"), "tab_here");
+    assert_eq!(extraction::sanitize_file_name("con"), "con_");
+    assert_eq!(extraction::sanitize_file_name("LPT1.mix"), "LPT1_.mix");
+    assert_eq!(extraction::sanitize_file_name("COM0"), "COM0");
+    assert_eq!(extraction::sanitize_file_name("C€"), "C€");
+    assert_eq!(extraction::sanitize_file_name("Aé1"), "Aé1");
+    assert_eq!(extraction::sanitize_file_name(". ."), "");
+    let long = "é".repeat(150);
+    assert_eq!(extraction::sanitize_file_name(&long), "é".repeat(100));
+}
+
+#[test]
+fn de_emphasis() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 10)];
+    let mut contents = common::build_nrg_v2(&tracks);
+
+    // Flag track 2 with pre-emphasis: its CUEX entries follow the lead-in
+    // and the two entries of track 1
+    let cuex = 170 * SECTOR_SIZE + 8;
+    contents[cuex + 3 * 8] = 0x11;
+    contents[cuex + 4 * 8] = 0x11;
+    let img = common::write_temp_file("emphasis.nrg", &contents);
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    let flags: Vec<bool> = metadata.tracks()
+        .map(|track| track.has_pre_emphasis())
+        .collect();
+    assert_eq!(flags, [false, true]);
+
+    let template = env::temp_dir().join("nrgrip-test-emphasis");
+    let options = ExtractionOptions::new()
+        .de_emphasis(true)
+        .name_template(&template.to_string_lossy());
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+
+    // Track 1 is copied unchanged, track 2 is filtered
+    let written = fs::read(&files[0].path).expect("fs::read()");
+    assert_eq!(written.len(), 20 * SECTOR_SIZE);
+    assert_eq!(&written[..10 * SECTOR_SIZE],
+               &contents[150 * SECTOR_SIZE..160 * SECTOR_SIZE]);
+    assert_ne!(&written[10 * SECTOR_SIZE..],
+               &contents[160 * SECTOR_SIZE..170 * SECTOR_SIZE]);
+}
+
+#[test]
+fn de_emphasis_filter() {
+    // A constant signal goes through unchanged, while the highest frequency
+    // is attenuated by 10.5 dB
+    let mut frames = Vec::new();
+    for i in 0..1000 {
+        let sample: i16 = if i % 2 == 0 { 10000 } else { -10000 };
+        frames.extend_from_slice(&1000i16.to_le_bytes());
+        frames.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    let mut writer = DeEmphasisWriter::new(Vec::new());
+    writer.write_all(&frames[..2001]).expect("write_all()");
+    writer.write_all(&frames[2001..]).expect("write_all()");
+    let filtered = writer.finish().expect("finish()");
+    assert_eq!(filtered.len(), frames.len());
+
+    let sample = |frame: usize, channel: usize| {
+        let pos = frame * 4 + channel * 2;
+        i16::from_le_bytes([filtered[pos], filtered[pos + 1]])
+    };
+    assert_eq!(sample(998, 0), 1000);
+    assert!((2990..3010).contains(&sample(997, 1).abs()));
+    assert!((2990..3010).contains(&sample(998, 1).abs()));
+}
+
+#[test]
+fn channel_modes() {
+    let tracks = [TrackSpec::new(150, 10)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("channels.nrg", &contents);
+    let audio = &contents[150 * SECTOR_SIZE..160 * SECTOR_SIZE];
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let template = env::temp_dir().join("nrgrip-test-channels");
+    let options = ExtractionOptions::new()
+        .channel_mode(ChannelMode::Swapped)
+        .name_template(&template.to_string_lossy());
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+    let written = fs::read(&files[0].path).expect("fs::read()");
+    assert_eq!(written.len(), audio.len());
+    for (out, frame) in written.chunks(4).zip(audio.chunks(4)) {
+        assert_eq!(out, [frame[2], frame[3], frame[0], frame[1]]);
+    }
+
+    let options = options
+        .channel_mode(ChannelMode::Mono)
+        .format(AudioFormat::Wav)
+        .checksums(true);
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+    let written = fs::read(&files[0].path).expect("fs::read()");
+    assert_eq!(files[0].audio_bytes, audio.len() as u64 / 2);
+    assert_eq!(files[0].crc32, Some(crc32(&written[44..])));
+    assert_eq!(&written[22..24], &1u16.to_le_bytes()); // channels
+    assert_eq!(&written[40..44], &(audio.len() as u32 / 2).to_le_bytes());
+    for (out, frame) in written[44..].chunks(2).zip(audio.chunks(4)) {
+        let left = i16::from_le_bytes([frame[0], frame[1]]);
+        let right = i16::from_le_bytes([frame[2], frame[3]]);
+        let mono = (i32::from(left) + i32::from(right)) >> 1;
+        assert_eq!(out, (mono as i16).to_le_bytes());
+    }
+}
+
+#[test]
+fn extract_small_buffer() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("buffer.nrg", &contents);
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    // Neither the audio data nor the sectors are multiples of the buffer
+    // size; sizes below one sector are raised to one sector
+    for &size in &[1000, 3 * SECTOR_SIZE + 5] {
+        let path = env::temp_dir()
+            .join(format!("nrgrip-test-buffer-{}.raw", size));
+        let options = ExtractionOptions::new()
+            .buffer_size(size)
+            .output_path(&path);
+        extraction::extract(&mut fd, &img.to_string_lossy(), &metadata,
+                            &options)
+            .expect("extract()");
+        let written = fs::read(&path).expect("fs::read()");
+        assert_eq!(written, &contents[150 * SECTOR_SIZE..180 * SECTOR_SIZE]);
+    }
+}
+
+#[test]
+fn extract_stripped_subchannel() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let raw96 = common::make_raw96_image(&contents, 255);
+    let img = common::write_temp_file("strip.nrg", &raw96);
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    // Batches of one sector, of several sectors with a remainder, and of the
+    // whole audio data
+    for &size in &[1000, 4 * 2448 + 5, 1 << 20] {
+        let path = env::temp_dir()
+            .join(format!("nrgrip-test-strip-{}.raw", size));
+        let options = ExtractionOptions::new()
+            .buffer_size(size)
+            .output_path(&path);
+        let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                        &metadata, &options)
+            .expect("extract()");
+        assert_eq!(files[0].audio_bytes, 105 * SECTOR_SIZE as u64);
+        let written = fs::read(&path).expect("fs::read()");
+        assert!(written == contents[150 * SECTOR_SIZE..255 * SECTOR_SIZE]);
+    }
+}
+
+#[test]
+fn extract_byte_range() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let raw96 = common::make_raw96_image(&contents, 255);
+    let sector = SECTOR_SIZE as u64;
+
+    // The range is widened to whole sectors
+    let mut out = Vec::new();
+    let range = extraction::extract_range(
+        &mut Cursor::new(&contents), 150 * sector + 10, 160 * sector - 10,
+        &mut out, &ExtractionOptions::new())
+        .expect("extract_range()");
+    assert_eq!(range, 150 * sector..160 * sector);
+    assert!(out == contents[150 * SECTOR_SIZE..160 * SECTOR_SIZE]);
+
+    // The sub-channel is stripped, or kept
+    let options = ExtractionOptions::new().sector_size(2448).buffer_size(5000);
+    out.clear();
+    let range = extraction::extract_range(
+        &mut Cursor::new(&raw96), 150 * 2448, 255 * 2448, &mut out, &options)
+        .expect("extract_range()");
+    assert_eq!(range, 150 * 2448..255 * 2448);
+    assert!(out == contents[150 * SECTOR_SIZE..255 * SECTOR_SIZE]);
+
+    out.clear();
+    extraction::extract_range(&mut Cursor::new(&raw96), 0, 2448, &mut out,
+                              &options.strip_subchannel(false))
+        .expect("extract_range()");
+    assert!(out == raw96[..2448]);
+
+    // Past the end of the image
+    let len = contents.len() as u64;
+    assert!(extraction::extract_range(
+        &mut Cursor::new(&contents), len, len + sector, &mut out,
+        &ExtractionOptions::new()).is_err());
+}
+
+/// Reader and writer handling at most 1000 bytes per call, and interrupted
+/// every other call, as pipes and network file systems may do.
+struct Flaky<T> {
+    inner: T,
+    interrupt: bool,
+}
+
+impl<T> Flaky<T> {
+    fn new(inner: T) -> Flaky<T> {
+        Flaky { inner, interrupt: false }
+    }
+
+    fn interrupted(&mut self) -> bool {
+        self.interrupt = !self.interrupt;
+        self.interrupt
+    }
+}
+
+impl<T: Read> Read for Flaky<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.interrupted() {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        let len = buf.len().min(1000);
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+impl<T: Seek> Seek for Flaky<T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl<T: Write> Write for Flaky<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.interrupted() {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        let len = buf.len().min(1000);
+        self.inner.write(&buf[..len])
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[test]
+fn extract_with_short_reads() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let raw96 = common::make_raw96_image(&contents, 255);
+    let sector = SECTOR_SIZE as u64;
+
+    let mut out = Flaky::new(Vec::new());
+    extraction::extract_range(
+        &mut Flaky::new(Cursor::new(&contents)), 150 * sector, 255 * sector,
+        &mut out, &ExtractionOptions::new())
+        .expect("extract_range()");
+    assert!(out.inner == contents[150 * SECTOR_SIZE..255 * SECTOR_SIZE]);
+
+    let mut out = Flaky::new(Vec::new());
+    extraction::extract_range(
+        &mut Flaky::new(Cursor::new(&raw96)), 150 * 2448, 255 * 2448,
+        &mut out, &ExtractionOptions::new().sector_size(2448))
+        .expect("extract_range()");
+    assert!(out.inner == contents[150 * SECTOR_SIZE..255 * SECTOR_SIZE]);
+}
+
+#[test]
+fn ignore_read_errors() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("damaged.nrg", &contents);
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    // Cut the image in the middle of sector 250, i.e. LBA 100
+    let truncated = 250 * SECTOR_SIZE + SECTOR_SIZE / 2;
+    fs::OpenOptions::new().write(true).open(&img)
+        .and_then(|fd| fd.set_len(truncated as u64))
+        .expect("set_len()");
+
+    let template = env::temp_dir().join("nrgrip-test-damaged");
+    let options = ExtractionOptions::new()
+        .name_template(&template.to_string_lossy());
+    assert!(extraction::extract(&mut fd, &img.to_string_lossy(), &metadata,
+                                &options).is_err());
+
+    let options = options.ignore_errors(true);
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+    assert_eq!(files[0].bad_sectors, [100, 101, 102, 103, 104]);
+    let written = fs::read(&files[0].path).expect("fs::read()");
+    // The incomplete sector is replaced as a whole
+    let readable = 100 * SECTOR_SIZE;
+    assert_eq!(written.len(), 105 * SECTOR_SIZE);
+    assert!(written[..readable]
+            == contents[150 * SECTOR_SIZE..250 * SECTOR_SIZE]);
+    assert!(written[readable..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn verify_writes() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("verify.nrg", &contents);
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let path = env::temp_dir().join("nrgrip-test-verify.wav");
+    let options = ExtractionOptions::new()
+        .format(AudioFormat::Wav)
+        .verify(true)
+        .output_path(&path);
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+    assert_eq!(files[0].verified, Some(true));
+    assert!(files[0].to_string().ends_with(", verified"));
+
+    // Unverified by default
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options.clone().verify(false))
+        .expect("extract()");
+    assert_eq!(files[0].verified, None);
+
+    // Encoded files can't be verified
+    assert!(extraction::extract(&mut fd, &img.to_string_lossy(), &metadata,
+                                &options.format(AudioFormat::Flac))
+            .is_err());
+}
+
+#[test]
+fn atomic_writes() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("atomic.nrg", &contents);
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let dir = env::temp_dir();
+    let path = dir.join("nrgrip-test-atomic.raw");
+    let temporary = dir.join(".nrgrip-test-atomic.part.raw");
+    let _ = fs::remove_file(&path);
+    let options = ExtractionOptions::new().output_path(&path);
+    extraction::extract(&mut fd, &img.to_string_lossy(), &metadata,
+                        &options)
+        .expect("extract()");
+    assert!(fs::read(&path).expect("fs::read()")
+            == contents[150 * SECTOR_SIZE..255 * SECTOR_SIZE]);
+    assert!(!temporary.exists());
+
+    // The audio data of a truncated image is missing: no file is left
+    let truncated = common::write_temp_file("atomic-truncated.nrg",
+                                            &contents[..200 * SECTOR_SIZE]);
+    let mut fd = File::open(&truncated)
+        .expect("File::open()");
+    let _ = fs::remove_file(&path);
+    assert!(extraction::extract(&mut fd, &truncated.to_string_lossy(),
+                                &metadata, &options)
+            .is_err());
+    assert!(!path.exists());
+    assert!(!temporary.exists());
+
+    // Unless written directly
+    assert!(extraction::extract(&mut fd, &truncated.to_string_lossy(),
+                                &metadata, &options.atomic(false))
+            .is_err());
+    assert!(path.exists());
+}
+
+#[test]
+fn rate_limit() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let sector = SECTOR_SIZE as u64;
+
+    // 105 sectors at 1 MB/s take about a quarter of a second
+    let options = ExtractionOptions::new()
+        .buffer_size(SECTOR_SIZE)
+        .rate_limit(1_000_000);
+    let mut out = Vec::new();
+    let start = Instant::now();
+    extraction::extract_range(&mut Cursor::new(&contents), 150 * sector,
+                              255 * sector, &mut out, &options)
+        .expect("extract_range()");
+    assert!(start.elapsed() >= Duration::from_millis(240));
+    assert!(out == contents[150 * SECTOR_SIZE..255 * SECTOR_SIZE]);
+}
+
+/// Writer cancelling `token` after its first write.
+struct CancellingWriter {
+    written: Vec<u8>,
+    token: CancellationToken,
+}
+
+impl Write for CancellingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.token.cancel();
+        self.written.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn cancel_extraction() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let sector = SECTOR_SIZE as u64;
+
+    // The copy stops at the next buffer
+    let token = CancellationToken::new();
+    let options = ExtractionOptions::new()
+        .buffer_size(SECTOR_SIZE)
+        .cancellation(&token);
+    let mut out = CancellingWriter { written: Vec::new(), token };
+    let err = extraction::extract_range(
+        &mut Cursor::new(&contents), 150 * sector, 255 * sector, &mut out,
+        &options)
+        .expect_err("extract_range()");
+    assert_eq!(err.kind(), NrgErrorKind::Cancelled);
+    assert_eq!(out.written.len(), SECTOR_SIZE);
+
+    // Nothing is written once cancelled
+    let img = common::write_temp_file("cancel.nrg", &contents);
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    let path = env::temp_dir().join("nrgrip-test-cancel.raw");
+    let _ = fs::remove_file(&path);
+    let token = CancellationToken::new();
+    token.clone().cancel();
+    assert!(token.is_cancelled());
+    let options = ExtractionOptions::new()
+        .output_path(&path)
+        .cancellation(&token);
+    let err = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                  &metadata, &options)
+        .expect_err("extract()");
+    assert_eq!(err.kind(), NrgErrorKind::Cancelled);
+    assert!(!path.exists());
+}
+
+#[test]
+fn extract_flac() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20),
+                  TrackSpec::new(0, 30)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("flac.nrg", &contents);
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    let path = env::temp_dir().join("nrgrip-test-flac.flac");
+    let options = ExtractionOptions::new()
+        .format(AudioFormat::Flac)
+        .checksums(true)
+        .output_path(&path);
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+    let audio = &contents[150 * SECTOR_SIZE..285 * SECTOR_SIZE];
+    assert_eq!(files[0].audio_bytes, audio.len() as u64);
+    assert_eq!(files[0].crc32, Some(crc32(audio)));
+
+    let flac = fs::read(&path).expect("fs::read()");
+    let mut reader = claxon::FlacReader::new(&flac[..])
+        .expect("FlacReader::new()");
+    let decoded: Vec<u8> = reader.samples()
+        .flat_map(|sample| (sample.expect("samples()") as i16).to_le_bytes())
+        .collect();
+    assert!(decoded == audio);
+
+    // Tracks starting at 0, 10 and 105 sectors, lead-out at 135 sectors
+    let blocks = common::flac_blocks(&flac);
+    let &(_, cue_sheet) = blocks.iter().find(|&&(kind, _)| kind == 5)
+        .expect("CUESHEET block");
+    assert_eq!(cue_sheet[395], 4);
+    let mut pos = 396;
+    for &(start, number, indexes) in &[(0u64, 1, 1), (10, 2, 2),
+                                       (105, 3, 1), (135, 170, 0)] {
+        assert_eq!(&cue_sheet[pos..pos + 8], &(start * 588).to_be_bytes());
+        assert_eq!(cue_sheet[pos + 8], number);
+        assert_eq!(cue_sheet[pos + 35], indexes);
+        pos += 36 + 12 * usize::from(indexes);
+    }
+    assert_eq!(pos, cue_sheet.len());
+
+    // No cue sheet in split files
+    let template = env::temp_dir().join("nrgrip-test-flac-{track}");
+    let options = ExtractionOptions::new()
+        .format(AudioFormat::Flac)
+        .split_tracks(true)
+        .name_template(&template.to_string_lossy());
+    let files = extraction::extract(&mut fd, &img.to_string_lossy(),
+                                    &metadata, &options)
+        .expect("extract()");
+    let flac = fs::read(&files[1].path).expect("fs::read()");
+    assert!(common::flac_blocks(&flac).iter().all(|&(kind, _)| kind != 5));
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "ffi")]
+
+extern crate nrgrip;
+use nrgrip::ffi::*;
+use std::env;
+use std::ffi::{CStr, CString};
+use std::fs;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+#[test]
+fn open_and_extract() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("ffi.nrg", &contents);
+    let img = CString::new(img.to_string_lossy().into_owned())
+        .expect("CString::new()");
+    let out = env::temp_dir().join("nrgrip-test-ffi-02.raw");
+    let out_c = CString::new(out.to_string_lossy().into_owned())
+        .expect("CString::new()");
+
+    unsafe {
+        let image = nrgrip_open(img.as_ptr());
+        assert!(!image.is_null());
+        assert_eq!(nrgrip_get_track_count(image), 2);
+
+        let mut info = NrgTrackInfo {
+            number: 0, mode: 0, sector_size: 0, position_sectors: 0,
+            pregap_sectors: 0, sector_count: 0,
+        };
+        assert_eq!(nrgrip_get_track_info(image, 1, &mut info), 0);
+        assert_eq!((info.number, info.pregap_sectors, info.sector_count),
+                   (2, 75, 20));
+
+        assert_eq!(nrgrip_get_track_info(image, 2, &mut info), -1);
+        let error = CStr::from_ptr(nrgrip_last_error());
+        assert!(error.to_string_lossy().ends_with("No track with index 2"));
+
+        assert_eq!(nrgrip_extract_track(image, 1, out_c.as_ptr()), 0);
+        nrgrip_close(image);
+    }
+
+    let audio = fs::read(&out).expect("fs::read()");
+    assert_eq!(audio, &contents[235 * SECTOR_SIZE..255 * SECTOR_SIZE]);
+}
@@ -0,0 +1,142 @@
+extern crate claxon;
+extern crate md5;
+extern crate nrgrip;
+use md5::{Digest, Md5};
+use nrgrip::flac::{FlacCueTrack, FlacWriter};
+use std::f64::consts;
+use std::io::{Cursor, Write};
+
+mod common;
+
+/// Encodes `pcm` in FLAC, in `channels` channels, through writes of
+/// `chunk` bytes.
+fn encode(pcm: &[u8], channels: u16, chunk: usize,
+          cue_tracks: &[FlacCueTrack]) -> Vec<u8> {
+    let samples = (pcm.len() / 2 / usize::from(channels)) as u64;
+    let mut writer = FlacWriter::new(Cursor::new(Vec::new()), channels,
+                                     samples, cue_tracks)
+        .expect("FlacWriter::new()");
+    for bytes in pcm.chunks(chunk) {
+        writer.write_all(bytes).expect("write_all()");
+    }
+    writer.finish().expect("finish()").into_inner()
+}
+
+/// Decodes `flac` and checks that it holds the samples of `pcm`.
+fn check_decoded(flac: &[u8], pcm: &[u8], channels: u16) {
+    let mut reader = claxon::FlacReader::new(flac)
+        .expect("FlacReader::new()");
+    let info = reader.streaminfo();
+    assert_eq!(info.sample_rate, 44100);
+    assert_eq!(info.channels, u32::from(channels));
+    assert_eq!(info.bits_per_sample, 16);
+    let samples = pcm.len() as u64 / 2 / u64::from(channels);
+    assert_eq!(info.samples, Some(samples));
+    assert_eq!(&info.md5sum[..], &Md5::digest(pcm)[..]);
+
+    let decoded: Vec<u8> = reader.samples()
+        .flat_map(|sample| (sample.expect("samples()") as i16).to_le_bytes())
+        .collect();
+    assert!(decoded == pcm);
+}
+
+#[test]
+fn encode_stereo() {
+    // Silence, a sine wave, the pattern of the test images and full-scale
+    // noise, with an incomplete last frame
+    let mut pcm = vec![0u8; 5000 * 4];
+    for i in 0..20000 {
+        let phase = f64::from(i) / 44100.0 * consts::TAU;
+        let left = (f64::from(i16::MAX) * (phase * 440.0).sin()) as i16;
+        let right = (f64::from(i16::MAX) * (phase * 660.0).sin()) as i16;
+        pcm.extend_from_slice(&left.to_le_bytes());
+        pcm.extend_from_slice(&right.to_le_bytes());
+    }
+    let contents = common::build_nrg_v2(&[common::TrackSpec::new(0, 20)]);
+    pcm.extend_from_slice(&contents[..20 * common::SECTOR_SIZE]);
+    let mut state = 1u32;
+    for _ in 0..30000 {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        pcm.extend_from_slice(&state.to_le_bytes()[1..3]);
+    }
+
+    let flac = encode(&pcm, 2, 10000, &[]);
+    assert!(flac.len() < pcm.len());
+    check_decoded(&flac, &pcm, 2);
+
+    // One seek point every 10 seconds, in the first frame here
+    let blocks = common::flac_blocks(&flac);
+    assert_eq!(blocks.iter().map(|&(kind, _)| kind).collect::<Vec<_>>(),
+               vec![0, 3]);
+    assert_eq!(blocks[1].1, &[0; 8 + 8].iter().chain(&[0x12, 0x00])
+               .cloned().collect::<Vec<u8>>()[..]);
+}
+
+#[test]
+fn encode_mono() {
+    let pcm: Vec<u8> = (0..10000i16)
+        .flat_map(|i| (i.wrapping_mul(37) % 3000).to_le_bytes())
+        .collect();
+    check_decoded(&encode(&pcm, 1, 4096, &[]), &pcm, 1);
+    check_decoded(&encode(&pcm[..2], 1, 2, &[]), &pcm[..2], 1);
+}
+
+#[test]
+fn embedded_cue_sheet() {
+    let pcm = vec![0u8; 3000 * 2352];
+    let tracks = [
+        FlacCueTrack {
+            number: 1,
+            isrc: Some("FRXXX2600001".to_string()),
+            audio: true,
+            pre_emphasis: false,
+            index0: None,
+            index1: 0,
+        },
+        FlacCueTrack {
+            number: 2,
+            isrc: None,
+            audio: true,
+            pre_emphasis: true,
+            index0: Some(1000 * 588),
+            index1: 1150 * 588,
+        },
+    ];
+    let flac = encode(&pcm, 2, 1 << 20, &tracks);
+    check_decoded(&flac, &pcm, 2);
+
+    let blocks = common::flac_blocks(&flac);
+    assert_eq!(blocks[2].0, 5);
+    let cue_sheet = blocks[2].1;
+    assert_eq!(&cue_sheet[128..136], &88200u64.to_be_bytes());
+    assert_eq!(cue_sheet[136], 0x80);
+    assert_eq!(cue_sheet[395], 3);
+    let track1 = &cue_sheet[396..];
+    assert_eq!(&track1[..9], &[0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(&track1[9..21], b"FRXXX2600001");
+    assert_eq!(track1[35], 1);
+    let track2 = &track1[36 + 12..];
+    assert_eq!(&track2[..8], &(1000u64 * 588).to_be_bytes());
+    assert_eq!(track2[21], 0x40);
+    assert_eq!(track2[35], 2);
+    assert_eq!(&track2[36..45], &[0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(&track2[48..56], &(150u64 * 588).to_be_bytes());
+    let lead_out = &track2[36 + 2 * 12..];
+    assert_eq!(&lead_out[..8], &(3000u64 * 588).to_be_bytes());
+    assert_eq!(lead_out[8], 170);
+
+    // Unaligned tracks
+    let mut unaligned = tracks.clone();
+    unaligned[1].index1 += 1;
+    assert!(FlacWriter::new(Cursor::new(Vec::new()), 2, 3000 * 588,
+                            &unaligned).is_err());
+}
+
+#[test]
+fn wrong_length() {
+    let mut writer = FlacWriter::new(Cursor::new(Vec::new()), 2, 10, &[])
+        .expect("FlacWriter::new()");
+    assert!(writer.write_all(&[0; 44]).is_err());
+    writer.write_all(&[0; 36]).expect("write_all()");
+    assert!(writer.finish().is_err());
+}
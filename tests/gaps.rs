@@ -0,0 +1,26 @@
+extern crate nrgrip;
+use nrgrip::{gaps, metadata};
+use std::fs::File;
+
+mod common;
+use common::TrackSpec;
+
+#[test]
+fn detect_gaps() {
+    let mut track2 = TrackSpec::new(75, 100);
+    track2.cuex_index0 = false;
+    let tracks = [TrackSpec::new(150, 100), track2, TrackSpec::new(0, 100)];
+    let img = common::write_temp_file("gaps.nrg",
+                                      &common::build_nrg_v2(&tracks));
+
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    let gaps = gaps::detect_gaps(&mut fd, &metadata)
+        .expect("detect_gaps()");
+
+    assert_eq!(gaps.len(), 2);
+    assert_eq!((gaps[0].track_number, gaps[0].sectors), (2, 75));
+    assert_eq!((gaps[1].track_number, gaps[1].sectors), (3, 0));
+}
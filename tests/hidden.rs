@@ -0,0 +1,73 @@
+extern crate nrgrip;
+#[cfg(feature = "fs")]
+use nrgrip::extraction::{self, ExtractionOptions};
+use nrgrip::hidden;
+use nrgrip::metadata;
+use std::convert::TryInto;
+#[cfg(feature = "fs")]
+use std::env;
+#[cfg(feature = "fs")]
+use std::fs::{self, File};
+use std::io::Cursor;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+/// Inserts `data` between the audio data and the chunks of the image `img`.
+fn insert_before_chunks(img: &mut Vec<u8>, data: &[u8]) {
+    let footer = img.len() - 8;
+    let chunk_offset = u64::from_be_bytes(img[footer..].try_into().unwrap());
+    let moved = chunk_offset + data.len() as u64;
+    img[footer..].copy_from_slice(&moved.to_be_bytes());
+    let pos = chunk_offset as usize;
+    img.splice(pos..pos, data.iter().cloned());
+}
+
+#[test]
+fn detect_hidden_data() {
+    let mut img = common::build_nrg_v2(&[TrackSpec::new(150, 10)]);
+    let metadata = metadata::read_from_slice(&img)
+        .expect("read_from_slice()");
+    assert_eq!(hidden::detect_hidden_data(&mut Cursor::new(&img), &metadata)
+               .expect("detect_hidden_data()"), None);
+
+    // Silence is not reported
+    let mut silent = img.clone();
+    insert_before_chunks(&mut silent, &vec![0; 2 * SECTOR_SIZE]);
+    let metadata = metadata::read_from_slice(&silent)
+        .expect("read_from_slice()");
+    assert_eq!(hidden::detect_hidden_data(&mut Cursor::new(&silent),
+                                          &metadata)
+               .expect("detect_hidden_data()"), None);
+
+    let mut data = vec![0; 2 * SECTOR_SIZE];
+    data[SECTOR_SIZE + 4..SECTOR_SIZE + 10].copy_from_slice(&[1; 6]);
+    insert_before_chunks(&mut img, &data);
+    let metadata = metadata::read_from_slice(&img)
+        .expect("read_from_slice()");
+    let hidden = hidden::detect_hidden_data(&mut Cursor::new(&img),
+                                            &metadata)
+        .expect("detect_hidden_data()")
+        .expect("hidden data");
+    let end = 160 * SECTOR_SIZE as u64;
+    assert_eq!(hidden.range, end..end + 2 * SECTOR_SIZE as u64);
+    assert_eq!(hidden.sector_count(), 2);
+    assert_eq!(hidden.nonzero_bytes, 6);
+
+    // The hidden data is extracted next to the tracks
+    #[cfg(feature = "fs")]
+    {
+        let img_path = common::write_temp_file("hidden.nrg", &img);
+        let mut fd = File::open(&img_path).expect("File::open()");
+        let options = ExtractionOptions::new().output_dir(&env::temp_dir());
+        let file = extraction::extract_hidden_data(
+            &mut fd, &img_path.to_string_lossy(), &hidden, &options)
+            .expect("extract_hidden_data()");
+        assert_eq!(Path::new(&file.path),
+                   env::temp_dir().join("nrgrip-test-hidden-hidden.raw"));
+        assert_eq!(file.sectors, 2);
+        assert!(fs::read(&file.path).expect("fs::read()") == data);
+    }
+}
@@ -0,0 +1,57 @@
+extern crate nrgrip;
+use nrgrip::error::NrgError;
+use nrgrip::image::NrgImage;
+use std::io::Cursor;
+
+mod common;
+use common::{SECTOR_SIZE, TrackSpec};
+
+#[test]
+fn read_sectors() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 10)];
+    let img = common::build_nrg_v2(&tracks);
+    let mut image = NrgImage::new(Cursor::new(img.clone()))
+        .expect("NrgImage::new()");
+    assert_eq!(image.lba_range(), -150..95);
+
+    // The first track's pre-gap
+    let sector = image.read_sector(-150).expect("read_sector()");
+    assert_eq!((sector.lba, sector.track_number), (-150, 1));
+    assert!(sector.data.iter().all(|&byte| byte == 0));
+    assert_eq!(sector.subchannel, None);
+
+    let sector = image.read_sector(3).expect("read_sector()");
+    let offset = 153 * SECTOR_SIZE;
+    assert_eq!(&sector.data[..], &img[offset..offset + SECTOR_SIZE]);
+
+    // The second track's pre-gap, then its first sector
+    let sector = image.read_sector(10).expect("read_sector()");
+    assert_eq!(sector.track_number, 2);
+    assert!(sector.data.iter().all(|&byte| byte == 0));
+    let sector = image.read_sector(85).expect("read_sector()");
+    assert_eq!(sector.track_number, 2);
+    assert_eq!(sector.data[..4], [1, 2, 3, 4]);
+    assert_eq!(image.sector_offset(85).map(|(offset, _)| offset),
+               Some(235 * SECTOR_SIZE as u64));
+
+    for &lba in &[-151, 95] {
+        match image.read_sector(lba) {
+            Err(NrgError::SectorNotFound(missing)) => assert_eq!(missing, lba),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn read_sectors_with_subchannel() {
+    let tracks = [TrackSpec::new(150, 10)];
+    let img = common::build_nrg_v2(&tracks);
+    let raw96 = common::make_raw96_image(&img, 160);
+    let mut image = NrgImage::new(Cursor::new(raw96))
+        .expect("NrgImage::new()");
+
+    let sector = image.read_sector(9).expect("read_sector()");
+    let offset = 159 * SECTOR_SIZE;
+    assert_eq!(&sector.data[..], &img[offset..offset + SECTOR_SIZE]);
+    assert_eq!(sector.subchannel, Some([0xEE; 96]));
+}
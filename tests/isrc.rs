@@ -0,0 +1,79 @@
+extern crate nrgrip;
+use nrgrip::editor::{self, NrgDaoxEdit};
+use nrgrip::isrc::Isrc;
+use nrgrip::metadata;
+use nrgrip::text;
+use std::io::Cursor;
+
+mod common;
+use common::TrackSpec;
+
+#[test]
+fn parse() {
+    let isrc = Isrc::parse("FRZ039800212").expect("Isrc::parse()");
+    assert_eq!(isrc.country_code(), "FR");
+    assert_eq!(isrc.registrant_code(), "Z03");
+    assert_eq!(isrc.year(), 98);
+    assert_eq!(isrc.designation(), 212);
+    assert_eq!(isrc.bogus_reason(), None);
+    assert_eq!(isrc.to_string(), "FR-Z03-98-00212");
+    assert_eq!(format!("{:#}", isrc), "FRZ039800212");
+    assert_eq!(Isrc::parse("FR-Z03-98-00212").ok(), Some(isrc));
+
+    for code in &["", "FRZ03980021", "frZ039800212", "F1Z039800212",
+                  "FRZ0?9800212", "FRZ03A800212", "FRZ0398002I2",
+                  "FRZ-03-98-00212", "FR-Z03-9800212"] {
+        assert!(Isrc::parse(code).is_err(), "{:?} is valid", code);
+    }
+
+    let bogus = Isrc::parse("FR0009800212").expect("Isrc::parse()");
+    assert_eq!(bogus.bogus_reason(), Some("the registrant code is 000"));
+    let bogus = Isrc::parse("FRZ039800000").expect("Isrc::parse()");
+    assert_eq!(bogus.bogus_reason(), Some("the designation code is 00000"));
+}
+
+#[test]
+fn metadata_isrcs() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 10),
+                  TrackSpec::new(0, 10)];
+    let mut fd = Cursor::new(common::build_nrg_v2(&tracks));
+    let edit = NrgDaoxEdit::new()
+        .isrc(1, "FR-Z03-98-00212")
+        .isrc(2, "FRZ039800000")
+        .isrc(3, "FRZ039800213");
+    editor::edit_daox(&mut fd, &edit).expect("edit_daox()");
+    let mut image = fd.into_inner();
+
+    // Break the last ISRC, which the editor wouldn't write
+    let pos = image.windows(12).position(|code| code == b"FRZ039800213")
+        .expect("ISRC of track 3");
+    image[pos..pos + 2].copy_from_slice(b"fr");
+
+    let metadata = metadata::read_from_slice(&image)
+        .expect("read_from_slice()");
+    let tracks: Vec<_> = metadata.tracks().collect();
+    assert_eq!(tracks[0].isrc.as_deref(), Some("FRZ039800212"));
+    assert!(tracks[0].to_string().contains(
+        "\tISRC: \"FRZ039800212\" (country FR, registrant Z03, year 98, \
+         designation 00212)\n"));
+    assert!(tracks[2].to_string().contains(
+        "\tISRC: \"frZ039800213\" (invalid)\n"));
+
+    let warnings = metadata.warnings();
+    assert_eq!(warnings, vec![
+        "DAOX track 02: ISRC \"FRZ039800000\" looks bogus: the designation \
+         code is 00000".to_string(),
+        "DAOX track 03: Invalid ISRC \"frZ039800213\": the country code \
+         isn't made of two upper case letters".to_string(),
+    ]);
+
+    let text = text::metadata_to_text(&metadata, false);
+    let isrcs: Vec<&str> = text.lines()
+        .skip_while(|line| *line != "ISRCs")
+        .skip(2)
+        .take_while(|line| !line.is_empty())
+        .collect();
+    assert_eq!(isrcs.len(), 2);
+    assert!(isrcs[0].split_whitespace()
+            .eq(vec!["01", "FR-Z03-98-00212", "FR", "Z03", "98", "00212"]));
+}
@@ -0,0 +1,41 @@
+extern crate nrgrip;
+use nrgrip::json::{self, JsonValue};
+use nrgrip::metadata;
+use std::fs::File;
+
+mod common;
+use common::TrackSpec;
+
+#[test]
+fn json_serialization() {
+    let value = JsonValue::object(vec![
+        ("title", "Say \"hi\"\\\n".into()),
+        ("isrc", None::<String>.into()),
+        ("numbers", vec![1u8, 2, 3].into()),
+        ("empty", JsonValue::Array(Vec::new())),
+    ]);
+    assert_eq!(value.to_string(),
+               "{\"title\":\"Say \\\"hi\\\"\\\\\\n\",\"isrc\":null,\
+                \"numbers\":[1,2,3],\"empty\":[]}");
+    assert_eq!(format!("{:#}", JsonValue::object(vec![("a", 1u8.into())])),
+               "{\n  \"a\": 1\n}");
+    assert_eq!(JsonValue::from(u64::MAX).to_string(), "18446744073709551615");
+}
+
+#[test]
+fn metadata_json() {
+    let tracks = [TrackSpec::new(150, 75), TrackSpec::new(0, 75)];
+    let img = common::write_temp_file("json.nrg",
+                                      &common::build_nrg_v2(&tracks));
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let doc = json::metadata_to_json(&metadata).to_string();
+    assert!(doc.starts_with("{\"file_size\":"));
+    assert!(doc.contains("\"disc\":{\"length_sectors\":150,"));
+    assert!(doc.contains("\"title\":\"Track 2\""));
+    assert!(doc.contains("\"warnings\":[],\"red_book_warnings\":[{\
+                          \"kind\":\"short_track\",\"track_number\":1,"));
+}
@@ -0,0 +1,61 @@
+extern crate nrgrip;
+use nrgrip::loudness::{LoudnessMeter, NrgLoudness};
+use std::f64::consts::PI;
+
+/// Builds `seconds` of a 1 kHz stereo sine wave at `level` dBFS.
+fn sine(level: f64, seconds: usize) -> Vec<u8> {
+    let amplitude = 10f64.powf(level / 20.0) * 32_767.0;
+    let mut audio = Vec::new();
+    for i in 0..seconds * 44_100 {
+        let x = (2.0 * PI * 1_000.0 * i as f64 / 44_100.0).sin();
+        let sample = (amplitude * x).round() as i16;
+        audio.extend_from_slice(&sample.to_le_bytes());
+        audio.extend_from_slice(&sample.to_le_bytes());
+    }
+    audio
+}
+
+fn measure(audio: &[u8]) -> NrgLoudness {
+    let mut meter = LoudnessMeter::new();
+    // Split in the middle of a sample
+    meter.update(&audio[..1001]);
+    meter.update(&audio[1001..]);
+    meter.value()
+}
+
+#[test]
+fn sine_loudness() {
+    // EBU Tech 3341, case 1: a 1 kHz sine at -23 dBFS measures -23 LUFS
+    let loud = measure(&sine(-23.0, 20));
+    let integrated = loud.integrated.expect("integrated");
+    assert!((integrated + 23.0).abs() < 0.1, "{}", integrated);
+    assert!((loud.replaygain_gain().unwrap() - 5.0).abs() < 0.1);
+    assert!((loud.peak - 10f64.powf(-23.0 / 20.0)).abs() < 0.001);
+
+    let quiet = measure(&sine(-45.0, 20));
+    assert!((quiet.integrated.unwrap() + 45.0).abs() < 0.1);
+
+    // The album's loudness is that of the whole, the quiet track being
+    // gated out
+    let album = NrgLoudness::album(&[loud.clone(), quiet]);
+    assert!((album.integrated.unwrap() + 23.0).abs() < 0.1);
+    assert_eq!(album.peak, loud.peak);
+
+    let silence = measure(&[0u8; 4 * 44_100]);
+    assert_eq!(silence.integrated, None);
+    assert_eq!(silence.replaygain_gain(), None);
+    assert_eq!(silence.peak, 0.0);
+}
+
+#[test]
+fn mono_loudness() {
+    // Mono audio measures as the same signal on both channels
+    let stereo = sine(-23.0, 10);
+    let mono: Vec<u8> = stereo.chunks(4).flat_map(|frame| frame[..2].to_vec())
+        .collect();
+    let mut meter = LoudnessMeter::mono();
+    meter.update(&mono);
+    let difference = meter.value().integrated.unwrap()
+        - measure(&stereo).integrated.unwrap();
+    assert!(difference.abs() < 1e-9);
+}
@@ -0,0 +1,37 @@
+#![cfg(feature = "fs")]
+
+extern crate nrgrip;
+use nrgrip::manifest;
+use std::env;
+use std::fs;
+
+const ABC_SHA256: &str =
+    "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+const EMPTY_SHA256: &str =
+    "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+#[test]
+fn write_manifest() {
+    let dir = env::temp_dir().join("nrgrip-test-manifest");
+    fs::create_dir_all(dir.join("sub")).expect("create_dir_all()");
+    let abc = dir.join("sub").join("abc.wav");
+    let empty = dir.join("empty.cue");
+    fs::write(&abc, b"abc").expect("fs::write()");
+    fs::write(&empty, b"").expect("fs::write()");
+
+    // An older entry for empty.cue is replaced, the other ones are kept
+    let manifest_path = dir.join(manifest::MANIFEST_NAME);
+    fs::write(&manifest_path, format!("{}  other.wav\n{}  empty.cue\n",
+                                      EMPTY_SHA256, ABC_SHA256))
+        .expect("fs::write()");
+
+    let written = manifest::write_manifest(&dir, &[abc, empty])
+        .expect("write_manifest()");
+    assert_eq!(written, manifest_path);
+    let contents = fs::read_to_string(&manifest_path)
+        .expect("fs::read_to_string()");
+    assert_eq!(contents, format!("{0}  other.wav\n\
+                                  {1}  sub/abc.wav\n\
+                                  {0}  empty.cue\n",
+                                 EMPTY_SHA256, ABC_SHA256));
+}
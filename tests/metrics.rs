@@ -0,0 +1,64 @@
+#![cfg(feature = "fs")]
+
+extern crate nrgrip;
+use nrgrip::extraction::{self, ExtractionOptions};
+use nrgrip::metadata;
+use nrgrip::metrics::{Metrics, MetricsCounters};
+use std::env;
+use std::fs::File;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+/// Receiver recording the calls to `started()` and `finished()`.
+#[derive(Default)]
+struct Calls(Mutex<Vec<&'static str>>);
+
+impl Metrics for Calls {
+    fn started(&self) {
+        self.0.lock().unwrap().push("started");
+    }
+
+    fn finished(&self, _: Duration) {
+        self.0.lock().unwrap().push("finished");
+    }
+}
+
+#[test]
+fn extraction_metrics() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let raw96 = common::make_raw96_image(&common::build_nrg_v2(&tracks), 255);
+    let img = common::write_temp_file("metrics.nrg", &raw96);
+    let mut fd = File::open(&img).expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let counters = Arc::new(MetricsCounters::new());
+    let template = env::temp_dir().join("nrgrip-test-metrics-{track}");
+    let options = ExtractionOptions::new()
+        .split_tracks(true)
+        .buffer_size(4 * 2448)
+        .name_template(&template.to_string_lossy())
+        .metrics(counters.clone());
+    extraction::extract(&mut fd, &img.to_string_lossy(), &metadata, &options)
+        .expect("extract()");
+
+    // 105 sectors, from the first track's index #1 to the end
+    let snapshot = counters.snapshot();
+    assert_eq!(snapshot.bytes_read, 105 * 2448);
+    assert_eq!(snapshot.bytes_written, 105 * SECTOR_SIZE as u64);
+    assert_eq!(snapshot.sectors_stripped, 105);
+    assert!(snapshot.elapsed > Duration::default());
+    assert_eq!(counters.snapshot().elapsed, snapshot.elapsed);
+
+    // The end of a failed extraction is reported too
+    let calls = Arc::new(Calls::default());
+    let options = ExtractionOptions::new()
+        .tracks(&[3])
+        .metrics(calls.clone());
+    assert!(extraction::extract(&mut fd, &img.to_string_lossy(), &metadata,
+                                &options).is_err());
+    assert_eq!(*calls.0.lock().unwrap(), ["started", "finished"]);
+}
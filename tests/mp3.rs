@@ -0,0 +1,73 @@
+#![cfg(feature = "mp3")]
+extern crate nrgrip;
+use nrgrip::mp3::{Mp3Bitrate, Mp3Writer};
+use nrgrip::tags::NrgTags;
+use std::f64::consts::TAU;
+use std::io::{Cursor, Write};
+
+/// Encodes one second of a 1 kHz sine wave in `channels` channels.
+fn encode(channels: u16, bitrate: Mp3Bitrate, tags: &NrgTags) -> Vec<u8> {
+    let pcm: Vec<u8> = (0..44100)
+        .flat_map(|n| {
+            let t = f64::from(n) / 44100.0;
+            let sample = (16384.0 * (TAU * 1000.0 * t).sin()) as i16;
+            vec![sample.to_le_bytes(); usize::from(channels)].concat()
+        })
+        .collect();
+    let mut writer = Mp3Writer::new(Cursor::new(Vec::new()), channels,
+                                    bitrate, tags)
+        .expect("Mp3Writer::new()");
+    for bytes in pcm.chunks(1001) {
+        writer.write_all(bytes).expect("write_all()");
+    }
+    writer.finish().expect("finish()").into_inner()
+}
+
+/// Returns the size of the ID3v2 tag at the beginning of `mp3`.
+fn id3v2_size(mp3: &[u8]) -> usize {
+    assert_eq!(&mp3[..3], b"ID3");
+    10 + mp3[6..10].iter()
+        .fold(0, |size, &byte| size << 7 | usize::from(byte))
+}
+
+#[test]
+fn encode_tagged() {
+    let tags = NrgTags {
+        track_number: Some(3),
+        track_total: Some(12),
+        title: Some("Chanson".to_string()),
+        ..NrgTags::default()
+    };
+    let mp3 = encode(2, Mp3Bitrate::default(), &tags);
+    let size = id3v2_size(&mp3);
+    let tag = &mp3[..size];
+    assert!(tag.windows(4).any(|bytes| bytes == b"TIT2"));
+    assert!(tag.windows(4).any(|bytes| bytes == b"TRCK"));
+
+    // The first frame is the LAME tag of a VBR stream
+    let frames = &mp3[size..];
+    assert_eq!(frames[0], 0xFF);
+    assert_eq!(frames[1] & 0xE0, 0xE0);
+    assert!(frames[..200].windows(4).any(|bytes| bytes == b"Xing"));
+    assert!(frames[..200].windows(4).any(|bytes| bytes == b"LAME"));
+}
+
+#[test]
+fn encode_constant_bitrate() {
+    let mp3 = encode(1, Mp3Bitrate::Constant(128), &NrgTags::default());
+    let frames = &mp3[id3v2_size(&mp3)..];
+    assert!(frames[..200].windows(4).any(|bytes| bytes == b"Info"));
+    // About one second at 128 kbit/s
+    assert!(frames.len() > 14000 && frames.len() < 20000);
+}
+
+#[test]
+fn invalid_parameters() {
+    let tags = NrgTags::default();
+    assert!(Mp3Writer::new(Cursor::new(Vec::new()), 3,
+                           Mp3Bitrate::default(), &tags).is_err());
+    assert!(Mp3Writer::new(Cursor::new(Vec::new()), 2,
+                           Mp3Bitrate::Constant(100), &tags).is_err());
+    assert!(Mp3Writer::new(Cursor::new(Vec::new()), 2,
+                           Mp3Bitrate::Variable(10), &tags).is_err());
+}
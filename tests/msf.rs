@@ -0,0 +1,20 @@
+extern crate nrgrip;
+use nrgrip::msf::Msf;
+
+#[test]
+fn msf_conversions() {
+    let msf = Msf::from_sectors(4 * 60 * 75 + 33 * 75 + 74);
+    assert_eq!(msf, Msf::new(4, 33, 74));
+    assert_eq!(msf.to_string(), "04:33:74");
+    assert_eq!(Msf::from_sectors(msf.to_sectors()), msf);
+
+    assert_eq!(Msf::from_bytes(150 * 2352 + 100, 2352), Msf::new(0, 2, 0));
+    assert_eq!(Msf::new(0, 2, 0).to_bytes(2448), 150 * 2448);
+    assert_eq!(Msf::from_bytes(2352, 0), Msf::default());
+    assert_eq!(Msf::new(1, 0, 15).as_seconds(), 60.2);
+
+    assert_eq!(Msf::parse("04:33:74"), Some(msf));
+    assert_eq!(Msf::parse("00:60:00"), None);
+    assert_eq!(Msf::parse("00:00:75"), None);
+    assert_eq!(Msf::parse("00:01"), None);
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "online")]
+
+extern crate nrgrip;
+use nrgrip::metadata;
+use nrgrip::musicbrainz;
+use std::{env, fs};
+use std::io::Cursor;
+
+mod common;
+use common::TrackSpec;
+
+const DISC_ID: &str = "lwHl8fGzJyLXQR33ug60E8jhf4k-";
+
+const RESPONSE: &str = r#"{
+  "id": "lwHl8fGzJyLXQR33ug60E8jhf4k-",
+  "releases": [
+    {
+      "id": "other-release",
+      "title": "Other",
+      "media": [{"discs": [{"id": "another-disc"}], "tracks": []}]
+    },
+    {
+      "id": "f0d7d1d5-8d92-4f6c-9a1e-3b0f2c7e2d55",
+      "title": "Album",
+      "artist-credit": [
+        {"name": "First", "joinphrase": " & "},
+        {"name": "Second", "joinphrase": ""}
+      ],
+      "media": [
+        {"discs": [{"id": "another-disc"}], "tracks": [{"title": "No"}]},
+        {
+          "discs": [{"id": "lwHl8fGzJyLXQR33ug60E8jhf4k-"}],
+          "tracks": [
+            {"title": "One", "artist-credit": [{"name": "First"}]},
+            {"title": "Two", "artist-credit": [{"name": "Second"}]}
+          ]
+        }
+      ]
+    }
+  ]
+}"#;
+
+#[test]
+fn parse_releases() {
+    let releases = musicbrainz::parse_releases(RESPONSE, DISC_ID)
+        .expect("parse_releases()");
+    assert_eq!(releases.len(), 1);
+    let release = &releases[0];
+    assert_eq!(release.title, "Album");
+    assert_eq!(release.artist, "First & Second");
+    assert_eq!(release.to_string(),
+               "First & Second - Album \
+                (f0d7d1d5-8d92-4f6c-9a1e-3b0f2c7e2d55)");
+    let titles: Vec<&str> = release.tracks.iter()
+        .map(|track| track.title.as_str())
+        .collect();
+    assert_eq!(titles, ["One", "Two"]);
+    assert_eq!(release.tracks[1].artist, "Second");
+
+    assert!(musicbrainz::parse_releases("{", DISC_ID).is_err());
+}
+
+#[test]
+fn cached_lookup() {
+    let cache_dir = env::temp_dir().join("nrgrip-test-musicbrainz");
+    fs::create_dir_all(&cache_dir).expect("create_dir_all()");
+    fs::write(cache_dir.join(format!("{}.json", DISC_ID)), RESPONSE)
+        .expect("fs::write()");
+
+    let releases = musicbrainz::lookup(DISC_ID, Some(&cache_dir))
+        .expect("lookup()");
+    assert_eq!(releases.len(), 1);
+
+    // The titles replace the names of the AFNM chunk
+    let img = common::build_nrg_v2(&[TrackSpec::new(150, 10),
+                                     TrackSpec::new(75, 20)]);
+    let mut metadata = metadata::read_nrg_metadata(&mut Cursor::new(&img))
+        .expect("read_nrg_metadata()");
+    releases[0].apply(&mut metadata);
+    let titles: Vec<Option<String>> = metadata.tracks()
+        .map(|track| track.title)
+        .collect();
+    assert_eq!(titles, [Some("One".to_string()), Some("Two".to_string())]);
+}
@@ -1,9 +1,32 @@
 extern crate nrgrip;
 use nrgrip::metadata;
-use std::fs::File;
-use std::io::{Seek, SeekFrom};
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
 use std::path::PathBuf;
 
+mod common;
+use common::TrackSpec;
+
+/// Reader counting the calls to `read()`, i.e. the system calls that would
+/// be made on a file.
+struct CountingReader {
+    inner: Cursor<Vec<u8>>,
+    reads: usize,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reads += 1;
+        self.inner.read(buf)
+    }
+}
+
+impl Seek for CountingReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
 #[test]
 fn nrg_format() {
     let mut img = PathBuf::new();
@@ -17,3 +40,54 @@ fn nrg_format() {
         .expect("read_nrg_version()");
     assert_eq!(ver, 2);
 }
+
+#[test]
+fn nrg_metadata_from_memory() {
+    let mut img = PathBuf::new();
+    img.push("tests");
+    img.push("minimal_v2.nrg");
+    let bytes = fs::read(img)
+        .expect("fs::read()");
+    let metadata = metadata::read_nrg_metadata(&mut Cursor::new(bytes))
+        .expect("read_nrg_metadata()");
+    assert_eq!(metadata.nrg_version(), 2);
+}
+
+#[test]
+fn buffered_metadata_parsing() {
+    // 99 tracks make chunks of several kilobytes, made of small fields
+    let tracks: Vec<TrackSpec> = (0..99).map(|_| TrackSpec::new(0, 1))
+        .collect();
+    let contents = common::build_nrg_v2(&tracks);
+    let end = contents.len() as u64;
+    let mut fd = CountingReader { inner: Cursor::new(contents), reads: 0 };
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    assert_eq!(metadata.tracks().count(), 99);
+
+    // A few reads for the footer, a few for the chunks
+    assert!(fd.reads < 10, "{} reads", fd.reads);
+    // The offset is left after the "END!" chunk ID
+    assert_eq!(fd.inner.position(), end - 16);
+}
+
+#[test]
+fn little_endian_daox_size() {
+    let mut contents = common::build_nrg_v2(&[TrackSpec::new(150, 10)]);
+    let metadata = metadata::read_from_slice(&contents)
+        .expect("read_from_slice()");
+    let daox = metadata.daox_chunk().unwrap();
+    assert!(!daox.is_size2_little_endian());
+
+    // Rewrite the second size of the DAOX chunk in little endian
+    let size = daox.size();
+    let pos = contents.windows(4).position(|id| id == b"DAOX")
+        .expect("DAOX chunk") + 8;
+    contents[pos..pos + 4].copy_from_slice(&size.to_le_bytes());
+    let metadata = metadata::read_from_slice(&contents)
+        .expect("read_from_slice()");
+    let daox = metadata.daox_chunk().unwrap();
+    assert!(daox.is_size2_little_endian());
+    assert_eq!(daox.size2(), size);
+    assert_eq!(metadata.tracks().count(), 1);
+}
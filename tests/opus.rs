@@ -0,0 +1,86 @@
+#![cfg(feature = "opus")]
+extern crate audiopus;
+extern crate nrgrip;
+extern crate ogg;
+use audiopus::coder::Decoder;
+use audiopus::packet::Packet;
+use audiopus::{Channels, MutSignals, SampleRate};
+use nrgrip::opus::OpusWriter;
+use nrgrip::tags::NrgTags;
+use ogg::reading::PacketReader;
+use std::convert::TryFrom;
+use std::f64::consts::TAU;
+use std::io::{Cursor, Write};
+
+/// Builds `samples` stereo samples of a 1 kHz sine wave at half the full
+/// scale, as 16-bit little-endian PCM.
+fn sine(samples: usize) -> Vec<u8> {
+    (0..samples)
+        .flat_map(|n| {
+            let t = n as f64 / 44100.0;
+            let sample = (16384.0 * (TAU * 1000.0 * t).sin()) as i16;
+            [sample.to_le_bytes(), sample.to_le_bytes()].concat()
+        })
+        .collect()
+}
+
+#[test]
+fn encode_stereo() {
+    let tags = NrgTags {
+        title: Some("Song".to_string()),
+        ..NrgTags::default()
+    };
+    let mut writer = OpusWriter::new(Cursor::new(Vec::new()), 2, 128, &tags)
+        .expect("OpusWriter::new()");
+    for bytes in sine(44100).chunks(1001) {
+        writer.write_all(bytes).expect("write_all()");
+    }
+    let stream = writer.finish().expect("finish()").into_inner();
+
+    let mut reader = PacketReader::new(Cursor::new(stream));
+    let head = reader.read_packet_expected().expect("OpusHead");
+    assert_eq!(&head.data[..8], b"OpusHead");
+    assert_eq!(head.data[9], 2);
+    let pre_skip = u64::from(u16::from_le_bytes([head.data[10],
+                                                 head.data[11]]));
+    assert_eq!(&head.data[12..16], &44100u32.to_le_bytes());
+    let comments = reader.read_packet_expected().expect("OpusTags");
+    assert_eq!(&comments.data[..8], b"OpusTags");
+    assert!(comments.data.windows(10).any(|bytes| bytes == b"TITLE=Song"));
+
+    // The granule position of the stream is its length plus the pre-skip
+    let mut decoder = Decoder::new(SampleRate::Hz48000, Channels::Stereo)
+        .expect("Decoder::new()");
+    let mut decoded = Vec::new();
+    let mut end = None;
+    while let Some(packet) = reader.read_packet().expect("read_packet()") {
+        assert!(end.is_none());
+        let mut output = vec![0i16; 2 * 5760];
+        let samples = decoder.decode(
+            Some(Packet::try_from(&packet.data[..]).expect("Packet")),
+            MutSignals::try_from(&mut output[..]).expect("MutSignals"),
+            false).expect("decode()");
+        decoded.extend_from_slice(&output[..2 * samples]);
+        if packet.last_in_stream() {
+            end = Some(packet.absgp_page());
+        }
+    }
+    assert_eq!(end, Some(pre_skip + 48000));
+    assert!(decoded.len() as u64 >= 2 * (pre_skip + 48000));
+
+    // The decoded audio has the level of the sine wave
+    let audio = &decoded[2 * pre_skip as usize..][..2 * 48000];
+    let rms = (audio[4800..43200].iter()
+               .map(|&sample| f64::from(sample).powi(2))
+               .sum::<f64>() / 38400.0).sqrt();
+    let expected = 16384.0 / 2f64.sqrt();
+    assert!((rms - expected).abs() < 0.1 * expected, "RMS = {}", rms);
+}
+
+#[test]
+fn invalid_parameters() {
+    let tags = NrgTags::default();
+    assert!(OpusWriter::new(Vec::new(), 3, 128, &tags).is_err());
+    assert!(OpusWriter::new(Vec::new(), 2, 0, &tags).is_err());
+    assert!(OpusWriter::new(Vec::new(), 1, 64, &tags).is_ok());
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "fs")]
+
+extern crate nrgrip;
+use nrgrip::pipeline::PipeWriter;
+use std::io::{self, Write};
+use std::thread;
+
+/// Writer failing once it has received `limit` bytes.
+struct FailingWriter {
+    written: usize,
+    limit: usize,
+}
+
+impl Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written + buf.len() > self.limit {
+            return Err(io::Error::other("disk full"));
+        }
+        self.written += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn pipe_two_stages() {
+    let data: Vec<u8> = (0..100_000u32).map(|i| (i % 253) as u8).collect();
+    let output = thread::scope(|scope| {
+        let last = PipeWriter::new(scope, Vec::new());
+        let mut first = PipeWriter::new(scope, last);
+        for chunk in data.chunks(1000) {
+            first.write_all(chunk).expect("write_all()");
+        }
+        first.finish().expect("finish()")
+            .finish().expect("finish()")
+    });
+    assert!(output == data);
+}
+
+#[test]
+fn pipe_error() {
+    let err = thread::scope(|scope| {
+        let last = PipeWriter::new(scope,
+                                   FailingWriter { written: 0, limit: 5000 });
+        let mut first = PipeWriter::new(scope, last);
+        // The error of the last stage reaches the writes of the first one
+        (0..1000).map(|_| first.write_all(&[0; 100]))
+            .find_map(Result::err)
+            .expect("write_all() error")
+    });
+    assert_eq!(err.to_string(), "disk full");
+}
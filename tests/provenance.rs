@@ -0,0 +1,86 @@
+#![cfg(feature = "fs")]
+
+extern crate nrgrip;
+use nrgrip::extraction::{self, ExtractionOptions};
+use nrgrip::json::JsonValue;
+use nrgrip::metadata;
+use nrgrip::provenance;
+use std::env;
+use std::fs::{self, File};
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+/// Returns the member `name` of the object `value`.
+fn member<'a>(value: &'a JsonValue, name: &str) -> &'a JsonValue {
+    match *value {
+        JsonValue::Object(ref members) => members.iter()
+            .find(|member| member.0 == name)
+            .map(|member| &member.1)
+            .unwrap_or_else(|| panic!("no member {}", name)),
+        _ => panic!("{} is not an object", value),
+    }
+}
+
+/// Returns the items of the array `value`.
+fn items(value: &JsonValue) -> &[JsonValue] {
+    match *value {
+        JsonValue::Array(ref items) => items,
+        _ => panic!("{} is not an array", value),
+    }
+}
+
+#[test]
+fn write_provenance() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("provenance.nrg", &contents);
+    let img_path = img.to_string_lossy().into_owned();
+
+    let mut fd = File::open(&img).expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    let template = env::temp_dir().join("nrgrip-test-provenance-{track}");
+    let options = ExtractionOptions::new()
+        .split_tracks(true)
+        .name_template(&template.to_string_lossy())
+        .checksums(true);
+    let files = extraction::extract(&mut fd, &img_path, &metadata, &options)
+        .expect("extract()");
+
+    // Track 1 without its pre-gap, track 2 with the pre-gap appended to
+    // track 1
+    let bounds = [(150, 235), (235, 255)];
+    for (file, &(start, end)) in files.iter().zip(bounds.iter()) {
+        assert_eq!(file.source_ranges,
+                   vec![(start * SECTOR_SIZE) as u64
+                        ..(end * SECTOR_SIZE) as u64]);
+        assert_eq!(file.sectors, (end - start) as u64);
+    }
+
+    let report = provenance::provenance_to_json(&img_path, &metadata,
+                                                &options, &files)
+        .expect("provenance_to_json()");
+    assert_eq!(member(member(&report, "image"), "path").to_string(),
+               JsonValue::from(img_path.as_str()).to_string());
+    assert_eq!(member(member(&report, "options"), "split_tracks")
+               .to_string(), "true");
+    let file_values = items(member(&report, "files"));
+    assert_eq!(file_values.len(), 2);
+    let first = &file_values[0];
+    assert_eq!(member(first, "sectors").to_string(), "85");
+    assert_eq!(member(&items(member(first, "source_ranges"))[0], "start")
+               .to_string(), (150 * SECTOR_SIZE).to_string());
+    assert_eq!(member(first, "size").to_string(),
+               (85 * SECTOR_SIZE).to_string());
+    assert_eq!(member(first, "crc32").to_string().len(), 10);
+    assert_eq!(member(first, "sha256").to_string().len(), 66);
+
+    let dir = env::temp_dir();
+    let path = provenance::write_provenance(&dir, &img_path, &metadata,
+                                            &options, &files)
+        .expect("write_provenance()");
+    assert_eq!(path, dir.join("nrgrip-test-provenance.provenance.json"));
+    let written = fs::read_to_string(&path).expect("fs::read_to_string()");
+    assert_eq!(written, format!("{:#}\n", report));
+}
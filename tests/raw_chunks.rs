@@ -0,0 +1,88 @@
+extern crate nrgrip;
+use nrgrip::metadata::{self, MetadataOptions};
+use nrgrip::metadata::raw::{self, NrgChunkStatus};
+use nrgrip::text;
+use nrgrip::tsv;
+use std::io::Cursor;
+
+mod common;
+use common::TrackSpec;
+
+#[test]
+fn raw_chunks() {
+    let tracks = [TrackSpec::new(150, 10)];
+    let img = common::build_nrg_v2(&tracks);
+    let chunks = raw::read_raw_chunks(&mut Cursor::new(&img))
+        .expect("read_raw_chunks()");
+
+    let ids: Vec<&str> = chunks.iter().map(|chunk| chunk.id.as_str()).collect();
+    assert_eq!(ids, ["CUEX", "DAOX", "SINF", "MTYP", "AFNM", "END!"]);
+
+    let sinf = &chunks[2];
+    assert_eq!(sinf.payload, [0, 0, 0, 1]);
+    assert_eq!(&img[sinf.offset as usize..sinf.offset as usize + 4], b"SINF");
+
+    let dump = chunks[4].to_string();
+    let offset = chunks[4].payload_offset();
+    assert!(dump.contains(&format!(
+        "\n{:08X}  54 72 61 63 6B 20 31 2E  77 61 76 00\
+         {:12}  |Track 1.wav.|", offset, "")));
+}
+
+#[test]
+fn chunk_listing() {
+    let tracks = [TrackSpec::new(150, 10)];
+    let mut img = common::build_nrg_v2(&tracks);
+    let chunks = raw::read_raw_chunks(&mut Cursor::new(&img))
+        .expect("read_raw_chunks()");
+    assert!(chunks.iter()
+            .all(|chunk| chunk.status() == NrgChunkStatus::Parsed));
+
+    // Turn the SINF chunk into a skipped one and the MTYP chunk into an
+    // unknown one
+    let (sinf, mtyp) = (chunks[2].offset as usize, chunks[3].offset as usize);
+    img[sinf..sinf + 4].copy_from_slice(b"RELO");
+    img[mtyp..mtyp + 4].copy_from_slice(b"ABCD");
+    let chunks = raw::read_raw_chunks(&mut Cursor::new(&img))
+        .expect("read_raw_chunks()");
+    assert_eq!(chunks[2].status(), NrgChunkStatus::Skipped);
+    assert_eq!(chunks[3].status(), NrgChunkStatus::Unknown);
+
+    let listing = text::chunk_listing_to_text(&chunks, false);
+    let lines: Vec<Vec<&str>> = listing.lines()
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[0], ["Chunk", "Offset", "Size", "Status"]);
+    assert_eq!(lines[3], ["RELO", sinf.to_string().as_str(), "4", "skipped"]);
+    assert_eq!(lines[4][3], "unknown");
+    assert_eq!(tsv::chunk_listing_to_tsv(&chunks[1]).fields()[4], "parsed");
+}
+
+#[test]
+fn skipped_chunks() {
+    let tracks = [TrackSpec::new(150, 10)];
+    let mut img = common::build_nrg_v2(&tracks);
+    let chunks = raw::read_raw_chunks(&mut Cursor::new(&img))
+        .expect("read_raw_chunks()");
+    let sinf = chunks[2].offset;
+    img[sinf as usize..sinf as usize + 4].copy_from_slice(b"RELO");
+
+    let metadata = metadata::read_from_slice(&img)
+        .expect("read_from_slice()");
+    assert_eq!(metadata.skipped_chunk_ids(), ["RELO"]);
+    let skipped = &metadata.skipped_chunks()[0];
+    assert_eq!((skipped.offset, skipped.size), (sinf, 4));
+    assert_eq!(skipped.byte_range(), sinf..sinf + 12);
+    assert_eq!(skipped.payload, None);
+    assert_eq!(skipped.to_string(),
+               format!("RELO chunk at offset {0} (0x{0:X}), 4 Bytes", sinf));
+
+    // The payload is kept on request
+    let options = MetadataOptions::new().keep_skipped_payloads(true);
+    let metadata = metadata::read_nrg_metadata_with(&mut Cursor::new(&img),
+                                                    &options)
+        .expect("read_nrg_metadata_with()");
+    assert_eq!(metadata.skipped_chunks()[0].payload,
+               Some(vec![0, 0, 0, 1]));
+}
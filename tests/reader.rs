@@ -0,0 +1,62 @@
+#![cfg(all(feature = "fs", any(unix, windows)))]
+
+extern crate nrgrip;
+use nrgrip::analysis;
+use nrgrip::metadata;
+use nrgrip::reader::NrgReader;
+use std::io::{Read, Seek, SeekFrom};
+use std::thread;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+#[test]
+fn positional_reads() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("reader.nrg", &contents);
+    let reader = NrgReader::open(&img).expect("NrgReader::open()");
+    assert_eq!(reader.len().expect("len()"), contents.len() as u64);
+
+    let mut buf = [0u8; 16];
+    reader.read_exact_at(&mut buf, 150 * SECTOR_SIZE as u64)
+        .expect("read_exact_at()");
+    assert_eq!(&buf[..], &contents[150 * SECTOR_SIZE..][..16]);
+    assert!(reader.read_exact_at(&mut buf, contents.len() as u64 - 8)
+            .is_err());
+
+    // The cursors have independent positions
+    let mut first = reader.cursor();
+    let mut second = reader.cursor();
+    first.seek(SeekFrom::End(-12)).expect("seek()");
+    second.seek(SeekFrom::Start(160 * SECTOR_SIZE as u64)).expect("seek()");
+    first.read_exact(&mut buf[..4]).expect("read_exact()");
+    assert_eq!(&buf[..4], b"NER5");
+    second.read_exact(&mut buf[..4]).expect("read_exact()");
+    assert_eq!(&buf[..4], &contents[160 * SECTOR_SIZE..][..4]);
+    assert!(second.seek(SeekFrom::Current(-1_000_000_000)).is_err());
+}
+
+#[test]
+fn concurrent_reads() {
+    let tracks = [TrackSpec::new(150, 50), TrackSpec::new(75, 60),
+                  TrackSpec::new(0, 70)];
+    let contents = common::build_nrg_v2(&tracks);
+    let img = common::write_temp_file("reader-threads.nrg", &contents);
+    let reader = NrgReader::open(&img).expect("NrgReader::open()");
+    let metadata = metadata::read_nrg_metadata(&mut reader.cursor())
+        .expect("read_nrg_metadata()");
+    let expected = analysis::analyze_tracks(&mut reader.cursor(), &metadata)
+        .expect("analyze_tracks()");
+
+    // Each thread reads the whole image through the same handle
+    thread::scope(|scope| {
+        let threads: Vec<_> = (0..4).map(|_| scope.spawn(|| {
+            analysis::analyze_tracks(&mut reader.cursor(), &metadata)
+                .expect("analyze_tracks()")
+        })).collect();
+        for thread in threads {
+            assert_eq!(thread.join().expect("join()"), expected);
+        }
+    });
+}
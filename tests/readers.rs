@@ -0,0 +1,56 @@
+extern crate nrgrip;
+use nrgrip::error::NrgError;
+use nrgrip::metadata::readers;
+use std::io::{Cursor, ErrorKind};
+
+#[test]
+fn integers() {
+    let bytes = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+    let mut fd = Cursor::new(&bytes);
+    assert_eq!(readers::read_u64(&mut fd).unwrap(), 0x0102030405060708);
+
+    let mut fd = Cursor::new(&bytes);
+    assert_eq!(readers::read_u32(&mut fd).unwrap(), 0x01020304);
+    assert_eq!(readers::read_u32_le(&mut fd).unwrap(), 0x08070605);
+
+    let mut fd = Cursor::new(&bytes);
+    assert_eq!(readers::read_u16(&mut fd).unwrap(), 0x0102);
+    assert_eq!(readers::read_u16_le(&mut fd).unwrap(), 0x0403);
+    assert_eq!(readers::read_u8(&mut fd).unwrap(), 0x05);
+
+    // Too few bytes left
+    match readers::read_u32(&mut fd) {
+        Err(NrgError::Io(err)) =>
+            assert_eq!(err.kind(), ErrorKind::UnexpectedEof),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn sized_strings() {
+    let mut fd = Cursor::new(b"DAOX\0\0\0\0rest");
+    assert_eq!(readers::read_sized_string(&mut fd, 4).unwrap(), "DAOX");
+    // The string stops at the first null byte, but all the bytes are read
+    assert_eq!(readers::read_sized_string(&mut fd, 4).unwrap(), "");
+    assert_eq!(fd.position(), 8);
+    assert!(readers::read_sized_string(&mut Cursor::new(b"\xFF\xFE"), 2)
+            .is_err());
+}
+
+#[test]
+fn bcd_bytes() {
+    let mut fd = Cursor::new([0x42, 0x09, 0xAA, 0x1A]);
+    assert_eq!(readers::read_u8_bcd(&mut fd).unwrap(), 42);
+    assert_eq!(readers::read_u8_bcd(&mut fd).unwrap(), 9);
+    assert_eq!(readers::read_u8_bcd(&mut fd).unwrap(), 0xAA);
+    assert_eq!(readers::read_u8_bcd(&mut fd).unwrap(), 20);
+}
+
+#[test]
+fn chunk_sizes() {
+    let mut fd = Cursor::new((readers::MAX_CHUNK_SIZE + 1).to_be_bytes());
+    match readers::read_chunk_size(&mut fd, "CUEX") {
+        Err(NrgError::NrgChunkSize(id, _)) => assert_eq!(id, "CUEX"),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,76 @@
+extern crate nrgrip;
+use nrgrip::json;
+use nrgrip::metadata;
+use nrgrip::msf::Msf;
+use nrgrip::red_book::{self, NrgRedBookWarning};
+use nrgrip::tsv;
+
+mod common;
+use common::{SECTOR_SIZE, TrackSpec};
+
+/// Moves the end of the last track of an image built by
+/// common::build_nrg_v2(), as recorded in its DAOX chunk, `sectors` sectors
+/// past its beginning, without writing the audio data.
+fn stretch_last_track(img: &mut [u8], sectors: u64) {
+    let daox = img.windows(4).rposition(|id| id == b"DAOX")
+        .expect("DAOX chunk");
+    let track_count = img[daox + 8 + 21] as usize;
+    let entry = daox + 8 + 22 + 42 * (track_count - 1);
+    let mut index1 = [0u8; 8];
+    index1.copy_from_slice(&img[entry + 26..entry + 34]);
+    let end = u64::from_be_bytes(index1) + sectors * SECTOR_SIZE as u64;
+    img[entry + 34..entry + 42].copy_from_slice(&end.to_be_bytes());
+}
+
+#[test]
+fn short_tracks() {
+    let tracks = [TrackSpec::new(150, 75), TrackSpec::new(0, 300),
+                  TrackSpec::new(0, 299)];
+    let metadata = metadata::read_from_slice(&common::build_nrg_v2(&tracks))
+        .expect("read_from_slice()");
+
+    let warnings = red_book::check(&metadata);
+    assert_eq!(warnings, vec![
+        NrgRedBookWarning::ShortTrack { track_number: 1,
+                                        length: Msf::new(0, 1, 0) },
+        NrgRedBookWarning::ShortTrack { track_number: 3,
+                                        length: Msf::new(0, 3, 74) },
+    ]);
+    assert_eq!(warnings[0].kind(), "short_track");
+    assert_eq!(warnings[1].to_string(),
+               "Track 03 is shorter than 4 seconds (00:03:74)");
+
+    let records = tsv::metadata_to_tsv(&metadata);
+    let record = records.iter()
+        .find(|record| record.fields()[0] == "red_book")
+        .expect("red_book record");
+    assert_eq!(&record.fields()[1..4], &["short_track", "1", "75"]);
+}
+
+#[test]
+fn disc_length() {
+    let tracks = [TrackSpec::new(150, 300)];
+    let mut img = common::build_nrg_v2(&tracks);
+    let metadata = metadata::read_from_slice(&img)
+        .expect("read_from_slice()");
+    assert!(red_book::check(&metadata).is_empty());
+
+    // 75 minutes, pre-gap included
+    stretch_last_track(&mut img, 75 * 60 * 75 - 150);
+    let metadata = metadata::read_from_slice(&img)
+        .expect("read_from_slice()");
+    assert_eq!(red_book::check(&metadata),
+               vec![NrgRedBookWarning::LongDisc(Msf::new(75, 0, 0))]);
+
+    stretch_last_track(&mut img, 81 * 60 * 75 - 150);
+    let metadata = metadata::read_from_slice(&img)
+        .expect("read_from_slice()");
+    let warnings = red_book::check(&metadata);
+    assert_eq!(warnings,
+               vec![NrgRedBookWarning::Overburned(Msf::new(81, 0, 0))]);
+    assert_eq!(warnings[0].to_string(),
+               "Disc length 81:00:00 exceeds 80 minutes (overburned)");
+
+    let json = json::metadata_to_json(&metadata).to_string();
+    assert!(json.contains("\"red_book_warnings\":[{\"kind\":\"overburned\""));
+}
@@ -0,0 +1,66 @@
+extern crate nrgrip;
+use nrgrip::metadata;
+use nrgrip::repair;
+use std::io::Cursor;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+/// Returns the index #0, index #1 and end positions of the tracks of `img`.
+fn track_ranges(img: &[u8]) -> Vec<(u64, u64, u64)> {
+    metadata::read_from_slice(img).expect("read_from_slice()")
+        .tracks()
+        .map(|track| (track.index0, track.index1, track.track_end))
+        .collect()
+}
+
+/// Repairs the image `img` truncated to `size` bytes.
+fn repair(img: &[u8], size: usize) -> (repair::NrgRepair, Vec<u8>) {
+    let mut out = Vec::new();
+    let repair = repair::write_repaired_copy(&mut Cursor::new(&img[..size]),
+                                             &mut out)
+        .expect("write_repaired_copy()");
+    assert_eq!(repair.size, out.len() as u64);
+    (repair, out)
+}
+
+#[test]
+fn repair_truncated_images() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let img = common::build_nrg_v2(&tracks);
+    let chunk_offset = 255 * SECTOR_SIZE;
+    let daox = chunk_offset + 8 + 6 * 8;
+    let sinf = daox + 8 + 22 + 2 * 42;
+
+    // Lost footer, and SINF chunk cut short
+    let scan = repair::scan_chunks(&mut Cursor::new(&img[..sinf + 6]))
+        .expect("scan_chunks()");
+    assert_eq!(scan.chunk_offset, Some(chunk_offset as u64));
+    assert_eq!(scan.chunks.len(), 2);
+    assert!(!scan.end_found);
+    let (result, repaired) = repair(&img, sinf + 6);
+    assert_eq!(result.audio_size, chunk_offset as u64);
+    assert_eq!(result.recovered, ["CUEX", "DAOX"]);
+    assert_eq!(result.rebuilt, ["SINF", "MTYP", "END!"]);
+    assert_eq!(track_ranges(&repaired), track_ranges(&img));
+
+    // DAOX chunk cut short, rebuilt from the CUEX chunk
+    let (result, repaired) = repair(&img, daox + 30);
+    assert_eq!(result.recovered, ["CUEX"]);
+    assert_eq!(result.rebuilt, ["DAOX", "SINF", "MTYP", "END!"]);
+    assert_eq!(track_ranges(&repaired), track_ranges(&img));
+
+    // No chunks left: a single track with a 2-second pre-gap
+    let (result, repaired) = repair(&img, chunk_offset - 100);
+    assert!(result.recovered.is_empty());
+    assert_eq!(result.audio_size, 254 * SECTOR_SIZE as u64);
+    assert_eq!(track_ranges(&repaired),
+               [(0, 150 * SECTOR_SIZE as u64, 254 * SECTOR_SIZE as u64)]);
+    assert_eq!(repaired[..result.audio_size as usize],
+               img[..result.audio_size as usize]);
+
+    // Too little audio data
+    assert!(repair::write_repaired_copy(
+        &mut Cursor::new(&img[..100 * SECTOR_SIZE]), &mut Vec::new())
+            .is_err());
+}
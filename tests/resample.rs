@@ -0,0 +1,59 @@
+extern crate nrgrip;
+use nrgrip::resample::Resampler;
+use std::f64::consts::TAU;
+
+/// Interleaved stereo sine waves of 1 kHz (left) and 5 kHz (right).
+fn sine(rate: u32, frames: usize) -> Vec<f64> {
+    (0..frames)
+        .flat_map(|n| {
+            let t = n as f64 / f64::from(rate);
+            vec![0.5 * (TAU * 1000.0 * t).sin(),
+                 0.25 * (TAU * 5000.0 * t).sin()]
+        })
+        .collect()
+}
+
+#[test]
+fn cd_to_48k() {
+    let input: Vec<i16> = sine(44100, 44100).iter()
+        .map(|sample| (sample * 32768.0).round() as i16)
+        .collect();
+    let mut resampler = Resampler::new(44100, 48000, 2);
+    let mut output = Vec::new();
+    for chunk in input.chunks(1234) {
+        resampler.process(chunk, &mut output);
+    }
+    resampler.flush(&mut output);
+    assert_eq!(output.len(), 2 * 48000);
+
+    // Away from the edges, the output matches the signal sampled at 48 kHz
+    let expected = sine(48000, 48000);
+    let error = output.iter().zip(&expected)
+        .skip(2 * 100)
+        .take(2 * 47800)
+        .map(|(&sample, expected)| (f64::from(sample) - expected).abs())
+        .fold(0.0, f64::max);
+    assert!(error < 1e-3, "error = {}", error);
+}
+
+#[test]
+fn length() {
+    let mut resampler = Resampler::new(44100, 48000, 1);
+    let mut output = Vec::new();
+    resampler.process(&[1000; 147], &mut output);
+    resampler.process(&[1000; 1], &mut output);
+    resampler.flush(&mut output);
+    assert_eq!(output.len(), 162);
+    assert!(output[50..110].iter()
+            .all(|&sample| (sample - 1000.0 / 32768.0).abs() < 1e-4));
+
+    // Same rates
+    let mut resampler = Resampler::new(44100, 44100, 1);
+    let mut output = Vec::new();
+    resampler.process(&[1, 2, 3, -4], &mut output);
+    resampler.flush(&mut output);
+    let expected = [1.0, 2.0, 3.0, -4.0];
+    assert!(output.iter().zip(&expected)
+            .all(|(sample, expected)| (sample * 32768.0 - expected).abs()
+                 < 1e-3));
+}
@@ -0,0 +1,52 @@
+extern crate nrgrip;
+use nrgrip::metadata;
+use nrgrip::metadata::track::Track;
+use std::io::Cursor;
+
+mod common;
+use common::{SECTOR_SIZE, TrackSpec};
+
+#[test]
+fn track_samples() {
+    let tracks = [TrackSpec::new(150, 3), TrackSpec::new(75, 2)];
+    let img = common::build_nrg_v2(&tracks);
+    let metadata = metadata::read_from_slice(&img)
+        .expect("read_from_slice()");
+    let tracks: Vec<Track> = metadata.tracks().collect();
+
+    let samples: Vec<[i16; 2]> = tracks[1].samples(Cursor::new(&img))
+        .expect("samples()")
+        .collect::<Result<_, _>>()
+        .expect("samples");
+    assert_eq!(samples.len(), 2 * 588);
+    assert_eq!(samples[0], [0x0201, 0x0403]);
+    let last = &img[tracks[1].track_end as usize - 4..][..4];
+    assert_eq!(samples[2 * 588 - 1],
+               [i16::from_le_bytes([last[0], last[1]]),
+                i16::from_le_bytes([last[2], last[3]])]);
+
+    // The sub-channel is skipped
+    let raw96 = common::make_raw96_image(&img, 230);
+    let metadata = metadata::read_from_slice(&raw96)
+        .expect("read_from_slice()");
+    let track = metadata.tracks().nth(1).expect("track 2");
+    let raw96_samples = track.samples(Cursor::new(&raw96))
+        .expect("samples()");
+    assert_eq!(raw96_samples.size_hint(), (0, Some(2 * 588)));
+    assert!(raw96_samples.map(Result::unwrap).eq(samples));
+}
+
+#[test]
+fn truncated_image() {
+    let tracks = [TrackSpec::new(150, 10)];
+    let img = common::build_nrg_v2(&tracks);
+    let metadata = metadata::read_from_slice(&img)
+        .expect("read_from_slice()");
+    let track = metadata.tracks().next().expect("track 1");
+
+    let truncated = &img[..155 * SECTOR_SIZE];
+    let mut samples = track.samples(Cursor::new(truncated))
+        .expect("samples()");
+    assert!(samples.next().expect("error").is_err());
+    assert!(samples.next().is_none());
+}
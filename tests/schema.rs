@@ -0,0 +1,283 @@
+//! The JSON and TSV output may only change within a schema version by
+//! adding members and fields (which means extending the lists below); any
+//! other change to these lists must come with a new
+//! nrgrip::json::SCHEMA_VERSION.
+
+extern crate nrgrip;
+use nrgrip::accuraterip::NrgAccurateRipTrack;
+use nrgrip::analysis::NrgTrackAnalysis;
+use nrgrip::cdtext::NrgCdText;
+use nrgrip::editor::{self, NrgDaoxEdit};
+use nrgrip::gaps::NrgGap;
+use nrgrip::hidden::NrgHiddenData;
+use nrgrip::json::{self, JsonValue};
+use nrgrip::metadata;
+use nrgrip::metadata::raw;
+use nrgrip::tsv::{self, TsvRecord};
+use nrgrip::verify::{self, NrgSuspicion, NrgSuspiciousRegion};
+use std::io::Cursor;
+
+mod common;
+use common::TrackSpec;
+
+/// Collects the paths of the members of `value` in order and without
+/// duplicates, e.g. "disc.length_msf", array items being denoted by "[]".
+fn member_paths(value: &JsonValue, prefix: &str, paths: &mut Vec<String>) {
+    match *value {
+        JsonValue::Object(ref members) => for (name, member) in members {
+            let path = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{}.{}", prefix, name)
+            };
+            if !paths.contains(&path) {
+                paths.push(path.clone());
+            }
+            member_paths(member, &path, paths);
+        },
+        JsonValue::Array(ref items) => for item in items {
+            member_paths(item, &format!("{}[]", prefix), paths);
+        },
+        _ => (),
+    }
+}
+
+fn json_paths(value: &JsonValue) -> Vec<String> {
+    let mut paths = Vec::new();
+    member_paths(value, "", &mut paths);
+    paths
+}
+
+/// Returns the kind and the number of fields of the records, in order and
+/// without duplicates.
+fn tsv_layout(records: &[TsvRecord]) -> Vec<(&str, usize)> {
+    let mut layout = Vec::new();
+    for record in records {
+        let entry = (record.fields()[0].as_str(), record.fields().len());
+        if !layout.contains(&entry) {
+            layout.push(entry);
+        }
+    }
+    layout
+}
+
+/// Builds an image with an ISRC, CD-Text, a short track and a skipped
+/// chunk, so that the optional members are present.
+fn image() -> Vec<u8> {
+    let tracks = [TrackSpec::new(150, 300), TrackSpec::new(0, 75)];
+    let mut fd = Cursor::new(common::build_nrg_v2(&tracks));
+    let edit = NrgDaoxEdit::new().isrc(1, "FRZ039800212");
+    editor::edit_daox(&mut fd, &edit).expect("edit_daox()");
+    let cd_text = NrgCdText::from_cue_sheet(
+        "TITLE Album\nTRACK 01 AUDIO\nTRACK 02 AUDIO\n");
+    editor::add_cd_text(&mut fd, &cd_text).expect("add_cd_text()");
+    let mut img = fd.into_inner();
+    let end = img.windows(4).rposition(|id| id == b"END!").unwrap();
+    img.splice(end..end, b"RELO\0\0\0\x04\0\0\0\0".iter().cloned());
+    img
+}
+
+#[test]
+fn schema_version() {
+    assert_eq!(json::SCHEMA_VERSION, 1);
+    assert_eq!(tsv::image_to_tsv("a.nrg").to_string(), "image\ta.nrg\t1");
+}
+
+#[test]
+fn metadata_schema() {
+    let metadata = metadata::read_from_slice(&image())
+        .expect("read_from_slice()");
+
+    assert_eq!(json_paths(&json::metadata_to_json(&metadata)), [
+        "file_size",
+        "nrg_version",
+        "chunk_offset",
+        "sector_size",
+        "disc",
+        "disc.length_sectors",
+        "disc.length_seconds",
+        "disc.length_msf",
+        "disc.cddb_disc_id",
+        "disc.musicbrainz_disc_id",
+        "tracks",
+        "tracks[].number",
+        "tracks[].mode",
+        "tracks[].isrc",
+        "tracks[].isrc_elements",
+        "tracks[].isrc_elements.country_code",
+        "tracks[].isrc_elements.registrant_code",
+        "tracks[].isrc_elements.year",
+        "tracks[].isrc_elements.designation",
+        "tracks[].title",
+        "tracks[].position_sectors",
+        "tracks[].pregap_sectors",
+        "tracks[].length_sectors",
+        "tracks[].length_seconds",
+        "tracks[].length_msf",
+        "tracks[].length_bytes",
+        "tracks[].sector_size",
+        "tracks[].index0",
+        "tracks[].index1",
+        "tracks[].track_end",
+        "chunks",
+        "chunks.CUEX",
+        "chunks.CUEX.size",
+        "chunks.CUEX.tracks",
+        "chunks.CUEX.tracks[].mode",
+        "chunks.CUEX.tracks[].track_number",
+        "chunks.CUEX.tracks[].index_number",
+        "chunks.CUEX.tracks[].padding",
+        "chunks.CUEX.tracks[].position_sectors",
+        "chunks.DAOX",
+        "chunks.DAOX.size",
+        "chunks.DAOX.size2",
+        "chunks.DAOX.upc",
+        "chunks.DAOX.padding",
+        "chunks.DAOX.toc_type",
+        "chunks.DAOX.first_track",
+        "chunks.DAOX.last_track",
+        "chunks.DAOX.tracks",
+        "chunks.DAOX.tracks[].isrc",
+        "chunks.DAOX.tracks[].sector_size",
+        "chunks.DAOX.tracks[].data_mode",
+        "chunks.DAOX.tracks[].unknown",
+        "chunks.DAOX.tracks[].index0",
+        "chunks.DAOX.tracks[].index1",
+        "chunks.DAOX.tracks[].track_end",
+        "chunks.CDTX",
+        "chunks.CDTX.size",
+        "chunks.CDTX.blocks",
+        "chunks.CDTX.blocks[].number",
+        "chunks.CDTX.blocks[].language",
+        "chunks.CDTX.blocks[].language_name",
+        "chunks.CDTX.blocks[].text",
+        "chunks.CDTX.blocks[].text.title",
+        "chunks.CDTX.blocks[].text.performer",
+        "chunks.CDTX.blocks[].text.songwriter",
+        "chunks.CDTX.blocks[].text.composer",
+        "chunks.CDTX.blocks[].text.arranger",
+        "chunks.CDTX.blocks[].text.message",
+        "chunks.CDTX.blocks[].text.genre_code",
+        "chunks.CDTX.blocks[].text.genre",
+        "chunks.CDTX.blocks[].text.upc",
+        "chunks.CDTX.blocks[].text.tracks",
+        "chunks.CDTX.blocks[].text.tracks[].title",
+        "chunks.CDTX.blocks[].text.tracks[].performer",
+        "chunks.CDTX.blocks[].text.tracks[].songwriter",
+        "chunks.CDTX.blocks[].text.tracks[].composer",
+        "chunks.CDTX.blocks[].text.tracks[].arranger",
+        "chunks.CDTX.blocks[].text.tracks[].message",
+        "chunks.CDTX.blocks[].text.tracks[].isrc",
+        "chunks.SINF",
+        "chunks.SINF.size",
+        "chunks.SINF.nb_tracks",
+        "chunks.MTYP",
+        "chunks.MTYP.size",
+        "chunks.MTYP.unknown",
+        "chunks.AFNM",
+        "chunks.AFNM.size",
+        "chunks.AFNM.tracks",
+        "skipped_chunks",
+        "skipped_chunk_regions",
+        "skipped_chunk_regions[].id",
+        "skipped_chunk_regions[].offset",
+        "skipped_chunk_regions[].size",
+        "warnings",
+        "red_book_warnings",
+        "red_book_warnings[].kind",
+        "red_book_warnings[].track_number",
+        "red_book_warnings[].length_sectors",
+        "red_book_warnings[].message",
+    ]);
+
+    assert_eq!(tsv_layout(&tsv::metadata_to_tsv(&metadata)),
+               [("track", 10), ("red_book", 5)]);
+}
+
+#[test]
+fn verification_schema() {
+    let mut verification = verify::verify(&mut Cursor::new(image()), true)
+        .expect("verify()");
+    verification.suspicious_regions.push(NrgSuspiciousRegion {
+        track_number: 1,
+        first_sector: 10,
+        sectors: 8,
+        kind: NrgSuspicion::ZeroFilled,
+    });
+
+    assert_eq!(json_paths(&json::verification_to_json(&verification)), [
+        "checks",
+        "checks[].name",
+        "checks[].passed",
+        "checks[].problems",
+        "suspicious_regions",
+        "suspicious_regions[].track_number",
+        "suspicious_regions[].first_sector",
+        "suspicious_regions[].sectors",
+        "suspicious_regions[].kind",
+        "red_book_warnings",
+        "red_book_warnings[].kind",
+        "red_book_warnings[].track_number",
+        "red_book_warnings[].length_sectors",
+        "red_book_warnings[].message",
+        "audio_sha1",
+        "passed",
+    ]);
+
+    let records = tsv::verification_to_tsv(&verification);
+    assert_eq!(tsv_layout(&records),
+               [("check", 3), ("suspicious", 5), ("red_book", 5),
+                ("audio_sha1", 2), ("result", 2)]);
+}
+
+#[test]
+fn record_schemas() {
+    let gap = NrgGap { track_number: 2, sectors: 150 };
+    assert_eq!(json_paths(&json::gap_to_json(&gap)),
+               ["track_number", "sectors"]);
+    assert_eq!(tsv::gap_to_tsv(&gap).fields().len(), 3);
+
+    let hidden = NrgHiddenData {
+        range: 2352..4704,
+        sector_size: 2352,
+        nonzero_bytes: 10,
+    };
+    assert_eq!(json_paths(&json::hidden_data_to_json(&hidden)),
+               ["start", "end", "sector_size", "sectors", "nonzero_bytes"]);
+    assert_eq!(tsv::hidden_data_to_tsv(&hidden).fields().len(), 5);
+
+    let analysis = NrgTrackAnalysis {
+        track_number: 1,
+        samples: 588,
+        leading_silence: 0,
+        trailing_silence: 0,
+        peak: 1000,
+        rms: 500.0,
+        clipped_samples: 0,
+    };
+    assert_eq!(json_paths(&json::track_analysis_to_json(&analysis)),
+               ["track_number", "samples", "leading_silence",
+                "trailing_silence", "audio_samples", "silent", "peak_dbfs",
+                "rms_dbfs", "clipped_samples"]);
+    assert_eq!(tsv::track_analysis_to_tsv(&analysis).fields().len(), 10);
+
+    let track = NrgAccurateRipTrack {
+        track_number: 1,
+        crc_v1: 1,
+        crc_v2: 2,
+        confidence: Some(3),
+    };
+    assert_eq!(json_paths(&json::accuraterip_to_json(&track)),
+               ["track_number", "crc_v1", "crc_v2", "confidence",
+                "accurate"]);
+    assert_eq!(tsv::accuraterip_to_tsv(&track).fields().len(), 5);
+
+    let chunks = raw::read_raw_chunks(&mut Cursor::new(image()))
+        .expect("read_raw_chunks()");
+    assert_eq!(json_paths(&json::chunk_listing_to_json(&chunks[0])),
+               ["id", "offset", "size", "status"]);
+    assert_eq!(json_paths(&json::raw_chunk_to_json(&chunks[0])),
+               ["id", "offset", "size", "payload"]);
+    assert_eq!(tsv::chunk_listing_to_tsv(&chunks[0]).fields().len(), 5);
+    assert_eq!(tsv::raw_chunk_to_tsv(&chunks[0]).fields().len(), 5);
+}
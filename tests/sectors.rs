@@ -0,0 +1,133 @@
+extern crate nrgrip;
+use nrgrip::sectors::{self, NrgSectorProblem, NrgSectorRepair};
+
+/// Builds a valid Mode 1 sector at `lba`.
+fn mode1_sector(lba: i32) -> Vec<u8> {
+    let mut sector = vec![0u8; 2352];
+    sector[..12].copy_from_slice(&sectors::SYNC_PATTERN);
+    let address = (lba + 150) as u32;
+    let bcd = |value: u32| (value / 10 * 16 + value % 10) as u8;
+    sector[12] = bcd(address / 75 / 60);
+    sector[13] = bcd(address / 75 % 60);
+    sector[14] = bcd(address % 75);
+    sector[15] = 1;
+    for (i, b) in sector[16..2064].iter_mut().enumerate() {
+        *b = (i * 7 % 253) as u8;
+    }
+    let edc = sectors::edc(&sector[..2064]);
+    sector[2064..2068].copy_from_slice(&edc.to_le_bytes());
+    sectors::write_ecc(&mut sector);
+    sector
+}
+
+/// Builds an empty Mode 1 sector at 00:02:00 (LBA 0), without its EDC.
+fn empty_mode1_sector() -> Vec<u8> {
+    let mut sector = vec![0u8; 2352];
+    sector[..12].copy_from_slice(&sectors::SYNC_PATTERN);
+    sector[12..16].copy_from_slice(&[0x00, 0x02, 0x00, 0x01]);
+    sector
+}
+
+/// EDC of the empty Mode 1 sector at 00:02:00.
+const EMPTY_MODE1_EDC: [u8; 4] = [0xC5, 0x13, 0x68, 0x2B];
+
+#[test]
+fn known_edc() {
+    // Check value of the CRC-32/CD-ROM-EDC in the CRC catalogue
+    assert_eq!(sectors::edc(b"123456789"), 0x6EC2_EDC4);
+
+    let mut sector = empty_mode1_sector();
+    assert_eq!(sectors::edc(&sector[..2064]).to_le_bytes(), EMPTY_MODE1_EDC);
+    assert_eq!(sectors::check_sector(&sector, 0),
+               Some(NrgSectorProblem::Edc));
+    sector[2064..2068].copy_from_slice(&EMPTY_MODE1_EDC);
+    assert_eq!(sectors::check_sector(&sector, 0), None);
+    sector[2067] ^= 0x01;
+    assert_eq!(sectors::check_sector(&sector, 0),
+               Some(NrgSectorProblem::Edc));
+}
+
+/// Non-null P and Q parity bytes of the empty Mode 1 sector at 00:02:00,
+/// with their offsets in the sector.
+const EMPTY_MODE1_ECC: [(usize, u8); 36] = [
+    (2077, 0xF7), (2079, 0xF5), (2150, 0x52), (2151, 0x35), (2152, 0xB8),
+    (2153, 0x7D), (2163, 0xF5), (2165, 0xF4), (2236, 0x97), (2237, 0x26),
+    (2238, 0xD0), (2239, 0x56), (2249, 0x41), (2270, 0x2D), (2271, 0x17),
+    (2272, 0x2E), (2273, 0x1B), (2274, 0xB1), (2275, 0x48), (2276, 0xB2),
+    (2277, 0x44), (2295, 0x65), (2297, 0xC2), (2299, 0xE6), (2301, 0x43),
+    (2322, 0x45), (2323, 0x3C), (2324, 0x53), (2325, 0x75), (2326, 0x33),
+    (2327, 0x2B), (2328, 0x25), (2329, 0x62), (2347, 0x90), (2349, 0xC1),
+    (2351, 0x12),
+];
+
+#[test]
+fn known_mode1_sector() {
+    let mut expected = empty_mode1_sector();
+    expected[2064..2068].copy_from_slice(&EMPTY_MODE1_EDC);
+    for &(pos, b) in &EMPTY_MODE1_ECC {
+        expected[pos] = b;
+    }
+
+    let mut sector = expected.clone();
+    for b in &mut sector[2076..] {
+        *b = 0;
+    }
+    sectors::write_ecc(&mut sector);
+    assert_eq!(sector, expected);
+
+    // The known parity is enough to correct damaged data
+    sector[20] = 0xFF;
+    sector[1000] = 0x42;
+    assert_eq!(sectors::repair_sector(&mut sector, 0),
+               NrgSectorRepair::Repaired);
+    assert_eq!(sector, expected);
+}
+
+#[test]
+fn repair_mode1_sectors() {
+    let sector = mode1_sector(1000);
+    assert_eq!(sectors::check_sector(&sector, 1000), None);
+    let mut copy = sector.clone();
+    assert_eq!(sectors::repair_sector(&mut copy, 1000),
+               NrgSectorRepair::Intact);
+
+    // Single wrong bytes, in the sync pattern, header, data and EDC
+    for &pos in &[0, 14, 100, 1500, 2065] {
+        let mut damaged = sector.clone();
+        damaged[pos] ^= 0x5A;
+        assert!(sectors::check_sector(&damaged, 1000).is_some());
+        assert_eq!(sectors::repair_sector(&mut damaged, 1000),
+                   NrgSectorRepair::Repaired, "byte {}", pos);
+        assert_eq!(damaged, sector);
+    }
+
+    // Wrong parity bytes don't damage the sector
+    let mut damaged = sector.clone();
+    damaged[2300] ^= 0x5A;
+    assert_eq!(sectors::repair_sector(&mut damaged, 1000),
+               NrgSectorRepair::Intact);
+
+    // Wrong bytes scattered over several codewords, and a burst in a row
+    let mut damaged = sector.clone();
+    for pos in (20..2000).step_by(301) {
+        damaged[pos] = !damaged[pos];
+    }
+    for b in &mut damaged[500..540] {
+        *b = 0;
+    }
+    assert_eq!(sectors::repair_sector(&mut damaged, 1000),
+               NrgSectorRepair::Repaired);
+    assert_eq!(damaged, sector);
+
+    // Too many errors
+    let mut damaged = sector.clone();
+    for b in &mut damaged[100..1100] {
+        *b = 0xFF;
+    }
+    let copy = damaged.clone();
+    assert_eq!(sectors::check_sector(&damaged, 1000),
+               Some(NrgSectorProblem::Edc));
+    assert_eq!(sectors::repair_sector(&mut damaged, 1000),
+               NrgSectorRepair::Irrecoverable);
+    assert_eq!(damaged, copy);
+}
@@ -0,0 +1,178 @@
+extern crate nrgrip;
+use nrgrip::metadata;
+use nrgrip::metadata::raw;
+use nrgrip::sessions;
+use nrgrip::verify;
+use std::convert::TryInto;
+use std::io::Cursor;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+/// Number of sectors between the sessions of the disc.
+const SESSION_GAP: i32 = 11400;
+
+/// Builds a two-session image from the images built by
+/// common::build_nrg_v2() for each session.
+fn build_two_sessions(first: &[u8], second: &[u8]) -> Vec<u8> {
+    let chunks1 = raw::read_raw_chunks(&mut Cursor::new(first)).unwrap();
+    let mut chunks2 = raw::read_raw_chunks(&mut Cursor::new(second))
+        .unwrap();
+    let size1 = chunks1[0].offset;
+    let tracks1 = u32::from_be_bytes(chunks1[2].payload[..4].try_into()
+                                     .unwrap()) as u8;
+
+    // Renumber and move the tracks of the second session
+    for entry in chunks2[0].payload.chunks_mut(8) {
+        if entry[1] != 0 && entry[1] != 0xAA {
+            entry[1] += tracks1;
+        }
+        let position = i32::from_be_bytes(entry[4..].try_into().unwrap())
+            + size1 as i32 / SECTOR_SIZE as i32 + SESSION_GAP;
+        entry[4..].copy_from_slice(&position.to_be_bytes());
+    }
+    let daox = &mut chunks2[1].payload;
+    daox[20] += tracks1;
+    daox[21] += tracks1;
+    for block in daox[22..].chunks_mut(42) {
+        for pos in [18, 26, 34] {
+            let moved = u64::from_be_bytes(block[pos..pos + 8].try_into()
+                                           .unwrap()) + size1;
+            block[pos..pos + 8].copy_from_slice(&moved.to_be_bytes());
+        }
+    }
+
+    // The file names of the AFNM chunk cover the tracks of both sessions
+    let tracks2 = u32::from_be_bytes(chunks2[2].payload[..4].try_into()
+                                     .unwrap()) as u8;
+    let mut afnm = raw::NrgRawChunk {
+        id: "AFNM".to_string(),
+        offset: 0,
+        payload: Vec::new(),
+    };
+    for number in 1..=tracks1 + tracks2 {
+        afnm.payload.extend(format!("Track {}.wav", number).as_bytes());
+        afnm.payload.push(0);
+    }
+
+    let mut img = first[..size1 as usize].to_vec();
+    img.extend(&second[..chunks2[0].offset as usize]);
+    let chunk_offset = img.len() as u64;
+    for chunk in [&chunks1[0], &chunks1[1], &chunks2[0], &chunks2[1],
+                  &chunks1[2], &chunks2[2], &chunks1[3], &afnm,
+                  &chunks1[5]] {
+        img.extend(chunk.id.as_bytes());
+        img.extend(&(chunk.payload.len() as u32).to_be_bytes());
+        img.extend(&chunk.payload);
+    }
+    img.extend(b"NER5");
+    img.extend(&chunk_offset.to_be_bytes());
+    img
+}
+
+#[test]
+fn split_sessions() {
+    let first = common::build_nrg_v2(&[TrackSpec::new(150, 10),
+                                       TrackSpec::new(0, 10)]);
+    let second = common::build_nrg_v2(&[TrackSpec::new(150, 20)]);
+    let img = build_two_sessions(&first, &second);
+    let mut fd = Cursor::new(&img);
+
+    let sessions = sessions::read_sessions(&mut fd).expect("read_sessions()");
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0].track_count, 2);
+    assert_eq!(sessions[1].track_count, 1);
+    assert_eq!(sessions[1].data_range,
+               170 * SECTOR_SIZE as u64..340 * SECTOR_SIZE as u64);
+    let ids: Vec<&str> = sessions[1].chunks.iter()
+        .map(|chunk| chunk.id.as_str())
+        .collect();
+    assert_eq!(ids, ["CUEX", "DAOX", "MTYP"]);
+
+    // The first session keeps its layout
+    let mut out = Vec::new();
+    sessions::write_session(&mut fd, &sessions[0], &mut out)
+        .expect("write_session()");
+    assert_eq!(out[..170 * SECTOR_SIZE], first[..170 * SECTOR_SIZE]);
+    let metadata = metadata::read_from_slice(&out).expect("read_from_slice()");
+    assert_eq!(metadata.sinf_chunk().unwrap().nb_tracks(), 2);
+
+    // The second session starts at the beginning of the disc
+    let mut out = Vec::new();
+    let size = sessions::write_session(&mut fd, &sessions[1], &mut out)
+        .expect("write_session()");
+    assert_eq!(size, out.len() as u64);
+    assert_eq!(out[..170 * SECTOR_SIZE], second[..170 * SECTOR_SIZE]);
+    let metadata = metadata::read_from_slice(&out).expect("read_from_slice()");
+    let tracks: Vec<_> = metadata.tracks()
+        .map(|track| (track.number, track.position_sectors, track.index0,
+                      track.index1, track.track_end))
+        .collect();
+    assert_eq!(tracks, [(3, 0, 0, 150 * SECTOR_SIZE as u64,
+                         170 * SECTOR_SIZE as u64)]);
+    let positions: Vec<i32> = metadata.cuex_chunk().unwrap().tracks().iter()
+        .map(|entry| entry.position_sectors())
+        .collect();
+    assert_eq!(positions, [-150, -150, 0, 20]);
+    let verification = verify::verify(&mut Cursor::new(&out), false)
+        .expect("verify()");
+    assert!(verification.passed());
+
+    // Single-session images yield a single session
+    let sessions = sessions::read_sessions(&mut Cursor::new(&first))
+        .expect("read_sessions()");
+    assert_eq!(sessions.len(), 1);
+}
+
+#[test]
+fn session_metadata() {
+    let first = common::build_nrg_v2(&[TrackSpec::new(150, 10),
+                                       TrackSpec::new(0, 10)]);
+    let second = common::build_nrg_v2(&[TrackSpec::new(150, 20)]);
+    let img = build_two_sessions(&first, &second);
+    let mut fd = Cursor::new(&img);
+
+    // The first session keeps its positions
+    let metadata = sessions::read_session_metadata(&mut fd, 1)
+        .expect("read_session_metadata()");
+    let tracks: Vec<_> = metadata.tracks()
+        .map(|track| (track.number, track.position_sectors, track.title))
+        .collect();
+    assert_eq!(tracks, [(1, 0, Some("Track 1".to_string())),
+                        (2, 10, Some("Track 2".to_string()))]);
+    assert_eq!(metadata.sinf_chunk().unwrap().nb_tracks(), 2);
+    assert_eq!(metadata.file_size(), img.len() as u64);
+
+    // The second session starts at the beginning of the disc, but its data
+    // is still read from the image
+    let metadata = sessions::read_session_metadata(&mut fd, 2)
+        .expect("read_session_metadata()");
+    let tracks: Vec<_> = metadata.tracks()
+        .map(|track| (track.number, track.position_sectors, track.index1,
+                      track.title))
+        .collect();
+    assert_eq!(tracks, [(3, 0, 320 * SECTOR_SIZE as u64,
+                         Some("Track 3".to_string()))]);
+    assert_eq!(metadata.sinf_chunk().unwrap().nb_tracks(), 1);
+    assert_eq!(metadata.chunk_offset(), 340 * SECTOR_SIZE as u64);
+
+    assert!(sessions::read_session_metadata(&mut fd, 3).is_err());
+}
+
+#[test]
+fn invalid_session_positions() {
+    // Image found by fuzzing: the DAOX track's index #1 precedes its index
+    // #0, which made moving the session's positions underflow
+    let mut img = common::build_nrg_v2(&[TrackSpec::new(150, 10)]);
+    let daox = img.windows(4).position(|id| id == b"DAOX")
+        .expect("DAOX chunk");
+    let block = daox + 8 + 22;
+    img[block + 18..block + 26].copy_from_slice(&1000u64.to_be_bytes());
+    img[block + 26..block + 34].copy_from_slice(&0u64.to_be_bytes());
+
+    let err = sessions::read_session_metadata(&mut Cursor::new(&img), 1)
+        .expect_err("read_session_metadata()");
+    assert_eq!(err.to_string(),
+               "NRG format error: Invalid DAOX track positions");
+    assert!(sessions::read_sessions(&mut Cursor::new(&img)).is_err());
+}
@@ -0,0 +1,127 @@
+#![cfg(feature = "fs")]
+
+extern crate nrgrip;
+use nrgrip::cdtext::NrgCdText;
+use nrgrip::editor;
+use nrgrip::extraction::{self, AudioFormat, ExtractionOptions};
+use nrgrip::metadata;
+use nrgrip::tags::{self, NrgTags};
+use std::env;
+use std::fs::{self, File};
+use std::io::Cursor;
+
+mod common;
+use common::TrackSpec;
+
+#[test]
+fn tag_wav_file() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 10)];
+    let img = common::write_temp_file("tags.nrg",
+                                      &common::build_nrg_v2(&tracks));
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let wav = env::temp_dir().join("nrgrip-test-tags.wav");
+    let options = ExtractionOptions::new()
+        .format(AudioFormat::Wav)
+        .tracks(&[2])
+        .output_path(&wav);
+    extraction::extract(&mut fd, &img.to_string_lossy(), &metadata, &options)
+        .expect("extract()");
+    let audio = fs::read(&wav).expect("fs::read()");
+
+    let tags = NrgTags::from_metadata(&metadata, Some(2))
+        .expect("from_metadata()")
+        .album("Album");
+    assert_eq!(tags.title, Some("Track 2".to_string()));
+    assert_eq!(tags.track_total, Some(2));
+    tags::write_tags(&wav, &tags).expect("write_tags()");
+    let tagged = fs::read(&wav).expect("fs::read()");
+    assert_eq!(&tagged[..audio.len()][8..], &audio[8..]);
+    assert_eq!(u32::from_le_bytes([tagged[4], tagged[5], tagged[6],
+                                   tagged[7]]) as usize,
+               tagged.len() - 8);
+
+    let list = &tagged[audio.len()..];
+    assert_eq!(&list[..4], b"LIST");
+    assert_eq!(&list[8..22], b"INFOITRK\x02\x00\x00\x002\x00");
+    assert!(list.windows(12).any(|entry| entry == b"IPRD\x06\x00\x00\x00Albu"));
+
+    // Tagging again replaces the list
+    tags::write_tags(&wav, &tags.artist("Artist")).expect("write_tags()");
+    let retagged = fs::read(&wav).expect("fs::read()");
+    assert_eq!(&retagged[8..audio.len()], &audio[8..]);
+    let list = &retagged[audio.len()..];
+    assert_eq!(list.windows(4).filter(|&id| id == b"LIST").count(), 1);
+    assert!(list.windows(5).any(|value| value == b"Album"));
+    assert!(list.windows(6).any(|value| value == b"Artist"));
+}
+
+#[test]
+fn tags_from_cd_text() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(0, 10)];
+    let mut fd = Cursor::new(common::build_nrg_v2(&tracks));
+    let mut cd_text = NrgCdText::from_cue_sheet(
+        "TITLE Album\nPERFORMER Band\nSONGWRITER Writer\n\
+         TRACK 01 AUDIO\nTRACK 02 AUDIO\nPERFORMER Guest\n\
+         ISRC FRXYZ2600002\n");
+    cd_text.genre_code = 5;
+    cd_text.tracks[1].message = "Live".to_string();
+    editor::add_cd_text(&mut fd, &cd_text).expect("add_cd_text()");
+    let metadata = metadata::read_from_slice(fd.get_ref())
+        .expect("read_from_slice()");
+
+    let tags = NrgTags::from_metadata(&metadata, Some(2))
+        .expect("from_metadata()");
+    assert_eq!(tags.album.as_deref(), Some("Album"));
+    assert_eq!(tags.artist.as_deref(), Some("Guest"));
+    assert_eq!(tags.songwriter.as_deref(), Some("Writer"));
+    assert_eq!(tags.genre.as_deref(), Some("Classical"));
+    assert_eq!(tags.comment.as_deref(), Some("Live"));
+    assert_eq!(tags.isrc.as_deref(), Some("FRXYZ2600002"));
+    let fields = tags.fields();
+    assert!(fields.contains(&("LYRICIST", "Writer".to_string())));
+    assert!(fields.contains(&("GENRE", "Classical".to_string())));
+
+    let tags = NrgTags::from_metadata(&metadata, Some(1))
+        .expect("from_metadata()");
+    assert_eq!(tags.artist.as_deref(), Some("Band"));
+    assert_eq!((tags.comment, tags.isrc), (None, None));
+}
+
+#[test]
+fn tag_flac_file() {
+    let metadata = metadata::read_from_slice(
+        &common::build_nrg_v2(&[TrackSpec::new(150, 10)]))
+        .expect("read_from_slice()");
+    let tags = NrgTags::from_metadata(&metadata, Some(1))
+        .expect("from_metadata()");
+
+    // Minimal FLAC file: STREAMINFO block, then frames
+    let mut flac = b"fLaC\x80\x00\x00\x22".to_vec();
+    flac.extend_from_slice(&[0x11; 34]);
+    flac.extend_from_slice(b"frames");
+    let path = common::write_temp_file("tags.flac", &flac);
+
+    tags::write_tags(&path, &tags).expect("write_tags()");
+    let tagged = fs::read(&path).expect("fs::read()");
+    assert_eq!(&tagged[..4], b"fLaC");
+    assert_eq!(tagged[4], 0x00); // STREAMINFO is no longer the last block
+    assert_eq!(&tagged[8..42], &flac[8..42]);
+    assert_eq!(tagged[42], 0x84); // Vorbis comments
+    assert!(tagged.ends_with(b"frames"));
+    let comments = String::from_utf8_lossy(&tagged[46..]).into_owned();
+    assert!(comments.contains("TRACKNUMBER=1"));
+    assert!(comments.contains("TITLE=Track 1"));
+
+    // Tagging again keeps the other comments
+    tags::write_tags(&path, &NrgTags::default().album("Album"))
+        .expect("write_tags()");
+    let retagged = fs::read(&path).expect("fs::read()");
+    let comments = String::from_utf8_lossy(&retagged[46..]).into_owned();
+    assert!(comments.contains("TITLE=Track 1"));
+    assert!(comments.contains("ALBUM=Album"));
+    assert!(retagged.ends_with(b"frames"));
+}
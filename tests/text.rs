@@ -0,0 +1,73 @@
+extern crate nrgrip;
+use nrgrip::metadata;
+use nrgrip::text::{self, Align, Style, TextTable};
+
+mod common;
+use common::TrackSpec;
+
+#[test]
+fn table_alignment() {
+    let mut table = TextTable::new()
+        .column("Track", Align::Right)
+        .column("Name", Align::Left)
+        .column("Size", Align::Right);
+    table.row(vec![("1".to_string(), Style::Plain),
+                   ("Intro".to_string(), Style::Plain),
+                   ("42".to_string(), Style::Warning)]);
+    table.row(vec![("10".to_string(), Style::Plain),
+                   ("A".to_string(), Style::Plain),
+                   ("1234".to_string(), Style::Plain)]);
+
+    assert_eq!(table.render(false),
+               "  Track  Name   Size\n\
+               \x20     1  Intro    42\n\
+               \x20    10  A      1234\n");
+    assert_eq!(table.render(true).lines().nth(1),
+               Some("      1  Intro    \x1b[33m42\x1b[0m"));
+}
+
+#[test]
+fn metadata_text() {
+    let tracks: Vec<TrackSpec> =
+        (0..10).map(|_| TrackSpec::new(0, 75)).collect();
+    let metadata = metadata::read_from_slice(&common::build_nrg_v2(&tracks))
+        .expect("read_from_slice()");
+
+    let plain = text::metadata_to_text(&metadata, false);
+    assert!(!plain.contains('\x1b'));
+    assert!(plain.starts_with("Image\n"));
+    assert!(plain.contains("\nCUEX chunk (Cue Sheet), "));
+    assert!(plain.contains("\n  lead-out      1  0x01"));
+
+    // The DAOX track numbers are followed by their (empty) ISRCs, in a
+    // column starting at the same position on each line
+    let isrc_columns: Vec<usize> = plain.lines()
+        .skip_while(|line| !line.starts_with("  Track  ISRC"))
+        .take_while(|line| !line.is_empty())
+        .map(|line| line.find(['I', '-']).unwrap())
+        .collect();
+    assert_eq!(isrc_columns.len(), 11);
+    assert!(isrc_columns.iter().all(|&column| column == isrc_columns[0]));
+
+    assert!(plain.contains("\n  Disc length:             750 sectors \
+                            (00:10:00)\n"));
+    assert!(plain.contains("\nTracks\n"));
+    assert!(plain.contains("\n     10  00:00:00       75  00:01:00  176400\n"));
+
+    let colored = text::metadata_to_text(&metadata, true);
+    assert!(colored.starts_with("\x1b[1mImage\x1b[0m\n"));
+}
+
+#[test]
+fn summary_text() {
+    let tracks = [TrackSpec::new(150, 300), TrackSpec::new(75, 450)];
+    let metadata = metadata::read_from_slice(&common::build_nrg_v2(&tracks))
+        .expect("read_from_slice()");
+
+    assert_eq!(text::summary_to_text(&metadata, false),
+               "  Disc length:  825 sectors (00:11:00)\n\
+               \n\
+               \x20 Track  Type      Start    Length  ISRC  Title\n\
+               \x20    01  Audio  00:00:00  00:04:00  -     Track 1\n\
+               \x20    02  Audio  00:05:00  00:06:00  -     Track 2\n");
+}
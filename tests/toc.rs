@@ -0,0 +1,30 @@
+extern crate nrgrip;
+use nrgrip::metadata;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+#[test]
+fn toc_indexes() {
+    let contents = common::build_nrg_v2(&[TrackSpec::new(150, 10),
+                                          TrackSpec::new(75, 20)]);
+    let metadata = metadata::read_from_slice(&contents)
+        .expect("read_from_slice()");
+    let toc = metadata.toc();
+
+    let indexes: Vec<Vec<_>> = toc.tracks.iter()
+        .map(|track| track.indexes.iter()
+             .map(|index| (index.number, index.position_sectors,
+                           index.byte_offset / SECTOR_SIZE as u64))
+             .collect())
+        .collect();
+    assert_eq!(indexes, [vec![(0, -150, 0), (1, 0, 150)],
+                         vec![(0, 10, 160), (1, 85, 235)]]);
+    assert_eq!(toc.lead_out_sectors, Some(105));
+
+    let track = toc.track(2).expect("track()");
+    assert_eq!(track.track.title.as_deref(), Some("Track 2"));
+    assert_eq!(track.index(1).map(|index| index.byte_offset),
+               Some(track.track.index1));
+    assert!(toc.track(3).is_none());
+}
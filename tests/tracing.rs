@@ -0,0 +1,90 @@
+#![cfg(feature = "tracing")]
+
+extern crate nrgrip;
+extern crate tracing;
+use nrgrip::extraction::{self, ExtractionOptions};
+use nrgrip::metadata;
+use std::env;
+use std::fs::File;
+use std::sync::{Arc, Mutex};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+mod common;
+use common::TrackSpec;
+
+/// Subscriber recording the names of the spans created and the number of
+/// events at the error level.
+#[derive(Clone, Default)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<String>>>,
+    errors: Arc<Mutex<usize>>,
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes) -> Id {
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span.metadata().name().to_string());
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event) {
+        if *event.metadata().level() == tracing::Level::ERROR {
+            *self.errors.lock().unwrap() += 1;
+        }
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+fn count(spans: &[String], name: &str) -> usize {
+    spans.iter().filter(|span| *span == name).count()
+}
+
+#[test]
+fn spans() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let img = common::write_temp_file("tracing.nrg",
+                                      &common::build_nrg_v2(&tracks));
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let mut fd = File::open(&img).expect("File::open()");
+        let metadata = metadata::read_nrg_metadata(&mut fd)
+            .expect("read_nrg_metadata()");
+        let template = env::temp_dir().join("nrgrip-test-tracing-{track}");
+        let options = ExtractionOptions::new()
+            .split_tracks(true)
+            .name_template(&template.to_string_lossy());
+        extraction::extract(&mut fd, &img.to_string_lossy(), &metadata,
+                            &options)
+            .expect("extract()");
+    });
+
+    let spans = recorder.spans.lock().unwrap();
+    assert_eq!(count(&spans, "nrg_metadata"), 1);
+    // The footer, the first chunk offset, and the CUEX, DAOX and END!
+    // chunks with their IDs
+    assert!(count(&spans, "nrg_chunk") >= 4);
+    assert_eq!(count(&spans, "nrg_extract"), 1);
+    assert_eq!(count(&spans, "nrg_output"), 2);
+    assert_eq!(*recorder.errors.lock().unwrap(), 0);
+
+    // A truncated image fails within a span
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let contents = common::build_nrg_v2(&tracks);
+        let truncated = &contents[..contents.len() - 20];
+        assert!(metadata::read_from_slice(truncated).is_err());
+    });
+    assert!(*recorder.errors.lock().unwrap() > 0);
+}
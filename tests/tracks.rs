@@ -0,0 +1,98 @@
+extern crate nrgrip;
+use nrgrip::metadata;
+use nrgrip::metadata::track::Track;
+use nrgrip::msf::Msf;
+use std::fs::File;
+
+mod common;
+use common::{SECTOR_SIZE, TrackSpec};
+
+#[test]
+fn tracks() {
+    let tracks = [TrackSpec::new(150, 100), TrackSpec::new(75, 300)];
+    let img = common::write_temp_file("tracks.nrg",
+                                      &common::build_nrg_v2(&tracks));
+    let mut fd = File::open(&img)
+        .expect("File::open()");
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+
+    let tracks: Vec<Track> = metadata.tracks().collect();
+    assert_eq!(tracks.len(), 2);
+
+    assert_eq!(tracks[0].number, 1);
+    assert_eq!(tracks[0].mode, 0x01);
+    assert_eq!(tracks[0].title, Some("Track 1".to_string()));
+    assert_eq!(tracks[0].pregap_sectors(), 150);
+    assert_eq!(tracks[0].sector_count(), 100);
+
+    let start = 325 * SECTOR_SIZE as u64;
+    assert_eq!(tracks[1].number, 2);
+    assert_eq!(tracks[1].isrc, None);
+    assert_eq!(tracks[1].position_sectors, 175);
+    assert_eq!(tracks[1].byte_range(),
+               start..start + 300 * SECTOR_SIZE as u64);
+    assert_eq!(tracks[1].duration(), 4.0);
+    assert_eq!(tracks[1].byte_count(), 300 * SECTOR_SIZE as u64);
+    assert_eq!(tracks[1].pregap_length(), Msf::new(0, 1, 0));
+
+    // The first track's pre-gap isn't part of the disc's length
+    assert_eq!(metadata.total_sectors(), 475);
+    assert_eq!(metadata.total_length(), Msf::new(0, 6, 25));
+    let display = metadata.to_string();
+    assert!(display.contains("\nDisc length: 475 sectors (00:06:25)\n"));
+    assert!(display.contains("\tLength: 300 sectors (00:04:00, 705600 \
+                              Bytes)"));
+}
+
+#[test]
+fn track_byte_ranges() {
+    let tracks = [TrackSpec::new(150, 100), TrackSpec::new(75, 300)];
+    let metadata = metadata::read_from_slice(&common::build_nrg_v2(&tracks))
+        .expect("read_from_slice()");
+
+    let sector_size = SECTOR_SIZE as u64;
+    assert_eq!(metadata.track_byte_range(1),
+               Some(150 * sector_size..250 * sector_size));
+    assert_eq!(metadata.track_sector_count(1), Some(100));
+    assert_eq!(metadata.track_byte_range(2),
+               Some(325 * sector_size..625 * sector_size));
+    assert_eq!(metadata.track_sector_count(2), Some(300));
+    assert_eq!(metadata.track_byte_range(3), None);
+    assert_eq!(metadata.track_sector_count(3), None);
+}
+
+#[test]
+fn chunk_accessors() {
+    let tracks = [TrackSpec::new(150, 100), TrackSpec::new(75, 300)];
+    let metadata = metadata::read_from_slice(&common::build_nrg_v2(&tracks))
+        .expect("read_from_slice()");
+
+    let cuex = metadata.cuex_chunk().expect("cuex_chunk()");
+    assert_eq!(cuex.tracks().len(), 6);
+    assert_eq!(cuex.tracks()[0].position_sectors(), -150);
+
+    let daox = metadata.daox_chunk().expect("daox_chunk()");
+    assert_eq!((daox.first_track(), daox.last_track()), (1, 2));
+    assert_eq!(daox.tracks()[1].sector_size(), SECTOR_SIZE as u16);
+
+    let afnm = metadata.afnm_chunk().expect("afnm_chunk()");
+    assert_eq!(afnm.tracks()[1].name(), "Track 2.wav");
+    assert_eq!(metadata.sinf_chunk().map(|sinf| sinf.nb_tracks()), Some(2));
+    assert!(metadata.skipped_chunks().is_empty());
+}
+
+#[test]
+fn compare_metadata() {
+    let tracks = [TrackSpec::new(150, 100), TrackSpec::new(75, 300)];
+    let metadata = metadata::read_from_slice(&common::build_nrg_v2(&tracks))
+        .expect("read_from_slice()");
+    assert_eq!(metadata.clone(), metadata);
+
+    let tracks = [TrackSpec::new(150, 100), TrackSpec::new(75, 301)];
+    let other = metadata::read_from_slice(&common::build_nrg_v2(&tracks))
+        .expect("read_from_slice()");
+    assert_ne!(other, metadata);
+    assert_eq!(other.cuex_chunk().map(|cuex| cuex.tracks()[..3].to_vec()),
+               metadata.cuex_chunk().map(|cuex| cuex.tracks()[..3].to_vec()));
+}
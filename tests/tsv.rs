@@ -0,0 +1,34 @@
+extern crate nrgrip;
+use nrgrip::metadata;
+use nrgrip::tsv::{self, TsvRecord};
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+#[test]
+fn tsv_escaping() {
+    let record = TsvRecord::new("file")
+        .field("a\tb\\c\n")
+        .optional_field(None::<u8>)
+        .field(42);
+    assert_eq!(record.fields().len(), 4);
+    assert_eq!(record.to_string(), "file\ta\\tb\\\\c\\n\t\t42");
+}
+
+#[test]
+fn metadata_tsv() {
+    let tracks = [TrackSpec::new(150, 75), TrackSpec::new(0, 75)];
+    let metadata = metadata::read_from_slice(&common::build_nrg_v2(&tracks))
+        .expect("read_from_slice()");
+
+    let records = tsv::metadata_to_tsv(&metadata);
+    // Both tracks are shorter than 4 seconds
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[3].to_string(),
+               "red_book\tshort_track\t2\t75\t\
+                Track 02 is shorter than 4 seconds (00:01:00)");
+    let end = 300 * SECTOR_SIZE;
+    assert_eq!(records[1].to_string(),
+               format!("track\t2\t1\tTrack 2\t\t0\t75\t{}\t{}\t{}",
+                       end - 75 * SECTOR_SIZE, end - 75 * SECTOR_SIZE, end));
+}
@@ -0,0 +1,30 @@
+extern crate nrgrip;
+use nrgrip::unicode;
+
+#[test]
+fn nfc() {
+    assert_eq!(unicode::nfc("Cafe\u{301}"), "Café");
+    assert_eq!(unicode::nfc("Café"), "Café");
+    assert_eq!(unicode::nfc("A\u{30A}ngstro\u{308}m"), "Ångström");
+    assert_eq!(unicode::nfc("A\u{308}\u{304}"), "\u{1DE}");
+    // Marks without a precomposed letter are kept, in canonical order
+    assert_eq!(unicode::nfc("q\u{301}"), "q\u{301}");
+    assert_eq!(unicode::nfc("a\u{301}\u{323}"), "\u{1EA1}\u{301}");
+    // Outside Latin-1: kana with dakuten, Vietnamese, Greek and Cyrillic
+    assert_eq!(unicode::nfc("\u{30AB}\u{3099}"), "\u{30AC}");
+    assert_eq!(unicode::nfc("Vie\u{323}\u{302}t"), "Vi\u{1EC7}t");
+    assert_eq!(unicode::nfc("\u{3B1}\u{301}"), "\u{3AC}");
+    assert_eq!(unicode::nfc("\u{439}"), "\u{439}");
+    assert_eq!(unicode::nfc("\u{438}\u{306}"), "\u{439}");
+}
+
+#[test]
+fn to_ascii() {
+    assert_eq!(unicode::to_ascii("Café Ærø"), "Cafe AEro");
+    assert_eq!(unicode::to_ascii("Cafe\u{301}"), "Cafe");
+    assert_eq!(unicode::to_ascii("Straße \u{201C}live\u{201D} \u{2013} 2"),
+               "Strasse \"live\" - 2");
+    assert_eq!(unicode::to_ascii("Łódź"), "Lodz");
+    assert_eq!(unicode::to_ascii("Vi\u{1EC7}t \u{1DE}"), "Viet A");
+    assert_eq!(unicode::to_ascii("東京"), "??");
+}
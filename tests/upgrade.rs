@@ -0,0 +1,65 @@
+extern crate nrgrip;
+use nrgrip::metadata::raw;
+use nrgrip::upgrade;
+use std::convert::TryInto;
+use std::io::Cursor;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+/// Turns an image built by common::build_nrg_v2() into an NRG v1 image.
+fn make_v1_image(img: &[u8]) -> Vec<u8> {
+    let chunks = raw::read_raw_chunks(&mut Cursor::new(img))
+        .expect("read_raw_chunks()");
+    let chunk_offset = chunks[0].offset as usize;
+    let mut v1 = img[..chunk_offset].to_vec();
+    for chunk in chunks {
+        let (id, payload) = match chunk.id.as_str() {
+            "CUEX" => ("CUES", chunk.payload.chunks(8).flat_map(|entry| {
+                let position = i32::from_be_bytes(entry[4..].try_into()
+                                                  .unwrap()) + 150;
+                let msf = [0, (position / 4500) as u8,
+                           (position / 75 % 60) as u8, (position % 75) as u8];
+                entry[..4].iter().chain(&msf).cloned().collect::<Vec<u8>>()
+            }).collect()),
+            "DAOX" => {
+                let tracks = (chunk.payload.len() - 22) / 42;
+                let mut payload = ((22 + 30 * tracks) as u32).to_be_bytes()
+                    .to_vec();
+                payload.extend(&chunk.payload[4..22]);
+                for track in chunk.payload[22..].chunks(42) {
+                    payload.extend(&track[..18]);
+                    for position in track[18..].chunks(8) {
+                        payload.extend(&position[4..]);
+                    }
+                }
+                ("DAOI", payload)
+            },
+            id => (id, chunk.payload.clone()),
+        };
+        v1.extend(id.as_bytes());
+        v1.extend(&(payload.len() as u32).to_be_bytes());
+        v1.extend(payload);
+    }
+    v1.extend(b"NERO");
+    v1.extend(&(chunk_offset as u32).to_be_bytes());
+    v1
+}
+
+#[test]
+fn upgrade_v1_image() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let img = common::build_nrg_v2(&tracks);
+    let v1 = make_v1_image(&img);
+    assert_eq!(&v1[255 * SECTOR_SIZE..][..4], b"CUES");
+
+    let mut out = Vec::new();
+    let size = upgrade::write_v2_copy(&mut Cursor::new(&v1), &mut out)
+        .expect("write_v2_copy()");
+    assert_eq!(size, out.len() as u64);
+    assert!(out == img);
+
+    // NRG v2 images are refused
+    assert!(upgrade::write_v2_copy(&mut Cursor::new(&img), &mut Vec::new())
+            .is_err());
+}
@@ -0,0 +1,158 @@
+extern crate nrgrip;
+extern crate sha1;
+use nrgrip::error::{NrgError, NrgErrorKind};
+use nrgrip::metadata::{self, MetadataOptions};
+use nrgrip::sectors;
+use nrgrip::verify;
+use sha1::{Digest, Sha1};
+use std::io::Cursor;
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+#[test]
+fn verify_valid_image() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let img = common::build_nrg_v2(&tracks);
+
+    let report = verify::verify(&mut Cursor::new(&img), true)
+        .expect("verify()");
+    assert!(report.passed(), "{}", report);
+    assert_eq!(report.checks.len(), 5);
+    assert_eq!(report.suspicious_regions, []);
+
+    let audio = &img[150 * SECTOR_SIZE..255 * SECTOR_SIZE];
+    let sha1: String = Sha1::digest(audio).iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    assert_eq!(report.audio_sha1, Some(sha1));
+}
+
+#[test]
+fn verify_inconsistent_image() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let mut img = common::build_nrg_v2(&tracks);
+
+    // Announce 3 tracks in the SINF chunk
+    let sinf = img.windows(4).position(|id| id == b"SINF")
+        .expect("SINF chunk");
+    img[sinf + 11] = 3;
+
+    let report = verify::verify(&mut Cursor::new(&img), false)
+        .expect("verify()");
+    assert!(!report.passed());
+    let check = report.checks.iter()
+        .find(|check| !check.passed())
+        .expect("failed check");
+    assert_eq!(check.name, "cuex/daox");
+    assert_eq!(check.problems, ["SINF announces 3 tracks, DAOX holds 2"]);
+    assert_eq!(report.audio_sha1, None);
+
+    let report = verify::verify(&mut Cursor::new(&img[..100]), false)
+        .expect("verify()");
+    assert_eq!(report.checks.len(), 1);
+    assert!(!report.checks[0].passed());
+}
+
+#[test]
+fn verify_data_sectors() {
+    let tracks = [TrackSpec::new(0, 20)];
+    let mut img = common::build_nrg_v2(&tracks);
+
+    // Turn the track into a Mode 1 data track, whose index 1 is at LBA -150
+    for i in 0..20 {
+        let sector = &mut img[i * SECTOR_SIZE..(i + 1) * SECTOR_SIZE];
+        sector[..12].copy_from_slice(&sectors::SYNC_PATTERN);
+        sector[12..16].copy_from_slice(&[0x00, 0x00, (i / 10 * 16 + i % 10)
+                                         as u8, 0x01]);
+        let edc = sectors::edc(&sector[..2064]);
+        sector[2064..2068].copy_from_slice(&edc.to_le_bytes());
+        assert_eq!(sectors::check_sector(sector, i as i32 - 150), None);
+    }
+    let cuex = 20 * SECTOR_SIZE + 8;
+    for entry in 0..4 {
+        img[cuex + entry * 8] = 0x41;
+    }
+
+    let report = verify::verify(&mut Cursor::new(&img), false)
+        .expect("verify()");
+    assert!(report.passed(), "{}", report);
+    let problem = sectors::check_sector(&img[..SECTOR_SIZE], 0)
+        .expect("check_sector()");
+    assert_eq!(problem.to_string(), "wrong address 00:00:00 in the header");
+
+    // Damage a byte of sector 5, and blank sectors 10 to 12
+    img[5 * SECTOR_SIZE + 100] ^= 0xFF;
+    for b in &mut img[10 * SECTOR_SIZE..13 * SECTOR_SIZE] {
+        *b = 0;
+    }
+    let report = verify::verify(&mut Cursor::new(&img), false)
+        .expect("verify()");
+    let check = &report.checks[4];
+    assert_eq!(check.name, "data sectors");
+    assert_eq!(check.problems,
+               ["Track 01, sector -145: EDC mismatch",
+                "Track 01, sectors -140 to -138: no sync pattern"]);
+}
+
+#[test]
+fn verify_suspicious_regions() {
+    let tracks = [TrackSpec::new(150, 100)];
+    let mut img = common::build_nrg_v2(&tracks);
+    let sector = |i: usize| (150 + i) * SECTOR_SIZE;
+
+    // Silence at the edges of the track isn't suspicious, neither are short
+    // runs of zero-filled sectors
+    for &(first, last) in &[(0, 3), (20, 30), (40, 44), (95, 100)] {
+        for b in &mut img[sector(first)..sector(last)] {
+            *b = 0;
+        }
+    }
+    for i in 51..53 {
+        img.copy_within(sector(50)..sector(51), sector(i));
+    }
+
+    let report = verify::verify(&mut Cursor::new(&img), false)
+        .expect("verify()");
+    assert!(report.passed(), "{}", report);
+    let regions: Vec<String> = report.suspicious_regions.iter()
+        .map(|region| region.to_string())
+        .collect();
+    assert_eq!(regions,
+               ["Track 01 at 00:00:20, 10 sectors: zero-filled",
+                "Track 01 at 00:00:51, 2 sectors: repeated"]);
+}
+
+#[test]
+fn strict_mode() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let mut img = common::build_nrg_v2(&tracks);
+    let strict = MetadataOptions::new().strict(true);
+    metadata::read_nrg_metadata_with(&mut Cursor::new(&img), &strict)
+        .expect("read_nrg_metadata_with()");
+
+    // Announce 3 tracks in the SINF chunk, and skip the MTYP chunk
+    let sinf = img.windows(4).position(|id| id == b"SINF")
+        .expect("SINF chunk");
+    img[sinf + 11] = 3;
+    let mtyp = img.windows(4).position(|id| id == b"MTYP")
+        .expect("MTYP chunk");
+    img[mtyp..mtyp + 4].copy_from_slice(b"RELO");
+
+    // The anomalies are only warnings by default
+    let metadata = metadata::read_from_slice(&img)
+        .expect("read_from_slice()");
+    assert_eq!(verify::anomalies(&metadata), [
+        format!("Unparsed RELO chunk at offset {0} (0x{0:X}), 4 Bytes", mtyp),
+        "SINF announces 3 tracks, DAOX holds 2".to_string(),
+    ]);
+
+    let err = metadata::read_nrg_metadata_with(&mut Cursor::new(&img),
+                                               &strict)
+        .expect_err("strict mode");
+    assert_eq!(err.kind(), NrgErrorKind::Format);
+    match err {
+        NrgError::Anomalies(ref anomalies) => assert_eq!(anomalies.len(), 2),
+        _ => panic!("unexpected error: {}", err),
+    }
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "vorbis")]
+extern crate lewton;
+extern crate nrgrip;
+use lewton::inside_ogg::OggStreamReader;
+use nrgrip::tags::NrgTags;
+use nrgrip::vorbis::VorbisWriter;
+use std::f64::consts::TAU;
+use std::io::{Cursor, Write};
+
+/// Builds `samples` stereo samples of a 1 kHz sine wave at half the full
+/// scale, as 16-bit little-endian PCM.
+fn sine(samples: usize) -> Vec<u8> {
+    (0..samples)
+        .flat_map(|n| {
+            let t = n as f64 / 44100.0;
+            let sample = (16384.0 * (TAU * 1000.0 * t).sin()) as i16;
+            [sample.to_le_bytes(), sample.to_le_bytes()].concat()
+        })
+        .collect()
+}
+
+#[test]
+fn encode_stereo() {
+    let tags = NrgTags {
+        title: Some("Song".to_string()),
+        ..NrgTags::default()
+    };
+    let mut writer = VorbisWriter::new(Cursor::new(Vec::new()), 2, 5.0,
+                                       &tags)
+        .expect("VorbisWriter::new()");
+    for bytes in sine(44100).chunks(1001) {
+        writer.write_all(bytes).expect("write_all()");
+    }
+    let stream = writer.finish().expect("finish()").into_inner();
+
+    let mut reader = OggStreamReader::new(Cursor::new(stream))
+        .expect("OggStreamReader::new()");
+    assert_eq!(reader.ident_hdr.audio_channels, 2);
+    assert_eq!(reader.ident_hdr.audio_sample_rate, 44100);
+    assert!(reader.comment_hdr.comment_list.iter()
+            .any(|(name, value)| name == "TITLE" && value == "Song"));
+
+    let mut decoded = Vec::new();
+    while let Some(packet) = reader.read_dec_packet_itl()
+        .expect("read_dec_packet_itl()") {
+        decoded.extend_from_slice(&packet);
+    }
+    assert_eq!(decoded.len(), 2 * 44100);
+
+    // The decoded audio has the level of the sine wave
+    let rms = (decoded[8820..79380].iter()
+               .map(|&sample| f64::from(sample).powi(2))
+               .sum::<f64>() / 70560.0).sqrt();
+    let expected = 16384.0 / 2f64.sqrt();
+    assert!((rms - expected).abs() < 0.1 * expected, "RMS = {}", rms);
+}
+
+#[test]
+fn invalid_parameters() {
+    let tags = NrgTags::default();
+    assert!(VorbisWriter::new(Vec::new(), 3, 3.0, &tags).is_err());
+    assert!(VorbisWriter::new(Vec::new(), 2, 11.0, &tags).is_err());
+    assert!(VorbisWriter::new(Vec::new(), 1, -1.0, &tags).is_ok());
+}
@@ -0,0 +1,74 @@
+extern crate nrgrip;
+use nrgrip::metadata;
+use nrgrip::wav::{self, WavReader, WAV_HEADER_SIZE};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+mod common;
+use common::{TrackSpec, SECTOR_SIZE};
+
+#[test]
+fn wav_header() {
+    let header = wav::wav_header(4 * SECTOR_SIZE as u64, 2)
+        .expect("wav_header()");
+    assert_eq!(&header[..4], b"RIFF");
+    assert_eq!(&header[4..8], &(36 + 4 * SECTOR_SIZE as u32).to_le_bytes());
+    assert_eq!(&header[8..16], b"WAVEfmt ");
+    assert_eq!(&header[22..24], &2u16.to_le_bytes());
+    assert_eq!(&header[24..28], &44100u32.to_le_bytes());
+    assert_eq!(&header[28..32], &176400u32.to_le_bytes());
+    assert_eq!(&header[36..40], b"data");
+    assert_eq!(&header[40..], &(4 * SECTOR_SIZE as u32).to_le_bytes());
+    assert!(wav::wav_header(1 << 32, 2).is_err());
+}
+
+#[test]
+fn read_track_as_wav() {
+    let tracks = [TrackSpec::new(150, 10), TrackSpec::new(75, 20)];
+    let contents = common::build_nrg_v2(&tracks);
+    let raw96 = common::make_raw96_image(&contents, 255);
+
+    // The samples are the same with and without sub-channel
+    for img in &[contents.clone(), raw96] {
+        let mut fd = Cursor::new(&img[..]);
+        let metadata = metadata::read_nrg_metadata(&mut fd)
+            .expect("read_nrg_metadata()");
+        let track = metadata.tracks().nth(1).expect("tracks()");
+        let mut wav = WavReader::new(fd, &track).expect("WavReader::new()");
+        assert_eq!(wav.len(), WAV_HEADER_SIZE + 20 * SECTOR_SIZE as u64);
+
+        let mut stream = Vec::new();
+        wav.read_to_end(&mut stream).expect("read_to_end()");
+        assert_eq!(&stream[..44],
+                   &wav::wav_header(20 * SECTOR_SIZE as u64, 2).unwrap());
+        assert_eq!(&stream[44..],
+                   &contents[235 * SECTOR_SIZE..][..20 * SECTOR_SIZE]);
+    }
+}
+
+#[test]
+fn seek_in_wav() {
+    let tracks = [TrackSpec::new(150, 10)];
+    let contents = common::build_nrg_v2(&tracks);
+    let raw96 = common::make_raw96_image(&contents, 160);
+    let mut fd = Cursor::new(&raw96[..]);
+    let metadata = metadata::read_nrg_metadata(&mut fd)
+        .expect("read_nrg_metadata()");
+    let track = metadata.tracks().next().expect("tracks()");
+    let mut wav = WavReader::new(fd, &track).expect("WavReader::new()");
+
+    // Across a sector boundary, and back into the header
+    let audio = &contents[150 * SECTOR_SIZE..];
+    let mut buf = [0u8; 100];
+    let pos = WAV_HEADER_SIZE + 3 * SECTOR_SIZE as u64 - 50;
+    assert_eq!(wav.seek(SeekFrom::Start(pos)).expect("seek()"), pos);
+    wav.read_exact(&mut buf).expect("read_exact()");
+    assert_eq!(&buf[..], &audio[3 * SECTOR_SIZE - 50..][..100]);
+    wav.seek(SeekFrom::Current(-(pos as i64) - 92)).expect("seek()");
+    wav.read_exact(&mut buf[..4]).expect("read_exact()");
+    assert_eq!(&buf[..4], b"WAVE");
+    wav.seek(SeekFrom::End(-10)).expect("seek()");
+    assert_eq!(wav.read(&mut buf).expect("read()"), 10);
+    assert_eq!(&buf[..10], &audio[10 * SECTOR_SIZE - 10..][..10]);
+    assert_eq!(wav.read(&mut buf).expect("read()"), 0);
+    assert!(wav.seek(SeekFrom::Current(-1_000_000)).is_err());
+}
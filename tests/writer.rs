@@ -0,0 +1,155 @@
+extern crate nrgrip;
+use nrgrip::error::NrgError;
+use nrgrip::metadata;
+use nrgrip::metadata::track::Track;
+use nrgrip::writer::{self, NrgCueSheet};
+#[cfg(feature = "fs")]
+use nrgrip::cdtext::{NrgCdText, NrgCdTextTrack};
+#[cfg(feature = "fs")]
+use nrgrip::writer::NrgWavTrack;
+#[cfg(feature = "fs")]
+use std::{env, fs};
+
+mod common;
+use common::SECTOR_SIZE;
+
+const CUE_SHEET: &str = "REM GENERATED-BY test
+CATALOG 0123456789012
+FILE \"disc image.bin\" BINARY
+  TRACK 01 AUDIO
+    TITLE \"First\"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    FLAGS DCP
+    ISRC FRXYZ2600001
+    INDEX 00 00:00:10
+    INDEX 01 00:00:30
+  TRACK 03 AUDIO
+    PREGAP 00:00:05
+    INDEX 01 00:01:00
+";
+
+#[test]
+fn write_from_cue_sheet() {
+    let audio: Vec<u8> = (0..100 * SECTOR_SIZE).map(|i| (i % 253) as u8)
+        .collect();
+    let sheet = NrgCueSheet::parse(CUE_SHEET).expect("parse()");
+    assert_eq!(sheet.file, "disc image.bin");
+    let disc = sheet.disc(audio.len() as u64).expect("disc()");
+
+    let mut image = Vec::new();
+    let size = writer::write_nrg(&disc, &mut audio.as_slice(), &mut image)
+        .expect("write_nrg()");
+    assert_eq!(size, image.len() as u64);
+
+    let metadata = metadata::read_from_slice(&image)
+        .expect("read_from_slice()");
+    assert!(metadata.warnings().is_empty());
+    assert_eq!(metadata.daox_chunk().unwrap().upc(), "0123456789012");
+
+    let tracks: Vec<Track> = metadata.tracks().collect();
+    let positions: Vec<(u64, u64, u64)> = tracks.iter()
+        .map(|track| (track.index0, track.index1, track.track_end))
+        .map(|(i0, i1, end)| (i0 / SECTOR_SIZE as u64,
+                              i1 / SECTOR_SIZE as u64,
+                              end / SECTOR_SIZE as u64))
+        .collect();
+    assert_eq!(positions, [(0, 150, 160), (160, 180, 225), (225, 230, 255)]);
+    assert_eq!(tracks[1].mode, 0x21);
+    assert_eq!(tracks[1].isrc.as_deref(), Some("FRXYZ2600001"));
+
+    // The audio data follows the silent pre-gaps
+    let sector = |n: usize| n * SECTOR_SIZE;
+    assert!(image[..sector(150)].iter().all(|&b| b == 0));
+    assert_eq!(&image[sector(150)..sector(225)], &audio[..sector(75)]);
+    assert!(image[sector(225)..sector(230)].iter().all(|&b| b == 0));
+    assert_eq!(&image[sector(230)..sector(255)], &audio[sector(75)..]);
+}
+
+#[test]
+fn cue_sheet_errors() {
+    let error = |text: &str| match NrgCueSheet::parse(text) {
+        Err(NrgError::CueSheet(err)) => err,
+        other => panic!("unexpected result: {:?}", other),
+    };
+    assert_eq!(error("FILE \"a.bin\" BINARY\nTRACK 01 MODE1/2352\n"),
+               "line 2: unsupported track type \"MODE1/2352\"");
+    assert_eq!(error("FILE a.bin BINARY\nTRACK 01 AUDIO\nTRACK 02 AUDIO\n"),
+               "line 3: previous track without index 01");
+    assert_eq!(error("FILE a.bin BINARY\nTRACK 01 AUDIO\n\
+                      INDEX 01 00:02:00\nTRACK 02 AUDIO\n\
+                      INDEX 01 00:01:00\n"),
+               "line 5: index position out of order");
+    assert_eq!(error("FILE a.bin BINARY\n"), "no tracks");
+
+    let sheet = NrgCueSheet::parse("FILE a.bin BINARY\nTRACK 01 AUDIO\n\
+                                    INDEX 01 00:00:00\nTRACK 02 AUDIO\n\
+                                    INDEX 01 00:01:00\n")
+        .expect("parse()");
+    assert!(sheet.disc(75 * SECTOR_SIZE as u64).is_err());
+}
+
+/// Returns a WAV file holding `samples`, followed by an INFO list.
+#[cfg(feature = "fs")]
+fn wav_file(samples: &[u8]) -> Vec<u8> {
+    let mut wav = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&[1, 0, 2, 0]);
+    wav.extend_from_slice(&44100u32.to_le_bytes());
+    wav.extend_from_slice(&(44100u32 * 4).to_le_bytes());
+    wav.extend_from_slice(&[4, 0, 16, 0]);
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+    wav.extend_from_slice(samples);
+    wav.extend_from_slice(b"LIST\x0C\0\0\0INFOINAM\0\0\0\0");
+    let riff_size = (wav.len() - 8) as u32;
+    wav[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    wav
+}
+
+#[cfg(feature = "fs")]
+#[test]
+fn write_from_wavs() {
+    let first: Vec<u8> = (0..20 * SECTOR_SIZE).map(|i| (i % 7) as u8 + 1)
+        .collect();
+    let second = vec![0x55u8; 10 * SECTOR_SIZE + 100];
+    let tracks = vec![
+        NrgWavTrack {
+            path: common::write_temp_file("writer-1.wav", &wav_file(&first)),
+            pregap: 0,
+        },
+        NrgWavTrack {
+            path: common::write_temp_file("writer-2.wav",
+                                          &wav_file(&second)),
+            pregap: 75,
+        },
+    ];
+    let cd_text = NrgCdText {
+        title: "Album".to_string(),
+        performer: "Artist".to_string(),
+        tracks: vec![NrgCdTextTrack::default(); 2],
+        ..NrgCdText::default()
+    };
+    let out_path = env::temp_dir().join("nrgrip-test-writer-wavs.nrg");
+    writer::write_nrg_from_wavs(&tracks, Some(cd_text), &out_path)
+        .expect("write_nrg_from_wavs()");
+
+    let image = fs::read(&out_path).expect("fs::read()");
+    let metadata = metadata::read_from_slice(&image)
+        .expect("read_from_slice()");
+    assert!(metadata.skipped_chunks().is_empty());
+    assert_eq!(metadata.cd_text().map(|cd_text| cd_text.performer.as_str()),
+               Some("Artist"));
+    let positions: Vec<(u64, u64, u64)> = metadata.tracks()
+        .map(|track| (track.index0, track.index1, track.track_end))
+        .collect();
+    let sector = |n: usize| (n * SECTOR_SIZE) as u64;
+    assert_eq!(positions, [(0, sector(150), sector(170)),
+                           (sector(170), sector(245), sector(256))]);
+
+    // The last sector is padded with silence
+    let start = sector(245) as usize;
+    assert_eq!(&image[start..start + second.len()], second.as_slice());
+    assert!(image[start + second.len()..sector(256) as usize].iter()
+            .all(|&b| b == 0));
+}